@@ -1,5 +1,7 @@
 //! CLI config for catalog ingest lifecycle
 
+use std::path::PathBuf;
+
 /// CLI config for catalog ingest lifecycle
 #[derive(Debug, Clone, clap::Parser)]
 #[allow(missing_copy_implementations)]
@@ -40,6 +42,16 @@ pub struct IngesterConfig {
     )]
     pub persist_memory_threshold_bytes: usize,
 
+    /// Once the persist memory threshold is crossed, the ingester persists the largest partitions
+    /// until the data buffered across all shards falls below this target. Must be no greater than
+    /// the persist memory threshold, which it defaults to.
+    #[clap(
+        long = "persist-memory-target-bytes",
+        env = "INFLUXDB_IOX_PERSIST_MEMORY_TARGET_BYTES",
+        action
+    )]
+    pub persist_memory_target_bytes: Option<usize>,
+
     /// If the total bytes written to an individual partition crosses
     /// this size threshold, it will be persisted.  The default value
     /// is 300MB (in bytes).
@@ -84,6 +96,80 @@ pub struct IngesterConfig {
     )]
     pub persist_partition_rows_max: usize,
 
+    /// If set, a partition receiving writes faster than this many bytes per second (averaged since
+    /// its first buffered write, over at least 10 seconds) is considered "hot" and persisted
+    /// early. Hot partitions are counted in the `ingester_lifecycle_hot_partitions` metric and
+    /// can be listed through the ingester's persist gRPC service.
+    #[clap(
+        long = "persist-hot-partition-bytes-per-second",
+        env = "INFLUXDB_IOX_PERSIST_HOT_PARTITION_BYTES_PER_SECOND",
+        action
+    )]
+    pub persist_hot_partition_bytes_per_second: Option<usize>,
+
+    /// The maximum number of partitions compacted and uploaded to object storage at once.
+    /// Partitions selected for persistence beyond this limit wait for a running persist job to
+    /// complete.
+    #[clap(
+        long = "persist-max-parallelism",
+        env = "INFLUXDB_IOX_PERSIST_MAX_PARALLELISM",
+        default_value = "5",
+        action
+    )]
+    pub persist_max_parallelism: usize,
+
+    /// The total estimated size of the buffered data being compacted and uploaded by persist jobs
+    /// at once. Each persist job reserves the size of the partition data it persists from this
+    /// budget, waiting until enough of it is available. A partition larger than the budget is
+    /// persisted once no other persist job is running. The default value is 1GiB (in bytes).
+    #[clap(
+        long = "persist-memory-budget-bytes",
+        env = "INFLUXDB_IOX_PERSIST_MEMORY_BUDGET_BYTES",
+        default_value = "1073741824",
+        action
+    )]
+    pub persist_memory_budget_bytes: usize,
+
+    /// If set, the data persisted for a partition is split by time across multiple parquet files
+    /// when it contains more than this many rows, each file containing roughly at most this many
+    /// rows. By default, all the data persisted for a partition is written to a single file.
+    #[clap(
+        long = "persist-max-file-rows",
+        env = "INFLUXDB_IOX_PERSIST_MAX_FILE_ROWS",
+        action
+    )]
+    pub persist_max_file_rows: Option<usize>,
+
+    /// If set, the data persisted for a partition is split by time across multiple parquet files
+    /// when its estimated in-memory size exceeds this many bytes. The resulting parquet files are
+    /// typically much smaller than this, as the data is compressed.
+    #[clap(
+        long = "persist-max-file-bytes",
+        env = "INFLUXDB_IOX_PERSIST_MAX_FILE_BYTES",
+        action
+    )]
+    pub persist_max_file_bytes: Option<usize>,
+
+    /// If set, the sorts run when compacting data for persistence are limited to
+    /// `--persist-memory-budget-bytes` of memory in total, and spill intermediate sorted runs
+    /// to files in this local directory once that limit is reached, instead of buffering the
+    /// whole partition in memory.
+    #[clap(
+        long = "persist-spill-directory",
+        env = "INFLUXDB_IOX_PERSIST_SPILL_DIRECTORY",
+        action
+    )]
+    pub persist_spill_directory: Option<PathBuf>,
+
+    /// A local directory in which to keep a write-ahead log of the buffered,
+    /// unpersisted data.
+    ///
+    /// When set, data buffered before a restart is recovered from this
+    /// directory rather than re-read from the write buffer. The directory
+    /// must not be shared with any other ingester.
+    #[clap(long = "wal-directory", env = "INFLUXDB_IOX_WAL_DIRECTORY", action)]
+    pub wal_directory: Option<PathBuf>,
+
     /// If the catalog's max sequence number for the partition is no longer available in the write
     /// buffer due to the retention policy, by default the ingester will panic. If this flag is
     /// specified, the ingester will skip any sequence numbers that have not been retained in the
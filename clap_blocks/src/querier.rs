@@ -228,6 +228,19 @@ pub struct QuerierConfig {
         action
     )]
     pub max_table_query_bytes: usize,
+
+    /// Limit the memory used by the queries running at once, in bytes.
+    ///
+    /// Sorts spill to disk rather than exceeding this limit, and queries
+    /// whose aggregate functions need more memory for their state fail.
+    ///
+    /// Unbounded if not set.
+    #[clap(
+        long = "exec-mem-pool-bytes",
+        env = "INFLUXDB_IOX_EXEC_MEM_POOL_BYTES",
+        action
+    )]
+    pub exec_mem_pool_bytes: Option<usize>,
 }
 
 impl QuerierConfig {
@@ -282,6 +295,11 @@ impl QuerierConfig {
     pub fn max_table_query_bytes(&self) -> usize {
         self.max_table_query_bytes
     }
+
+    /// Memory limit for the queries running at once, in bytes, if any.
+    pub fn exec_mem_pool_bytes(&self) -> Option<usize> {
+        self.exec_mem_pool_bytes
+    }
 }
 
 fn deserialize_shard_ingester_map(
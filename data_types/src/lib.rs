@@ -450,6 +450,37 @@ pub struct Namespace {
     pub max_tables: i32,
     /// The maximum number of columns per table in this namespace
     pub max_columns_per_table: i32,
+    /// The retention period in nanoseconds. `None` represents infinite
+    /// retention.
+    #[sqlx(default)]
+    pub retention_period_ns: Option<i64>,
+    /// When this namespace was marked for deletion, if at all.
+    #[sqlx(default)]
+    pub deleted_at: Option<Timestamp>,
+    /// The template used to partition writes to this namespace. `None`
+    /// represents the default template configured in the router.
+    #[sqlx(default)]
+    pub partition_template: Option<PartitionTemplate>,
+}
+
+/// The number of lines and bytes written to a namespace during a period of
+/// time, as accounted by the routers that accepted the writes.
+///
+/// Only one record can exist for a given namespace and period start (enforced
+/// via primary key) - the usage reported by each router for the period is
+/// added to it.
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct NamespaceUsage {
+    /// the namespace written to
+    pub namespace_id: NamespaceId,
+    /// the (inclusive) start of the period
+    pub period_start: Timestamp,
+    /// the (exclusive) end of the period
+    pub period_end: Timestamp,
+    /// the number of lines (or equivalent points) written
+    pub lines: i64,
+    /// the number of request body bytes written
+    pub bytes: i64,
 }
 
 /// Schema collection for a namespace. This is an in-memory object useful for a schema
@@ -466,6 +497,12 @@ pub struct NamespaceSchema {
     pub tables: BTreeMap<String, TableSchema>,
     /// the number of columns per table this namespace allows
     pub max_columns_per_table: usize,
+    /// the retention period of this namespace in nanoseconds, `None` for
+    /// infinite retention
+    pub retention_period_ns: Option<i64>,
+    /// the template used to partition writes to this namespace, `None` for
+    /// the default template
+    pub partition_template: Option<PartitionTemplate>,
 }
 
 impl NamespaceSchema {
@@ -482,6 +519,8 @@ impl NamespaceSchema {
             topic_id,
             query_pool_id,
             max_columns_per_table: max_columns_per_table as usize,
+            retention_period_ns: None,
+            partition_template: None,
         }
     }
 
@@ -780,6 +819,22 @@ pub struct Shard {
     pub min_unpersisted_sequence_number: SequenceNumber,
 }
 
+/// Pins all writes (and table-scoped deletes) for a table in a namespace to a
+/// specific shard index, overriding the router's sharder. Used to isolate hot
+/// tables onto their own shard.
+///
+/// Only one override can exist for a given namespace and table name (enforced
+/// via primary key).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, sqlx::FromRow)]
+pub struct TableShardOverride {
+    /// the namespace containing the table
+    pub namespace_id: NamespaceId,
+    /// the name of the table, which need not exist yet
+    pub table_name: String,
+    /// the shard index all operations for the table are routed to
+    pub shard_index: ShardIndex,
+}
+
 /// Defines an partition via an arbitrary string within a table within
 /// a namespace.
 ///
@@ -1276,6 +1331,111 @@ pub struct StrftimeColumn {
     pub format: String,
 }
 
+/// An error parsing the textual form of a [`TemplatePart`].
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+pub enum TemplatePartParseError {
+    #[snafu(display(
+        "invalid partition template part '{}', expected one of 'table', \
+         'tag:<column>', 'time:<format>', 'regex:<column>:<regex>' or \
+         'strftime:<column>:<format>'",
+        part
+    ))]
+    Invalid { part: String },
+
+    #[snafu(display("partition template part '{}' has an empty value", part))]
+    EmptyValue { part: String },
+}
+
+/// Renders the textual form of a [`TemplatePart`], as accepted by its
+/// [`FromStr`](std::str::FromStr) implementation:
+///
+/// * `table` for [`TemplatePart::Table`]
+/// * `tag:<column>` for [`TemplatePart::Column`]
+/// * `time:<format>` for [`TemplatePart::TimeFormat`]
+/// * `regex:<column>:<regex>` for [`TemplatePart::RegexCapture`]
+/// * `strftime:<column>:<format>` for [`TemplatePart::StrftimeColumn`]
+impl std::fmt::Display for TemplatePart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Table => write!(f, "table"),
+            Self::Column(column) => write!(f, "tag:{}", column),
+            Self::TimeFormat(format) => write!(f, "time:{}", format),
+            Self::RegexCapture(RegexCapture { column, regex }) => {
+                write!(f, "regex:{}:{}", column, regex)
+            }
+            Self::StrftimeColumn(StrftimeColumn { column, format }) => {
+                write!(f, "strftime:{}:{}", column, format)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for TemplatePart {
+    type Err = TemplatePartParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Split "<column>:<value>" for the parts that take two arguments.
+        let column_and_value = |v: &str| {
+            v.split_once(':')
+                .filter(|(column, value)| !column.is_empty() && !value.is_empty())
+                .map(|(column, value)| (column.to_string(), value.to_string()))
+                .ok_or_else(|| TemplatePartParseError::EmptyValue {
+                    part: s.to_string(),
+                })
+        };
+
+        let part = match s.split_once(':') {
+            None if s == "table" => Self::Table,
+            Some((_, "")) => return EmptyValueSnafu { part: s }.fail(),
+            Some(("tag", column)) => Self::Column(column.to_string()),
+            Some(("time", format)) => Self::TimeFormat(format.to_string()),
+            Some(("regex", v)) => {
+                let (column, regex) = column_and_value(v)?;
+                Self::RegexCapture(RegexCapture { column, regex })
+            }
+            Some(("strftime", v)) => {
+                let (column, format) = column_and_value(v)?;
+                Self::StrftimeColumn(StrftimeColumn { column, format })
+            }
+            _ => return InvalidSnafu { part: s }.fail(),
+        };
+
+        Ok(part)
+    }
+}
+
+/// A [`PartitionTemplate`] is stored in the catalog as an array of the textual
+/// form of its [`TemplatePart`].
+impl sqlx::Type<sqlx::Postgres> for PartitionTemplate {
+    fn type_info() -> sqlx::postgres::PgTypeInfo {
+        <Vec<String> as sqlx::Type<sqlx::Postgres>>::type_info()
+    }
+}
+
+impl<'q> sqlx::Encode<'q, sqlx::Postgres> for PartitionTemplate {
+    fn encode_by_ref(&self, buf: &mut sqlx::postgres::PgArgumentBuffer) -> sqlx::encode::IsNull {
+        let parts = self
+            .parts
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        <Vec<String> as sqlx::Encode<'_, sqlx::Postgres>>::encode_by_ref(&parts, buf)
+    }
+}
+
+impl<'r> sqlx::Decode<'r, sqlx::Postgres> for PartitionTemplate {
+    fn decode(value: sqlx::postgres::PgValueRef<'r>) -> Result<Self, sqlx::error::BoxDynError> {
+        let parts = <Vec<String> as sqlx::Decode<'r, sqlx::Postgres>>::decode(value)?;
+        Ok(Self {
+            parts: parts
+                .iter()
+                .map(|v| v.parse())
+                .collect::<Result<_, TemplatePartParseError>>()?,
+        })
+    }
+}
+
 /// Represents a parsed delete predicate for evaluation by the InfluxDB IOx
 /// query engine.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -3374,6 +3534,45 @@ mod tests {
         assert!(schema1.size() < schema2.size());
     }
 
+    #[test]
+    fn test_template_part_round_trip() {
+        let parts = [
+            TemplatePart::Table,
+            TemplatePart::Column("region".to_string()),
+            TemplatePart::Column("a:b".to_string()),
+            TemplatePart::TimeFormat("%Y-%m-%d %H:%M".to_string()),
+            TemplatePart::RegexCapture(RegexCapture {
+                column: "host".to_string(),
+                regex: "^(.*):\\d+$".to_string(),
+            }),
+            TemplatePart::StrftimeColumn(StrftimeColumn {
+                column: "ts".to_string(),
+                format: "%Y".to_string(),
+            }),
+        ];
+
+        for part in parts {
+            let text = part.to_string();
+            assert_eq!(text.parse::<TemplatePart>().unwrap(), part, "{text}");
+        }
+
+        assert_eq!(
+            "tag:region".parse::<TemplatePart>().unwrap(),
+            TemplatePart::Column("region".to_string())
+        );
+        for invalid in [
+            "",
+            "tables",
+            "tag",
+            "bananas:region",
+            "tag:",
+            "regex:host",
+            "regex::x",
+        ] {
+            assert!(invalid.parse::<TemplatePart>().is_err(), "{invalid}");
+        }
+    }
+
     #[test]
     fn test_namespace_schema_size() {
         let schema1 = NamespaceSchema {
@@ -3382,6 +3581,8 @@ mod tests {
             query_pool_id: QueryPoolId::new(3),
             tables: BTreeMap::from([]),
             max_columns_per_table: 4,
+            retention_period_ns: None,
+            partition_template: None,
         };
         let schema2 = NamespaceSchema {
             id: NamespaceId::new(1),
@@ -3389,6 +3590,8 @@ mod tests {
             query_pool_id: QueryPoolId::new(3),
             tables: BTreeMap::from([(String::from("foo"), TableSchema::new(TableId::new(1)))]),
             max_columns_per_table: 4,
+            retention_period_ns: None,
+            partition_template: None,
         };
         assert!(schema1.size() < schema2.size());
     }
@@ -13,7 +13,7 @@
     clippy::dbg_macro
 )]
 
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 use data_types::{DeletePredicate, NonEmptyString, PartitionKey, Sequence, StatValues, Statistics};
 use hashbrown::HashMap;
@@ -35,6 +35,9 @@ pub struct DmlMeta {
 
     /// Bytes read from the wire
     bytes_read: Option<usize>,
+
+    /// Key/value annotations describing the provenance of this operation
+    annotations: BTreeMap<String, String>,
 }
 
 impl DmlMeta {
@@ -50,6 +53,7 @@ impl DmlMeta {
             producer_ts: Some(producer_ts),
             span_ctx,
             bytes_read: Some(bytes_read),
+            annotations: Default::default(),
         }
     }
 
@@ -60,9 +64,18 @@ impl DmlMeta {
             producer_ts: None,
             span_ctx,
             bytes_read: None,
+            annotations: Default::default(),
         }
     }
 
+    /// Attach the key/value `annotations` describing the provenance of this
+    /// operation (such as the client that produced it), replacing any
+    /// existing annotations.
+    pub fn with_annotations(mut self, annotations: BTreeMap<String, String>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
     /// Gets the sequence number associated with the write if any
     pub fn sequence(&self) -> Option<&Sequence> {
         self.sequence.as_ref()
@@ -89,11 +102,21 @@ impl DmlMeta {
         self.bytes_read
     }
 
+    /// Returns the provenance annotations attached to this operation
+    pub fn annotations(&self) -> &BTreeMap<String, String> {
+        &self.annotations
+    }
+
     /// Return the approximate memory size of the metadata, in bytes.
     ///
     /// This includes `Self`.
     pub fn size(&self) -> usize {
         std::mem::size_of::<Self>()
+            + self
+                .annotations
+                .iter()
+                .map(|(k, v)| k.capacity() + v.capacity())
+                .sum::<usize>()
             + self
                 .span_ctx
                 .as_ref()
@@ -442,5 +465,6 @@ pub mod test_util {
             m.span_context().cloned(),
             m.bytes_read().unwrap(),
         )
+        .with_annotations(m.annotations().clone())
     }
 }
@@ -31,6 +31,11 @@ fn main() -> Result<()> {
 /// - `influxdata.iox.write.v1.rs`
 /// - `influxdata.iox.write_buffer.v1.rs`
 /// - `influxdata.platform.storage.rs`
+/// - `opentelemetry.proto.collector.metrics.v1.rs`
+/// - `opentelemetry.proto.common.v1.rs`
+/// - `opentelemetry.proto.metrics.v1.rs`
+/// - `opentelemetry.proto.resource.v1.rs`
+/// - `prometheus.rs`
 fn generate_grpc_types(root: &Path) -> Result<()> {
     let catalog_path = root.join("influxdata/iox/catalog/v1");
     let compactor_path = root.join("influxdata/iox/compactor/v1");
@@ -45,6 +50,7 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
     let write_buffer_path = root.join("influxdata/iox/write_buffer/v1");
     let write_summary_path = root.join("influxdata/iox/write_summary/v1");
     let storage_path = root.join("influxdata/platform/storage");
+    let otel_path = root.join("opentelemetry/proto");
 
     let proto_files = vec![
         catalog_path.join("parquet_file.proto"),
@@ -52,6 +58,7 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
         compactor_path.join("service.proto"),
         delete_path.join("service.proto"),
         ingester_path.join("parquet_metadata.proto"),
+        ingester_path.join("persist.proto"),
         ingester_path.join("query.proto"),
         ingester_path.join("write_info.proto"),
         namespace_path.join("service.proto"),
@@ -63,6 +70,12 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
         root.join("google/rpc/status.proto"),
         root.join("grpc/health/v1/service.proto"),
         root.join("influxdata/pbdata/v1/influxdb_pb_data_protocol.proto"),
+        otel_path.join("collector/metrics/v1/metrics_service.proto"),
+        otel_path.join("common/v1/common.proto"),
+        otel_path.join("metrics/v1/metrics.proto"),
+        otel_path.join("resource/v1/resource.proto"),
+        root.join("prometheus/remote.proto"),
+        root.join("prometheus/types.proto"),
         schema_path.join("service.proto"),
         sharder_path.join("sharder.proto"),
         write_buffer_path.join("write_buffer.proto"),
@@ -83,7 +96,7 @@ fn generate_grpc_types(root: &Path) -> Result<()> {
 
     config
         .compile_well_known_types()
-        .disable_comments(&[".google"])
+        .disable_comments(&[".google", ".opentelemetry"])
         .extern_path(".google.protobuf", "::pbjson_types")
         .btree_map(&[
             ".influxdata.iox.ingester.v1.IngesterQueryResponseMetadata.unpersisted_partitions",
@@ -453,6 +453,48 @@ mod tests {
             self.requests.write().push(request.into_inner());
             Ok(tonic::Response::new(self.reply_with.clone()))
         }
+
+        async fn map_to_shards(
+            &self,
+            _request: tonic::Request<MapToShardsRequest>,
+        ) -> Result<tonic::Response<MapToShardsResponse>, tonic::Status> {
+            unimplemented!()
+        }
+
+        async fn set_table_shard_override(
+            &self,
+            _request: tonic::Request<SetTableShardOverrideRequest>,
+        ) -> Result<tonic::Response<SetTableShardOverrideResponse>, tonic::Status> {
+            unimplemented!()
+        }
+
+        async fn delete_table_shard_override(
+            &self,
+            _request: tonic::Request<DeleteTableShardOverrideRequest>,
+        ) -> Result<tonic::Response<DeleteTableShardOverrideResponse>, tonic::Status> {
+            unimplemented!()
+        }
+
+        async fn list_table_shard_overrides(
+            &self,
+            _request: tonic::Request<ListTableShardOverridesRequest>,
+        ) -> Result<tonic::Response<ListTableShardOverridesResponse>, tonic::Status> {
+            unimplemented!()
+        }
+
+        async fn reshard(
+            &self,
+            _request: tonic::Request<ReshardRequest>,
+        ) -> Result<tonic::Response<ReshardResponse>, tonic::Status> {
+            unimplemented!()
+        }
+
+        async fn get_shard_ring(
+            &self,
+            _request: tonic::Request<GetShardRingRequest>,
+        ) -> Result<tonic::Response<GetShardRingResponse>, tonic::Status> {
+            unimplemented!()
+        }
     }
 
     async fn create_test_shard_service(
@@ -92,6 +92,8 @@ pub async fn command(config: Config) -> Result<()> {
                     parquet_store.id(),
                     Arc::clone(parquet_store.object_store()),
                 )]),
+                reorg_spill: None,
+                query_memory_limit_bytes: None,
             }));
             let time_provider = Arc::new(SystemProvider::new());
 
@@ -26,6 +26,10 @@ struct Get {
     /// The name of the namespace for which you want to fetch the schema
     #[clap(action)]
     namespace: String,
+
+    /// Only fetch the schema of this table in the namespace
+    #[clap(long, action)]
+    table: Option<String>,
 }
 
 /// All possible subcommands for catalog
@@ -39,8 +43,16 @@ pub async fn command(connection: Connection, config: Config) -> Result<(), Error
     match config.command {
         Command::Get(command) => {
             let mut client = schema::Client::new(connection);
-            let schema = client.get_schema(&command.namespace).await?;
-            println!("{}", serde_json::to_string_pretty(&schema)?);
+            match command.table {
+                Some(table) => {
+                    let schema = client.get_table_schema(&command.namespace, &table).await?;
+                    println!("{}", serde_json::to_string_pretty(&schema)?);
+                }
+                None => {
+                    let schema = client.get_schema(&command.namespace).await?;
+                    println!("{}", serde_json::to_string_pretty(&schema)?);
+                }
+            }
         } // Deliberately not adding _ => so the compiler will direct people here to impl new
           // commands
     }
@@ -1,7 +1,10 @@
 //! This module implements the `remote store` CLI subcommand
 
 use futures::StreamExt;
-use influxdb_iox_client::{connection::Connection, store};
+use influxdb_iox_client::{
+    connection::Connection,
+    store::{self, generated_types::GetParquetFileByObjectStoreIdRequest},
+};
 use thiserror::Error;
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
@@ -36,6 +39,18 @@ struct Get {
     /// The filename to write the data to
     #[clap(action)]
     file_name: String,
+
+    /// The offset of the first byte of the parquet file to fetch
+    #[clap(long, default_value = "0", action)]
+    offset: i64,
+
+    /// The maximum number of bytes to fetch, defaulting to the rest of the file
+    #[clap(long, action)]
+    length: Option<i64>,
+
+    /// The maximum number of bytes in each chunk streamed by the server
+    #[clap(long, default_value = "0", action)]
+    chunk_size: i32,
 }
 
 /// All possible subcommands for partition
@@ -48,7 +63,15 @@ pub async fn command(connection: Connection, config: Config) -> Result<(), Error
     match config.command {
         Command::Get(get) => {
             let mut client = store::Client::new(connection);
-            let mut response = client.get_parquet_file_by_object_store_id(get.uuid).await?;
+            let mut response = client
+                .get_parquet_file_range(GetParquetFileByObjectStoreIdRequest {
+                    uuid: get.uuid,
+                    offset: get.offset,
+                    length: get.length,
+                    chunk_size: get.chunk_size,
+                    prefetch_chunks: 0,
+                })
+                .await?;
             let mut file = File::create(&get.file_name).await?;
             while let Some(res) = response.next().await {
                 let res = res.unwrap();
@@ -323,6 +323,16 @@ pub struct Config {
         action
     )]
     pub querier_max_table_query_bytes: usize,
+
+    /// Limit the memory used by the queries running at once, in bytes.
+    ///
+    /// Unbounded if not set.
+    #[clap(
+        long = "querier-exec-mem-pool-bytes",
+        env = "INFLUXDB_IOX_QUERIER_EXEC_MEM_POOL_BYTES",
+        action
+    )]
+    pub querier_exec_mem_pool_bytes: Option<usize>,
 }
 
 impl Config {
@@ -348,6 +358,7 @@ impl Config {
             querier_ram_pool_data_bytes,
             querier_max_concurrent_queries,
             querier_max_table_query_bytes,
+            querier_exec_mem_pool_bytes,
         } = self;
 
         let database_directory = object_store_config.database_directory.clone();
@@ -401,6 +412,7 @@ impl Config {
             shard_index_range_end,
             pause_ingest_size_bytes,
             persist_memory_threshold_bytes,
+            persist_memory_target_bytes: None,
             persist_partition_size_threshold_bytes,
             persist_partition_age_threshold_seconds,
             persist_partition_cold_threshold_seconds,
@@ -408,6 +420,13 @@ impl Config {
             test_flight_do_get_panic: 0,
             concurrent_request_limit: 10,
             persist_partition_rows_max: 500_000,
+            persist_hot_partition_bytes_per_second: None,
+            persist_max_parallelism: 5,
+            persist_memory_budget_bytes: 1024 * 1024 * 1024,
+            persist_max_file_rows: None,
+            persist_max_file_bytes: None,
+            persist_spill_directory: None,
+            wal_directory: None,
         };
 
         // create a CompactorConfig for the all in one server based on
@@ -436,6 +455,7 @@ impl Config {
             ram_pool_data_bytes: querier_ram_pool_data_bytes,
             max_concurrent_queries: querier_max_concurrent_queries,
             max_table_query_bytes: querier_max_table_query_bytes,
+            exec_mem_pool_bytes: querier_exec_mem_pool_bytes,
         };
 
         SpecializedConfig {
@@ -523,6 +543,8 @@ pub async fn command(config: Config) -> Result<()> {
             parquet_store.id(),
             Arc::clone(parquet_store.object_store()),
         )]),
+        reorg_spill: None,
+        query_memory_limit_bytes: querier_config.exec_mem_pool_bytes(),
     }));
 
     info!("starting router");
@@ -533,7 +555,29 @@ pub async fn command(config: Config) -> Result<()> {
         Arc::clone(&object_store),
         &write_buffer_config,
         QUERY_POOL_NAME,
-        1_000, // max 1,000 concurrent HTTP requests
+        1_000,  // max 1,000 concurrent HTTP requests
+        None,   // no request limits file
+        vec![], // unauthenticated HTTP writes & deletes
+        None,   // no per-org write line rate limit
+        None,   // no per-org write byte rate limit
+        vec![], // no cross-origin requests
+        None,   // browser default CORS preflight cache duration
+        None,   // unbounded lines per write
+        None,   // unbounded fields per line
+        None,   // unbounded tags per line
+        None,   // no HTTP request timeout
+        None,   // use the default jump hash sharder
+        None,   // no shard circuit breaker
+        None,   // default shard circuit breaker cool down
+        vec![], // reject all schema conflicts
+        None,   // no per-table DML metrics
+        None,   // unbounded parallel writes
+        None,   // no periodic namespace cache refresh
+        vec![], // unauthenticated admin gRPC services
+        None,   // no gRPC compression
+        None,   // unbounded gRPC message size
+        false,  // no write provenance annotations
+        None,   // no usage accounting
     )
     .await?;
 
@@ -105,6 +105,8 @@ pub async fn command(config: Config) -> Result<(), Error> {
             parquet_store.id(),
             Arc::clone(parquet_store.object_store()),
         )]),
+        reorg_spill: None,
+        query_memory_limit_bytes: None,
     }));
     let time_provider = Arc::new(SystemProvider::new());
 
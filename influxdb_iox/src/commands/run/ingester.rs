@@ -5,7 +5,7 @@ use clap_blocks::{
     catalog_dsn::CatalogDsnConfig, ingester::IngesterConfig, run_config::RunConfig,
     write_buffer::WriteBufferConfig,
 };
-use iox_query::exec::Executor;
+use iox_query::exec::{Executor, ExecutorConfig, SpillConfig};
 use iox_time::{SystemProvider, TimeProvider};
 use ioxd_common::server_type::{CommonServerState, CommonServerStateError};
 use ioxd_common::Service;
@@ -13,7 +13,7 @@ use ioxd_ingester::create_ingester_server_type;
 use object_store::DynObjectStore;
 use object_store_metrics::ObjectStoreMetrics;
 use observability_deps::tracing::*;
-use std::sync::Arc;
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 use thiserror::Error;
 
 use super::main;
@@ -34,6 +34,12 @@ pub enum Error {
 
     #[error("Catalog DSN error: {0}")]
     CatalogDsn(#[from] clap_blocks::catalog_dsn::Error),
+
+    #[error("Cannot create persist spill directory {}: {source}", path.display())]
+    SpillDirectory {
+        path: PathBuf,
+        source: std::io::Error,
+    },
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
@@ -96,7 +102,28 @@ pub async fn command(config: Config) -> Result<()> {
         &*metric_registry,
     ));
 
-    let exec = Arc::new(Executor::new(config.query_exec_thread_count));
+    // When a spill directory is configured, the sorts run by persist compactions share the
+    // persist memory budget and spill to disk once it is exhausted.
+    let reorg_spill = match &config.ingester_config.persist_spill_directory {
+        Some(directory) => {
+            std::fs::create_dir_all(directory).map_err(|source| Error::SpillDirectory {
+                path: directory.clone(),
+                source,
+            })?;
+            Some(SpillConfig {
+                memory_limit_bytes: config.ingester_config.persist_memory_budget_bytes,
+                directory: Some(directory.clone()),
+            })
+        }
+        None => None,
+    };
+    let exec = Arc::new(Executor::new_with_config(ExecutorConfig {
+        num_threads: config.query_exec_thread_count,
+        target_query_partitions: config.query_exec_thread_count,
+        object_stores: HashMap::default(),
+        reorg_spill,
+        query_memory_limit_bytes: None,
+    }));
     let server_type = create_ingester_server_type(
         &common_state,
         Arc::clone(&metric_registry),
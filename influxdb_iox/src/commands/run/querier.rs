@@ -5,7 +5,7 @@ use clap_blocks::{
     catalog_dsn::CatalogDsnConfig, object_store::make_object_store, querier::QuerierConfig,
     run_config::RunConfig,
 };
-use iox_query::exec::Executor;
+use iox_query::exec::{Executor, ExecutorConfig};
 use iox_time::{SystemProvider, TimeProvider};
 use ioxd_common::{
     server_type::{CommonServerState, CommonServerStateError},
@@ -15,7 +15,7 @@ use ioxd_querier::{create_querier_server_type, QuerierServerTypeArgs};
 use object_store::DynObjectStore;
 use object_store_metrics::ObjectStoreMetrics;
 use observability_deps::tracing::*;
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -97,7 +97,13 @@ pub async fn command(config: Config) -> Result<(), Error> {
     let ingester_addresses = config.querier_config.ingester_addresses()?;
     info!(?ingester_addresses, "using ingester addresses");
 
-    let exec = Arc::new(Executor::new(num_threads));
+    let exec = Arc::new(Executor::new_with_config(ExecutorConfig {
+        num_threads,
+        target_query_partitions: num_threads,
+        object_stores: HashMap::default(),
+        reorg_spill: None,
+        query_memory_limit_bytes: config.querier_config.exec_mem_pool_bytes(),
+    }));
 
     let server_type = create_querier_server_type(QuerierServerTypeArgs {
         common_state: &common_state,
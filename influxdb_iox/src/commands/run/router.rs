@@ -14,8 +14,14 @@ use ioxd_router::create_router_server_type;
 use object_store::DynObjectStore;
 use object_store_metrics::ObjectStoreMetrics;
 use observability_deps::tracing::*;
-use std::sync::Arc;
+use std::{
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
 use thiserror::Error;
+use tonic::codec::CompressionEncoding;
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -87,6 +93,327 @@ pub struct Config {
         action
     )]
     pub(crate) http_request_limit: usize,
+
+    /// Read the HTTP request limits from this JSON file, and reload it when
+    /// the router receives SIGHUP to change the limits without a restart.
+    ///
+    /// The file contains an object with any of the "max_request_bytes",
+    /// "max_requests", "lines_per_second" and "bytes_per_second" limits, for
+    /// example:
+    ///
+    ///   {"max_requests": 100, "lines_per_second": 10000}
+    ///
+    /// Limits in the file replace those configured by the command line, and a
+    /// rate of 0 removes the rate limit. Requests already being serviced are
+    /// unaffected by a reload.
+    #[clap(
+        long = "http-limits-file",
+        env = "INFLUXDB_IOX_HTTP_LIMITS_FILE",
+        action
+    )]
+    pub(crate) http_limits_file: Option<PathBuf>,
+
+    /// Require HTTP write & delete requests to present one of these API
+    /// tokens, either as `Authorization: Token <token>` or as the password of
+    /// InfluxDB 1.x basic credentials.
+    ///
+    /// Each grant is of the form `<token>=<namespace>:<permission>[;...]`,
+    /// where the namespace is a namespace name or `*` (all namespaces), and
+    /// the permission is `write`, `delete` or `*` (both), for example:
+    ///
+    ///   "s3cret=bananas_test:write;bananas_dev:*"
+    ///
+    /// Passed as a comma separated list of grants. HTTP writes & deletes are
+    /// unauthenticated if not set.
+    #[clap(
+        long = "http-auth-token",
+        env = "INFLUXDB_IOX_HTTP_AUTH_TOKENS",
+        use_value_delimiter = true,
+        hide_env_values = true,
+        action = clap::ArgAction::Append
+    )]
+    pub(crate) http_auth_tokens: Vec<String>,
+
+    /// The maximum number of lines each org may write per second, allowing
+    /// bursts of up to one second's worth of lines.
+    ///
+    /// Writes from an org exceeding its rate are rejected with a 429 response
+    /// advising when to retry. Unlimited if not set.
+    #[clap(
+        long = "org-write-lines-per-second",
+        env = "INFLUXDB_IOX_ORG_WRITE_LINES_PER_SECOND",
+        action
+    )]
+    pub(crate) org_write_lines_per_second: Option<NonZeroU64>,
+
+    /// The maximum number of (decompressed) request body bytes each org may
+    /// write per second, allowing bursts of up to one second's worth of
+    /// bytes.
+    ///
+    /// Writes from an org exceeding its rate are rejected with a 429 response
+    /// advising when to retry. Unlimited if not set.
+    #[clap(
+        long = "org-write-bytes-per-second",
+        env = "INFLUXDB_IOX_ORG_WRITE_BYTES_PER_SECOND",
+        action
+    )]
+    pub(crate) org_write_bytes_per_second: Option<NonZeroU64>,
+
+    /// Permit cross-origin HTTP requests from browser-based clients served
+    /// from these origins, such as "https://example.com", or from any origin
+    /// if "*" is given.
+    ///
+    /// Passed as a comma separated list of origins. Cross-origin requests are
+    /// rejected by browsers if not set.
+    #[clap(
+        long = "http-cors-allowed-origins",
+        env = "INFLUXDB_IOX_HTTP_CORS_ALLOWED_ORIGINS",
+        use_value_delimiter = true,
+        action = clap::ArgAction::Append
+    )]
+    pub(crate) cors_allowed_origins: Vec<String>,
+
+    /// Allow browsers to cache the response to a CORS preflight request for
+    /// this long (for example "10m").
+    ///
+    /// Uses the browser default if not set.
+    #[clap(
+        long = "http-cors-max-age",
+        env = "INFLUXDB_IOX_HTTP_CORS_MAX_AGE",
+        value_parser = humantime::parse_duration,
+        action
+    )]
+    pub(crate) cors_max_age: Option<Duration>,
+
+    /// Reject HTTP writes containing more than this many lines (or points, or
+    /// samples, for writes in other formats).
+    ///
+    /// Unbounded if not set.
+    #[clap(long = "max-write-lines", env = "INFLUXDB_IOX_MAX_WRITE_LINES", action)]
+    pub(crate) max_write_lines: Option<NonZeroUsize>,
+
+    /// Reject HTTP writes containing a line (or point) with more than this
+    /// many fields.
+    ///
+    /// Unbounded if not set.
+    #[clap(
+        long = "max-write-fields-per-line",
+        env = "INFLUXDB_IOX_MAX_WRITE_FIELDS_PER_LINE",
+        action
+    )]
+    pub(crate) max_write_fields_per_line: Option<NonZeroUsize>,
+
+    /// Reject HTTP writes containing a line (or point) with more than this
+    /// many tags.
+    ///
+    /// Unbounded if not set.
+    #[clap(
+        long = "max-write-tags-per-line",
+        env = "INFLUXDB_IOX_MAX_WRITE_TAGS_PER_LINE",
+        action
+    )]
+    pub(crate) max_write_tags_per_line: Option<NonZeroUsize>,
+
+    /// Abort HTTP requests that are not serviced within this duration (for
+    /// example "30s"), returning a 504 response.
+    ///
+    /// A write aborted by the timeout may have been partially applied.
+    /// Requests are not timed out if not set, or set to zero.
+    #[clap(
+        long = "http-request-timeout",
+        env = "INFLUXDB_IOX_HTTP_REQUEST_TIMEOUT",
+        value_parser = humantime::parse_duration,
+        action
+    )]
+    pub(crate) http_request_timeout: Option<Duration>,
+
+    /// Shard writes using a consistent hash ring with this many virtual nodes
+    /// per shard, instead of the default jump hash.
+    ///
+    /// The hash ring allows shards to be added and removed at runtime with
+    /// the Reshard RPC, remapping only a minimal fraction of tables.
+    ///
+    /// Changing the sharding strategy of an existing deployment remaps most
+    /// tables to a different shard - all routers MUST be configured with the
+    /// same value.
+    #[clap(
+        long = "shard-ring-vnodes",
+        env = "INFLUXDB_IOX_SHARD_RING_VNODES",
+        action
+    )]
+    pub(crate) shard_ring_vnodes: Option<NonZeroUsize>,
+
+    /// Fast-fail writes to a shard once this many consecutive writes to it
+    /// have failed, rather than waiting on an unhealthy write buffer.
+    ///
+    /// Writes to the shard are rejected for the
+    /// --shard-circuit-breaker-cool-down duration, after which a single write
+    /// is let through to probe the shard. Disabled if not set.
+    #[clap(
+        long = "shard-circuit-breaker-error-threshold",
+        env = "INFLUXDB_IOX_SHARD_CIRCUIT_BREAKER_ERROR_THRESHOLD",
+        action
+    )]
+    pub(crate) shard_circuit_breaker_error_threshold: Option<NonZeroUsize>,
+
+    /// The duration for which writes to a shard are rejected once its circuit
+    /// breaker has opened.
+    ///
+    /// Only used if --shard-circuit-breaker-error-threshold is set. Defaults
+    /// to 5 seconds if not set.
+    #[clap(
+        long = "shard-circuit-breaker-cool-down",
+        env = "INFLUXDB_IOX_SHARD_CIRCUIT_BREAKER_COOL_DOWN",
+        value_parser = humantime::parse_duration,
+        action
+    )]
+    pub(crate) shard_circuit_breaker_cool_down: Option<Duration>,
+
+    /// Namespaces in which integer and unsigned integer values written to an
+    /// existing float column are widened to floats, instead of the write
+    /// being rejected with a schema conflict.
+    ///
+    /// Integer values with a magnitude greater than 2^53 may lose precision
+    /// when widened.
+    ///
+    /// Passed as a comma separated list of namespace names.
+    #[clap(
+        long = "schema-coerce-namespaces",
+        env = "INFLUXDB_IOX_SCHEMA_COERCE_NAMESPACES",
+        use_value_delimiter = true,
+        action = clap::ArgAction::Append
+    )]
+    pub(crate) schema_coerce_namespaces: Vec<String>,
+
+    /// Record per-table DML request latency metrics for at most this many
+    /// distinct tables.
+    ///
+    /// Requests for tables observed once this limit is reached are recorded
+    /// under a shared "_overflow" table label, bounding the metric
+    /// cardinality. Per-table metrics are disabled if not set.
+    #[clap(
+        long = "dml-table-metrics-max-tables",
+        env = "INFLUXDB_IOX_DML_TABLE_METRICS_MAX_TABLES",
+        action
+    )]
+    pub(crate) dml_table_metrics_max_tables: Option<NonZeroUsize>,
+
+    /// The maximum number of partitioned writes of a single request dispatched
+    /// to the write buffer concurrently.
+    ///
+    /// Each partitioned write is split into one operation per shard, with all
+    /// shards written to in parallel. Unbounded if not set.
+    #[clap(
+        long = "max-parallel-writes",
+        env = "INFLUXDB_IOX_MAX_PARALLEL_WRITES",
+        action
+    )]
+    pub(crate) max_parallel_writes: Option<NonZeroUsize>,
+
+    /// Reload every cached namespace schema from the catalog at this interval
+    /// (for example "5m"), applying changes made to the catalog by other
+    /// routers or by hand without a restart.
+    ///
+    /// Cached schemas are only reloaded when explicitly refreshed if not set.
+    #[clap(
+        long = "namespace-cache-refresh-interval",
+        env = "INFLUXDB_IOX_NAMESPACE_CACHE_REFRESH_INTERVAL",
+        value_parser = humantime::parse_duration,
+        action
+    )]
+    pub(crate) namespace_cache_refresh_interval: Option<Duration>,
+
+    /// Require calls to the catalog, object store and namespace gRPC services
+    /// to present one of these API tokens as `Authorization: Token <token>`.
+    ///
+    /// Each grant is of the form `<token>=<pattern>[;<pattern>...]`, where a
+    /// pattern is `*` (all methods), `<service>/*` (all methods of the
+    /// fully-qualified service) or `<service>/<method>`, for example:
+    ///
+    ///   "s3cret=influxdata.iox.namespace.v1.NamespaceService/GetNamespaces"
+    ///
+    /// Passed as a comma separated list of grants. These services are
+    /// unauthenticated if not set.
+    #[clap(
+        long = "grpc-admin-token",
+        env = "INFLUXDB_IOX_GRPC_ADMIN_TOKENS",
+        use_value_delimiter = true,
+        hide_env_values = true,
+        action = clap::ArgAction::Append
+    )]
+    pub(crate) grpc_admin_tokens: Vec<String>,
+
+    /// Accept gRPC requests compressed with this encoding, and compress gRPC
+    /// responses with it for clients that accept it.
+    ///
+    /// Only gzip is available - the gRPC library in use does not support
+    /// zstd.
+    #[clap(
+        value_enum,
+        long = "grpc-compression",
+        env = "INFLUXDB_IOX_GRPC_COMPRESSION",
+        default_value = "none",
+        ignore_case = true,
+        action
+    )]
+    pub(crate) grpc_compression: GrpcCompression,
+
+    /// Reject gRPC request messages larger than this many bytes (as sent,
+    /// before decompression).
+    ///
+    /// Each message of a streaming request is limited individually. Unbounded
+    /// if not set.
+    #[clap(
+        long = "grpc-max-message-size",
+        env = "INFLUXDB_IOX_GRPC_MAX_MESSAGE_SIZE",
+        action
+    )]
+    pub(crate) grpc_max_message_size: Option<NonZeroUsize>,
+
+    /// Attach the client agent (from the "User-Agent" header) and originating
+    /// host (from the "X-Forwarded-For" header) of each HTTP write & delete
+    /// request to the operations written to the write buffer, to trace data
+    /// back to the client that produced it.
+    #[clap(
+        long = "write-provenance-annotations",
+        env = "INFLUXDB_IOX_WRITE_PROVENANCE_ANNOTATIONS",
+        action
+    )]
+    pub(crate) write_provenance_annotations: bool,
+
+    /// Account the lines and bytes written to each namespace in periods of
+    /// this length (for example "1h"), writing the usage of each completed
+    /// period to the catalog for billing and chargeback.
+    ///
+    /// The usage accounted by all routers for the same period is summed in a
+    /// single catalog record. Usage is not accounted if not set.
+    #[clap(
+        long = "usage-accounting-period",
+        env = "INFLUXDB_IOX_USAGE_ACCOUNTING_PERIOD",
+        value_parser = humantime::parse_duration,
+        action
+    )]
+    pub(crate) usage_accounting_period: Option<Duration>,
+}
+
+/// The encoding of compressed gRPC messages.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, clap::ValueEnum)]
+pub enum GrpcCompression {
+    /// No compression.
+    None,
+
+    /// gzip.
+    Gzip,
+}
+
+impl GrpcCompression {
+    /// The encoding to configure the gRPC services with, if any.
+    fn encoding(self) -> Option<CompressionEncoding> {
+        match self {
+            Self::None => None,
+            Self::Gzip => Some(CompressionEncoding::Gzip),
+        }
+    }
 }
 
 pub async fn command(config: Config) -> Result<()> {
@@ -116,6 +443,28 @@ pub async fn command(config: Config) -> Result<()> {
         &config.write_buffer_config,
         &config.query_pool_name,
         config.http_request_limit,
+        config.http_limits_file,
+        config.http_auth_tokens,
+        config.org_write_lines_per_second,
+        config.org_write_bytes_per_second,
+        config.cors_allowed_origins,
+        config.cors_max_age,
+        config.max_write_lines,
+        config.max_write_fields_per_line,
+        config.max_write_tags_per_line,
+        config.http_request_timeout,
+        config.shard_ring_vnodes,
+        config.shard_circuit_breaker_error_threshold,
+        config.shard_circuit_breaker_cool_down,
+        config.schema_coerce_namespaces,
+        config.dml_table_metrics_max_tables,
+        config.max_parallel_writes,
+        config.namespace_cache_refresh_interval,
+        config.grpc_admin_tokens,
+        config.grpc_compression.encoding(),
+        config.grpc_max_message_size,
+        config.write_provenance_annotations,
+        config.usage_accounting_period,
     )
     .await?;
 
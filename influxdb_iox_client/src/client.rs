@@ -23,6 +23,12 @@ pub mod namespace;
 /// Client for schema API
 pub mod schema;
 
+/// Client for the ingester's persist API
+pub mod persist;
+
+/// Client for the router's shard API
+pub mod sharder;
+
 /// Client for interacting with a remote object store
 pub mod store;
 
@@ -49,4 +49,15 @@ impl Client {
 
         Ok(response.into_inner().partitions)
     }
+
+    /// List a page of the parquet file records in a partition matching the
+    /// filters in `request`
+    pub async fn list_parquet_files(
+        &mut self,
+        request: ListParquetFilesRequest,
+    ) -> Result<ListParquetFilesResponse, Error> {
+        let response = self.inner.list_parquet_files(request).await?;
+
+        Ok(response.into_inner())
+    }
 }
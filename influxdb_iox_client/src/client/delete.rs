@@ -1,3 +1,4 @@
+use ::generated_types::google::OptionalField;
 use client_util::connection::GrpcConnection;
 
 use self::generated_types::{delete_service_client::DeleteServiceClient, *};
@@ -34,7 +35,7 @@ pub mod generated_types {
 ///
 /// let mut client = Client::new(connection);
 ///
-/// // Delete some data
+/// // Delete some data, and query the progress of the delete
 /// let pred = Predicate {
 ///     range: Some(TimestampRange {
 ///         start: 100,
@@ -50,7 +51,7 @@ pub mod generated_types {
 ///         }),
 ///     }],
 /// };
-/// client
+/// let job_id = client
 ///     .delete(
 ///         "my_db",
 ///         "my_table",
@@ -58,6 +59,10 @@ pub mod generated_types {
 ///     )
 ///     .await
 ///     .expect("failed to delete data");
+/// let status = client
+///     .get_delete_status(job_id)
+///     .await
+///     .expect("failed to get delete status");
 /// # }
 /// ```
 #[derive(Debug, Clone)]
@@ -73,17 +78,19 @@ impl Client {
         }
     }
 
-    /// Delete data from a table on a specified predicate
+    /// Delete data from a table (or all tables, if `table_name` is empty) on
+    /// a specified predicate, returning the ID of the job applying the delete
     pub async fn delete(
         &mut self,
         db_name: impl Into<String> + Send,
         table_name: impl Into<String> + Send,
         predicate: Predicate,
-    ) -> Result<(), Error> {
+    ) -> Result<u64, Error> {
         let db_name = db_name.into();
         let table_name = table_name.into();
 
-        self.inner
+        let response = self
+            .inner
             .delete(DeleteRequest {
                 payload: Some(DeletePayload {
                     db_name,
@@ -93,6 +100,16 @@ impl Client {
             })
             .await?;
 
-        Ok(())
+        Ok(response.into_inner().job_id)
+    }
+
+    /// Get the progress of the delete job `job_id` in each shard
+    pub async fn get_delete_status(&mut self, job_id: u64) -> Result<DeleteJobStatus, Error> {
+        let response = self
+            .inner
+            .get_delete_status(GetDeleteStatusRequest { job_id })
+            .await?;
+
+        Ok(response.into_inner().status.unwrap_field("status")?)
     }
 }
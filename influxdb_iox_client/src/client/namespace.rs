@@ -1,3 +1,4 @@
+use ::generated_types::google::OptionalField;
 use client_util::connection::GrpcConnection;
 
 use self::generated_types::{namespace_service_client::NamespaceServiceClient, *};
@@ -29,4 +30,143 @@ impl Client {
 
         Ok(response.into_inner().namespaces)
     }
+
+    /// Get a single namespace, including its service protection limits
+    pub async fn get_namespace(&mut self, name: &str) -> Result<Namespace, Error> {
+        let response = self
+            .inner
+            .get_namespace(GetNamespaceRequest {
+                name: name.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().namespace.unwrap_field("namespace")?)
+    }
+
+    /// Update a service protection limit of a namespace
+    pub async fn update_namespace_service_protection_limit(
+        &mut self,
+        name: &str,
+        limit_update: update_namespace_service_protection_limit_request::LimitUpdate,
+    ) -> Result<Namespace, Error> {
+        let response = self
+            .inner
+            .update_namespace_service_protection_limit(
+                UpdateNamespaceServiceProtectionLimitRequest {
+                    name: name.to_string(),
+                    limit_update: Some(limit_update),
+                },
+            )
+            .await?;
+
+        Ok(response.into_inner().namespace.unwrap_field("namespace")?)
+    }
+
+    /// Create a namespace with the given retention period, in nanoseconds
+    /// (`None` for infinite retention)
+    pub async fn create_namespace(
+        &mut self,
+        name: &str,
+        retention_period_ns: Option<i64>,
+    ) -> Result<Namespace, Error> {
+        let response = self
+            .inner
+            .create_namespace(CreateNamespaceRequest {
+                name: name.to_string(),
+                retention_period_ns,
+            })
+            .await?;
+
+        Ok(response.into_inner().namespace.unwrap_field("namespace")?)
+    }
+
+    /// Update the retention period of a namespace, in nanoseconds (`None` for
+    /// infinite retention)
+    pub async fn update_namespace_retention(
+        &mut self,
+        name: &str,
+        retention_period_ns: Option<i64>,
+    ) -> Result<Namespace, Error> {
+        let response = self
+            .inner
+            .update_namespace_retention(UpdateNamespaceRetentionRequest {
+                name: name.to_string(),
+                retention_period_ns,
+            })
+            .await?;
+
+        Ok(response.into_inner().namespace.unwrap_field("namespace")?)
+    }
+
+    /// Set the partition template of a namespace, given as a list of template
+    /// parts such as `tag:region` or `time:%Y-%m-%d` (`None` to reset the
+    /// namespace to the default partition template)
+    pub async fn update_namespace_partition_template(
+        &mut self,
+        name: &str,
+        parts: Option<Vec<String>>,
+    ) -> Result<Namespace, Error> {
+        let response = self
+            .inner
+            .update_namespace_partition_template(UpdateNamespacePartitionTemplateRequest {
+                name: name.to_string(),
+                partition_template: parts.map(|parts| PartitionTemplate { parts }),
+            })
+            .await?;
+
+        Ok(response.into_inner().namespace.unwrap_field("namespace")?)
+    }
+
+    /// Validate a partition template, returning the distinct partition keys it
+    /// derives for the (optionally empty) sample `line_protocol`
+    pub async fn validate_partition_template(
+        &mut self,
+        parts: Vec<String>,
+        line_protocol: &str,
+    ) -> Result<Vec<String>, Error> {
+        let response = self
+            .inner
+            .validate_partition_template(ValidatePartitionTemplateRequest {
+                partition_template: Some(PartitionTemplate { parts }),
+                line_protocol: line_protocol.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().partition_keys)
+    }
+
+    /// Mark a namespace as deleted
+    pub async fn delete_namespace(&mut self, name: &str) -> Result<(), Error> {
+        self.inner
+            .delete_namespace(DeleteNamespaceRequest {
+                name: name.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Evict a namespace (or all namespaces, if `name` is `None`) from the
+    /// schema cache of the router, returning the number of namespaces evicted
+    pub async fn invalidate_namespace_cache(&mut self, name: Option<&str>) -> Result<u64, Error> {
+        let response = self
+            .inner
+            .invalidate_namespace_cache(InvalidateNamespaceCacheRequest {
+                name: name.unwrap_or_default().to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().evicted)
+    }
+
+    /// Reload the cached schema of a namespace in the router from the catalog
+    pub async fn refresh_namespace_cache(&mut self, name: &str) -> Result<(), Error> {
+        self.inner
+            .refresh_namespace_cache(RefreshNamespaceCacheRequest {
+                name: name.to_string(),
+            })
+            .await?;
+
+        Ok(())
+    }
 }
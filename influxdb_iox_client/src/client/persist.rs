@@ -0,0 +1,64 @@
+use client_util::connection::GrpcConnection;
+
+use self::generated_types::{persist_service_client::PersistServiceClient, *};
+
+use crate::connection::Connection;
+use crate::error::Error;
+
+/// Re-export generated_types
+pub mod generated_types {
+    pub use generated_types::influxdata::iox::ingester::v1::{
+        persist_service_client, persist_service_server, GetHotPartitionsRequest,
+        GetHotPartitionsResponse, HotPartition, PersistPartitionRequest, PersistPartitionResponse,
+    };
+}
+
+/// A basic client for persisting the data buffered by a single ingester.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: PersistServiceClient<GrpcConnection>,
+}
+
+impl Client {
+    /// Creates a new client with the provided connection
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            inner: PersistServiceClient::new(connection.into_grpc_connection()),
+        }
+    }
+
+    /// Persist the data buffered for the partition identified by
+    /// `namespace`, `table` and `partition_key`, returning once it has been
+    /// persisted.
+    ///
+    /// Returns false if the partition had no buffered data to persist.
+    pub async fn persist_partition(
+        &mut self,
+        namespace: impl Into<String> + Send,
+        table: impl Into<String> + Send,
+        partition_key: impl Into<String> + Send,
+    ) -> Result<bool, Error> {
+        let response = self
+            .inner
+            .persist_partition(PersistPartitionRequest {
+                namespace: namespace.into(),
+                table: table.into(),
+                partition_key: partition_key.into(),
+            })
+            .await?;
+
+        Ok(response.into_inner().persisted)
+    }
+
+    /// List the partitions buffered by the ingester that are currently
+    /// receiving writes faster than its hot partition threshold, hottest
+    /// first.
+    pub async fn get_hot_partitions(&mut self) -> Result<Vec<HotPartition>, Error> {
+        let response = self
+            .inner
+            .get_hot_partitions(GetHotPartitionsRequest {})
+            .await?;
+
+        Ok(response.into_inner().partitions)
+    }
+}
@@ -35,4 +35,21 @@ impl Client {
 
         Ok(response.into_inner().schema.unwrap_field("schema")?)
     }
+
+    /// Get the schema for a single table in a namespace.
+    pub async fn get_table_schema(
+        &mut self,
+        namespace: &str,
+        table: &str,
+    ) -> Result<TableSchema, Error> {
+        let response = self
+            .inner
+            .get_table_schema(GetTableSchemaRequest {
+                namespace: namespace.to_string(),
+                table: table.to_string(),
+            })
+            .await?;
+
+        Ok(response.into_inner().schema.unwrap_field("schema")?)
+    }
 }
@@ -0,0 +1,135 @@
+use self::generated_types::{shard_service_client::ShardServiceClient, *};
+use crate::{connection::Connection, error::Error};
+use ::generated_types::google::OptionalField;
+use client_util::connection::GrpcConnection;
+
+/// Re-export generated_types
+pub mod generated_types {
+    pub use generated_types::influxdata::iox::sharder::v1::*;
+}
+
+/// A basic client for interacting with the router's shard service.
+#[derive(Debug, Clone)]
+pub struct Client {
+    inner: ShardServiceClient<GrpcConnection>,
+}
+
+impl Client {
+    /// Creates a new client with the provided connection
+    pub fn new(connection: Connection) -> Self {
+        Self {
+            inner: ShardServiceClient::new(connection.into_grpc_connection()),
+        }
+    }
+
+    /// Map a table in a namespace to the shard its writes are routed to
+    pub async fn map_to_shard(
+        &mut self,
+        namespace_name: impl Into<String> + Send,
+        table_name: impl Into<String> + Send,
+    ) -> Result<MapToShardResponse, Error> {
+        let response = self
+            .inner
+            .map_to_shard(MapToShardRequest {
+                namespace_name: namespace_name.into(),
+                table_name: table_name.into(),
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Map many (namespace, table) pairs to the shards their writes are routed
+    /// to in a single call, returning a mapping per pair in input order
+    pub async fn map_to_shards(
+        &mut self,
+        tables: impl IntoIterator<Item = (String, String)> + Send,
+    ) -> Result<Vec<ShardMapping>, Error> {
+        let response = self
+            .inner
+            .map_to_shards(MapToShardsRequest {
+                tables: tables
+                    .into_iter()
+                    .map(|(namespace_name, table_name)| MapToShardRequest {
+                        namespace_name,
+                        table_name,
+                    })
+                    .collect(),
+            })
+            .await?;
+
+        Ok(response.into_inner().mappings)
+    }
+
+    /// Pin all writes for a table in a namespace to the shard with the given index
+    pub async fn set_table_shard_override(
+        &mut self,
+        namespace_name: impl Into<String> + Send,
+        table_name: impl Into<String> + Send,
+        shard_index: i32,
+    ) -> Result<TableShardOverride, Error> {
+        let response = self
+            .inner
+            .set_table_shard_override(SetTableShardOverrideRequest {
+                shard_override: Some(TableShardOverride {
+                    namespace_name: namespace_name.into(),
+                    table_name: table_name.into(),
+                    shard_index,
+                }),
+            })
+            .await?;
+
+        Ok(response
+            .into_inner()
+            .shard_override
+            .unwrap_field("shard_override")?)
+    }
+
+    /// Remove the shard override for a table in a namespace
+    pub async fn delete_table_shard_override(
+        &mut self,
+        namespace_name: impl Into<String> + Send,
+        table_name: impl Into<String> + Send,
+    ) -> Result<(), Error> {
+        self.inner
+            .delete_table_shard_override(DeleteTableShardOverrideRequest {
+                namespace_name: namespace_name.into(),
+                table_name: table_name.into(),
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    /// List all table shard overrides
+    pub async fn list_table_shard_overrides(&mut self) -> Result<Vec<TableShardOverride>, Error> {
+        let response = self
+            .inner
+            .list_table_shard_overrides(ListTableShardOverridesRequest {})
+            .await?;
+
+        Ok(response.into_inner().overrides)
+    }
+
+    /// Replace the set of shards in the router's consistent hash ring
+    pub async fn reshard(
+        &mut self,
+        shard_indexes: impl IntoIterator<Item = i32> + Send,
+    ) -> Result<ReshardResponse, Error> {
+        let response = self
+            .inner
+            .reshard(ReshardRequest {
+                shard_indexes: shard_indexes.into_iter().collect(),
+            })
+            .await?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Get the shards in the router's consistent hash ring
+    pub async fn shard_ring(&mut self) -> Result<Vec<ShardRingMember>, Error> {
+        let response = self.inner.get_shard_ring(GetShardRingRequest {}).await?;
+
+        Ok(response.into_inner().members)
+    }
+}
@@ -31,10 +31,24 @@ impl Client {
         &mut self,
         uuid: String,
     ) -> Result<BoxStream<'static, Result<GetParquetFileByObjectStoreIdResponse, Status>>, Error>
+    {
+        self.get_parquet_file_range(GetParquetFileByObjectStoreIdRequest {
+            uuid,
+            ..Default::default()
+        })
+        .await
+    }
+
+    /// Get the parquet file data, or the byte range of it, described by
+    /// `request`
+    pub async fn get_parquet_file_range(
+        &mut self,
+        request: GetParquetFileByObjectStoreIdRequest,
+    ) -> Result<BoxStream<'static, Result<GetParquetFileByObjectStoreIdResponse, Status>>, Error>
     {
         let response = self
             .inner
-            .get_parquet_file_by_object_store_id(GetParquetFileByObjectStoreIdRequest { uuid })
+            .get_parquet_file_by_object_store_id(request)
             .await?;
 
         Ok(Box::pin(response.into_inner()))
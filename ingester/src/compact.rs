@@ -2,16 +2,21 @@
 
 use std::sync::Arc;
 
-use datafusion::{error::DataFusionError, physical_plan::SendableRecordBatchStream};
+use datafusion::{
+    error::DataFusionError,
+    physical_plan::{ExecutionPlan, SendableRecordBatchStream},
+};
 use iox_query::{
     exec::{Executor, ExecutorType},
     frontend::reorg::ReorgPlanner,
+    util::compute_timenanosecond_min_max_for_one_record_batch,
     QueryChunk, QueryChunkMeta,
 };
+use observability_deps::tracing::debug;
 use schema::sort::{adjust_sort_key_columns, compute_sort_key, SortKey};
 use snafu::{ResultExt, Snafu};
 
-use crate::{data::partition::PersistingBatch, query::QueryableBatch};
+use crate::{data::partition::PersistingBatch, persist::estimate_data_size, query::QueryableBatch};
 
 #[derive(Debug, Snafu)]
 #[allow(missing_copy_implementations, missing_docs)]
@@ -53,9 +58,13 @@ pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// Result of calling [`compact_persisting_batch`]
 pub(crate) struct CompactedStream {
-    /// A stream of compacted, deduplicated
-    /// [`RecordBatch`](arrow::record_batch::RecordBatch)es
-    pub(crate) stream: SendableRecordBatchStream,
+    /// Streams of compacted, deduplicated
+    /// [`RecordBatch`](arrow::record_batch::RecordBatch)es, one per output
+    /// parquet file, covering non-overlapping time ranges in ascending order.
+    ///
+    /// When there is more than one stream, they are outputs of the same plan
+    /// and MUST be consumed concurrently, otherwise the plan may deadlock.
+    pub(crate) streams: Vec<SendableRecordBatchStream>,
 
     /// The sort key value the catalog should be updated to, if any.
     ///
@@ -78,7 +87,7 @@ pub(crate) struct CompactedStream {
 impl std::fmt::Debug for CompactedStream {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("CompactedStream")
-            .field("stream", &"<SendableRecordBatchStream>")
+            .field("streams", &self.streams.len())
             .field("data_sort_key", &self.data_sort_key)
             .field("catalog_sort_key_update", &self.catalog_sort_key_update)
             .finish()
@@ -87,10 +96,16 @@ impl std::fmt::Debug for CompactedStream {
 
 /// Compact a given persisting batch into a [`CompactedStream`] or
 /// `None` if there is no data to compact.
+///
+/// If the batch contains more than `max_file_rows` rows, or its in-memory
+/// size exceeds `max_file_bytes`, the compacted output is split by time into
+/// multiple streams, each expected to be within the limits.
 pub(crate) async fn compact_persisting_batch(
     executor: &Executor,
     sort_key: Option<SortKey>,
     batch: Arc<PersistingBatch>,
+    max_file_rows: Option<usize>,
+    max_file_bytes: Option<usize>,
 ) -> Result<CompactedStream> {
     assert!(!batch.data.data.is_empty());
 
@@ -117,16 +132,93 @@ pub(crate) async fn compact_persisting_batch(
         }
     };
 
-    // Compact
-    let stream = compact(executor, Arc::clone(&batch.data), data_sort_key.clone()).await?;
+    // Compact, splitting the output across multiple streams if it is too
+    // large for a single file.
+    let split_times = compute_split_times(&batch.data, max_file_rows, max_file_bytes)?;
+    let streams = if split_times.is_empty() {
+        vec![compact(executor, Arc::clone(&batch.data), data_sort_key.clone()).await?]
+    } else {
+        debug!(
+            partition_id=%batch.partition_id,
+            n_files=split_times.len() + 1,
+            "splitting persisted data across multiple files"
+        );
+        split(
+            executor,
+            Arc::clone(&batch.data),
+            data_sort_key.clone(),
+            split_times,
+        )
+        .await?
+    };
 
     Ok(CompactedStream {
-        stream,
+        streams,
         catalog_sort_key_update,
         data_sort_key,
     })
 }
 
+/// Compute the times at which to split the compacted output of `data` so
+/// that each output file contains at most `max_file_rows` rows and
+/// `max_file_bytes` of (estimated in-memory) data.
+///
+/// As in the compactor, rows are assumed to be evenly distributed over the
+/// time range of the data, so the output files are only approximately within
+/// the limits. Returns an empty list if the output does not need splitting.
+fn compute_split_times(
+    data: &QueryableBatch,
+    max_file_rows: Option<usize>,
+    max_file_bytes: Option<usize>,
+) -> Result<Vec<i64>> {
+    let rows: usize = data.data.iter().map(|s| s.data.num_rows()).sum();
+    let bytes = estimate_data_size(data);
+
+    let n_files = [(rows, max_file_rows), (bytes, max_file_bytes)]
+        .into_iter()
+        .filter_map(|(v, max)| max.map(|max| v / max + usize::from(v % max != 0)))
+        .max()
+        .unwrap_or(1);
+    if n_files <= 1 {
+        return Ok(vec![]);
+    }
+
+    let snapshot_times = data
+        .data
+        .iter()
+        .map(|s| compute_timenanosecond_min_max_for_one_record_batch(&s.data))
+        .collect::<Result<Vec<_>, _>>()
+        .context(MinMaxSnafu)?;
+    let min_time = snapshot_times.iter().map(|(min, _)| *min).min().unwrap();
+    let max_time = snapshot_times.iter().map(|(_, max)| *max).max().unwrap();
+
+    // Each split time is the inclusive upper bound of an output file, so the
+    // last file always ends at `max_time`.
+    let range = max_time as i128 - min_time as i128;
+    let mut split_times: Vec<_> = (1..n_files as i128)
+        .map(|i| min_time + (range * i / n_files as i128) as i64)
+        .filter(|&t| t < max_time)
+        .collect();
+    split_times.dedup();
+
+    // Merge time ranges no snapshot has data for into the following range,
+    // rather than producing empty files.
+    split_times.retain({
+        let mut lower = i64::MIN;
+        move |&t| {
+            let present = snapshot_times
+                .iter()
+                .any(|&(min, max)| max > lower && min <= t);
+            if present {
+                lower = t;
+            }
+            present
+        }
+    });
+
+    Ok(split_times)
+}
+
 /// Compact a given Queryable Batch
 pub(crate) async fn compact(
     executor: &Executor,
@@ -154,9 +246,50 @@ pub(crate) async fn compact(
     Ok(output_stream)
 }
 
+/// Compact a given Queryable Batch into one stream per time range delimited
+/// by `split_times`, as described in [`ReorgPlanner::split_plan`].
+pub(crate) async fn split(
+    executor: &Executor,
+    data: Arc<QueryableBatch>,
+    sort_key: SortKey,
+    split_times: Vec<i64>,
+) -> Result<Vec<SendableRecordBatchStream>> {
+    // Build logical plan for compaction
+    let ctx = executor.new_context(ExecutorType::Reorg);
+    let logical_plan = ReorgPlanner::new(ctx.child_ctx("ReorgPlanner"))
+        .split_plan(
+            data.schema(),
+            [data as Arc<dyn QueryChunk>],
+            sort_key,
+            split_times,
+        )
+        .context(LogicalPlanSnafu {})?;
+
+    // Build physical plan
+    let physical_plan = ctx
+        .create_physical_plan(&logical_plan)
+        .await
+        .context(PhysicalPlanSnafu {})?;
+
+    // Execute each output partition of the plan
+    let mut streams = vec![];
+    for partition in 0..physical_plan.output_partitioning().partition_count() {
+        let stream = ctx
+            .execute_stream_partitioned(Arc::clone(&physical_plan), partition)
+            .await
+            .context(ExecutePlanSnafu {})?;
+        streams.push(stream);
+    }
+
+    Ok(streams)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use arrow_util::assert_batches_eq;
+    use iox_query::exec::{ExecutorConfig, SpillConfig};
     use mutable_batch_lp::lines_to_batches;
     use schema::selection::Selection;
     use uuid::Uuid;
@@ -210,12 +343,12 @@ mod tests {
 
         // compact
         let exc = Executor::new(1);
-        let CompactedStream { stream, .. } =
-            compact_persisting_batch(&exc, Some(SortKey::empty()), persisting_batch)
+        let CompactedStream { streams, .. } =
+            compact_persisting_batch(&exc, Some(SortKey::empty()), persisting_batch, None, None)
                 .await
                 .unwrap();
 
-        let output_batches = datafusion::physical_plan::common::collect(stream)
+        let output_batches = datafusion::physical_plan::common::collect(single_stream(streams))
             .await
             .expect("should execute plan");
 
@@ -262,14 +395,14 @@ mod tests {
         // compact
         let exc = Executor::new(1);
         let CompactedStream {
-            stream,
+            streams,
             data_sort_key,
             catalog_sort_key_update,
-        } = compact_persisting_batch(&exc, Some(SortKey::empty()), persisting_batch)
+        } = compact_persisting_batch(&exc, Some(SortKey::empty()), persisting_batch, None, None)
             .await
             .unwrap();
 
-        let output_batches = datafusion::physical_plan::common::collect(stream)
+        let output_batches = datafusion::physical_plan::common::collect(single_stream(streams))
             .await
             .expect("should execute plan");
 
@@ -326,14 +459,14 @@ mod tests {
 
         // NO SORT KEY from the catalog here, first persisting batch
         let CompactedStream {
-            stream,
+            streams,
             data_sort_key,
             catalog_sort_key_update,
-        } = compact_persisting_batch(&exc, Some(SortKey::empty()), persisting_batch)
+        } = compact_persisting_batch(&exc, Some(SortKey::empty()), persisting_batch, None, None)
             .await
             .unwrap();
 
-        let output_batches = datafusion::physical_plan::common::collect(stream)
+        let output_batches = datafusion::physical_plan::common::collect(single_stream(streams))
             .await
             .expect("should execute plan");
 
@@ -395,18 +528,20 @@ mod tests {
         // SPECIFY A SORT KEY HERE to simulate a sort key being stored in the catalog
         // this is NOT what the computed sort key would be based on this data's cardinality
         let CompactedStream {
-            stream,
+            streams,
             data_sort_key,
             catalog_sort_key_update,
         } = compact_persisting_batch(
             &exc,
             Some(SortKey::from_columns(["tag3", "tag1", "time"])),
             persisting_batch,
+            None,
+            None,
         )
         .await
         .unwrap();
 
-        let output_batches = datafusion::physical_plan::common::collect(stream)
+        let output_batches = datafusion::physical_plan::common::collect(single_stream(streams))
             .await
             .expect("should execute plan");
 
@@ -468,18 +603,20 @@ mod tests {
         // this is NOT what the computed sort key would be based on this data's cardinality
         // The new column, tag1, should get added just before the time column
         let CompactedStream {
-            stream,
+            streams,
             data_sort_key,
             catalog_sort_key_update,
         } = compact_persisting_batch(
             &exc,
             Some(SortKey::from_columns(["tag3", "time"])),
             persisting_batch,
+            None,
+            None,
         )
         .await
         .unwrap();
 
-        let output_batches = datafusion::physical_plan::common::collect(stream)
+        let output_batches = datafusion::physical_plan::common::collect(single_stream(streams))
             .await
             .expect("should execute plan");
 
@@ -544,18 +681,20 @@ mod tests {
         // this is NOT what the computed sort key would be based on this data's cardinality
         // This contains a sort key, "tag4", that doesn't appear in the data.
         let CompactedStream {
-            stream,
+            streams,
             data_sort_key,
             catalog_sort_key_update,
         } = compact_persisting_batch(
             &exc,
             Some(SortKey::from_columns(["tag3", "tag1", "tag4", "time"])),
             persisting_batch,
+            None,
+            None,
         )
         .await
         .unwrap();
 
-        let output_batches = datafusion::physical_plan::common::collect(stream)
+        let output_batches = datafusion::physical_plan::common::collect(single_stream(streams))
             .await
             .expect("should execute plan");
 
@@ -583,6 +722,114 @@ mod tests {
         assert!(catalog_sort_key_update.is_none());
     }
 
+    #[tokio::test]
+    async fn test_compact_persisting_batch_split_by_rows() {
+        test_helpers::maybe_start_logging();
+
+        // create input data
+        let batch = lines_to_batches(
+            "cpu,tag1=a bar=1 10\n\
+             cpu,tag1=b bar=2 20\n\
+             cpu,tag1=a bar=3 30\n\
+             cpu,tag1=b bar=4 40\n\
+             cpu,tag1=a bar=5 40",
+            0,
+        )
+        .unwrap()
+        .get("cpu")
+        .unwrap()
+        .to_arrow(Selection::All)
+        .unwrap();
+        let persisting_batch =
+            make_persisting_batch(1, 1, 1, "cpu", 1, Uuid::new_v4(), vec![Arc::new(batch)]);
+
+        // compact into files of at most 3 rows
+        let exc = Executor::new(1);
+        let CompactedStream { streams, .. } = compact_persisting_batch(
+            &exc,
+            Some(SortKey::from_columns(["tag1", "time"])),
+            persisting_batch,
+            Some(3),
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(streams.len(), 2);
+
+        // the split streams must be consumed concurrently
+        let output = futures::future::try_join_all(
+            streams
+                .into_iter()
+                .map(datafusion::physical_plan::common::collect),
+        )
+        .await
+        .expect("should execute plan");
+
+        // the data is split at time 25, each file sorted on the sort key
+        let expected_data = vec![
+            "+-----+------+--------------------------------+",
+            "| bar | tag1 | time                           |",
+            "+-----+------+--------------------------------+",
+            "| 1   | a    | 1970-01-01T00:00:00.000000010Z |",
+            "| 2   | b    | 1970-01-01T00:00:00.000000020Z |",
+            "+-----+------+--------------------------------+",
+        ];
+        assert_batches_eq!(&expected_data, &output[0]);
+        let expected_data = vec![
+            "+-----+------+--------------------------------+",
+            "| bar | tag1 | time                           |",
+            "+-----+------+--------------------------------+",
+            "| 3   | a    | 1970-01-01T00:00:00.000000030Z |",
+            "| 5   | a    | 1970-01-01T00:00:00.000000040Z |",
+            "| 4   | b    | 1970-01-01T00:00:00.000000040Z |",
+            "+-----+------+--------------------------------+",
+        ];
+        assert_batches_eq!(&expected_data, &output[1]);
+    }
+
+    #[test]
+    fn test_compute_split_times() {
+        let batch = |lp: &str| {
+            Arc::new(
+                lines_to_batches(lp, 0)
+                    .unwrap()
+                    .get("cpu")
+                    .unwrap()
+                    .to_arrow(Selection::All)
+                    .unwrap(),
+            )
+        };
+        let data = make_queryable_batch(
+            "cpu",
+            1,
+            1,
+            vec![
+                batch("cpu bar=1 0\ncpu bar=2 10\ncpu bar=3 20"),
+                batch("cpu bar=4 90\ncpu bar=5 100"),
+            ],
+        );
+
+        // within the limits, or no limits
+        assert!(compute_split_times(&data, None, None).unwrap().is_empty());
+        assert!(compute_split_times(&data, Some(5), None)
+            .unwrap()
+            .is_empty());
+        assert!(compute_split_times(&data, None, Some(usize::MAX))
+            .unwrap()
+            .is_empty());
+
+        // 5 rows into files of 2 rows is 3 files, but the range (33, 66] has
+        // no data and is merged into the last file.
+        assert_eq!(compute_split_times(&data, Some(2), None).unwrap(), [33]);
+
+        // A tiny byte limit splits the time range at every nanosecond, except
+        // where neither snapshot has data.
+        assert_eq!(
+            compute_split_times(&data, Some(5), Some(1)).unwrap(),
+            (0..=20).chain(90..100).collect::<Vec<_>>()
+        );
+    }
+
     #[tokio::test]
     async fn test_compact_one_row_batch() {
         test_helpers::maybe_start_logging();
@@ -721,6 +968,54 @@ mod tests {
         assert_batches_eq!(&expected, &output_batches);
     }
 
+    #[tokio::test]
+    async fn test_compact_many_batches_with_spill() {
+        // create many-batches input data
+        let batches = create_batches_with_influxtype().await;
+
+        // build queryable batch from the input batches
+        let compact_batch = make_queryable_batch("test_table", 0, 1, batches);
+        let schema = compact_batch.schema();
+        let sort_key = compute_sort_key(
+            &schema,
+            compact_batch.data.iter().map(|sb| sb.data.as_ref()),
+        );
+
+        // compact with a memory limit small enough that the sort must spill
+        let spill_dir = tempfile::tempdir().unwrap();
+        let exc = Executor::new_with_config(ExecutorConfig {
+            num_threads: 1,
+            target_query_partitions: 1,
+            object_stores: HashMap::default(),
+            reorg_spill: Some(SpillConfig {
+                memory_limit_bytes: 1,
+                directory: Some(spill_dir.path().to_path_buf()),
+            }),
+            query_memory_limit_bytes: None,
+        });
+        let stream = compact(&exc, compact_batch, sort_key).await.unwrap();
+        let output_batches = datafusion::physical_plan::common::collect(stream)
+            .await
+            .unwrap();
+
+        // verify compacted data is the same as without spilling
+        let expected = vec![
+            "+-----------+------+--------------------------------+",
+            "| field_int | tag1 | time                           |",
+            "+-----------+------+--------------------------------+",
+            "| 100       | AL   | 1970-01-01T00:00:00.000000050Z |",
+            "| 70        | CT   | 1970-01-01T00:00:00.000000100Z |",
+            "| 70        | CT   | 1970-01-01T00:00:00.000000500Z |",
+            "| 30        | MT   | 1970-01-01T00:00:00.000000005Z |",
+            "| 1000      | MT   | 1970-01-01T00:00:00.000001Z    |",
+            "| 1000      | MT   | 1970-01-01T00:00:00.000002Z    |",
+            "| 5         | MT   | 1970-01-01T00:00:00.000005Z    |",
+            "| 10        | MT   | 1970-01-01T00:00:00.000007Z    |",
+            "+-----------+------+--------------------------------+",
+        ];
+        assert_batches_eq!(&expected, &output_batches);
+    }
+
     #[tokio::test]
     async fn test_compact_many_batches_different_columns_with_duplicates() {
         // create many-batches input data
@@ -887,4 +1182,10 @@ mod tests {
         // the schema merge will thorw a panic
         compact_batch.schema();
     }
+
+    /// Unwrap the only output stream of a compaction that was not split.
+    fn single_stream(mut streams: Vec<SendableRecordBatchStream>) -> SendableRecordBatchStream {
+        assert_eq!(streams.len(), 1);
+        streams.pop().unwrap()
+    }
 }
@@ -1,11 +1,12 @@
 //! Data for the lifecycle of the Ingester
 
-use std::{collections::BTreeMap, sync::Arc};
+use std::{collections::BTreeMap, ops::ControlFlow, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use backoff::{Backoff, BackoffConfig};
 use data_types::{
-    CompactionLevel, NamespaceId, PartitionId, SequenceNumber, ShardId, ShardIndex, TableId,
+    CompactionLevel, NamespaceId, PartitionId, PartitionKey, SequenceNumber, ShardId, ShardIndex,
+    TableId,
 };
 
 use dml::DmlOperation;
@@ -17,14 +18,18 @@ use object_store::DynObjectStore;
 use observability_deps::tracing::*;
 use parquet_file::{
     metadata::IoxMetadata,
-    storage::{ParquetStorage, StorageId},
+    serialize::CodecError,
+    storage::{ParquetStorage, StorageId, UploadError},
 };
-use snafu::{OptionExt, Snafu};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
+use uuid::Uuid;
 use write_summary::ShardProgress;
 
 use crate::{
     compact::{compact_persisting_batch, CompactedStream},
     lifecycle::LifecycleHandle,
+    persist::{PersistConfig, PersistScheduler},
+    wal::Wal,
 };
 
 pub(crate) mod namespace;
@@ -32,11 +37,21 @@ pub mod partition;
 pub(crate) mod shard;
 pub(crate) mod table;
 
-use self::{partition::resolver::PartitionProvider, shard::ShardData};
+mod sequence_range;
+pub(crate) use sequence_range::*;
+
+use self::{
+    namespace::NamespaceName, partition::resolver::PartitionProvider, shard::ShardData,
+    table::TableName,
+};
 
 #[cfg(test)]
 mod triggers;
 
+/// How often [`IngesterData::replay_wal()`] checks whether a paused replay
+/// can resume.
+const WAL_REPLAY_PAUSE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
 #[derive(Debug, Snafu)]
 #[allow(missing_copy_implementations, missing_docs)]
 pub enum Error {
@@ -49,6 +64,16 @@ pub enum Error {
     #[snafu(display("Table {} not found in buffer", table_name))]
     TableNotFound { table_name: String },
 
+    #[snafu(display(
+        "Partition {} of table {} not found in buffer",
+        partition_key,
+        table_name
+    ))]
+    PartitionNotFound {
+        table_name: String,
+        partition_key: PartitionKey,
+    },
+
     #[snafu(display("Error accessing catalog: {}", source))]
     Catalog {
         source: iox_catalog::interface::Error,
@@ -65,6 +90,9 @@ pub enum Error {
 
     #[snafu(display("Error adding to buffer in mutable batch: {}", source))]
     BufferWrite { source: mutable_batch::Error },
+
+    #[snafu(display("Error accessing write-ahead log: {}", source))]
+    Wal { source: crate::wal::Error },
 }
 
 /// A specialized `Error` for Ingester Data errors
@@ -87,6 +115,13 @@ pub struct IngesterData {
     /// Executor for running queries and compacting and persisting
     exec: Arc<Executor>,
 
+    /// Limits the number and size of persist jobs compacting and uploading
+    /// partitions at once
+    persist_scheduler: PersistScheduler,
+
+    /// An optional local log of the buffered operations, replayed on startup
+    wal: Option<Arc<Wal>>,
+
     /// Backoff config
     backoff_config: BackoffConfig,
 
@@ -143,11 +178,30 @@ impl IngesterData {
             catalog,
             shards,
             exec,
+            persist_scheduler: PersistScheduler::new(PersistConfig::default(), &metrics),
+            wal: None,
             backoff_config,
             persisted_file_size_bytes,
         }
     }
 
+    /// Limit the persist jobs executing at once to those admitted by
+    /// `persist_scheduler`.
+    ///
+    /// By default, the number and size of concurrent persist jobs is
+    /// unbounded.
+    pub fn with_persist_scheduler(mut self, persist_scheduler: PersistScheduler) -> Self {
+        self.persist_scheduler = persist_scheduler;
+        self
+    }
+
+    /// Record every buffered operation in `wal` before it is applied, so that
+    /// unpersisted data can be recovered from `wal` after a restart.
+    pub fn with_wal(mut self, wal: Arc<Wal>) -> Self {
+        self.wal = Some(wal);
+        self
+    }
+
     /// Executor for running queries and compacting and persisting
     pub(crate) fn exec(&self) -> &Arc<Executor> {
         &self.exec
@@ -170,6 +224,12 @@ impl IngesterData {
     /// created in the catalog before putting into the buffer. Writes will
     /// get logged in the lifecycle manager. If it indicates ingest should
     /// be paused, this function will return true.
+    ///
+    /// If a WAL is configured, the operation is appended to the WAL before
+    /// it is buffered. Appends that fail with a transient error (such as a
+    /// full disk) are retried until they succeed, stalling ingest on
+    /// `shard_id`, as skipping the operation would leave a gap in the WAL that
+    /// is never re-read from the write buffer after a restart.
     pub async fn buffer_operation(
         &self,
         shard_id: ShardId,
@@ -180,11 +240,152 @@ impl IngesterData {
             .shards
             .get(&shard_id)
             .context(ShardNotFoundSnafu { shard_id })?;
+
+        if let Some(wal) = &self.wal {
+            Backoff::new(&self.backoff_config)
+                .retry_with_backoff("append to WAL", || async {
+                    match wal.append(shard_id, &dml_operation).await {
+                        Ok(()) => ControlFlow::Break(Ok(())),
+                        Err(e) if e.is_transient() => ControlFlow::Continue(e),
+                        Err(e) => ControlFlow::Break(Err(e)),
+                    }
+                })
+                .await
+                .expect("retry forever")
+                .context(WalSnafu)?;
+        }
+
         shard_data
             .buffer_operation(dml_operation, &self.catalog, lifecycle_handle)
             .await
     }
 
+    /// Buffer the operations for `shard_id` recorded in the WAL with a
+    /// sequence number of at least `min_unpersisted`, returning the sequence
+    /// number of the next operation to read from the write buffer.
+    ///
+    /// If no WAL is configured, or the WAL does not contain every operation
+    /// from `min_unpersisted`, nothing is replayed and `min_unpersisted` is
+    /// returned.
+    pub(crate) async fn replay_wal(
+        &self,
+        shard_id: ShardId,
+        min_unpersisted: SequenceNumber,
+        lifecycle_handle: &dyn LifecycleHandle,
+    ) -> Result<SequenceNumber> {
+        let wal = match &self.wal {
+            Some(v) => v,
+            None => return Ok(min_unpersisted),
+        };
+        let shard_data = self
+            .shards
+            .get(&shard_id)
+            .context(ShardNotFoundSnafu { shard_id })?;
+
+        let ops = match wal
+            .replay(shard_id, shard_data.shard_index(), min_unpersisted)
+            .await
+            .context(WalSnafu)?
+        {
+            Some(v) => v,
+            None => return Ok(min_unpersisted),
+        };
+
+        let mut next = min_unpersisted;
+        let n_ops = ops.len();
+        for op in ops {
+            let sequence_number = op
+                .meta()
+                .sequence()
+                .expect("WAL operations are sequenced")
+                .sequence_number;
+            next = SequenceNumber::new(sequence_number.get() + 1);
+
+            // As with ops read from the write buffer, an op that cannot be
+            // applied is logged and skipped.
+            let should_pause = match shard_data
+                .buffer_operation(op, &self.catalog, lifecycle_handle)
+                .await
+            {
+                Ok(DmlApplyAction::Applied(should_pause)) => should_pause,
+                Ok(DmlApplyAction::Skipped) => false,
+                Err(e) => {
+                    error!(
+                        error=%e,
+                        %shard_id,
+                        op_sequence_number=?sequence_number,
+                        potential_data_loss=true,
+                        "failed to apply dml operation replayed from WAL"
+                    );
+                    false
+                }
+            };
+
+            // Wait for persistence to shed memory, as the stream handler does
+            // when consuming from the write buffer.
+            if should_pause {
+                while !lifecycle_handle.can_resume_ingest() {
+                    tokio::time::sleep(WAL_REPLAY_PAUSE_POLL_INTERVAL).await;
+                }
+            }
+        }
+
+        info!(
+            %shard_id,
+            ?min_unpersisted,
+            n_ops,
+            next_sequence_number=?next,
+            "replayed WAL"
+        );
+
+        Ok(next)
+    }
+
+    /// Return the ID of the buffered partition identified by `namespace`,
+    /// `table` and `partition_key`, searching all shards.
+    pub(crate) async fn partition_id(
+        &self,
+        namespace: &str,
+        table: &str,
+        partition_key: &PartitionKey,
+    ) -> Result<PartitionId> {
+        let namespace_name = NamespaceName::from(namespace);
+        let table_name = TableName::from(table);
+
+        let mut found_namespace = false;
+        let mut found_table = false;
+        for shard_data in self.shards.values() {
+            let namespace_data = match shard_data.namespace(&namespace_name) {
+                Some(v) => v,
+                None => continue,
+            };
+            found_namespace = true;
+
+            let table_data = match namespace_data.table_data(&table_name) {
+                Some(v) => v,
+                None => continue,
+            };
+            found_table = true;
+
+            if let Some(p) = table_data.read().await.get_partition_by_key(partition_key) {
+                return Ok(p.partition_id());
+            }
+        }
+
+        ensure!(
+            found_namespace,
+            NamespaceNotFoundSnafu {
+                namespace: namespace
+            }
+        );
+        ensure!(found_table, TableNotFoundSnafu { table_name: table });
+        PartitionNotFoundSnafu {
+            table_name: table,
+            partition_key: partition_key.clone(),
+        }
+        .fail()
+    }
+
     /// Return the ingestion progress for the specified shards
     /// Returns an empty `ShardProgress` for any shards that this ingester doesn't know about.
     pub(super) async fn progresses(
@@ -333,16 +534,28 @@ impl Persister for IngesterData {
         // compaction, instead of retaining a copy of the data post-compaction.
         let object_store_id = batch.object_store_id();
 
+        // Wait for the persist job to be admitted within the configured
+        // concurrency and memory limits, holding the permit until the
+        // compacted data has been uploaded.
+        let permit = self.persist_scheduler.admit(&batch).await;
+
         // do the CPU intensive work of compaction, de-duplication and sorting
+        let persist_config = self.persist_scheduler.config();
         let CompactedStream {
-            stream: record_stream,
+            streams,
             catalog_sort_key_update,
             data_sort_key,
-        } = compact_persisting_batch(&self.exec, sort_key, batch)
-            .await
-            .expect("unable to compact persisting batch");
+        } = compact_persisting_batch(
+            &self.exec,
+            sort_key,
+            batch,
+            persist_config.max_file_rows(),
+            persist_config.max_file_bytes(),
+        )
+        .await
+        .expect("unable to compact persisting batch");
 
-        // Construct the metadata for this parquet file.
+        // Construct the metadata for the parquet files.
         let iox_metadata = IoxMetadata {
             object_store_id,
             creation_timestamp: SystemProvider::new().now(),
@@ -358,14 +571,42 @@ impl Persister for IngesterData {
             sort_key: Some(data_sort_key),
         };
 
-        // Save the compacted data to a parquet file in object storage.
+        // Save the compacted data to parquet files in object storage, one per
+        // compacted stream. If the compacted output was split across multiple
+        // files, each is assigned its own object store ID, and all are
+        // uploaded concurrently as the split streams must be consumed
+        // concurrently.
         //
-        // This call retries until it completes.
-        let (md, file_size) = self
-            .store
-            .upload(record_stream, &iox_metadata)
-            .await
-            .expect("unexpected fatal persist error");
+        // Each upload retries until it completes.
+        let n_files = streams.len();
+        let files =
+            futures::future::join_all(streams.into_iter().enumerate().map(|(i, stream)| {
+                let mut meta = iox_metadata.clone();
+                if i > 0 {
+                    meta.object_store_id = Uuid::new_v4();
+                }
+                async move {
+                    match self.store.upload(stream, &meta).await {
+                        // A time range of a split compaction may legitimately
+                        // contain no rows.
+                        Err(UploadError::Serialise(CodecError::NoRows)) if n_files > 1 => {
+                            debug!(
+                                object_store_id=%meta.object_store_id,
+                                %shard_id,
+                                %table_id,
+                                %partition_id,
+                                "split persist produced an empty file, skipping"
+                            );
+                            None
+                        }
+                        res => Some((meta, res.expect("unexpected fatal persist error"))),
+                    }
+                }
+            }))
+            .await;
+
+        // The compacted streams have been consumed, releasing the job's memory.
+        drop(permit);
 
         // Update the sort key in the catalog if there are
         // additional columns BEFORE adding parquet file to the
@@ -422,11 +663,6 @@ impl Persister for IngesterData {
             .await
             .expect("retry forever");
 
-        // Add the parquet file to the catalog until succeed
-        let parquet_file = iox_metadata.to_parquet_file(partition_id, file_size, &md, |name| {
-            table_schema.columns.get(name).expect("Unknown column").id
-        });
-
         // Assert partitions are persisted in-order.
         //
         // It is an invariant that partitions are persisted in order so that
@@ -434,33 +670,46 @@ impl Persister for IngesterData {
         // advanced and accurate.
         if let Some(last_persist) = last_persisted_sequence_number {
             assert!(
-                parquet_file.max_sequence_number > last_persist,
+                max_sequence_number > last_persist,
                 "out of order partition persistence, persisting {}, previously persisted {}",
-                parquet_file.max_sequence_number.get(),
+                max_sequence_number.get(),
                 last_persist.get(),
             );
         }
 
-        // Add the parquet file to the catalog.
-        //
-        // This has the effect of allowing the queriers to "discover" the
-        // parquet file by polling / querying the catalog.
-        Backoff::new(&self.backoff_config)
-            .retry_all_errors("add parquet file to catalog", || async {
-                let mut repos = self.catalog.repositories().await;
-                let parquet_file = repos.parquet_files().create(parquet_file.clone()).await?;
-                debug!(
-                    ?partition_id,
-                    table_id=?parquet_file.table_id,
-                    parquet_file_id=?parquet_file.id,
-                    table_name=%iox_metadata.table_name,
-                    "parquet file written to catalog"
-                );
-                // compiler insisted on getting told the type of the error :shrug:
-                Ok(()) as Result<(), iox_catalog::interface::Error>
-            })
-            .await
-            .expect("retry forever");
+        let attributes = Attributes::from([("shard_id", format!("{}", shard_id).into())]);
+        for (meta, (md, file_size)) in files.into_iter().flatten() {
+            // Build the catalog entry for this file.
+            let parquet_file = meta.to_parquet_file(partition_id, file_size, &md, |name| {
+                table_schema.columns.get(name).expect("Unknown column").id
+            });
+
+            // Add the parquet file to the catalog.
+            //
+            // This has the effect of allowing the queriers to "discover" the
+            // parquet file by polling / querying the catalog.
+            Backoff::new(&self.backoff_config)
+                .retry_all_errors("add parquet file to catalog", || async {
+                    let mut repos = self.catalog.repositories().await;
+                    let parquet_file = repos.parquet_files().create(parquet_file.clone()).await?;
+                    debug!(
+                        ?partition_id,
+                        table_id=?parquet_file.table_id,
+                        parquet_file_id=?parquet_file.id,
+                        table_name=%iox_metadata.table_name,
+                        "parquet file written to catalog"
+                    );
+                    // compiler insisted on getting told the type of the error :shrug:
+                    Ok(()) as Result<(), iox_catalog::interface::Error>
+                })
+                .await
+                .expect("retry forever");
+
+            // Record metrics
+            self.persisted_file_size_bytes
+                .recorder(attributes.clone())
+                .record(file_size as u64);
+        }
 
         // Update the per-partition persistence watermark, so that new
         // ingester instances skip the just-persisted ops during replay.
@@ -488,21 +737,12 @@ impl Persister for IngesterData {
                     .repositories()
                     .await
                     .partitions()
-                    .update_persisted_sequence_number(
-                        parquet_file.partition_id,
-                        parquet_file.max_sequence_number,
-                    )
+                    .update_persisted_sequence_number(partition_id, max_sequence_number)
                     .await
             })
             .await
             .expect("retry forever");
 
-        // Record metrics
-        let attributes = Attributes::from([("shard_id", format!("{}", shard_id).into())]);
-        self.persisted_file_size_bytes
-            .recorder(attributes)
-            .record(file_size as u64);
-
         // and remove the persisted data from memory
         namespace
             .mark_persisted(
@@ -540,7 +780,16 @@ impl Persister for IngesterData {
                     .await
             })
             .await
-            .expect("retry forever")
+            .expect("retry forever");
+
+        // Only once the catalog is updated will a restarted ingester no longer
+        // replay the ops below sequence_number, and the WAL segments
+        // containing them can be removed.
+        if let Some(wal) = &self.wal {
+            if let Err(e) = wal.truncate(shard_id, sequence_number).await {
+                warn!(error=%e, %shard_id, "failed to truncate WAL");
+            }
+        }
     }
 }
 
@@ -1433,6 +1682,125 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn buffer_operation_retries_failed_wal_appends() {
+        let metrics = Arc::new(metric::Registry::new());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("whatevs").await.unwrap();
+        let query_pool = repos.query_pools().create_or_get("whatevs").await.unwrap();
+        let shard_index = ShardIndex::new(0);
+        let namespace = repos
+            .namespaces()
+            .create("foo", "inf", topic.id, query_pool.id)
+            .await
+            .unwrap();
+        let shard1 = repos
+            .shards()
+            .create_or_get(&topic, shard_index)
+            .await
+            .unwrap();
+
+        let schema = NamespaceSchema::new(namespace.id, topic.id, query_pool.id, 100);
+        let w1 = DmlWrite::new(
+            "foo",
+            lines_to_batches("mem foo=1 10", 0).unwrap(),
+            Some("1970-01-01".into()),
+            DmlMeta::sequenced(
+                Sequence::new(shard_index, SequenceNumber::new(1)),
+                Time::from_timestamp_millis(42),
+                None,
+                50,
+            ),
+        );
+        let _ = validate_or_insert_schema(w1.tables(), &schema, repos.deref_mut())
+            .await
+            .unwrap()
+            .unwrap();
+        std::mem::drop(repos);
+
+        let wal_dir = tempfile::tempdir().unwrap();
+        let wal = Arc::new(Wal::new(wal_dir.path()));
+        // Start the log of the shard at the first write.
+        assert!(wal
+            .replay(shard1.id, shard_index, SequenceNumber::new(1))
+            .await
+            .unwrap()
+            .is_none());
+
+        let data = IngesterData::new(
+            Arc::new(InMemory::new()),
+            Arc::clone(&catalog),
+            [(shard1.id, shard_index)],
+            Arc::new(Executor::new(1)),
+            Arc::new(CatalogPartitionResolver::new(catalog)),
+            BackoffConfig {
+                init_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(10),
+                base: 2.,
+                deadline: None,
+            },
+            Arc::clone(&metrics),
+        )
+        .with_wal(Arc::clone(&wal));
+
+        let manager = LifecycleManager::new(
+            LifecycleConfig::new(
+                1000000000,
+                0,
+                0,
+                Duration::from_secs(1),
+                Duration::from_secs(1),
+                1000000,
+            ),
+            metrics,
+            Arc::new(SystemProvider::new()),
+        );
+        let handle = manager.handle();
+
+        // Replace the shard's WAL directory with a file, causing appends to
+        // fail.
+        let shard_dir = wal_dir.path().join(shard1.id.to_string());
+        let moved_dir = wal_dir.path().join("moved");
+        std::fs::rename(&shard_dir, &moved_dir).unwrap();
+        std::fs::write(&shard_dir, b"").unwrap();
+
+        // The operation is neither skipped nor buffered while the append
+        // fails.
+        let op = data.buffer_operation(shard1.id, DmlOperation::Write(w1), &handle);
+        tokio::pin!(op);
+        tokio::time::timeout(Duration::from_millis(100), &mut op)
+            .await
+            .expect_err("buffer_operation should retry the failed append");
+        assert!(data
+            .shard(shard1.id)
+            .unwrap()
+            .namespace(&"foo".into())
+            .is_none());
+
+        // Once the failure clears, the retried append succeeds and the
+        // operation is buffered.
+        std::fs::remove_file(&shard_dir).unwrap();
+        std::fs::rename(&moved_dir, &shard_dir).unwrap();
+        tokio::time::timeout(Duration::from_secs(5), op)
+            .await
+            .expect("retried append should succeed")
+            .unwrap();
+        assert!(data
+            .shard(shard1.id)
+            .unwrap()
+            .namespace(&"foo".into())
+            .is_some());
+
+        // And the operation is in the WAL.
+        let ops = Wal::new(wal_dir.path())
+            .replay(shard1.id, shard_index, SequenceNumber::new(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(ops.len(), 1);
+    }
+
     /// Verifies that the progress in data is the same as expected_progress
     async fn assert_progress(
         data: &IngesterData,
@@ -3,7 +3,9 @@
 use std::sync::Arc;
 
 use arrow::record_batch::RecordBatch;
-use data_types::{NamespaceId, PartitionId, PartitionKey, SequenceNumber, ShardId, TableId};
+use data_types::{
+    NamespaceId, PartitionId, PartitionKey, SequenceNumber, ShardId, TableId, TimestampMinMax,
+};
 use mutable_batch::MutableBatch;
 use observability_deps::tracing::*;
 use schema::{selection::Selection, sort::SortKey};
@@ -29,6 +31,9 @@ pub(crate) struct UnpersistedPartitionData {
     pub(crate) non_persisted: Vec<Arc<SnapshotBatch>>,
     pub(crate) persisting: Option<QueryableBatch>,
     pub(crate) partition_status: PartitionStatus,
+    /// The range of timestamps in `non_persisted` and `persisting`, or
+    /// [`None`] if the partition contains no data.
+    pub(crate) timestamps: Option<TimestampMinMax>,
 }
 
 /// PersistingBatch contains all needed info and data for creating
@@ -227,6 +232,12 @@ impl PartitionData {
         self.data.get_persisting_data()
     }
 
+    /// Return the inclusive range of the timestamps in the non-persisting and
+    /// persisting data of this partition, or [`None`] if it has no data.
+    pub(super) fn timestamp_range(&self) -> Option<TimestampMinMax> {
+        self.data.timestamp_range()
+    }
+
     /// Write the given mb in the buffer
     pub(super) fn buffer_write(
         &mut self,
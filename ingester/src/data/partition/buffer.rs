@@ -2,7 +2,7 @@
 
 use std::sync::Arc;
 
-use data_types::{PartitionId, SequenceNumber, ShardId, TableId};
+use data_types::{PartitionId, SequenceNumber, ShardId, TableId, TimestampMinMax};
 use mutable_batch::MutableBatch;
 use schema::selection::Selection;
 use snafu::ResultExt;
@@ -11,8 +11,13 @@ use write_summary::ShardProgress;
 
 use crate::data::table::TableName;
 
+use self::traits::{merge_timestamp_ranges, mutable_batch_timestamp_range};
 use super::{PersistingBatch, QueryableBatch, SnapshotBatch};
 
+mod mutable_buffer;
+mod state_machine;
+pub(crate) mod traits;
+
 /// Data of an IOx partition split into batches
 /// ┌────────────────────────┐        ┌────────────────────────┐      ┌─────────────────────────┐
 /// │         Buffer         │        │       Snapshots        │      │       Persisting        │
@@ -49,6 +54,13 @@ pub(crate) struct DataBuffer {
     /// and then all `snapshots` will be moved to a `persisting`.
     /// Both `buffer` and 'snaphots` will be empty when this happens.
     pub(crate) persisting: Option<Arc<PersistingBatch>>,
+
+    /// The range of timestamps in `snapshots`, or [`None`] if there are no
+    /// snapshots.
+    snapshot_timestamps: Option<TimestampMinMax>,
+    /// The range of timestamps in `persisting`, or [`None`] if nothing is
+    /// being persisted.
+    persisting_timestamps: Option<TimestampMinMax>,
     // Extra Notes:
     //  . In MVP, we will only persist a set of snapshots at a time.
     //    In later version, multiple persisting operations may be happening concurrently but
@@ -71,6 +83,8 @@ impl DataBuffer {
     pub(crate) fn generate_snapshot(&mut self) -> Result<(), mutable_batch::Error> {
         let snapshot = self.copy_buffer_to_snapshot()?;
         if let Some(snapshot) = snapshot {
+            self.snapshot_timestamps =
+                merge_timestamp_ranges(self.snapshot_timestamps, self.buffer_timestamps());
             self.snapshots.push(snapshot);
             self.buffer = None;
         }
@@ -146,6 +160,8 @@ impl DataBuffer {
         }
 
         if let Some(queryable_batch) = self.snapshot_to_queryable_batch(table_name, partition_id) {
+            self.persisting_timestamps = self.snapshot_timestamps.take();
+
             let persisting_batch = Arc::new(PersistingBatch {
                 shard_id,
                 table_id,
@@ -173,6 +189,24 @@ impl DataBuffer {
         Some((*persisting.data).clone())
     }
 
+    /// Returns the inclusive range of the timestamps across the buffer,
+    /// snapshots and persisting data, or [`None`] if there is no data.
+    ///
+    /// This is always a cheap method call, allowing queries to skip
+    /// partitions containing no data for the queried time range without
+    /// reading it.
+    pub(super) fn timestamp_range(&self) -> Option<TimestampMinMax> {
+        merge_timestamp_ranges(
+            merge_timestamp_ranges(self.buffer_timestamps(), self.snapshot_timestamps),
+            self.persisting_timestamps,
+        )
+    }
+
+    /// Returns the range of timestamps in `buffer`, if any.
+    fn buffer_timestamps(&self) -> Option<TimestampMinMax> {
+        mutable_batch_timestamp_range(&self.buffer.as_ref()?.data)
+    }
+
     /// Return the progress in this DataBuffer
     pub(super) fn progress(&self) -> ShardProgress {
         let progress = ShardProgress::new();
@@ -207,6 +241,7 @@ impl DataBuffer {
 
     pub(crate) fn mark_persisted(&mut self) {
         self.persisting = None;
+        self.persisting_timestamps = None;
     }
 }
 
@@ -271,4 +306,49 @@ mod tests {
         assert_eq!(snapshot.max_sequence_number, seq_num1);
         assert_eq!(&*snapshot.data, &record_batch1);
     }
+
+    #[test]
+    fn timestamp_range_tracks_all_data() {
+        let mut data_buffer = DataBuffer::default();
+        let table_name = TableName::from("foo");
+        let range = |b: &DataBuffer| b.timestamp_range().map(|t| (t.min, t.max));
+
+        assert_eq!(range(&data_buffer), None);
+
+        let write = |b: &mut DataBuffer, lp: &str, n: i64| {
+            let (_, data) = lp_to_mutable_batch(lp);
+            b.buffer = Some(BufferBatch {
+                min_sequence_number: SequenceNumber::new(n),
+                max_sequence_number: SequenceNumber::new(n),
+                data,
+            });
+        };
+
+        // Buffered data is reflected in the range.
+        write(&mut data_buffer, "foo v=1 10\nfoo v=2 30", 1);
+        assert_eq!(range(&data_buffer), Some((10, 30)));
+
+        // The range is preserved when the buffer is snapshot.
+        data_buffer.generate_snapshot().unwrap();
+        assert_eq!(range(&data_buffer), Some((10, 30)));
+
+        // And when the snapshots are moved to a persisting batch.
+        data_buffer
+            .snapshot_to_persisting(
+                ShardId::new(1),
+                TableId::new(1),
+                PartitionId::new(1),
+                &table_name,
+            )
+            .expect("should have persisting data");
+        assert_eq!(range(&data_buffer), Some((10, 30)));
+
+        // Writes while persisting extend the range.
+        write(&mut data_buffer, "foo v=3 50", 2);
+        assert_eq!(range(&data_buffer), Some((10, 50)));
+
+        // Once persisted, only the buffered data remains.
+        data_buffer.mark_persisted();
+        assert_eq!(range(&data_buffer), Some((50, 50)));
+    }
 }
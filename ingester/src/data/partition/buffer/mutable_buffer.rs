@@ -9,7 +9,7 @@ use schema::selection::Selection;
 ///
 /// A [`Buffer`] can contain no writes.
 ///
-/// [`BufferState`]: super::super::BufferState
+/// [`BufferState`]: super::state_machine::BufferState
 #[derive(Debug, Default)]
 pub(super) struct Buffer {
     buffer: Option<MutableBatch>,
@@ -2,7 +2,7 @@
 use std::sync::Arc;
 
 use arrow::record_batch::RecordBatch;
-use data_types::SequenceNumber;
+use data_types::{SequenceNumber, TimestampMinMax};
 use mutable_batch::MutableBatch;
 
 mod buffering;
@@ -52,11 +52,11 @@ impl<A, B> Transition<A, B> {
 ///
 /// ```text
 ///                  ┌──────────────┐
-///                  │  Buffering   │
-///                  └───────┬──────┘
-///                          │
-///                          ▼
-///                  ┌ ─ ─ ─ ─ ─ ─ ─       ┌ ─ ─ ─ ─ ─ ─ ─
+///                  │  Buffering   │◀──────────────┐
+///                  └───────┬──────┘               │
+///                          │                      │
+///                          ▼                      │
+///                  ┌ ─ ─ ─ ─ ─ ─ ─       ┌ ─ ─ ─ ─┴─ ─ ─
 ///                      Snapshot   ├─────▶   Persisting  │
 ///                  └ ─ ─ ─ ─ ─ ─ ─       └ ─ ─ ─ ─ ─ ─ ─
 /// ```
@@ -67,8 +67,24 @@ impl<A, B> Transition<A, B> {
 /// Boxes with solid lines indicate a mutable state to which further writes can
 /// be applied.
 ///
+/// Writes are never blocked by a persist: a [`Persisting`] state holds a fresh
+/// [`Buffering`] state as the next generation of the buffer, to which writes
+/// applied during the persist are routed. Queries observe both generations, and
+/// once the persist completes the [`Persisting`] state transitions to the next
+/// generation, discarding the persisted data.
+///
 /// A [`BufferState`] tracks the bounding [`SequenceNumber`] values it has
 /// observed, and enforces monotonic writes (w.r.t their [`SequenceNumber`]).
+///
+/// NOTE: this FSM is not yet used by [`PartitionData`], which still buffers
+/// writes in a [`DataBuffer`]. The next generation buffer, snapshot coalescing
+/// and [`Queryable::timestamp_range()`] implemented here take effect only once
+/// partitions are migrated to it; until then, the querier handler prunes
+/// partitions using [`DataBuffer::timestamp_range()`].
+///
+/// [`PartitionData`]: crate::data::partition::PartitionData
+/// [`DataBuffer`]: super::DataBuffer
+/// [`DataBuffer::timestamp_range()`]: super::DataBuffer::timestamp_range
 #[derive(Debug)]
 pub(crate) struct BufferState<T> {
     state: T,
@@ -126,6 +142,10 @@ where
     fn get_query_data(&self) -> Vec<Arc<RecordBatch>> {
         self.state.get_query_data()
     }
+
+    fn timestamp_range(&self) -> Option<TimestampMinMax> {
+        self.state.timestamp_range()
+    }
 }
 
 #[cfg(test)]
@@ -134,6 +154,7 @@ mod tests {
     use std::ops::Deref;
 
     use arrow_util::assert_batches_eq;
+    use data_types::TimestampRange;
     use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
     use schema::selection::Selection;
 
@@ -232,7 +253,7 @@ mod tests {
         );
 
         // Extract the final buffered result
-        let final_data = buffer.into_data();
+        let final_data = buffer.persisting_data();
 
         // And once again verify no data was changed, copied or re-ordered.
         assert_eq!(w2_data, final_data);
@@ -271,4 +292,56 @@ mod tests {
 
         assert_eq!(&**snapshot, &want);
     }
+
+    fn assert_timestamp_range<T: Queryable>(buffer: &T, want: Option<(i64, i64)>) {
+        let got = buffer.timestamp_range().map(|t| (t.min, t.max));
+        assert_eq!(got, want);
+    }
+
+    #[test]
+    fn test_timestamp_range() {
+        let mut buffer = BufferState::new();
+        assert_timestamp_range(&buffer, None);
+        assert!(!buffer.overlaps(TimestampRange::new(i64::MIN, i64::MAX)));
+
+        buffer
+            .write(
+                lp_to_mutable_batch("bananas v=1i 20\nbananas v=2i 10").1,
+                SequenceNumber::new(0),
+            )
+            .unwrap();
+        buffer
+            .write(
+                lp_to_mutable_batch("bananas v=3i 30").1,
+                SequenceNumber::new(1),
+            )
+            .unwrap();
+        assert_timestamp_range(&buffer, Some((10, 30)));
+
+        let buffer: BufferState<Snapshot> = match buffer.snapshot() {
+            Transition::Ok(v) => v,
+            Transition::Unchanged(_) => panic!("did not transition to snapshot state"),
+        };
+        assert_timestamp_range(&buffer, Some((10, 30)));
+        assert!(buffer.overlaps(TimestampRange::new(30, 40)));
+        assert!(!buffer.overlaps(TimestampRange::new(31, 40)));
+        assert!(!buffer.overlaps(TimestampRange::new(0, 10)));
+
+        // Writes to the next generation extend the range of the persisting
+        // state.
+        let mut buffer = buffer.into_persisting();
+        assert_timestamp_range(&buffer, Some((10, 30)));
+        buffer
+            .write(
+                lp_to_mutable_batch("bananas v=4i 50").1,
+                SequenceNumber::new(2),
+            )
+            .unwrap();
+        assert_timestamp_range(&buffer, Some((10, 50)));
+        assert!(buffer.overlaps(TimestampRange::new(40, 60)));
+
+        let buffer = buffer.into_next_generation();
+        assert_timestamp_range(&buffer, Some((50, 50)));
+        assert!(!buffer.overlaps(TimestampRange::new(10, 31)));
+    }
 }
@@ -3,12 +3,13 @@
 use std::sync::Arc;
 
 use arrow::record_batch::RecordBatch;
+use data_types::TimestampMinMax;
 use mutable_batch::MutableBatch;
 use schema::selection::Selection;
 
 use crate::data::partition::buffer::{
     mutable_buffer::Buffer,
-    traits::{Queryable, Writeable},
+    traits::{mutable_batch_timestamp_range, Queryable, Writeable},
 };
 
 use super::{snapshot::Snapshot, BufferState, Transition};
@@ -44,6 +45,12 @@ impl Queryable for Buffering {
             None => vec![],
         }
     }
+
+    /// Read the timestamp range from the statistics the [`MutableBatch`]
+    /// maintains as writes are applied, without generating a snapshot.
+    fn timestamp_range(&self) -> Option<TimestampMinMax> {
+        mutable_batch_timestamp_range(self.buffer.buffer()?)
+    }
 }
 
 impl Writeable for Buffering {
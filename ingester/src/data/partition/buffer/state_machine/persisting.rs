@@ -3,35 +3,207 @@
 use std::sync::Arc;
 
 use arrow::record_batch::RecordBatch;
+use data_types::{SequenceNumber, TimestampMinMax};
+use mutable_batch::MutableBatch;
 
-use crate::data::partition::buffer::traits::Queryable;
+use crate::data::{
+    partition::buffer::traits::{merge_timestamp_ranges, Queryable},
+    SequenceNumberRange,
+};
 
-use super::BufferState;
+use super::{buffering::Buffering, BufferState};
 
-/// An immutable set of [`RecordBatch`] in the process of being persisted.
+/// An immutable set of [`RecordBatch`] in the process of being persisted,
+/// alongside a fresh [`Buffering`] state accepting writes that arrive while
+/// the persist is in progress.
+///
+/// The persisting data and the new writes are tracked as two separate
+/// generations, each with their own [`SequenceNumberRange`]. Queries observe
+/// both generations.
 #[derive(Debug)]
 pub(crate) struct Persisting {
     /// Snapshots generated from previous buffer contents to be persisted.
     ///
     /// INVARIANT: this array is always non-empty.
     snapshots: Vec<Arc<RecordBatch>>,
+
+    /// The range of timestamps in `snapshots`.
+    timestamps: TimestampMinMax,
+
+    /// The next generation of the buffer, containing writes applied after the
+    /// transition to this state.
+    next: BufferState<Buffering>,
 }
 
 impl Persisting {
-    pub(super) fn new(snapshots: Vec<Arc<RecordBatch>>) -> Self {
-        Self { snapshots }
+    pub(super) fn new(snapshots: Vec<Arc<RecordBatch>>, timestamps: TimestampMinMax) -> Self {
+        Self {
+            snapshots,
+            timestamps,
+            next: BufferState::new(),
+        }
     }
 }
 
+/// Both the persisting generation and the next generation are queryable, with
+/// the persisting data ordered before the (newer) buffered writes.
 impl Queryable for Persisting {
     fn get_query_data(&self) -> Vec<Arc<RecordBatch>> {
-        self.snapshots.clone()
+        let mut data = self.snapshots.clone();
+        data.extend(self.next.get_query_data());
+        data
+    }
+
+    fn timestamp_range(&self) -> Option<TimestampMinMax> {
+        merge_timestamp_ranges(Some(self.timestamps), self.next.timestamp_range())
     }
 }
 
 impl BufferState<Persisting> {
-    /// Consume `self`, returning the data it holds as a set of [`RecordBatch`].
-    pub(super) fn into_data(self) -> Vec<Arc<RecordBatch>> {
-        self.state.snapshots
+    /// Apply `batch` to the next generation of this buffer, without affecting
+    /// the data being persisted.
+    ///
+    /// The provided [`SequenceNumber`] MUST be for the given [`MutableBatch`].
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called non-monotonic writes/sequence
+    /// numbers, including a [`SequenceNumber`] that is not greater than those
+    /// of the persisting data.
+    pub(crate) fn write(
+        &mut self,
+        batch: MutableBatch,
+        n: SequenceNumber,
+    ) -> Result<(), mutable_batch::Error> {
+        if let Some(max) = self.sequence_range.inclusive_max() {
+            assert!(n > max, "monotonicity violation");
+        }
+        self.state.next.write(batch, n)
+    }
+
+    /// Return the data being persisted, excluding any writes applied to the
+    /// next generation.
+    pub(crate) fn persisting_data(&self) -> Vec<Arc<RecordBatch>> {
+        self.state.snapshots.clone()
+    }
+
+    /// Return the next generation of this buffer, containing the writes
+    /// applied since the persist started.
+    pub(crate) fn next_generation(&self) -> &BufferState<Buffering> {
+        &self.state.next
+    }
+
+    /// Returns the [`SequenceNumberRange`] of the data across both the
+    /// persisting and next generations.
+    pub(crate) fn buffered_sequence_range(&self) -> SequenceNumberRange {
+        self.sequence_range
+            .merge(self.state.next.sequence_number_range())
+    }
+
+    /// Mark the persisting data as persisted, discarding it and returning the
+    /// next generation [`Buffering`] state containing all writes applied
+    /// since the persist started.
+    pub(crate) fn into_next_generation(self) -> BufferState<Buffering> {
+        self.state.next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Deref;
+
+    use arrow_util::assert_batches_eq;
+    use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
+
+    use super::{super::Transition, *};
+
+    fn persisting_buffer() -> BufferState<Persisting> {
+        let mut buffer = BufferState::new();
+        buffer
+            .write(
+                lp_to_mutable_batch(r#"bananas,tag=platanos v=1i 1"#).1,
+                SequenceNumber::new(1),
+            )
+            .unwrap();
+
+        match buffer.snapshot() {
+            Transition::Ok(v) => v.into_persisting(),
+            Transition::Unchanged(_) => panic!("did not transition to snapshot state"),
+        }
+    }
+
+    #[test]
+    fn test_write_while_persisting() {
+        let mut buffer = persisting_buffer();
+        let persisting = buffer.persisting_data();
+
+        // Writes are accepted while persisting, and applied to the next
+        // generation.
+        buffer
+            .write(
+                lp_to_mutable_batch(r#"bananas,tag=platanos v=2i 2"#).1,
+                SequenceNumber::new(2),
+            )
+            .expect("write while persisting should succeed");
+
+        // The persisting data is unchanged.
+        let same_arcs = persisting
+            .iter()
+            .zip(buffer.persisting_data().iter())
+            .all(|(a, b)| Arc::ptr_eq(a, b));
+        assert!(same_arcs);
+        assert_eq!(
+            buffer.sequence_number_range().inclusive_max(),
+            Some(SequenceNumber::new(1))
+        );
+
+        // Each generation tracks its own sequence numbers.
+        let next_range = buffer.next_generation().sequence_number_range();
+        assert_eq!(next_range.inclusive_min(), Some(SequenceNumber::new(2)));
+        assert_eq!(next_range.inclusive_max(), Some(SequenceNumber::new(2)));
+
+        let range = buffer.buffered_sequence_range();
+        assert_eq!(range.inclusive_min(), Some(SequenceNumber::new(1)));
+        assert_eq!(range.inclusive_max(), Some(SequenceNumber::new(2)));
+
+        // Queries observe both generations.
+        let data = buffer.get_query_data();
+        assert_eq!(data.len(), 2);
+        let expected = vec![
+            "+----------+--------------------------------+---+",
+            "| tag      | time                           | v |",
+            "+----------+--------------------------------+---+",
+            "| platanos | 1970-01-01T00:00:00.000000001Z | 1 |",
+            "| platanos | 1970-01-01T00:00:00.000000002Z | 2 |",
+            "+----------+--------------------------------+---+",
+        ];
+        assert_batches_eq!(
+            &expected,
+            &data.iter().map(|b| b.deref().clone()).collect::<Vec<_>>()
+        );
+
+        // Once persisted, only the next generation remains.
+        let buffer = buffer.into_next_generation();
+        let expected = vec![
+            "+----------+--------------------------------+---+",
+            "| tag      | time                           | v |",
+            "+----------+--------------------------------+---+",
+            "| platanos | 1970-01-01T00:00:00.000000002Z | 2 |",
+            "+----------+--------------------------------+---+",
+        ];
+        assert_batches_eq!(&expected, &[buffer.get_query_data()[0].deref().clone()]);
+    }
+
+    #[test]
+    #[should_panic = "monotonicity violation"]
+    fn test_write_while_persisting_monotonicity() {
+        let mut buffer = persisting_buffer();
+
+        buffer
+            .write(
+                lp_to_mutable_batch(r#"bananas,tag=platanos v=2i 2"#).1,
+                SequenceNumber::new(1),
+            )
+            .unwrap();
     }
 }
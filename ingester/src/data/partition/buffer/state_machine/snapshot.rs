@@ -2,12 +2,18 @@
 
 use std::sync::Arc;
 
-use arrow::record_batch::RecordBatch;
+use arrow::{compute::concat_batches, record_batch::RecordBatch};
+use data_types::TimestampMinMax;
+use iox_query::util::compute_timenanosecond_min_max;
 
 use crate::data::partition::buffer::{state_machine::persisting::Persisting, traits::Queryable};
 
 use super::BufferState;
 
+/// The number of rows [`Snapshot`] aims for when coalescing small
+/// [`RecordBatch`] instances.
+const SNAPSHOT_TARGET_BATCH_ROWS: usize = 8 * 1024;
+
 /// An immutable, queryable FSM state containing at least one buffer snapshot.
 #[derive(Debug)]
 pub(crate) struct Snapshot {
@@ -15,12 +21,75 @@ pub(crate) struct Snapshot {
     ///
     /// INVARIANT: this array is always non-empty.
     snapshots: Vec<Arc<RecordBatch>>,
+
+    /// The range of timestamps in `snapshots`.
+    timestamps: TimestampMinMax,
 }
 
 impl Snapshot {
     pub(super) fn new(snapshots: Vec<Arc<RecordBatch>>) -> Self {
         assert!(!snapshots.is_empty());
-        Self { snapshots }
+        let snapshots = coalesce(snapshots, SNAPSHOT_TARGET_BATCH_ROWS);
+        let timestamps = compute_timenanosecond_min_max(
+            &snapshots
+                .iter()
+                .map(|b| b.as_ref().clone())
+                .collect::<Vec<_>>(),
+        )
+        .expect("snapshot batches always contain a non-null time column");
+
+        Self {
+            snapshots,
+            timestamps,
+        }
+    }
+}
+
+/// Concatenate runs of consecutive [`RecordBatch`] sharing the same schema
+/// into batches of up to `target_rows` rows, preserving their order.
+///
+/// Batches that are not combined with any other batch are returned without
+/// being copied.
+///
+/// The live `DataBuffer` does not yet coalesce its snapshots; see
+/// [`BufferState`].
+fn coalesce(snapshots: Vec<Arc<RecordBatch>>, target_rows: usize) -> Vec<Arc<RecordBatch>> {
+    let mut coalesced = Vec::with_capacity(snapshots.len());
+    let mut pending: Vec<Arc<RecordBatch>> = vec![];
+    let mut pending_rows = 0;
+
+    for batch in snapshots {
+        let compatible = pending
+            .first()
+            .map_or(true, |p| p.schema() == batch.schema());
+        if !compatible || pending_rows + batch.num_rows() > target_rows {
+            flush(&mut pending, &mut coalesced);
+            pending_rows = 0;
+        }
+
+        pending_rows += batch.num_rows();
+        pending.push(batch);
+    }
+    flush(&mut pending, &mut coalesced);
+
+    coalesced
+}
+
+/// Move the batches in `pending` into `coalesced` as a single batch.
+fn flush(pending: &mut Vec<Arc<RecordBatch>>, coalesced: &mut Vec<Arc<RecordBatch>>) {
+    match pending.len() {
+        0 => {}
+        1 => coalesced.extend(pending.pop()),
+        _ => {
+            let schema = pending[0].schema();
+            let batches = pending
+                .drain(..)
+                .map(|b| b.as_ref().clone())
+                .collect::<Vec<_>>();
+            let batch = concat_batches(&schema, &batches)
+                .expect("concatenating batches with identical schemas should succeed");
+            coalesced.push(Arc::new(batch));
+        }
     }
 }
 
@@ -28,14 +97,86 @@ impl Queryable for Snapshot {
     fn get_query_data(&self) -> Vec<Arc<RecordBatch>> {
         self.snapshots.clone()
     }
+
+    fn timestamp_range(&self) -> Option<TimestampMinMax> {
+        Some(self.timestamps)
+    }
 }
 
 impl BufferState<Snapshot> {
     pub(crate) fn into_persisting(self) -> BufferState<Persisting> {
         assert!(!self.state.snapshots.is_empty());
         BufferState {
-            state: Persisting::new(self.state.snapshots),
+            state: Persisting::new(self.state.snapshots, self.state.timestamps),
             sequence_range: self.sequence_range,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use arrow_util::assert_batches_eq;
+    use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
+    use schema::selection::Selection;
+
+    use super::*;
+
+    fn batch(lp: &str) -> Arc<RecordBatch> {
+        Arc::new(lp_to_mutable_batch(lp).1.to_arrow(Selection::All).unwrap())
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let snapshots = vec![
+            batch("bananas v=1i 1"),
+            batch("bananas v=2i 2"),
+            batch("bananas v=3i 3"),
+            // A batch with a different schema is never concatenated
+            batch("bananas,tag=platanos v=4i 4"),
+            batch("bananas,tag=platanos v=5i 5"),
+            // A batch at the target size is returned as-is
+            batch("bananas v=6i 6\nbananas v=7i 7"),
+        ];
+        let large = Arc::clone(&snapshots[5]);
+
+        let got = coalesce(snapshots, 2);
+        assert_eq!(
+            got.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            [2, 1, 2, 2]
+        );
+        assert!(Arc::ptr_eq(&got[3], &large));
+
+        let expected = vec![
+            "+--------------------------------+---+",
+            "| time                           | v |",
+            "+--------------------------------+---+",
+            "| 1970-01-01T00:00:00.000000001Z | 1 |",
+            "| 1970-01-01T00:00:00.000000002Z | 2 |",
+            "+--------------------------------+---+",
+        ];
+        assert_batches_eq!(&expected, &[got[0].as_ref().clone()]);
+
+        let expected = vec![
+            "+----------+--------------------------------+---+",
+            "| tag      | time                           | v |",
+            "+----------+--------------------------------+---+",
+            "| platanos | 1970-01-01T00:00:00.000000004Z | 4 |",
+            "| platanos | 1970-01-01T00:00:00.000000005Z | 5 |",
+            "+----------+--------------------------------+---+",
+        ];
+        assert_batches_eq!(&expected, &[got[2].as_ref().clone()]);
+    }
+
+    #[test]
+    fn test_snapshot_coalesces_small_batches() {
+        let snapshots = (1..=100)
+            .map(|t| batch(&format!("bananas v={}i {}", t, t)))
+            .collect();
+
+        let snapshot = Snapshot::new(snapshots);
+
+        let data = snapshot.get_query_data();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].num_rows(), 100);
+    }
+}
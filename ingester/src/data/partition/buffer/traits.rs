@@ -3,7 +3,9 @@
 use std::{fmt::Debug, sync::Arc};
 
 use arrow::record_batch::RecordBatch;
+use data_types::{Statistics, TimestampMinMax, TimestampRange};
 use mutable_batch::MutableBatch;
+use schema::TIME_COLUMN_NAME;
 
 /// A state that can accept writes.
 pub(crate) trait Writeable: Debug {
@@ -14,4 +16,39 @@ pub(crate) trait Writeable: Debug {
 /// [`RecordBatch`] instances.
 pub(crate) trait Queryable: Debug {
     fn get_query_data(&self) -> Vec<Arc<RecordBatch>>;
+
+    /// Returns the inclusive range of the timestamps in the data returned by
+    /// [`Self::get_query_data()`], or [`None`] if it contains no rows.
+    ///
+    /// This is always a cheap method call.
+    fn timestamp_range(&self) -> Option<TimestampMinMax>;
+
+    /// Returns true if any of the data returned by [`Self::get_query_data()`]
+    /// may fall within `range`, allowing queries to skip states that contain
+    /// no data for the queried time range without reading it.
+    fn overlaps(&self, range: TimestampRange) -> bool {
+        self.timestamp_range().map_or(false, |t| t.overlaps(range))
+    }
+}
+
+/// Merge two optional [`TimestampMinMax`], returning the range covering both.
+pub(crate) fn merge_timestamp_ranges(
+    a: Option<TimestampMinMax>,
+    b: Option<TimestampMinMax>,
+) -> Option<TimestampMinMax> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(TimestampMinMax::new(a.min.min(b.min), a.max.max(b.max))),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Read the timestamp range of `batch` from the statistics the
+/// [`MutableBatch`] maintains as writes are applied, without converting it to
+/// a [`RecordBatch`].
+pub(crate) fn mutable_batch_timestamp_range(batch: &MutableBatch) -> Option<TimestampMinMax> {
+    let time = batch.column(TIME_COLUMN_NAME).ok()?;
+    match time.stats() {
+        Statistics::I64(v) => Some(TimestampMinMax::new(v.min?, v.max?)),
+        _ => None,
+    }
 }
@@ -28,7 +28,6 @@ impl DoubleRef {
         assert!(self.by_id.insert(id, key).is_none());
     }
 
-    #[cfg(test)]
     fn by_key(&self, key: &PartitionKey) -> Option<&PartitionData> {
         self.by_key.get(key)
     }
@@ -201,7 +200,6 @@ impl TableData {
     }
 
     /// Return the [`PartitionData`] for the specified partition key.
-    #[cfg(test)]
     pub(crate) fn get_partition_by_key(
         &self,
         partition_key: &PartitionKey,
@@ -230,6 +228,7 @@ impl TableData {
                 partition_status: PartitionStatus {
                     parquet_max_sequence_number: p.max_persisted_sequence_number(),
                 },
+                timestamps: p.timestamp_range(),
             })
             .collect()
     }
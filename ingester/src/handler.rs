@@ -4,7 +4,7 @@ use std::{collections::BTreeMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use backoff::BackoffConfig;
-use data_types::{Shard, ShardIndex, TopicMetadata};
+use data_types::{PartitionKey, Shard, ShardIndex, TopicMetadata};
 use futures::{
     future::{BoxFuture, Shared},
     stream::FuturesUnordered,
@@ -33,13 +33,17 @@ use crate::{
         shard::ShardData,
         IngesterData,
     },
-    lifecycle::{run_lifecycle_manager, LifecycleConfig, LifecycleManager},
+    lifecycle::{
+        run_lifecycle_manager, HotPartition, LifecycleConfig, LifecycleHandleImpl, LifecycleManager,
+    },
+    persist::{PersistConfig, PersistScheduler},
     poison::PoisonCabinet,
     querier_handler::{prepare_data_to_querier, IngesterQueryResponse},
     stream_handler::{
         handler::SequencedStreamHandler, sink_adaptor::IngestSinkAdaptor,
         sink_instrumentation::SinkInstrumentation, PeriodicWatermarkFetcher,
     },
+    wal::Wal,
 };
 
 /// The maximum duration of time between creating a [`PartitionData`] and its
@@ -60,6 +64,8 @@ pub enum Error {
     PartitionCache {
         source: iox_catalog::interface::Error,
     },
+    #[snafu(display("error replaying write-ahead log: {}", source))]
+    WalReplay { source: crate::data::Error },
 }
 
 /// A specialized `Error` for Catalog errors
@@ -81,6 +87,22 @@ pub trait IngestHandler: Send + Sync {
         shard_indexes: Vec<ShardIndex>,
     ) -> BTreeMap<ShardIndex, ShardProgress>;
 
+    /// Persist the buffered data of the partition identified by `namespace`,
+    /// `table` and `partition_key`, regardless of its size and age, waiting
+    /// until it has been persisted.
+    ///
+    /// Returns false if the partition had no buffered data to persist.
+    async fn persist_partition(
+        &self,
+        namespace: &str,
+        table: &str,
+        partition_key: &PartitionKey,
+    ) -> Result<bool, crate::data::Error>;
+
+    /// Return the buffered partitions currently receiving writes faster than
+    /// the configured hot partition threshold, hottest first.
+    fn hot_partitions(&self) -> Vec<HotPartition>;
+
     /// Wait until the handler finished  to shutdown.
     ///
     /// Use [`shutdown`](Self::shutdown) to trigger a shutdown.
@@ -115,6 +137,9 @@ pub struct IngestHandlerImpl<T = SystemProvider> {
     /// The cache and buffered data for the ingester
     data: Arc<IngesterData>,
 
+    /// A handle to the lifecycle manager persisting the buffered data
+    lifecycle_handle: LifecycleHandleImpl,
+
     time_provider: T,
 
     /// Query execution duration distribution for successes.
@@ -142,6 +167,8 @@ impl IngestHandlerImpl {
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
         lifecycle_config: LifecycleConfig,
+        persist_config: PersistConfig,
+        wal: Option<Wal>,
         topic: TopicMetadata,
         shard_states: BTreeMap<ShardIndex, Shard>,
         catalog: Arc<dyn Catalog>,
@@ -192,7 +219,7 @@ impl IngestHandlerImpl {
             );
         }
 
-        let data = Arc::new(IngesterData::new(
+        let data = IngesterData::new(
             object_store,
             catalog,
             shard_states.clone().into_iter().map(|(idx, s)| (s.id, idx)),
@@ -200,7 +227,12 @@ impl IngestHandlerImpl {
             partition_provider,
             BackoffConfig::default(),
             Arc::clone(&metric_registry),
-        ));
+        )
+        .with_persist_scheduler(PersistScheduler::new(persist_config, &metric_registry));
+        let data = match wal {
+            Some(wal) => Arc::new(data.with_wal(Arc::new(wal))),
+            None => Arc::new(data),
+        };
 
         let ingester_data = Arc::clone(&data);
         let topic_name = topic.name.clone();
@@ -221,8 +253,8 @@ impl IngestHandlerImpl {
             Arc::new(PoisonCabinet::new()),
         ));
         info!(
-            "ingester handler and lifecycle started with config {:?}",
-            lifecycle_config
+            ?persist_config,
+            "ingester handler and lifecycle started with config {:?}", lifecycle_config
         );
 
         let mut join_handles = Vec::with_capacity(shard_states.len() + 1);
@@ -231,8 +263,20 @@ impl IngestHandlerImpl {
         for (shard_index, shard) in shard_states {
             let metric_registry = Arc::clone(&metric_registry);
 
+            // Recover the unpersisted ops recorded in the WAL (if any), so
+            // that they need not be read from the write buffer again.
+            let next_sequence_number = ingester_data
+                .replay_wal(
+                    shard.id,
+                    shard.min_unpersisted_sequence_number,
+                    &lifecycle_handle,
+                )
+                .await
+                .context(WalReplaySnafu)?;
+
             // Acquire a write buffer stream and seek it to the last
-            // definitely-already-persisted op
+            // definitely-already-persisted op, or past the last op replayed
+            // from the WAL
             let mut op_stream = write_buffer
                 .stream_handler(shard_index)
                 .await
@@ -240,10 +284,11 @@ impl IngestHandlerImpl {
             info!(
                 shard_index = shard_index.get(),
                 min_unpersisted_sequence_number = shard.min_unpersisted_sequence_number.get(),
+                next_sequence_number = next_sequence_number.get(),
                 "Seek stream",
             );
             op_stream
-                .seek(shard.min_unpersisted_sequence_number)
+                .seek(next_sequence_number)
                 .await
                 .context(WriteBufferSnafu)?;
 
@@ -278,7 +323,7 @@ impl IngestHandlerImpl {
                 async move {
                     let handler = SequencedStreamHandler::new(
                         op_stream,
-                        shard.min_unpersisted_sequence_number,
+                        next_sequence_number,
                         sink,
                         lifecycle_handle,
                         topic_name,
@@ -315,6 +360,7 @@ impl IngestHandlerImpl {
 
         Ok(Self {
             data,
+            lifecycle_handle,
             topic,
             join_handles,
             shutdown,
@@ -419,6 +465,32 @@ impl IngestHandler for IngestHandlerImpl {
     ) -> BTreeMap<ShardIndex, ShardProgress> {
         self.data.progresses(shard_indexes).await
     }
+
+    async fn persist_partition(
+        &self,
+        namespace: &str,
+        table: &str,
+        partition_key: &PartitionKey,
+    ) -> Result<bool, crate::data::Error> {
+        let partition_id = self
+            .data
+            .partition_id(namespace, table, partition_key)
+            .await?;
+
+        info!(
+            %namespace,
+            %table,
+            %partition_key,
+            %partition_id,
+            "persisting partition on request"
+        );
+
+        Ok(self.lifecycle_handle.persist_partition(partition_id).await)
+    }
+
+    fn hot_partitions(&self) -> Vec<HotPartition> {
+        self.lifecycle_handle.hot_partitions()
+    }
 }
 
 impl<T> Drop for IngestHandlerImpl<T> {
@@ -576,6 +648,8 @@ mod tests {
         );
         let ingester = IngestHandlerImpl::new(
             lifecycle_config,
+            PersistConfig::default(),
+            None,
             topic.clone(),
             shard_states,
             Arc::clone(&catalog),
@@ -22,11 +22,13 @@ pub mod data;
 pub mod handler;
 mod job;
 pub mod lifecycle;
+pub mod persist;
 mod poison;
 pub mod querier_handler;
 pub(crate) mod query;
 pub mod server;
 pub(crate) mod stream_handler;
+pub mod wal;
 
 #[cfg(test)]
 pub(crate) mod test_util;
@@ -7,13 +7,19 @@
 
 pub mod mock_handle;
 
-use std::{collections::BTreeMap, sync::Arc, time::Duration};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    sync::Arc,
+    time::Duration,
+};
 
 use data_types::{NamespaceId, PartitionId, SequenceNumber, ShardId, TableId};
 use iox_time::{Time, TimeProvider};
-use metric::{Metric, U64Counter};
-use observability_deps::tracing::{error, info, trace, warn};
+use metric::{Metric, U64Counter, U64Gauge};
+use observability_deps::tracing::{debug, error, info, trace, warn};
 use parking_lot::Mutex;
+use tokio::sync::{oneshot, Notify};
 use tokio_util::sync::CancellationToken;
 use tracker::TrackedFutureExt;
 
@@ -58,6 +64,10 @@ pub(crate) struct LifecycleHandleImpl {
 
     /// The state shared with the [`LifecycleManager`].
     state: Arc<Mutex<LifecycleState>>,
+
+    /// Wakes the [`LifecycleManager`] when the buffered data crosses a memory
+    /// watermark, or a partition is requested to be persisted.
+    wake: Arc<Notify>,
 }
 
 impl LifecycleHandle for LifecycleHandleImpl {
@@ -109,10 +119,26 @@ impl LifecycleHandle for LifecycleHandleImpl {
             "logged write"
         );
 
-        s.total_bytes += bytes_written;
+        let before = s.total_bytes;
+        s.account(shard_id, namespace_id, bytes_written);
+
+        // Wake the lifecycle manager to persist the largest partitions as
+        // soon as the soft (persist) or hard (pause) watermark is crossed,
+        // rather than waiting for its next periodic check.
+        let soft = self.config.persist_memory_threshold;
+        let hard = self.config.pause_ingest_size;
+        if (before <= soft && s.total_bytes > soft) || (before < hard && s.total_bytes >= hard) {
+            debug!(
+                total_bytes = s.total_bytes,
+                persist_memory_threshold = soft,
+                pause_ingest_size = hard,
+                "buffered data crossed memory watermark"
+            );
+            self.wake.notify_one();
+        }
 
         // Pause if the server has exceeded the configured memory limit.
-        s.total_bytes >= self.config.pause_ingest_size
+        s.total_bytes >= hard
     }
 
     fn can_resume_ingest(&self) -> bool {
@@ -121,6 +147,55 @@ impl LifecycleHandle for LifecycleHandleImpl {
     }
 }
 
+impl LifecycleHandleImpl {
+    /// Request the [`LifecycleManager`] persists the buffered data of
+    /// `partition_id` on its next pass, regardless of the size and age of the
+    /// partition, waiting until it has been persisted.
+    ///
+    /// Returns false without waiting if the partition has no buffered data
+    /// that is not already being persisted.
+    pub(crate) async fn persist_partition(&self, partition_id: PartitionId) -> bool {
+        let rx = {
+            let mut s = self.state.lock();
+            if !s.partition_stats.contains_key(&partition_id) {
+                return false;
+            }
+
+            let (tx, rx) = oneshot::channel();
+            s.persist_requests.entry(partition_id).or_default().push(tx);
+            rx
+        };
+
+        self.wake.notify_one();
+
+        // The sender is dropped without notifying only if the lifecycle
+        // manager is stopped.
+        rx.await.is_ok()
+    }
+
+    /// Returns the buffered partitions currently receiving writes faster
+    /// than the configured hot partition threshold, hottest first.
+    ///
+    /// Returns an empty list if no threshold is configured.
+    pub(crate) fn hot_partitions(&self) -> Vec<HotPartition> {
+        let threshold = match self.config.hot_partition_bytes_per_second {
+            Some(v) => v,
+            None => return vec![],
+        };
+        let now = self.time_provider.now();
+
+        let mut hot: Vec<_> = self
+            .state
+            .lock()
+            .partition_stats
+            .values()
+            .filter_map(|s| s.hot(now, threshold))
+            .collect();
+        hot.sort_by(|a, b| b.bytes_per_second.cmp(&a.bytes_per_second));
+        hot
+    }
+}
+
 /// The lifecycle manager keeps track of the size and age of partitions across
 /// all shards. It triggers persistence based on keeping total memory usage
 /// around a set amount while ensuring that partitions don't get too old or
@@ -136,7 +211,9 @@ impl LifecycleHandle for LifecycleHandleImpl {
 /// [`LifecycleManager`] through their respective [`LifecycleHandle`] instances.
 ///
 /// A [`LifecycleManager`] MUST be driven by an external actor periodically
-/// calling [`LifecycleManager::maybe_persist()`].
+/// calling [`LifecycleManager::maybe_persist()`], and calling it again as soon
+/// as a [`LifecycleHandle`] signals the buffered data crossed the persist memory
+/// threshold (soft watermark) or pause ingest size (hard watermark).
 #[derive(Debug)]
 pub(crate) struct LifecycleManager {
     config: Arc<LifecycleConfig>,
@@ -149,6 +226,18 @@ pub(crate) struct LifecycleManager {
     /// [`LifecycleHandle::log_write()`].
     state: Arc<Mutex<LifecycleState>>,
 
+    /// Notified by [`LifecycleHandleImpl`] instances when the buffered data
+    /// crosses a memory watermark, or a partition is requested to be
+    /// persisted.
+    wake: Arc<Notify>,
+
+    /// The bytes buffered per shard, as of the last call to
+    /// [`LifecycleManager::maybe_persist()`].
+    shard_bytes: Metric<U64Gauge>,
+    /// The bytes buffered per namespace, as of the last call to
+    /// [`LifecycleManager::maybe_persist()`].
+    namespace_bytes: Metric<U64Gauge>,
+
     /// Counter for memory pressure triggering a persist.
     persist_memory_counter: U64Counter,
     /// Counter for the size of a partition triggering a persist.
@@ -160,6 +249,14 @@ pub(crate) struct LifecycleManager {
     /// Counter tracking the number of times a partition has been evicted for
     /// containing too many rows.
     persist_rows_counter: U64Counter,
+    /// Counter for explicit requests triggering a persist.
+    persist_request_counter: U64Counter,
+    /// Counter for a partition receiving writes abnormally fast triggering a
+    /// persist.
+    persist_hot_counter: U64Counter,
+    /// The number of hot partitions, as of the last call to
+    /// [`LifecycleManager::maybe_persist()`].
+    hot_partitions: U64Gauge,
 }
 
 /// The configuration options for the lifecycle on the ingester.
@@ -172,6 +269,11 @@ pub struct LifecycleConfig {
     /// partitions currently buffered until it falls below this threshold. An ingester running
     /// in a steady state should operate around this amount of memory usage.
     persist_memory_threshold: usize,
+    /// Once the `persist_memory_threshold` is crossed, the lifecycle manager persists the largest
+    /// partitions until the buffered data falls below this target. Defaults to the
+    /// `persist_memory_threshold`; a lower target persists more data per pass, avoiding
+    /// persisting a single partition each time a write crosses the threshold again.
+    persist_memory_target: usize,
     /// If the total bytes written to an individual partition crosses
     /// this threshold, it will be persisted.
     ///
@@ -196,6 +298,11 @@ pub struct LifecycleConfig {
     /// Reaching this limit pauses ingest while the partition is flushed to
     /// object storage.
     partition_row_max: usize,
+
+    /// If set, a partition receiving writes faster than this many bytes per
+    /// second is considered "hot" and persisted early, rather than growing
+    /// until it crosses one of the other thresholds.
+    hot_partition_bytes_per_second: Option<usize>,
 }
 
 impl LifecycleConfig {
@@ -215,24 +322,77 @@ impl LifecycleConfig {
         Self {
             pause_ingest_size,
             persist_memory_threshold,
+            persist_memory_target: persist_memory_threshold,
             partition_size_threshold,
             partition_age_threshold,
             partition_cold_threshold,
             partition_row_max,
+            hot_partition_bytes_per_second: None,
         }
     }
+
+    /// Persist the largest partitions until the buffered data falls below
+    /// `persist_memory_target` once the persist memory threshold is crossed.
+    /// Panics if `persist_memory_target` is greater than the
+    /// `persist_memory_threshold`.
+    pub const fn with_persist_memory_target(mut self, persist_memory_target: usize) -> Self {
+        assert!(persist_memory_target <= self.persist_memory_threshold);
+
+        self.persist_memory_target = persist_memory_target;
+        self
+    }
+
+    /// Persist partitions receiving writes faster than
+    /// `hot_partition_bytes_per_second`, averaged over the time since their
+    /// first buffered write. Panics if `hot_partition_bytes_per_second` is
+    /// zero.
+    pub const fn with_hot_partition_threshold(
+        mut self,
+        hot_partition_bytes_per_second: usize,
+    ) -> Self {
+        assert!(hot_partition_bytes_per_second > 0);
+
+        self.hot_partition_bytes_per_second = Some(hot_partition_bytes_per_second);
+        self
+    }
 }
 
 #[derive(Default, Debug)]
 struct LifecycleState {
     total_bytes: usize,
+    /// The bytes buffered per shard, summing to `total_bytes`.
+    shard_bytes: BTreeMap<ShardId, usize>,
+    /// The bytes buffered per namespace, summing to `total_bytes`.
+    namespace_bytes: BTreeMap<NamespaceId, usize>,
     partition_stats: BTreeMap<PartitionId, PartitionLifecycleStats>,
+    /// Partitions explicitly requested to be persisted, and the callers to
+    /// notify once they have been.
+    persist_requests: BTreeMap<PartitionId, Vec<oneshot::Sender<()>>>,
 }
 
 impl LifecycleState {
     fn remove(&mut self, partition_id: &PartitionId) -> Option<PartitionLifecycleStats> {
         self.partition_stats.remove(partition_id)
     }
+
+    /// Account `bytes` buffered for `shard_id` and `namespace_id`.
+    fn account(&mut self, shard_id: ShardId, namespace_id: NamespaceId, bytes: usize) {
+        self.total_bytes += bytes;
+        *self.shard_bytes.entry(shard_id).or_default() += bytes;
+        *self.namespace_bytes.entry(namespace_id).or_default() += bytes;
+    }
+
+    /// Release `bytes` previously accounted for `shard_id` and
+    /// `namespace_id`, once the data has been persisted.
+    fn release(&mut self, shard_id: ShardId, namespace_id: NamespaceId, bytes: usize) {
+        self.total_bytes -= bytes;
+        if let Some(v) = self.shard_bytes.get_mut(&shard_id) {
+            *v -= bytes;
+        }
+        if let Some(v) = self.namespace_bytes.get_mut(&namespace_id) {
+            *v -= bytes;
+        }
+    }
 }
 
 /// A snapshot of the stats for the lifecycle manager
@@ -241,8 +401,14 @@ struct LifecycleStats {
     /// total number of bytes the lifecycle manager is aware of across all shards and
     /// partitions. Based on the mutable batch sizes received into all partitions.
     pub total_bytes: usize,
+    /// the number of bytes buffered per shard.
+    pub shard_bytes: BTreeMap<ShardId, usize>,
+    /// the number of bytes buffered per namespace.
+    pub namespace_bytes: BTreeMap<NamespaceId, usize>,
     /// the stats for every partition the lifecycle manager is tracking.
     pub partition_stats: Vec<PartitionLifecycleStats>,
+    /// the partitions explicitly requested to be persisted.
+    pub persist_requests: BTreeSet<PartitionId>,
 }
 
 /// The stats for a partition
@@ -272,6 +438,65 @@ struct PartitionLifecycleStats {
     first_sequence_number: SequenceNumber,
 }
 
+impl PartitionLifecycleStats {
+    /// Returns the partition as a [`HotPartition`] if the average rate at
+    /// which bytes have been written to it since its first write exceeds
+    /// `bytes_per_second_threshold`.
+    ///
+    /// The rate is averaged over at least [`HOT_PARTITION_MIN_WINDOW`], so
+    /// that the first few writes to a new partition are not mistaken for a
+    /// burst.
+    fn hot(&self, now: Time, bytes_per_second_threshold: usize) -> Option<HotPartition> {
+        let window = now
+            .checked_duration_since(self.first_write)
+            .unwrap_or_default()
+            .max(HOT_PARTITION_MIN_WINDOW)
+            .as_secs_f64();
+        let bytes_per_second = (self.bytes_written as f64 / window) as u64;
+        let rows_per_second = (self.rows_written as f64 / window) as u64;
+
+        (bytes_per_second > bytes_per_second_threshold as u64).then(|| HotPartition {
+            shard_id: self.shard_id,
+            namespace_id: self.namespace_id,
+            table_id: self.table_id,
+            partition_id: self.partition_id,
+            bytes_written: self.bytes_written,
+            rows_written: self.rows_written,
+            bytes_per_second,
+            rows_per_second,
+        })
+    }
+}
+
+/// The shortest period over which the write rate of a partition is averaged
+/// when checking whether it is hot.
+const HOT_PARTITION_MIN_WINDOW: Duration = Duration::from_secs(10);
+
+/// A buffered partition receiving writes faster than the configured hot
+/// partition threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HotPartition {
+    /// The shard this partition is under
+    pub shard_id: ShardId,
+    /// The namespace identifier
+    pub namespace_id: NamespaceId,
+    /// The table identifier
+    pub table_id: TableId,
+    /// The partition identifier
+    pub partition_id: PartitionId,
+    /// The number of bytes buffered for the partition as estimated by the
+    /// mutable batch sizes.
+    pub bytes_written: usize,
+    /// The number of rows buffered for the partition.
+    pub rows_written: usize,
+    /// The average number of bytes written per second since the first
+    /// buffered write.
+    pub bytes_per_second: u64,
+    /// The average number of rows written per second since the first
+    /// buffered write.
+    pub rows_per_second: u64,
+}
+
 impl LifecycleManager {
     /// Initialize a new lifecycle manager that will persist when `maybe_persist` is called
     /// if anything is over the size or age threshold.
@@ -290,6 +515,24 @@ impl LifecycleManager {
         let persist_age_counter = persist_counter.recorder(&[("trigger", "age")]);
         let persist_cold_counter = persist_counter.recorder(&[("trigger", "cold")]);
         let persist_rows_counter = persist_counter.recorder(&[("trigger", "rows")]);
+        let persist_request_counter = persist_counter.recorder(&[("trigger", "request")]);
+        let persist_hot_counter = persist_counter.recorder(&[("trigger", "hot")]);
+
+        let hot_partitions = metric_registry
+            .register_metric::<U64Gauge>(
+                "ingester_lifecycle_hot_partitions",
+                "number of buffered partitions receiving writes faster than the hot partition threshold",
+            )
+            .recorder(&[]);
+
+        let shard_bytes = metric_registry.register_metric(
+            "ingester_lifecycle_shard_buffered_bytes",
+            "bytes buffered in the ingester for each shard, as tracked by the lifecycle manager",
+        );
+        let namespace_bytes = metric_registry.register_metric(
+            "ingester_lifecycle_namespace_buffered_bytes",
+            "bytes buffered in the ingester for each namespace, as tracked by the lifecycle manager",
+        );
 
         let job_registry = Arc::new(JobRegistry::new(
             metric_registry,
@@ -300,11 +543,17 @@ impl LifecycleManager {
             time_provider,
             job_registry,
             state: Default::default(),
+            wake: Default::default(),
+            shard_bytes,
+            namespace_bytes,
             persist_memory_counter,
             persist_size_counter,
             persist_age_counter,
             persist_cold_counter,
             persist_rows_counter,
+            persist_request_counter,
+            persist_hot_counter,
+            hot_partitions,
         }
     }
 
@@ -314,21 +563,38 @@ impl LifecycleManager {
             time_provider: Arc::clone(&self.time_provider),
             config: Arc::clone(&self.config),
             state: Arc::clone(&self.state),
+            wake: Arc::clone(&self.wake),
         }
     }
 
-    /// This will persist any partitions that are over their size or age thresholds and
-    /// persist as many partitions as necessary (largest first) to get below the memory threshold.
+    /// This will persist any partitions that are over their size or age thresholds and, if the
+    /// memory threshold has been crossed, persist as many partitions as necessary (largest first)
+    /// to get below the memory target.
     /// The persist operations are spawned in new tasks and run at the same time, but the
     /// function waits for all to return before completing.
     pub async fn maybe_persist<P: Persister>(&mut self, persister: &Arc<P>) {
         let LifecycleStats {
             mut total_bytes,
+            shard_bytes,
+            namespace_bytes,
             partition_stats,
+            persist_requests,
         } = self.stats();
 
+        for (shard_id, bytes) in shard_bytes {
+            self.shard_bytes
+                .recorder([("shard_id", Cow::from(shard_id.to_string()))])
+                .set(bytes as u64);
+        }
+        for (namespace_id, bytes) in namespace_bytes {
+            self.namespace_bytes
+                .recorder([("namespace_id", Cow::from(namespace_id.to_string()))])
+                .set(bytes as u64);
+        }
+
         // get anything over the threshold size or age to persist
         let now = self.time_provider.now();
+        let mut hot_partitions = 0;
 
         let (mut to_persist, mut rest): (
             Vec<PartitionLifecycleStats>,
@@ -432,8 +698,54 @@ impl LifecycleManager {
                 self.persist_size_counter.inc(1);
             }
 
-            aged_out || sized_out || is_cold || exceeded_max_rows
+            // If the partition was explicitly requested to be persisted, flush
+            // it.
+            let requested = persist_requests.contains(&s.partition_id);
+            if requested {
+                info!(
+                    shard_id=%s.shard_id,
+                    partition_id=%s.partition_id,
+                    first_write=%s.first_write,
+                    last_write=%s.last_write,
+                    bytes_written=s.bytes_written,
+                    rows_written=s.rows_written,
+                    first_sequence_number=?s.first_sequence_number,
+                    "partition persistence requested, persisting"
+                );
+                self.persist_request_counter.inc(1);
+            }
+
+            // If the partition is receiving writes abnormally fast, flush it
+            // early rather than letting it grow until another threshold is
+            // crossed.
+            let hot = match self
+                .config
+                .hot_partition_bytes_per_second
+                .and_then(|threshold| s.hot(now, threshold))
+            {
+                Some(hot) => {
+                    info!(
+                        shard_id=%s.shard_id,
+                        partition_id=%s.partition_id,
+                        first_write=%s.first_write,
+                        last_write=%s.last_write,
+                        bytes_written=s.bytes_written,
+                        rows_written=s.rows_written,
+                        first_sequence_number=?s.first_sequence_number,
+                        bytes_per_second=hot.bytes_per_second,
+                        rows_per_second=hot.rows_per_second,
+                        "partition is hot, persisting"
+                    );
+                    self.persist_hot_counter.inc(1);
+                    hot_partitions += 1;
+                    true
+                }
+                None => false,
+            };
+
+            aged_out || sized_out || is_cold || exceeded_max_rows || requested || hot
         });
+        self.hot_partitions.set(hot_partitions);
 
         // keep track of what we'll be evicting to see what else to drop
         for s in &to_persist {
@@ -441,8 +753,8 @@ impl LifecycleManager {
         }
 
         // if we're still over the memory threshold, persist as many of the largest partitions
-        // until we're under. It's ok if this is stale, it'll just get handled on the next pass
-        // through.
+        // until we're under the memory target. It's ok if this is stale, it'll just get handled on
+        // the next pass through.
         if total_bytes > self.config.persist_memory_threshold {
             rest.sort_by(|a, b| b.bytes_written.cmp(&a.bytes_written));
 
@@ -450,7 +762,7 @@ impl LifecycleManager {
 
             let mut memory_persist_counter = 0;
             for s in rest {
-                if total_bytes >= self.config.persist_memory_threshold {
+                if total_bytes >= self.config.persist_memory_target {
                     total_bytes -= s.bytes_written;
                     to_persist.push(s);
                     memory_persist_counter += 1;
@@ -506,14 +818,25 @@ impl LifecycleManager {
                 });
 
                 let state = Arc::clone(&self.state);
+                let (shard_id, namespace_id, partition_id) =
+                    (s.shard_id, s.namespace_id, s.partition_id);
                 tokio::task::spawn(async move {
                     persister
                         .persist(s.shard_id, s.namespace_id, s.table_id, s.partition_id)
                         .await;
                     // Now the data has been uploaded and the memory it was
                     // using has been freed, released the memory capacity back
-                    // the ingester.
-                    state.lock().total_bytes -= partition_memory_usage;
+                    // the ingester, and notify any callers waiting for the
+                    // partition to be persisted.
+                    let requests = {
+                        let mut state = state.lock();
+                        state.release(shard_id, namespace_id, partition_memory_usage);
+                        state.persist_requests.remove(&partition_id)
+                    };
+                    for tx in requests.into_iter().flatten() {
+                        // The caller may have stopped waiting.
+                        let _ = tx.send(());
+                    }
                 })
                 .track(registration)
             })
@@ -570,7 +893,10 @@ impl LifecycleManager {
 
         LifecycleStats {
             total_bytes: s.total_bytes,
+            shard_bytes: s.shard_bytes.clone(),
+            namespace_bytes: s.namespace_bytes.clone(),
             partition_stats,
+            persist_requests: s.persist_requests.keys().copied().collect(),
         }
     }
 
@@ -583,13 +909,15 @@ impl LifecycleManager {
 
 const CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
-/// Runs the lifecycle manager to trigger persistence every second.
+/// Runs the lifecycle manager to trigger persistence every second, or as soon as the buffered data
+/// crosses a memory watermark.
 pub(crate) async fn run_lifecycle_manager<P: Persister>(
     mut manager: LifecycleManager,
     persister: Arc<P>,
     shutdown: CancellationToken,
     poison_cabinet: Arc<PoisonCabinet>,
 ) {
+    let wake = Arc::clone(&manager.wake);
     loop {
         if poison_cabinet.contains(&PoisonPill::LifecyclePanic) {
             panic!("Lifecycle manager poisoned, panic");
@@ -610,6 +938,7 @@ pub(crate) async fn run_lifecycle_manager<P: Persister>(
 
         tokio::select!(
             _ = tokio::time::sleep(CHECK_INTERVAL) => {},
+            _ = wake.notified() => {},
             _ = shutdown.cancelled() => {},
         );
     }
@@ -620,6 +949,7 @@ mod tests {
     use std::collections::BTreeSet;
 
     use async_trait::async_trait;
+    use futures::FutureExt;
     use iox_time::MockProvider;
     use metric::{Attributes, Registry};
     use tokio::sync::Barrier;
@@ -765,10 +1095,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 20,
             persist_memory_threshold: 10,
+            persist_memory_target: 10,
             partition_size_threshold: 5,
             partition_age_threshold: Duration::from_nanos(0),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let TestLifecycleManger {
             m, time_provider, ..
@@ -829,10 +1161,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 10,
             persist_memory_threshold: 10,
+            persist_memory_target: 10,
             partition_size_threshold: 5,
             partition_age_threshold: Duration::from_nanos(0),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let partition_id = PartitionId::new(1);
         let TestLifecycleManger { mut m, .. } = TestLifecycleManger::new(config);
@@ -885,10 +1219,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 20,
             persist_memory_threshold: 10,
+            persist_memory_target: 10,
             partition_size_threshold: 5,
             partition_age_threshold: Duration::from_nanos(0),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 10,
+            hot_partition_bytes_per_second: None,
         };
         let partition_id = PartitionId::new(1);
         let TestLifecycleManger { mut m, .. } = TestLifecycleManger::new(config);
@@ -964,10 +1300,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 20,
             persist_memory_threshold: 10,
+            persist_memory_target: 10,
             partition_size_threshold: 5,
             partition_age_threshold: Duration::from_nanos(0),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let partition_id = PartitionId::new(1);
         let TestLifecycleManger { mut m, .. } = TestLifecycleManger::new(config);
@@ -1027,10 +1365,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 30,
             persist_memory_threshold: 20,
+            persist_memory_target: 20,
             partition_size_threshold: 10,
             partition_age_threshold: Duration::from_nanos(5),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let TestLifecycleManger {
             mut m,
@@ -1098,10 +1438,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 30,
             persist_memory_threshold: 20,
+            persist_memory_target: 20,
             partition_size_threshold: 10,
             partition_age_threshold: Duration::from_nanos(5),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let TestLifecycleManger {
             mut m,
@@ -1179,10 +1521,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 30,
             persist_memory_threshold: 20,
+            persist_memory_target: 20,
             partition_size_threshold: 5,
             partition_age_threshold: Duration::from_millis(100),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let TestLifecycleManger {
             mut m,
@@ -1254,10 +1598,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 60,
             persist_memory_threshold: 20,
+            persist_memory_target: 20,
             partition_size_threshold: 20,
             partition_age_threshold: Duration::from_millis(1000),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let shard_id = ShardId::new(1);
         let TestLifecycleManger {
@@ -1350,10 +1696,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 60,
             persist_memory_threshold: 6,
+            persist_memory_target: 6,
             partition_size_threshold: 5,
             partition_age_threshold: Duration::from_millis(1000),
             partition_cold_threshold: Duration::from_secs(500),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let shard_id = ShardId::new(1);
         let TestLifecycleManger {
@@ -1416,10 +1764,12 @@ mod tests {
         let config = LifecycleConfig {
             pause_ingest_size: 500,
             persist_memory_threshold: 500,
+            persist_memory_target: 500,
             partition_size_threshold: 500,
             partition_age_threshold: Duration::from_secs(1000),
             partition_cold_threshold: Duration::from_secs(5),
             partition_row_max: 100,
+            hot_partition_bytes_per_second: None,
         };
         let TestLifecycleManger {
             mut m,
@@ -1481,6 +1831,345 @@ mod tests {
         assert_eq!(cold_counter, 1);
     }
 
+    #[tokio::test]
+    async fn persists_down_to_memory_target() {
+        let config = LifecycleConfig::new(
+            60,
+            20,
+            100,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        )
+        .with_persist_memory_target(10);
+        let shard_id = ShardId::new(1);
+        let TestLifecycleManger {
+            mut m,
+            metric_registry,
+            ..
+        } = TestLifecycleManger::new(config);
+        let h = m.handle();
+        let persister = Arc::new(TestPersister::default());
+
+        for (partition_id, bytes) in [(1, 13), (2, 8), (3, 7)] {
+            h.log_write(
+                PartitionId::new(partition_id),
+                shard_id,
+                NamespaceId::new(91),
+                TableId::new(92),
+                SequenceNumber::new(partition_id),
+                bytes,
+                1,
+            );
+        }
+
+        m.maybe_persist(&persister).await;
+
+        // Crossing the threshold of 20 persists the largest partitions until
+        // the buffered data is below the target of 10, rather than just below
+        // the threshold.
+        assert!(persister.persist_called_for(PartitionId::new(1)));
+        assert!(persister.persist_called_for(PartitionId::new(2)));
+        assert!(!persister.persist_called_for(PartitionId::new(3)));
+
+        let stats = m.stats();
+        assert_eq!(stats.total_bytes, 7);
+        assert_eq!(get_counter(&metric_registry, "memory"), 2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn memory_target_above_threshold() {
+        LifecycleConfig::new(
+            60,
+            20,
+            100,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        )
+        .with_persist_memory_target(21);
+    }
+
+    #[tokio::test]
+    async fn memory_watermarks_wake_manager() {
+        let config = LifecycleConfig::new(
+            30,
+            20,
+            100,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        );
+        let TestLifecycleManger { m, .. } = TestLifecycleManger::new(config);
+        let h = m.handle();
+
+        let log_write = |bytes| {
+            h.log_write(
+                PartitionId::new(1),
+                ShardId::new(1),
+                NamespaceId::new(91),
+                TableId::new(92),
+                SequenceNumber::new(1),
+                bytes,
+                1,
+            )
+        };
+
+        // Writes below the soft watermark do not wake the manager.
+        assert!(!log_write(15));
+        assert!(m.wake.notified().now_or_never().is_none());
+
+        // Crossing the soft watermark does.
+        assert!(!log_write(10));
+        assert!(m.wake.notified().now_or_never().is_some());
+
+        // Further writes above the soft watermark do not, until the hard
+        // watermark is crossed and ingest is paused.
+        assert!(!log_write(1));
+        assert!(m.wake.notified().now_or_never().is_none());
+        assert!(log_write(10));
+        assert!(m.wake.notified().now_or_never().is_some());
+    }
+
+    #[tokio::test]
+    async fn tracks_buffered_bytes_per_shard_and_namespace() {
+        let config = LifecycleConfig::new(
+            100,
+            50,
+            20,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        );
+        let TestLifecycleManger {
+            mut m,
+            metric_registry,
+            ..
+        } = TestLifecycleManger::new(config);
+        let h = m.handle();
+        let persister = Arc::new(TestPersister::default());
+
+        let writes = [
+            // (partition, shard, namespace, bytes)
+            (1, 1, 91, 5),
+            (2, 1, 92, 30),
+            (3, 2, 92, 7),
+        ];
+        for (partition_id, shard_id, namespace_id, bytes) in writes {
+            h.log_write(
+                PartitionId::new(partition_id),
+                ShardId::new(shard_id),
+                NamespaceId::new(namespace_id),
+                TableId::new(1),
+                SequenceNumber::new(1),
+                bytes,
+                1,
+            );
+        }
+
+        // Partition 2 exceeds the partition size threshold, and is persisted
+        // before the gauges are next refreshed.
+        m.maybe_persist(&persister).await;
+        assert!(persister.persist_called_for(PartitionId::new(2)));
+
+        let stats = m.stats();
+        assert_eq!(
+            stats.shard_bytes,
+            [(ShardId::new(1), 5), (ShardId::new(2), 7)]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(
+            stats.namespace_bytes,
+            [(NamespaceId::new(91), 5), (NamespaceId::new(92), 7)]
+                .into_iter()
+                .collect()
+        );
+
+        m.maybe_persist(&persister).await;
+        let gauge = |name, key, value: i64| {
+            metric_registry
+                .get_instrument::<Metric<U64Gauge>>(name)
+                .unwrap()
+                .get_observer(&Attributes::from([(key, Cow::from(value.to_string()))]))
+                .unwrap()
+                .fetch()
+        };
+        assert_eq!(
+            gauge("ingester_lifecycle_shard_buffered_bytes", "shard_id", 1),
+            5
+        );
+        assert_eq!(
+            gauge("ingester_lifecycle_shard_buffered_bytes", "shard_id", 2),
+            7
+        );
+        assert_eq!(
+            gauge(
+                "ingester_lifecycle_namespace_buffered_bytes",
+                "namespace_id",
+                91
+            ),
+            5
+        );
+        assert_eq!(
+            gauge(
+                "ingester_lifecycle_namespace_buffered_bytes",
+                "namespace_id",
+                92
+            ),
+            7
+        );
+    }
+
+    #[tokio::test]
+    async fn persists_on_request() {
+        let config = LifecycleConfig::new(
+            100,
+            50,
+            50,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        );
+        let TestLifecycleManger {
+            mut m,
+            metric_registry,
+            ..
+        } = TestLifecycleManger::new(config);
+        let h = m.handle();
+        let persister = Arc::new(TestPersister::default());
+
+        for partition_id in [1, 2] {
+            h.log_write(
+                PartitionId::new(partition_id),
+                ShardId::new(1),
+                NamespaceId::new(91),
+                TableId::new(92),
+                SequenceNumber::new(partition_id),
+                10,
+                1,
+            );
+        }
+
+        // Requests for partitions without buffered data return immediately.
+        assert!(!h.persist_partition(PartitionId::new(3)).await);
+
+        let request = tokio::spawn({
+            let h = h.clone();
+            async move { h.persist_partition(PartitionId::new(1)).await }
+        });
+
+        // The request wakes the manager, which persists the requested
+        // partition despite it being under every threshold.
+        m.wake.notified().await;
+        m.maybe_persist(&persister).await;
+        assert!(request.await.unwrap());
+
+        assert!(persister.persist_called_for(PartitionId::new(1)));
+        assert!(!persister.persist_called_for(PartitionId::new(2)));
+        assert_eq!(m.stats().total_bytes, 10);
+        assert!(m.stats().persist_requests.is_empty());
+        assert_eq!(get_counter(&metric_registry, "request"), 1);
+    }
+
+    #[tokio::test]
+    async fn persists_hot_partitions() {
+        let config = LifecycleConfig::new(
+            1000,
+            900,
+            1000,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        )
+        .with_hot_partition_threshold(10);
+        let TestLifecycleManger {
+            mut m,
+            time_provider,
+            metric_registry,
+        } = TestLifecycleManger::new(config);
+        let h = m.handle();
+        let persister = Arc::new(TestPersister::default());
+        let shard_id = ShardId::new(1);
+
+        let write = |partition_id, sequence_number, bytes| {
+            h.log_write(
+                PartitionId::new(partition_id),
+                shard_id,
+                NamespaceId::new(91),
+                TableId::new(92),
+                SequenceNumber::new(sequence_number),
+                bytes,
+                1,
+            );
+        };
+
+        // Partition 1 receives 200 bytes in its first 10 seconds, partition 2
+        // only 50.
+        write(1, 1, 200);
+        write(2, 2, 50);
+
+        let hot = h.hot_partitions();
+        assert_eq!(hot.len(), 1);
+        assert_eq!(hot[0].partition_id, PartitionId::new(1));
+        assert_eq!(hot[0].bytes_written, 200);
+        assert_eq!(hot[0].bytes_per_second, 20);
+
+        time_provider.inc(Duration::from_secs(5));
+        m.maybe_persist(&persister).await;
+
+        assert!(persister.persist_called_for(PartitionId::new(1)));
+        assert!(!persister.persist_called_for(PartitionId::new(2)));
+        assert_eq!(get_counter(&metric_registry, "hot"), 1);
+        assert_eq!(hot_partitions_gauge(&metric_registry), 1);
+        assert!(h.hot_partitions().is_empty());
+
+        // Partition 2 speeds up, averaging 12 bytes per second since its
+        // first write.
+        time_provider.inc(Duration::from_secs(20));
+        write(2, 3, 250);
+
+        let hot = h.hot_partitions();
+        assert_eq!(hot.len(), 1);
+        assert_eq!(hot[0].partition_id, PartitionId::new(2));
+        assert_eq!(hot[0].bytes_per_second, 12);
+
+        m.maybe_persist(&persister).await;
+        assert!(persister.persist_called_for(PartitionId::new(2)));
+        assert_eq!(get_counter(&metric_registry, "hot"), 2);
+        assert_eq!(m.stats().total_bytes, 0);
+
+        m.maybe_persist(&persister).await;
+        assert_eq!(hot_partitions_gauge(&metric_registry), 0);
+    }
+
+    #[test]
+    fn hot_partitions_disabled_by_default() {
+        let config = LifecycleConfig::new(
+            1000,
+            900,
+            1000,
+            Duration::from_secs(1000),
+            Duration::from_secs(500),
+            100,
+        );
+        let TestLifecycleManger { m, .. } = TestLifecycleManger::new(config);
+        let h = m.handle();
+
+        h.log_write(
+            PartitionId::new(1),
+            ShardId::new(1),
+            NamespaceId::new(91),
+            TableId::new(92),
+            SequenceNumber::new(1),
+            500,
+            1,
+        );
+
+        assert!(h.hot_partitions().is_empty());
+    }
+
     struct TestLifecycleManger {
         m: LifecycleManager,
         time_provider: Arc<MockProvider>,
@@ -1514,4 +2203,13 @@ mod tests {
             .fetch();
         v
     }
+
+    fn hot_partitions_gauge(registry: &Registry) -> u64 {
+        registry
+            .get_instrument::<Metric<U64Gauge>>("ingester_lifecycle_hot_partitions")
+            .unwrap()
+            .get_observer(&Attributes::from(&[]))
+            .unwrap()
+            .fetch()
+    }
 }
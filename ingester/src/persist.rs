@@ -0,0 +1,359 @@
+//! Admission control for partition persist jobs.
+//!
+//! Each persist job compacts a snapshot of a partition's buffered data on the
+//! shared [`Executor`] before uploading it to object storage. Without a limit,
+//! a lifecycle pass that selects many partitions at once runs all of their
+//! compactions concurrently, holding every sorted copy of the data in memory
+//! and contending with queries for the executor threads.
+//!
+//! The [`PersistScheduler`] bounds both the number of persist jobs compacting
+//! and uploading at any one time, and the total (estimated) size of the data
+//! they are processing. Jobs that cannot be admitted wait in FIFO order.
+//!
+//! The [`PersistConfig`] also optionally bounds the size of the parquet files
+//! each job produces, splitting the compacted output of a large partition
+//! across multiple files.
+//!
+//! [`Executor`]: iox_query::exec::Executor
+
+use metric::{DurationHistogram, U64Gauge};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::{data::partition::PersistingBatch, query::QueryableBatch};
+
+/// The granularity with which the memory budget is reserved.
+const MEMORY_PERMIT_BYTES: usize = 1024;
+
+/// Limits applied to concurrently executing persist jobs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PersistConfig {
+    /// The maximum number of persist jobs executing at once.
+    max_parallel_jobs: usize,
+
+    /// The total size of the buffered data that may be processed by persist
+    /// jobs at once.
+    memory_budget_bytes: usize,
+
+    /// The maximum number of rows written to a single parquet file.
+    max_file_rows: Option<usize>,
+
+    /// The maximum (estimated) size of the data written to a single parquet
+    /// file.
+    max_file_bytes: Option<usize>,
+}
+
+impl PersistConfig {
+    /// Allow at most `max_parallel_jobs` persist jobs to execute at once,
+    /// processing at most `memory_budget_bytes` of buffered data between them.
+    ///
+    /// A single job larger than `memory_budget_bytes` is still executed, but
+    /// only once no other job is running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either limit is zero.
+    pub fn new(max_parallel_jobs: usize, memory_budget_bytes: usize) -> Self {
+        assert!(
+            max_parallel_jobs > 0,
+            "persist parallelism must be non-zero"
+        );
+        assert!(
+            memory_budget_bytes > 0,
+            "persist memory budget must be non-zero"
+        );
+
+        Self {
+            max_parallel_jobs: max_parallel_jobs.min(Semaphore::MAX_PERMITS),
+            memory_budget_bytes,
+            max_file_rows: None,
+            max_file_bytes: None,
+        }
+    }
+
+    /// Split the compacted output of a persist job across multiple parquet
+    /// files when it contains more than `max_file_rows` rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_file_rows` is zero.
+    pub fn with_max_file_rows(mut self, max_file_rows: usize) -> Self {
+        assert!(max_file_rows > 0, "max file rows must be non-zero");
+
+        self.max_file_rows = Some(max_file_rows);
+        self
+    }
+
+    /// Split the compacted output of a persist job across multiple parquet
+    /// files when the in-memory size of the data being persisted exceeds
+    /// `max_file_bytes`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_file_bytes` is zero.
+    pub fn with_max_file_bytes(mut self, max_file_bytes: usize) -> Self {
+        assert!(max_file_bytes > 0, "max file bytes must be non-zero");
+
+        self.max_file_bytes = Some(max_file_bytes);
+        self
+    }
+
+    /// The maximum number of persist jobs executing at once.
+    pub fn max_parallel_jobs(&self) -> usize {
+        self.max_parallel_jobs
+    }
+
+    /// The total size of the buffered data that may be processed by persist
+    /// jobs at once.
+    pub fn memory_budget_bytes(&self) -> usize {
+        self.memory_budget_bytes
+    }
+
+    /// The maximum number of rows written to a single parquet file, if
+    /// limited.
+    pub fn max_file_rows(&self) -> Option<usize> {
+        self.max_file_rows
+    }
+
+    /// The maximum (estimated) size of the data written to a single parquet
+    /// file, if limited.
+    pub fn max_file_bytes(&self) -> Option<usize> {
+        self.max_file_bytes
+    }
+
+    /// The memory budget in units of [`MEMORY_PERMIT_BYTES`], which the
+    /// semaphore permits are issued in.
+    fn memory_permits(&self) -> u32 {
+        let permits = (self.memory_budget_bytes / MEMORY_PERMIT_BYTES).max(1);
+        u32::try_from(permits).unwrap_or(u32::MAX)
+    }
+}
+
+impl Default for PersistConfig {
+    /// No limits on the number or size of concurrent persist jobs.
+    fn default() -> Self {
+        Self::new(Semaphore::MAX_PERMITS, usize::MAX)
+    }
+}
+
+/// Admits persist jobs for execution within the limits of a [`PersistConfig`].
+#[derive(Debug)]
+pub struct PersistScheduler {
+    config: PersistConfig,
+
+    /// One permit per job that may execute concurrently.
+    jobs: Semaphore,
+
+    /// One permit per [`MEMORY_PERMIT_BYTES`] of the memory budget.
+    memory: Semaphore,
+
+    /// Jobs waiting to be admitted.
+    queued: U64Gauge,
+    /// Jobs admitted and not yet complete.
+    active: U64Gauge,
+    /// Estimated bytes reserved by admitted jobs.
+    reserved_bytes: U64Gauge,
+    /// Time spent by jobs waiting to be admitted.
+    wait_duration: DurationHistogram,
+}
+
+impl PersistScheduler {
+    /// Initialise a scheduler enforcing the limits in `config`.
+    pub fn new(config: PersistConfig, metrics: &metric::Registry) -> Self {
+        let jobs = metrics.register_metric::<U64Gauge>(
+            "ingester_persist_jobs",
+            "number of persist jobs waiting to be admitted or executing",
+        );
+        let reserved_bytes = metrics
+            .register_metric::<U64Gauge>(
+                "ingester_persist_memory_reserved_bytes",
+                "estimated size of the data being persisted by executing persist jobs",
+            )
+            .recorder(&[]);
+        let wait_duration = metrics
+            .register_metric::<DurationHistogram>(
+                "ingester_persist_wait_duration",
+                "time persist jobs spent waiting for the persist concurrency and memory limits",
+            )
+            .recorder(&[]);
+
+        Self {
+            config,
+            jobs: Semaphore::new(config.max_parallel_jobs),
+            memory: Semaphore::new(config.memory_permits() as usize),
+            queued: jobs.recorder(&[("state", "queued")]),
+            active: jobs.recorder(&[("state", "active")]),
+            reserved_bytes,
+            wait_duration,
+        }
+    }
+
+    /// The limits enforced by this scheduler.
+    pub fn config(&self) -> PersistConfig {
+        self.config
+    }
+
+    /// Wait until a persist job processing `batch` can be executed within the
+    /// configured limits, returning a [`PersistPermit`] that must be held
+    /// until the job completes.
+    pub(crate) async fn admit(&self, batch: &PersistingBatch) -> PersistPermit<'_> {
+        self.admit_bytes(estimate_size(batch)).await
+    }
+
+    /// Wait until a persist job processing `bytes` of data can be executed.
+    async fn admit_bytes(&self, bytes: usize) -> PersistPermit<'_> {
+        let started = std::time::Instant::now();
+        let queued = Queued::new(&self.queued);
+
+        // Acquire the job slot before the memory reservation, so that at most
+        // `max_parallel_jobs` jobs hold (or wait on) the memory budget.
+        let job = self.jobs.acquire().await.expect("persist semaphore closed");
+
+        // A job larger than the entire budget reserves the entire budget,
+        // running once all other jobs have released their reservations.
+        let permits = (bytes / MEMORY_PERMIT_BYTES).max(1);
+        let permits = u32::try_from(permits)
+            .unwrap_or(u32::MAX)
+            .min(self.config.memory_permits());
+        let memory = self
+            .memory
+            .acquire_many(permits)
+            .await
+            .expect("persist semaphore closed");
+
+        drop(queued);
+        self.active.inc(1);
+        self.reserved_bytes.inc(bytes as u64);
+        self.wait_duration.record(started.elapsed());
+
+        PersistPermit {
+            scheduler: self,
+            bytes,
+            _job: job,
+            _memory: memory,
+        }
+    }
+}
+
+/// Accounts a job as queued until dropped, including when the job is
+/// cancelled while waiting to be admitted.
+#[derive(Debug)]
+struct Queued<'a>(&'a U64Gauge);
+
+impl<'a> Queued<'a> {
+    fn new(gauge: &'a U64Gauge) -> Self {
+        gauge.inc(1);
+        Self(gauge)
+    }
+}
+
+impl<'a> Drop for Queued<'a> {
+    fn drop(&mut self) {
+        self.0.dec(1);
+    }
+}
+
+/// Returns the estimated in-memory size of the data in `batch`.
+fn estimate_size(batch: &PersistingBatch) -> usize {
+    estimate_data_size(&batch.data)
+}
+
+/// Returns the estimated in-memory size of the snapshots in `data`.
+pub(crate) fn estimate_data_size(data: &QueryableBatch) -> usize {
+    data.data
+        .iter()
+        .flat_map(|snapshot| snapshot.data.columns())
+        .map(|col| col.get_array_memory_size())
+        .sum()
+}
+
+/// A persist job admitted by a [`PersistScheduler`], releasing its job slot
+/// and memory reservation when dropped.
+#[derive(Debug)]
+pub(crate) struct PersistPermit<'a> {
+    scheduler: &'a PersistScheduler,
+    bytes: usize,
+    _job: SemaphorePermit<'a>,
+    _memory: SemaphorePermit<'a>,
+}
+
+impl<'a> Drop for PersistPermit<'a> {
+    fn drop(&mut self) {
+        self.scheduler.active.dec(1);
+        self.scheduler.reserved_bytes.dec(self.bytes as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::FutureExt;
+
+    use super::*;
+
+    const MB: usize = 1024 * 1024;
+
+    #[tokio::test]
+    async fn test_parallel_job_limit() {
+        let metrics = metric::Registry::default();
+        let scheduler = PersistScheduler::new(PersistConfig::new(2, 100 * MB), &metrics);
+
+        let a = scheduler.admit_bytes(MB).await;
+        let _b = scheduler.admit_bytes(MB).await;
+
+        // The third job must wait for a job slot.
+        let mut c = Box::pin(scheduler.admit_bytes(MB));
+        assert!((&mut c).now_or_never().is_none());
+        assert_eq!(scheduler.queued.fetch(), 1);
+        assert_eq!(scheduler.active.fetch(), 2);
+
+        drop(a);
+        let _c = c.now_or_never().expect("job should be admitted");
+        assert_eq!(scheduler.queued.fetch(), 0);
+        assert_eq!(scheduler.active.fetch(), 2);
+        assert_eq!(scheduler.reserved_bytes.fetch(), 2 * MB as u64);
+    }
+
+    #[tokio::test]
+    async fn test_memory_budget() {
+        let metrics = metric::Registry::default();
+        let scheduler = PersistScheduler::new(PersistConfig::new(10, 10 * MB), &metrics);
+
+        let a = scheduler.admit_bytes(6 * MB).await;
+        let _c = scheduler.admit_bytes(4 * MB).await;
+
+        // The third job does not fit within the remaining budget.
+        let mut b = Box::pin(scheduler.admit_bytes(MB));
+        assert!((&mut b).now_or_never().is_none());
+        assert_eq!(scheduler.queued.fetch(), 1);
+
+        drop(a);
+        let _b = b.now_or_never().expect("job should be admitted");
+        assert_eq!(scheduler.reserved_bytes.fetch(), 5 * MB as u64);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_job_runs_alone() {
+        let metrics = metric::Registry::default();
+        let scheduler = PersistScheduler::new(PersistConfig::new(10, 10 * MB), &metrics);
+
+        let a = scheduler.admit_bytes(MB).await;
+
+        // A job larger than the whole budget waits for all other jobs to
+        // complete, rather than waiting forever.
+        let mut b = Box::pin(scheduler.admit_bytes(50 * MB));
+        assert!((&mut b).now_or_never().is_none());
+
+        drop(a);
+        let _b = b.now_or_never().expect("job should be admitted");
+        assert_eq!(scheduler.reserved_bytes.fetch(), 50 * MB as u64);
+
+        // And no other job may run alongside it.
+        assert!(scheduler.admit_bytes(MB).now_or_never().is_none());
+        assert_eq!(scheduler.queued.fetch(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "persist parallelism must be non-zero")]
+    fn test_zero_parallelism() {
+        PersistConfig::new(0, MB);
+    }
+}
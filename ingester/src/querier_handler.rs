@@ -2,27 +2,48 @@
 
 use crate::{
     data::{
-        namespace::NamespaceName, partition::UnpersistedPartitionData, table::TableName,
+        namespace::NamespaceName,
+        partition::{SnapshotBatch, UnpersistedPartitionData},
+        table::TableName,
         IngesterData,
     },
     query::QueryableBatch,
 };
-use arrow::{array::new_null_array, error::ArrowError, record_batch::RecordBatch};
+use arrow::{
+    array::{new_null_array, Array, DictionaryArray, StringArray, TimestampNanosecondArray},
+    datatypes::Int32Type,
+    error::ArrowError,
+    record_batch::RecordBatch,
+};
 use arrow_util::optimize::{optimize_record_batch, optimize_schema};
-use data_types::{PartitionId, SequenceNumber};
-use datafusion::physical_plan::SendableRecordBatchStream;
-use datafusion_util::MemoryStream;
+use data_types::{
+    ColumnSummary, InfluxDbType, PartitionId, SequenceNumber, StatValues, Statistics, TableSummary,
+};
+use datafusion::{
+    logical_expr::utils::expr_to_columns,
+    physical_plan::{stream::RecordBatchStreamAdapter, SendableRecordBatchStream},
+};
 use futures::{Stream, StreamExt, TryStreamExt};
 use generated_types::ingester::IngesterQueryRequest;
+use iox_query::{pruning::prune_summaries, QueryChunkMeta};
 use observability_deps::tracing::debug;
-use schema::{merge::SchemaMerger, selection::Selection};
+use predicate::Predicate;
+use schema::{merge::SchemaMerger, selection::Selection, InfluxColumnType};
 use snafu::{ensure, Snafu};
-use std::{pin::Pin, sync::Arc};
+use std::{collections::HashSet, pin::Pin, sync::Arc};
 use trace::span::{Span, SpanRecorder};
 
 /// Number of table data read locks that shall be acquired in parallel
 const CONCURRENT_TABLE_DATA_LOCKS: usize = 10;
 
+/// Maximum number of rows in each [`RecordBatch`] sent to the querier.
+///
+/// Snapshots are streamed as zero-copy slices of at most this many rows, so
+/// that each slice is optimised and encoded only once the client is ready to
+/// receive it, instead of encoding a whole (possibly very large) snapshot at
+/// once.
+const QUERY_BATCH_ROWS: usize = 8 * 1024;
+
 #[derive(Debug, Snafu)]
 #[allow(missing_copy_implementations, missing_docs)]
 pub enum Error {
@@ -357,6 +378,22 @@ fn prepare_data_to_querier_for_partition(
 ) -> Vec<SendableRecordBatchStream> {
     let mut span_recorder = SpanRecorder::new(span);
 
+    // Skip the partition entirely when none of its data falls within the
+    // queried time range, without reading or pruning any of its snapshots.
+    if let Some(range) = request.predicate.as_ref().and_then(|p| p.range) {
+        let overlaps = unpersisted_partition_data
+            .timestamps
+            .map_or(false, |t| t.overlaps(range));
+        if !overlaps {
+            debug!(
+                partition_id=%unpersisted_partition_data.partition_id,
+                "partition pruned by time range"
+            );
+            span_recorder.ok("pruned");
+            return vec![];
+        }
+    }
+
     // ------------------------------------------------
     // Accumulate data
 
@@ -380,36 +417,186 @@ fn prepare_data_to_querier_for_partition(
         })
         .with_data(unpersisted_partition_data.non_persisted);
 
-    let streams = queryable_batch
-        .data
+    // Skip the snapshots that cannot contain rows matching the predicate,
+    // rather than sending them to the querier to be filtered out there.
+    let snapshots = match &request.predicate {
+        Some(predicate) => prune_snapshots(&queryable_batch, predicate),
+        None => queryable_batch.data,
+    };
+
+    // Project each snapshot to the requested columns before it is encoded,
+    // so that only the columns the querier needs are sent over the wire.
+    //
+    // Snapshots containing none of the requested columns are skipped, as in
+    // the `read_filter()` implementation of QueryableBatch.
+    let streams = snapshots
         .iter()
-        .map(|snapshot_batch| {
-            let batch = snapshot_batch.data.as_ref();
+        .filter_map(|snapshot_batch| {
+            let batch = snapshot_batch
+                .scan(selection)
+                .expect("projecting a snapshot to existing columns cannot fail")?;
             let schema = batch.schema();
+            let batches = split_batch(batch, QUERY_BATCH_ROWS).map(Ok);
+            Some(Box::pin(RecordBatchStreamAdapter::new(
+                schema,
+                futures::stream::iter(batches),
+            )) as SendableRecordBatchStream)
+        })
+        .collect();
+
+    span_recorder.ok("done");
+
+    streams
+}
+
+/// Lazily split `batch` into zero-copy slices of at most `max_rows` rows.
+fn split_batch(
+    batch: Arc<RecordBatch>,
+    max_rows: usize,
+) -> impl Iterator<Item = RecordBatch> + Send {
+    let num_rows = batch.num_rows();
+    (0..num_rows)
+        .step_by(max_rows)
+        .map(move |offset| batch.slice(offset, max_rows.min(num_rows - offset)))
+}
+
+/// Return the snapshots in `batch` that may contain rows matching the time
+/// range and tag predicates of `predicate`, using the min/max statistics of
+/// each snapshot.
+///
+/// Only whole snapshots are removed; the rows of the returned snapshots are
+/// not filtered. If the snapshots cannot be pruned, all are returned.
+fn prune_snapshots(batch: &QueryableBatch, predicate: &Predicate) -> Vec<Arc<SnapshotBatch>> {
+    let filter_expr = match predicate.filter_expr() {
+        Some(v) if !batch.data.is_empty() => v,
+        _ => return batch.data.clone(),
+    };
+
+    // Compute statistics only for the tag and time columns the predicate
+    // refers to.
+    let mut referenced = HashSet::new();
+    if expr_to_columns(&filter_expr, &mut referenced).is_err() {
+        return batch.data.clone();
+    }
+    let schema = batch.schema();
+    let columns: Vec<_> = schema
+        .iter()
+        .filter_map(|(influx_type, field)| match influx_type {
+            Some(InfluxColumnType::Tag) => Some((field.name().as_str(), InfluxDbType::Tag)),
+            Some(InfluxColumnType::Timestamp) => {
+                Some((field.name().as_str(), InfluxDbType::Timestamp))
+            }
+            _ => None,
+        })
+        .filter(|(name, _)| referenced.iter().any(|c| c.name == *name))
+        .collect();
+
+    let summaries = batch
+        .data
+        .iter()
+        .map(|snapshot| Some(Arc::new(snapshot_summary(&snapshot.data, &columns))))
+        .collect();
+
+    match prune_summaries(Arc::clone(&schema), &summaries, predicate) {
+        Ok(keep) => {
+            let snapshots: Vec<_> = batch
+                .data
+                .iter()
+                .zip(keep)
+                .filter_map(|(snapshot, keep)| keep.then(|| Arc::clone(snapshot)))
+                .collect();
+            debug!(
+                partition_id=%batch.partition_id,
+                n_snapshots=batch.data.len(),
+                n_pruned=batch.data.len() - snapshots.len(),
+                "pruned snapshots"
+            );
+            snapshots
+        }
+        Err(reason) => {
+            debug!(partition_id=%batch.partition_id, %reason, "could not prune snapshots");
+            batch.data.clone()
+        }
+    }
+}
 
-            // Apply selection to in-memory batch
-            let batch = match selection {
-                Selection::All => batch.clone(),
-                Selection::Some(columns) => {
-                    let projection = columns
+/// Compute the min/max statistics of the tag and time `columns` of `batch`.
+///
+/// Columns missing from `batch` are omitted from the summary, and therefore
+/// never cause a snapshot to be pruned.
+fn snapshot_summary(batch: &RecordBatch, columns: &[(&str, InfluxDbType)]) -> TableSummary {
+    let schema = batch.schema();
+    let columns = columns
+        .iter()
+        .filter_map(|&(name, influx_type)| {
+            let array = batch.column(schema.index_of(name).ok()?);
+            let stats = match influx_type {
+                InfluxDbType::Timestamp => {
+                    let values = array
+                        .as_any()
+                        .downcast_ref::<TimestampNanosecondArray>()?
                         .iter()
-                        .flat_map(|&column_name| {
-                            // ignore non-existing columns
-                            schema.index_of(column_name).ok()
-                        })
-                        .collect::<Vec<_>>();
-                    batch.project(&projection).expect("bug in projection")
+                        .flatten();
+                    Statistics::I64(min_max_stats(values, array.as_ref()))
                 }
+                InfluxDbType::Tag => {
+                    let dictionary = array
+                        .as_any()
+                        .downcast_ref::<DictionaryArray<Int32Type>>()?;
+                    let values = dictionary.values().as_any().downcast_ref::<StringArray>()?;
+                    let values = dictionary
+                        .keys()
+                        .iter()
+                        .flatten()
+                        .map(|key| values.value(key as usize));
+                    let stats = min_max_stats(values, array.as_ref());
+                    Statistics::String(StatValues {
+                        min: stats.min.map(ToString::to_string),
+                        max: stats.max.map(ToString::to_string),
+                        total_count: stats.total_count,
+                        null_count: stats.null_count,
+                        distinct_count: None,
+                    })
+                }
+                InfluxDbType::Field => return None,
             };
 
-            // create stream
-            Box::pin(MemoryStream::new(vec![batch])) as SendableRecordBatchStream
+            Some(ColumnSummary {
+                name: name.to_string(),
+                influxdb_type: Some(influx_type),
+                stats,
+            })
         })
         .collect();
 
-    span_recorder.ok("done");
+    TableSummary { columns }
+}
 
-    streams
+/// Return the min and max of the non-null `values` of `array`.
+fn min_max_stats<T>(values: impl Iterator<Item = T>, array: &dyn Array) -> StatValues<T>
+where
+    T: PartialOrd + Copy,
+{
+    let (min, max) = values.fold((None, None), |(min, max), v| {
+        (
+            Some(match min {
+                Some(m) if m <= v => m,
+                _ => v,
+            }),
+            Some(match max {
+                Some(m) if m >= v => m,
+                _ => v,
+            }),
+        )
+    });
+
+    StatValues {
+        min,
+        max,
+        total_count: array.len() as u64,
+        null_count: Some(array.null_count() as u64),
+        distinct_count: None,
+    }
 }
 
 #[cfg(test)]
@@ -417,17 +604,20 @@ mod tests {
     use std::task::{Context, Poll};
 
     use arrow::{datatypes::SchemaRef, record_batch::RecordBatch};
-    use arrow_util::assert_batches_sorted_eq;
+    use arrow_util::{assert_batches_eq, assert_batches_sorted_eq};
     use assert_matches::assert_matches;
     use datafusion::{
         physical_plan::RecordBatchStream,
         prelude::{col, lit},
     };
+    use datafusion_util::lit_dict;
     use mutable_batch_lp::test_helpers::lp_to_mutable_batch;
     use predicate::Predicate;
 
     use super::*;
-    use crate::test_util::{make_ingester_data, DataLocation, TEST_NAMESPACE, TEST_TABLE};
+    use crate::test_util::{
+        make_ingester_data, make_snapshot_batch, DataLocation, TEST_NAMESPACE, TEST_TABLE,
+    };
 
     #[tokio::test]
     async fn test_ingester_query_response_flatten() {
@@ -612,7 +802,8 @@ mod tests {
             vec!["city".to_string(), "temp".to_string(), "time".to_string()],
             Some(pred),
         ));
-        // predicates and de-dup are NOT applied!, otherwise this would look like this:
+        // predicates are only used to prune whole snapshots (none of which can be pruned here),
+        // and de-dup is NOT applied!, otherwise this would look like this:
         // let expected = vec![
         //     "+------------+------+--------------------------------+",
         //     "| city       | temp | time                           |",
@@ -679,6 +870,194 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_prepare_data_to_querier_projection() {
+        test_helpers::maybe_start_logging();
+
+        // Only the "temp" field is requested, which some snapshots do not
+        // contain.
+        let request = Arc::new(IngesterQueryRequest::new(
+            TEST_NAMESPACE.to_string(),
+            TEST_TABLE.to_string(),
+            vec!["temp".to_string()],
+            None,
+        ));
+
+        for two_partitions in [false, true] {
+            for loc in [
+                DataLocation::BUFFER,
+                DataLocation::BUFFER_SNAPSHOT,
+                DataLocation::BUFFER_PERSISTING,
+                DataLocation::BUFFER_SNAPSHOT_PERSISTING,
+                DataLocation::SNAPSHOT,
+                DataLocation::SNAPSHOT_PERSISTING,
+                DataLocation::PERSISTING,
+            ] {
+                println!("Location: {loc:?}, two partitions: {two_partitions}");
+                let scenario = Arc::new(make_ingester_data(two_partitions, loc).await);
+
+                let mut stream = prepare_data_to_querier(&scenario, &request, None)
+                    .await
+                    .unwrap()
+                    .flatten();
+
+                let mut temps = vec![];
+                while let Some(msg) = stream.try_next().await.unwrap() {
+                    match msg {
+                        FlatIngesterQueryResponse::StartPartition { .. } => {}
+                        FlatIngesterQueryResponse::StartSnapshot { schema } => {
+                            // Only the requested column is sent.
+                            let names: Vec<_> =
+                                schema.fields().iter().map(|f| f.name().as_str()).collect();
+                            assert_eq!(names, ["temp"]);
+                        }
+                        FlatIngesterQueryResponse::RecordBatch { batch } => {
+                            let values = batch
+                                .column(0)
+                                .as_any()
+                                .downcast_ref::<arrow::array::Float64Array>()
+                                .unwrap();
+                            temps.extend(values.iter().flatten());
+                        }
+                    }
+                }
+
+                temps.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                assert_eq!(temps, [55.0, 56.0, 58.0, 60.0]);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prepare_data_to_querier_prunes_partitions() {
+        test_helpers::maybe_start_logging();
+
+        // All the test data has timestamps in [22, 46].
+        let request = Arc::new(IngesterQueryRequest::new(
+            TEST_NAMESPACE.to_string(),
+            TEST_TABLE.to_string(),
+            vec![],
+            Some(Predicate::default().with_range(100, 200)),
+        ));
+
+        for two_partitions in [false, true] {
+            for loc in [
+                DataLocation::BUFFER,
+                DataLocation::BUFFER_SNAPSHOT,
+                DataLocation::BUFFER_PERSISTING,
+                DataLocation::BUFFER_SNAPSHOT_PERSISTING,
+                DataLocation::SNAPSHOT,
+                DataLocation::SNAPSHOT_PERSISTING,
+                DataLocation::PERSISTING,
+            ] {
+                println!("Location: {loc:?}, two partitions: {two_partitions}");
+                let scenario = Arc::new(make_ingester_data(two_partitions, loc).await);
+
+                let msgs: Vec<_> = prepare_data_to_querier(&scenario, &request, None)
+                    .await
+                    .unwrap()
+                    .flatten()
+                    .try_collect()
+                    .await
+                    .unwrap();
+
+                // Every partition is still reported, so the querier learns
+                // its persistence status, but no data is sent for any of
+                // them.
+                let want = if two_partitions { 2 } else { 1 };
+                assert_eq!(msgs.len(), want);
+                assert!(msgs
+                    .iter()
+                    .all(|m| matches!(m, FlatIngesterQueryResponse::StartPartition { .. })));
+            }
+        }
+    }
+
+    #[test]
+    fn test_prune_snapshots() {
+        let snapshots = [
+            "t,city=Boston v=1 10\nt,city=Boston v=2 20",
+            "t,city=Medford v=3 30",
+            "t,city=Andover v=4 100\nt v=5 110",
+        ]
+        .into_iter()
+        .enumerate()
+        .map(|(i, lp)| {
+            let seq = SequenceNumber::new(i as i64);
+            Arc::new(make_snapshot_batch(Arc::new(lp_to_batch(lp)), seq, seq))
+        })
+        .collect();
+        let batch = QueryableBatch::new("t".into(), PartitionId::new(1), snapshots);
+
+        let kept = |predicate: Predicate| -> Vec<i64> {
+            prune_snapshots(&batch, &predicate)
+                .iter()
+                .map(|s| s.min_sequence_number.get())
+                .collect()
+        };
+
+        // Time range
+        assert_eq!(kept(Predicate::default().with_range(0, 25)), [0]);
+        assert_eq!(kept(Predicate::default().with_range(25, 105)), [1, 2]);
+
+        // Tag predicates, alone and combined with a time range
+        assert_eq!(
+            kept(Predicate::default().with_expr(col("city").eq(lit_dict("Medford")))),
+            [1]
+        );
+        assert_eq!(
+            kept(
+                Predicate::default()
+                    .with_expr(col("city").eq(lit_dict("Boston")))
+                    .with_range(50, 200)
+            ),
+            Vec::<i64>::new()
+        );
+
+        // Field predicates and unknown columns never prune
+        assert_eq!(
+            kept(Predicate::default().with_expr(col("v").gt(lit(100.0)))),
+            [0, 1, 2]
+        );
+        assert_eq!(
+            kept(Predicate::default().with_expr(col("region").eq(lit_dict("us")))),
+            [0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn test_split_batch() {
+        let batch = Arc::new(lp_to_batch(
+            "table x=1 1\ntable x=2 2\ntable x=3 3\ntable x=4 4\ntable x=5 5",
+        ));
+
+        let slices: Vec<_> = split_batch(Arc::clone(&batch), 2).collect();
+        let rows: Vec<_> = slices.iter().map(RecordBatch::num_rows).collect();
+        assert_eq!(rows, [2, 2, 1]);
+        assert!(slices.iter().all(|slice| slice.schema() == batch.schema()));
+
+        let expected = [
+            "+--------------------------------+---+",
+            "| time                           | x |",
+            "+--------------------------------+---+",
+            "| 1970-01-01T00:00:00.000000001Z | 1 |",
+            "| 1970-01-01T00:00:00.000000002Z | 2 |",
+            "| 1970-01-01T00:00:00.000000003Z | 3 |",
+            "| 1970-01-01T00:00:00.000000004Z | 4 |",
+            "| 1970-01-01T00:00:00.000000005Z | 5 |",
+            "+--------------------------------+---+",
+        ];
+        assert_batches_eq!(expected, &slices);
+
+        // A batch no larger than the limit is not split.
+        let slices: Vec<_> = split_batch(Arc::clone(&batch), 5).collect();
+        assert_eq!(slices.len(), 1);
+        assert_eq!(slices[0], *batch);
+
+        let empty = Arc::new(batch.slice(0, 0));
+        assert_eq!(split_batch(empty, 2).count(), 0);
+    }
+
     pub struct TestRecordBatchStream {
         schema: SchemaRef,
         batches: Vec<Result<RecordBatch, ArrowError>>,
@@ -10,10 +10,12 @@ use arrow_flight::{
     Action, ActionType, Criteria, Empty, FlightData, FlightDescriptor, FlightInfo,
     HandshakeRequest, HandshakeResponse, IpcMessage, PutResult, SchemaAsIpc, SchemaResult, Ticket,
 };
+use data_types::PartitionKey;
 use flatbuffers::FlatBufferBuilder;
 use futures::Stream;
 use generated_types::influxdata::iox::ingester::v1::{
     self as proto,
+    persist_service_server::{PersistService, PersistServiceServer},
     write_info_service_server::{WriteInfoService, WriteInfoServiceServer},
 };
 use observability_deps::tracing::{debug, info, warn};
@@ -66,6 +68,13 @@ impl<I: IngestHandler + Send + Sync + 'static> GrpcDelegate<I> {
             Arc::clone(&self.ingest_handler) as _
         ))
     }
+
+    /// Acquire a Persist gRPC service implementation.
+    pub fn persist_service(&self) -> PersistServiceServer<impl PersistService> {
+        PersistServiceServer::new(PersistServiceImpl::new(
+            Arc::clone(&self.ingest_handler) as _
+        ))
+    }
 }
 
 /// Implementation of write info
@@ -115,6 +124,76 @@ impl WriteInfoService for WriteInfoServiceImpl {
     }
 }
 
+/// Implementation of manual partition persistence
+struct PersistServiceImpl {
+    handler: Arc<dyn IngestHandler + Send + Sync + 'static>,
+}
+
+impl PersistServiceImpl {
+    pub fn new(handler: Arc<dyn IngestHandler + Send + Sync + 'static>) -> Self {
+        Self { handler }
+    }
+}
+
+#[tonic::async_trait]
+impl PersistService for PersistServiceImpl {
+    async fn persist_partition(
+        &self,
+        request: Request<proto::PersistPartitionRequest>,
+    ) -> Result<Response<proto::PersistPartitionResponse>, tonic::Status> {
+        let proto::PersistPartitionRequest {
+            namespace,
+            table,
+            partition_key,
+        } = request.into_inner();
+
+        let partition_key = PartitionKey::from(partition_key);
+        let persisted = self
+            .handler
+            .persist_partition(&namespace, &table, &partition_key)
+            .await
+            .map_err(|e| match e {
+                crate::data::Error::NamespaceNotFound { .. }
+                | crate::data::Error::TableNotFound { .. }
+                | crate::data::Error::PartitionNotFound { .. } => {
+                    tonic::Status::not_found(e.to_string())
+                }
+                _ => tonic::Status::internal(e.to_string()),
+            })?;
+
+        debug!(%namespace, %table, %partition_key, persisted, "persist partition request");
+
+        Ok(tonic::Response::new(proto::PersistPartitionResponse {
+            persisted,
+        }))
+    }
+
+    async fn get_hot_partitions(
+        &self,
+        _request: Request<proto::GetHotPartitionsRequest>,
+    ) -> Result<Response<proto::GetHotPartitionsResponse>, tonic::Status> {
+        let partitions = self
+            .handler
+            .hot_partitions()
+            .into_iter()
+            .map(|p| proto::HotPartition {
+                shard_id: p.shard_id.get(),
+                namespace_id: p.namespace_id.get(),
+                table_id: p.table_id.get(),
+                partition_id: p.partition_id.get(),
+                bytes_buffered: p.bytes_written as u64,
+                rows_buffered: p.rows_written as u64,
+                bytes_per_second: p.bytes_per_second,
+                rows_per_second: p.rows_per_second,
+            })
+            .collect();
+
+        Ok(tonic::Response::new(proto::GetHotPartitionsResponse {
+            partitions,
+        }))
+    }
+}
+
 #[derive(Debug, Snafu)]
 #[allow(missing_docs)]
 pub enum Error {
@@ -0,0 +1,716 @@
+//! A local write-ahead log of the DML operations buffered by the ingester.
+//!
+//! Without a WAL, an ingester that restarts must rewind each shard's write
+//! buffer stream to the shard's `min_unpersisted_sequence_number` and
+//! re-consume every operation since, which can be a long way back for a
+//! partition that has not been persisted for some time. With a WAL, every
+//! operation is appended to a local log before it is buffered, and on startup
+//! the log is replayed from local disk, seeking the write buffer stream past
+//! the last operation in the log.
+//!
+//! # Layout
+//!
+//! Each shard has its own directory (named after the [`ShardId`]) containing:
+//!
+//! * A `start` marker holding the sequence number from which the log is
+//!   known to contain every operation applied to the shard.
+//! * A series of segment files, each named after the sequence number of its
+//!   first record. A new segment is started once the current segment exceeds
+//!   [`SEGMENT_SIZE_BYTES`].
+//!
+//! Each record in a segment is framed as:
+//!
+//! ```text
+//! [ body length: u32 LE ][ CRC32 of body: u32 LE ][ body ]
+//!
+//! body = [ sequence number: i64 LE ][ producer timestamp nanos: i64 LE ]
+//!        [ namespace length: u32 LE ][ namespace ][ encoded operation ]
+//! ```
+//!
+//! Segments containing only records below the shard's
+//! `min_unpersisted_sequence_number` are deleted as data is persisted.
+//!
+//! # Durability
+//!
+//! Every record is synced to disk before the operation is buffered. A record
+//! torn by a crash part way through a write can only be the last record of
+//! the most recent segment, and is discarded during replay.
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use data_types::{Sequence, SequenceNumber, ShardId, ShardIndex};
+use dml::DmlOperation;
+use iox_time::Time;
+use observability_deps::tracing::*;
+use parking_lot::Mutex;
+use snafu::{ResultExt, Snafu};
+use write_buffer::codec::{decode, encode_operation, ContentType, IoxHeaders};
+
+/// Segments larger than this are closed, and a new segment started.
+pub const SEGMENT_SIZE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// The size of the length and CRC prefix of each record.
+const RECORD_HEADER_BYTES: usize = 8;
+
+/// The file extension of segment files.
+const SEGMENT_EXTENSION: &str = "wal";
+
+/// The name of the marker file holding the sequence number the log starts at.
+const START_MARKER: &str = "start";
+
+#[derive(Debug, Snafu)]
+#[allow(missing_copy_implementations, missing_docs)]
+pub enum Error {
+    #[snafu(display("WAL I/O error for {}: {}", path.display(), source))]
+    Io { source: io::Error, path: PathBuf },
+
+    #[snafu(display("unable to encode operation for the WAL: {}", source))]
+    Encode {
+        source: write_buffer::core::WriteBufferError,
+    },
+
+    #[snafu(display("corrupt WAL record in {} at offset {}: {}", path.display(), offset, reason))]
+    Corrupt {
+        path: PathBuf,
+        offset: u64,
+        reason: String,
+    },
+
+    #[snafu(display("unable to decode WAL record: {}", reason))]
+    Decode { reason: String },
+
+    #[snafu(display("operation appended to the WAL has no sequence number"))]
+    Unsequenced,
+
+    #[snafu(display("WAL task failed: {}", source))]
+    Task { source: tokio::task::JoinError },
+}
+
+impl Error {
+    /// Returns true if retrying the failed operation may succeed, such as
+    /// after an I/O error caused by a full disk.
+    pub(crate) fn is_transient(&self) -> bool {
+        matches!(self, Self::Io { .. } | Self::Task { .. })
+    }
+}
+
+/// A specialized `Error` for WAL errors
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A write-ahead log of the operations buffered for each shard, stored under
+/// a local directory.
+#[derive(Debug)]
+pub struct Wal {
+    dir: PathBuf,
+    shards: Mutex<BTreeMap<ShardId, Arc<Mutex<ShardLog>>>>,
+}
+
+impl Wal {
+    /// Store the WAL of each shard under `dir`, which is created if it does
+    /// not exist.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            shards: Default::default(),
+        }
+    }
+
+    /// Returns the log of `shard_id`, loading the existing segments from disk
+    /// on first use.
+    fn shard(&self, shard_id: ShardId) -> Arc<Mutex<ShardLog>> {
+        let mut shards = self.shards.lock();
+        Arc::clone(
+            shards
+                .entry(shard_id)
+                .or_insert_with(|| Arc::new(Mutex::new(ShardLog::new(&self.dir, shard_id)))),
+        )
+    }
+
+    /// Append `op` to the log of `shard_id`, returning once it has been
+    /// synced to disk.
+    pub(crate) async fn append(&self, shard_id: ShardId, op: &DmlOperation) -> Result<()> {
+        let sequence_number = op
+            .meta()
+            .sequence()
+            .ok_or(Error::Unsequenced)?
+            .sequence_number;
+        let record = encode_record(sequence_number, op)?;
+
+        let log = self.shard(shard_id);
+        tokio::task::spawn_blocking(move || log.lock().append(sequence_number, &record))
+            .await
+            .context(TaskSnafu)?
+    }
+
+    /// Returns the operations in the log of `shard_id` with a sequence number
+    /// of at least `from`, in the order they were appended.
+    ///
+    /// Returns [`None`] if the log is not known to contain every operation
+    /// from `from`, in which case the log is discarded and restarted from
+    /// `from`.
+    pub(crate) async fn replay(
+        &self,
+        shard_id: ShardId,
+        shard_index: ShardIndex,
+        from: SequenceNumber,
+    ) -> Result<Option<Vec<DmlOperation>>> {
+        let log = self.shard(shard_id);
+        tokio::task::spawn_blocking(move || {
+            let mut log = log.lock();
+
+            match log.start()? {
+                Some(start) if start <= from => {}
+                start => {
+                    warn!(
+                        %shard_id,
+                        ?start,
+                        replay_from=?from,
+                        "WAL does not cover unpersisted data, discarding"
+                    );
+                    log.reset(from)?;
+                    return Ok(None);
+                }
+            }
+
+            let ops = log
+                .read()?
+                .into_iter()
+                .filter(|(sequence_number, _)| *sequence_number >= from)
+                .map(|(_, body)| decode_record(shard_index, &body))
+                .collect::<Result<Vec<_>>>()?;
+            Ok(Some(ops))
+        })
+        .await
+        .context(TaskSnafu)?
+    }
+
+    /// Delete the segments of the log of `shard_id` that contain only
+    /// operations below `min_unpersisted`, as they have all been persisted.
+    pub(crate) async fn truncate(
+        &self,
+        shard_id: ShardId,
+        min_unpersisted: SequenceNumber,
+    ) -> Result<()> {
+        let log = self.shard(shard_id);
+        tokio::task::spawn_blocking(move || log.lock().truncate(min_unpersisted))
+            .await
+            .context(TaskSnafu)?
+    }
+}
+
+/// The log of a single shard.
+#[derive(Debug)]
+struct ShardLog {
+    dir: PathBuf,
+
+    /// The segments of the log keyed by the sequence number of their first
+    /// record, loaded from disk on first use.
+    segments: Option<BTreeMap<SequenceNumber, PathBuf>>,
+
+    /// The most recent segment, open for appending, and its size.
+    current: Option<(File, u64)>,
+}
+
+impl ShardLog {
+    fn new(root: &Path, shard_id: ShardId) -> Self {
+        Self {
+            dir: root.join(shard_id.to_string()),
+            segments: None,
+            current: None,
+        }
+    }
+
+    /// Returns the segments of the log, listing the shard directory on first
+    /// use.
+    fn segments(&mut self) -> Result<&mut BTreeMap<SequenceNumber, PathBuf>> {
+        if self.segments.is_none() {
+            let mut segments = BTreeMap::new();
+            let entries = match fs::read_dir(&self.dir) {
+                Ok(v) => Some(v),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+                Err(source) => {
+                    return Err(Error::Io {
+                        source,
+                        path: self.dir.clone(),
+                    })
+                }
+            };
+
+            for entry in entries.into_iter().flatten() {
+                let path = entry.context(IoSnafu { path: &self.dir })?.path();
+                if path.extension().and_then(|v| v.to_str()) != Some(SEGMENT_EXTENSION) {
+                    continue;
+                }
+                let first = path
+                    .file_stem()
+                    .and_then(|v| v.to_str())
+                    .and_then(|v| v.parse().ok())
+                    .map(SequenceNumber::new);
+                match first {
+                    Some(first) => {
+                        segments.insert(first, path);
+                    }
+                    None => warn!(path=%path.display(), "ignoring unrecognised WAL file"),
+                }
+            }
+
+            self.segments = Some(segments);
+        }
+
+        Ok(self.segments.as_mut().expect("segments loaded"))
+    }
+
+    /// Returns the sequence number from which the log contains every
+    /// operation, if known.
+    fn start(&self) -> Result<Option<SequenceNumber>> {
+        let path = self.dir.join(START_MARKER);
+        match fs::read_to_string(&path) {
+            Ok(v) => Ok(v.trim().parse().ok().map(SequenceNumber::new)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(source) => Err(Error::Io { source, path }),
+        }
+    }
+
+    /// Delete every segment, and restart the log from `start`.
+    fn reset(&mut self, start: SequenceNumber) -> Result<()> {
+        self.current = None;
+        let segments = std::mem::take(self.segments()?);
+        for path in segments.into_values() {
+            fs::remove_file(&path).context(IoSnafu { path })?;
+        }
+
+        self.set_start(start)
+    }
+
+    /// Atomically replace the start marker of the log with `start`.
+    fn set_start(&self, start: SequenceNumber) -> Result<()> {
+        fs::create_dir_all(&self.dir).context(IoSnafu { path: &self.dir })?;
+        let path = self.dir.join(START_MARKER);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, start.get().to_string()).context(IoSnafu { path: &tmp })?;
+        fs::rename(&tmp, &path).context(IoSnafu { path })?;
+        sync_dir(&self.dir)
+    }
+
+    fn append(&mut self, sequence_number: SequenceNumber, record: &[u8]) -> Result<()> {
+        let rotate = match &self.current {
+            Some((_, size)) => *size >= SEGMENT_SIZE_BYTES,
+            None => true,
+        };
+        if rotate {
+            // Once the current segment is full (or on the first append since
+            // startup), start a new segment rather than appending to one that
+            // may end with a torn record.
+            //
+            // Sequence numbers only increase, so an existing segment with the
+            // same name can only hold a torn record that was discarded during
+            // replay, and is overwritten.
+            fs::create_dir_all(&self.dir).context(IoSnafu { path: &self.dir })?;
+            let path = self.dir.join(format!(
+                "{:020}.{}",
+                sequence_number.get(),
+                SEGMENT_EXTENSION
+            ));
+            let file = OpenOptions::new()
+                .create(true)
+                .write(true)
+                .truncate(true)
+                .open(&path)
+                .context(IoSnafu { path: &path })?;
+            sync_dir(&self.dir)?;
+
+            self.segments()?.insert(sequence_number, path);
+            self.current = Some((file, 0));
+        }
+
+        let (file, size) = self.current.as_mut().expect("segment is open");
+        let path = &self.dir;
+        let res = file
+            .write_all(record)
+            .and_then(|_| file.sync_data())
+            .context(IoSnafu { path });
+        match res {
+            Ok(()) => *size += record.len() as u64,
+            Err(e) => {
+                // Remove any partially written record so the segment does not
+                // end with a torn record followed by the retried append.
+                if let Err(truncate_err) = file.set_len(*size) {
+                    warn!(
+                        error=%truncate_err,
+                        path=%self.dir.display(),
+                        "failed to remove partial WAL record"
+                    );
+                }
+                // Start a new segment for the next append, rather than
+                // writing at the current file position.
+                self.current = None;
+                return Err(e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read every record in the log, returning the sequence number and body
+    /// of each.
+    fn read(&mut self) -> Result<Vec<(SequenceNumber, Vec<u8>)>> {
+        let segments: Vec<_> = self.segments()?.values().cloned().collect();
+        let mut records = vec![];
+        for (i, path) in segments.iter().enumerate() {
+            let is_last = i == segments.len() - 1;
+            read_segment(path, is_last, &mut records)?;
+        }
+        Ok(records)
+    }
+
+    fn truncate(&mut self, min_unpersisted: SequenceNumber) -> Result<()> {
+        // A segment only contains records below `min_unpersisted` if the
+        // segment after it starts at or below `min_unpersisted`. The most
+        // recent segment is never deleted.
+        let firsts: Vec<_> = self.segments()?.keys().copied().collect();
+        let obsolete: Vec<_> = firsts
+            .windows(2)
+            .filter(|w| w[1] <= min_unpersisted)
+            .map(|w| w[0])
+            .collect();
+        if obsolete.is_empty() {
+            return Ok(());
+        }
+
+        // Advance the start marker before removing any segment, so that the
+        // marker never claims the log holds operations it has discarded.
+        if matches!(self.start()?, Some(start) if start < min_unpersisted) {
+            self.set_start(min_unpersisted)?;
+        }
+
+        let segments = self.segments()?;
+        for first in obsolete {
+            let path = segments.remove(&first).expect("segment exists");
+            debug!(path=%path.display(), ?min_unpersisted, "deleting persisted WAL segment");
+            fs::remove_file(&path).context(IoSnafu { path })?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Read the records of the segment at `path` into `records`.
+///
+/// A truncated or corrupt record at the end of the `last` segment is the
+/// result of a crash part way through an append, and is discarded by
+/// truncating the segment to the end of the preceding record. Appends always
+/// start a new segment, so once discarded, the torn record can never be
+/// followed by another.
+fn read_segment(
+    path: &Path,
+    last: bool,
+    records: &mut Vec<(SequenceNumber, Vec<u8>)>,
+) -> Result<()> {
+    let mut data = vec![];
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut data))
+        .context(IoSnafu { path })?;
+
+    let mut offset = 0;
+    while offset < data.len() {
+        let corrupt = |reason: &str| Error::Corrupt {
+            path: path.to_owned(),
+            offset: offset as u64,
+            reason: reason.to_string(),
+        };
+
+        let record = parse_record(&data[offset..]);
+        let (body, len) = match record {
+            Ok(v) => v,
+            Err(reason) if last => {
+                warn!(
+                    path=%path.display(),
+                    offset,
+                    reason,
+                    "discarding torn record at end of WAL"
+                );
+                OpenOptions::new()
+                    .write(true)
+                    .open(path)
+                    .and_then(|f| f.set_len(offset as u64).and_then(|_| f.sync_all()))
+                    .context(IoSnafu { path })?;
+                break;
+            }
+            Err(reason) => return Err(corrupt(reason)),
+        };
+
+        if body.len() < 8 {
+            return Err(corrupt("record too short"));
+        }
+        let sequence_number =
+            SequenceNumber::new(i64::from_le_bytes(body[..8].try_into().expect("8 bytes")));
+        records.push((sequence_number, body.to_vec()));
+        offset += len;
+    }
+
+    Ok(())
+}
+
+/// Parse the record at the start of `data`, returning its body and the total
+/// length of the record.
+fn parse_record(data: &[u8]) -> Result<(&[u8], usize), &'static str> {
+    if data.len() < RECORD_HEADER_BYTES {
+        return Err("truncated record header");
+    }
+    let len = u32::from_le_bytes(data[..4].try_into().expect("4 bytes")) as usize;
+    let crc = u32::from_le_bytes(data[4..8].try_into().expect("4 bytes"));
+
+    let body = data
+        .get(RECORD_HEADER_BYTES..RECORD_HEADER_BYTES + len)
+        .ok_or("truncated record body")?;
+    if crc32fast::hash(body) != crc {
+        return Err("record checksum mismatch");
+    }
+
+    Ok((body, RECORD_HEADER_BYTES + len))
+}
+
+/// Encode `op` as a framed WAL record.
+fn encode_record(sequence_number: SequenceNumber, op: &DmlOperation) -> Result<Vec<u8>> {
+    let namespace = op.namespace().as_bytes();
+    let producer_ts = op
+        .meta()
+        .producer_ts()
+        .map(|t| t.timestamp_nanos())
+        .unwrap_or_default();
+
+    let mut body = Vec::with_capacity(20 + namespace.len());
+    body.extend_from_slice(&sequence_number.get().to_le_bytes());
+    body.extend_from_slice(&producer_ts.to_le_bytes());
+    body.extend_from_slice(&(namespace.len() as u32).to_le_bytes());
+    body.extend_from_slice(namespace);
+    encode_operation(op.namespace(), op, &mut body).context(EncodeSnafu)?;
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_BYTES + body.len());
+    record.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    record.extend_from_slice(&crc32fast::hash(&body).to_le_bytes());
+    record.extend_from_slice(&body);
+    Ok(record)
+}
+
+/// Decode the operation in a WAL record body.
+fn decode_record(shard_index: ShardIndex, body: &[u8]) -> Result<DmlOperation> {
+    let corrupt = |reason: String| Error::Decode { reason };
+    let field = |range: std::ops::Range<usize>| {
+        body.get(range)
+            .ok_or_else(|| corrupt("record too short".to_string()))
+    };
+
+    let sequence_number = i64::from_le_bytes(field(0..8)?.try_into().expect("8 bytes"));
+    let producer_ts = i64::from_le_bytes(field(8..16)?.try_into().expect("8 bytes"));
+    let namespace_len = u32::from_le_bytes(field(16..20)?.try_into().expect("4 bytes")) as usize;
+    let namespace =
+        std::str::from_utf8(field(20..20 + namespace_len)?).map_err(|e| corrupt(e.to_string()))?;
+    let payload = &body[20 + namespace_len..];
+
+    decode(
+        payload,
+        IoxHeaders::new(ContentType::Protobuf, None, namespace.to_string()),
+        Sequence::new(shard_index, SequenceNumber::new(sequence_number)),
+        Time::from_timestamp_nanos(producer_ts),
+        payload.len(),
+    )
+    .map_err(|e| corrupt(e.to_string()))
+}
+
+/// Sync the directory entries of `dir`, making created, renamed and deleted
+/// files durable.
+fn sync_dir(dir: &Path) -> Result<()> {
+    File::open(dir)
+        .and_then(|f| f.sync_all())
+        .context(IoSnafu { path: dir })
+}
+
+#[cfg(test)]
+mod tests {
+    use data_types::PartitionKey;
+    use dml::{DmlMeta, DmlWrite};
+    use mutable_batch_lp::lines_to_batches;
+
+    use super::*;
+
+    const SHARD_ID: ShardId = ShardId::new(1);
+    const SHARD_INDEX: ShardIndex = ShardIndex::new(0);
+
+    fn make_write(sequence_number: i64, lp: &str) -> DmlOperation {
+        DmlOperation::Write(DmlWrite::new(
+            "bananas",
+            lines_to_batches(lp, 0).unwrap(),
+            Some(PartitionKey::from("1970-01-01")),
+            DmlMeta::sequenced(
+                Sequence::new(SHARD_INDEX, SequenceNumber::new(sequence_number)),
+                Time::from_timestamp_nanos(42),
+                None,
+                50,
+            ),
+        ))
+    }
+
+    fn sequence_numbers(ops: &[DmlOperation]) -> Vec<i64> {
+        ops.iter()
+            .map(|op| op.meta().sequence().unwrap().sequence_number.get())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_append_replay() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let wal = Wal::new(dir.path());
+        // Nothing to replay without a start marker.
+        assert!(wal
+            .replay(SHARD_ID, SHARD_INDEX, SequenceNumber::new(1))
+            .await
+            .unwrap()
+            .is_none());
+
+        for n in 1..=3 {
+            wal.append(SHARD_ID, &make_write(n, &format!("cpu v={n} 10")))
+                .await
+                .unwrap();
+        }
+
+        // A new instance (as after a restart) replays the appended operations
+        // from the requested sequence number.
+        let wal = Wal::new(dir.path());
+        let ops = wal
+            .replay(SHARD_ID, SHARD_INDEX, SequenceNumber::new(2))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sequence_numbers(&ops), [2, 3]);
+
+        let write = match &ops[0] {
+            DmlOperation::Write(w) => w,
+            _ => panic!("expected write"),
+        };
+        assert_eq!(write.namespace(), "bananas");
+        assert_eq!(write.table_count(), 1);
+        assert_eq!(
+            write.meta().producer_ts(),
+            Some(Time::from_timestamp_nanos(42))
+        );
+
+        // The log does not cover data from before it started.
+        assert!(wal
+            .replay(SHARD_ID, SHARD_INDEX, SequenceNumber::new(0))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_torn_record_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let wal = Wal::new(dir.path());
+        wal.replay(SHARD_ID, SHARD_INDEX, SequenceNumber::new(1))
+            .await
+            .unwrap();
+        wal.append(SHARD_ID, &make_write(1, "cpu v=1 10"))
+            .await
+            .unwrap();
+        wal.append(SHARD_ID, &make_write(2, "cpu v=2 10"))
+            .await
+            .unwrap();
+
+        // Simulate a crash part way through writing the last record.
+        let segment = dir
+            .path()
+            .join(SHARD_ID.to_string())
+            .join(format!("{:020}.wal", 1));
+        let len = fs::metadata(&segment).unwrap().len();
+        OpenOptions::new()
+            .write(true)
+            .open(&segment)
+            .unwrap()
+            .set_len(len - 3)
+            .unwrap();
+
+        let wal = Wal::new(dir.path());
+        let ops = wal
+            .replay(SHARD_ID, SHARD_INDEX, SequenceNumber::new(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sequence_numbers(&ops), [1]);
+
+        // Subsequent appends go to a new segment, and are replayed after the
+        // torn segment.
+        wal.append(SHARD_ID, &make_write(2, "cpu v=2 10"))
+            .await
+            .unwrap();
+        let ops = Wal::new(dir.path())
+            .replay(SHARD_ID, SHARD_INDEX, SequenceNumber::new(1))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sequence_numbers(&ops), [1, 2]);
+    }
+
+    #[test]
+    fn test_truncate() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = ShardLog::new(dir.path(), SHARD_ID);
+        log.reset(SequenceNumber::new(1)).unwrap();
+
+        // Start a new segment for each append.
+        for n in [1, 5, 9] {
+            let record = encode_record(
+                SequenceNumber::new(n),
+                &make_write(n, &format!("cpu v={n} 10")),
+            )
+            .unwrap();
+            log.current = None;
+            log.append(SequenceNumber::new(n), &record).unwrap();
+        }
+
+        // The segment starting at 5 may still contain unpersisted data.
+        log.truncate(SequenceNumber::new(7)).unwrap();
+        let firsts: Vec<_> = log.segments().unwrap().keys().map(|v| v.get()).collect();
+        assert_eq!(firsts, [5, 9]);
+
+        // The most recent segment is never removed.
+        log.truncate(SequenceNumber::new(100)).unwrap();
+        let firsts: Vec<_> = log.segments().unwrap().keys().map(|v| v.get()).collect();
+        assert_eq!(firsts, [9]);
+
+        let records = log.read().unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].0, SequenceNumber::new(9));
+    }
+
+    #[test]
+    fn test_corrupt_record_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut log = ShardLog::new(dir.path(), SHARD_ID);
+        log.reset(SequenceNumber::new(1)).unwrap();
+
+        for n in [1, 2] {
+            let record =
+                encode_record(SequenceNumber::new(n), &make_write(n, "cpu v=1 10")).unwrap();
+            log.current = None;
+            log.append(SequenceNumber::new(n), &record).unwrap();
+        }
+
+        // Corruption of a segment other than the last is an error.
+        let path = log.segments().unwrap()[&SequenceNumber::new(1)].clone();
+        let mut data = fs::read(&path).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 0xFF;
+        fs::write(&path, data).unwrap();
+
+        assert!(matches!(log.read(), Err(Error::Corrupt { .. })));
+    }
+}
@@ -1,4 +1,10 @@
-use std::{collections::HashMap, num::NonZeroU32, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use data_types::{
     Namespace, NamespaceSchema, PartitionKey, QueryPoolId, Sequence, SequenceNumber, ShardId,
@@ -9,7 +15,9 @@ use generated_types::ingester::IngesterQueryRequest;
 use ingester::{
     handler::{IngestHandler, IngestHandlerImpl},
     lifecycle::LifecycleConfig,
+    persist::PersistConfig,
     querier_handler::IngesterQueryResponse,
+    wal::Wal,
 };
 use iox_catalog::{interface::Catalog, mem::MemCatalog, validate_or_insert_schema};
 use iox_query::exec::Executor;
@@ -60,6 +68,9 @@ pub struct TestContext {
     object_store: Arc<DynObjectStore>,
     write_buffer_state: MockBufferSharedState,
     metrics: Arc<metric::Registry>,
+
+    // The WAL directory used by the ingester, if any.
+    wal_dir: Option<PathBuf>,
 }
 
 impl TestContext {
@@ -99,6 +110,8 @@ impl TestContext {
 
         let ingester = IngestHandlerImpl::new(
             TEST_LIFECYCLE_CONFIG,
+            PersistConfig::default(),
+            None,
             topic.clone(),
             [(TEST_SHARD_INDEX, shard)].into_iter().collect(),
             Arc::clone(&catalog),
@@ -122,6 +135,7 @@ impl TestContext {
             write_buffer_state,
             metrics,
             namespaces: Default::default(),
+            wal_dir: None,
         }
     }
 
@@ -131,6 +145,11 @@ impl TestContext {
     pub async fn restart(&mut self) {
         info!("restarting test context ingester");
 
+        // Stop the running ingester, so it does not continue to consume from
+        // the write buffer (or append to the WAL) alongside its replacement.
+        self.ingester.shutdown();
+        self.ingester.join().await;
+
         let write_buffer_read: Arc<dyn WriteBufferReading> =
             Arc::new(MockBufferForReading::new(self.write_buffer_state.clone(), None).unwrap());
 
@@ -154,6 +173,8 @@ impl TestContext {
 
         self.ingester = IngestHandlerImpl::new(
             TEST_LIFECYCLE_CONFIG,
+            PersistConfig::default(),
+            self.wal_dir.as_ref().map(Wal::new),
             topic,
             [(TEST_SHARD_INDEX, shard)].into_iter().collect(),
             Arc::clone(&self.catalog),
@@ -168,6 +189,12 @@ impl TestContext {
         .unwrap();
     }
 
+    /// Restart the Ingester with a WAL stored in `dir`.
+    pub async fn enable_wal(&mut self, dir: &Path) {
+        self.wal_dir = Some(dir.to_owned());
+        self.restart().await;
+    }
+
     /// Create a namespace in the catalog for the ingester to discover.
     ///
     /// # Panics
@@ -275,6 +302,11 @@ impl TestContext {
         .await
     }
 
+    /// Remove all ops from the write buffer, simulating their expiry.
+    pub fn clear_write_buffer(&self) {
+        self.write_buffer_state.clear_messages(TEST_SHARD_INDEX);
+    }
+
     /// Utilise the progress API to query for the current state of the test
     /// shard.
     pub async fn progress(&self) -> ShardProgress {
@@ -317,6 +349,19 @@ impl TestContext {
         self.ingester.query(req, None).await
     }
 
+    /// Persist the specified partition through the ingester's public persist
+    /// interface, returning once it has been persisted.
+    pub async fn persist_partition(
+        &self,
+        namespace: &str,
+        table: &str,
+        partition_key: &PartitionKey,
+    ) -> Result<bool, ingester::data::Error> {
+        self.ingester
+            .persist_partition(namespace, table, partition_key)
+            .await
+    }
+
     /// Retrieve the specified metric value.
     pub fn get_metric<T, A>(&self, name: &'static str, attrs: A) -> T::Recorder
     where
@@ -419,3 +419,148 @@ async fn test_seek_dropped_offset() {
         .fetch();
     assert!(metric > 0);
 }
+
+// Write data to an ingester, then explicitly persist the partition it was
+// buffered in, long before any persistence threshold is reached.
+#[tokio::test]
+async fn test_persist_partition() {
+    let mut ctx = TestContext::new().await;
+
+    let ns = ctx.ensure_namespace("test_namespace").await;
+
+    let partition_key = PartitionKey::from("1970-01-01");
+    let offset = ctx
+        .write_lp(
+            "test_namespace",
+            "bananas greatness=\"unbounded\" 10",
+            partition_key.clone(),
+            0,
+        )
+        .await;
+    ctx.wait_for_readable(offset).await;
+
+    // Unknown partitions are rejected.
+    assert_matches!(
+        ctx.persist_partition(
+            "test_namespace",
+            "bananas",
+            &PartitionKey::from("2022-01-01")
+        )
+        .await,
+        Err(ingester::data::Error::PartitionNotFound { .. })
+    );
+    assert_matches!(
+        ctx.persist_partition("test_namespace", "platanos", &partition_key)
+            .await,
+        Err(ingester::data::Error::TableNotFound { .. })
+    );
+
+    let persisted = ctx
+        .persist_partition("test_namespace", "bananas", &partition_key)
+        .await
+        .expect("persist should succeed");
+    assert!(persisted);
+
+    // The buffered data was written to a parquet file.
+    let partitions = ctx
+        .catalog()
+        .repositories()
+        .await
+        .partitions()
+        .list_by_namespace(ns.id)
+        .await
+        .unwrap();
+    assert_matches!(&*partitions, &[Partition { .. }]);
+    let files = ctx
+        .catalog()
+        .repositories()
+        .await
+        .parquet_files()
+        .list_by_partition_not_to_delete(partitions[0].id)
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+
+    // With nothing left buffered, a subsequent request persists nothing.
+    let persisted = ctx
+        .persist_partition("test_namespace", "bananas", &partition_key)
+        .await
+        .expect("persist should succeed");
+    assert!(!persisted);
+
+    let requests = ctx
+        .get_metric::<U64Counter, _>(
+            "ingester_lifecycle_persist_count",
+            &[("trigger", "request")],
+        )
+        .fetch();
+    assert_eq!(requests, 1);
+}
+
+// Ensure an ingester configured with a WAL recovers the ops buffered before a
+// restart from the WAL, reading only newer ops from the write buffer.
+#[tokio::test]
+async fn test_wal_replay() {
+    let wal_dir = tempfile::tempdir().unwrap();
+
+    let mut ctx = TestContext::new().await;
+    ctx.enable_wal(wal_dir.path()).await;
+
+    ctx.ensure_namespace("test_namespace").await;
+    let partition_key = PartitionKey::from("1970-01-01");
+    ctx.write_lp(
+        "test_namespace",
+        "bananas greatness=\"unbounded\" 10",
+        partition_key.clone(),
+        0,
+    )
+    .await;
+    let w2 = ctx
+        .write_lp(
+            "test_namespace",
+            "bananas greatness=\"amazing\" 20",
+            partition_key.clone(),
+            1,
+        )
+        .await;
+    ctx.wait_for_readable(w2).await;
+
+    // Drop the buffered ops from the write buffer, so that they can only be
+    // recovered from the WAL, and add a new op that is not in the WAL.
+    ctx.clear_write_buffer();
+    let w3 = ctx
+        .write_lp(
+            "test_namespace",
+            "bananas greatness=\"fabulous\" 30",
+            partition_key,
+            2,
+        )
+        .await;
+
+    // Restart the ingester.
+    ctx.restart().await;
+    ctx.wait_for_readable(w3).await;
+
+    let data = ctx
+        .query(IngesterQueryRequest {
+            namespace: "test_namespace".to_string(),
+            table: "bananas".to_string(),
+            columns: vec![],
+            predicate: None,
+        })
+        .await
+        .expect("query should succeed")
+        .into_record_batches()
+        .await;
+
+    let expected = vec![
+        "+-----------+--------------------------------+",
+        "| greatness | time                           |",
+        "+-----------+--------------------------------+",
+        "| amazing   | 1970-01-01T00:00:00.000000020Z |",
+        "| fabulous  | 1970-01-01T00:00:00.000000030Z |",
+        "| unbounded | 1970-01-01T00:00:00.000000010Z |",
+        "+-----------+--------------------------------+",
+    ];
+    assert_batches_sorted_eq!(&expected, &data);
+}
@@ -0,0 +1,5 @@
+-- Add an explicit retention period (in nanoseconds, NULL for infinite
+-- retention) and a soft-deletion timestamp to namespaces.
+ALTER TABLE IF EXISTS namespace
+    ADD COLUMN IF NOT EXISTS retention_period_ns BIGINT DEFAULT NULL,
+    ADD COLUMN IF NOT EXISTS deleted_at BIGINT DEFAULT NULL;
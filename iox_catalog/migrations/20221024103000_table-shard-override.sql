@@ -0,0 +1,8 @@
+-- Allow (namespace, table) pairs to be pinned to a specific shard index,
+-- overriding the router's sharder.
+CREATE TABLE IF NOT EXISTS table_shard_override (
+    namespace_id BIGINT NOT NULL REFERENCES namespace (id) ON DELETE CASCADE,
+    table_name VARCHAR NOT NULL,
+    shard_index INT NOT NULL,
+    PRIMARY KEY (namespace_id, table_name)
+);
@@ -0,0 +1,5 @@
+-- Add an optional per-namespace partition template, stored as an array of
+-- template parts (e.g. {"tag:region", "time:%Y-%m-%d"}). NULL uses the
+-- default template configured in the router.
+ALTER TABLE IF EXISTS namespace
+    ADD COLUMN IF NOT EXISTS partition_template TEXT[] DEFAULT NULL;
@@ -0,0 +1,10 @@
+-- Record the number of lines and bytes written to each namespace per
+-- accounting period, for usage based billing.
+CREATE TABLE IF NOT EXISTS namespace_usage (
+    namespace_id BIGINT NOT NULL REFERENCES namespace (id) ON DELETE CASCADE,
+    period_start BIGINT NOT NULL,
+    period_end BIGINT NOT NULL,
+    lines BIGINT NOT NULL,
+    bytes BIGINT NOT NULL,
+    PRIMARY KEY (namespace_id, period_start)
+);
@@ -0,0 +1,6 @@
+-- Persist the set of shards in the consistent hash ring used by the routers
+-- writing to a topic, so that all routers apply the same membership.
+CREATE TABLE IF NOT EXISTS shard_ring (
+    topic_id BIGINT NOT NULL PRIMARY KEY REFERENCES topic (id) ON DELETE CASCADE,
+    shard_indexes INT[] NOT NULL
+);
@@ -3,10 +3,11 @@
 use async_trait::async_trait;
 use data_types::{
     Column, ColumnSchema, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId,
-    NamespaceSchema, ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId,
-    PartitionKey, PartitionParam, ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber,
-    Shard, ShardId, ShardIndex, SkippedCompaction, Table, TableId, TablePartition, TableSchema,
-    Timestamp, Tombstone, TombstoneId, TopicId, TopicMetadata,
+    NamespaceSchema, NamespaceUsage, ParquetFile, ParquetFileId, ParquetFileParams, Partition,
+    PartitionId, PartitionKey, PartitionParam, PartitionTemplate, ProcessedTombstone, QueryPool,
+    QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex, SkippedCompaction, Table, TableId,
+    TablePartition, TableSchema, TableShardOverride, Timestamp, TimestampMinMax, Tombstone,
+    TombstoneId, TopicId, TopicMetadata,
 };
 use iox_time::TimeProvider;
 use snafu::{OptionExt, Snafu};
@@ -129,6 +130,45 @@ pub enum Error {
 
     #[snafu(display("could not delete skipped compactions: {source}"))]
     CouldNotDeleteSkippedCompactions { source: sqlx::Error },
+
+    #[snafu(display(
+        "could not set shard override for table {table_name} in namespace {namespace_id}: {source}"
+    ))]
+    CouldNotSetTableShardOverride {
+        source: sqlx::Error,
+        namespace_id: NamespaceId,
+        table_name: String,
+    },
+
+    #[snafu(display("could not list table shard overrides: {source}"))]
+    CouldNotListTableShardOverrides { source: sqlx::Error },
+
+    #[snafu(display("could not delete table shard override: {source}"))]
+    CouldNotDeleteTableShardOverride { source: sqlx::Error },
+
+    #[snafu(display("could not set shard ring members for topic {topic_id}: {source}"))]
+    CouldNotSetShardRingMembers {
+        source: sqlx::Error,
+        topic_id: TopicId,
+    },
+
+    #[snafu(display("could not get shard ring members for topic {topic_id}: {source}"))]
+    CouldNotGetShardRingMembers {
+        source: sqlx::Error,
+        topic_id: TopicId,
+    },
+
+    #[snafu(display("could not record usage for namespace {namespace_id}: {source}"))]
+    CouldNotRecordNamespaceUsage {
+        source: sqlx::Error,
+        namespace_id: NamespaceId,
+    },
+
+    #[snafu(display("could not list usage for namespace {namespace_id}: {source}"))]
+    CouldNotListNamespaceUsage {
+        source: sqlx::Error,
+        namespace_id: NamespaceId,
+    },
 }
 
 /// A specialized `Error` for Catalog errors
@@ -295,7 +335,7 @@ pub trait NamespaceRepo: Send + Sync {
         query_pool_id: QueryPoolId,
     ) -> Result<Namespace>;
 
-    /// List all namespaces.
+    /// List all namespaces that have not been soft-deleted.
     async fn list(&mut self) -> Result<Vec<Namespace>>;
 
     /// Gets the namespace by its ID.
@@ -309,6 +349,40 @@ pub trait NamespaceRepo: Send + Sync {
 
     /// Update the limit on the number of columns that can exist per table in a given namespace.
     async fn update_column_limit(&mut self, name: &str, new_max: i32) -> Result<Namespace>;
+
+    /// Update the retention period of the namespace, in nanoseconds. `None` represents infinite
+    /// retention.
+    async fn update_retention_period(
+        &mut self,
+        name: &str,
+        retention_period_ns: Option<i64>,
+    ) -> Result<Namespace>;
+
+    /// Update the template used to partition writes to the namespace. `None` represents the
+    /// default template configured in the router.
+    async fn update_partition_template(
+        &mut self,
+        name: &str,
+        partition_template: Option<PartitionTemplate>,
+    ) -> Result<Namespace>;
+
+    /// Mark the namespace as deleted, excluding it from [`NamespaceRepo::list()`]. The namespace
+    /// and its data are not removed from the catalog.
+    async fn soft_delete(&mut self, name: &str) -> Result<()>;
+
+    /// Add `lines` and `bytes` to the usage recorded for the namespace in the period starting at
+    /// `period_start`, creating the record if it does not exist, and returning the updated record.
+    async fn record_usage(
+        &mut self,
+        namespace_id: NamespaceId,
+        period_start: Timestamp,
+        period_end: Timestamp,
+        lines: i64,
+        bytes: i64,
+    ) -> Result<NamespaceUsage>;
+
+    /// List the usage recorded for the namespace, ordered by period start.
+    async fn list_usage(&mut self, namespace_id: NamespaceId) -> Result<Vec<NamespaceUsage>>;
 }
 
 /// Functions for working with tables in the catalog
@@ -417,6 +491,38 @@ pub trait ShardRepo: Send + Sync {
         shard: ShardId,
         sequence_number: SequenceNumber,
     ) -> Result<()>;
+
+    /// pin the table named `table_name` in the given namespace to `shard_index`, replacing any
+    /// existing override for the table
+    async fn set_table_override(
+        &mut self,
+        namespace_id: NamespaceId,
+        table_name: &str,
+        shard_index: ShardIndex,
+    ) -> Result<TableShardOverride>;
+
+    /// list all table shard overrides
+    async fn list_table_overrides(&mut self) -> Result<Vec<TableShardOverride>>;
+
+    /// remove the shard override for the table named `table_name` in the given namespace,
+    /// returning it if one existed
+    async fn delete_table_override(
+        &mut self,
+        namespace_id: NamespaceId,
+        table_name: &str,
+    ) -> Result<Option<TableShardOverride>>;
+
+    /// replace the shards in the consistent hash ring of the routers writing to `topic_id` with
+    /// `shard_indexes`
+    async fn set_ring_members(
+        &mut self,
+        topic_id: TopicId,
+        shard_indexes: &[ShardIndex],
+    ) -> Result<()>;
+
+    /// get the shards in the consistent hash ring of the routers writing to `topic_id`, or `None`
+    /// if they have never been set
+    async fn get_ring_members(&mut self, topic_id: TopicId) -> Result<Option<Vec<ShardIndex>>>;
 }
 
 /// Functions for working with IOx partitions in the catalog. Note that these are how IOx splits up
@@ -622,6 +728,25 @@ pub trait ParquetFileRepo: Send + Sync {
         partition_id: PartitionId,
     ) -> Result<Vec<ParquetFile>>;
 
+    /// List up to `limit` parquet files for a given partition that are NOT
+    /// marked as [`to_delete`](ParquetFile::to_delete) and have an ID greater
+    /// than `after` (if any), ordered by ID.
+    ///
+    /// If specified, only the files overlapping the inclusive `time_range` and
+    /// of the given `compaction_level` are returned.
+    ///
+    /// Callers page through all the files by passing the ID of the last file
+    /// returned as `after` in the next call, until fewer than `limit` files
+    /// are returned.
+    async fn list_by_partition_not_to_delete_paged(
+        &mut self,
+        partition_id: PartitionId,
+        time_range: Option<TimestampMinMax>,
+        compaction_level: Option<CompactionLevel>,
+        after: Option<ParquetFileId>,
+        limit: usize,
+    ) -> Result<Vec<ParquetFile>>;
+
     /// Update the compaction level of the specified parquet files to
     /// the specified [`CompactionLevel`].
     /// Returns the IDs of the files that were successfully updated.
@@ -726,12 +851,16 @@ where
     let columns = repos.columns().list_by_namespace_id(namespace.id).await?;
     let tables = repos.tables().list_by_namespace_id(namespace.id).await?;
 
+    let retention_period_ns = namespace.retention_period_ns;
+    let partition_template = namespace.partition_template;
     let mut namespace = NamespaceSchema::new(
         namespace.id,
         namespace.topic_id,
         namespace.query_pool_id,
         namespace.max_columns_per_table,
     );
+    namespace.retention_period_ns = retention_period_ns;
+    namespace.partition_template = partition_template;
 
     let mut table_id_to_schema = BTreeMap::new();
     for t in tables {
@@ -864,6 +993,8 @@ pub async fn list_schemas(
         .filter_map(move |v| {
             let mut ns =
                 NamespaceSchema::new(v.id, v.topic_id, v.query_pool_id, v.max_columns_per_table);
+            ns.retention_period_ns = v.retention_period_ns;
+            ns.partition_template = v.partition_template.clone();
             ns.tables = joined.remove(&v.id)?;
             Some((v, ns))
         });
@@ -877,7 +1008,7 @@ pub(crate) mod test_helpers {
 
     use super::*;
     use ::test_helpers::{assert_contains, tracing::TracingCapture};
-    use data_types::{ColumnId, ColumnSet, CompactionLevel};
+    use data_types::{ColumnId, ColumnSet, CompactionLevel, TemplatePart};
     use metric::{Attributes, DurationHistogram, Metric};
     use std::{
         ops::{Add, DerefMut},
@@ -1036,6 +1167,129 @@ pub(crate) mod test_helpers {
             .await
             .expect("namespace should be updateable");
         assert_eq!(NEW_COLUMN_LIMIT, modified.max_columns_per_table);
+
+        const NEW_RETENTION_PERIOD_NS: i64 = 5 * 60 * 60 * 1000 * 1000 * 1000;
+        let modified = repos
+            .namespaces()
+            .update_retention_period(namespace_name, Some(NEW_RETENTION_PERIOD_NS))
+            .await
+            .expect("namespace should be updateable");
+        assert_eq!(Some(NEW_RETENTION_PERIOD_NS), modified.retention_period_ns);
+
+        let modified = repos
+            .namespaces()
+            .update_retention_period(namespace_name, None)
+            .await
+            .expect("namespace should be updateable");
+        assert!(modified.retention_period_ns.is_none());
+
+        let err = repos
+            .namespaces()
+            .update_retention_period("does_not_exist", None)
+            .await
+            .expect_err("unknown namespace should not be updateable");
+        assert!(matches!(err, Error::NamespaceNotFoundByName { .. }));
+
+        let template = PartitionTemplate {
+            parts: vec![
+                TemplatePart::Column("region".to_string()),
+                TemplatePart::TimeFormat("%Y-%m-%d".to_string()),
+            ],
+        };
+        let modified = repos
+            .namespaces()
+            .update_partition_template(namespace_name, Some(template.clone()))
+            .await
+            .expect("namespace should be updateable");
+        assert_eq!(modified.partition_template.as_ref(), Some(&template));
+        let schema = get_schema_by_name(namespace_name, repos.deref_mut())
+            .await
+            .expect("schema should be readable");
+        assert_eq!(schema.partition_template, Some(template));
+
+        let modified = repos
+            .namespaces()
+            .update_partition_template(namespace_name, None)
+            .await
+            .expect("namespace should be updateable");
+        assert!(modified.partition_template.is_none());
+
+        let err = repos
+            .namespaces()
+            .update_partition_template("does_not_exist", None)
+            .await
+            .expect_err("unknown namespace should not be updateable");
+        assert!(matches!(err, Error::NamespaceNotFoundByName { .. }));
+
+        // A soft-deleted namespace is no longer listed, but can still be
+        // looked up.
+        repos
+            .namespaces()
+            .soft_delete(namespace2_name)
+            .await
+            .expect("namespace should be deletable");
+        let namespaces = repos.namespaces().list().await.unwrap();
+        assert_eq!(namespaces.len(), 1);
+        assert_eq!(namespaces[0].name, namespace_name);
+        let deleted = repos
+            .namespaces()
+            .get_by_name(namespace2_name)
+            .await
+            .unwrap()
+            .expect("namespace should be there");
+        assert!(deleted.deleted_at.is_some());
+
+        let err = repos
+            .namespaces()
+            .soft_delete("does_not_exist")
+            .await
+            .expect_err("unknown namespace should not be deletable");
+        assert!(matches!(err, Error::NamespaceNotFoundByName { .. }));
+
+        // Usage recorded for the same period is accumulated.
+        let usage = repos
+            .namespaces()
+            .list_usage(found.id)
+            .await
+            .expect("usage should be listable");
+        assert!(usage.is_empty());
+        for (period_start, lines, bytes) in [(20, 1, 10), (10, 2, 20), (10, 3, 30)] {
+            repos
+                .namespaces()
+                .record_usage(
+                    found.id,
+                    Timestamp::new(period_start),
+                    Timestamp::new(period_start + 10),
+                    lines,
+                    bytes,
+                )
+                .await
+                .expect("usage should be recordable");
+        }
+        let usage = repos
+            .namespaces()
+            .list_usage(found.id)
+            .await
+            .expect("usage should be listable");
+        assert_eq!(
+            usage,
+            vec![
+                NamespaceUsage {
+                    namespace_id: found.id,
+                    period_start: Timestamp::new(10),
+                    period_end: Timestamp::new(20),
+                    lines: 5,
+                    bytes: 50,
+                },
+                NamespaceUsage {
+                    namespace_id: found.id,
+                    period_start: Timestamp::new(20),
+                    period_end: Timestamp::new(30),
+                    lines: 1,
+                    bytes: 10,
+                },
+            ]
+        );
     }
 
     async fn test_table(catalog: Arc<dyn Catalog>) {
@@ -1393,6 +1647,94 @@ pub(crate) mod test_helpers {
             .await
             .unwrap();
         assert!(shard.is_none());
+
+        // table shard overrides
+        let pool = repos
+            .query_pools()
+            .create_or_get("shard_test")
+            .await
+            .unwrap();
+        let namespace = repos
+            .namespaces()
+            .create("namespace_shard_override_test", "inf", topic.id, pool.id)
+            .await
+            .unwrap();
+        let overrides = repos.shards().list_table_overrides().await.unwrap();
+        assert!(overrides.is_empty());
+
+        let bananas = repos
+            .shards()
+            .set_table_override(namespace.id, "bananas", ShardIndex::new(1))
+            .await
+            .unwrap();
+        assert_eq!(bananas.namespace_id, namespace.id);
+        assert_eq!(bananas.table_name, "bananas");
+        assert_eq!(bananas.shard_index, ShardIndex::new(1));
+
+        // setting the override again replaces it
+        let bananas = repos
+            .shards()
+            .set_table_override(namespace.id, "bananas", ShardIndex::new(2))
+            .await
+            .unwrap();
+        assert_eq!(bananas.shard_index, ShardIndex::new(2));
+        let platanos = repos
+            .shards()
+            .set_table_override(namespace.id, "platanos", ShardIndex::new(3))
+            .await
+            .unwrap();
+
+        let mut overrides = repos.shards().list_table_overrides().await.unwrap();
+        overrides.sort_by(|a, b| a.table_name.cmp(&b.table_name));
+        assert_eq!(overrides, vec![bananas.clone(), platanos]);
+
+        let deleted = repos
+            .shards()
+            .delete_table_override(namespace.id, "platanos")
+            .await
+            .unwrap();
+        assert_eq!(deleted.map(|v| v.shard_index), Some(ShardIndex::new(3)));
+        let deleted = repos
+            .shards()
+            .delete_table_override(namespace.id, "platanos")
+            .await
+            .unwrap();
+        assert!(deleted.is_none());
+
+        let overrides = repos.shards().list_table_overrides().await.unwrap();
+        assert_eq!(overrides, vec![bananas]);
+
+        // shard ring members
+        let other_topic = repos.topics().create_or_get("shard_test_2").await.unwrap();
+        let members = repos.shards().get_ring_members(topic.id).await.unwrap();
+        assert!(members.is_none());
+
+        let want = vec![ShardIndex::new(1), ShardIndex::new(3)];
+        repos
+            .shards()
+            .set_ring_members(topic.id, &want)
+            .await
+            .unwrap();
+        let members = repos.shards().get_ring_members(topic.id).await.unwrap();
+        assert_eq!(members, Some(want));
+
+        // setting the members again replaces them
+        let want = vec![ShardIndex::new(2)];
+        repos
+            .shards()
+            .set_ring_members(topic.id, &want)
+            .await
+            .unwrap();
+        let members = repos.shards().get_ring_members(topic.id).await.unwrap();
+        assert_eq!(members, Some(want));
+
+        // the members of other topics are unaffected
+        let members = repos
+            .shards()
+            .get_ring_members(other_topic.id)
+            .await
+            .unwrap();
+        assert!(members.is_none());
     }
 
     async fn test_partition(catalog: Arc<dyn Catalog>) {
@@ -3675,6 +4017,78 @@ pub(crate) mod test_helpers {
             .await
             .unwrap();
         assert_eq!(files, vec![parquet_file.clone(), level1_file.clone()]);
+
+        // Page through the files one at a time
+        let page = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(partition.id, None, None, None, 1)
+            .await
+            .unwrap();
+        assert_eq!(page, vec![parquet_file.clone()]);
+        let page = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(
+                partition.id,
+                None,
+                None,
+                Some(parquet_file.id),
+                1,
+            )
+            .await
+            .unwrap();
+        assert_eq!(page, vec![level1_file.clone()]);
+        let page = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(
+                partition.id,
+                None,
+                None,
+                Some(level1_file.id),
+                1,
+            )
+            .await
+            .unwrap();
+        assert!(page.is_empty());
+
+        // Filter by compaction level
+        let files = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(
+                partition.id,
+                None,
+                Some(CompactionLevel::FileNonOverlapped),
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(files, vec![level1_file.clone()]);
+
+        // Filter by time range, inclusive of the file's min and max time
+        let files = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(
+                partition.id,
+                Some(TimestampMinMax::new(10, 20)),
+                None,
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+        assert_eq!(files, vec![parquet_file.clone(), level1_file.clone()]);
+        let files = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(
+                partition.id,
+                Some(TimestampMinMax::new(11, 20)),
+                None,
+                None,
+                10,
+            )
+            .await
+            .unwrap();
+        assert!(files.is_empty());
     }
 
     async fn test_update_to_compaction_level_1(catalog: Arc<dyn Catalog>) {
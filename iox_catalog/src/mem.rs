@@ -14,10 +14,10 @@ use crate::{
 use async_trait::async_trait;
 use data_types::{
     Column, ColumnId, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId,
-    ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey,
-    PartitionParam, ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId,
-    ShardIndex, SkippedCompaction, Table, TableId, TablePartition, Timestamp, Tombstone,
-    TombstoneId, TopicId, TopicMetadata,
+    NamespaceUsage, ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId,
+    PartitionKey, PartitionParam, PartitionTemplate, ProcessedTombstone, QueryPool, QueryPoolId,
+    SequenceNumber, Shard, ShardId, ShardIndex, SkippedCompaction, Table, TableId, TablePartition,
+    TableShardOverride, Timestamp, TimestampMinMax, Tombstone, TombstoneId, TopicId, TopicMetadata,
 };
 use iox_time::{SystemProvider, TimeProvider};
 use observability_deps::tracing::warn;
@@ -61,9 +61,12 @@ struct MemCollections {
     topics: Vec<TopicMetadata>,
     query_pools: Vec<QueryPool>,
     namespaces: Vec<Namespace>,
+    namespace_usage: Vec<NamespaceUsage>,
     tables: Vec<Table>,
     columns: Vec<Column>,
     shards: Vec<Shard>,
+    table_shard_overrides: Vec<TableShardOverride>,
+    shard_ring_members: Vec<(TopicId, Vec<ShardIndex>)>,
     partitions: Vec<Partition>,
     skipped_compactions: Vec<SkippedCompaction>,
     tombstones: Vec<Tombstone>,
@@ -305,6 +308,9 @@ impl NamespaceRepo for MemTxn {
             retention_duration: Some(retention_duration.to_string()),
             max_tables: DEFAULT_MAX_TABLES,
             max_columns_per_table: DEFAULT_MAX_COLUMNS_PER_TABLE,
+            retention_period_ns: None,
+            deleted_at: None,
+            partition_template: None,
         };
         stage.namespaces.push(namespace);
         Ok(stage.namespaces.last().unwrap().clone())
@@ -313,7 +319,12 @@ impl NamespaceRepo for MemTxn {
     async fn list(&mut self) -> Result<Vec<Namespace>> {
         let stage = self.stage();
 
-        Ok(stage.namespaces.clone())
+        Ok(stage
+            .namespaces
+            .iter()
+            .filter(|n| n.deleted_at.is_none())
+            .cloned()
+            .collect())
     }
 
     async fn get_by_id(&mut self, id: NamespaceId) -> Result<Option<Namespace>> {
@@ -353,6 +364,104 @@ impl NamespaceRepo for MemTxn {
             }),
         }
     }
+
+    async fn update_retention_period(
+        &mut self,
+        name: &str,
+        retention_period_ns: Option<i64>,
+    ) -> Result<Namespace> {
+        let stage = self.stage();
+        match stage.namespaces.iter_mut().find(|n| n.name == name) {
+            Some(n) => {
+                n.retention_period_ns = retention_period_ns;
+                Ok(n.clone())
+            }
+            None => Err(Error::NamespaceNotFoundByName {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    async fn update_partition_template(
+        &mut self,
+        name: &str,
+        partition_template: Option<PartitionTemplate>,
+    ) -> Result<Namespace> {
+        let stage = self.stage();
+        match stage.namespaces.iter_mut().find(|n| n.name == name) {
+            Some(n) => {
+                n.partition_template = partition_template;
+                Ok(n.clone())
+            }
+            None => Err(Error::NamespaceNotFoundByName {
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    async fn soft_delete(&mut self, name: &str) -> Result<()> {
+        let deleted_at = Timestamp::from(self.time_provider.now());
+        let stage = self.stage();
+
+        match stage.namespaces.iter_mut().find(|n| n.name == name) {
+            Some(n) => n.deleted_at = Some(deleted_at),
+            None => {
+                return Err(Error::NamespaceNotFoundByName {
+                    name: name.to_string(),
+                })
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn record_usage(
+        &mut self,
+        namespace_id: NamespaceId,
+        period_start: Timestamp,
+        period_end: Timestamp,
+        lines: i64,
+        bytes: i64,
+    ) -> Result<NamespaceUsage> {
+        let stage = self.stage();
+
+        match stage
+            .namespace_usage
+            .iter_mut()
+            .find(|u| u.namespace_id == namespace_id && u.period_start == period_start)
+        {
+            Some(existing) => {
+                existing.lines += lines;
+                existing.bytes += bytes;
+                Ok(existing.clone())
+            }
+            None => {
+                let usage = NamespaceUsage {
+                    namespace_id,
+                    period_start,
+                    period_end,
+                    lines,
+                    bytes,
+                };
+                stage.namespace_usage.push(usage.clone());
+                Ok(usage)
+            }
+        }
+    }
+
+    async fn list_usage(&mut self, namespace_id: NamespaceId) -> Result<Vec<NamespaceUsage>> {
+        let stage = self.stage();
+
+        let mut usage: Vec<_> = stage
+            .namespace_usage
+            .iter()
+            .filter(|u| u.namespace_id == namespace_id)
+            .cloned()
+            .collect();
+        usage.sort_by_key(|u| u.period_start);
+
+        Ok(usage)
+    }
 }
 
 #[async_trait]
@@ -716,6 +825,83 @@ impl ShardRepo for MemTxn {
 
         Ok(())
     }
+
+    async fn set_table_override(
+        &mut self,
+        namespace_id: NamespaceId,
+        table_name: &str,
+        shard_index: ShardIndex,
+    ) -> Result<TableShardOverride> {
+        let stage = self.stage();
+
+        let o = TableShardOverride {
+            namespace_id,
+            table_name: table_name.to_string(),
+            shard_index,
+        };
+
+        match stage
+            .table_shard_overrides
+            .iter_mut()
+            .find(|o| o.namespace_id == namespace_id && o.table_name == table_name)
+        {
+            Some(existing) => *existing = o.clone(),
+            None => stage.table_shard_overrides.push(o.clone()),
+        }
+
+        Ok(o)
+    }
+
+    async fn list_table_overrides(&mut self) -> Result<Vec<TableShardOverride>> {
+        let stage = self.stage();
+        Ok(stage.table_shard_overrides.clone())
+    }
+
+    async fn delete_table_override(
+        &mut self,
+        namespace_id: NamespaceId,
+        table_name: &str,
+    ) -> Result<Option<TableShardOverride>> {
+        let stage = self.stage();
+
+        let idx = stage
+            .table_shard_overrides
+            .iter()
+            .position(|o| o.namespace_id == namespace_id && o.table_name == table_name);
+
+        Ok(idx.map(|idx| stage.table_shard_overrides.remove(idx)))
+    }
+
+    async fn set_ring_members(
+        &mut self,
+        topic_id: TopicId,
+        shard_indexes: &[ShardIndex],
+    ) -> Result<()> {
+        let stage = self.stage();
+
+        match stage
+            .shard_ring_members
+            .iter_mut()
+            .find(|(id, _)| *id == topic_id)
+        {
+            Some((_, existing)) => *existing = shard_indexes.to_vec(),
+            None => stage
+                .shard_ring_members
+                .push((topic_id, shard_indexes.to_vec())),
+        }
+
+        Ok(())
+    }
+
+    async fn get_ring_members(&mut self, topic_id: TopicId) -> Result<Option<Vec<ShardIndex>>> {
+        let stage = self.stage();
+
+        Ok(stage
+            .shard_ring_members
+            .iter()
+            .find(|(id, _)| *id == topic_id)
+            .map(|(_, members)| members.clone()))
+    }
 }
 
 #[async_trait]
@@ -1369,6 +1555,40 @@ impl ParquetFileRepo for MemTxn {
             .collect())
     }
 
+    async fn list_by_partition_not_to_delete_paged(
+        &mut self,
+        partition_id: PartitionId,
+        time_range: Option<TimestampMinMax>,
+        compaction_level: Option<CompactionLevel>,
+        after: Option<ParquetFileId>,
+        limit: usize,
+    ) -> Result<Vec<ParquetFile>> {
+        let stage = self.stage();
+
+        let mut files: Vec<_> = stage
+            .parquet_files
+            .iter()
+            .filter(|f| f.partition_id == partition_id && f.to_delete.is_none())
+            .filter(|f| {
+                time_range
+                    .map(|r| f.max_time.get() >= r.min && f.min_time.get() <= r.max)
+                    .unwrap_or(true)
+            })
+            .filter(|f| {
+                compaction_level
+                    .map(|l| f.compaction_level == l)
+                    .unwrap_or(true)
+            })
+            .filter(|f| after.map(|id| f.id > id).unwrap_or(true))
+            .cloned()
+            .collect();
+
+        files.sort_unstable_by_key(|f| f.id);
+        files.truncate(limit);
+
+        Ok(files)
+    }
+
     async fn update_compaction_level(
         &mut self,
         parquet_file_ids: &[ParquetFileId],
@@ -7,11 +7,11 @@ use crate::interface::{
 };
 use async_trait::async_trait;
 use data_types::{
-    Column, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId, ParquetFile,
-    ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey, PartitionParam,
-    ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex,
-    SkippedCompaction, Table, TableId, TablePartition, Timestamp, Tombstone, TombstoneId, TopicId,
-    TopicMetadata,
+    Column, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId, NamespaceUsage,
+    ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey,
+    PartitionParam, PartitionTemplate, ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber,
+    Shard, ShardId, ShardIndex, SkippedCompaction, Table, TableId, TablePartition,
+    TableShardOverride, Timestamp, TimestampMinMax, Tombstone, TombstoneId, TopicId, TopicMetadata,
 };
 use iox_time::{SystemProvider, TimeProvider};
 use metric::{DurationHistogram, Metric};
@@ -199,6 +199,11 @@ decorate!(
         "namespace_get_by_name" = get_by_name(&mut self, name: &str) -> Result<Option<Namespace>>;
         "namespace_update_table_limit" = update_table_limit(&mut self, name: &str, new_max: i32) -> Result<Namespace>;
         "namespace_update_column_limit" = update_column_limit(&mut self, name: &str, new_max: i32) -> Result<Namespace>;
+        "namespace_update_retention_period" = update_retention_period(&mut self, name: &str, retention_period_ns: Option<i64>) -> Result<Namespace>;
+        "namespace_update_partition_template" = update_partition_template(&mut self, name: &str, partition_template: Option<PartitionTemplate>) -> Result<Namespace>;
+        "namespace_soft_delete" = soft_delete(&mut self, name: &str) -> Result<()>;
+        "namespace_record_usage" = record_usage(&mut self, namespace_id: NamespaceId, period_start: Timestamp, period_end: Timestamp, lines: i64, bytes: i64) -> Result<NamespaceUsage>;
+        "namespace_list_usage" = list_usage(&mut self, namespace_id: NamespaceId) -> Result<Vec<NamespaceUsage>>;
     ]
 );
 
@@ -233,6 +238,11 @@ decorate!(
         "shard_list" = list(&mut self) -> Result<Vec<Shard>>;
         "shard_list_by_topic" = list_by_topic(&mut self, topic: &TopicMetadata) -> Result<Vec<Shard>>;
         "shard_update_min_unpersisted_sequence_number" = update_min_unpersisted_sequence_number(&mut self, shard_id: ShardId, sequence_number: SequenceNumber) -> Result<()>;
+        "shard_set_table_override" = set_table_override(&mut self, namespace_id: NamespaceId, table_name: &str, shard_index: ShardIndex) -> Result<TableShardOverride>;
+        "shard_list_table_overrides" = list_table_overrides(&mut self) -> Result<Vec<TableShardOverride>>;
+        "shard_delete_table_override" = delete_table_override(&mut self, namespace_id: NamespaceId, table_name: &str) -> Result<Option<TableShardOverride>>;
+        "shard_set_ring_members" = set_ring_members(&mut self, topic_id: TopicId, shard_indexes: &[ShardIndex]) -> Result<()>;
+        "shard_get_ring_members" = get_ring_members(&mut self, topic_id: TopicId) -> Result<Option<Vec<ShardIndex>>>;
     ]
 );
 
@@ -277,6 +287,7 @@ decorate!(
         "parquet_delete_old" = delete_old(&mut self, older_than: Timestamp) -> Result<Vec<ParquetFile>>;
         "parquet_delete_old_ids_only" = delete_old_ids_only(&mut self, older_than: Timestamp) -> Result<Vec<ParquetFileId>>;
         "parquet_list_by_partition_not_to_delete" = list_by_partition_not_to_delete(&mut self, partition_id: PartitionId) -> Result<Vec<ParquetFile>>;
+        "parquet_list_by_partition_not_to_delete_paged" = list_by_partition_not_to_delete_paged(&mut self, partition_id: PartitionId, time_range: Option<TimestampMinMax>, compaction_level: Option<CompactionLevel>, after: Option<ParquetFileId>, limit: usize) -> Result<Vec<ParquetFile>>;
         "parquet_level_0" = level_0(&mut self, shard_id: ShardId) -> Result<Vec<ParquetFile>>;
         "parquet_level_1" = level_1(&mut self, table_partition: TablePartition, min_time: Timestamp, max_time: Timestamp) -> Result<Vec<ParquetFile>>;
         "parquet_update_compaction_level" = update_compaction_level(&mut self, parquet_file_ids: &[ParquetFileId], compaction_level: CompactionLevel) -> Result<Vec<ParquetFileId>>;
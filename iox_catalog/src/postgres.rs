@@ -12,11 +12,11 @@ use crate::{
 };
 use async_trait::async_trait;
 use data_types::{
-    Column, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId, ParquetFile,
-    ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey, PartitionParam,
-    ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber, Shard, ShardId, ShardIndex,
-    SkippedCompaction, Table, TableId, TablePartition, Timestamp, Tombstone, TombstoneId, TopicId,
-    TopicMetadata,
+    Column, ColumnType, ColumnTypeCount, CompactionLevel, Namespace, NamespaceId, NamespaceUsage,
+    ParquetFile, ParquetFileId, ParquetFileParams, Partition, PartitionId, PartitionKey,
+    PartitionParam, PartitionTemplate, ProcessedTombstone, QueryPool, QueryPoolId, SequenceNumber,
+    Shard, ShardId, ShardIndex, SkippedCompaction, Table, TableId, TablePartition,
+    TableShardOverride, Timestamp, TimestampMinMax, Tombstone, TombstoneId, TopicId, TopicMetadata,
 };
 use iox_time::{SystemProvider, TimeProvider};
 use observability_deps::tracing::{debug, info, warn};
@@ -630,7 +630,8 @@ RETURNING *;
         let rec = sqlx::query_as::<_, Namespace>(
             r#"
 SELECT *
-FROM namespace;
+FROM namespace
+WHERE deleted_at IS NULL;
             "#,
         )
         .fetch_all(&mut self.inner)
@@ -729,6 +730,123 @@ RETURNING *;
 
         Ok(namespace)
     }
+
+    async fn update_retention_period(
+        &mut self,
+        name: &str,
+        retention_period_ns: Option<i64>,
+    ) -> Result<Namespace> {
+        let rec = sqlx::query_as::<_, Namespace>(
+            r#"
+UPDATE namespace
+SET retention_period_ns = $1
+WHERE name = $2
+RETURNING *;
+        "#,
+        )
+        .bind(&retention_period_ns) // $1
+        .bind(&name) // $2
+        .fetch_one(&mut self.inner)
+        .await;
+
+        let namespace = rec.map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::NamespaceNotFoundByName {
+                name: name.to_string(),
+            },
+            _ => Error::SqlxError { source: e },
+        })?;
+
+        Ok(namespace)
+    }
+
+    async fn update_partition_template(
+        &mut self,
+        name: &str,
+        partition_template: Option<PartitionTemplate>,
+    ) -> Result<Namespace> {
+        let rec = sqlx::query_as::<_, Namespace>(
+            r#"
+UPDATE namespace
+SET partition_template = $1
+WHERE name = $2
+RETURNING *;
+        "#,
+        )
+        .bind(&partition_template) // $1
+        .bind(&name) // $2
+        .fetch_one(&mut self.inner)
+        .await;
+
+        let namespace = rec.map_err(|e| match e {
+            sqlx::Error::RowNotFound => Error::NamespaceNotFoundByName {
+                name: name.to_string(),
+            },
+            _ => Error::SqlxError { source: e },
+        })?;
+
+        Ok(namespace)
+    }
+
+    async fn soft_delete(&mut self, name: &str) -> Result<()> {
+        let deleted_at = Timestamp::from(self.time_provider.now());
+
+        let res = sqlx::query(r#"UPDATE namespace SET deleted_at = $1 WHERE name = $2;"#)
+            .bind(&deleted_at) // $1
+            .bind(&name) // $2
+            .execute(&mut self.inner)
+            .await
+            .map_err(|e| Error::SqlxError { source: e })?;
+
+        if res.rows_affected() == 0 {
+            return Err(Error::NamespaceNotFoundByName {
+                name: name.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn record_usage(
+        &mut self,
+        namespace_id: NamespaceId,
+        period_start: Timestamp,
+        period_end: Timestamp,
+        lines: i64,
+        bytes: i64,
+    ) -> Result<NamespaceUsage> {
+        sqlx::query_as::<_, NamespaceUsage>(
+            r#"
+INSERT INTO namespace_usage ( namespace_id, period_start, period_end, lines, bytes )
+VALUES ( $1, $2, $3, $4, $5 )
+ON CONFLICT ( namespace_id, period_start )
+DO UPDATE SET lines = namespace_usage.lines + EXCLUDED.lines,
+              bytes = namespace_usage.bytes + EXCLUDED.bytes
+RETURNING *;
+        "#,
+        )
+        .bind(&namespace_id) // $1
+        .bind(&period_start) // $2
+        .bind(&period_end) // $3
+        .bind(lines) // $4
+        .bind(bytes) // $5
+        .fetch_one(&mut self.inner)
+        .await
+        .context(interface::CouldNotRecordNamespaceUsageSnafu { namespace_id })
+    }
+
+    async fn list_usage(&mut self, namespace_id: NamespaceId) -> Result<Vec<NamespaceUsage>> {
+        sqlx::query_as::<_, NamespaceUsage>(
+            r#"
+SELECT * FROM namespace_usage
+WHERE namespace_id = $1
+ORDER BY period_start;
+        "#,
+        )
+        .bind(&namespace_id) // $1
+        .fetch_all(&mut self.inner)
+        .await
+        .context(interface::CouldNotListNamespaceUsageSnafu { namespace_id })
+    }
 }
 
 #[async_trait]
@@ -1105,6 +1223,100 @@ WHERE id = $2;
 
         Ok(())
     }
+
+    async fn set_table_override(
+        &mut self,
+        namespace_id: NamespaceId,
+        table_name: &str,
+        shard_index: ShardIndex,
+    ) -> Result<TableShardOverride> {
+        sqlx::query_as::<_, TableShardOverride>(
+            r#"
+INSERT INTO table_shard_override ( namespace_id, table_name, shard_index )
+VALUES ( $1, $2, $3 )
+ON CONFLICT ( namespace_id, table_name )
+DO UPDATE SET shard_index = EXCLUDED.shard_index
+RETURNING *;
+        "#,
+        )
+        .bind(&namespace_id) // $1
+        .bind(table_name) // $2
+        .bind(&shard_index) // $3
+        .fetch_one(&mut self.inner)
+        .await
+        .context(interface::CouldNotSetTableShardOverrideSnafu {
+            namespace_id,
+            table_name,
+        })
+    }
+
+    async fn list_table_overrides(&mut self) -> Result<Vec<TableShardOverride>> {
+        sqlx::query_as::<_, TableShardOverride>(
+            r#"
+SELECT * FROM table_shard_override;
+        "#,
+        )
+        .fetch_all(&mut self.inner)
+        .await
+        .context(interface::CouldNotListTableShardOverridesSnafu)
+    }
+
+    async fn delete_table_override(
+        &mut self,
+        namespace_id: NamespaceId,
+        table_name: &str,
+    ) -> Result<Option<TableShardOverride>> {
+        sqlx::query_as::<_, TableShardOverride>(
+            r#"
+DELETE FROM table_shard_override
+WHERE namespace_id = $1 AND table_name = $2
+RETURNING *;
+        "#,
+        )
+        .bind(&namespace_id) // $1
+        .bind(table_name) // $2
+        .fetch_optional(&mut self.inner)
+        .await
+        .context(interface::CouldNotDeleteTableShardOverrideSnafu)
+    }
+
+    async fn set_ring_members(
+        &mut self,
+        topic_id: TopicId,
+        shard_indexes: &[ShardIndex],
+    ) -> Result<()> {
+        let shard_indexes = shard_indexes.iter().map(|v| v.get()).collect::<Vec<_>>();
+
+        let _ = sqlx::query(
+            r#"
+INSERT INTO shard_ring ( topic_id, shard_indexes )
+VALUES ( $1, $2 )
+ON CONFLICT ( topic_id )
+DO UPDATE SET shard_indexes = EXCLUDED.shard_indexes;
+        "#,
+        )
+        .bind(&topic_id) // $1
+        .bind(&shard_indexes) // $2
+        .execute(&mut self.inner)
+        .await
+        .context(interface::CouldNotSetShardRingMembersSnafu { topic_id })?;
+
+        Ok(())
+    }
+
+    async fn get_ring_members(&mut self, topic_id: TopicId) -> Result<Option<Vec<ShardIndex>>> {
+        let shard_indexes = sqlx::query_scalar::<_, Vec<i32>>(
+            r#"
+SELECT shard_indexes FROM shard_ring WHERE topic_id = $1;
+        "#,
+        )
+        .bind(&topic_id) // $1
+        .fetch_optional(&mut self.inner)
+        .await
+        .context(interface::CouldNotGetShardRingMembersSnafu { topic_id })?;
+
+        Ok(shard_indexes.map(|v| v.into_iter().map(ShardIndex::new).collect()))
+    }
 }
 
 #[async_trait]
@@ -1875,6 +2087,43 @@ WHERE parquet_file.partition_id = $1
         .map_err(|e| Error::SqlxError { source: e })
     }
 
+    async fn list_by_partition_not_to_delete_paged(
+        &mut self,
+        partition_id: PartitionId,
+        time_range: Option<TimestampMinMax>,
+        compaction_level: Option<CompactionLevel>,
+        after: Option<ParquetFileId>,
+        limit: usize,
+    ) -> Result<Vec<ParquetFile>> {
+        // Deliberately doesn't use `SELECT *` to avoid the performance hit of fetching the large
+        // `parquet_metadata` column!!
+        sqlx::query_as::<_, ParquetFile>(
+            r#"
+SELECT id, shard_id, namespace_id, table_id, partition_id, object_store_id,
+       max_sequence_number, min_time, max_time, to_delete, file_size_bytes,
+       row_count, compaction_level, created_at, column_set
+FROM parquet_file
+WHERE parquet_file.partition_id = $1
+  AND parquet_file.to_delete IS NULL
+  AND ($2::BIGINT IS NULL OR parquet_file.max_time >= $2)
+  AND ($3::BIGINT IS NULL OR parquet_file.min_time <= $3)
+  AND ($4::SMALLINT IS NULL OR parquet_file.compaction_level = $4)
+  AND ($5::BIGINT IS NULL OR parquet_file.id > $5)
+ORDER BY parquet_file.id
+LIMIT $6;
+        "#,
+        )
+        .bind(&partition_id) // $1
+        .bind(time_range.map(|r| r.min)) // $2
+        .bind(time_range.map(|r| r.max)) // $3
+        .bind(compaction_level) // $4
+        .bind(after) // $5
+        .bind(limit as i64) // $6
+        .fetch_all(&mut self.inner)
+        .await
+        .map_err(|e| Error::SqlxError { source: e })
+    }
+
     async fn update_compaction_level(
         &mut self,
         parquet_file_ids: &[ParquetFileId],
@@ -16,7 +16,7 @@ use object_store::DynObjectStore;
 use parquet_file::storage::StorageId;
 use trace::span::{SpanExt, SpanRecorder};
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use datafusion::{
     self,
@@ -28,6 +28,7 @@ use datafusion::{
     logical_expr::{Expr, LogicalPlan},
     prelude::SessionContext,
 };
+use query_functions::selectors::AccumulatorMemory;
 
 pub use context::{IOxSessionConfig, IOxSessionContext, SessionContextIOxExt};
 use schema_pivot::SchemaPivotNode;
@@ -45,6 +46,29 @@ pub struct ExecutorConfig {
 
     /// Object stores
     pub object_stores: HashMap<StorageId, Arc<DynObjectStore>>,
+
+    /// If set, limit the memory used by reorganization plans (such as
+    /// compaction), spilling intermediate sort runs to disk rather than
+    /// exceeding the limit.
+    pub reorg_spill: Option<SpillConfig>,
+
+    /// If set, limit the memory used by the queries running at once on
+    /// this executor. Sorts spill to the OS temporary directory rather
+    /// than exceeding the limit, and queries whose aggregate functions
+    /// need more memory for their state fail.
+    pub query_memory_limit_bytes: Option<usize>,
+}
+
+/// Configuration for spilling the intermediate state of plans to disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpillConfig {
+    /// The total memory that may be used by the sorts of all plans executing
+    /// at once before they spill to disk.
+    pub memory_limit_bytes: usize,
+
+    /// The directory to write spill files to, or the OS temporary directory
+    /// if `None`.
+    pub directory: Option<PathBuf>,
 }
 
 #[derive(Debug)]
@@ -91,8 +115,22 @@ pub struct Executor {
     config: ExecutorConfig,
 
     /// The DataFusion [RuntimeEnv] (including memory manager and disk
-    /// manager) used for all executions
+    /// manager) used for all query executions
     runtime: Arc<RuntimeEnv>,
+
+    /// The memory of `runtime` for the state of the aggregate functions
+    /// of all query executions (see
+    /// [`ExecutorConfig::query_memory_limit_bytes`])
+    accumulator_memory: Arc<AccumulatorMemory>,
+
+    /// The DataFusion [RuntimeEnv] used for all reorganization executions,
+    /// which may be configured to spill to disk (see
+    /// [`ExecutorConfig::reorg_spill`])
+    reorg_runtime: Arc<RuntimeEnv>,
+
+    /// The memory of `reorg_runtime` for the state of the aggregate
+    /// functions of all reorganization executions
+    reorg_accumulator_memory: Arc<AccumulatorMemory>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -112,6 +150,8 @@ impl Executor {
             num_threads,
             target_query_partitions: num_threads,
             object_stores: HashMap::default(),
+            reorg_spill: None,
+            query_memory_limit_bytes: None,
         })
     }
 
@@ -132,21 +172,53 @@ impl Executor {
     ) -> Self {
         assert_eq!(config.num_threads, executors.num_threads);
 
-        let runtime_config = RuntimeConfig::new();
+        let runtime_config = match config.query_memory_limit_bytes {
+            Some(limit) => RuntimeConfig::new().with_memory_limit(limit, 1.0),
+            None => RuntimeConfig::new(),
+        };
+        let runtime = Self::new_runtime(&config, runtime_config);
+        let accumulator_memory = Arc::new(AccumulatorMemory::new(
+            Arc::clone(&runtime),
+            config.query_memory_limit_bytes.unwrap_or(usize::MAX),
+        ));
+
+        let (reorg_runtime, reorg_accumulator_memory) = match &config.reorg_spill {
+            Some(spill) => {
+                let mut runtime_config =
+                    RuntimeConfig::new().with_memory_limit(spill.memory_limit_bytes, 1.0);
+                if let Some(directory) = &spill.directory {
+                    runtime_config = runtime_config.with_temp_file_path(directory.clone());
+                }
+                let reorg_runtime = Self::new_runtime(&config, runtime_config);
+                let reorg_accumulator_memory = Arc::new(AccumulatorMemory::new(
+                    Arc::clone(&reorg_runtime),
+                    spill.memory_limit_bytes,
+                ));
+                (reorg_runtime, reorg_accumulator_memory)
+            }
+            None => (Arc::clone(&runtime), Arc::clone(&accumulator_memory)),
+        };
+
+        Self {
+            executors,
+            config,
+            runtime,
+            accumulator_memory,
+            reorg_runtime,
+            reorg_accumulator_memory,
+        }
+    }
 
+    /// Create a [`RuntimeEnv`] from `runtime_config`, registering the object
+    /// stores of `config`.
+    fn new_runtime(config: &ExecutorConfig, runtime_config: RuntimeConfig) -> Arc<RuntimeEnv> {
         for (id, store) in &config.object_stores {
             runtime_config
                 .object_store_registry
                 .register_store("iox", id, Arc::clone(store));
         }
 
-        let runtime = Arc::new(RuntimeEnv::new(runtime_config).expect("creating runtime"));
-
-        Self {
-            executors,
-            config,
-            runtime,
-        }
+        Arc::new(RuntimeEnv::new(runtime_config).expect("creating runtime"))
     }
 
     /// Return a new execution config, suitable for executing a new query or system task.
@@ -154,7 +226,11 @@ impl Executor {
     /// Note that this context (and all its clones) will be shut down once `Executor` is dropped.
     pub fn new_execution_config(&self, executor_type: ExecutorType) -> IOxSessionConfig {
         let exec = self.executor(executor_type).clone();
-        IOxSessionConfig::new(exec, Arc::clone(&self.runtime))
+        let (runtime, accumulator_memory) = match executor_type {
+            ExecutorType::Query => (&self.runtime, &self.accumulator_memory),
+            ExecutorType::Reorg => (&self.reorg_runtime, &self.reorg_accumulator_memory),
+        };
+        IOxSessionConfig::new(exec, Arc::clone(runtime), Arc::clone(accumulator_memory))
             .with_target_partitions(self.config.target_query_partitions)
     }
 
@@ -177,6 +253,15 @@ impl Executor {
         self.new_execution_config(executor_type).build()
     }
 
+    /// The memory currently used by the state of the aggregate
+    /// functions of the executions of the specified type, in bytes
+    pub fn accumulator_memory_used(&self, executor_type: ExecutorType) -> usize {
+        match executor_type {
+            ExecutorType::Query => self.accumulator_memory.used(),
+            ExecutorType::Reorg => self.reorg_accumulator_memory.used(),
+        }
+    }
+
     /// Return the execution pool  of the specified type
     fn executor(&self, executor_type: ExecutorType) -> &DedicatedExecutor {
         match executor_type {
@@ -510,6 +595,33 @@ mod tests {
         exec.join().await;
     }
 
+    #[tokio::test]
+    async fn query_memory_limit_is_applied() {
+        let exec = Executor::new_with_config(ExecutorConfig {
+            num_threads: 1,
+            target_query_partitions: 1,
+            object_stores: HashMap::default(),
+            reorg_spill: None,
+            query_memory_limit_bytes: Some(1),
+        });
+        let ctx = exec.new_context(ExecutorType::Query);
+
+        let err = ctx
+            .inner()
+            .sql("SELECT selector_median(column1) FROM (VALUES (1.0), (2.0)) AS t")
+            .await
+            .expect("planned query")
+            .collect()
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Resources exhausted"), "{}", err);
+
+        // the memory of the failed query is released
+        assert_eq!(exec.accumulator_memory_used(ExecutorType::Query), 0);
+
+        exec.join().await;
+    }
+
     /// return a set for testing
     fn to_set(strs: &[&str]) -> StringSetRef {
         StringSetRef::new(strs.iter().map(|s| s.to_string()).collect::<StringSet>())
@@ -32,7 +32,7 @@ use datafusion::{
         context::{QueryPlanner, SessionState, TaskContext},
         runtime_env::RuntimeEnv,
     },
-    logical_expr::{LogicalPlan, UserDefinedLogicalNode},
+    logical_expr::{AggregateUDF, LogicalPlan, ScalarUDF, UserDefinedLogicalNode},
     physical_plan::{
         coalesce_partitions::CoalescePartitionsExec,
         displayable,
@@ -45,7 +45,12 @@ use executor::DedicatedExecutor;
 use futures::TryStreamExt;
 use observability_deps::tracing::debug;
 use parquet_file::serialize::ROW_GROUP_WRITE_SIZE;
-use query_functions::selectors::register_selector_aggregates;
+use query_functions::{
+    extension::FunctionRegistryExt,
+    register_scalar_functions,
+    selectors::{register_selector_aggregates, AccumulatorMemory},
+    series::register_series_functions,
+};
 use std::{convert::TryInto, fmt, sync::Arc};
 use trace::{
     ctx::SpanContext,
@@ -162,11 +167,20 @@ pub struct IOxSessionConfig {
     /// Shared DataFusion runtime
     runtime: Arc<RuntimeEnv>,
 
+    /// Shared memory for the state of the IOx aggregate functions
+    accumulator_memory: Arc<AccumulatorMemory>,
+
     /// Default catalog
     default_catalog: Option<Arc<dyn CatalogProvider>>,
 
     /// Span context from which to create spans for this query
     span_ctx: Option<SpanContext>,
+
+    /// Additional user defined scalar functions
+    udfs: Vec<Arc<ScalarUDF>>,
+
+    /// Additional user defined aggregate functions
+    udafs: Vec<Arc<AggregateUDF>>,
 }
 
 impl fmt::Debug for IOxSessionConfig {
@@ -184,7 +198,11 @@ const COALESCE_BATCH_SIZE: usize = BATCH_SIZE / 2;
 const _: () = assert!(ROW_GROUP_WRITE_SIZE % BATCH_SIZE == 0);
 
 impl IOxSessionConfig {
-    pub(super) fn new(exec: DedicatedExecutor, runtime: Arc<RuntimeEnv>) -> Self {
+    pub(super) fn new(
+        exec: DedicatedExecutor,
+        runtime: Arc<RuntimeEnv>,
+        accumulator_memory: Arc<AccumulatorMemory>,
+    ) -> Self {
         let session_config = SessionConfig::new()
             .with_batch_size(BATCH_SIZE)
             // TODO add function in SessionCofig
@@ -200,8 +218,11 @@ impl IOxSessionConfig {
             exec,
             session_config,
             runtime,
+            accumulator_memory,
             default_catalog: None,
             span_ctx: None,
+            udfs: vec![],
+            udafs: vec![],
         }
     }
 
@@ -226,12 +247,35 @@ impl IOxSessionConfig {
         Self { span_ctx, ..self }
     }
 
+    /// Register an additional user defined scalar function, which
+    /// can be invoked via SQL
+    pub fn with_udf(mut self, udf: Arc<ScalarUDF>) -> Self {
+        self.udfs.push(udf);
+        self
+    }
+
+    /// Register an additional user defined aggregate function, which
+    /// can be invoked via SQL. Replaces any IOx function with the
+    /// same name
+    pub fn with_udaf(mut self, udaf: Arc<AggregateUDF>) -> Self {
+        self.udafs.push(udaf);
+        self
+    }
+
     /// Create an ExecutionContext suitable for executing DataFusion plans
     pub fn build(self) -> IOxSessionContext {
         let state = SessionState::with_config_rt(self.session_config, self.runtime)
             .with_query_planner(Arc::new(IOxQueryPlanner {}));
 
-        let state = register_selector_aggregates(state);
+        let state = register_scalar_functions(state);
+        let state = register_selector_aggregates(state, &self.accumulator_memory);
+        let mut state = register_series_functions(state, &self.accumulator_memory);
+        for udf in self.udfs {
+            state.register_udf(udf);
+        }
+        for udaf in self.udafs {
+            state.register_udaf(udaf);
+        }
 
         let inner = SessionContext::with_state(state);
 
@@ -23,13 +23,16 @@ use datafusion::{
 };
 use datafusion_util::AsExpr;
 use futures::{Stream, StreamExt, TryStreamExt};
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use observability_deps::tracing::{debug, trace, warn};
 use predicate::{rpc_predicate::InfluxRpcPredicate, Predicate, PredicateMatch};
 use query_functions::{
     group_by::{Aggregate, WindowDuration},
     make_window_bound_expr,
-    selectors::{selector_first, selector_last, selector_max, selector_min, SelectorOutput},
+    selectors::{
+        selector_time, selector_value, struct_selector_first, struct_selector_last,
+        struct_selector_max, struct_selector_min,
+    },
 };
 use schema::{selection::Selection, InfluxColumnType, Schema, TIME_COLUMN_NAME};
 use snafu::{ensure, OptionExt, ResultExt, Snafu};
@@ -1198,6 +1201,8 @@ impl InfluxRpcPlanner {
             .aggregate(group_exprs, agg_exprs)
             .context(BuildingPlanSnafu)?;
 
+        let plan_builder = expand_selectors(plan_builder, agg, &field_columns)?;
+
         // Reorganize the output so it is ordered and sorted on tag columns
 
         // no columns if there are no tags in the input and no group columns in the query
@@ -1312,6 +1317,8 @@ impl InfluxRpcPlanner {
             .aggregate(group_exprs, agg_exprs)?
             .sort(sort_exprs)?;
 
+        let plan_builder = expand_selectors(plan_builder, agg, &field_columns)?;
+
         let plan_builder = cast_aggregates(plan_builder, agg, &field_columns)?;
 
         // and finally create the plan
@@ -1614,6 +1621,55 @@ fn cast_aggregates(
     plan_builder.project(cast_exprs).context(BuildingPlanSnafu)
 }
 
+/// Splits the struct produced by each selector aggregate (named
+/// after its field in `field_columns`) into a value column and a time
+/// column. Other columns are passed through unchanged.
+///
+/// The value and time are accessed from the single shared aggregate,
+/// so the selector is only computed once per field.
+fn expand_selectors(
+    plan_builder: LogicalPlanBuilder,
+    agg: Aggregate,
+    field_columns: &FieldColumns,
+) -> Result<LogicalPlanBuilder> {
+    if !matches!(
+        agg,
+        Aggregate::First | Aggregate::Last | Aggregate::Min | Aggregate::Max
+    ) {
+        return Ok(plan_builder);
+    }
+
+    // selectors always produce a distinct timestamp per field
+    let time_names: HashMap<&str, &str> = match field_columns {
+        FieldColumns::SharedTimestamp(_) => return Ok(plan_builder),
+        FieldColumns::DifferentTimestamp(fields_and_timestamps) => fields_and_timestamps
+            .iter()
+            .map(|(field, timestamp)| (field.as_ref(), timestamp.as_ref()))
+            .collect(),
+    };
+
+    let schema = plan_builder.schema();
+
+    let select_exprs = schema
+        .fields()
+        .iter()
+        .flat_map(|df_field| {
+            let field_name = df_field.name();
+            match time_names.get(field_name.as_str()) {
+                Some(time_name) => vec![
+                    selector_value(field_name.as_expr()).alias(field_name),
+                    selector_time(field_name.as_expr()).alias(*time_name),
+                ],
+                None => vec![field_name.as_expr()],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    plan_builder
+        .project(select_exprs)
+        .context(BuildingPlanSnafu)
+}
+
 /// Helper for creating aggregates
 pub(crate) struct AggExprs {
     agg_exprs: Vec<Expr>,
@@ -1627,7 +1683,6 @@ pub(crate) struct AggExprs {
 struct FieldExpr<'a> {
     expr: Expr,
     name: &'a str,
-    datatype: &'a DataType,
 }
 
 // Returns an iterator of fields from schema that pass the predicate. If there
@@ -1665,7 +1720,6 @@ fn filtered_fields_iter<'a>(
         Some(FieldExpr {
             expr: expr.alias(f.name()),
             name: f.name(),
-            datatype: f.data_type(),
         })
     })
 }
@@ -1710,16 +1764,15 @@ impl AggExprs {
     }
 
     // Creates special aggregate "selector" expressions for the fields in the
-    // provided schema. Selectors ensure that the relevant aggregate functions
-    // are also provided to a distinct time column for each field column.
+    // provided schema. Each selector produces a struct of the selected value
+    // and its time, which `expand_selectors` later splits into the field
+    // column and a distinct time column for each field column.
     //
     // Equivalent SQL would look like:
     //
-    //   agg_function(_val1) as _value1
-    //   agg_function(time) as time1
+    //   agg_function(_val1, time) as _value1
     //   ..
-    //   agg_function(_valN) as _valueN
-    //   agg_function(time) as timeN
+    //   agg_function(_valN, time) as _valueN
     fn selector_aggregates(agg: Aggregate, schema: &Schema, predicate: &Predicate) -> Result<Self> {
         // might be nice to use a more functional style here
         let mut agg_exprs = Vec::new();
@@ -1727,22 +1780,10 @@ impl AggExprs {
 
         for field in filtered_fields_iter(schema, predicate) {
             let field_name = field.name;
-            agg_exprs.push(make_selector_expr(
-                agg,
-                SelectorOutput::Value,
-                field.clone(),
-                field_name,
-            )?);
+            agg_exprs.push(make_selector_expr(agg, field, field_name)?);
 
             let time_column_name = format!("{}_{}", TIME_COLUMN_NAME, field_name);
 
-            agg_exprs.push(make_selector_expr(
-                agg,
-                SelectorOutput::Time,
-                field,
-                &time_column_name,
-            )?);
-
             field_list.push((
                 Arc::from(field_name), // value name
                 Arc::from(time_column_name.as_str()),
@@ -1773,7 +1814,6 @@ impl AggExprs {
                     agg,
                     FieldExpr {
                         expr: field.name().as_expr(),
-                        datatype: field.data_type(),
                         name: field.name(),
                     },
                 )
@@ -1842,32 +1882,27 @@ fn make_agg_expr(agg: Aggregate, field_expr: FieldExpr<'_>) -> Result<Expr> {
         .map(|agg| agg.alias(field_name))
 }
 
-/// Creates a DataFusion expression suitable for calculating the time part of a
-/// selector:
+/// Creates a DataFusion expression suitable for calculating a selector:
 ///
-/// The output expression is equivalent to `CAST selector_time(field_expression)
-/// as col_name`.
+/// The output expression is equivalent to `selector(field_expression, time)
+/// as col_name` and produces a struct containing both the selected value and
+/// its time.
 ///
 /// In the simplest scenarios the field expressions are `Column` expressions.
 /// In some cases the field expressions are `CASE` statements such as for
 /// example:
 ///
-/// CAST selector_time(
+/// selector(
 ///     CASE WHEN field = 1.87 OR field = 1.99 THEN field
 ///     ELSE NULL
-/// END) as col_name
+/// END, time) as col_name
 ///
-fn make_selector_expr<'a>(
-    agg: Aggregate,
-    output: SelectorOutput,
-    field: FieldExpr<'a>,
-    col_name: &'a str,
-) -> Result<Expr> {
+fn make_selector_expr<'a>(agg: Aggregate, field: FieldExpr<'a>, col_name: &'a str) -> Result<Expr> {
     let uda = match agg {
-        Aggregate::First => selector_first(field.datatype, output),
-        Aggregate::Last => selector_last(field.datatype, output),
-        Aggregate::Min => selector_min(field.datatype, output),
-        Aggregate::Max => selector_max(field.datatype, output),
+        Aggregate::First => struct_selector_first(),
+        Aggregate::Last => struct_selector_last(),
+        Aggregate::Min => struct_selector_min(),
+        Aggregate::Max => struct_selector_max(),
         _ => return InternalAggregateNotSelectorSnafu { agg }.fail(),
     };
 
@@ -82,6 +82,8 @@ impl TestCatalog {
                     parquet_store.id(),
                     Arc::clone(parquet_store.object_store()),
                 )]),
+                reorg_spill: None,
+                query_memory_limit_bytes: None,
             },
             exec,
         ));
@@ -301,6 +303,16 @@ impl TestNamespace {
             .await
             .unwrap();
     }
+
+    /// Set the retention period of this namespace, in nanoseconds.
+    pub async fn update_retention_period(&self, retention_period_ns: Option<i64>) {
+        let mut repos = self.catalog.catalog.repositories().await;
+        repos
+            .namespaces()
+            .update_retention_period(&self.namespace.name, retention_period_ns)
+            .await
+            .unwrap();
+    }
 }
 
 /// A test shard with its namespace in the catalog
@@ -1,4 +1,7 @@
-use hyper::{Body, Response, StatusCode};
+use hyper::{
+    header::{HeaderName, HeaderValue, CONTENT_TYPE},
+    Body, HeaderMap, Response, StatusCode,
+};
 use observability_deps::tracing::warn;
 
 /// Constants used in API error codes.
@@ -20,6 +23,7 @@ pub enum HttpApiErrorCode {
     MethodNotAllowed,
     RequestTooLarge,
     UnsupportedMediaType,
+    GatewayTimeout,
 }
 
 impl HttpApiErrorCode {
@@ -39,6 +43,7 @@ impl HttpApiErrorCode {
             Self::MethodNotAllowed => "method not allowed",
             Self::RequestTooLarge => "request too large",
             Self::UnsupportedMediaType => "unsupported media type",
+            Self::GatewayTimeout => "gateway timeout",
         }
     }
 
@@ -58,6 +63,7 @@ impl HttpApiErrorCode {
             Self::MethodNotAllowed => StatusCode::METHOD_NOT_ALLOWED,
             Self::RequestTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
             Self::UnsupportedMediaType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::GatewayTimeout => StatusCode::GATEWAY_TIMEOUT,
         }
     }
 
@@ -83,6 +89,7 @@ impl From<StatusCode> for HttpApiErrorCode {
             StatusCode::METHOD_NOT_ALLOWED => Self::MethodNotAllowed,
             StatusCode::PAYLOAD_TOO_LARGE => Self::RequestTooLarge,
             StatusCode::UNSUPPORTED_MEDIA_TYPE => Self::UnsupportedMediaType,
+            StatusCode::GATEWAY_TIMEOUT => Self::GatewayTimeout,
             v => {
                 warn!(code=%v, "returning unexpected status code as internal error");
                 Self::InternalError
@@ -101,6 +108,12 @@ pub struct HttpApiError {
 
     /// Human-readable message.
     msg: String,
+
+    /// Additional headers to include in the response.
+    headers: HeaderMap,
+
+    /// Additional machine-readable fields to include in the response body.
+    details: serde_json::Map<String, serde_json::Value>,
 }
 
 impl HttpApiError {
@@ -109,26 +122,46 @@ impl HttpApiError {
         Self {
             code: code.into(),
             msg: msg.into(),
+            headers: HeaderMap::new(),
+            details: serde_json::Map::new(),
         }
     }
 
+    /// Include the header `name` with `value` in the response.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Include the field `key` with `value` in the response body, alongside
+    /// the code and message.
+    pub fn with_detail(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<serde_json::Value>,
+    ) -> Self {
+        self.details.insert(key.into(), value.into());
+        self
+    }
+
     /// Generate response body for this error.
     fn body(&self) -> Body {
-        let json = serde_json::json!({
-            "code": self.code.as_text().to_string(),
-            "message": self.msg.clone(),
-        })
-        .to_string();
+        let mut json = self.details.clone();
+        json.insert("code".to_string(), self.code.as_text().into());
+        json.insert("message".to_string(), self.msg.clone().into());
 
-        Body::from(json)
+        Body::from(serde_json::Value::Object(json).to_string())
     }
 
     /// Generate response for this error.
     pub fn response(&self) -> Response<Body> {
-        Response::builder()
+        let mut response = Response::builder()
             .status(self.code.status_code())
+            .header(CONTENT_TYPE, "application/json")
             .body(self.body())
-            .unwrap()
+            .unwrap();
+        response.headers_mut().extend(self.headers.clone());
+        response
     }
 
     /// Check if the error is an internal server error.
@@ -125,7 +125,7 @@ async fn route_request(
     let content_length = req.headers().get("content-length").cloned();
 
     let response = match (method.clone(), uri.path()) {
-        (Method::GET, "/health") => health(),
+        (Method::GET, "/health") => Ok(server_type.health()),
         (Method::GET, "/metrics") => handle_metrics(server_type.as_ref()),
         (Method::GET, "/debug/pprof") => pprof_home(req).await,
         (Method::GET, "/debug/pprof/profile") => pprof_profile(req).await,
@@ -154,11 +154,6 @@ async fn route_request(
     }
 }
 
-fn health() -> Result<Response<Body>, ApplicationError> {
-    let response_body = "OK";
-    Ok(Response::new(Body::from(response_body.to_string())))
-}
-
 fn handle_metrics(server_type: &dyn ServerType) -> Result<Response<Body>, ApplicationError> {
     let mut body: Vec<u8> = Default::default();
     let mut reporter = metric_exporters::PrometheusTextEncoder::new(&mut body);
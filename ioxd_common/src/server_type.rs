@@ -51,6 +51,13 @@ pub trait ServerType: std::fmt::Debug + Send + Sync + 'static {
         req: Request<Body>,
     ) -> Result<Response<Body>, Box<dyn HttpApiErrorSource>>;
 
+    /// Respond to a request to the shared `/health` route.
+    ///
+    /// Defaults to a plain-text `OK` response.
+    fn health(&self) -> Response<Body> {
+        Response::new(Body::from("OK"))
+    }
+
     /// Construct and serve gRPC subsystem.
     async fn server_grpc(self: Arc<Self>, builder_input: RpcBuilderInput) -> Result<(), RpcError>;
 
@@ -5,7 +5,9 @@ use hyper::{Body, Request, Response};
 use ingester::{
     handler::{IngestHandler, IngestHandlerImpl},
     lifecycle::LifecycleConfig,
+    persist::PersistConfig,
     server::{grpc::GrpcDelegate, http::HttpDelegate, IngesterServer},
+    wal::Wal,
 };
 use iox_catalog::interface::Catalog;
 use iox_query::exec::Executor;
@@ -39,6 +41,18 @@ pub enum Error {
     #[error("shard_index_range_start must be <= shard_index_range_end")]
     ShardIndexRange,
 
+    #[error("persist_memory_target_bytes must be <= persist_memory_threshold_bytes")]
+    PersistMemoryTarget,
+
+    #[error("persist_max_parallelism and persist_memory_budget_bytes must be non-zero")]
+    PersistLimits,
+
+    #[error("persist_hot_partition_bytes_per_second must be non-zero")]
+    HotPartitionThreshold,
+
+    #[error("persist_max_file_rows and persist_max_file_bytes must be non-zero")]
+    PersistFileLimits,
+
     #[error("error initializing ingester: {0}")]
     Ingester(#[from] ingester::handler::Error),
 
@@ -93,6 +107,7 @@ impl<I: IngestHandler + Sync + Send + Debug + 'static> ServerType for IngesterSe
         let builder = setup_builder!(builder_input, self);
         add_service!(builder, self.server.grpc().flight_service());
         add_service!(builder, self.server.grpc().write_info_service());
+        add_service!(builder, self.server.grpc().persist_service());
         serve_builder!(builder);
 
         Ok(())
@@ -177,7 +192,7 @@ pub async fn create_ingester_server_type(
         )
         .await?;
 
-    let lifecycle_config = LifecycleConfig::new(
+    let mut lifecycle_config = LifecycleConfig::new(
         ingester_config.pause_ingest_size_bytes,
         ingester_config.persist_memory_threshold_bytes,
         ingester_config.persist_partition_size_threshold_bytes,
@@ -185,9 +200,45 @@ pub async fn create_ingester_server_type(
         Duration::from_secs(ingester_config.persist_partition_cold_threshold_seconds),
         ingester_config.persist_partition_rows_max,
     );
+    if let Some(target) = ingester_config.persist_memory_target_bytes {
+        if target > ingester_config.persist_memory_threshold_bytes {
+            return Err(Error::PersistMemoryTarget);
+        }
+        lifecycle_config = lifecycle_config.with_persist_memory_target(target);
+    }
+    if let Some(threshold) = ingester_config.persist_hot_partition_bytes_per_second {
+        if threshold == 0 {
+            return Err(Error::HotPartitionThreshold);
+        }
+        lifecycle_config = lifecycle_config.with_hot_partition_threshold(threshold);
+    }
+
+    if ingester_config.persist_max_parallelism == 0
+        || ingester_config.persist_memory_budget_bytes == 0
+    {
+        return Err(Error::PersistLimits);
+    }
+    let mut persist_config = PersistConfig::new(
+        ingester_config.persist_max_parallelism,
+        ingester_config.persist_memory_budget_bytes,
+    );
+    if ingester_config.persist_max_file_rows == Some(0)
+        || ingester_config.persist_max_file_bytes == Some(0)
+    {
+        return Err(Error::PersistFileLimits);
+    }
+    if let Some(rows) = ingester_config.persist_max_file_rows {
+        persist_config = persist_config.with_max_file_rows(rows);
+    }
+    if let Some(bytes) = ingester_config.persist_max_file_bytes {
+        persist_config = persist_config.with_max_file_bytes(bytes);
+    }
+
     let ingest_handler = Arc::new(
         IngestHandlerImpl::new(
             lifecycle_config,
+            persist_config,
+            ingester_config.wal_directory.map(Wal::new),
             topic,
             shards,
             catalog,
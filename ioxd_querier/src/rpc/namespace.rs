@@ -30,6 +30,14 @@ fn namespace_to_proto(namespace: Namespace) -> proto::Namespace {
     proto::Namespace {
         id: namespace.id.get(),
         name: namespace.name,
+        retention_period_ns: namespace.retention_period_ns,
+        max_tables: namespace.max_tables,
+        max_columns_per_table: namespace.max_columns_per_table,
+        partition_template: namespace
+            .partition_template
+            .map(|template| proto::PartitionTemplate {
+                parts: template.parts.iter().map(ToString::to_string).collect(),
+            }),
     }
 }
 
@@ -49,6 +57,99 @@ impl proto::namespace_service_server::NamespaceService for NamespaceServiceImpl
             namespaces,
         }))
     }
+
+    async fn get_namespace(
+        &self,
+        request: tonic::Request<proto::GetNamespaceRequest>,
+    ) -> Result<tonic::Response<proto::GetNamespaceResponse>, tonic::Status> {
+        let name = request.into_inner().name;
+
+        let namespace = self
+            .server
+            .namespaces()
+            .await
+            .into_iter()
+            .find(|ns| ns.name == name)
+            .ok_or_else(|| tonic::Status::not_found(format!("namespace {name} not found")))?;
+
+        Ok(tonic::Response::new(proto::GetNamespaceResponse {
+            namespace: Some(namespace_to_proto(namespace)),
+        }))
+    }
+
+    async fn create_namespace(
+        &self,
+        _request: tonic::Request<proto::CreateNamespaceRequest>,
+    ) -> Result<tonic::Response<proto::CreateNamespaceResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "namespaces are created through the router",
+        ))
+    }
+
+    async fn update_namespace_retention(
+        &self,
+        _request: tonic::Request<proto::UpdateNamespaceRetentionRequest>,
+    ) -> Result<tonic::Response<proto::UpdateNamespaceRetentionResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "namespaces are updated through the router",
+        ))
+    }
+
+    async fn delete_namespace(
+        &self,
+        _request: tonic::Request<proto::DeleteNamespaceRequest>,
+    ) -> Result<tonic::Response<proto::DeleteNamespaceResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "namespaces are deleted through the router",
+        ))
+    }
+
+    async fn update_namespace_service_protection_limit(
+        &self,
+        _request: tonic::Request<proto::UpdateNamespaceServiceProtectionLimitRequest>,
+    ) -> Result<tonic::Response<proto::UpdateNamespaceServiceProtectionLimitResponse>, tonic::Status>
+    {
+        Err(tonic::Status::unimplemented(
+            "namespaces are updated through the router",
+        ))
+    }
+
+    async fn update_namespace_partition_template(
+        &self,
+        _request: tonic::Request<proto::UpdateNamespacePartitionTemplateRequest>,
+    ) -> Result<tonic::Response<proto::UpdateNamespacePartitionTemplateResponse>, tonic::Status>
+    {
+        Err(tonic::Status::unimplemented(
+            "namespaces are updated through the router",
+        ))
+    }
+
+    async fn validate_partition_template(
+        &self,
+        _request: tonic::Request<proto::ValidatePartitionTemplateRequest>,
+    ) -> Result<tonic::Response<proto::ValidatePartitionTemplateResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "partition templates are validated through the router",
+        ))
+    }
+
+    async fn invalidate_namespace_cache(
+        &self,
+        _request: tonic::Request<proto::InvalidateNamespaceCacheRequest>,
+    ) -> Result<tonic::Response<proto::InvalidateNamespaceCacheResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "namespace caches are invalidated through the router",
+        ))
+    }
+
+    async fn refresh_namespace_cache(
+        &self,
+        _request: tonic::Request<proto::RefreshNamespaceCacheRequest>,
+    ) -> Result<tonic::Response<proto::RefreshNamespaceCacheResponse>, tonic::Status> {
+        Err(tonic::Status::unimplemented(
+            "namespace caches are refreshed through the router",
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -134,14 +235,41 @@ mod tests {
                     proto::Namespace {
                         id: 1,
                         name: "namespace2".to_string(),
+                        retention_period_ns: None,
+                        max_tables: iox_catalog::DEFAULT_MAX_TABLES,
+                        max_columns_per_table: iox_catalog::DEFAULT_MAX_COLUMNS_PER_TABLE,
+                        partition_template: None,
                     },
                     proto::Namespace {
                         id: 2,
                         name: "namespace1".to_string(),
+                        retention_period_ns: None,
+                        max_tables: iox_catalog::DEFAULT_MAX_TABLES,
+                        max_columns_per_table: iox_catalog::DEFAULT_MAX_COLUMNS_PER_TABLE,
+                        partition_template: None,
                     },
                 ]
             }
         );
+
+        let namespace = service
+            .get_namespace(tonic::Request::new(proto::GetNamespaceRequest {
+                name: "namespace1".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner()
+            .namespace
+            .unwrap();
+        assert_eq!(namespace.id, 2);
+
+        let err = service
+            .get_namespace(tonic::Request::new(proto::GetNamespaceRequest {
+                name: "namespace3".to_string(),
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), tonic::Code::NotFound);
     }
 
     async fn get_namespaces(service: &NamespaceServiceImpl) -> proto::GetNamespacesResponse {
@@ -1,12 +1,18 @@
 use async_trait::async_trait;
+use backoff::BackoffConfig;
 use clap_blocks::write_buffer::WriteBufferConfig;
-use data_types::{DatabaseName, PartitionTemplate, TemplatePart};
+use data_types::{DatabaseName, DeletePredicate, PartitionTemplate, ShardIndex, TemplatePart};
 use hashbrown::HashMap;
-use hyper::{Body, Request, Response};
+use hyper::{
+    header::{HeaderValue, RETRY_AFTER},
+    Body, HeaderMap, Request, Response,
+};
 use iox_catalog::interface::Catalog;
+use iox_time::SystemProvider;
 use ioxd_common::{
     add_service,
     http::error::{HttpApiError, HttpApiErrorSource},
+    reexport::tonic_health::{server::HealthReporter, ServingStatus},
     rpc::RpcBuilderInput,
     serve_builder,
     server_type::{CommonServerState, RpcError, ServerType},
@@ -19,30 +25,49 @@ use observability_deps::tracing::info;
 use router::{
     dml_handlers::{
         DmlHandler, DmlHandlerChainExt, FanOutAdaptor, InstrumentationDecorator,
-        NamespaceAutocreation, Partitioner, SchemaValidator, ShardedWriteBuffer,
-        WriteSummaryAdapter,
+        NamespaceAutocreation, Partitioner, RetentionValidator, SchemaConflictPolicy,
+        SchemaValidator, ShardedWriteBuffer, WriteSummaryAdapter,
     },
     namespace_cache::{
-        metrics::InstrumentedCache, MemoryNamespaceCache, NamespaceCache, ShardedCache,
+        metrics::InstrumentedCache, spawn_refresh_task, MemoryNamespaceCache, NamespaceCache,
+        ShardedCache,
     },
     server::{
-        grpc::{sharder::ShardService, GrpcDelegate},
-        http::HttpDelegate,
+        grpc::{
+            auth::{GrpcAuthPolicy, TokenGrant, TokenGrantError},
+            delete::DeleteService,
+            sharder::ShardService,
+            GrpcDelegate,
+        },
+        http::{
+            CorsPolicy, HttpDelegate, NamespaceGrant, NamespaceGrantError, OrgRateLimiter,
+            RequestLimits, StaticTokenAuthorizer,
+        },
         RouterServer,
     },
-    shard::Shard,
+    shard::{CircuitBreakerConfig, Shard},
+    usage::{spawn_flush_task, UsageAccountant},
+    write_buffer_health::{spawn_probe_task, WriteBufferHealth},
 };
-use sharder::{JumpHash, Sharder};
+use sharder::{HashRing, JumpHash, OverrideSharder, Sharder, TableOverrides};
 use std::{
     collections::BTreeSet,
     fmt::{Debug, Display},
+    num::{NonZeroU64, NonZeroUsize},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 use thiserror::Error;
+use tokio::sync::watch;
 use tokio_util::sync::CancellationToken;
+use tonic::codec::CompressionEncoding;
 use trace::TraceCollector;
 use write_summary::WriteSummary;
 
+mod limits;
+pub use limits::LimitsFileError;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("failed to initialise write buffer connection: {0}")]
@@ -62,18 +87,76 @@ pub enum Error {
 
     #[error("Failed to init shard grpc service: {0}")]
     ShardServiceInit(iox_catalog::interface::Error),
+
+    #[error("Invalid gRPC admin token grant: {0}")]
+    GrpcAdminToken(#[from] TokenGrantError),
+
+    #[error("Invalid HTTP token grant: {0}")]
+    HttpAuthToken(#[from] NamespaceGrantError),
+
+    #[error("Usage accounting period must be at least one second, got {0:?}")]
+    UsageAccountingPeriod(Duration),
+
+    #[error("Request limits error: {0}")]
+    LimitsFile(#[from] LimitsFileError),
 }
 
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
-pub struct RouterServerType<D, S> {
-    server: RouterServer<D, S>,
+/// The maximum duration a write to the write buffer is retried for after a
+/// transient error, before the error is returned to the client.
+const WRITE_RETRY_DEADLINE: Duration = Duration::from_secs(5);
+
+/// The interval at which the health of the dependencies of the gRPC services
+/// is probed and reported by the gRPC health service.
+const GRPC_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The interval at which the connectivity of each write buffer shard is
+/// probed.
+const WRITE_BUFFER_HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The interval at which the hash ring membership is reloaded from the
+/// catalog, bounding how long routers may disagree on it after a reshard.
+const SHARD_RING_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The sharder used by the router: a [`BaseSharder`] across all shards, with
+/// specific tables optionally pinned to a shard.
+type RouterSharder = OverrideSharder<BaseSharder, Arc<Shard>>;
+
+/// The sharder mapping tables to shards, prior to applying table shard
+/// overrides.
+#[derive(Debug)]
+enum BaseSharder {
+    /// Shard with a [`JumpHash`] over a fixed set of shards.
+    JumpHash(JumpHash<Arc<Shard>>),
+    /// Shard with a consistent [`HashRing`], allowing shards to be added and
+    /// removed at runtime.
+    HashRing(Arc<HashRing<ShardIndex, Arc<Shard>>>),
+}
+
+impl<P> Sharder<P> for BaseSharder
+where
+    JumpHash<Arc<Shard>>: Sharder<P>,
+    HashRing<ShardIndex, Arc<Shard>>: Sharder<P, Item = <JumpHash<Arc<Shard>> as Sharder<P>>::Item>,
+{
+    type Item = <JumpHash<Arc<Shard>> as Sharder<P>>::Item;
+
+    fn shard(&self, table: &str, namespace: &DatabaseName<'_>, payload: &P) -> Self::Item {
+        match self {
+            Self::JumpHash(s) => s.shard(table, namespace, payload),
+            Self::HashRing(s) => s.shard(table, namespace, payload),
+        }
+    }
+}
+
+pub struct RouterServerType<D, S, C> {
+    server: RouterServer<D, S, C>,
     shutdown: CancellationToken,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 }
 
-impl<D, S> RouterServerType<D, S> {
-    pub fn new(server: RouterServer<D, S>, common_state: &CommonServerState) -> Self {
+impl<D, S, C> RouterServerType<D, S, C> {
+    pub fn new(server: RouterServer<D, S, C>, common_state: &CommonServerState) -> Self {
         Self {
             server,
             shutdown: CancellationToken::new(),
@@ -82,17 +165,25 @@ impl<D, S> RouterServerType<D, S> {
     }
 }
 
-impl<D, S> std::fmt::Debug for RouterServerType<D, S> {
+impl<D, S, C> std::fmt::Debug for RouterServerType<D, S, C> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "Router")
     }
 }
 
 #[async_trait]
-impl<D, S> ServerType for RouterServerType<D, S>
+impl<D, S, C> ServerType for RouterServerType<D, S, C>
 where
-    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary> + 'static,
-    S: Sharder<(), Item = Arc<Shard>> + Clone + 'static,
+    D: DmlHandler<
+            WriteInput = HashMap<String, MutableBatch>,
+            WriteOutput = WriteSummary,
+            DeleteOutput = WriteSummary,
+        > + 'static,
+    S: Sharder<(), Item = Arc<Shard>>
+        + Sharder<DeletePredicate, Item = Vec<Arc<Shard>>>
+        + Clone
+        + 'static,
+    C: NamespaceCache + Clone + 'static,
 {
     /// Return the [`metric::Registry`] used by the router.
     fn metric_registry(&self) -> Arc<Registry> {
@@ -111,14 +202,24 @@ where
         &self,
         req: Request<Body>,
     ) -> Result<Response<Body>, Box<dyn HttpApiErrorSource>> {
-        self.server
-            .http()
-            .route(req)
+        let http = self.server.http();
+        // Error responses must also carry the CORS headers (if any) for
+        // browser-based clients to be able to read them.
+        let cors_headers = http.cors_headers(req.headers());
+        http.route(req)
             .await
-            .map_err(IoxHttpErrorAdaptor)
+            .map_err(|e| IoxHttpErrorAdaptor::new(e).with_headers(cors_headers))
             .map_err(|e| Box::new(e) as _)
     }
 
+    /// Returns the InfluxDB compatible health check response of the router
+    /// [`HttpDelegate`].
+    ///
+    /// [`HttpDelegate`]: router::server::http::HttpDelegate
+    fn health(&self) -> Response<Body> {
+        self.server.http().health()
+    }
+
     /// Registers the services exposed by the router [`GrpcDelegate`] delegate.
     ///
     /// [`GrpcDelegate`]: router::server::grpc::GrpcDelegate
@@ -128,6 +229,18 @@ where
         add_service!(builder, self.server.grpc().catalog_service());
         add_service!(builder, self.server.grpc().object_store_service());
         add_service!(builder, self.server.grpc().shard_service());
+        add_service!(builder, self.server.grpc().write_service());
+        add_service!(builder, self.server.grpc().flight_service());
+        add_service!(builder, self.server.grpc().namespace_service());
+        add_service!(builder, self.server.grpc().delete_service());
+
+        // Update the status of each service in the health service as the
+        // health of its dependencies changes.
+        tokio::spawn(report_service_health(
+            Arc::clone(&self),
+            builder.health_reporter.clone(),
+        ));
+
         serve_builder!(builder);
 
         Ok(())
@@ -146,11 +259,31 @@ where
 /// satisfies the requirements of ioxd's runner framework, keeping the
 /// two decoupled.
 #[derive(Debug)]
-pub struct IoxHttpErrorAdaptor(router::server::http::Error);
+pub struct IoxHttpErrorAdaptor {
+    err: router::server::http::Error,
+
+    /// Additional headers to include in the error response.
+    headers: HeaderMap,
+}
+
+impl IoxHttpErrorAdaptor {
+    pub fn new(err: router::server::http::Error) -> Self {
+        Self {
+            err,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Include `headers` in the error response.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        self
+    }
+}
 
 impl Display for IoxHttpErrorAdaptor {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        Display::fmt(&self.0, f)
+        Display::fmt(&self.err, f)
     }
 }
 
@@ -158,11 +291,26 @@ impl std::error::Error for IoxHttpErrorAdaptor {}
 
 impl HttpApiErrorSource for IoxHttpErrorAdaptor {
     fn to_http_api_error(&self) -> HttpApiError {
-        HttpApiError::new(self.0.as_status_code(), self.to_string())
+        let mut err = HttpApiError::new(self.err.as_status_code(), self.to_string())
+            .with_detail("error_code", self.err.error_code());
+        if let Some(secs) = self.err.retry_after() {
+            err = err.with_header(RETRY_AFTER, HeaderValue::from(secs));
+        }
+        if let Some(n) = self.err.in_flight_requests() {
+            err = err.with_detail("in_flight_requests", n);
+        }
+        if let Some(timeout) = self.err.timeout() {
+            err = err.with_detail("timeout_ms", timeout.as_millis() as u64);
+        }
+        for (name, value) in &self.headers {
+            err = err.with_header(name.clone(), value.clone());
+        }
+        err
     }
 }
 
 /// Instantiate a router server
+#[allow(clippy::too_many_arguments)]
 pub async fn create_router_server_type(
     common_state: &CommonServerState,
     metrics: Arc<metric::Registry>,
@@ -171,11 +319,44 @@ pub async fn create_router_server_type(
     write_buffer_config: &WriteBufferConfig,
     query_pool_name: &str,
     request_limit: usize,
+    http_limits_file: Option<PathBuf>,
+    http_auth_tokens: Vec<String>,
+    org_write_lines_per_second: Option<NonZeroU64>,
+    org_write_bytes_per_second: Option<NonZeroU64>,
+    cors_allowed_origins: Vec<String>,
+    cors_max_age: Option<Duration>,
+    max_write_lines: Option<NonZeroUsize>,
+    max_write_fields_per_line: Option<NonZeroUsize>,
+    max_write_tags_per_line: Option<NonZeroUsize>,
+    http_request_timeout: Option<Duration>,
+    shard_ring_vnodes: Option<NonZeroUsize>,
+    shard_circuit_breaker_error_threshold: Option<NonZeroUsize>,
+    shard_circuit_breaker_cool_down: Option<Duration>,
+    schema_coerce_namespaces: Vec<String>,
+    table_metrics_max_tables: Option<NonZeroUsize>,
+    max_parallel_writes: Option<NonZeroUsize>,
+    namespace_cache_refresh_interval: Option<Duration>,
+    grpc_admin_tokens: Vec<String>,
+    grpc_compression: Option<CompressionEncoding>,
+    grpc_max_message_size: Option<NonZeroUsize>,
+    write_provenance_annotations: bool,
+    usage_accounting_period: Option<Duration>,
 ) -> Result<Arc<dyn ServerType>> {
+    // Fast-fail writes to persistently erroring shards, if configured.
+    let circuit_breaker = shard_circuit_breaker_error_threshold.map(|threshold| {
+        let default = CircuitBreakerConfig::default();
+        CircuitBreakerConfig {
+            error_threshold: threshold.get(),
+            cool_down: shard_circuit_breaker_cool_down.unwrap_or(default.cool_down),
+        }
+    });
+
     // Initialise the sharded write buffer and instrument it with DML handler
     // metrics.
-    let (write_buffer, sharder) = init_write_buffer(
+    let (write_buffer, sharder, write_buffer_health) = init_write_buffer(
         write_buffer_config,
+        shard_ring_vnodes,
+        circuit_breaker,
         Arc::clone(&metrics),
         common_state.trace_collector(),
     )
@@ -197,17 +378,45 @@ pub async fn create_router_server_type(
         .await
         .expect("namespace cache pre-warming failed");
 
+    // Periodically reload the cached schemas from the catalog, applying
+    // out-of-band changes without a restart.
+    if let Some(interval) = namespace_cache_refresh_interval.filter(|v| !v.is_zero()) {
+        spawn_refresh_task(Arc::clone(&catalog), &ns_cache, interval, &*metrics);
+    }
+
+    // The namespace gRPC service applies namespace updates to the same cache.
+    let grpc_ns_cache = Arc::clone(&ns_cache);
+
+    // Initialise and instrument the retention validator, dropping points
+    // older than the namespace retention period.
+    let retention_validator = RetentionValidator::new(
+        Arc::clone(&catalog),
+        Arc::clone(&ns_cache),
+        Arc::new(SystemProvider::new()),
+        &*metrics,
+    );
+    let retention_validator =
+        InstrumentationDecorator::new("retention_validator", &*metrics, retention_validator);
+
     // Initialise and instrument the schema validator
-    let schema_validator =
+    let mut schema_validator =
         SchemaValidator::new(Arc::clone(&catalog), Arc::clone(&ns_cache), &*metrics);
+    for namespace in schema_coerce_namespaces {
+        schema_validator =
+            schema_validator.with_conflict_policy(namespace, SchemaConflictPolicy::Coerce);
+    }
     let schema_validator =
         InstrumentationDecorator::new("schema_validator", &*metrics, schema_validator);
 
-    // Add a write partitioner into the handler stack that splits by the date
-    // portion of the write's timestamp.
-    let partitioner = Partitioner::new(PartitionTemplate {
-        parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
-    });
+    // Add a write partitioner into the handler stack that splits by the
+    // partition template of the namespace, defaulting to the date portion of
+    // the write's timestamp.
+    let partitioner = Partitioner::new(
+        PartitionTemplate {
+            parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
+        },
+        Arc::clone(&ns_cache),
+    );
     let partitioner = InstrumentationDecorator::new("partitioner", &*metrics, partitioner);
 
     ////////////////////////////////////////////////////////////////////////////
@@ -257,12 +466,20 @@ pub async fn create_router_server_type(
     //
     ////////////////////////////////////////////////////////////////////////////
 
-    let parallel_write = WriteSummaryAdapter::new(FanOutAdaptor::new(write_buffer));
+    // Dispatch the partitioned writes of a request to the write buffer
+    // concurrently, optionally bounding the number in flight, and merge their
+    // write summaries.
+    let mut parallel_write = FanOutAdaptor::new(write_buffer);
+    if let Some(max) = max_parallel_writes {
+        parallel_write = parallel_write.with_max_parallelism(max);
+    }
+    let parallel_write = WriteSummaryAdapter::new(parallel_write);
 
     // Build the chain of DML handlers that forms the request processing
     // pipeline, starting with the namespace creator (for testing purposes) and
     // write partitioner that yields a set of partitioned batches.
     let handler_stack = ns_creator
+        .and_then(retention_validator)
         .and_then(schema_validator)
         .and_then(partitioner)
         // Once writes have been partitioned, they are processed in parallel.
@@ -277,39 +494,203 @@ pub async fn create_router_server_type(
             parallel_write,
         ));
 
-    // Record the overall request handling latency
-    let handler_stack = InstrumentationDecorator::new("request", &*metrics, handler_stack);
+    // Record the overall request handling latency, optionally broken down by
+    // the tables in each request.
+    let mut handler_stack = InstrumentationDecorator::new("request", &*metrics, handler_stack);
+    if let Some(max_tables) = table_metrics_max_tables {
+        handler_stack = handler_stack.with_table_metrics(&*metrics, max_tables.get());
+    }
 
-    // Initialise the shard-mapping gRPC service.
+    // Account the lines & bytes written to each namespace per period,
+    // flushing the usage of each completed period to the catalog.
+    let usage = usage_accounting_period
+        .map(|period| {
+            if period < Duration::from_secs(1) {
+                return Err(Error::UsageAccountingPeriod(period));
+            }
+            let accountant = Arc::new(UsageAccountant::new(
+                Arc::clone(&catalog),
+                Arc::new(SystemProvider::new()),
+                period,
+                &metrics,
+            ));
+            spawn_flush_task(&accountant, period);
+            Ok(accountant)
+        })
+        .transpose()?;
+
+    // Initialise the delete and shard-mapping gRPC services.
+    let delete_service = DeleteService::new(Arc::clone(&sharder));
     let shard_service = init_shard_service(sharder, write_buffer_config, catalog).await?;
 
+    // The request limits configured on the command line, optionally replaced
+    // by those in the limits file, which is reloaded on SIGHUP.
+    let base_limits = RequestLimits {
+        max_request_bytes: common_state.run_config().max_http_request_size,
+        max_requests: request_limit,
+        lines_per_second: org_write_lines_per_second,
+        bytes_per_second: org_write_bytes_per_second,
+    };
+    let (limits_tx, limits_rx) = match &http_limits_file {
+        Some(path) => watch::channel(limits::load_limits(path, base_limits)?),
+        None => watch::channel(base_limits),
+    };
+    if let Some(path) = http_limits_file {
+        info!(?path, "reloading request limits file on SIGHUP");
+        limits::spawn_reload_task(path, base_limits, limits_tx);
+    }
+
     // Initialise the API delegates
     let handler_stack = Arc::new(handler_stack);
-    let http = HttpDelegate::new(
-        common_state.run_config().max_http_request_size,
-        request_limit,
+    let mut http = HttpDelegate::new(
+        base_limits.max_request_bytes,
+        base_limits.max_requests,
         Arc::clone(&handler_stack),
         &metrics,
     );
-    let grpc = GrpcDelegate::new(schema_catalog, object_store, shard_service);
+    // Limit the rate at which each org may write, if configured.
+    if org_write_lines_per_second.is_some() || org_write_bytes_per_second.is_some() {
+        http = http.with_rate_limiter(OrgRateLimiter::new(
+            org_write_lines_per_second,
+            org_write_bytes_per_second,
+        ));
+    }
+    http = http
+        .with_limits(limits_rx)
+        .with_provenance_annotations(write_provenance_annotations)
+        .with_write_buffer_health(Arc::clone(&write_buffer_health));
+    if let Some(usage) = usage {
+        http = http.with_usage_accounting(usage);
+    }
+
+    // Require HTTP writes & deletes to present a token granted access to the
+    // target namespace, if any tokens are configured.
+    if !http_auth_tokens.is_empty() {
+        let authorizer = http_auth_tokens
+            .iter()
+            .map(|grant| grant.parse::<NamespaceGrant>())
+            .try_fold(StaticTokenAuthorizer::default(), |authorizer, grant| {
+                grant.map(|grant| authorizer.with_grant(grant))
+            })?;
+        http = http.with_authorizer(Arc::new(authorizer));
+    }
+
+    // Permit browser-based clients served from the allowed origins to make
+    // requests, if any origins are configured.
+    if !cors_allowed_origins.is_empty() {
+        let mut policy = match cors_allowed_origins.iter().any(|v| v == "*") {
+            true => CorsPolicy::any_origin(),
+            false => CorsPolicy::new(cors_allowed_origins),
+        };
+        if let Some(max_age) = cors_max_age {
+            policy = policy.with_max_age(max_age);
+        }
+        http = http.with_cors(policy);
+    }
+
+    // Reject writes exceeding the configured limits on their shape.
+    if let Some(max) = max_write_lines {
+        http = http.with_max_lines(max.get());
+    }
+    if let Some(max) = max_write_fields_per_line {
+        http = http.with_max_fields_per_line(max.get());
+    }
+    if let Some(max) = max_write_tags_per_line {
+        http = http.with_max_tags_per_line(max.get());
+    }
+
+    // Abort requests that are not serviced within the configured timeout.
+    if let Some(timeout) = http_request_timeout.filter(|v| !v.is_zero()) {
+        http = http.with_request_timeout(timeout);
+    }
+    let mut grpc = GrpcDelegate::new(
+        Arc::clone(&handler_stack),
+        schema_catalog,
+        grpc_ns_cache,
+        object_store,
+        shard_service,
+        delete_service,
+        topic_id,
+        query_id,
+    );
+
+    // Require the administrative gRPC services to be called with an
+    // authorized token, if any tokens are configured.
+    if !grpc_admin_tokens.is_empty() {
+        let policy = grpc_admin_tokens
+            .iter()
+            .map(|grant| grant.parse::<TokenGrant>())
+            .try_fold(GrpcAuthPolicy::default(), |policy, grant| {
+                grant.map(|grant| policy.with_grant(grant))
+            })?;
+        grpc = grpc.with_admin_auth(Arc::new(policy));
+    }
+    if let Some(encoding) = grpc_compression {
+        grpc = grpc.with_compression(encoding);
+    }
+    if let Some(max) = grpc_max_message_size {
+        grpc = grpc.with_max_message_size(max.get());
+    }
+    grpc = grpc.with_write_buffer_health(write_buffer_health);
 
     let router_server = RouterServer::new(http, grpc, metrics, common_state.trace_collector());
     let server_type = Arc::new(RouterServerType::new(router_server, common_state));
     Ok(server_type)
 }
 
+/// Periodically probe the dependencies of the gRPC services of `server`,
+/// reporting the resulting per-service status to `reporter` until the server
+/// is shut down.
+async fn report_service_health<D, S, C>(
+    server: Arc<RouterServerType<D, S, C>>,
+    mut reporter: HealthReporter,
+) where
+    D: DmlHandler<
+            WriteInput = HashMap<String, MutableBatch>,
+            WriteOutput = WriteSummary,
+            DeleteOutput = WriteSummary,
+        > + 'static,
+    S: Sharder<(), Item = Arc<Shard>>
+        + Sharder<DeletePredicate, Item = Vec<Arc<Shard>>>
+        + Clone
+        + 'static,
+    C: NamespaceCache + Clone + 'static,
+{
+    let mut ticker = tokio::time::interval(GRPC_HEALTH_CHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = server.shutdown.cancelled() => return,
+            _ = ticker.tick() => {},
+        }
+
+        for health in server.server.grpc().service_health().await {
+            let status = match health.serving {
+                true => ServingStatus::Serving,
+                false => ServingStatus::NotServing,
+            };
+            reporter.set_service_status(health.service, status).await;
+        }
+    }
+}
+
 /// Initialise the [`ShardedWriteBuffer`] with one shard per Kafka partition,
-/// using [`JumpHash`] to shard operations by their destination namespace &
-/// table name.
+/// using [`JumpHash`] (or a [`HashRing`] with `shard_ring_vnodes` virtual
+/// nodes per shard, if specified) to shard operations by their destination
+/// namespace & table name, unless the table is pinned to a specific shard by
+/// an override.
 ///
-/// Returns both the DML handler and the sharder it uses.
+/// Returns the DML handler, the sharder it uses, and the health of the write
+/// buffer shards, which is probed in the background.
 async fn init_write_buffer(
     write_buffer_config: &WriteBufferConfig,
+    shard_ring_vnodes: Option<NonZeroUsize>,
+    circuit_breaker: Option<CircuitBreakerConfig>,
     metrics: Arc<metric::Registry>,
     trace_collector: Option<Arc<dyn TraceCollector>>,
 ) -> Result<(
-    ShardedWriteBuffer<Arc<JumpHash<Arc<Shard>>>>,
-    Arc<JumpHash<Arc<Shard>>>,
+    ShardedWriteBuffer<Arc<RouterSharder>>,
+    Arc<RouterSharder>,
+    Arc<WriteBufferHealth>,
 )> {
     let write_buffer = Arc::new(
         write_buffer_config
@@ -335,25 +716,57 @@ async fn init_write_buffer(
         return Err(Error::Sharder);
     }
 
+    // Probe the connectivity of each shard, failing the health checks of the
+    // router while any shard is unreachable.
+    let health = Arc::new(WriteBufferHealth::new(Arc::clone(&write_buffer), &metrics));
+    spawn_probe_task(&health, WRITE_BUFFER_HEALTH_CHECK_INTERVAL);
+
     // Initialise the sharder that maps (table, namespace, payload) to shards.
-    let sharder = Arc::new(JumpHash::new(
-        shards
-            .into_iter()
-            .map(|shard_index| Shard::new(shard_index, Arc::clone(&write_buffer), &metrics))
-            .map(Arc::new),
+    //
+    // Transient write buffer errors are retried with an exponential backoff
+    // by each shard, rather than failing the client request on the first
+    // error. Only the failed shards of a write are retried.
+    let shards = shards.into_iter().map(|shard_index| {
+        let mut shard = Shard::new(shard_index, Arc::clone(&write_buffer), &metrics);
+        if let Some(config) = circuit_breaker {
+            shard = shard.with_circuit_breaker(config, &metrics);
+        }
+        Arc::new(shard.with_retry(
+            BackoffConfig {
+                init_backoff: Duration::from_millis(50),
+                max_backoff: Duration::from_secs(1),
+                ..Default::default()
+            },
+            WRITE_RETRY_DEADLINE,
+            &metrics,
+        ))
+    });
+    let sharder = match shard_ring_vnodes {
+        Some(vnodes) => BaseSharder::HashRing(Arc::new(HashRing::new(
+            vnodes,
+            shards.map(|s| (s.shard_index(), s)),
+        ))),
+        None => BaseSharder::JumpHash(JumpHash::new(shards)),
+    };
+
+    // The table overrides are loaded from the catalog by the shard service.
+    let sharder = Arc::new(OverrideSharder::new(
+        sharder,
+        Arc::new(TableOverrides::default()),
     ));
 
-    Ok((ShardedWriteBuffer::new(Arc::clone(&sharder)), sharder))
+    Ok((
+        ShardedWriteBuffer::new(Arc::clone(&sharder)),
+        sharder,
+        health,
+    ))
 }
 
-async fn init_shard_service<S>(
-    sharder: S,
+async fn init_shard_service(
+    sharder: Arc<RouterSharder>,
     write_buffer_config: &WriteBufferConfig,
     catalog: Arc<dyn Catalog>,
-) -> Result<ShardService<S>>
-where
-    S: Send + Sync,
-{
+) -> Result<ShardService<Arc<RouterSharder>>> {
     // Get the TopicMetadata from the catalog for the configured topic.
     let topic = catalog
         .repositories()
@@ -365,10 +778,34 @@ where
             topic_name: write_buffer_config.topic().to_string(),
         })?;
 
-    // Initialise the sharder
-    ShardService::new(sharder, topic, catalog)
+    // Initialise the sharder, loading the table shard overrides from the
+    // catalog.
+    let overrides = Arc::clone(sharder.overrides());
+    let (shards, ring) = match sharder.inner() {
+        BaseSharder::JumpHash(s) => (s.shards().to_vec(), None),
+        BaseSharder::HashRing(s) => (
+            s.shards().into_iter().map(|(_, shard, _)| shard).collect(),
+            Some(Arc::clone(s)),
+        ),
+    };
+
+    let service = ShardService::new(sharder, overrides, shards, topic, catalog)
         .await
-        .map_err(Error::ShardServiceInit)
+        .map_err(Error::ShardServiceInit)?;
+
+    // Apply the hash ring membership persisted in the catalog, and reload it
+    // periodically to apply reshards made through other routers.
+    Ok(match ring {
+        Some(ring) => {
+            let service = service
+                .with_hash_ring(ring)
+                .await
+                .map_err(Error::ShardServiceInit)?;
+            service.spawn_hash_ring_refresh(SHARD_RING_REFRESH_INTERVAL);
+            service
+        }
+        None => service,
+    })
 }
 
 /// Pre-populate `cache` with the all existing schemas in `catalog`.
@@ -434,4 +871,95 @@ mod tests {
 
         assert!(got.tables.get("name").is_some());
     }
+
+    #[test]
+    fn test_retry_after_header() {
+        let err = IoxHttpErrorAdaptor::new(router::server::http::Error::RateLimited {
+            org: "bananas".to_string(),
+            retry_after: std::time::Duration::from_millis(1500),
+        });
+        let response = err.to_http_api_error().response();
+
+        assert_eq!(response.status(), hyper::StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "2");
+
+        let err = IoxHttpErrorAdaptor::new(router::server::http::Error::NoHandler);
+        let response = err.to_http_api_error().response();
+        assert!(response.headers().get(RETRY_AFTER).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_request_limit_response() {
+        let err = IoxHttpErrorAdaptor::new(router::server::http::Error::RequestLimit {
+            in_flight: 42,
+            retry_after: std::time::Duration::from_millis(2500),
+        });
+        let response = err.to_http_api_error().response();
+
+        assert_eq!(response.status(), hyper::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers().get(RETRY_AFTER).unwrap(), "3");
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "unavailable");
+        assert_eq!(body["error_code"], "request_limit_exceeded");
+        assert_eq!(body["in_flight_requests"], 42);
+    }
+
+    #[tokio::test]
+    async fn test_timeout_response() {
+        let err = IoxHttpErrorAdaptor::new(router::server::http::Error::Timeout(
+            std::time::Duration::from_secs(5),
+        ));
+        let response = err.to_http_api_error().response();
+
+        assert_eq!(response.status(), hyper::StatusCode::GATEWAY_TIMEOUT);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "gateway timeout");
+        assert_eq!(body["timeout_ms"], 5000);
+    }
+
+    #[tokio::test]
+    async fn test_error_body() {
+        let err = IoxHttpErrorAdaptor::new(router::server::http::Error::NoHandler);
+        let response = err.to_http_api_error().response();
+
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[hyper::header::CONTENT_TYPE],
+            "application/json"
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "code": "not found",
+                "message": "not found",
+                "error_code": "not_found",
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_extra_headers() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN,
+            HeaderValue::from_static("https://bananas.example"),
+        );
+
+        let err =
+            IoxHttpErrorAdaptor::new(router::server::http::Error::NoHandler).with_headers(headers);
+        let response = err.to_http_api_error().response();
+
+        assert_eq!(response.status(), hyper::StatusCode::NOT_FOUND);
+        assert_eq!(
+            response.headers()[hyper::header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://bananas.example"
+        );
+    }
 }
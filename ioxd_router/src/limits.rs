@@ -0,0 +1,262 @@
+//! Loading of the router HTTP [`RequestLimits`] from a file, which is reloaded
+//! on `SIGHUP` to change the limits without restarting the router.
+
+use observability_deps::tracing::{error, info};
+use router::server::http::RequestLimits;
+use serde::Deserialize;
+use std::{
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tokio::sync::watch;
+
+/// Errors returned when loading a request limits file.
+#[derive(Debug, Error)]
+pub enum LimitsFileError {
+    #[error("failed to read request limits file {path:?}: {source}")]
+    Read {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[error("invalid request limits file {path:?}: {source}")]
+    Parse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+}
+
+/// The contents of a request limits file - a JSON object such as:
+///
+/// ```json
+/// {"max_requests": 100, "lines_per_second": 10000}
+/// ```
+///
+/// Each limit present in the file replaces the value configured on the
+/// command line. A rate of 0 removes the rate limit.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LimitsFile {
+    max_request_bytes: Option<usize>,
+    max_requests: Option<usize>,
+    lines_per_second: Option<u64>,
+    bytes_per_second: Option<u64>,
+}
+
+impl LimitsFile {
+    fn apply(&self, base: RequestLimits) -> RequestLimits {
+        RequestLimits {
+            max_request_bytes: self.max_request_bytes.unwrap_or(base.max_request_bytes),
+            max_requests: self.max_requests.unwrap_or(base.max_requests),
+            lines_per_second: self
+                .lines_per_second
+                .map_or(base.lines_per_second, NonZeroU64::new),
+            bytes_per_second: self
+                .bytes_per_second
+                .map_or(base.bytes_per_second, NonZeroU64::new),
+        }
+    }
+}
+
+/// Read the limits file at `path`, returning `base` with the limits it
+/// specifies applied.
+pub(crate) fn load_limits(
+    path: &Path,
+    base: RequestLimits,
+) -> Result<RequestLimits, LimitsFileError> {
+    let contents = std::fs::read(path).map_err(|source| LimitsFileError::Read {
+        path: path.to_owned(),
+        source,
+    })?;
+    let file: LimitsFile =
+        serde_json::from_slice(&contents).map_err(|source| LimitsFileError::Parse {
+            path: path.to_owned(),
+            source,
+        })?;
+    Ok(file.apply(base))
+}
+
+/// Reload the limits file at `path`, sending the resulting limits to the
+/// [`HttpDelegate`] receiving from `tx`.
+///
+/// The current limits are left unchanged if the file cannot be loaded.
+///
+/// [`HttpDelegate`]: router::server::http::HttpDelegate
+pub(crate) fn reload_limits(
+    path: &Path,
+    base: RequestLimits,
+    tx: &watch::Sender<RequestLimits>,
+) -> Result<RequestLimits, LimitsFileError> {
+    let limits = load_limits(path, base)?;
+    // The delegate holds the receiver for the lifetime of the router.
+    let _ = tx.send(limits);
+    Ok(limits)
+}
+
+/// Reload the limits file at `path` each time the process receives `SIGHUP`.
+#[cfg(unix)]
+pub(crate) fn spawn_reload_task(
+    path: PathBuf,
+    base: RequestLimits,
+    tx: watch::Sender<RequestLimits>,
+) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangup = signal(SignalKind::hangup()).expect("failed to register signal handler");
+
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!(?path, "received SIGHUP, reloading request limits");
+            match reload_limits(&path, base, &tx) {
+                Ok(limits) => info!(?limits, "reloaded request limits"),
+                Err(e) => {
+                    error!(error=%e, "failed to reload request limits, keeping current limits")
+                }
+            }
+        }
+    });
+}
+
+/// There is no `SIGHUP` on this platform - the limits file is only read at
+/// startup.
+#[cfg(not(unix))]
+pub(crate) fn spawn_reload_task(
+    _path: PathBuf,
+    _base: RequestLimits,
+    _tx: watch::Sender<RequestLimits>,
+) {
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use async_trait::async_trait;
+    use data_types::{DatabaseName, DeletePredicate};
+    use hashbrown::HashMap;
+    use hyper::{Body, Request};
+    use mutable_batch::MutableBatch;
+    use router::{
+        dml_handlers::{DmlError, DmlHandler},
+        server::http::{Error, HttpDelegate},
+    };
+    use std::sync::Arc;
+    use test_helpers::make_temp_file;
+    use trace::ctx::SpanContext;
+    use write_summary::WriteSummary;
+
+    const BASE: RequestLimits = RequestLimits {
+        max_request_bytes: 1024,
+        max_requests: 10,
+        lines_per_second: None,
+        bytes_per_second: NonZeroU64::new(100),
+    };
+
+    /// A DML handler accepting all writes & deletes.
+    #[derive(Debug)]
+    struct AcceptHandler;
+
+    #[async_trait]
+    impl DmlHandler for AcceptHandler {
+        type WriteError = DmlError;
+        type DeleteError = DmlError;
+        type WriteInput = HashMap<String, MutableBatch>;
+        type WriteOutput = WriteSummary;
+        type DeleteOutput = WriteSummary;
+
+        async fn write(
+            &self,
+            _namespace: &DatabaseName<'static>,
+            _batches: Self::WriteInput,
+            _span_ctx: Option<SpanContext>,
+        ) -> Result<Self::WriteOutput, Self::WriteError> {
+            Ok(WriteSummary::default())
+        }
+
+        async fn delete(
+            &self,
+            _namespace: &DatabaseName<'static>,
+            _table_name: &str,
+            _predicate: &DeletePredicate,
+            _span_ctx: Option<SpanContext>,
+        ) -> Result<Self::DeleteOutput, Self::DeleteError> {
+            Ok(WriteSummary::default())
+        }
+    }
+
+    #[test]
+    fn test_load_limits() {
+        let file = make_temp_file(r#"{"max_requests": 2, "lines_per_second": 5}"#);
+        let got = load_limits(file.path(), BASE).unwrap();
+        assert_eq!(
+            got,
+            RequestLimits {
+                max_requests: 2,
+                lines_per_second: NonZeroU64::new(5),
+                ..BASE
+            }
+        );
+
+        // A rate of 0 removes the rate limit.
+        let file = make_temp_file(r#"{"bytes_per_second": 0}"#);
+        let got = load_limits(file.path(), BASE).unwrap();
+        assert_eq!(
+            got,
+            RequestLimits {
+                bytes_per_second: None,
+                ..BASE
+            }
+        );
+
+        let file = make_temp_file(r#"{"max_requests": 2, "bananas": 42}"#);
+        let got = load_limits(file.path(), BASE);
+        assert_matches!(got, Err(LimitsFileError::Parse { .. }));
+
+        let got = load_limits(Path::new("/does/not/exist.json"), BASE);
+        assert_matches!(got, Err(LimitsFileError::Read { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_reload_running_delegate() {
+        let metrics = metric::Registry::default();
+        let (tx, rx) = watch::channel(BASE);
+        let delegate = HttpDelegate::new(
+            BASE.max_request_bytes,
+            BASE.max_requests,
+            Arc::new(AcceptHandler),
+            &metrics,
+        )
+        .with_limits(rx);
+
+        let request = || {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .body(Body::from("platanos val=42i 1"))
+                .unwrap()
+        };
+
+        delegate
+            .route(request())
+            .await
+            .expect("write within the limits should succeed");
+
+        // Reduce the maximum request size below the size of the request.
+        let file = make_temp_file(r#"{"max_request_bytes": 10}"#);
+        reload_limits(file.path(), BASE, &tx).unwrap();
+        let err = delegate
+            .route(request())
+            .await
+            .expect_err("write should exceed the reloaded size limit");
+        assert_matches!(err, Error::RequestSizeExceeded(10));
+
+        // An invalid file leaves the current limits in place.
+        std::fs::write(file.path(), "{").unwrap();
+        reload_limits(file.path(), BASE, &tx).unwrap_err();
+        let err = delegate
+            .route(request())
+            .await
+            .expect_err("write should exceed the current size limit");
+        assert_matches!(err, Error::RequestSizeExceeded(10));
+    }
+}
@@ -243,6 +243,44 @@ impl Column {
         mem::size_of::<Self>() + data_size + self.valid.byte_len()
     }
 
+    /// Widen this integer or unsigned integer field column to a float field,
+    /// converting all of its values to `f64`.
+    ///
+    /// Values with a magnitude greater than 2^53 may lose precision. Returns
+    /// false, leaving the column unchanged, if it is not an integer or
+    /// unsigned integer field.
+    pub(crate) fn widen_to_float(&mut self) -> bool {
+        fn widen_stats<T>(stats: &StatValues<T>, f: impl Fn(&T) -> f64) -> StatValues<f64> {
+            StatValues {
+                min: stats.min.as_ref().map(&f),
+                max: stats.max.as_ref().map(&f),
+                total_count: stats.total_count,
+                null_count: stats.null_count,
+                distinct_count: stats.distinct_count,
+            }
+        }
+
+        let data = match &self.data {
+            ColumnData::I64(v, stats)
+                if self.influx_type == InfluxColumnType::Field(InfluxFieldType::Integer) =>
+            {
+                ColumnData::F64(
+                    v.iter().map(|v| *v as f64).collect(),
+                    widen_stats(stats, |v| *v as f64),
+                )
+            }
+            ColumnData::U64(v, stats) => ColumnData::F64(
+                v.iter().map(|v| *v as f64).collect(),
+                widen_stats(stats, |v| *v as f64),
+            ),
+            _ => return false,
+        };
+
+        self.influx_type = InfluxColumnType::Field(InfluxFieldType::Float);
+        self.data = data;
+        true
+    }
+
     /// Converts this column to an arrow [`ArrayRef`]
     pub fn to_arrow(&self) -> Result<ArrayRef> {
         let nulls = self.valid.to_arrow();
@@ -23,8 +23,8 @@ use data_types::StatValues;
 use hashbrown::HashMap;
 use iox_time::Time;
 use schema::selection::Selection;
-use schema::{builder::SchemaBuilder, Schema, TIME_COLUMN_NAME};
-use snafu::{OptionExt, ResultExt, Snafu};
+use schema::{builder::SchemaBuilder, InfluxColumnType, Schema, TIME_COLUMN_NAME};
+use snafu::{ensure, OptionExt, ResultExt, Snafu};
 use std::{collections::BTreeSet, ops::Range};
 
 pub mod column;
@@ -51,6 +51,16 @@ pub enum Error {
     #[snafu(display("Column not found: {}", column))]
     ColumnNotFound { column: String },
 
+    #[snafu(display(
+        "Column {} of type {} cannot be widened to a float",
+        column,
+        influx_type
+    ))]
+    NotWidenable {
+        column: String,
+        influx_type: InfluxColumnType,
+    },
+
     #[snafu(context(false))]
     WriterError { source: writer::Error },
 }
@@ -200,6 +210,29 @@ impl MutableBatch {
         Ok(&self.columns[*idx])
     }
 
+    /// Widen the integer or unsigned integer field `column` to a float field,
+    /// converting all of its values to `f64`.
+    ///
+    /// Values with a magnitude greater than 2^53 may lose precision.
+    pub fn widen_to_float(&mut self, column: &str) -> Result<()> {
+        let idx = *self
+            .column_names
+            .get(column)
+            .context(ColumnNotFoundSnafu { column })?;
+
+        let col = &mut self.columns[idx];
+        let influx_type = col.influx_type();
+        ensure!(
+            col.widen_to_float(),
+            NotWidenableSnafu {
+                column,
+                influx_type
+            }
+        );
+
+        Ok(())
+    }
+
     /// Return the approximate memory size of the batch, in bytes.
     ///
     /// This includes `Self`.
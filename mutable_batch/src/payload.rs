@@ -9,6 +9,8 @@ use std::{num::NonZeroUsize, ops::Range};
 mod filter;
 mod partition;
 
+pub use partition::{validate_partition_template, PartitionTemplateError};
+
 /// A payload that can be written to a mutable batch
 pub trait WritePayload {
     /// Write this payload to `batch`
@@ -6,11 +6,72 @@ use crate::{
     column::{Column, ColumnData},
     MutableBatch,
 };
-use chrono::{format::StrftimeItems, TimeZone, Utc};
+use chrono::{
+    format::{Item, StrftimeItems},
+    TimeZone, Utc,
+};
 use data_types::{PartitionTemplate, TemplatePart};
 use schema::TIME_COLUMN_NAME;
+use snafu::{ensure, Snafu};
 use std::ops::Range;
 
+/// A [`PartitionTemplate`] that cannot be used to partition writes.
+#[derive(Debug, Snafu)]
+#[allow(missing_docs)]
+pub enum PartitionTemplateError {
+    #[snafu(display("partition template has no parts"))]
+    NoParts,
+
+    #[snafu(display("partition template part '{}' is not supported", part))]
+    Unsupported { part: String },
+
+    #[snafu(display("partition template part '{}' has an empty column name", part))]
+    EmptyColumn { part: String },
+
+    #[snafu(display("partition template part '{}' has an invalid time format", part))]
+    InvalidTimeFormat { part: String },
+}
+
+/// Validate that `template` can be used to partition writes with
+/// [`PartitionWrite::partition()`](crate::PartitionWrite::partition).
+///
+/// Only the [`TemplatePart::Table`], [`TemplatePart::Column`] and
+/// [`TemplatePart::TimeFormat`] parts are supported, and time formats must be
+/// valid, non-empty `strftime` format strings.
+pub fn validate_partition_template(
+    template: &PartitionTemplate,
+) -> Result<(), PartitionTemplateError> {
+    ensure!(!template.parts.is_empty(), NoPartsSnafu);
+
+    for part in &template.parts {
+        match part {
+            TemplatePart::Table => {}
+            TemplatePart::Column(name) => {
+                ensure!(
+                    !name.is_empty(),
+                    EmptyColumnSnafu {
+                        part: part.to_string()
+                    }
+                )
+            }
+            TemplatePart::TimeFormat(fmt) => ensure!(
+                !fmt.is_empty() && !StrftimeItems::new(fmt).any(|v| matches!(v, Item::Error)),
+                InvalidTimeFormatSnafu {
+                    part: part.to_string()
+                }
+            ),
+            TemplatePart::RegexCapture(_) | TemplatePart::StrftimeColumn(_) => {
+                return UnsupportedSnafu {
+                    part: part.to_string(),
+                }
+                .fail()
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Returns an iterator identifying consecutive ranges for a given partition key
 pub fn partition_batch<'a>(
     batch: &'a MutableBatch,
@@ -188,6 +249,35 @@ mod tests {
         assert_eq!(original, hydrated)
     }
 
+    #[test]
+    fn test_validate_partition_template() {
+        let template = |parts: &[&str]| PartitionTemplate {
+            parts: parts.iter().map(|v| v.parse().unwrap()).collect(),
+        };
+
+        validate_partition_template(&template(&["tag:region", "time:%Y-%m-%d"])).unwrap();
+        validate_partition_template(&template(&["table"])).unwrap();
+
+        assert!(matches!(
+            validate_partition_template(&template(&[])),
+            Err(PartitionTemplateError::NoParts)
+        ));
+        assert!(matches!(
+            validate_partition_template(&template(&["time:%Y-%Q"])),
+            Err(PartitionTemplateError::InvalidTimeFormat { .. })
+        ));
+        assert!(matches!(
+            validate_partition_template(&template(&["table", "regex:host:.*"])),
+            Err(PartitionTemplateError::Unsupported { .. })
+        ));
+        assert!(matches!(
+            validate_partition_template(&PartitionTemplate {
+                parts: vec![TemplatePart::Column(String::new())]
+            }),
+            Err(PartitionTemplateError::EmptyColumn { .. })
+        ));
+    }
+
     #[test]
     fn test_partition() {
         let mut batch = MutableBatch::new();
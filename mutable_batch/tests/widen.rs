@@ -0,0 +1,65 @@
+use arrow_util::assert_batches_eq;
+use data_types::{StatValues, Statistics};
+use mutable_batch::{writer::Writer, Error, MutableBatch};
+use schema::{selection::Selection, InfluxColumnType, InfluxFieldType};
+
+#[test]
+fn test_widen_to_float() {
+    let mut batch = MutableBatch::new();
+    let mut writer = Writer::new(&mut batch, 4);
+
+    writer
+        .write_i64("i", Some(&[0b00001011]), vec![-1, 2, 4].into_iter())
+        .unwrap();
+    writer
+        .write_u64("u", None, vec![1, 2, 3, 4].into_iter())
+        .unwrap();
+    writer
+        .write_tag("t", None, vec!["a", "b", "a", "b"].into_iter())
+        .unwrap();
+    writer
+        .write_time("time", vec![0, 1, 2, 3].into_iter())
+        .unwrap();
+    writer.commit();
+
+    batch.widen_to_float("i").unwrap();
+    batch.widen_to_float("u").unwrap();
+
+    let col = batch.column("i").unwrap();
+    assert_eq!(
+        col.influx_type(),
+        InfluxColumnType::Field(InfluxFieldType::Float)
+    );
+    assert_eq!(
+        col.stats(),
+        Statistics::F64(StatValues::new(Some(-1.0), Some(4.0), 4, Some(1)))
+    );
+
+    let expected = &[
+        "+----+---+--------------------------------+---+",
+        "| i  | t | time                           | u |",
+        "+----+---+--------------------------------+---+",
+        "| -1 | a | 1970-01-01T00:00:00Z           | 1 |",
+        "| 2  | b | 1970-01-01T00:00:00.000000001Z | 2 |",
+        "|    | a | 1970-01-01T00:00:00.000000002Z | 3 |",
+        "| 4  | b | 1970-01-01T00:00:00.000000003Z | 4 |",
+        "+----+---+--------------------------------+---+",
+    ];
+    assert_batches_eq!(expected, &[batch.to_arrow(Selection::All).unwrap()]);
+
+    // Widened columns can be extended with further float values.
+    let mut writer = Writer::new(&mut batch, 1);
+    writer.write_f64("i", None, vec![4.5].into_iter()).unwrap();
+    writer.write_time("time", vec![4].into_iter()).unwrap();
+    writer.commit();
+    assert_eq!(batch.rows(), 5);
+
+    // Non-numeric columns, the timestamp and float columns cannot be widened.
+    for column in ["t", "time", "i"] {
+        let err = batch.widen_to_float(column).unwrap_err();
+        assert!(matches!(err, Error::NotWidenable { .. }), "{err}");
+    }
+
+    let err = batch.widen_to_float("bananas").unwrap_err();
+    assert!(matches!(err, Error::ColumnNotFound { .. }), "{err}");
+}
@@ -35,8 +35,48 @@ pub enum Error {
     #[snafu(display("empty write payload"))]
     EmptyPayload,
 
-    #[snafu(display("timestamp overflows i64"))]
-    TimestampOverflow,
+    #[snafu(display("timestamp overflows i64 on line {}", line))]
+    TimestampOverflow { line: usize },
+
+    #[snafu(display("write exceeds the maximum of {} lines", max))]
+    TooManyLines { max: usize },
+
+    #[snafu(display("line {} has {} fields, exceeding the maximum of {}", line, count, max))]
+    TooManyFields {
+        line: usize,
+        count: usize,
+        max: usize,
+    },
+
+    #[snafu(display("line {} has {} tags, exceeding the maximum of {}", line, count, max))]
+    TooManyTags {
+        line: usize,
+        count: usize,
+        max: usize,
+    },
+}
+
+impl Error {
+    /// Returns the (1-based) number of the line this error refers to, if any.
+    pub fn line(&self) -> Option<usize> {
+        match self {
+            Self::LineProtocol { line, .. }
+            | Self::Write { line, .. }
+            | Self::TimestampOverflow { line }
+            | Self::TooManyFields { line, .. }
+            | Self::TooManyTags { line, .. } => Some(*line),
+            Self::EmptyPayload | Self::TooManyLines { .. } => None,
+        }
+    }
+
+    /// Returns true if this error is due to exceeding a limit configured on
+    /// the [`LinesConverter`], rather than invalid line protocol.
+    pub fn is_limit_exceeded(&self) -> bool {
+        matches!(
+            self,
+            Self::TooManyLines { .. } | Self::TooManyFields { .. } | Self::TooManyTags { .. }
+        )
+    }
 }
 
 /// Result type for line protocol conversion
@@ -62,6 +102,18 @@ pub struct LinesConverter {
     stats: PayloadStatistics,
     /// The current batches
     batches: HashMap<String, MutableBatch>,
+    /// The number of lines parsed, including rejected lines
+    line_count: usize,
+    /// Whether invalid lines are skipped rather than failing the write
+    skip_invalid_lines: bool,
+    /// The errors of any skipped lines
+    rejected: Vec<Error>,
+    /// The maximum number of lines that may be written, if any
+    max_lines: Option<usize>,
+    /// The maximum number of fields in a single line, if any
+    max_fields_per_line: Option<usize>,
+    /// The maximum number of tags in a single line, if any
+    max_tags_per_line: Option<usize>,
 }
 
 impl LinesConverter {
@@ -72,6 +124,12 @@ impl LinesConverter {
             timestamp_base: 1,
             stats: Default::default(),
             batches: Default::default(),
+            line_count: 0,
+            skip_invalid_lines: false,
+            rejected: Default::default(),
+            max_lines: None,
+            max_fields_per_line: None,
+            max_tags_per_line: None,
         }
     }
 
@@ -80,8 +138,47 @@ impl LinesConverter {
         self.timestamp_base = timestamp_base
     }
 
+    /// Sets whether lines that cannot be parsed or written are skipped,
+    /// rather than failing the entire write.
+    ///
+    /// The errors of any skipped lines are available from
+    /// [`Self::rejected_lines()`].
+    pub fn set_skip_invalid_lines(&mut self, skip_invalid_lines: bool) {
+        self.skip_invalid_lines = skip_invalid_lines
+    }
+
+    /// Sets the maximum number of lines that may be written (including any
+    /// skipped lines), returning [`Error::TooManyLines`] once exceeded.
+    ///
+    /// Unlike the per-line limits, exceeding this limit always fails the
+    /// write.
+    pub fn set_max_lines(&mut self, max_lines: usize) {
+        self.max_lines = Some(max_lines)
+    }
+
+    /// Sets the maximum number of fields in a single line, rejecting any
+    /// line with more fields with [`Error::TooManyFields`].
+    pub fn set_max_fields_per_line(&mut self, max_fields: usize) {
+        self.max_fields_per_line = Some(max_fields)
+    }
+
+    /// Sets the maximum number of tags in a single line, rejecting any line
+    /// with more tags with [`Error::TooManyTags`].
+    pub fn set_max_tags_per_line(&mut self, max_tags: usize) {
+        self.max_tags_per_line = Some(max_tags)
+    }
+
+    /// Returns the errors of the lines skipped so far, in line order.
+    pub fn rejected_lines(&self) -> &[Error] {
+        &self.rejected
+    }
+
     /// Write some line protocol data.
     ///
+    /// Line protocol may be written in multiple calls, so long as each call
+    /// contains only complete lines - the line numbers of any errors returned
+    /// are relative to all lines written to this [`LinesConverter`].
+    ///
     /// If a field / tag name appears more than once in a single line, the
     /// following semantics apply:
     ///
@@ -100,32 +197,85 @@ impl LinesConverter {
     ///     [`mutable_batch::writer::Error::TypeMismatch`]
     ///
     pub fn write_lp(&mut self, lines: &str) -> Result<()> {
-        for (line_idx, maybe_line) in parse_lines(lines).enumerate() {
-            let mut line = maybe_line.context(LineProtocolSnafu { line: line_idx + 1 })?;
-
-            if let Some(t) = line.timestamp.as_mut() {
-                *t = t
-                    .checked_mul(self.timestamp_base)
-                    .ok_or(Error::TimestampOverflow)?;
+        for maybe_line in parse_lines(lines) {
+            self.line_count += 1;
+            if let Some(max) = self.max_lines {
+                if self.line_count > max {
+                    return Err(Error::TooManyLines { max });
+                }
             }
+            match self.convert_line(maybe_line) {
+                Ok(()) => {}
+                Err(e) if self.skip_invalid_lines => self.rejected.push(e),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
 
-            self.stats.num_lines += 1;
-            self.stats.num_fields += line.field_set.len();
-
-            let measurement = line.series.measurement.as_str();
+    /// Write the line numbered `self.line_count`, leaving the batches
+    /// unchanged if it cannot be written.
+    fn convert_line(
+        &mut self,
+        maybe_line: Result<ParsedLine<'_>, influxdb_line_protocol::Error>,
+    ) -> Result<()> {
+        let line_num = self.line_count;
+        let mut line = maybe_line.context(LineProtocolSnafu { line: line_num })?;
+
+        if let Some(t) = line.timestamp.as_mut() {
+            *t = t
+                .checked_mul(self.timestamp_base)
+                .ok_or(Error::TimestampOverflow { line: line_num })?;
+        }
 
-            let (_, batch) = self
-                .batches
-                .raw_entry_mut()
-                .from_key(measurement)
-                .or_insert_with(|| (measurement.to_string(), MutableBatch::new()));
+        if let Some(max) = self.max_fields_per_line {
+            let count = line.field_set.len();
+            if count > max {
+                return Err(Error::TooManyFields {
+                    line: line_num,
+                    count,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_tags_per_line {
+            let count = line.series.tag_set.as_ref().map_or(0, |t| t.len());
+            if count > max {
+                return Err(Error::TooManyTags {
+                    line: line_num,
+                    count,
+                    max,
+                });
+            }
+        }
 
-            // TODO: Reuse writer
-            let mut writer = Writer::new(batch, 1);
-            write_line(&mut writer, &line, self.default_time)
-                .context(WriteSnafu { line: line_idx + 1 })?;
-            writer.commit();
+        let measurement = line.series.measurement.as_str();
+
+        let (_, batch) = self
+            .batches
+            .raw_entry_mut()
+            .from_key(measurement)
+            .or_insert_with(|| (measurement.to_string(), MutableBatch::new()));
+
+        // TODO: Reuse writer
+        let mut writer = Writer::new(batch, 1);
+        if let Err(e) = write_line(&mut writer, &line, self.default_time) {
+            // Rollback the partially written line, and do not leave an empty
+            // batch behind for a skipped line.
+            drop(writer);
+            if batch.rows() == 0 {
+                self.batches.remove(measurement);
+            }
+            return Err(Error::Write {
+                source: e,
+                line: line_num,
+            });
         }
+        writer.commit();
+
+        self.stats.num_lines += 1;
+        self.stats.num_fields += line.field_set.len();
+
         Ok(())
     }
 
@@ -445,6 +595,121 @@ m b=t 1639612800000000000
         assert!(!u.is_valid(2));
     }
 
+    #[test]
+    fn test_multiple_writes() {
+        let mut converter = LinesConverter::new(5);
+        converter.write_lp("m i=1i 1\nm i=2i 2\n").unwrap();
+        converter.write_lp("\nm i=3i 3").unwrap();
+
+        // Line numbers are relative to all lines written.
+        let err = converter.write_lp("m i=4i 4\nm i=bananas 5").unwrap_err();
+        assert_matches!(err, Error::LineProtocol { line: 5, .. });
+
+        let (batches, stats) = converter.finish().unwrap();
+        assert_eq!(stats.num_lines, 4);
+        assert_eq!(batches["m"].rows(), 4);
+    }
+
+    #[test]
+    fn test_skip_invalid_lines() {
+        let lp = r#"m i=1i 1
+not line protocol
+m i=2.0 2
+n i=3i 9223372036854775807
+n i=4i 4
+"#;
+
+        let mut converter = LinesConverter::new(5);
+        converter.set_timestamp_base(10);
+        converter.set_skip_invalid_lines(true);
+        converter.write_lp(lp).unwrap();
+
+        assert_matches!(
+            converter.rejected_lines(),
+            [
+                Error::LineProtocol { line: 2, .. },
+                Error::Write { line: 3, .. },
+                Error::TimestampOverflow { line: 4 },
+            ]
+        );
+
+        let (batches, stats) = converter.finish().unwrap();
+        assert_eq!(stats.num_lines, 2);
+        assert_eq!(stats.num_fields, 2);
+        assert_eq!(batches["m"].rows(), 1);
+        assert_eq!(batches["n"].rows(), 1);
+    }
+
+    #[test]
+    fn test_limits() {
+        let lp = r#"m,t1=a,t2=b i=1i,f=2.0 1
+m,t1=a i=1i,f=2.0,g=3.0 2
+m,t1=a,t2=b,t3=c i=1i 3
+m i=1i 4
+"#;
+
+        let mut converter = LinesConverter::new(5);
+        converter.set_max_fields_per_line(2);
+        converter.set_max_tags_per_line(2);
+        let err = converter
+            .write_lp(lp)
+            .expect_err("should exceed field limit");
+        assert_matches!(
+            err,
+            Error::TooManyFields {
+                line: 2,
+                count: 3,
+                max: 2
+            }
+        );
+        assert!(err.is_limit_exceeded());
+
+        // Lines exceeding the per-line limits may be skipped.
+        let mut converter = LinesConverter::new(5);
+        converter.set_max_fields_per_line(2);
+        converter.set_max_tags_per_line(2);
+        converter.set_skip_invalid_lines(true);
+        converter.write_lp(lp).unwrap();
+        assert_matches!(
+            converter.rejected_lines(),
+            [
+                Error::TooManyFields { line: 2, .. },
+                Error::TooManyTags {
+                    line: 3,
+                    count: 3,
+                    max: 2
+                },
+            ]
+        );
+        let (_, stats) = converter.finish().unwrap();
+        assert_eq!(stats.num_lines, 2);
+
+        // But the line limit always fails the write, including across
+        // multiple calls.
+        let mut converter = LinesConverter::new(5);
+        converter.set_max_lines(3);
+        converter.set_skip_invalid_lines(true);
+        converter.write_lp("m i=1i 1\nm i=2i 2").unwrap();
+        let err = converter
+            .write_lp("m i=3i 3\nm i=4i 4")
+            .expect_err("should exceed line limit");
+        assert_matches!(err, Error::TooManyLines { max: 3 });
+        assert_eq!(err.line(), None);
+    }
+
+    #[test]
+    fn test_skip_invalid_lines_no_empty_batches() {
+        let mut converter = LinesConverter::new(5);
+        converter.set_skip_invalid_lines(true);
+        converter.write_lp("m i=1i 1\nn,i=A i=2i 2").unwrap();
+
+        // The line written to "n" conflicts, and no batch is created for it.
+        assert_matches!(converter.rejected_lines(), [Error::Write { line: 2, .. }]);
+        let (batches, _) = converter.finish().unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches["m"].rows(), 1);
+    }
+
     // https://github.com/influxdata/influxdb_iox/issues/4326
     mod issue4326 {
         use super::*;
@@ -0,0 +1,213 @@
+use std::sync::Arc;
+
+use arrow::{
+    array::{Array, ArrayRef, TimestampNanosecondArray},
+    datatypes::DataType,
+};
+use chrono::{LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{ScalarUDF, Volatility},
+    physical_plan::ColumnarValue,
+    prelude::create_udf,
+    scalar::ScalarValue,
+};
+use once_cell::sync::Lazy;
+use schema::{TIME_DATA_TIMEZONE, TIME_DATA_TYPE};
+
+/// The name of the date_bin_tz UDF given to DataFusion.
+pub(crate) const DATE_BIN_TZ_UDF_NAME: &str = "date_bin_tz";
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// Implementation of date_bin_tz
+pub(crate) static DATE_BIN_TZ_UDF: Lazy<Arc<ScalarUDF>> = Lazy::new(|| {
+    Arc::new(create_udf(
+        DATE_BIN_TZ_UDF_NAME,
+        // takes three arguments: interval (in nanoseconds), time, timezone
+        vec![DataType::Int64, TIME_DATA_TYPE(), DataType::Utf8],
+        Arc::new(TIME_DATA_TYPE()),
+        Volatility::Stable,
+        Arc::new(date_bin_tz_udf),
+    ))
+});
+
+/// Implement `date_bin_tz(interval, time, tz)` as a DataFusion UDF.
+///
+/// The `interval` and `tz` arguments must be constants.
+fn date_bin_tz_udf(args: &[ColumnarValue]) -> DataFusionResult<ColumnarValue> {
+    assert_eq!(args.len(), 3);
+
+    let interval = match &args[0] {
+        ColumnarValue::Scalar(ScalarValue::Int64(Some(interval))) if *interval > 0 => *interval,
+        arg => {
+            return Err(DataFusionError::Execution(format!(
+                "date_bin_tz expected a positive constant interval, got {:?}",
+                arg
+            )))
+        }
+    };
+
+    let tz: Tz = match &args[2] {
+        ColumnarValue::Scalar(ScalarValue::Utf8(Some(tz))) => tz.parse().map_err(|e| {
+            DataFusionError::Execution(format!("date_bin_tz invalid timezone '{}': {}", tz, e))
+        })?,
+        arg => {
+            return Err(DataFusionError::Execution(format!(
+                "date_bin_tz expected a constant timezone, got {:?}",
+                arg
+            )))
+        }
+    };
+
+    let time = match &args[1] {
+        ColumnarValue::Scalar(v) => {
+            return Err(DataFusionError::NotImplemented(format!(
+                "date_bin_tz against scalar arguments ({:?}) not yet implemented",
+                v
+            )))
+        }
+        ColumnarValue::Array(arr) => arr,
+    };
+
+    Ok(ColumnarValue::Array(date_bin_tz_array(time, interval, &tz)))
+}
+
+/// Compute the start of the bin of each timestamp in `arg`
+fn date_bin_tz_array(arg: &dyn Array, interval: i64, tz: &Tz) -> ArrayRef {
+    let time = arg
+        .as_any()
+        .downcast_ref::<TimestampNanosecondArray>()
+        .expect("cast of time failed");
+
+    let values = time
+        .iter()
+        .map(|ts| ts.map(|ts| date_bin_tz(interval, ts, tz)))
+        .collect::<Vec<_>>();
+
+    Arc::new(TimestampNanosecondArray::from_opt_vec(
+        values,
+        TIME_DATA_TIMEZONE(),
+    )) as ArrayRef
+}
+
+/// Return the start of the `interval` nanosecond bin containing
+/// `time`, where the bins are aligned to midnight on 1970-01-01 in
+/// the local time of `tz`.
+///
+/// As the offset of `tz` from UTC may change (such as for daylight
+/// saving time) the bins are computed in local time. If the start of
+/// the bin is ambiguous in local time the earliest time is used, and
+/// if it does not exist the offset at `time` is used.
+fn date_bin_tz(interval: i64, time: i64, tz: &Tz) -> i64 {
+    let offset = tz
+        .offset_from_utc_datetime(&naive_from_nanos(time))
+        .fix()
+        .local_minus_utc() as i64
+        * NANOS_PER_SECOND;
+
+    let local = time + offset;
+    let local_bin = local - local.rem_euclid(interval);
+
+    match tz.from_local_datetime(&naive_from_nanos(local_bin)) {
+        LocalResult::Single(bin) | LocalResult::Ambiguous(bin, _) => bin.timestamp_nanos(),
+        LocalResult::None => local_bin - offset,
+    }
+}
+
+/// Return the naive date time `nanos` nanoseconds after the epoch
+fn naive_from_nanos(nanos: i64) -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(
+        nanos.div_euclid(NANOS_PER_SECOND),
+        nanos.rem_euclid(NANOS_PER_SECOND) as u32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 3600 * NANOS_PER_SECOND;
+    const DAY: i64 = 24 * HOUR;
+
+    /// 2022-03-27T00:00:00Z, the day clocks in London move forward an
+    /// hour at 01:00 UTC
+    const DST_START_DAY: i64 = 1_648_339_200 * NANOS_PER_SECOND;
+
+    /// 2022-10-30T00:00:00Z, the day clocks in London move back an
+    /// hour at 01:00 UTC
+    const DST_END_DAY: i64 = 1_667_088_000 * NANOS_PER_SECOND;
+
+    #[test]
+    fn test_date_bin_tz_utc() {
+        let tz: Tz = "UTC".parse().unwrap();
+        assert_eq!(
+            date_bin_tz(DAY, DST_START_DAY + 5 * HOUR, &tz),
+            DST_START_DAY
+        );
+        assert_eq!(
+            date_bin_tz(HOUR, DST_START_DAY + 90 * 60 * NANOS_PER_SECOND, &tz),
+            DST_START_DAY + HOUR
+        );
+    }
+
+    #[test]
+    fn test_date_bin_tz_offset() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        // 2022-03-27 is during daylight saving time, UTC-4, so the
+        // local day starts at 04:00 UTC
+        assert_eq!(
+            date_bin_tz(DAY, DST_START_DAY + 5 * HOUR, &tz),
+            DST_START_DAY + 4 * HOUR
+        );
+        assert_eq!(
+            date_bin_tz(DAY, DST_START_DAY + 3 * HOUR, &tz),
+            DST_START_DAY - DAY + 4 * HOUR
+        );
+    }
+
+    #[test]
+    fn test_date_bin_tz_daylight_saving() {
+        let tz: Tz = "Europe/London".parse().unwrap();
+
+        // The local day starts at midnight GMT
+        assert_eq!(
+            date_bin_tz(DAY, DST_START_DAY + 12 * HOUR, &tz),
+            DST_START_DAY
+        );
+        // and the next day starts at midnight BST, 23:00 UTC
+        assert_eq!(
+            date_bin_tz(DAY, DST_START_DAY + 23 * HOUR, &tz),
+            DST_START_DAY + 23 * HOUR
+        );
+
+        // The local day starts at midnight BST, 23:00 UTC the day before
+        assert_eq!(
+            date_bin_tz(DAY, DST_END_DAY + 12 * HOUR, &tz),
+            DST_END_DAY - HOUR
+        );
+
+        // 01:00 local time happens twice: the earliest is used
+        assert_eq!(
+            date_bin_tz(HOUR, DST_END_DAY + HOUR + 30 * 60 * NANOS_PER_SECOND, &tz),
+            DST_END_DAY
+        );
+    }
+
+    #[test]
+    fn test_date_bin_tz_array() {
+        let tz: Tz = "America/New_York".parse().unwrap();
+        let input: ArrayRef = Arc::new(TimestampNanosecondArray::from_opt_vec(
+            vec![Some(DST_START_DAY + 5 * HOUR), None],
+            TIME_DATA_TIMEZONE(),
+        ));
+
+        let expected: ArrayRef = Arc::new(TimestampNanosecondArray::from_opt_vec(
+            vec![Some(DST_START_DAY + 4 * HOUR), None],
+            TIME_DATA_TIMEZONE(),
+        ));
+
+        assert_eq!(&expected, &date_bin_tz_array(&input, DAY, &tz));
+    }
+}
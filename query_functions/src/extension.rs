@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use datafusion::{
+    execution::context::SessionState,
+    logical_expr::{AggregateUDF, ScalarUDF},
+};
+
+/// Extension trait for registering user defined functions so they
+/// can be invoked via SQL.
+///
+/// This is how IOx registers its own functions (see
+/// [`register_selector_aggregates`](crate::selectors::register_selector_aggregates)),
+/// and embedding applications can use it to register their own
+/// functions in the same way.
+pub trait FunctionRegistryExt {
+    /// Register the user defined scalar function `udf`, replacing
+    /// any existing function with the same name
+    fn register_udf(&mut self, udf: Arc<ScalarUDF>);
+
+    /// Register the user defined aggregate function `udaf`,
+    /// replacing any existing function with the same name
+    fn register_udaf(&mut self, udaf: Arc<AggregateUDF>);
+}
+
+impl FunctionRegistryExt for SessionState {
+    fn register_udf(&mut self, udf: Arc<ScalarUDF>) {
+        //TODO make a nicer api for this in DataFusion
+        self.scalar_functions.insert(udf.name.to_string(), udf);
+    }
+
+    fn register_udaf(&mut self, udaf: Arc<AggregateUDF>) {
+        //TODO make a nicer api for this in DataFusion
+        self.aggregate_functions.insert(udaf.name.to_string(), udaf);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::datatypes::DataType;
+    use datafusion::{
+        execution::{runtime_env::RuntimeEnv, FunctionRegistry},
+        logical_expr::Volatility,
+        physical_plan::ColumnarValue,
+        prelude::{create_udf, SessionConfig},
+    };
+
+    use super::*;
+    use crate::selectors::{
+        register_selector_aggregates, struct_selector_first, AccumulatorMemory,
+    };
+
+    #[test]
+    fn test_register_functions() {
+        let udf = Arc::new(create_udf(
+            "my_udf",
+            vec![DataType::Int64],
+            Arc::new(DataType::Int64),
+            Volatility::Immutable,
+            Arc::new(|args: &[ColumnarValue]| Ok(args[0].clone())),
+        ));
+
+        let runtime = Arc::new(RuntimeEnv::default());
+        let memory = Arc::new(AccumulatorMemory::new(Arc::clone(&runtime), usize::MAX));
+        let state = SessionState::with_config_rt(SessionConfig::new(), runtime);
+        let mut state = register_selector_aggregates(state, &memory);
+        state.register_udf(udf);
+
+        // can also replace functions
+        state.register_udaf(struct_selector_first());
+
+        assert!(state.udf("my_udf").is_ok());
+        assert!(state.udaf("selector_first").is_ok());
+        assert!(state.udaf("selector_last").is_ok());
+    }
+}
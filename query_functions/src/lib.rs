@@ -12,27 +12,42 @@
 )]
 
 use datafusion::{
-    execution::FunctionRegistry,
+    execution::{context::SessionState, FunctionRegistry},
     prelude::{lit, Expr},
 };
+use extension::FunctionRegistryExt;
 use group_by::WindowDuration;
 use window::EncodedWindowDuration;
 
 /// Grouping by structs
 pub mod group_by;
 
+/// date_bin_tz expressions
+mod date_bin_tz;
+
+/// Registering user defined functions
+pub mod extension;
+
 /// Regular Expressions
 mod regex;
 
 /// Flux selector expressions
 pub mod selectors;
 
+/// InfluxQL series functions
+pub mod series;
+
 /// window_bounds expressions
 mod window;
 
 /// Function registry
 mod registry;
 
+#[cfg(test)]
+mod test_util;
+
+pub use crate::regex::REGEX_EXTRACT_UDF_NAME;
+pub use crate::regex::REGEX_MATCH_SQL_UDF_NAME;
 pub use crate::regex::REGEX_MATCH_UDF_NAME;
 pub use crate::regex::REGEX_NOT_MATCH_UDF_NAME;
 
@@ -62,6 +77,17 @@ pub fn regex_not_match_expr(input: Expr, pattern: String) -> Expr {
         .call(vec![input, lit(pattern)])
 }
 
+/// Return an Expr that invokes `regex_extract` to extract the text
+/// matched by capture group `group` of the regex `pattern` from each
+/// value, or NULL if the value does not match. Group 0 is the whole
+/// match.
+pub fn regex_extract_expr(input: Expr, pattern: String, group: i64) -> Expr {
+    registry()
+        .udf(regex::REGEX_EXTRACT_UDF_NAME)
+        .expect("RegexExtract function not registered")
+        .call(vec![input, lit(pattern), lit(group)])
+}
+
 /// Create a DataFusion `Expr` that invokes `window_bounds` with the
 /// appropriate every and offset arguments at runtime
 pub fn make_window_bound_expr(
@@ -86,6 +112,31 @@ pub fn make_window_bound_expr(
         ])
 }
 
+/// Create a DataFusion `Expr` that invokes `date_bin_tz` to compute
+/// the start of the `interval` nanosecond bin containing `time_arg`,
+/// where the bins are aligned to midnight in the local time of the
+/// named timezone `tz`, such as `"Europe/London"`.
+///
+/// Unlike aligning the bins with a fixed offset from UTC, the bins
+/// follow any changes to the offset of `tz`, such as for daylight
+/// saving time.
+pub fn make_date_bin_tz_expr(interval: i64, time_arg: Expr, tz: impl Into<String>) -> Expr {
+    registry()
+        .udf(date_bin_tz::DATE_BIN_TZ_UDF_NAME)
+        .expect("DateBinTz function not registered")
+        .call(vec![lit(interval), time_arg, lit(tz.into())])
+}
+
+/// Registers the scalar functions that can be invoked via SQL:
+/// `regex_match(col, pattern)` and `regex_extract(col, pattern, group)`
+pub fn register_scalar_functions(mut state: SessionState) -> SessionState {
+    for name in [REGEX_MATCH_SQL_UDF_NAME, REGEX_EXTRACT_UDF_NAME] {
+        let udf = registry().udf(name).expect("function registered");
+        state.register_udf(udf);
+    }
+    state
+}
+
 /// Return an [`FunctionRegistry`] with the implementations of IOx UDFs
 pub fn registry() -> &'static dyn FunctionRegistry {
     registry::instance()
@@ -160,6 +211,42 @@ mod test {
         assert_batches_eq!(&expected, &result);
     }
 
+    /// plumbing test to validate registry is connected. functions are
+    /// tested more thoroughly in their own modules
+    #[tokio::test]
+    async fn test_regex_extract_expr() {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "data",
+            Arc::new(StringArray::from(vec!["Foo1", "Bar", "Foo23"])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let ctx = context_with_table(batch);
+        let result = ctx
+            .table("t")
+            .unwrap()
+            .select(vec![
+                col("data"),
+                regex_extract_expr(col("data"), r#"Foo(\d+)"#.into(), 1).alias("n"),
+            ])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+-------+----+",
+            "| data  | n  |",
+            "+-------+----+",
+            "| Foo1  | 1  |",
+            "| Bar   |    |",
+            "| Foo23 | 23 |",
+            "+-------+----+",
+        ];
+
+        assert_batches_eq!(&expected, &result);
+    }
+
     /// plumbing test to validate registry is connected. functions are
     /// tested more thoroughly in their own modules
     #[tokio::test]
@@ -197,4 +284,44 @@ mod test {
 
         assert_batches_eq!(&expected, &result);
     }
+
+    /// plumbing test to validate registry is connected. functions are
+    /// tested more thoroughly in their own modules
+    #[tokio::test]
+    async fn test_make_date_bin_tz_expr() {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "time",
+            Arc::new(TimestampNanosecondArray::from(vec![
+                Some(1_648_357_200_000_000_000),
+                Some(1_648_368_000_000_000_000),
+            ])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let day = 24 * 3600 * 1_000_000_000;
+
+        let ctx = context_with_table(batch);
+        let result = ctx
+            .table("t")
+            .unwrap()
+            .select(vec![
+                col("time"),
+                make_date_bin_tz_expr(day, col("time"), "America/New_York").alias("bin"),
+            ])
+            .unwrap()
+            .collect()
+            .await
+            .unwrap();
+
+        let expected = vec![
+            "+---------------------+---------------------+",
+            "| time                | bin                 |",
+            "+---------------------+---------------------+",
+            "| 2022-03-27 05:00:00 | 2022-03-27 04:00:00 |",
+            "| 2022-03-27 08:00:00 | 2022-03-27 04:00:00 |",
+            "+---------------------+---------------------+",
+        ];
+
+        assert_batches_eq!(&expected, &result);
+    }
 }
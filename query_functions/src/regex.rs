@@ -1,7 +1,10 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
 use arrow::{
-    array::{as_string_array, ArrayRef, BooleanArray},
+    array::{as_string_array, ArrayRef, BooleanArray, StringArray},
     datatypes::DataType,
 };
 use datafusion::{
@@ -19,6 +22,19 @@ pub const REGEX_MATCH_UDF_NAME: &str = "RegexMatch";
 /// The name of the not_regex_match UDF given to DataFusion.
 pub const REGEX_NOT_MATCH_UDF_NAME: &str = "RegexNotMatch";
 
+/// The name of the regex_match UDF that can be invoked via SQL.
+pub const REGEX_MATCH_SQL_UDF_NAME: &str = "regex_match";
+
+/// The name of the regex_extract UDF given to DataFusion.
+pub const REGEX_EXTRACT_UDF_NAME: &str = "regex_extract";
+
+/// The maximum number of compiled patterns kept by [`compile_pattern`]
+const PATTERN_CACHE_SIZE: usize = 1000;
+
+/// Compiled patterns, keyed by the pattern as given
+static PATTERN_CACHE: Lazy<Mutex<HashMap<String, regex::Regex>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
 /// Implementation of regexp_match
 pub(crate) static REGEX_MATCH_UDF: Lazy<Arc<ScalarUDF>> = Lazy::new(|| {
     Arc::new(create_udf(
@@ -43,6 +59,55 @@ pub(crate) static REGEX_NOT_MATCH_UDF: Lazy<Arc<ScalarUDF>> = Lazy::new(|| {
     ))
 });
 
+/// Implementation of `regex_match(col, pattern)`, the same as
+/// [`REGEX_MATCH_UDF`] but with a name that can be invoked via SQL
+pub(crate) static REGEX_MATCH_SQL_UDF: Lazy<Arc<ScalarUDF>> = Lazy::new(|| {
+    Arc::new(create_udf(
+        REGEX_MATCH_SQL_UDF_NAME,
+        // takes two arguments: regex, pattern
+        vec![DataType::Utf8, DataType::Utf8],
+        Arc::new(DataType::Boolean),
+        Volatility::Stable,
+        regex_match_expr_impl(true),
+    ))
+});
+
+/// Implementation of `regex_extract(col, pattern, group)`
+pub(crate) static REGEX_EXTRACT_UDF: Lazy<Arc<ScalarUDF>> = Lazy::new(|| {
+    Arc::new(create_udf(
+        REGEX_EXTRACT_UDF_NAME,
+        // takes three arguments: column, pattern, capture group
+        vec![DataType::Utf8, DataType::Utf8, DataType::Int64],
+        Arc::new(DataType::Utf8),
+        Volatility::Stable,
+        Arc::new(regex_extract),
+    ))
+});
+
+/// Compile `pattern`, first rewriting it to be compatible with the
+/// golang regexp syntax (see [`clean_non_meta_escapes`]).
+///
+/// The same patterns are typically used for every batch of a query,
+/// and by many queries, so the compiled patterns are cached.
+fn compile_pattern(pattern: &str) -> Result<regex::Regex, DataFusionError> {
+    let mut cache = PATTERN_CACHE.lock().expect("pattern cache poisoned");
+    if let Some(regex) = cache.get(pattern) {
+        return Ok(regex.clone());
+    }
+
+    let regex = regex::Regex::new(&clean_non_meta_escapes(pattern))
+        .map_err(|e| DataFusionError::Internal(format!("error compiling regex pattern: {}", e)))?;
+
+    // Rather than track which patterns are least recently used,
+    // start again when the cache is full
+    if cache.len() >= PATTERN_CACHE_SIZE {
+        cache.clear();
+    }
+    cache.insert(pattern.to_string(), regex.clone());
+
+    Ok(regex)
+}
+
 /// Given a column containing string values and a single regex pattern,
 /// `regex_match_expr` determines which values satisfy the pattern and which do
 /// not.
@@ -90,11 +155,7 @@ fn regex_match_expr_impl(matches: bool) -> ScalarFunctionImplementation {
 
         // Attempt to make the pattern compatible with what is accepted by
         // the golang regexp library which is different than Rust's regexp
-        let pattern = clean_non_meta_escapes(pattern);
-
-        let pattern = regex::Regex::new(&pattern).map_err(|e| {
-            DataFusionError::Internal(format!("error compiling regex pattern: {}", e))
-        })?;
+        let pattern = compile_pattern(pattern)?;
 
         match &args[0] {
             ColumnarValue::Array(arr) => {
@@ -123,6 +184,68 @@ fn regex_match_expr_impl(matches: bool) -> ScalarFunctionImplementation {
     Arc::new(func)
 }
 
+/// Implement `regex_extract(col, pattern, group)`: the text matched
+/// by capture group `group` of `pattern` in each value, where group 0
+/// is the whole match.
+///
+/// Returns NULL for values that do not match, or where the group does
+/// not participate in the match. The `pattern` and `group` arguments
+/// must be constants.
+fn regex_extract(args: &[ColumnarValue]) -> Result<ColumnarValue, DataFusionError> {
+    assert_eq!(args.len(), 3);
+
+    let pattern = match &args[1] {
+        ColumnarValue::Scalar(ScalarValue::Utf8(Some(pattern))) => compile_pattern(pattern)?,
+        arg => {
+            return Err(DataFusionError::Execution(format!(
+                "regex_extract expected a constant pattern, got {:?}",
+                arg
+            )))
+        }
+    };
+
+    let group = match &args[2] {
+        ColumnarValue::Scalar(ScalarValue::Int64(Some(group)))
+            if *group >= 0 && (*group as usize) < pattern.captures_len() =>
+        {
+            *group as usize
+        }
+        arg => {
+            return Err(DataFusionError::Execution(format!(
+                "regex_extract expected a constant capture group between 0 and {} for pattern '{}', got {:?}",
+                pattern.captures_len() - 1,
+                pattern,
+                arg
+            )))
+        }
+    };
+
+    let extract = |v: &str| {
+        pattern
+            .captures(v)
+            .and_then(|captures| captures.get(group))
+            .map(|m| m.as_str().to_string())
+    };
+
+    match &args[0] {
+        ColumnarValue::Array(arr) => {
+            let results = as_string_array(arr)
+                .iter()
+                .map(|row| row.and_then(extract))
+                .collect::<StringArray>();
+
+            Ok(ColumnarValue::Array(Arc::new(results) as ArrayRef))
+        }
+        ColumnarValue::Scalar(ScalarValue::Utf8(row)) => Ok(ColumnarValue::Scalar(
+            ScalarValue::Utf8(row.as_deref().and_then(extract)),
+        )),
+        ColumnarValue::Scalar(v) => Err(DataFusionError::Internal(format!(
+            "regex_extract expected first argument to be utf8, got ('{}')",
+            v
+        ))),
+    }
+}
+
 fn is_valid_character_after_escape(c: char) -> bool {
     // same list as https://docs.rs/regex-syntax/0.6.25/src/regex_syntax/ast/parse.rs.html#1445-1538
     match c {
@@ -205,8 +328,11 @@ mod test {
         util::pretty::pretty_format_batches,
     };
     use datafusion::{
+        assert_batches_eq,
+        dataframe::DataFrame,
         error::DataFusionError,
-        prelude::{col, lit, Expr},
+        execution::{context::SessionState, runtime_env::RuntimeEnv},
+        prelude::{col, lit, Expr, SessionConfig, SessionContext},
     };
     use datafusion_util::context_with_table;
     use std::sync::Arc;
@@ -304,8 +430,21 @@ mod test {
         assert!(actual.to_string().contains("error compiling regex pattern"))
     }
 
-    // Run a plan against the following input table as "t"
+    // Run a plan against the input table of `run_plan`, selecting the
+    // words and `op`
+    async fn run_select(op: Expr) -> Result<Vec<String>, DataFusionError> {
+        run_query(|df| df.select(vec![col("words"), op])).await
+    }
+
+    // Run a plan against the input table of `run_plan`, filtering by `op`
     async fn run_plan(op: Expr) -> Result<Vec<String>, DataFusionError> {
+        run_query(|df| df.filter(op)).await
+    }
+
+    // Run a plan against the following input table as "t"
+    async fn run_query(
+        plan: impl FnOnce(Arc<DataFrame>) -> Result<Arc<DataFrame>, DataFusionError>,
+    ) -> Result<Vec<String>, DataFusionError> {
         // define data for table
         let words = vec![
             Some("air"),
@@ -332,7 +471,7 @@ mod test {
 
         let ctx = context_with_table(rb);
         let df = ctx.table("t").unwrap();
-        let df = df.filter(op).unwrap();
+        let df = plan(df).unwrap();
 
         // execute the query
         let record_batches = df.collect().await?;
@@ -345,6 +484,134 @@ mod test {
             .collect())
     }
 
+    #[tokio::test]
+    async fn regex_extract_expr() {
+        let cases = vec![
+            (
+                r#"^(\w+) (\w+)$"#, // two words
+                1,                  // the first
+                vec![
+                    "+---------------+---------+",
+                    "| words         | extract |",
+                    "+---------------+---------+",
+                    "| air           |         |",
+                    "| aphex twin    | aphex   |",
+                    "| bruce         |         |",
+                    "| Blood Orange  | Blood   |",
+                    "|               |         |",
+                    "|               |         |",
+                    "| cocteau twins | cocteau |",
+                    "+---------------+---------+",
+                ],
+            ),
+            (
+                r#"(O)?r(\w)"#, // an optional group
+                1,
+                vec![
+                    "+---------------+---------+",
+                    "| words         | extract |",
+                    "+---------------+---------+",
+                    "| air           |         |",
+                    "| aphex twin    |         |",
+                    "| bruce         |         |",
+                    "| Blood Orange  | O       |",
+                    "|               |         |",
+                    "|               |         |",
+                    "| cocteau twins |         |",
+                    "+---------------+---------+",
+                ],
+            ),
+            (
+                r#"\:?[aeiou]+\w"#, // the whole match, with a golang escape
+                0,
+                vec![
+                    "+---------------+---------+",
+                    "| words         | extract |",
+                    "+---------------+---------+",
+                    "| air           | air     |",
+                    "| aphex twin    | ap      |",
+                    "| bruce         | uc      |",
+                    "| Blood Orange  | ood     |",
+                    "|               |         |",
+                    "|               |         |",
+                    "| cocteau twins | oc      |",
+                    "+---------------+---------+",
+                ],
+            ),
+        ];
+
+        for (pattern, group, expected) in cases.into_iter() {
+            let regex_expr = REGEX_EXTRACT_UDF
+                .call(vec![col("words"), lit(pattern), lit(group as i64)])
+                .alias("extract");
+
+            let actual = run_select(regex_expr).await.unwrap();
+
+            assert_eq!(
+                expected, actual,
+                "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+                expected, actual
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn regex_extract_expr_invalid_group() {
+        let regex_expr = crate::regex_extract_expr(col("words"), "(a)(b)".to_string(), 3);
+
+        let actual = run_select(regex_expr).await.expect_err("expected error");
+        assert!(
+            actual
+                .to_string()
+                .contains("regex_extract expected a constant capture group between 0 and 2"),
+            "unexpected error: {}",
+            actual
+        );
+    }
+
+    #[tokio::test]
+    async fn regex_functions_sql() {
+        let batch = RecordBatch::try_from_iter(vec![(
+            "words",
+            Arc::new(StringArray::from(vec!["aphex twin", "bruce"])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let state =
+            SessionState::with_config_rt(SessionConfig::new(), Arc::new(RuntimeEnv::default()));
+        let ctx = SessionContext::with_state(crate::register_scalar_functions(state));
+        ctx.register_batch("t", batch).unwrap();
+
+        let sql = "SELECT words FROM t WHERE regex_match(words, '^a') \
+                   UNION ALL \
+                   SELECT regex_extract(words, '(\\w+)$', 1) FROM t";
+
+        let record_batches = ctx.sql(sql).await.unwrap().collect().await.unwrap();
+
+        let expected = vec![
+            "+------------+",
+            "| words      |",
+            "+------------+",
+            "| aphex twin |",
+            "| twin       |",
+            "| bruce      |",
+            "+------------+",
+        ];
+
+        assert_batches_eq!(&expected, &record_batches);
+    }
+
+    #[test]
+    fn test_compile_pattern_cached() {
+        let pattern = r#"^cached\:pattern$"#;
+        let first = compile_pattern(pattern).unwrap();
+        assert_eq!(first.as_str(), "^cached:pattern$");
+
+        assert!(PATTERN_CACHE.lock().unwrap().contains_key(pattern));
+        let second = compile_pattern(pattern).unwrap();
+        assert_eq!(first.as_str(), second.as_str());
+    }
+
     #[test]
     fn test_clean_non_meta_escapes() {
         let cases = vec![
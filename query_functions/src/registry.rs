@@ -7,7 +7,7 @@ use datafusion::{
 };
 use once_cell::sync::Lazy;
 
-use crate::{regex, window};
+use crate::{date_bin_tz, regex, window};
 
 static REGISTRY: Lazy<IOxFunctionRegistry> = Lazy::new(IOxFunctionRegistry::new);
 
@@ -23,17 +23,26 @@ impl IOxFunctionRegistry {
 
 impl FunctionRegistry for IOxFunctionRegistry {
     fn udfs(&self) -> HashSet<String> {
-        [regex::REGEX_MATCH_UDF_NAME, regex::REGEX_NOT_MATCH_UDF_NAME]
-            .into_iter()
-            .map(|s| s.to_string())
-            .collect()
+        [
+            regex::REGEX_MATCH_UDF_NAME,
+            regex::REGEX_NOT_MATCH_UDF_NAME,
+            regex::REGEX_MATCH_SQL_UDF_NAME,
+            regex::REGEX_EXTRACT_UDF_NAME,
+            date_bin_tz::DATE_BIN_TZ_UDF_NAME,
+        ]
+        .into_iter()
+        .map(|s| s.to_string())
+        .collect()
     }
 
     fn udf(&self, name: &str) -> DataFusionResult<Arc<ScalarUDF>> {
         match name {
             regex::REGEX_MATCH_UDF_NAME => Ok(regex::REGEX_MATCH_UDF.clone()),
             regex::REGEX_NOT_MATCH_UDF_NAME => Ok(regex::REGEX_NOT_MATCH_UDF.clone()),
+            regex::REGEX_MATCH_SQL_UDF_NAME => Ok(regex::REGEX_MATCH_SQL_UDF.clone()),
+            regex::REGEX_EXTRACT_UDF_NAME => Ok(regex::REGEX_EXTRACT_UDF.clone()),
             window::WINDOW_BOUNDS_UDF_NAME => Ok(window::WINDOW_BOUNDS_UDF.clone()),
+            date_bin_tz::DATE_BIN_TZ_UDF_NAME => Ok(date_bin_tz::DATE_BIN_TZ_UDF.clone()),
             _ => Err(DataFusionError::Plan(format!(
                 "IOx FunctionRegistry does not contain function '{}'",
                 name
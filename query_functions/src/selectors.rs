@@ -8,16 +8,21 @@
 //! scalar. Selector functions return the entire row that was
 //! "selected" from the timeseries (value and time pair).
 //!
-//! Note: At the time of writing, DataFusion aggregate functions have
-//! no way to handle aggregates that produce multiple columns.
+//! Each selector is computed by a single accumulator which produces
+//! a struct `{value, time}`. Queries that need the value and time as
+//! separate columns compute the selector once and then extract the
+//! parts using [`selector_value`] and [`selector_time`].
 //!
-//! This module implements a workaround of "do the aggregation twice
-//! with two distinct functions" to get something working. It should
-//! should be removed when DataFusion / Arrow has proper support
+//! The first, last, min and max selectors also accept additional
+//! columns after the time, such as `selector_first(value, time,
+//! other_1, other_2)`. The values of these columns in the selected row
+//! are returned in additional struct fields named `other_1`, `other_2`,
+//! and so on, so that the entire selected row can be returned.
 use std::{fmt::Debug, sync::Arc};
 
 use arrow::{
-    array::ArrayRef,
+    array::{Array, ArrayRef},
+    compute::cast,
     datatypes::{DataType, Field},
 };
 use datafusion::{
@@ -27,6 +32,7 @@ use datafusion::{
         AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
     },
     physical_plan::{udaf::AggregateUDF, Accumulator},
+    prelude::Expr,
     scalar::ScalarValue,
 };
 
@@ -34,32 +40,89 @@ use datafusion::{
 mod internal;
 use internal::{
     BooleanFirstSelector, BooleanLastSelector, BooleanMaxSelector, BooleanMinSelector,
+    Decimal128FirstSelector, Decimal128LastSelector, Decimal128MaxSelector, Decimal128MinSelector,
     F64FirstSelector, F64LastSelector, F64MaxSelector, F64MinSelector, I64FirstSelector,
     I64LastSelector, I64MaxSelector, I64MinSelector, U64FirstSelector, U64LastSelector,
-    U64MaxSelector, U64MinSelector, Utf8FirstSelector, Utf8LastSelector, Utf8MaxSelector,
-    Utf8MinSelector,
+    U64MaxSelector, U64MinSelector, Utf8CollatedMaxSelector, Utf8CollatedMinSelector,
+    Utf8FirstSelector, Utf8LastSelector, Utf8MaxSelector, Utf8MinSelector,
 };
 use schema::TIME_DATA_TYPE;
 
-/// registers selector functions so they can be invoked via SQL
-pub fn register_selector_aggregates(mut state: SessionState) -> SessionState {
-    let first = struct_selector_first();
-    let last = struct_selector_last();
-    let min = struct_selector_min();
-    let max = struct_selector_max();
+use crate::extension::FunctionRegistryExt;
 
-    //TODO make a nicer api for this in DataFusion
-    state
-        .aggregate_functions
-        .insert(first.name.to_string(), first);
+// Streaming first/last operator for input sorted by group and time
+mod exec;
+pub use exec::{SortedSelectorExec, SortedSelectorType};
 
-    state
-        .aggregate_functions
-        .insert(last.name.to_string(), last);
+// Memory accounting for accumulators whose state grows with their input
+pub(crate) mod memory;
+pub use memory::AccumulatorMemory;
+
+// Implementation of the top / bottom selector functions
+mod top;
+use top::{make_top_uda, TopType};
+
+// Implementation of the mode and median functions
+mod median;
+mod mode;
+use median::make_median_uda;
+use mode::make_mode_uda;
+
+// Implementation of the spread function
+mod spread;
+use spread::make_spread_uda;
+
+// Implementation of the approx_percentile function
+mod approx_percentile;
+use approx_percentile::make_approx_percentile_uda;
 
-    state.aggregate_functions.insert(min.name.to_string(), min);
+// Implementation of the histogram function
+mod histogram;
+use histogram::make_histogram_uda;
 
-    state.aggregate_functions.insert(max.name.to_string(), max);
+// Implementation of the distinct functions
+mod distinct;
+use distinct::{make_count_distinct_uda, make_distinct_uda};
+
+/// Name of the struct field holding the selected value
+pub const SELECTOR_VALUE_FIELD: &str = "value";
+
+/// Name of the struct field holding the time of the selected value
+pub const SELECTOR_TIME_FIELD: &str = "time";
+
+/// The maximum number of additional columns that can be passed
+/// through from the selected row, as in
+/// `selector_first(value, time, other_1, ..., other_N)`
+pub const MAX_SELECTOR_OTHER_COLUMNS: usize = 16;
+
+/// registers selector functions so they can be invoked via SQL.
+///
+/// The functions whose state grows with their input track the
+/// memory for that state with `memory`, failing queries that would
+/// exceed its limit
+pub fn register_selector_aggregates(
+    mut state: SessionState,
+    memory: &Arc<AccumulatorMemory>,
+) -> SessionState {
+    let udafs = [
+        struct_selector_first(),
+        struct_selector_last(),
+        struct_selector_min(),
+        struct_selector_max(),
+        struct_selector_top(memory),
+        struct_selector_bottom(memory),
+        selector_mode(memory),
+        selector_median(memory),
+        approx_percentile(),
+        histogram(),
+        selector_spread(),
+        selector_distinct(memory),
+        selector_count_distinct(),
+    ];
+
+    for udaf in udafs {
+        state.register_udaf(udaf);
+    }
 
     state
 }
@@ -77,11 +140,20 @@ pub fn register_selector_aggregates(mut state: SessionState) -> SessionState {
 /// ```
 ///
 /// If there are multiple rows with the minimum timestamp value, the
-/// value is arbitrary
+/// value is arbitrary. See [`struct_selector_first_with_tie_break`]
+/// to choose the value deterministically.
 pub fn struct_selector_first() -> Arc<AggregateUDF> {
+    struct_selector_first_with_tie_break(SelectorTieBreak::FirstSeen)
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the first(value, time) selector function, as
+/// [`struct_selector_first`], using `tie_break` to choose the value
+/// if there are multiple rows with the minimum timestamp value.
+pub fn struct_selector_first_with_tie_break(tie_break: SelectorTieBreak) -> Arc<AggregateUDF> {
     Arc::new(make_uda(
-        "selector_first",
-        FactoryBuilder::new(SelectorType::First, SelectorOutput::Struct),
+        &tie_break.function_name("selector_first"),
+        FactoryBuilder::new(SelectorType::First).with_tie_break(tie_break),
     ))
 }
 
@@ -98,11 +170,20 @@ pub fn struct_selector_first() -> Arc<AggregateUDF> {
 /// ```
 ///
 /// If there are multiple rows with the maximum timestamp value, the
-/// value is arbitrary
+/// value is arbitrary. See [`struct_selector_last_with_tie_break`]
+/// to choose the value deterministically.
 pub fn struct_selector_last() -> Arc<AggregateUDF> {
+    struct_selector_last_with_tie_break(SelectorTieBreak::FirstSeen)
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the last(value, time) selector function, as
+/// [`struct_selector_last`], using `tie_break` to choose the value if
+/// there are multiple rows with the maximum timestamp value.
+pub fn struct_selector_last_with_tie_break(tie_break: SelectorTieBreak) -> Arc<AggregateUDF> {
     Arc::new(make_uda(
-        "selector_last",
-        FactoryBuilder::new(SelectorType::Last, SelectorOutput::Struct),
+        &tie_break.function_name("selector_last"),
+        FactoryBuilder::new(SelectorType::Last).with_tie_break(tie_break),
     ))
 }
 
@@ -123,7 +204,7 @@ pub fn struct_selector_last() -> Arc<AggregateUDF> {
 pub fn struct_selector_min() -> Arc<AggregateUDF> {
     Arc::new(make_uda(
         "selector_min",
-        FactoryBuilder::new(SelectorType::Min, SelectorOutput::Struct),
+        FactoryBuilder::new(SelectorType::Min),
     ))
 }
 
@@ -144,120 +225,326 @@ pub fn struct_selector_min() -> Arc<AggregateUDF> {
 pub fn struct_selector_max() -> Arc<AggregateUDF> {
     Arc::new(make_uda(
         "selector_max",
-        FactoryBuilder::new(SelectorType::Max, SelectorOutput::Struct),
+        FactoryBuilder::new(SelectorType::Max),
     ))
 }
 
 /// Returns a DataFusion user defined aggregate function for computing
-/// one field of the first() selector function.
-///
-/// Note that until <https://issues.apache.org/jira/browse/ARROW-10945>
-/// is fixed, selector functions must be computed using two separate
-/// function calls, one each for the value and time part
+/// the top(value, time, n) selector function, returning a list of
+/// structs:
 ///
-/// first(value_column, timestamp_column) -> value and timestamp
+/// top(value, time, n) -> list [ struct { value, time } ]
 ///
-/// timestamp is the minimum value of the timestamp_column
+/// ```text
+/// [
+///   {
+///     value: one of the n largest values
+///     time: value of time for the row with that value
+///   },
+///   ...
+/// ]
+/// ```
 ///
-/// value is the value of the value_column at the position of the
-/// minimum of the timestamp column. If there are multiple rows with
-/// the minimum timestamp value, the value of the value_column is
-/// arbitrarily picked
-pub fn selector_first(data_type: &DataType, output: SelectorOutput) -> AggregateUDF {
-    let name = match output {
-        SelectorOutput::Value => "selector_first_value",
-        SelectorOutput::Time => "selector_first_time",
-        SelectorOutput::Struct => "selector_first",
-    };
-
-    make_uda(
-        name,
-        FactoryBuilder::new(SelectorType::First, output).with_value_type(data_type.clone()),
-    )
+/// The list is ordered from largest to smallest value. If there are
+/// multiple rows with the same value, the rows with the first
+/// (earliest/smallest) timestamps are chosen
+pub fn struct_selector_top(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_top_uda("selector_top", TopType::Top, memory))
 }
 
 /// Returns a DataFusion user defined aggregate function for computing
-/// one field of the last() selector function.
+/// the bottom(value, time, n) selector function, returning a list of
+/// structs:
 ///
-/// Note that until <https://issues.apache.org/jira/browse/ARROW-10945>
-/// is fixed, selector functions must be computed using two separate
-/// function calls, one each for the value and time part
+/// bottom(value, time, n) -> list [ struct { value, time } ]
 ///
-/// selector_last(data_column, timestamp_column) -> value and timestamp
-///
-/// timestamp is the maximum value of the timestamp_column
+/// ```text
+/// [
+///   {
+///     value: one of the n smallest values
+///     time: value of time for the row with that value
+///   },
+///   ...
+/// ]
+/// ```
 ///
-/// value is the value of the data_column at the position of the
-/// maximum of the timestamp column. If there are multiple rows with
-/// the maximum timestamp value, the value of the data_column is
-/// arbitrarily picked
-pub fn selector_last(data_type: &DataType, output: SelectorOutput) -> AggregateUDF {
-    let name = match output {
-        SelectorOutput::Value => "selector_last_value",
-        SelectorOutput::Time => "selector_last_time",
-        SelectorOutput::Struct => "selector_last",
-    };
+/// The list is ordered from smallest to largest value. If there are
+/// multiple rows with the same value, the rows with the first
+/// (earliest/smallest) timestamps are chosen
+pub fn struct_selector_bottom(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_top_uda("selector_bottom", TopType::Bottom, memory))
+}
 
-    make_uda(
-        name,
-        FactoryBuilder::new(SelectorType::Last, output).with_value_type(data_type.clone()),
-    )
+/// Returns a DataFusion user defined aggregate function for computing
+/// the mode(value) function: the most frequent value.
+///
+/// mode(value) -> value
+///
+/// If there are multiple values with the same (maximum) frequency,
+/// the smallest value is chosen
+pub fn selector_mode(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_mode_uda("selector_mode", memory))
 }
 
 /// Returns a DataFusion user defined aggregate function for computing
-/// one field of the min() selector function.
+/// the median(value) function: the middle value once sorted.
 ///
-/// Note that until <https://issues.apache.org/jira/browse/ARROW-10945>
-/// is fixed, selector functions must be computed using two separate
-/// function calls, one each for the value and time part
+/// median(value) -> f64
 ///
-/// selector_min(data_column, timestamp_column) -> value and timestamp
+/// If there are an even number of values, the median is the mean of
+/// the two middle values
+pub fn selector_median(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_median_uda("selector_median", memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for
+/// estimating the `p`th percentile (between 0 and 1) of the values
+/// using a t-digest.
 ///
-/// value is the minimum value of the data_column
+/// approx_percentile(value, p) -> f64
 ///
-/// timestamp is the value of the timestamp_column at the position of
-/// the minimum value_column. If there are multiple rows with the
-/// minimum timestamp value, the value of the data_column with the
-/// first (earliest/smallest) timestamp is chosen
-pub fn selector_min(data_type: &DataType, output: SelectorOutput) -> AggregateUDF {
-    let name = match output {
-        SelectorOutput::Value => "selector_min_value",
-        SelectorOutput::Time => "selector_min_time",
-        SelectorOutput::Struct => "selector_min",
-    };
+/// The estimate is exact for small numbers of values, and for large
+/// numbers is most accurate for percentiles close to 0 and 1. Use
+/// this rather than sorting all the values when there are many
+pub fn approx_percentile() -> Arc<AggregateUDF> {
+    Arc::new(make_approx_percentile_uda("approx_percentile"))
+}
 
-    make_uda(
-        name,
-        FactoryBuilder::new(SelectorType::Min, output).with_value_type(data_type.clone()),
-    )
+/// Returns a DataFusion user defined aggregate function for counting
+/// the values in each bucket of a histogram, where the buckets are
+/// between consecutive values of `bucket_edges`, a list of floats in
+/// ascending order.
+///
+/// histogram(value, bucket_edges) -> list of {lower: f64, upper: f64, count: u64}
+///
+/// Each bucket includes values equal to its lower edge but not its
+/// upper edge, other than the last bucket which includes both. Values
+/// outside of the edges are not counted
+pub fn histogram() -> Arc<AggregateUDF> {
+    Arc::new(make_histogram_uda("histogram"))
 }
 
 /// Returns a DataFusion user defined aggregate function for computing
-/// one field of the max() selector function.
+/// the InfluxQL spread(value) function: the difference between the
+/// largest and smallest values.
 ///
-/// Note that until <https://issues.apache.org/jira/browse/ARROW-10945>
-/// is fixed, selector functions must be computed using two separate
-/// function calls, one each for the value and time part
+/// spread(value) -> value
+pub fn selector_spread() -> Arc<AggregateUDF> {
+    Arc::new(make_spread_uda("selector_spread"))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL distinct(value) function: the distinct values.
+///
+/// distinct(value) -> list of value
 ///
-/// selector_max(data_column, timestamp_column) -> value and timestamp
+/// The values are returned in ascending order
+pub fn selector_distinct(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_distinct_uda("selector_distinct", memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for
+/// estimating the number of distinct values using HyperLogLog, as for
+/// count(distinct(value)) in InfluxQL.
 ///
-/// value is the maximum value of the data_column
+/// count_distinct(value) -> u64
 ///
-/// timestamp is the value of the timestamp_column at the position of
-/// the maximum value_column. If there are multiple rows with the
-/// maximum timestamp value, the value of the data_column with the
-/// first (earliest/smallest) timestamp is chosen
-pub fn selector_max(data_type: &DataType, output: SelectorOutput) -> AggregateUDF {
-    let name = match output {
-        SelectorOutput::Value => "selector_max_value",
-        SelectorOutput::Time => "selector_max_time",
-        SelectorOutput::Struct => "selector_max",
-    };
+/// The estimate is exact for small numbers of distinct values, and
+/// has a standard error of about 1.6% for large numbers
+pub fn selector_count_distinct() -> Arc<AggregateUDF> {
+    Arc::new(make_count_distinct_uda("selector_count_distinct"))
+}
 
-    make_uda(
-        name,
-        FactoryBuilder::new(SelectorType::Max, output).with_value_type(data_type.clone()),
-    )
+/// Returns an expression that extracts the `value` part of the
+/// struct produced by a selector expression such as
+/// `selector_first(value, time)`
+pub fn selector_value(selector: Expr) -> Expr {
+    selector_field(selector, SELECTOR_VALUE_FIELD)
+}
+
+/// Returns an expression that extracts the `time` part of the struct
+/// produced by a selector expression such as
+/// `selector_first(value, time)`
+pub fn selector_time(selector: Expr) -> Expr {
+    selector_field(selector, SELECTOR_TIME_FIELD)
+}
+
+fn selector_field(selector: Expr, name: &str) -> Expr {
+    Expr::GetIndexedField {
+        expr: Box::new(selector),
+        key: ScalarValue::Utf8(Some(name.to_string())),
+    }
+}
+
+/// Determines which value the first / last selectors choose when
+/// there are multiple rows with the same minimum / maximum time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelectorTieBreak {
+    /// Choose the value that was seen first. As the order in which
+    /// rows are seen depends on the order of the input batches, the
+    /// chosen value may differ between executions
+    #[default]
+    FirstSeen,
+    /// Choose the smallest value
+    SmallestValue,
+    /// Choose the largest value
+    LargestValue,
+}
+
+impl SelectorTieBreak {
+    /// Choose between `current` and `candidate`, which were seen (in
+    /// that order) at the same time
+    fn choose<T: PartialOrd>(self, current: T, candidate: T) -> T {
+        match self {
+            Self::FirstSeen => current,
+            Self::SmallestValue if candidate < current => candidate,
+            Self::LargestValue if current < candidate => candidate,
+            Self::SmallestValue | Self::LargestValue => current,
+        }
+    }
+
+    /// Returns the name of selector function `base_name` using this
+    /// policy, so that functions with different policies are distinct
+    fn function_name(self, base_name: &str) -> String {
+        match self {
+            Self::FirstSeen => base_name.to_string(),
+            Self::SmallestValue => format!("{}_smallest_value", base_name),
+            Self::LargestValue => format!("{}_largest_value", base_name),
+        }
+    }
+}
+
+/// Determines what the first / last / min / max selectors return
+/// when there are no (non null) values to select from, such as for
+/// an empty window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelectorEmptyBehavior {
+    /// Return a struct whose fields are all null
+    #[default]
+    NullFields,
+    /// Return null instead of a struct, so that the empty groups can
+    /// be dropped by filtering on `IS NOT NULL`
+    Null,
+    /// Return an error
+    Error,
+}
+
+impl SelectorEmptyBehavior {
+    /// Returns the name of selector function `base_name` using this
+    /// policy, so that functions with different policies are distinct
+    fn function_name(self, base_name: &str) -> String {
+        match self {
+            Self::NullFields => base_name.to_string(),
+            Self::Null => format!("{}_null_on_empty", base_name),
+            Self::Error => format!("{}_error_on_empty", base_name),
+        }
+    }
+}
+
+/// Determines how the min / max selectors compare string values
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelectorCollation {
+    /// Compare the UTF-8 bytes of the strings, so that, for example,
+    /// all upper case ASCII letters sort before lower case ones
+    #[default]
+    Binary,
+    /// Compare the strings using the Unicode Collation Algorithm with
+    /// the CLDR root locale order (as used by ICU), so that, for
+    /// example, "a" < "B" < "é" < "z"
+    Locale,
+}
+
+impl SelectorCollation {
+    /// Returns the name of selector function `base_name` using this
+    /// policy, so that functions with different policies are distinct
+    fn function_name(self, base_name: &str) -> String {
+        match self {
+            Self::Binary => base_name.to_string(),
+            Self::Locale => format!("{}_locale_collation", base_name),
+        }
+    }
+}
+
+/// Builder for the first / last / min / max selector functions, for
+/// choosing the behavior of the function where it is configurable.
+///
+/// ```
+/// use query_functions::selectors::{SelectorBuilder, SelectorEmptyBehavior};
+///
+/// let udaf = SelectorBuilder::last()
+///     .with_empty_behavior(SelectorEmptyBehavior::Null)
+///     .build();
+/// assert_eq!(udaf.name, "selector_last_null_on_empty");
+/// ```
+#[derive(Debug)]
+pub struct SelectorBuilder {
+    base_name: &'static str,
+    factory_builder: FactoryBuilder,
+}
+
+impl SelectorBuilder {
+    /// Build the first(value, time) selector, as [`struct_selector_first`]
+    pub fn first() -> Self {
+        Self::new("selector_first", SelectorType::First)
+    }
+
+    /// Build the last(value, time) selector, as [`struct_selector_last`]
+    pub fn last() -> Self {
+        Self::new("selector_last", SelectorType::Last)
+    }
+
+    /// Build the min(value, time) selector, as [`struct_selector_min`]
+    pub fn min() -> Self {
+        Self::new("selector_min", SelectorType::Min)
+    }
+
+    /// Build the max(value, time) selector, as [`struct_selector_max`]
+    pub fn max() -> Self {
+        Self::new("selector_max", SelectorType::Max)
+    }
+
+    fn new(base_name: &'static str, selector_type: SelectorType) -> Self {
+        Self {
+            base_name,
+            factory_builder: FactoryBuilder::new(selector_type),
+        }
+    }
+
+    /// Specify how to choose between rows with the same time. Only
+    /// used by the first and last selectors
+    pub fn with_tie_break(mut self, tie_break: SelectorTieBreak) -> Self {
+        self.factory_builder = self.factory_builder.with_tie_break(tie_break);
+        self
+    }
+
+    /// Specify what to return when there are no values to select from
+    pub fn with_empty_behavior(mut self, empty_behavior: SelectorEmptyBehavior) -> Self {
+        self.factory_builder = self.factory_builder.with_empty_behavior(empty_behavior);
+        self
+    }
+
+    /// Specify how to compare string values. Only used by the min
+    /// and max selectors
+    pub fn with_collation(mut self, collation: SelectorCollation) -> Self {
+        self.factory_builder = self.factory_builder.with_collation(collation);
+        self
+    }
+
+    /// Create the user defined aggregate function. Its name includes
+    /// any non default behaviors, so that differently configured
+    /// functions can be registered together
+    pub fn build(self) -> Arc<AggregateUDF> {
+        let Self {
+            base_name,
+            factory_builder,
+        } = self;
+
+        let name = factory_builder.tie_break.function_name(base_name);
+        let name = factory_builder.collation.function_name(&name);
+        let name = factory_builder.empty_behavior.function_name(&name);
+
+        Arc::new(make_uda(&name, factory_builder))
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -273,43 +560,48 @@ enum SelectorType {
 struct FactoryBuilder {
     selector_type: SelectorType,
 
-    output_type: SelectorOutput,
+    // How to choose between rows with the same time (only used by
+    // the first and last selectors)
+    tie_break: SelectorTieBreak,
 
-    // If the selector output is "time" we can't determine the
-    // accumuator type from the return type, so hold we pass the data type explicitly
-    value_type: Option<DataType>,
+    // What to return when there are no values to select from
+    empty_behavior: SelectorEmptyBehavior,
+
+    // How to compare strings (only used by the min and max selectors)
+    collation: SelectorCollation,
 }
 
 impl FactoryBuilder {
-    fn new(selector_type: SelectorType, output_type: SelectorOutput) -> Self {
+    fn new(selector_type: SelectorType) -> Self {
         Self {
             selector_type,
-            output_type,
-            value_type: None,
+            tie_break: SelectorTieBreak::default(),
+            empty_behavior: SelectorEmptyBehavior::default(),
+            collation: SelectorCollation::default(),
         }
     }
 
-    /// Specify the value_type of this selector (needed when the
-    /// output_type is "Time")
-    fn with_value_type(mut self, value_type: DataType) -> Self {
-        self.value_type = Some(value_type);
+    /// Specify how to choose between rows with the same time
+    fn with_tie_break(mut self, tie_break: SelectorTieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Specify what to return when there are no values to select from
+    fn with_empty_behavior(mut self, empty_behavior: SelectorEmptyBehavior) -> Self {
+        self.empty_behavior = empty_behavior;
         self
     }
 
-    fn output_type(&self) -> SelectorOutput {
-        self.output_type
+    /// Specify how to compare strings
+    fn with_collation(mut self, collation: SelectorCollation) -> Self {
+        self.collation = collation;
+        self
     }
 
     fn build_state_type_factory(&self) -> StateTypeFactory {
-        let value_type = self.value_type.clone();
-
         Arc::new(move |return_type| {
-            let value_type = match &value_type {
-                Some(t) => t,
-                None => value_data_type_from_return_data_type(return_type),
-            };
-
-            let state_types = make_state_datatypes(value_type.clone());
+            let state_types = make_state_datatypes(return_type);
             Ok(Arc::new(state_types))
         })
     }
@@ -318,57 +610,53 @@ impl FactoryBuilder {
     fn build_accumulator_factory(self) -> AccumulatorFunctionImplementation {
         let Self {
             selector_type,
-            output_type,
-            value_type,
+            tie_break,
+            empty_behavior,
+            collation,
         } = self;
 
         Arc::new(move |return_type| {
-            let value_type = match &value_type {
-                Some(t) => t,
-                None => value_data_type_from_return_data_type(return_type),
-            };
+            let value_type = value_data_type_from_return_data_type(return_type);
+            let other_types = other_data_types_from_return_data_type(return_type);
 
             let accumulator: Box<dyn Accumulator> = match (selector_type, value_type) {
                 // First
-                (SelectorType::First, DataType::Float64) => {
-                    Box::new(SelectorAccumulator::<F64FirstSelector>::new(output_type))
-                }
-                (SelectorType::First, DataType::Int64) => Box::new(SelectorAccumulator::<I64FirstSelector>::new(output_type)),
-                (SelectorType::First, DataType::UInt64) => Box::new(SelectorAccumulator::<U64FirstSelector>::new(output_type)),
-                (SelectorType::First, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8FirstSelector>::new(output_type)),
-                (SelectorType::First, DataType::Boolean) => Box::new(SelectorAccumulator::<BooleanFirstSelector>::new(
-                    output_type,
-                )),
+                (SelectorType::First, DataType::Float64) => Box::new(SelectorAccumulator::<F64FirstSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::First, DataType::Int64) => Box::new(SelectorAccumulator::<I64FirstSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::First, DataType::UInt64) => Box::new(SelectorAccumulator::<U64FirstSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::First, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8FirstSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::First, DataType::Boolean) => Box::new(SelectorAccumulator::<BooleanFirstSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::First, DataType::Decimal128(_, _)) => Box::new(SelectorAccumulator::<Decimal128FirstSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
 
                 // Last
-                (SelectorType::Last, DataType::Float64) => Box::new(SelectorAccumulator::<F64LastSelector>::new(output_type)),
-                (SelectorType::Last, DataType::Int64) => Box::new(SelectorAccumulator::<I64LastSelector>::new(output_type)),
-                (SelectorType::Last, DataType::UInt64) => Box::new(SelectorAccumulator::<U64LastSelector>::new(output_type)),
-                (SelectorType::Last, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8LastSelector>::new(output_type)),
-                (SelectorType::Last, DataType::Boolean) => {
-                    Box::new(SelectorAccumulator::<BooleanLastSelector>::new(output_type))
-                },
+                (SelectorType::Last, DataType::Float64) => Box::new(SelectorAccumulator::<F64LastSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Last, DataType::Int64) => Box::new(SelectorAccumulator::<I64LastSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Last, DataType::UInt64) => Box::new(SelectorAccumulator::<U64LastSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Last, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8LastSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Last, DataType::Boolean) => Box::new(SelectorAccumulator::<BooleanLastSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Last, DataType::Decimal128(_, _)) => Box::new(SelectorAccumulator::<Decimal128LastSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
 
                 // Min
-                (SelectorType::Min, DataType::Float64) => Box::new(SelectorAccumulator::<F64MinSelector>::new(output_type)),
-                (SelectorType::Min, DataType::Int64) => Box::new(SelectorAccumulator::<I64MinSelector>::new(output_type)),
-                (SelectorType::Min, DataType::UInt64) => Box::new(SelectorAccumulator::<U64MinSelector>::new(output_type)),
-                (SelectorType::Min, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8MinSelector>::new(output_type)),
-                (SelectorType::Min, DataType::Boolean) => {
-                    Box::new(SelectorAccumulator::<BooleanMinSelector>::new(output_type))
-                },
+                (SelectorType::Min, DataType::Float64) => Box::new(SelectorAccumulator::<F64MinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Min, DataType::Int64) => Box::new(SelectorAccumulator::<I64MinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Min, DataType::UInt64) => Box::new(SelectorAccumulator::<U64MinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Min, DataType::Utf8) if collation == SelectorCollation::Locale => Box::new(SelectorAccumulator::<Utf8CollatedMinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Min, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8MinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Min, DataType::Boolean) => Box::new(SelectorAccumulator::<BooleanMinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Min, DataType::Decimal128(_, _)) => Box::new(SelectorAccumulator::<Decimal128MinSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
 
                 // Max
-                (SelectorType::Max, DataType::Float64) => Box::new(SelectorAccumulator::<F64MaxSelector>::new(output_type)),
-                (SelectorType::Max, DataType::Int64) => Box::new(SelectorAccumulator::<I64MaxSelector>::new(output_type)),
-                (SelectorType::Max, DataType::UInt64) => Box::new(SelectorAccumulator::<U64MaxSelector>::new(output_type)),
-                (SelectorType::Max, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8MaxSelector>::new(output_type)),
-                (SelectorType::Max, DataType::Boolean) => {
-                    Box::new(SelectorAccumulator::<BooleanMaxSelector>::new(output_type))
-                },
+                (SelectorType::Max, DataType::Float64) => Box::new(SelectorAccumulator::<F64MaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Max, DataType::Int64) => Box::new(SelectorAccumulator::<I64MaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Max, DataType::UInt64) => Box::new(SelectorAccumulator::<U64MaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Max, DataType::Utf8) if collation == SelectorCollation::Locale => Box::new(SelectorAccumulator::<Utf8CollatedMaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Max, DataType::Utf8) => Box::new(SelectorAccumulator::<Utf8MaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Max, DataType::Boolean) => Box::new(SelectorAccumulator::<BooleanMaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+                (SelectorType::Max, DataType::Decimal128(_, _)) => Box::new(SelectorAccumulator::<Decimal128MaxSelector>::new(tie_break, empty_behavior, value_type, other_types)?),
+
                 // Catch
                 (selector_type, value_type) => return Err(DataFusionError::Internal(format!(
-                    "Unhandled selector type. Expected value type of f64/i64/u64/string/bool, got {:?} for {:?}",
+                    "Unhandled selector type. Expected value type of f64/i64/u64/string/bool/decimal, got {:?} for {:?}",
                     selector_type, value_type,
                 ))),
             };
@@ -380,52 +668,42 @@ impl FactoryBuilder {
 /// Implements the logic of the specific selector function (this is a
 /// cutdown version of the Accumulator DataFusion trait, to allow
 /// sharing between implementations)
-trait Selector: Debug + Default + Send + Sync {
+trait Selector: Debug + Send + Sync {
+    /// Create a new selector for values of `value_type` with no
+    /// state, using `tie_break` to choose between rows with the same
+    /// time where applicable
+    fn new(tie_break: SelectorTieBreak, value_type: &DataType) -> Self;
+
     /// What type of values does this selector function work with (time is
     /// always I64)
-    fn value_data_type() -> DataType;
+    fn value_data_type(&self) -> DataType;
 
     /// return state in a form that DataFusion can store during execution
     fn datafusion_state(&self) -> DataFusionResult<Vec<AggregateState>>;
 
-    /// produces the final value of this selector for the specified output type
-    fn evaluate(&self, output: &SelectorOutput) -> DataFusionResult<ScalarValue>;
+    /// produces the final value of this selector as a struct {value, time}
+    fn evaluate(&self) -> DataFusionResult<ScalarValue>;
 
     /// Update this selector's state based on values in value_arr and time_arr
     fn update_batch(&mut self, value_arr: &ArrayRef, time_arr: &ArrayRef) -> DataFusionResult<()>;
 }
 
-/// Describes which part of the selector to return: the timestamp or
-/// the value (when <https://issues.apache.org/jira/browse/ARROW-10945>
-/// is fixed, this enum should be removed)
-#[derive(Debug, Clone, Copy)]
-pub enum SelectorOutput {
-    /// Return the value
-    Value,
-    /// Return the timestamp
-    Time,
-    /// Return the value and timestamp as a struct {value, time}
-    Struct,
-}
-
-impl SelectorOutput {
-    /// return the data type produced for this type of input
-    fn return_type(&self, input_type: &DataType) -> DataType {
-        match self {
-            Self::Value => input_type.clone(),
-            // timestamps are always the same type
-            Self::Time => TIME_DATA_TYPE(),
-            Self::Struct => DataType::Struct(make_struct_fields(input_type.clone())),
-        }
-    }
-}
-
-/// Create the struct fields for a selector with DataType `value_type`
-fn make_struct_fields(value_type: DataType) -> Vec<Field> {
-    vec![
-        Field::new("value", value_type, true),
-        Field::new("time", TIME_DATA_TYPE(), true),
+/// Create the struct fields for a selector with DataType
+/// `value_type`, followed by a field for each of the additional
+/// columns passed through from the selected row
+pub(crate) fn make_struct_fields(value_type: DataType, other_types: &[DataType]) -> Vec<Field> {
+    let others = other_types
+        .iter()
+        .enumerate()
+        .map(|(i, other_type)| Field::new(&format!("other_{}", i + 1), other_type.clone(), true));
+
+    [
+        Field::new(SELECTOR_VALUE_FIELD, value_type, true),
+        Field::new(SELECTOR_TIME_FIELD, TIME_DATA_TYPE(), true),
     ]
+    .into_iter()
+    .chain(others)
+    .collect()
 }
 
 /// Return the value type given the (struct) output data type
@@ -436,15 +714,47 @@ fn value_data_type_from_return_data_type(output_type: &DataType) -> &DataType {
     }
 }
 
-type ReturnTypeFunction = Arc<dyn Fn(&[DataType]) -> DataFusionResult<Arc<DataType>> + Send + Sync>;
-type StateTypeFactory =
+/// Return the types of the additional columns passed through from
+/// the selected row, given the (struct) output data type
+fn other_data_types_from_return_data_type(output_type: &DataType) -> Vec<DataType> {
+    match output_type {
+        DataType::Struct(fields) => fields
+            .iter()
+            .skip(2)
+            .map(|field| field.data_type().clone())
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Find the index of the (first) row with the selected value and
+/// time, if any
+fn find_selected_row(
+    value_arr: &ArrayRef,
+    time_arr: &ArrayRef,
+    selected_value: &ScalarValue,
+    selected_time: &ScalarValue,
+) -> DataFusionResult<Option<usize>> {
+    for idx in 0..value_arr.len() {
+        if &ScalarValue::try_from_array(time_arr, idx)? == selected_time
+            && &ScalarValue::try_from_array(value_arr, idx)? == selected_value
+        {
+            return Ok(Some(idx));
+        }
+    }
+    Ok(None)
+}
+
+pub(crate) type ReturnTypeFunction =
+    Arc<dyn Fn(&[DataType]) -> DataFusionResult<Arc<DataType>> + Send + Sync>;
+pub(crate) type StateTypeFactory =
     Arc<dyn Fn(&DataType) -> DataFusionResult<Arc<Vec<DataType>>> + Send + Sync>;
 
 /// Create a User Defined Aggregate Function (UDAF) for datafusion.
 fn make_uda(name: &str, factory_builder: FactoryBuilder) -> AggregateUDF {
-    let output_type = factory_builder.output_type();
-
-    // All selectors support the same input types / signatures
+    // All selectors support the same input types / signatures. Any
+    // number of additional columns (of any type) may follow the value
+    // and time
     let input_signature = Signature::one_of(
         vec![
             TypeSignature::Exact(vec![DataType::Float64, TIME_DATA_TYPE()]),
@@ -452,24 +762,54 @@ fn make_uda(name: &str, factory_builder: FactoryBuilder) -> AggregateUDF {
             TypeSignature::Exact(vec![DataType::UInt64, TIME_DATA_TYPE()]),
             TypeSignature::Exact(vec![DataType::Utf8, TIME_DATA_TYPE()]),
             TypeSignature::Exact(vec![DataType::Boolean, TIME_DATA_TYPE()]),
-        ],
+            TypeSignature::Exact(vec![DataType::Float32, TIME_DATA_TYPE()]),
+            TypeSignature::Exact(vec![DataType::Int32, TIME_DATA_TYPE()]),
+            TypeSignature::Exact(vec![DataType::UInt32, TIME_DATA_TYPE()]),
+            // Decimal128 values of any precision and scale, checked
+            // by the return type function
+            TypeSignature::Any(2),
+        ]
+        .into_iter()
+        .chain((1..=MAX_SELECTOR_OTHER_COLUMNS).map(|n| TypeSignature::Any(n + 2)))
+        .collect(),
         Volatility::Stable,
     );
 
     // return type of the selector is based on the input arguments.
     //
-    // The inputs are (value, time) and the output is a struct with a
-    // 'value' and 'time' field of the same time.
+    // The inputs are (value, time, other_1, ..., other_N) and the
+    // output is a struct with a 'value' and 'time' field of the same
+    // time, followed by fields 'other_1' through 'other_N'. 32-bit
+    // values are widened to their 64-bit equivalents, and Decimal128
+    // values keep their precision and scale.
     let return_type_func: ReturnTypeFunction = Arc::new(move |arg_types| {
-        assert_eq!(
-            arg_types.len(),
-            2,
-            "selector expected exactly 2 arguments, got {}",
+        assert!(
+            arg_types.len() >= 2,
+            "selector expected at least 2 arguments, got {}",
             arg_types.len()
         );
-        let input_type = &arg_types[0];
-        assert_eq!(&arg_types[1], &TIME_DATA_TYPE());
-        let return_type = output_type.return_type(input_type);
+        let input_type = widened_value_type(&arg_types[0]);
+        if !matches!(
+            input_type,
+            DataType::Float64
+                | DataType::Int64
+                | DataType::UInt64
+                | DataType::Utf8
+                | DataType::Boolean
+                | DataType::Decimal128(_, _)
+        ) {
+            return Err(DataFusionError::Plan(format!(
+                "selector expected a f64/i64/u64/string/bool/decimal value, got {:?}",
+                input_type
+            )));
+        }
+        if arg_types[1] != TIME_DATA_TYPE() {
+            return Err(DataFusionError::Plan(format!(
+                "selector expected the second argument to be a time, got {:?}",
+                arg_types[1]
+            )));
+        }
+        let return_type = DataType::Struct(make_struct_fields(input_type, &arg_types[2..]));
 
         Ok(Arc::new(return_type))
     });
@@ -486,9 +826,37 @@ fn make_uda(name: &str, factory_builder: FactoryBuilder) -> AggregateUDF {
     )
 }
 
-/// Return the state in which the arguments are stored
-fn make_state_datatypes(value_type: DataType) -> Vec<DataType> {
-    vec![value_type, TIME_DATA_TYPE()]
+/// Return the type selector values of `value_type` are computed as:
+/// 32-bit numeric types are widened to the 64-bit equivalent
+fn widened_value_type(value_type: &DataType) -> DataType {
+    match value_type {
+        DataType::Float32 => DataType::Float64,
+        DataType::Int32 => DataType::Int64,
+        DataType::UInt32 => DataType::UInt64,
+        t => t.clone(),
+    }
+}
+
+/// Cast `value_arr` to its [`widened_value_type`], if different
+fn widen_value_array(value_arr: &ArrayRef) -> DataFusionResult<ArrayRef> {
+    let value_type = widened_value_type(value_arr.data_type());
+    if &value_type == value_arr.data_type() {
+        return Ok(Arc::clone(value_arr));
+    }
+    Ok(cast(value_arr, &value_type)?)
+}
+
+/// Return the state in which the arguments are stored, given the
+/// (struct) output data type: the value, the time and then any
+/// additional columns
+fn make_state_datatypes(return_type: &DataType) -> Vec<DataType> {
+    match return_type {
+        DataType::Struct(fields) => fields
+            .iter()
+            .map(|field| field.data_type().clone())
+            .collect(),
+        t => vec![t.clone(), TIME_DATA_TYPE()],
+    }
 }
 
 /// Structure that implements the Accumulator trait for DataFusion
@@ -500,19 +868,32 @@ where
 {
     // The underlying implementation for the selector
     selector: SELECTOR,
-    // Determine which value is output
-    output: SelectorOutput,
+    // The values of any additional columns in the selected row
+    others: Vec<ScalarValue>,
+    // What to return when there are no values to select from
+    empty_behavior: SelectorEmptyBehavior,
 }
 
 impl<SELECTOR> SelectorAccumulator<SELECTOR>
 where
     SELECTOR: Selector,
 {
-    pub fn new(output: SelectorOutput) -> Self {
-        Self {
-            output,
-            selector: SELECTOR::default(),
-        }
+    pub fn new(
+        tie_break: SelectorTieBreak,
+        empty_behavior: SelectorEmptyBehavior,
+        value_type: &DataType,
+        other_types: Vec<DataType>,
+    ) -> DataFusionResult<Self> {
+        let others = other_types
+            .iter()
+            .map(ScalarValue::try_from)
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        Ok(Self {
+            selector: SELECTOR::new(tie_break, value_type),
+            others,
+            empty_behavior,
+        })
     }
 }
 
@@ -524,12 +905,41 @@ where
     // `ScalarValue`s, which DataFusion uses to pass this state
     // between execution stages.
     fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
-        self.selector.datafusion_state()
+        let mut state = self.selector.datafusion_state()?;
+        state.extend(self.others.iter().cloned().map(AggregateState::Scalar));
+        Ok(state)
     }
 
     // Return the final value of this aggregator.
     fn evaluate(&self) -> DataFusionResult<ScalarValue> {
-        self.selector.evaluate(&self.output)
+        let (values, fields) = match self.selector.evaluate()? {
+            ScalarValue::Struct(Some(values), fields) if self.others.is_empty() => (values, fields),
+            ScalarValue::Struct(Some(mut values), _) => {
+                let other_types: Vec<_> = self.others.iter().map(|v| v.get_datatype()).collect();
+                let fields = make_struct_fields(values[0].get_datatype(), &other_types);
+                values.extend(self.others.iter().cloned());
+                (values, Box::new(fields))
+            }
+            v => {
+                return Err(DataFusionError::Internal(format!(
+                    "Internal error: Expected selector to produce a struct, got {:?}",
+                    v
+                )))
+            }
+        };
+
+        // nothing was selected if there is no time
+        if !values[1].is_null() {
+            return Ok(ScalarValue::Struct(Some(values), fields));
+        }
+
+        match self.empty_behavior {
+            SelectorEmptyBehavior::NullFields => Ok(ScalarValue::Struct(Some(values), fields)),
+            SelectorEmptyBehavior::Null => Ok(ScalarValue::Struct(None, fields)),
+            SelectorEmptyBehavior::Error => Err(DataFusionError::Execution(
+                "Selector found no values to select from".to_string(),
+            )),
+        }
     }
 
     // This function receives one entry per argument of this
@@ -539,15 +949,41 @@ where
             return Ok(());
         }
 
-        if values.len() != 2 {
+        let expected_len = 2 + self.others.len();
+        if values.len() != expected_len {
             return Err(DataFusionError::Internal(format!(
-                "Internal error: Expected 2 arguments passed to selector function but got {}",
+                "Internal error: Expected {} arguments passed to selector function but got {}",
+                expected_len,
                 values.len()
             )));
         }
 
+        let value_arr = widen_value_array(&values[0])?;
+
+        if self.others.is_empty() {
+            // invoke the actual worker function.
+            self.selector.update_batch(&value_arr, &values[1])?;
+            return Ok(());
+        }
+
+        let before = self.selector.evaluate()?;
         // invoke the actual worker function.
-        self.selector.update_batch(&values[0], &values[1])?;
+        self.selector.update_batch(&value_arr, &values[1])?;
+        let after = self.selector.evaluate()?;
+
+        // If a different row was selected, remember its other columns
+        if before != after {
+            if let ScalarValue::Struct(Some(selected), _) = &after {
+                if let Some(idx) =
+                    find_selected_row(&value_arr, &values[1], &selected[0], &selected[1])?
+                {
+                    self.others = values[2..]
+                        .iter()
+                        .map(|arr| ScalarValue::try_from_array(arr, idx))
+                        .collect::<DataFusionResult<Vec<_>>>()?;
+                }
+            }
+        }
         Ok(())
     }
 
@@ -564,327 +1000,255 @@ where
 mod test {
     use arrow::{
         array::{
-            BooleanArray, Float64Array, Int64Array, StringArray, TimestampNanosecondArray,
-            UInt64Array,
+            Decimal128Array, Float32Array, Float64Array, Int32Array, Int64Array, StringArray,
+            TimestampNanosecondArray, UInt32Array,
         },
-        datatypes::{Field, Schema, SchemaRef},
+        datatypes::{Field, Schema},
         record_batch::RecordBatch,
-        util::pretty::pretty_format_batches,
     };
     use datafusion::{datasource::MemTable, prelude::*};
     use schema::TIME_DATA_TIMEZONE;
 
     use super::*;
+    use crate::test_util::{run_case, run_projected_plan, run_with_inputs};
 
     #[tokio::test]
-    async fn test_selector_first() {
+    async fn test_selector_first_fields() {
         let cases = vec![
             (
-                selector_first(&DataType::Float64, SelectorOutput::Value),
-                selector_first(&DataType::Float64, SelectorOutput::Time),
                 "f64_value",
                 vec![
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| selector_first_value(t.f64_value,t.time) | selector_first_time(t.f64_value,t.time) |",
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| 2                                        | 1970-01-01 00:00:00.000001              |",
-                    "+------------------------------------------+-----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 2     | 1970-01-01 00:00:00.000001 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_first(&DataType::Int64, SelectorOutput::Value),
-                selector_first(&DataType::Int64, SelectorOutput::Time),
                 "i64_value",
                 vec![
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| selector_first_value(t.i64_value,t.time) | selector_first_time(t.i64_value,t.time) |",
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| 20                                       | 1970-01-01 00:00:00.000001              |",
-                    "+------------------------------------------+-----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 20    | 1970-01-01 00:00:00.000001 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_first(&DataType::UInt64, SelectorOutput::Value),
-                selector_first(&DataType::UInt64, SelectorOutput::Time),
                 "u64_value",
                 vec![
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| selector_first_value(t.u64_value,t.time) | selector_first_time(t.u64_value,t.time) |",
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| 20                                       | 1970-01-01 00:00:00.000001              |",
-                    "+------------------------------------------+-----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 20    | 1970-01-01 00:00:00.000001 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_first(&DataType::Utf8, SelectorOutput::Value),
-                selector_first(&DataType::Utf8, SelectorOutput::Time),
                 "string_value",
                 vec![
-                    "+---------------------------------------------+--------------------------------------------+",
-                    "| selector_first_value(t.string_value,t.time) | selector_first_time(t.string_value,t.time) |",
-                    "+---------------------------------------------+--------------------------------------------+",
-                    "| two                                         | 1970-01-01 00:00:00.000001                 |",
-                    "+---------------------------------------------+--------------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| two   | 1970-01-01 00:00:00.000001 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_first(&DataType::Boolean, SelectorOutput::Value),
-                selector_first(&DataType::Boolean, SelectorOutput::Time),
                 "bool_value",
                 vec![
-                    "+-------------------------------------------+------------------------------------------+",
-                    "| selector_first_value(t.bool_value,t.time) | selector_first_time(t.bool_value,t.time) |",
-                    "+-------------------------------------------+------------------------------------------+",
-                    "| true                                      | 1970-01-01 00:00:00.000001               |",
-                    "+-------------------------------------------+------------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| true  | 1970-01-01 00:00:00.000001 |",
+                    "+-------+----------------------------+",
                 ],
-            )
+            ),
         ];
 
-        for (val_func, time_func, val_column, expected) in cases.into_iter() {
-            let args = vec![col(val_column), col("time")];
-            let aggs = vec![val_func.call(args.clone()), time_func.call(args)];
-            let actual = run_plan(aggs).await;
-
-            assert_eq!(
-                expected, actual,
-                "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
-                expected, actual
-            );
+        for (val_column, expected) in cases.into_iter() {
+            run_fields_case(struct_selector_first(), val_column, expected).await;
         }
     }
 
     #[tokio::test]
-    async fn test_selector_last() {
+    async fn test_selector_last_fields() {
         let cases = vec![
             (
-                selector_last(&DataType::Float64, SelectorOutput::Value),
-                selector_last(&DataType::Float64, SelectorOutput::Time),
                 "f64_value",
                 vec![
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| selector_last_value(t.f64_value,t.time) | selector_last_time(t.f64_value,t.time) |",
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| 3                                       | 1970-01-01 00:00:00.000006             |",
-                    "+-----------------------------------------+----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 3     | 1970-01-01 00:00:00.000006 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_last(&DataType::Int64, SelectorOutput::Value),
-                selector_last(&DataType::Int64, SelectorOutput::Time),
                 "i64_value",
                 vec![
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| selector_last_value(t.i64_value,t.time) | selector_last_time(t.i64_value,t.time) |",
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| 30                                      | 1970-01-01 00:00:00.000006             |",
-                    "+-----------------------------------------+----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 30    | 1970-01-01 00:00:00.000006 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_last(&DataType::UInt64, SelectorOutput::Value),
-                selector_last(&DataType::UInt64, SelectorOutput::Time),
                 "u64_value",
                 vec![
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| selector_last_value(t.u64_value,t.time) | selector_last_time(t.u64_value,t.time) |",
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| 30                                      | 1970-01-01 00:00:00.000006             |",
-                    "+-----------------------------------------+----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 30    | 1970-01-01 00:00:00.000006 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_last(&DataType::Utf8, SelectorOutput::Value),
-                selector_last(&DataType::Utf8, SelectorOutput::Time),
                 "string_value",
                 vec![
-                    "+--------------------------------------------+-------------------------------------------+",
-                    "| selector_last_value(t.string_value,t.time) | selector_last_time(t.string_value,t.time) |",
-                    "+--------------------------------------------+-------------------------------------------+",
-                    "| three                                      | 1970-01-01 00:00:00.000006                |",
-                    "+--------------------------------------------+-------------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| three | 1970-01-01 00:00:00.000006 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_last(&DataType::Boolean, SelectorOutput::Value),
-                selector_last(&DataType::Boolean, SelectorOutput::Time),
                 "bool_value",
                 vec![
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| selector_last_value(t.bool_value,t.time) | selector_last_time(t.bool_value,t.time) |",
-                    "+------------------------------------------+-----------------------------------------+",
-                    "| false                                    | 1970-01-01 00:00:00.000006              |",
-                    "+------------------------------------------+-----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| false | 1970-01-01 00:00:00.000006 |",
+                    "+-------+----------------------------+",
                 ],
-            )
+            ),
         ];
 
-        for (val_func, time_func, val_column, expected) in cases.into_iter() {
-            let args = vec![col(val_column), col("time")];
-            let aggs = vec![val_func.call(args.clone()), time_func.call(args)];
-            let actual = run_plan(aggs).await;
-
-            assert_eq!(
-                expected, actual,
-                "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
-                expected, actual
-            );
+        for (val_column, expected) in cases.into_iter() {
+            run_fields_case(struct_selector_last(), val_column, expected).await;
         }
     }
 
     #[tokio::test]
-    async fn test_selector_min() {
+    async fn test_selector_min_fields() {
         let cases = vec![
             (
-                selector_min(&DataType::Float64, SelectorOutput::Value),
-                selector_min(&DataType::Float64, SelectorOutput::Time),
                 "f64_value",
                 vec![
-                    "+----------------------------------------+---------------------------------------+",
-                    "| selector_min_value(t.f64_value,t.time) | selector_min_time(t.f64_value,t.time) |",
-                    "+----------------------------------------+---------------------------------------+",
-                    "| 1                                      | 1970-01-01 00:00:00.000004            |",
-                    "+----------------------------------------+---------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 1     | 1970-01-01 00:00:00.000004 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_min(&DataType::Int64, SelectorOutput::Value),
-                selector_min(&DataType::Int64, SelectorOutput::Time),
                 "i64_value",
                 vec![
-                    "+----------------------------------------+---------------------------------------+",
-                    "| selector_min_value(t.i64_value,t.time) | selector_min_time(t.i64_value,t.time) |",
-                    "+----------------------------------------+---------------------------------------+",
-                    "| 10                                     | 1970-01-01 00:00:00.000004            |",
-                    "+----------------------------------------+---------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 10    | 1970-01-01 00:00:00.000004 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_min(&DataType::UInt64, SelectorOutput::Value),
-                selector_min(&DataType::UInt64, SelectorOutput::Time),
                 "u64_value",
                 vec![
-                    "+----------------------------------------+---------------------------------------+",
-                    "| selector_min_value(t.u64_value,t.time) | selector_min_time(t.u64_value,t.time) |",
-                    "+----------------------------------------+---------------------------------------+",
-                    "| 10                                     | 1970-01-01 00:00:00.000004            |",
-                    "+----------------------------------------+---------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 10    | 1970-01-01 00:00:00.000004 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_min(&DataType::Utf8, SelectorOutput::Value),
-                selector_min(&DataType::Utf8, SelectorOutput::Time),
                 "string_value",
                 vec![
-                    "+-------------------------------------------+------------------------------------------+",
-                    "| selector_min_value(t.string_value,t.time) | selector_min_time(t.string_value,t.time) |",
-                    "+-------------------------------------------+------------------------------------------+",
-                    "| a_one                                     | 1970-01-01 00:00:00.000004               |",
-                    "+-------------------------------------------+------------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| a_one | 1970-01-01 00:00:00.000004 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_min(&DataType::Boolean, SelectorOutput::Value),
-                selector_min(&DataType::Boolean, SelectorOutput::Time),
                 "bool_value",
                 vec![
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| selector_min_value(t.bool_value,t.time) | selector_min_time(t.bool_value,t.time) |",
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| false                                   | 1970-01-01 00:00:00.000002             |",
-                    "+-----------------------------------------+----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| false | 1970-01-01 00:00:00.000002 |",
+                    "+-------+----------------------------+",
                 ],
-            )
+            ),
         ];
 
-        for (val_func, time_func, val_column, expected) in cases.into_iter() {
-            let args = vec![col(val_column), col("time")];
-            let aggs = vec![val_func.call(args.clone()), time_func.call(args)];
-            let actual = run_plan(aggs).await;
-
-            assert_eq!(
-                expected, actual,
-                "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
-                expected, actual
-            );
+        for (val_column, expected) in cases.into_iter() {
+            run_fields_case(struct_selector_min(), val_column, expected).await;
         }
     }
 
     #[tokio::test]
-    async fn test_selector_max() {
+    async fn test_selector_max_fields() {
         let cases = vec![
             (
-                selector_max(&DataType::Float64, SelectorOutput::Value),
-                selector_max(&DataType::Float64, SelectorOutput::Time),
                 "f64_value",
                 vec![
-                    "+----------------------------------------+---------------------------------------+",
-                    "| selector_max_value(t.f64_value,t.time) | selector_max_time(t.f64_value,t.time) |",
-                    "+----------------------------------------+---------------------------------------+",
-                    "| 5                                      | 1970-01-01 00:00:00.000005            |",
-                    "+----------------------------------------+---------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 5     | 1970-01-01 00:00:00.000005 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_max(&DataType::Int64, SelectorOutput::Value),
-                selector_max(&DataType::Int64, SelectorOutput::Time),
                 "i64_value",
                 vec![
-                    "+----------------------------------------+---------------------------------------+",
-                    "| selector_max_value(t.i64_value,t.time) | selector_max_time(t.i64_value,t.time) |",
-                    "+----------------------------------------+---------------------------------------+",
-                    "| 50                                     | 1970-01-01 00:00:00.000005            |",
-                    "+----------------------------------------+---------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 50    | 1970-01-01 00:00:00.000005 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_max(&DataType::UInt64, SelectorOutput::Value),
-                selector_max(&DataType::UInt64, SelectorOutput::Time),
                 "u64_value",
                 vec![
-                    "+----------------------------------------+---------------------------------------+",
-                    "| selector_max_value(t.u64_value,t.time) | selector_max_time(t.u64_value,t.time) |",
-                    "+----------------------------------------+---------------------------------------+",
-                    "| 50                                     | 1970-01-01 00:00:00.000005            |",
-                    "+----------------------------------------+---------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| 50    | 1970-01-01 00:00:00.000005 |",
+                    "+-------+----------------------------+",
                 ],
             ),
             (
-                selector_max(&DataType::Utf8, SelectorOutput::Value),
-                selector_max(&DataType::Utf8, SelectorOutput::Time),
                 "string_value",
                 vec![
-                    "+-------------------------------------------+------------------------------------------+",
-                    "| selector_max_value(t.string_value,t.time) | selector_max_time(t.string_value,t.time) |",
-                    "+-------------------------------------------+------------------------------------------+",
-                    "| z_five                                    | 1970-01-01 00:00:00.000005               |",
-                    "+-------------------------------------------+------------------------------------------+",
+                    "+--------+----------------------------+",
+                    "| value  | time                       |",
+                    "+--------+----------------------------+",
+                    "| z_five | 1970-01-01 00:00:00.000005 |",
+                    "+--------+----------------------------+",
                 ],
             ),
             (
-                selector_max(&DataType::Boolean, SelectorOutput::Value),
-                selector_max(&DataType::Boolean, SelectorOutput::Time),
                 "bool_value",
                 vec![
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| selector_max_value(t.bool_value,t.time) | selector_max_time(t.bool_value,t.time) |",
-                    "+-----------------------------------------+----------------------------------------+",
-                    "| true                                    | 1970-01-01 00:00:00.000001             |",
-                    "+-----------------------------------------+----------------------------------------+",
+                    "+-------+----------------------------+",
+                    "| value | time                       |",
+                    "+-------+----------------------------+",
+                    "| true  | 1970-01-01 00:00:00.000001 |",
+                    "+-------+----------------------------+",
                 ],
-            )
+            ),
         ];
 
-        for (val_func, time_func, val_column, expected) in cases.into_iter() {
-            let args = vec![col(val_column), col("time")];
-            let aggs = vec![val_func.call(args.clone()), time_func.call(args)];
-            let actual = run_plan(aggs).await;
-
-            assert_eq!(
-                expected, actual,
-                "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
-                expected, actual
-            );
+        for (val_column, expected) in cases.into_iter() {
+            run_fields_case(struct_selector_max(), val_column, expected).await;
         }
     }
 
@@ -1196,56 +1560,181 @@ mod test {
         .await;
     }
 
-    // Begin utility functions
+    // Begin pass through of other columns
 
-    /// Runs the expr using `run_plan` and compares the result to `expected`
-    async fn run_case(expr: Expr, expected: Vec<&'static str>) {
-        println!("Running case for {}", expr);
+    #[tokio::test]
+    async fn test_struct_selector_first_with_others() {
+        run_case(
+            struct_selector_first().call(vec![
+                col("f64_value"),
+                col("time"),
+                col("string_value"),
+                col("bool_value"),
+            ]),
+            vec![
+                "+-------------------------------------------------------------------------------------+",
+                "| selector_first(t.f64_value,t.time,t.string_value,t.bool_value)                      |",
+                "+-------------------------------------------------------------------------------------+",
+                "| {\"value\": 2, \"time\": 1970-01-01 00:00:00.000001, \"other_1\": \"two\", \"other_2\": true} |",
+                "+-------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_last_with_others() {
+        run_case(
+            struct_selector_last().call(vec![col("f64_value"), col("time"), col("u64_value")]),
+            vec![
+                "+-----------------------------------------------------------------+",
+                "| selector_last(t.f64_value,t.time,t.u64_value)                   |",
+                "+-----------------------------------------------------------------+",
+                "| {\"value\": 3, \"time\": 1970-01-01 00:00:00.000006, \"other_1\": 30} |",
+                "+-----------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
 
-        let actual = run_plan(vec![expr.clone()]).await;
+    #[tokio::test]
+    async fn test_struct_selector_min_with_others() {
+        run_case(
+            struct_selector_min().call(vec![col("i64_value"), col("time"), col("string_value")]),
+            vec![
+                "+-----------------------------------------------------------------------+",
+                "| selector_min(t.i64_value,t.time,t.string_value)                       |",
+                "+-----------------------------------------------------------------------+",
+                "| {\"value\": 10, \"time\": 1970-01-01 00:00:00.000004, \"other_1\": \"a_one\"} |",
+                "+-----------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
 
-        assert_eq!(
-            expected, actual,
-            "\n\nexpr: {}\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
-            expr, expected, actual
-        );
+    #[tokio::test]
+    async fn test_struct_selector_max_with_others() {
+        run_case(
+            struct_selector_max().call(vec![col("string_value"), col("time"), col("f64_value")]),
+            vec![
+                "+-----------------------------------------------------------------------+",
+                "| selector_max(t.string_value,t.time,t.f64_value)                       |",
+                "+-----------------------------------------------------------------------+",
+                "| {\"value\": \"z_five\", \"time\": 1970-01-01 00:00:00.000005, \"other_1\": 5} |",
+                "+-----------------------------------------------------------------------+",
+            ],
+        )
+        .await;
     }
 
-    /// Run a plan against the following input table as "t"
-    ///
-    /// ```text
-    /// +-----------+-----------+-----------+--------------+------------+----------------------------+,
-    /// | f64_value | i64_value | u64_value | string_value | bool_value | time                       |,
-    /// +-----------+-----------+--------------+------------+----------------------------+,
-    /// | 2         | 20        | 20        | two          | true       | 1970-01-01 00:00:00.000001 |,
-    /// | 4         | 40        | 40        | four         | false      | 1970-01-01 00:00:00.000002 |,
-    /// |           |           |           |              |            | 1970-01-01 00:00:00.000003 |,
-    /// | 1         | 10        | 10        | a_one        | true       | 1970-01-01 00:00:00.000004 |,
-    /// | 5         | 50        | 50        | z_five       | false      | 1970-01-01 00:00:00.000005 |,
-    /// | 3         | 30        | 30        | three        | false      | 1970-01-01 00:00:00.000006 |,
-    /// +-----------+-----------+--------------+------------+----------------------------+,
-    /// ```
-    async fn run_plan(aggs: Vec<Expr>) -> Vec<String> {
-        // define a schema for input
-        // (value) and timestamp
+    #[tokio::test]
+    async fn test_struct_selector_tie_break() {
+        let cases = vec![
+            (
+                SelectorTieBreak::SmallestValue,
+                vec![
+                    "+-------+------+",
+                    "| first | last |",
+                    "+-------+------+",
+                    "| 10    | 5    |",
+                    "+-------+------+",
+                ],
+            ),
+            (
+                SelectorTieBreak::LargestValue,
+                vec![
+                    "+-------+------+",
+                    "| first | last |",
+                    "+-------+------+",
+                    "| 20    | 30   |",
+                    "+-------+------+",
+                ],
+            ),
+        ];
+
+        // Rows 20 and 10 share the minimum time, rows 5 and 30 share
+        // the maximum time, and they are split across batches
         let schema = Arc::new(Schema::new(vec![
-            Field::new("f64_value", DataType::Float64, true),
             Field::new("i64_value", DataType::Int64, true),
-            Field::new("u64_value", DataType::UInt64, true),
-            Field::new("string_value", DataType::Utf8, true),
-            Field::new("bool_value", DataType::Boolean, true),
             Field::new("time", TIME_DATA_TYPE(), true),
         ]));
 
-        // define data in two partitions
         let batch1 = RecordBatch::try_new(
             Arc::clone(&schema),
             vec![
-                Arc::new(Float64Array::from(vec![Some(2.0), Some(4.0), None])),
-                Arc::new(Int64Array::from(vec![Some(20), Some(40), None])),
-                Arc::new(UInt64Array::from(vec![Some(20), Some(40), None])),
-                Arc::new(StringArray::from(vec![Some("two"), Some("four"), None])),
-                Arc::new(BooleanArray::from(vec![Some(true), Some(false), None])),
+                Arc::new(Int64Array::from(vec![Some(20), Some(5)])),
+                Arc::new(TimestampNanosecondArray::from_vec(
+                    vec![1000, 2000],
+                    TIME_DATA_TIMEZONE(),
+                )),
+            ],
+        )
+        .unwrap();
+
+        let batch2 = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Int64Array::from(vec![Some(10), Some(30)])),
+                Arc::new(TimestampNanosecondArray::from_vec(
+                    vec![1000, 2000],
+                    TIME_DATA_TIMEZONE(),
+                )),
+            ],
+        )
+        .unwrap();
+
+        for (tie_break, expected) in cases {
+            let args = vec![col("i64_value"), col("time")];
+            let aggs = vec![
+                struct_selector_first_with_tie_break(tie_break)
+                    .call(args.clone())
+                    .alias("first"),
+                struct_selector_last_with_tie_break(tie_break)
+                    .call(args)
+                    .alias("last"),
+            ];
+            let projection = vec![
+                selector_value(col("first")).alias("first"),
+                selector_value(col("last")).alias("last"),
+            ];
+
+            // the result is the same regardless of the order of inputs
+            for input in [
+                vec![batch1.clone(), batch2.clone()],
+                vec![batch2.clone(), batch1.clone()],
+            ] {
+                let actual = run_with_inputs(
+                    Arc::clone(&schema),
+                    aggs.clone(),
+                    Some(projection.clone()),
+                    input,
+                )
+                .await;
+
+                assert_eq!(
+                    expected, actual,
+                    "\n\n{:?}\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+                    tie_break, expected, actual
+                );
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_selector_widens_32_bit_values() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("f32_value", DataType::Float32, true),
+            Field::new("i32_value", DataType::Int32, true),
+            Field::new("u32_value", DataType::UInt32, true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Float32Array::from(vec![Some(1.5), Some(3.5), None])),
+                Arc::new(Int32Array::from(vec![Some(-5), Some(-10), None])),
+                Arc::new(UInt32Array::from(vec![Some(10), Some(30), None])),
                 Arc::new(TimestampNanosecondArray::from_vec(
                     vec![1000, 2000, 3000],
                     TIME_DATA_TIMEZONE(),
@@ -1254,98 +1743,278 @@ mod test {
         )
         .unwrap();
 
-        // No values in this batch
-        let batch2 = match RecordBatch::try_new(
+        let aggs = vec![
+            struct_selector_max()
+                .call(vec![col("f32_value"), col("time")])
+                .alias("f32"),
+            struct_selector_min()
+                .call(vec![col("i32_value"), col("time")])
+                .alias("i32"),
+            struct_selector_last()
+                .call(vec![col("u32_value"), col("time")])
+                .alias("u32"),
+        ];
+        let projection = vec![
+            selector_value(col("f32")).alias("f32"),
+            selector_value(col("i32")).alias("i32"),
+            selector_value(col("u32")).alias("u32"),
+            selector_time(col("u32")).alias("time"),
+        ];
+
+        let actual = run_with_inputs(schema, aggs, Some(projection), vec![batch]).await;
+
+        let expected = vec![
+            "+-----+-----+-----+----------------------------+",
+            "| f32 | i32 | u32 | time                       |",
+            "+-----+-----+-----+----------------------------+",
+            "| 3.5 | -10 | 30  | 1970-01-01 00:00:00.000002 |",
+            "+-----+-----+-----+----------------------------+",
+        ];
+
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
+    }
+
+    #[tokio::test]
+    async fn test_selector_decimal128() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("decimal_value", DataType::Decimal128(10, 2), true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+        ]));
+
+        let batch = RecordBatch::try_new(
             Arc::clone(&schema),
             vec![
-                Arc::new(Float64Array::from(vec![] as Vec<Option<f64>>)),
-                Arc::new(Int64Array::from(vec![] as Vec<Option<i64>>)),
-                Arc::new(UInt64Array::from(vec![] as Vec<Option<u64>>)),
-                Arc::new(StringArray::from(vec![] as Vec<Option<&str>>)),
-                Arc::new(BooleanArray::from(vec![] as Vec<Option<bool>>)),
+                Arc::new(
+                    vec![Some(350), Some(150), None, Some(350), Some(250)]
+                        .into_iter()
+                        .collect::<Decimal128Array>()
+                        .with_precision_and_scale(10, 2)
+                        .unwrap(),
+                ),
                 Arc::new(TimestampNanosecondArray::from_vec(
-                    vec![],
+                    vec![1000, 2000, 3000, 4000, 5000],
                     TIME_DATA_TIMEZONE(),
                 )),
             ],
-        ) {
-            Ok(a) => a,
-            _ => unreachable!(),
-        };
+        )
+        .unwrap();
+
+        let aggs = vec![
+            struct_selector_first()
+                .call(vec![col("decimal_value"), col("time")])
+                .alias("first"),
+            struct_selector_last()
+                .call(vec![col("decimal_value"), col("time")])
+                .alias("last"),
+            struct_selector_min()
+                .call(vec![col("decimal_value"), col("time")])
+                .alias("min"),
+            struct_selector_max()
+                .call(vec![col("decimal_value"), col("time")])
+                .alias("max"),
+        ];
+
+        let actual = run_with_inputs(schema, aggs, None, vec![batch]).await;
+
+        let expected = vec![
+            "+-----------------------------------------------------+-----------------------------------------------------+-----------------------------------------------------+-----------------------------------------------------+",
+            "| first                                               | last                                                | min                                                 | max                                                 |",
+            "+-----------------------------------------------------+-----------------------------------------------------+-----------------------------------------------------+-----------------------------------------------------+",
+            "| {\"value\": 3.50, \"time\": 1970-01-01 00:00:00.000001} | {\"value\": 2.50, \"time\": 1970-01-01 00:00:00.000005} | {\"value\": 1.50, \"time\": 1970-01-01 00:00:00.000002} | {\"value\": 3.50, \"time\": 1970-01-01 00:00:00.000001} |",
+            "+-----------------------------------------------------+-----------------------------------------------------+-----------------------------------------------------+-----------------------------------------------------+",
+        ];
 
-        let batch3 = RecordBatch::try_new(
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
+    }
+
+    #[tokio::test]
+    async fn test_selector_collation() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("string_value", DataType::Utf8, true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+        ]));
+
+        let batch = RecordBatch::try_new(
             Arc::clone(&schema),
             vec![
-                Arc::new(Float64Array::from(vec![Some(1.0), Some(5.0), Some(3.0)])),
-                Arc::new(Int64Array::from(vec![Some(10), Some(50), Some(30)])),
-                Arc::new(UInt64Array::from(vec![Some(10), Some(50), Some(30)])),
                 Arc::new(StringArray::from(vec![
-                    Some("a_one"),
-                    Some("z_five"),
-                    Some("three"),
-                ])),
-                Arc::new(BooleanArray::from(vec![
-                    Some(true),
-                    Some(false),
-                    Some(false),
+                    Some("b"),
+                    Some("A"),
+                    Some("a"),
+                    Some("Émile"),
+                    Some("Ernie"),
+                    Some("a"),
                 ])),
                 Arc::new(TimestampNanosecondArray::from_vec(
-                    vec![4000, 5000, 6000],
+                    vec![1000, 2000, 3000, 4000, 5000, 6000],
                     TIME_DATA_TIMEZONE(),
                 )),
             ],
         )
         .unwrap();
 
-        // Ensure the answer is the same regardless of the order of inputs
-        let input = vec![batch1, batch2, batch3];
-        let input_string = pretty_format_batches(&input).unwrap();
-        let results = run_with_inputs(Arc::clone(&schema), aggs.clone(), input.clone()).await;
-
-        use itertools::Itertools;
-        // Get all permutations of the input
-        for p in input.iter().permutations(3) {
-            let p_batches = p.into_iter().cloned().collect::<Vec<_>>();
-            let p_input_string = pretty_format_batches(&p_batches).unwrap();
-            let p_results = run_with_inputs(Arc::clone(&schema), aggs.clone(), p_batches).await;
-            assert_eq!(
-                results, p_results,
-                "Mismatch with permutation.\n\
-                        Input1 \n\n\
-                        {}\n\n\
-                        produces output:\n\n\
-                        {:#?}\n\n\
-                        Input 2\n\n\
-                        {}\n\n\
-                        produces output:\n\n\
-                        {:#?}\n\n",
-                input_string, results, p_input_string, p_results
-            );
-        }
+        let min = SelectorBuilder::min()
+            .with_collation(SelectorCollation::Locale)
+            .build();
+        let max = SelectorBuilder::max()
+            .with_collation(SelectorCollation::Locale)
+            .build();
+        assert_eq!(min.name, "selector_min_locale_collation");
+
+        let aggs = vec![
+            struct_selector_min()
+                .call(vec![col("string_value"), col("time")])
+                .alias("binary_min"),
+            struct_selector_max()
+                .call(vec![col("string_value"), col("time")])
+                .alias("binary_max"),
+            min.call(vec![col("string_value"), col("time")])
+                .alias("locale_min"),
+            max.call(vec![col("string_value"), col("time")])
+                .alias("locale_max"),
+        ];
+        let projection = vec![
+            selector_value(col("binary_min")).alias("binary_min"),
+            selector_value(col("binary_max")).alias("binary_max"),
+            selector_value(col("locale_min")).alias("locale_min"),
+            selector_time(col("locale_min")).alias("locale_min_time"),
+            selector_value(col("locale_max")).alias("locale_max"),
+        ];
+
+        let actual = run_with_inputs(schema, aggs, Some(projection), vec![batch]).await;
 
-        results
+        let expected = vec![
+            "+------------+------------+------------+----------------------------+------------+",
+            "| binary_min | binary_max | locale_min | locale_min_time            | locale_max |",
+            "+------------+------------+------------+----------------------------+------------+",
+            "| A          | Émile      | a          | 1970-01-01 00:00:00.000003 | Ernie      |",
+            "+------------+------------+------------+----------------------------+------------+",
+        ];
+
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
     }
 
-    async fn run_with_inputs(
-        schema: SchemaRef,
-        aggs: Vec<Expr>,
-        inputs: Vec<RecordBatch>,
-    ) -> Vec<String> {
-        let provider = MemTable::try_new(Arc::clone(&schema), vec![inputs]).unwrap();
-        let ctx = SessionContext::new();
-        ctx.register_table("t", Arc::new(provider)).unwrap();
+    #[tokio::test]
+    async fn test_selector_empty_behavior() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("f64_value", DataType::Float64, true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+        ]));
+
+        // no non null values to select from
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Float64Array::from(vec![None, None])),
+                Arc::new(TimestampNanosecondArray::from_vec(
+                    vec![1000, 2000],
+                    TIME_DATA_TIMEZONE(),
+                )),
+            ],
+        )
+        .unwrap();
 
-        let df = ctx.table("t").unwrap();
-        let df = df.aggregate(vec![], aggs).unwrap();
+        let null_fields = SelectorBuilder::first().build();
+        let null = SelectorBuilder::first()
+            .with_empty_behavior(SelectorEmptyBehavior::Null)
+            .build();
+        assert_eq!(null.name, "selector_first_null_on_empty");
+
+        let aggs = vec![
+            null_fields
+                .call(vec![col("f64_value"), col("time")])
+                .alias("null_fields"),
+            null.call(vec![col("f64_value"), col("time")]).alias("null"),
+        ];
+        let projection = vec![
+            col("null_fields").is_null().alias("null_fields_is_null"),
+            col("null").is_null().alias("null_is_null"),
+        ];
 
-        // execute the query
-        let record_batches = df.collect().await.unwrap();
+        let actual = run_with_inputs(
+            Arc::clone(&schema),
+            aggs,
+            Some(projection),
+            vec![batch.clone()],
+        )
+        .await;
 
-        pretty_format_batches(&record_batches)
+        let expected = vec![
+            "+---------------------+--------------+",
+            "| null_fields_is_null | null_is_null |",
+            "+---------------------+--------------+",
+            "| false               | true         |",
+            "+---------------------+--------------+",
+        ];
+
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
+
+        // the error behavior fails the query
+        let error = SelectorBuilder::last()
+            .with_empty_behavior(SelectorEmptyBehavior::Error)
+            .build();
+        assert_eq!(error.name, "selector_last_error_on_empty");
+
+        let provider = MemTable::try_new(schema, vec![vec![batch]]).unwrap();
+        let ctx = SessionContext::new();
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let result = ctx
+            .table("t")
+            .unwrap()
+            .aggregate(
+                vec![],
+                vec![error.call(vec![col("f64_value"), col("time")])],
+            )
             .unwrap()
-            .to_string()
-            .split('\n')
-            .map(|s| s.to_owned())
             .collect()
+            .await;
+
+        let err = result.unwrap_err().to_string();
+        assert!(
+            err.contains("Selector found no values to select from"),
+            "unexpected error: {}",
+            err
+        );
+    }
+
+    /// Runs `selector` on `val_column`, extracts the value and time
+    /// parts of the resulting struct and compares the result to `expected`
+    async fn run_fields_case(
+        selector: Arc<AggregateUDF>,
+        val_column: &str,
+        expected: Vec<&'static str>,
+    ) {
+        let agg = selector
+            .call(vec![col(val_column), col("time")])
+            .alias("selector");
+        let projection = vec![
+            selector_value(col("selector")).alias("value"),
+            selector_time(col("selector")).alias("time"),
+        ];
+
+        let actual = run_projected_plan(vec![agg], Some(projection)).await;
+
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
     }
 }
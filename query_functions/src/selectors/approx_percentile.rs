@@ -0,0 +1,435 @@
+//! Implementation of the `approx_percentile` function, which
+//! estimates a percentile of the values using a t-digest.
+//!
+//! Unlike `selector_median`, which keeps every value, the state of
+//! `approx_percentile` is a fixed size summary of the values that can
+//! be merged across partitions, so it is cheap to compute over large
+//! numbers of values.
+//!
+//! See "Computing Extremely Accurate Quantiles Using t-Digests" by Ted
+//! Dunning and Otmar Ertl (<https://arxiv.org/abs/1902.04023>).
+
+use std::{cmp::Ordering, f64::consts::PI, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, ListArray},
+    compute::cast,
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+
+use super::{ReturnTypeFunction, StateTypeFactory};
+
+/// The compression of the t-digest: roughly twice the number of
+/// centroids kept. Larger values are more accurate but use more memory
+const COMPRESSION: f64 = 100.0;
+
+/// The number of values to buffer before merging them into the
+/// centroids
+const BUFFER_SIZE: usize = 10 * COMPRESSION as usize;
+
+/// Create a User Defined Aggregate Function (UDAF) for
+/// approx_percentile(value, p)
+pub(super) fn make_approx_percentile_uda(name: &str) -> AggregateUDF {
+    let input_signature = Signature::one_of(
+        vec![
+            TypeSignature::Exact(vec![DataType::Float64, DataType::Float64]),
+            TypeSignature::Exact(vec![DataType::Int64, DataType::Float64]),
+            TypeSignature::Exact(vec![DataType::UInt64, DataType::Float64]),
+        ],
+        Volatility::Stable,
+    );
+
+    // The percentile is interpolated between values, so is always a
+    // float
+    let return_type_func: ReturnTypeFunction =
+        Arc::new(move |_arg_types| Ok(Arc::new(DataType::Float64)));
+
+    // The state is the means of the centroids, their weights and p
+    let state_type_factory: StateTypeFactory = Arc::new(move |_return_type| {
+        let list_type = DataType::List(Box::new(Field::new("item", DataType::Float64, true)));
+        Ok(Arc::new(vec![
+            list_type.clone(),
+            list_type,
+            DataType::Float64,
+        ]))
+    });
+
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |_return_type| {
+        let accumulator: Box<dyn Accumulator> = Box::new(ApproxPercentileAccumulator::default());
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// A cluster of values, summarised by their mean and number
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+/// A t-digest: a summary of a set of values as centroids, which are
+/// kept small near the extremes so that percentiles close to 0 and
+/// 1 are estimated accurately
+#[derive(Debug, Clone, Default)]
+struct TDigest {
+    /// Centroids ordered by mean
+    centroids: Vec<Centroid>,
+
+    /// Centroids not yet merged into `centroids`
+    unmerged: Vec<Centroid>,
+}
+
+impl TDigest {
+    /// Add `weight` values with the mean `mean`
+    fn add(&mut self, mean: f64, weight: f64) {
+        self.unmerged.push(Centroid { mean, weight });
+        if self.unmerged.len() >= BUFFER_SIZE {
+            self.compress();
+        }
+    }
+
+    /// Merge the unmerged centroids into the digest, combining
+    /// adjacent centroids while the change in [`scale`] across the
+    /// combined centroid is at most 1
+    fn compress(&mut self) {
+        if self.unmerged.is_empty() {
+            return;
+        }
+
+        let mut all = std::mem::take(&mut self.centroids);
+        all.append(&mut self.unmerged);
+        all.sort_by(|a, b| a.mean.partial_cmp(&b.mean).unwrap_or(Ordering::Equal));
+
+        let total: f64 = all.iter().map(|c| c.weight).sum();
+
+        let mut merged: Vec<Centroid> = Vec::with_capacity(COMPRESSION as usize);
+        // the weight of the centroids before the last merged centroid
+        let mut weight_before = 0.0;
+        let mut k_before = scale(0.0);
+        for centroid in all {
+            let last = match merged.last_mut() {
+                Some(last) => last,
+                None => {
+                    merged.push(centroid);
+                    continue;
+                }
+            };
+
+            let weight = last.weight + centroid.weight;
+            if scale((weight_before + weight) / total) - k_before <= 1.0 {
+                last.mean += (centroid.mean - last.mean) * centroid.weight / weight;
+                last.weight = weight;
+            } else {
+                weight_before += last.weight;
+                k_before = scale(weight_before / total);
+                merged.push(centroid);
+            }
+        }
+
+        self.centroids = merged;
+    }
+
+    /// Estimate the `p`th percentile (0 to 1) of the values, by
+    /// interpolating between the centers of the centroids. Returns
+    /// `None` if there are no values
+    ///
+    /// The digest must be compressed
+    fn percentile(&self, p: f64) -> Option<f64> {
+        let first = self.centroids.first()?;
+
+        let total: f64 = self.centroids.iter().map(|c| c.weight).sum();
+        let target = p * total;
+
+        // the cumulative weight at the center of the previous centroid
+        let mut prev_center = first.weight / 2.0;
+        if target <= prev_center {
+            return Some(first.mean);
+        }
+
+        let mut weight_before = first.weight;
+        for (prev, centroid) in self.centroids.iter().zip(&self.centroids[1..]) {
+            let center = weight_before + centroid.weight / 2.0;
+            if target <= center {
+                let fraction = (target - prev_center) / (center - prev_center);
+                return Some(prev.mean + fraction * (centroid.mean - prev.mean));
+            }
+            prev_center = center;
+            weight_before += centroid.weight;
+        }
+
+        self.centroids.last().map(|c| c.mean)
+    }
+}
+
+/// The t-digest scale function `k1`, which maps the percentile `q` to
+/// an index that changes quickly near 0 and 1, so centroids near the
+/// extremes hold few values. It ranges from `-COMPRESSION / 4` to
+/// `COMPRESSION / 4`, so a digest has at most about `COMPRESSION / 2`
+/// centroids
+fn scale(q: f64) -> f64 {
+    COMPRESSION / (2.0 * PI) * (2.0 * q - 1.0).clamp(-1.0, 1.0).asin()
+}
+
+/// Accumulator that summarises the values with a [`TDigest`]
+#[derive(Debug, Default)]
+struct ApproxPercentileAccumulator {
+    /// The percentile to estimate. Not known until the first batch
+    /// is seen, as it is passed as an argument
+    p: Option<f64>,
+
+    digest: TDigest,
+}
+
+impl ApproxPercentileAccumulator {
+    /// Record the percentile to estimate from the first non null
+    /// value in `p_arr`
+    fn update_p(&mut self, p_arr: &ArrayRef) -> DataFusionResult<()> {
+        if self.p.is_some() {
+            return Ok(());
+        }
+
+        let p_arr = p_arr
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Second argument was p");
+
+        if let Some(p) = p_arr.iter().flatten().next() {
+            if !(0.0..=1.0).contains(&p) {
+                return Err(DataFusionError::Execution(format!(
+                    "approx_percentile expected a percentile between 0 and 1, got {}",
+                    p
+                )));
+            }
+            self.p = Some(p);
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for ApproxPercentileAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let mut digest = self.digest.clone();
+        digest.compress();
+
+        let (means, weights) = digest
+            .centroids
+            .iter()
+            .map(|c| {
+                (
+                    ScalarValue::Float64(Some(c.mean)),
+                    ScalarValue::Float64(Some(c.weight)),
+                )
+            })
+            .unzip();
+
+        Ok(vec![
+            AggregateState::Scalar(ScalarValue::new_list(Some(means), DataType::Float64)),
+            AggregateState::Scalar(ScalarValue::new_list(Some(weights), DataType::Float64)),
+            AggregateState::Scalar(ScalarValue::Float64(self.p)),
+        ])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let p = match self.p {
+            Some(p) => p,
+            None => return Ok(ScalarValue::Float64(None)),
+        };
+
+        let mut digest = self.digest.clone();
+        digest.compress();
+
+        Ok(ScalarValue::Float64(digest.percentile(p)))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if values.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 arguments passed to approx_percentile function but got {}",
+                values.len()
+            )));
+        }
+
+        self.update_p(&values[1])?;
+
+        let value_arr = cast(&values[0], &DataType::Float64)?;
+        let value_arr = value_arr
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .expect("cast to f64");
+
+        for value in value_arr.iter().flatten() {
+            self.digest.add(value, 1.0);
+        }
+        Ok(())
+    }
+
+    // Each row of the states is a list of centroid means and a list
+    // of their weights previously produced by `state`, and p
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        if states.len() != 3 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 3 states passed to approx_percentile function but got {}",
+                states.len()
+            )));
+        }
+
+        self.update_p(&states[2])?;
+
+        let mean_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("First state was a list of means");
+
+        let weight_lists = states[1]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("Second state was a list of weights");
+
+        for idx in 0..mean_lists.len() {
+            if mean_lists.is_null(idx) || weight_lists.is_null(idx) {
+                continue;
+            }
+
+            let means = mean_lists.value(idx);
+            let means = means
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .expect("means were f64");
+
+            let weights = weight_lists.value(idx);
+            let weights = weights
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .expect("weights were f64");
+
+            for (mean, weight) in means.iter().zip(weights.iter()) {
+                if let (Some(mean), Some(weight)) = (mean, weight) {
+                    self.digest.add(mean, weight);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use super::*;
+    use crate::{selectors::approx_percentile, test_util::run_case};
+
+    #[test]
+    fn test_tdigest_empty() {
+        let mut digest = TDigest::default();
+        digest.compress();
+        assert_eq!(digest.percentile(0.5), None);
+    }
+
+    #[test]
+    fn test_tdigest_exact_for_few_values() {
+        let mut digest = TDigest::default();
+        for value in [2.0, 4.0, 1.0, 5.0, 3.0] {
+            digest.add(value, 1.0);
+        }
+        digest.compress();
+
+        assert_eq!(digest.centroids.len(), 5);
+        assert_eq!(digest.percentile(0.0), Some(1.0));
+        assert_eq!(digest.percentile(0.5), Some(3.0));
+        assert_eq!(digest.percentile(0.25), Some(1.75));
+        assert_eq!(digest.percentile(1.0), Some(5.0));
+    }
+
+    #[test]
+    fn test_tdigest_accuracy() {
+        // add 0..100_000 in an order that is not sorted, split
+        // across digests that are then merged
+        let mut digests = vec![TDigest::default(); 4];
+        for i in 0..100_000u64 {
+            let value = ((i * 7919) % 100_000) as f64;
+            digests[(i % 4) as usize].add(value, 1.0);
+        }
+
+        let mut digest = TDigest::default();
+        for mut partial in digests {
+            partial.compress();
+            for c in partial.centroids {
+                digest.add(c.mean, c.weight);
+            }
+        }
+        digest.compress();
+
+        assert!(digest.centroids.len() <= COMPRESSION as usize);
+
+        for (p, tolerance) in [(0.01, 100.0), (0.5, 500.0), (0.99, 100.0), (0.999, 100.0)] {
+            let expected = p * 100_000.0;
+            let actual = digest.percentile(p).unwrap();
+            assert!(
+                (actual - expected).abs() <= tolerance,
+                "p={} expected {} got {}",
+                p,
+                expected,
+                actual
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approx_percentile_f64() {
+        run_case(
+            approx_percentile()
+                .call(vec![col("f64_value"), lit(0.5)])
+                .alias("p"),
+            vec!["+---+", "| p |", "+---+", "| 3 |", "+---+"],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_approx_percentile_i64() {
+        run_case(
+            approx_percentile()
+                .call(vec![col("i64_value"), lit(0.25)])
+                .alias("p"),
+            vec!["+------+", "| p    |", "+------+", "| 17.5 |", "+------+"],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_approx_percentile_u64() {
+        run_case(
+            approx_percentile()
+                .call(vec![col("u64_value"), lit(1.0)])
+                .alias("p"),
+            vec!["+----+", "| p  |", "+----+", "| 50 |", "+----+"],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,475 @@
+//! Implementation of the `selector_distinct` function, which returns
+//! the distinct values, and the `selector_count_distinct` function,
+//! which estimates the number of distinct values using HyperLogLog.
+
+use std::{cmp::Ordering, collections::HashSet, hash::Hasher, mem::size_of, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, BinaryArray, ListArray},
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+use siphasher::sip::SipHasher13;
+
+use super::{
+    memory::{scalar_heap_size, AccumulatorMemory, MemoryReservation},
+    ReturnTypeFunction, StateTypeFactory,
+};
+
+/// The number of bits of the hash used to choose the HyperLogLog
+/// register, giving a standard error of about 1.6%
+const HLL_PRECISION: u32 = 12;
+
+/// The number of HyperLogLog registers
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// A randomly generated static siphash key, so that every process
+/// hashes the same value to the same register and the sketches they
+/// produce can be merged.
+///
+/// Generated with: xxd -i -l 16 /dev/urandom
+const HLL_HASH_KEY: [u8; 16] = [
+    0xe9, 0xa3, 0x03, 0xe5, 0x92, 0x97, 0x16, 0x8d, 0x7c, 0x1b, 0xfc, 0xb1, 0xae, 0x74, 0xbf, 0x19,
+];
+
+/// The types of values accepted by the distinct functions
+fn input_signature() -> Signature {
+    Signature::one_of(
+        vec![TypeSignature::Uniform(
+            1,
+            vec![
+                DataType::Float64,
+                DataType::Int64,
+                DataType::UInt64,
+                DataType::Utf8,
+                DataType::Boolean,
+            ],
+        )],
+        Volatility::Stable,
+    )
+}
+
+/// Create a User Defined Aggregate Function (UDAF) for distinct(value),
+/// whose accumulators track their memory with `memory`
+pub(super) fn make_distinct_uda(name: &str, memory: &Arc<AccumulatorMemory>) -> AggregateUDF {
+    // The output is a list of the input values
+    let return_type_func: ReturnTypeFunction =
+        Arc::new(move |arg_types| Ok(Arc::new(make_list_type(arg_types[0].clone()))));
+
+    // The state is the distinct values seen
+    let state_type_factory: StateTypeFactory =
+        Arc::new(move |return_type| Ok(Arc::new(vec![return_type.clone()])));
+
+    let memory = Arc::clone(memory);
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |return_type| {
+        let value_type = match return_type {
+            DataType::List(field) => field.data_type().clone(),
+            t => {
+                return Err(DataFusionError::Internal(format!(
+                    "Unexpected return type for distinct: {:?}",
+                    t
+                )))
+            }
+        };
+        let accumulator: Box<dyn Accumulator> =
+            Box::new(DistinctAccumulator::new(value_type, &memory));
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature(),
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// Create a User Defined Aggregate Function (UDAF) for
+/// count_distinct(value)
+pub(super) fn make_count_distinct_uda(name: &str) -> AggregateUDF {
+    let return_type_func: ReturnTypeFunction = Arc::new(move |_| Ok(Arc::new(DataType::UInt64)));
+
+    // The state is the HyperLogLog registers
+    let state_type_factory: StateTypeFactory =
+        Arc::new(move |_| Ok(Arc::new(vec![DataType::Binary])));
+
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |_| {
+        let accumulator: Box<dyn Accumulator> = Box::new(CountDistinctAccumulator::new());
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature(),
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// A list of `item_type`, as produced by [`ScalarValue::new_list`]
+fn make_list_type(item_type: DataType) -> DataType {
+    DataType::List(Box::new(Field::new("item", item_type, true)))
+}
+
+/// Check that exactly one input or state column was passed to `name`
+fn check_single_arg(name: &str, kind: &str, values: &[ArrayRef]) -> DataFusionResult<()> {
+    if values.len() != 1 {
+        return Err(DataFusionError::Internal(format!(
+            "Internal error: Expected 1 {} passed to {} function but got {}",
+            kind,
+            name,
+            values.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Accumulator that collects the distinct values
+#[derive(Debug)]
+struct DistinctAccumulator {
+    value_type: DataType,
+
+    values: HashSet<ScalarValue>,
+
+    /// The memory allocated by `values` outside of `values` itself
+    values_heap_size: usize,
+
+    /// The memory reserved for `values`
+    reservation: MemoryReservation,
+}
+
+impl DistinctAccumulator {
+    fn new(value_type: DataType, memory: &Arc<AccumulatorMemory>) -> Self {
+        Self {
+            value_type,
+            values: HashSet::new(),
+            values_heap_size: 0,
+            reservation: MemoryReservation::new(memory),
+        }
+    }
+
+    /// The memory used by the accumulator, in bytes
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.values.capacity() * size_of::<ScalarValue>()
+            + self.values_heap_size
+    }
+
+    /// Add each non null value in `value_arr`
+    fn add_values(&mut self, value_arr: &ArrayRef) -> DataFusionResult<()> {
+        for idx in 0..value_arr.len() {
+            if value_arr.is_valid(idx) {
+                let value = ScalarValue::try_from_array(value_arr, idx)?;
+                let heap_size = scalar_heap_size(&value);
+                if self.values.insert(value) {
+                    self.values_heap_size += heap_size;
+                }
+            }
+        }
+
+        let size = self.size();
+        self.reservation.try_resize("distinct", size)
+    }
+}
+
+impl Accumulator for DistinctAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        Ok(vec![AggregateState::Scalar(self.evaluate()?)])
+    }
+
+    // Returns the distinct values in ascending order, so the result
+    // does not depend on the order of the input
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let mut values: Vec<_> = self.values.iter().cloned().collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        Ok(ScalarValue::new_list(Some(values), self.value_type.clone()))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        check_single_arg("distinct", "argument", values)?;
+
+        self.add_values(&values[0])
+    }
+
+    // Each row of the state is a list of distinct values, previously
+    // produced by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        check_single_arg("distinct", "state", states)?;
+
+        let value_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("state was a list of values");
+
+        for idx in 0..value_lists.len() {
+            if value_lists.is_valid(idx) {
+                self.add_values(&value_lists.value(idx))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Accumulator that estimates the number of distinct values with a
+/// HyperLogLog sketch
+#[derive(Debug)]
+struct CountDistinctAccumulator {
+    /// The largest number of leading zeros (plus one) seen in the
+    /// hashes of the values assigned to each register
+    registers: Vec<u8>,
+}
+
+impl CountDistinctAccumulator {
+    fn new() -> Self {
+        Self {
+            registers: vec![0; HLL_REGISTERS],
+        }
+    }
+
+    /// Add the hash of `value` to the sketch.
+    ///
+    /// The hash is computed over the bytes of the value with a fixed
+    /// key, rather than with the [`std::hash::Hash`] implementation of
+    /// [`ScalarValue`], which is not guaranteed to be stable across
+    /// versions of Rust or DataFusion.
+    fn add(&mut self, value: &ScalarValue) -> DataFusionResult<()> {
+        let mut hasher = SipHasher13::new_with_key(&HLL_HASH_KEY);
+        match value {
+            ScalarValue::Float64(Some(v)) => hasher.write(&v.to_bits().to_le_bytes()),
+            ScalarValue::Int64(Some(v)) => hasher.write(&v.to_le_bytes()),
+            ScalarValue::UInt64(Some(v)) => hasher.write(&v.to_le_bytes()),
+            ScalarValue::Utf8(Some(v)) => hasher.write(v.as_bytes()),
+            ScalarValue::Boolean(Some(v)) => hasher.write(&[u8::from(*v)]),
+            v => {
+                return Err(DataFusionError::Internal(format!(
+                    "Internal error: Unexpected value for count_distinct: {:?}",
+                    v
+                )))
+            }
+        }
+        let hash = hasher.finish();
+
+        // the first bits choose the register, and the remaining bits
+        // are used to count the leading zeros
+        let idx = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+
+        self.registers[idx] = self.registers[idx].max(rank as u8);
+        Ok(())
+    }
+
+    /// Combine the sketch with `registers` from another sketch
+    fn merge_registers(&mut self, registers: &[u8]) -> DataFusionResult<()> {
+        if registers.len() != HLL_REGISTERS {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected {} registers for count_distinct but got {}",
+                HLL_REGISTERS,
+                registers.len()
+            )));
+        }
+
+        for (register, other) in self.registers.iter_mut().zip(registers) {
+            *register = (*register).max(*other);
+        }
+        Ok(())
+    }
+
+    /// The estimated number of distinct values added to the sketch
+    fn estimate(&self) -> u64 {
+        let m = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+
+        let sum: f64 = self.registers.iter().map(|r| 2f64.powi(-(*r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+
+        // use linear counting for small cardinalities, where the
+        // HyperLogLog estimate is biased
+        let zeros = self.registers.iter().filter(|r| **r == 0).count();
+        let estimate = if estimate <= 2.5 * m && zeros > 0 {
+            m * (m / zeros as f64).ln()
+        } else {
+            estimate
+        };
+
+        estimate.round() as u64
+    }
+}
+
+impl Accumulator for CountDistinctAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        Ok(vec![AggregateState::Scalar(ScalarValue::Binary(Some(
+            self.registers.clone(),
+        )))])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        Ok(ScalarValue::UInt64(Some(self.estimate())))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+        check_single_arg("count_distinct", "argument", values)?;
+
+        let value_arr = &values[0];
+        for idx in 0..value_arr.len() {
+            if value_arr.is_valid(idx) {
+                self.add(&ScalarValue::try_from_array(value_arr, idx)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Each row of the state is the registers of a sketch, previously
+    // produced by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+        check_single_arg("count_distinct", "state", states)?;
+
+        let registers = states[0]
+            .as_any()
+            .downcast_ref::<BinaryArray>()
+            .expect("state was binary registers");
+
+        for idx in 0..registers.len() {
+            if registers.is_valid(idx) {
+                self.merge_registers(registers.value(idx))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        selectors::{selector_count_distinct, selector_distinct},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_selector_distinct_f64() {
+        run_case(
+            selector_distinct(&unbounded_memory()).call(vec![col("f64_value")]),
+            vec![
+                "+--------------------------------+",
+                "| selector_distinct(t.f64_value) |",
+                "+--------------------------------+",
+                "| [1, 2, 3, 4, 5]                |",
+                "+--------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_distinct_i64() {
+        run_case(
+            selector_distinct(&unbounded_memory()).call(vec![col("i64_value")]),
+            vec![
+                "+--------------------------------+",
+                "| selector_distinct(t.i64_value) |",
+                "+--------------------------------+",
+                "| [10, 20, 30, 40, 50]           |",
+                "+--------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_distinct_string() {
+        run_case(
+            selector_distinct(&unbounded_memory()).call(vec![col("string_value")]),
+            vec![
+                "+-----------------------------------+",
+                "| selector_distinct(t.string_value) |",
+                "+-----------------------------------+",
+                "| [a_one, four, three, two, z_five] |",
+                "+-----------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_distinct_bool() {
+        run_case(
+            selector_distinct(&unbounded_memory()).call(vec![col("bool_value")]),
+            vec![
+                "+---------------------------------+",
+                "| selector_distinct(t.bool_value) |",
+                "+---------------------------------+",
+                "| [false, true]                   |",
+                "+---------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_count_distinct_f64() {
+        run_case(
+            selector_count_distinct().call(vec![col("f64_value")]),
+            vec![
+                "+--------------------------------------+",
+                "| selector_count_distinct(t.f64_value) |",
+                "+--------------------------------------+",
+                "| 5                                    |",
+                "+--------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_count_distinct_u64() {
+        run_case(
+            selector_count_distinct().call(vec![col("u64_value")]),
+            vec![
+                "+--------------------------------------+",
+                "| selector_count_distinct(t.u64_value) |",
+                "+--------------------------------------+",
+                "| 5                                    |",
+                "+--------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_count_distinct_bool() {
+        run_case(
+            selector_count_distinct().call(vec![col("bool_value")]),
+            vec![
+                "+---------------------------------------+",
+                "| selector_count_distinct(t.bool_value) |",
+                "+---------------------------------------+",
+                "| 2                                     |",
+                "+---------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
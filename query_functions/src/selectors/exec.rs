@@ -0,0 +1,517 @@
+//! Implementation of [`SortedSelectorExec`], a physical operator that
+//! computes the first or last value of columns for each group of its
+//! input in a single streaming pass.
+//!
+//! The generic hash aggregate hashes the group columns of every row,
+//! and keeps the state of every group until its input is exhausted.
+//! When the input is sorted by the group columns and then time, as is
+//! often the case for GROUP BY time queries against data sorted by
+//! the primary key, each group is a contiguous run of rows so the
+//! groups can be found by comparing adjacent rows and each group
+//! emitted as soon as the next one starts.
+
+use std::{
+    any::Any,
+    fmt::{self, Debug},
+    ops::Range,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use arrow::{
+    array::{Array, ArrayRef, TimestampNanosecondArray},
+    compute::{lexicographical_partition_ranges, SortColumn},
+    datatypes::{Field, Schema, SchemaRef},
+    error::Result as ArrowResult,
+    record_batch::RecordBatch,
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::context::TaskContext,
+    physical_plan::{
+        expressions::PhysicalSortExpr,
+        metrics::{BaselineMetrics, ExecutionPlanMetricsSet, MetricsSet},
+        DisplayFormatType, Distribution, ExecutionPlan, Partitioning, RecordBatchStream,
+        SendableRecordBatchStream, Statistics,
+    },
+    scalar::ScalarValue,
+};
+use futures::{ready, Stream, StreamExt};
+use schema::TIME_DATA_TYPE;
+
+/// Which value of each group is selected by [`SortedSelectorExec`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortedSelectorType {
+    /// The first non null value, as for `selector_first`
+    First,
+    /// The last non null value, as for `selector_last`
+    Last,
+}
+
+/// Physical operator that computes the first or last non null value
+/// (and its time) of each of the value columns, for each group of
+/// rows with the same values in the group columns.
+///
+/// The input MUST be sorted by the group columns and then by time,
+/// and is read as a single partition.
+///
+/// The output has the group columns, followed by two columns for each
+/// value column `<name>`: the selected value `<name>` and the time of
+/// the selected value `<name>_time`. Groups are output in input order.
+pub struct SortedSelectorExec {
+    input: Arc<dyn ExecutionPlan>,
+    selector_type: SortedSelectorType,
+
+    /// Indexes of the group columns in the input
+    group_columns: Vec<usize>,
+    /// Index of the time column in the input
+    time_column: usize,
+    /// Indexes of the value columns in the input
+    value_columns: Vec<usize>,
+
+    schema: SchemaRef,
+    metrics: ExecutionPlanMetricsSet,
+}
+
+impl SortedSelectorExec {
+    /// Create a new operator selecting from `value_columns` for each
+    /// group of `group_columns` of `input`
+    pub fn try_new(
+        input: Arc<dyn ExecutionPlan>,
+        selector_type: SortedSelectorType,
+        group_columns: &[&str],
+        time_column: &str,
+        value_columns: &[&str],
+    ) -> DataFusionResult<Self> {
+        let input_schema = input.schema();
+
+        let group_columns = group_columns
+            .iter()
+            .map(|name| input_schema.index_of(name))
+            .collect::<ArrowResult<Vec<_>>>()?;
+        let time_column = input_schema.index_of(time_column)?;
+        let value_columns = value_columns
+            .iter()
+            .map(|name| input_schema.index_of(name))
+            .collect::<ArrowResult<Vec<_>>>()?;
+
+        let time_field = input_schema.field(time_column);
+        if time_field.data_type() != &TIME_DATA_TYPE() {
+            return Err(DataFusionError::Plan(format!(
+                "SortedSelectorExec expected time column '{}' to be {:?}, got {:?}",
+                time_field.name(),
+                TIME_DATA_TYPE(),
+                time_field.data_type()
+            )));
+        }
+
+        let group_fields = group_columns
+            .iter()
+            .map(|&idx| input_schema.field(idx).clone());
+        let value_fields = value_columns.iter().flat_map(|&idx| {
+            let field = input_schema.field(idx);
+            [
+                Field::new(field.name(), field.data_type().clone(), true),
+                Field::new(&format!("{}_time", field.name()), TIME_DATA_TYPE(), true),
+            ]
+        });
+        let schema = Arc::new(Schema::new(group_fields.chain(value_fields).collect()));
+
+        Ok(Self {
+            input,
+            selector_type,
+            group_columns,
+            time_column,
+            value_columns,
+            schema,
+            metrics: ExecutionPlanMetricsSet::new(),
+        })
+    }
+}
+
+impl Debug for SortedSelectorExec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SortedSelectorExec")
+    }
+}
+
+impl ExecutionPlan for SortedSelectorExec {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+
+    fn output_partitioning(&self) -> Partitioning {
+        Partitioning::UnknownPartitioning(1)
+    }
+
+    fn output_ordering(&self) -> Option<&[PhysicalSortExpr]> {
+        None
+    }
+
+    // groups may span input partitions, so they must be merged
+    fn required_child_distribution(&self) -> Distribution {
+        Distribution::SinglePartition
+    }
+
+    fn children(&self) -> Vec<Arc<dyn ExecutionPlan>> {
+        vec![Arc::clone(&self.input)]
+    }
+
+    fn with_new_children(
+        self: Arc<Self>,
+        children: Vec<Arc<dyn ExecutionPlan>>,
+    ) -> DataFusionResult<Arc<dyn ExecutionPlan>> {
+        match children.len() {
+            1 => Ok(Arc::new(Self {
+                input: Arc::clone(&children[0]),
+                selector_type: self.selector_type,
+                group_columns: self.group_columns.clone(),
+                time_column: self.time_column,
+                value_columns: self.value_columns.clone(),
+                schema: Arc::clone(&self.schema),
+                metrics: ExecutionPlanMetricsSet::new(),
+            })),
+            _ => Err(DataFusionError::Internal(
+                "SortedSelectorExec wrong number of children".to_string(),
+            )),
+        }
+    }
+
+    fn execute(
+        &self,
+        partition: usize,
+        context: Arc<TaskContext>,
+    ) -> DataFusionResult<SendableRecordBatchStream> {
+        if partition != 0 {
+            return Err(DataFusionError::Internal(format!(
+                "SortedSelectorExec invalid partition {}",
+                partition
+            )));
+        }
+
+        let input = self.input.execute(partition, context)?;
+
+        Ok(Box::pin(SortedSelectorStream {
+            input,
+            selector_type: self.selector_type,
+            group_columns: self.group_columns.clone(),
+            time_column: self.time_column,
+            value_columns: self.value_columns.clone(),
+            schema: Arc::clone(&self.schema),
+            current: None,
+            done: false,
+            baseline_metrics: BaselineMetrics::new(&self.metrics, partition),
+        }))
+    }
+
+    fn fmt_as(&self, t: DisplayFormatType, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match t {
+            DisplayFormatType::Default => {
+                write!(f, "SortedSelectorExec: selector={:?}", self.selector_type)
+            }
+        }
+    }
+
+    fn metrics(&self) -> Option<MetricsSet> {
+        Some(self.metrics.clone_inner())
+    }
+
+    fn statistics(&self) -> Statistics {
+        Statistics::default()
+    }
+}
+
+/// The values selected so far for a group
+#[derive(Debug)]
+struct Group {
+    /// The values of the group columns
+    key: Vec<ScalarValue>,
+    /// The selected value and its time, for each value column
+    selected: Vec<Option<(ScalarValue, i64)>>,
+}
+
+struct SortedSelectorStream {
+    input: SendableRecordBatchStream,
+    selector_type: SortedSelectorType,
+    group_columns: Vec<usize>,
+    time_column: usize,
+    value_columns: Vec<usize>,
+    schema: SchemaRef,
+
+    /// The group being read, which may continue in the next batch
+    current: Option<Group>,
+    /// True once the input has been exhausted
+    done: bool,
+
+    baseline_metrics: BaselineMetrics,
+}
+
+impl SortedSelectorStream {
+    /// Update the groups with the rows of `batch`, returning the
+    /// groups that were completed, if any
+    fn process(&mut self, batch: &RecordBatch) -> DataFusionResult<Option<RecordBatch>> {
+        let group_arrs: Vec<_> = self
+            .group_columns
+            .iter()
+            .map(|&idx| Arc::clone(batch.column(idx)))
+            .collect();
+
+        let time_arr = batch.column(self.time_column);
+        let time_arr = time_arr
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            .ok_or_else(|| {
+                DataFusionError::Internal("SortedSelectorExec time column was not time".into())
+            })?;
+
+        let ranges: Vec<Range<usize>> = if group_arrs.is_empty() {
+            vec![0..batch.num_rows()]
+        } else {
+            let sort_columns: Vec<_> = group_arrs
+                .iter()
+                .map(|values| SortColumn {
+                    values: Arc::clone(values),
+                    options: None,
+                })
+                .collect();
+            lexicographical_partition_ranges(&sort_columns)?.collect()
+        };
+
+        let mut completed = vec![];
+        for range in ranges {
+            if range.is_empty() {
+                continue;
+            }
+
+            let key = group_arrs
+                .iter()
+                .map(|arr| ScalarValue::try_from_array(arr, range.start))
+                .collect::<DataFusionResult<Vec<_>>>()?;
+
+            let is_new_group = self.current.as_ref().map_or(true, |group| group.key != key);
+            if is_new_group {
+                completed.extend(self.current.take());
+                self.current = Some(Group {
+                    key,
+                    selected: vec![None; self.value_columns.len()],
+                });
+            }
+
+            let group = self.current.as_mut().expect("current group");
+            for (selected, &idx) in group.selected.iter_mut().zip(&self.value_columns) {
+                let value_arr = batch.column(idx);
+                let valid = |&row: &usize| value_arr.is_valid(row) && time_arr.is_valid(row);
+
+                // the rows are sorted by time so the first (last)
+                // value is the first (last) valid row
+                let row = match self.selector_type {
+                    SortedSelectorType::First if selected.is_none() => range.clone().find(valid),
+                    SortedSelectorType::First => None,
+                    SortedSelectorType::Last => range.clone().rev().find(valid),
+                };
+
+                if let Some(row) = row {
+                    *selected = Some((
+                        ScalarValue::try_from_array(value_arr, row)?,
+                        time_arr.value(row),
+                    ));
+                }
+            }
+        }
+
+        self.make_batch(completed)
+    }
+
+    /// Create the output for the `groups`, if any
+    fn make_batch(&self, groups: Vec<Group>) -> DataFusionResult<Option<RecordBatch>> {
+        if groups.is_empty() {
+            return Ok(None);
+        }
+
+        let mut columns: Vec<ArrayRef> = vec![];
+        for idx in 0..self.group_columns.len() {
+            let keys = groups.iter().map(|group| group.key[idx].clone());
+            columns.push(ScalarValue::iter_to_array(keys)?);
+        }
+
+        for idx in 0..self.value_columns.len() {
+            let value_type = self
+                .schema
+                .field(self.group_columns.len() + 2 * idx)
+                .data_type();
+            let values = groups
+                .iter()
+                .map(|group| match &group.selected[idx] {
+                    Some((value, _)) => Ok(value.clone()),
+                    None => ScalarValue::try_from(value_type),
+                })
+                .collect::<DataFusionResult<Vec<_>>>()?;
+            columns.push(ScalarValue::iter_to_array(values)?);
+
+            let times: TimestampNanosecondArray = groups
+                .iter()
+                .map(|group| group.selected[idx].as_ref().map(|(_, time)| *time))
+                .collect();
+            columns.push(Arc::new(times));
+        }
+
+        Ok(Some(RecordBatch::try_new(
+            Arc::clone(&self.schema),
+            columns,
+        )?))
+    }
+}
+
+impl Stream for SortedSelectorStream {
+    type Item = ArrowResult<RecordBatch>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let output = match ready!(self.input.poll_next_unpin(cx)) {
+                Some(Ok(batch)) => {
+                    let elapsed_compute = self.baseline_metrics.elapsed_compute().clone();
+                    let _timer = elapsed_compute.timer();
+                    self.process(&batch)
+                }
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => {
+                    // the input is exhausted, so the last group is complete
+                    self.done = true;
+                    let last = self.current.take();
+                    self.make_batch(last.into_iter().collect())
+                }
+            };
+
+            match output {
+                Ok(Some(batch)) => {
+                    self.baseline_metrics.record_output(batch.num_rows());
+                    return Poll::Ready(Some(Ok(batch)));
+                }
+                // no groups completed, so read more input
+                Ok(None) => continue,
+                Err(e) => return Poll::Ready(Some(Err(e.into()))),
+            }
+        }
+    }
+}
+
+impl RecordBatchStream for SortedSelectorStream {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use arrow::{
+        array::{Float64Array, StringArray},
+        util::pretty::pretty_format_batches,
+    };
+    use datafusion::{
+        physical_plan::{collect, memory::MemoryExec},
+        prelude::SessionContext,
+    };
+    use schema::TIME_DATA_TIMEZONE;
+
+    use super::*;
+
+    /// Run a [`SortedSelectorExec`] grouping by `tag` over the
+    /// following input, split into two batches:
+    ///
+    /// ```text
+    /// +-----+---+----------------------------+
+    /// | tag | v | time                       |
+    /// +-----+---+----------------------------+
+    /// | a   |   | 1970-01-01 00:00:00.000001 |
+    /// | a   | 1 | 1970-01-01 00:00:00.000002 |
+    /// | b   | 2 | 1970-01-01 00:00:00.000003 |
+    /// +-----+---+----------------------------+
+    /// | b   | 3 | 1970-01-01 00:00:00.000004 |
+    /// | b   |   | 1970-01-01 00:00:00.000005 |
+    /// | c   | 4 | 1970-01-01 00:00:00.000006 |
+    /// +-----+---+----------------------------+
+    /// ```
+    async fn run(selector_type: SortedSelectorType) -> Vec<String> {
+        let make_batch = |tags: Vec<&str>, values: Vec<Option<f64>>, times: Vec<i64>| {
+            RecordBatch::try_from_iter(vec![
+                ("tag", Arc::new(StringArray::from(tags)) as ArrayRef),
+                ("v", Arc::new(Float64Array::from(values)) as ArrayRef),
+                (
+                    "time",
+                    Arc::new(TimestampNanosecondArray::from_vec(
+                        times,
+                        TIME_DATA_TIMEZONE(),
+                    )) as ArrayRef,
+                ),
+            ])
+            .unwrap()
+        };
+
+        let batch1 = make_batch(
+            vec!["a", "a", "b"],
+            vec![None, Some(1.0), Some(2.0)],
+            vec![1000, 2000, 3000],
+        );
+        let batch2 = make_batch(
+            vec!["b", "b", "c"],
+            vec![Some(3.0), None, Some(4.0)],
+            vec![4000, 5000, 6000],
+        );
+        let schema = batch1.schema();
+
+        let input = Arc::new(MemoryExec::try_new(&[vec![batch1, batch2]], schema, None).unwrap());
+        let exec = Arc::new(
+            SortedSelectorExec::try_new(input, selector_type, &["tag"], "time", &["v"]).unwrap(),
+        );
+
+        let results = collect(exec, SessionContext::new().task_ctx())
+            .await
+            .unwrap();
+
+        pretty_format_batches(&results)
+            .unwrap()
+            .to_string()
+            .split('\n')
+            .map(|s| s.to_owned())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_sorted_selector_first() {
+        let expected = vec![
+            "+-----+---+----------------------------+",
+            "| tag | v | v_time                     |",
+            "+-----+---+----------------------------+",
+            "| a   | 1 | 1970-01-01 00:00:00.000002 |",
+            "| b   | 2 | 1970-01-01 00:00:00.000003 |",
+            "| c   | 4 | 1970-01-01 00:00:00.000006 |",
+            "+-----+---+----------------------------+",
+        ];
+
+        assert_eq!(expected, run(SortedSelectorType::First).await);
+    }
+
+    #[tokio::test]
+    async fn test_sorted_selector_last() {
+        let expected = vec![
+            "+-----+---+----------------------------+",
+            "| tag | v | v_time                     |",
+            "+-----+---+----------------------------+",
+            "| a   | 1 | 1970-01-01 00:00:00.000002 |",
+            "| b   | 3 | 1970-01-01 00:00:00.000004 |",
+            "| c   | 4 | 1970-01-01 00:00:00.000006 |",
+            "+-----+---+----------------------------+",
+        ];
+
+        assert_eq!(expected, run(SortedSelectorType::Last).await);
+    }
+}
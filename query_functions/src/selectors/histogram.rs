@@ -0,0 +1,348 @@
+//! Implementation of the `histogram` function, which counts the
+//! values in each of a set of buckets.
+
+use std::{cmp::Ordering, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, ListArray, UInt64Array},
+    compute::cast,
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+
+use super::{ReturnTypeFunction, StateTypeFactory};
+
+/// Create a User Defined Aggregate Function (UDAF) for
+/// histogram(value, bucket_edges)
+pub(super) fn make_histogram_uda(name: &str) -> AggregateUDF {
+    let edges_type = make_list_type(DataType::Float64);
+    let input_signature = Signature::one_of(
+        vec![
+            TypeSignature::Exact(vec![DataType::Float64, edges_type.clone()]),
+            TypeSignature::Exact(vec![DataType::Int64, edges_type.clone()]),
+            TypeSignature::Exact(vec![DataType::UInt64, edges_type]),
+        ],
+        Volatility::Stable,
+    );
+
+    let return_type_func: ReturnTypeFunction =
+        Arc::new(move |_arg_types| Ok(Arc::new(make_list_type(make_bucket_type()))));
+
+    // The state is the count in each bucket and the bucket edges
+    let state_type_factory: StateTypeFactory = Arc::new(move |_return_type| {
+        Ok(Arc::new(vec![
+            make_list_type(DataType::UInt64),
+            make_list_type(DataType::Float64),
+        ]))
+    });
+
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |_return_type| {
+        let accumulator: Box<dyn Accumulator> = Box::new(HistogramAccumulator::default());
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// A list of `item_type`, as produced by [`ScalarValue::new_list`]
+fn make_list_type(item_type: DataType) -> DataType {
+    DataType::List(Box::new(Field::new("item", item_type, true)))
+}
+
+/// The fields of a bucket in the output of `histogram`
+fn make_bucket_fields() -> Vec<Field> {
+    vec![
+        Field::new("lower", DataType::Float64, true),
+        Field::new("upper", DataType::Float64, true),
+        Field::new("count", DataType::UInt64, true),
+    ]
+}
+
+/// The type of a bucket in the output of `histogram`
+fn make_bucket_type() -> DataType {
+    DataType::Struct(make_bucket_fields())
+}
+
+/// Accumulator that counts the values in each bucket
+#[derive(Debug, Default)]
+struct HistogramAccumulator {
+    /// The edges of the buckets, in ascending order. Not known until
+    /// the first batch is seen, as they are passed as an argument
+    edges: Option<Vec<f64>>,
+
+    /// The number of values in each bucket
+    counts: Vec<u64>,
+}
+
+impl HistogramAccumulator {
+    /// Record the bucket edges from the first non null list in
+    /// `edges_arr`
+    fn update_edges(&mut self, edges_arr: &ArrayRef) -> DataFusionResult<()> {
+        if self.edges.is_some() {
+            return Ok(());
+        }
+
+        let edge_lists = edges_arr
+            .as_any()
+            .downcast_ref::<ListArray>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Second argument was a list of edges");
+
+        let idx = match (0..edge_lists.len()).find(|&idx| edge_lists.is_valid(idx)) {
+            Some(idx) => idx,
+            None => return Ok(()),
+        };
+
+        let edges = edge_lists.value(idx);
+        let edges = edges
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .expect("edges were f64");
+
+        let edges = edges.iter().collect::<Option<Vec<_>>>().ok_or_else(|| {
+            DataFusionError::Execution("histogram expected bucket edges to not be null".into())
+        })?;
+
+        let ascending = edges
+            .windows(2)
+            .all(|w| w[0].partial_cmp(&w[1]) == Some(Ordering::Less));
+        if edges.len() < 2 || !ascending {
+            return Err(DataFusionError::Execution(format!(
+                "histogram expected at least 2 bucket edges in ascending order, got {:?}",
+                edges
+            )));
+        }
+
+        self.counts = vec![0; edges.len() - 1];
+        self.edges = Some(edges);
+        Ok(())
+    }
+
+    /// The index of the bucket containing `value`, if any. Each
+    /// bucket includes its lower edge but not its upper edge, other
+    /// than the last bucket which includes both
+    fn bucket(edges: &[f64], value: f64) -> Option<usize> {
+        let last = *edges.last()?;
+        if value.is_nan() || value < edges[0] || value > last {
+            return None;
+        }
+        if value == last {
+            return Some(edges.len() - 2);
+        }
+        // the number of edges at or below the value
+        Some(edges.partition_point(|edge| *edge <= value) - 1)
+    }
+}
+
+impl Accumulator for HistogramAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let counts = self
+            .counts
+            .iter()
+            .map(|c| ScalarValue::UInt64(Some(*c)))
+            .collect();
+        let edges = self
+            .edges
+            .iter()
+            .flatten()
+            .map(|e| ScalarValue::Float64(Some(*e)))
+            .collect();
+
+        Ok(vec![
+            AggregateState::Scalar(ScalarValue::new_list(Some(counts), DataType::UInt64)),
+            AggregateState::Scalar(ScalarValue::new_list(Some(edges), DataType::Float64)),
+        ])
+    }
+
+    // Returns a struct {lower, upper, count} for each bucket, in
+    // ascending order
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let buckets = match &self.edges {
+            Some(edges) => edges
+                .windows(2)
+                .zip(&self.counts)
+                .map(|(edges, count)| {
+                    ScalarValue::Struct(
+                        Some(vec![
+                            ScalarValue::Float64(Some(edges[0])),
+                            ScalarValue::Float64(Some(edges[1])),
+                            ScalarValue::UInt64(Some(*count)),
+                        ]),
+                        Box::new(make_bucket_fields()),
+                    )
+                })
+                .collect(),
+            None => vec![],
+        };
+
+        Ok(ScalarValue::new_list(Some(buckets), make_bucket_type()))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if values.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 arguments passed to histogram function but got {}",
+                values.len()
+            )));
+        }
+
+        self.update_edges(&values[1])?;
+        let edges = match &self.edges {
+            Some(edges) => edges,
+            // no values seen yet
+            None => return Ok(()),
+        };
+
+        let value_arr = cast(&values[0], &DataType::Float64)?;
+        let value_arr = value_arr
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .expect("cast to f64");
+
+        for value in value_arr.iter().flatten() {
+            if let Some(bucket) = Self::bucket(edges, value) {
+                self.counts[bucket] += 1;
+            }
+        }
+        Ok(())
+    }
+
+    // Each row of the states is a list of counts and a list of the
+    // bucket edges previously produced by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        if states.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 states passed to histogram function but got {}",
+                states.len()
+            )));
+        }
+
+        let count_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("First state was a list of counts");
+
+        for idx in 0..count_lists.len() {
+            if count_lists.is_null(idx) {
+                continue;
+            }
+
+            let counts = count_lists.value(idx);
+            let counts = counts
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .expect("counts were u64");
+
+            // the state of an accumulator that saw no values has no counts
+            if counts.is_empty() {
+                continue;
+            }
+
+            self.update_edges(&states[1].slice(idx, 1))?;
+            if counts.len() != self.counts.len() {
+                return Err(DataFusionError::Internal(format!(
+                    "Internal error: Expected {} bucket counts passed to histogram function but got {}",
+                    self.counts.len(),
+                    counts.len()
+                )));
+            }
+
+            for (count, other) in self.counts.iter_mut().zip(counts.iter()) {
+                *count += other.unwrap_or_default();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::datatypes::DataType;
+    use datafusion::{
+        prelude::{col, lit, Expr},
+        scalar::ScalarValue,
+    };
+
+    use crate::{selectors::histogram, test_util::run_case};
+
+    #[tokio::test]
+    async fn test_histogram_f64() {
+        run_case(
+            histogram()
+                .call(vec![col("f64_value"), histogram_edges(&[0.0, 2.0, 4.0, 5.0])])
+                .alias("histogram"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------+",
+                "| histogram                                                                                                          |",
+                "+--------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"lower\": 0, \"upper\": 2, \"count\": 1}, {\"lower\": 2, \"upper\": 4, \"count\": 2}, {\"lower\": 4, \"upper\": 5, \"count\": 2}] |",
+                "+--------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_histogram_i64() {
+        run_case(
+            histogram()
+                .call(vec![col("i64_value"), histogram_edges(&[0.0, 25.0, 100.0])])
+                .alias("histogram"),
+            vec![
+                "+----------------------------------------------------------------------------------+",
+                "| histogram                                                                        |",
+                "+----------------------------------------------------------------------------------+",
+                "| [{\"lower\": 0, \"upper\": 25, \"count\": 2}, {\"lower\": 25, \"upper\": 100, \"count\": 3}] |",
+                "+----------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_histogram_u64() {
+        run_case(
+            histogram()
+                .call(vec![col("u64_value"), histogram_edges(&[0.0, 25.0, 100.0])])
+                .alias("histogram"),
+            vec![
+                "+----------------------------------------------------------------------------------+",
+                "| histogram                                                                        |",
+                "+----------------------------------------------------------------------------------+",
+                "| [{\"lower\": 0, \"upper\": 25, \"count\": 2}, {\"lower\": 25, \"upper\": 100, \"count\": 3}] |",
+                "+----------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    /// A literal list of histogram bucket edges
+    fn histogram_edges(edges: &[f64]) -> Expr {
+        let edges = edges
+            .iter()
+            .map(|e| ScalarValue::Float64(Some(*e)))
+            .collect();
+        lit(ScalarValue::new_list(Some(edges), DataType::Float64))
+    }
+}
@@ -6,26 +6,33 @@
 //! couldn't get the traits to work out correctly (as Bool, I64/F64
 //! and Utf8 arrow types don't share enough in common).
 
-use std::fmt::Debug;
+use std::{cmp::Ordering, fmt::Debug};
 
 use arrow::{
     array::{
         Array, ArrayRef, BooleanArray, Float64Array, Int64Array, StringArray,
         TimestampNanosecondArray, UInt64Array,
     },
-    compute::kernels::aggregate::{
-        max as array_max, max_boolean as array_max_boolean, max_string as array_max_string,
-        min as array_min, min_boolean as array_min_boolean, min_string as array_min_string,
+    compute::kernels::{
+        aggregate::{
+            max as array_max, max_boolean as array_max_boolean, max_string as array_max_string,
+            min as array_min, min_boolean as array_min_boolean, min_string as array_min_string,
+        },
+        boolean::is_not_null,
+        comparison::{eq_bool_scalar, eq_scalar, eq_utf8_scalar},
+        filter::filter,
     },
     datatypes::{DataType, Field},
 };
 use datafusion::{
-    error::Result as DataFusionResult, logical_expr::AggregateState, scalar::ScalarValue,
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::AggregateState,
+    scalar::ScalarValue,
 };
 
-use observability_deps::tracing::debug;
+use feruca::{Collator, Locale, Tailoring};
 
-use super::{Selector, SelectorOutput};
+use super::{Selector, SelectorTieBreak, SelectorType, SELECTOR_TIME_FIELD, SELECTOR_VALUE_FIELD};
 
 /// Trait for comparing values in arrays with their native
 /// representation. This so the same comparison expression can be used
@@ -111,8 +118,8 @@ impl ToState<String> for &str {
 
 fn make_scalar_struct(data_fields: Vec<ScalarValue>) -> ScalarValue {
     let fields = vec![
-        Field::new("value", data_fields[0].get_datatype(), true),
-        Field::new("time", data_fields[1].get_datatype(), true),
+        Field::new(SELECTOR_VALUE_FIELD, data_fields[0].get_datatype(), true),
+        Field::new(SELECTOR_TIME_FIELD, data_fields[1].get_datatype(), true),
     ];
 
     ScalarValue::Struct(Some(data_fields), Box::new(fields))
@@ -124,19 +131,19 @@ macro_rules! make_first_selector {
         pub struct $STRUCTNAME {
             value: Option<$RUSTTYPE>,
             time: Option<i64>,
+            tie_break: SelectorTieBreak,
         }
 
-        impl Default for $STRUCTNAME {
-            fn default() -> Self {
+        impl Selector for $STRUCTNAME {
+            fn new(tie_break: SelectorTieBreak, _value_type: &DataType) -> Self {
                 Self {
                     value: None,
                     time: None,
+                    tie_break,
                 }
             }
-        }
 
-        impl Selector for $STRUCTNAME {
-            fn value_data_type() -> DataType {
+            fn value_data_type(&self) -> DataType {
                 $ARROWTYPE
             }
 
@@ -147,15 +154,11 @@ macro_rules! make_first_selector {
                 ])
             }
 
-            fn evaluate(&self, output: &SelectorOutput) -> DataFusionResult<ScalarValue> {
-                match output {
-                    SelectorOutput::Value => Ok($TO_SCALARVALUE(self.value.clone())),
-                    SelectorOutput::Time => Ok(ScalarValue::TimestampNanosecond(self.time, None)),
-                    SelectorOutput::Struct => Ok(make_scalar_struct(vec![
-                        $TO_SCALARVALUE(self.value.clone()),
-                        ScalarValue::TimestampNanosecond(self.time, None),
-                    ])),
-                }
+            fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+                Ok(make_scalar_struct(vec![
+                    $TO_SCALARVALUE(self.value.clone()),
+                    ScalarValue::TimestampNanosecond(self.time, None),
+                ]))
             }
 
             fn update_batch(
@@ -163,18 +166,6 @@ macro_rules! make_first_selector {
                 value_arr: &ArrayRef,
                 time_arr: &ArrayRef,
             ) -> DataFusionResult<()> {
-                let value_arr = value_arr
-                    .as_any()
-                    .downcast_ref::<$ARRTYPE>()
-                    // the input type arguments should be ensured by datafusion
-                    .expect("First argument was value");
-
-                let time_arr = time_arr
-                    .as_any()
-                    .downcast_ref::<TimestampNanosecondArray>()
-                    // the input type arguments should be ensured by datafusion
-                    .expect("Second argument was time");
-
                 // Only look for times where the array also has a non
                 // null value (the time array should have no nulls itself)
                 //
@@ -186,41 +177,54 @@ macro_rules! make_first_selector {
                 // NULL  | 100
                 // A     | 200
                 // B     | 300
-                //
-                // Note this could likely be faster if we used `ArrayData` APIs
-                let time_arr: TimestampNanosecondArray = time_arr
-                    .iter()
-                    .zip(value_arr.iter())
-                    .map(|(ts, value)| if value.is_some() { ts } else { None })
-                    .collect();
+                let has_value = is_not_null(value_arr.as_ref())?;
+                let value_arr = filter(value_arr.as_ref(), &has_value)?;
+                let time_arr = filter(time_arr.as_ref(), &has_value)?;
+
+                let value_arr = value_arr
+                    .as_any()
+                    .downcast_ref::<$ARRTYPE>()
+                    // the input type arguments should be ensured by datafusion
+                    .expect("First argument was value");
 
-                let cur_min_time = $MINFUNC(&time_arr);
+                let time_arr = time_arr
+                    .as_any()
+                    .downcast_ref::<TimestampNanosecondArray>()
+                    // the input type arguments should be ensured by datafusion
+                    .expect("Second argument was time");
 
-                let need_update = match (&self.time, &cur_min_time) {
-                    (Some(time), Some(cur_min_time)) => cur_min_time < time,
-                    // No existing minimum, so update needed
-                    (None, Some(_)) => true,
+                let cur_min_time = match $MINFUNC(time_arr) {
+                    Some(cur_min_time) => cur_min_time,
                     // No actual minimum time found, so no update needed
-                    (_, None) => false,
+                    None => return Ok(()),
+                };
+
+                let need_update = match self.time {
+                    // rows with the same time may still be chosen by the tie break
+                    Some(time) => cur_min_time <= time,
+                    // No existing minimum, so update needed
+                    None => true,
                 };
 
                 if need_update {
-                    let index = time_arr
+                    // find the rows with the minimum time, see also
+                    // https://github.com/apache/arrow-datafusion/issues/600
+                    let is_candidate = eq_scalar(time_arr, cur_min_time)?;
+
+                    let tie_break = self.tie_break;
+                    let candidate = is_candidate
                         .iter()
-                        // arrow doesn't tell us what index had the
-                        // minimum, so need to find it ourselves see also
-                        // https://github.com/apache/arrow-datafusion/issues/600
                         .enumerate()
-                        .find(|(_, time)| cur_min_time == *time)
-                        .map(|(idx, _)| idx)
+                        .filter(|(_, is_candidate)| *is_candidate == Some(true))
+                        .map(|(idx, _)| value_arr.value(idx).to_owned())
+                        .reduce(|current, candidate| tie_break.choose(current, candidate))
                         .unwrap(); // value always exists
 
-                    self.time = cur_min_time;
-                    self.value = if value_arr.is_null(index) {
-                        None
-                    } else {
-                        Some(value_arr.value(index).to_owned())
+                    self.value = match (self.time == Some(cur_min_time), self.value.take()) {
+                        (true, Some(current)) => Some(tie_break.choose(current, candidate)),
+                        _ => Some(candidate),
                     };
+                    self.time = Some(cur_min_time);
                 }
 
                 Ok(())
@@ -235,19 +239,19 @@ macro_rules! make_last_selector {
         pub struct $STRUCTNAME {
             value: Option<$RUSTTYPE>,
             time: Option<i64>,
+            tie_break: SelectorTieBreak,
         }
 
-        impl Default for $STRUCTNAME {
-            fn default() -> Self {
+        impl Selector for $STRUCTNAME {
+            fn new(tie_break: SelectorTieBreak, _value_type: &DataType) -> Self {
                 Self {
                     value: None,
                     time: None,
+                    tie_break,
                 }
             }
-        }
 
-        impl Selector for $STRUCTNAME {
-            fn value_data_type() -> DataType {
+            fn value_data_type(&self) -> DataType {
                 $ARROWTYPE
             }
 
@@ -258,15 +262,11 @@ macro_rules! make_last_selector {
                 ])
             }
 
-            fn evaluate(&self, output: &SelectorOutput) -> DataFusionResult<ScalarValue> {
-                match output {
-                    SelectorOutput::Value => Ok($TO_SCALARVALUE(self.value.clone())),
-                    SelectorOutput::Time => Ok(ScalarValue::TimestampNanosecond(self.time, None)),
-                    SelectorOutput::Struct => Ok(make_scalar_struct(vec![
-                        $TO_SCALARVALUE(self.value.clone()),
-                        ScalarValue::TimestampNanosecond(self.time, None),
-                    ])),
-                }
+            fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+                Ok(make_scalar_struct(vec![
+                    $TO_SCALARVALUE(self.value.clone()),
+                    ScalarValue::TimestampNanosecond(self.time, None),
+                ]))
             }
 
             fn update_batch(
@@ -274,18 +274,6 @@ macro_rules! make_last_selector {
                 value_arr: &ArrayRef,
                 time_arr: &ArrayRef,
             ) -> DataFusionResult<()> {
-                let value_arr = value_arr
-                    .as_any()
-                    .downcast_ref::<$ARRTYPE>()
-                    // the input type arguments should be ensured by datafusion
-                    .expect("First argument was value");
-
-                let time_arr = time_arr
-                    .as_any()
-                    .downcast_ref::<TimestampNanosecondArray>()
-                    // the input type arguments should be ensured by datafusion
-                    .expect("Second argument was time");
-
                 // Only look for times where the array also has a non
                 // null value (the time array should have no nulls itself)
                 //
@@ -297,40 +285,53 @@ macro_rules! make_last_selector {
                 // A     | 100
                 // B     | 200
                 // NULL  | 300
-                //
-                // Note this could likely be faster if we used `ArrayData` APIs
-                let time_arr: TimestampNanosecondArray = time_arr
-                    .iter()
-                    .zip(value_arr.iter())
-                    .map(|(ts, value)| if value.is_some() { ts } else { None })
-                    .collect();
+                let has_value = is_not_null(value_arr.as_ref())?;
+                let value_arr = filter(value_arr.as_ref(), &has_value)?;
+                let time_arr = filter(time_arr.as_ref(), &has_value)?;
 
-                let cur_max_time = $MAXFUNC(&time_arr);
+                let value_arr = value_arr
+                    .as_any()
+                    .downcast_ref::<$ARRTYPE>()
+                    // the input type arguments should be ensured by datafusion
+                    .expect("First argument was value");
+
+                let time_arr = time_arr
+                    .as_any()
+                    .downcast_ref::<TimestampNanosecondArray>()
+                    // the input type arguments should be ensured by datafusion
+                    .expect("Second argument was time");
 
-                let need_update = match (&self.time, &cur_max_time) {
-                    (Some(time), Some(cur_max_time)) => time < cur_max_time,
+                let cur_max_time = match $MAXFUNC(time_arr) {
+                    Some(cur_max_time) => cur_max_time,
+                    // No actual maximum time found, so no update needed
+                    None => return Ok(()),
+                };
+
+                let need_update = match self.time {
+                    // rows with the same time may still be chosen by the tie break
+                    Some(time) => time <= cur_max_time,
                     // No existing maximum, so update needed
-                    (None, Some(_)) => true,
-                    // No actual maximum value found, so no update needed
-                    (_, None) => false,
+                    None => true,
                 };
 
                 if need_update {
-                    let index = time_arr
+                    // find the rows with the maximum time
+                    let is_candidate = eq_scalar(time_arr, cur_max_time)?;
+
+                    let tie_break = self.tie_break;
+                    let candidate = is_candidate
                         .iter()
-                        // arrow doesn't tell us what index had the
-                        // maximum, so need to find it ourselves
                         .enumerate()
-                        .find(|(_, time)| cur_max_time == *time)
-                        .map(|(idx, _)| idx)
+                        .filter(|(_, is_candidate)| *is_candidate == Some(true))
+                        .map(|(idx, _)| value_arr.value(idx).to_owned())
+                        .reduce(|current, candidate| tie_break.choose(current, candidate))
                         .unwrap(); // value always exists
 
-                    self.time = cur_max_time;
-                    self.value = if value_arr.is_null(index) {
-                        None
-                    } else {
-                        Some(value_arr.value(index).to_owned())
+                    self.value = match (self.time == Some(cur_max_time), self.value.take()) {
+                        (true, Some(current)) => Some(tie_break.choose(current, candidate)),
+                        _ => Some(candidate),
                     };
+                    self.time = Some(cur_max_time);
                 }
 
                 Ok(())
@@ -365,24 +366,24 @@ impl ActionNeeded {
 }
 
 macro_rules! make_min_selector {
-    ($STRUCTNAME:ident, $RUSTTYPE:ident, $ARROWTYPE:expr, $ARRTYPE:ident, $MINFUNC:ident, $TO_SCALARVALUE: expr) => {
+    ($STRUCTNAME:ident, $RUSTTYPE:ident, $ARROWTYPE:expr, $ARRTYPE:ident, $MINFUNC:ident, $EQFUNC:ident, $TO_SCALARVALUE: expr) => {
         #[derive(Debug)]
         pub struct $STRUCTNAME {
             value: Option<$RUSTTYPE>,
             time: Option<i64>,
         }
 
-        impl Default for $STRUCTNAME {
-            fn default() -> Self {
+        impl Selector for $STRUCTNAME {
+            // Rows with the same value are always resolved by choosing
+            // the earliest time
+            fn new(_tie_break: SelectorTieBreak, _value_type: &DataType) -> Self {
                 Self {
                     value: None,
                     time: None,
                 }
             }
-        }
 
-        impl Selector for $STRUCTNAME {
-            fn value_data_type() -> DataType {
+            fn value_data_type(&self) -> DataType {
                 $ARROWTYPE
             }
 
@@ -393,15 +394,11 @@ macro_rules! make_min_selector {
                 ])
             }
 
-            fn evaluate(&self, output: &SelectorOutput) -> DataFusionResult<ScalarValue> {
-                match output {
-                    SelectorOutput::Value => Ok($TO_SCALARVALUE(self.value.clone())),
-                    SelectorOutput::Time => Ok(ScalarValue::TimestampNanosecond(self.time, None)),
-                    SelectorOutput::Struct => Ok(make_scalar_struct(vec![
-                        $TO_SCALARVALUE(self.value.clone()),
-                        ScalarValue::TimestampNanosecond(self.time, None),
-                    ])),
-                }
+            fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+                Ok(make_scalar_struct(vec![
+                    $TO_SCALARVALUE(self.value.clone()),
+                    ScalarValue::TimestampNanosecond(self.time, None),
+                ]))
             }
 
             fn update_batch(
@@ -449,29 +446,24 @@ macro_rules! make_min_selector {
 
                 if action_needed.update_time() {
                     // arrow doesn't tell us what index(es) had the
-                    // minimum value, so need to find them ourselves
-                    // and compute the minimum timestamp found. See
+                    // minimum value, so find them with a comparison
+                    // kernel and compute the minimum timestamp found. See
                     // https://github.com/apache/arrow-datafusion/issues/600
-                    self.time = value_arr
-                        .iter()
-                        .enumerate()
-                        // stream of Option<i64>
-                        .map(|(idx, value)| {
-                            // Note: time should never be null but handle it anyways
-                            let null_time = time_arr.is_null(idx);
-                            if null_time {
-                                debug!(idx, "MIN selector saw null time value");
-                            }
-                            if value == cur_min_value && !null_time {
-                                Some(time_arr.value(idx))
-                            } else {
-                                None
-                            }
-                        })
+                    //
+                    // Note: time should never be null, but any nulls are
+                    // ignored by the min kernel
+                    let cur_min_value = cur_min_value.expect("minimum value exists");
+                    let is_min = $EQFUNC(value_arr, cur_min_value)?;
+                    let min_times = filter(time_arr, &is_min)?;
+                    let min_times = min_times
+                        .as_any()
+                        .downcast_ref::<TimestampNanosecondArray>()
+                        .expect("filtered time array");
+
+                    self.time = array_min(min_times)
+                        .into_iter()
                         // include existing time, potentially
-                        .chain(std::iter::once(self.time.take()))
-                        // clean out any Nones
-                        .filter_map(|v| v)
+                        .chain(self.time.take())
                         .min();
                 }
                 Ok(())
@@ -481,24 +473,24 @@ macro_rules! make_min_selector {
 }
 
 macro_rules! make_max_selector {
-    ($STRUCTNAME:ident, $RUSTTYPE:ident, $ARROWTYPE:expr, $ARRTYPE:ident, $MAXFUNC:ident, $TO_SCALARVALUE: expr) => {
+    ($STRUCTNAME:ident, $RUSTTYPE:ident, $ARROWTYPE:expr, $ARRTYPE:ident, $MAXFUNC:ident, $EQFUNC:ident, $TO_SCALARVALUE: expr) => {
         #[derive(Debug)]
         pub struct $STRUCTNAME {
             value: Option<$RUSTTYPE>,
             time: Option<i64>,
         }
 
-        impl Default for $STRUCTNAME {
-            fn default() -> Self {
+        impl Selector for $STRUCTNAME {
+            // Rows with the same value are always resolved by choosing
+            // the earliest time
+            fn new(_tie_break: SelectorTieBreak, _value_type: &DataType) -> Self {
                 Self {
                     value: None,
                     time: None,
                 }
             }
-        }
 
-        impl Selector for $STRUCTNAME {
-            fn value_data_type() -> DataType {
+            fn value_data_type(&self) -> DataType {
                 $ARROWTYPE
             }
 
@@ -509,15 +501,11 @@ macro_rules! make_max_selector {
                 ])
             }
 
-            fn evaluate(&self, output: &SelectorOutput) -> DataFusionResult<ScalarValue> {
-                match output {
-                    SelectorOutput::Value => Ok($TO_SCALARVALUE(self.value.clone())),
-                    SelectorOutput::Time => Ok(ScalarValue::TimestampNanosecond(self.time, None)),
-                    SelectorOutput::Struct => Ok(make_scalar_struct(vec![
-                        $TO_SCALARVALUE(self.value.clone()),
-                        ScalarValue::TimestampNanosecond(self.time, None),
-                    ])),
-                }
+            fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+                Ok(make_scalar_struct(vec![
+                    $TO_SCALARVALUE(self.value.clone()),
+                    ScalarValue::TimestampNanosecond(self.time, None),
+                ]))
             }
 
             fn update_batch(
@@ -568,28 +556,25 @@ macro_rules! make_max_selector {
                 // numerical value
                 if action_needed.update_time() {
                     // arrow doesn't tell us what index(es) had the
-                    // minimum value, so need to find them ourselves
-                    // and compute the minimum timestamp found. See
+                    // maximum value, so find them with a comparison
+                    // kernel and compute the minimum timestamp found. See
                     // https://github.com/apache/arrow-datafusion/issues/600
-                    self.time = value_arr
-                        .iter()
-                        .enumerate()
-                        .map(|(idx, value)| {
-                            let null_time = time_arr.is_null(idx);
-                            if null_time {
-                                debug!(idx, "MAX selector saw null time value");
-                            }
-                            if value == cur_max_value && !null_time {
-                                Some(time_arr.value(idx))
-                            } else {
-                                None
-                            }
-                        })
+                    //
+                    // Note: time should never be null, but any nulls are
+                    // ignored by the min kernel
+                    let cur_max_value = cur_max_value.expect("maximum value exists");
+                    let is_max = $EQFUNC(value_arr, cur_max_value)?;
+                    let max_times = filter(time_arr, &is_max)?;
+                    let max_times = max_times
+                        .as_any()
+                        .downcast_ref::<TimestampNanosecondArray>()
+                        .expect("filtered time array");
+
+                    self.time = array_min(max_times) // still use min
+                        .into_iter()
                         // include existing time, potentially
-                        .chain(std::iter::once(self.time.take()))
-                        // clean out any Nones
-                        .filter_map(|v| v)
-                        .min(); // still use min
+                        .chain(self.time.take())
+                        .min();
                 }
                 Ok(())
             }
@@ -691,6 +676,7 @@ make_min_selector!(
     DataType::Float64,
     Float64Array,
     array_min,
+    eq_scalar,
     ScalarValue::Float64
 );
 make_min_selector!(
@@ -699,6 +685,7 @@ make_min_selector!(
     DataType::Int64,
     Int64Array,
     array_min,
+    eq_scalar,
     ScalarValue::Int64
 );
 make_min_selector!(
@@ -707,6 +694,7 @@ make_min_selector!(
     DataType::UInt64,
     UInt64Array,
     array_min,
+    eq_scalar,
     ScalarValue::UInt64
 );
 make_min_selector!(
@@ -715,6 +703,7 @@ make_min_selector!(
     DataType::Utf8,
     StringArray,
     array_min_string,
+    eq_utf8_scalar,
     ScalarValue::Utf8
 );
 make_min_selector!(
@@ -723,6 +712,7 @@ make_min_selector!(
     DataType::Boolean,
     BooleanArray,
     array_min_boolean,
+    eq_bool_scalar,
     ScalarValue::Boolean
 );
 
@@ -734,6 +724,7 @@ make_max_selector!(
     DataType::Float64,
     Float64Array,
     array_max,
+    eq_scalar,
     ScalarValue::Float64
 );
 make_max_selector!(
@@ -742,6 +733,7 @@ make_max_selector!(
     DataType::Int64,
     Int64Array,
     array_max,
+    eq_scalar,
     ScalarValue::Int64
 );
 make_max_selector!(
@@ -750,6 +742,7 @@ make_max_selector!(
     DataType::UInt64,
     UInt64Array,
     array_max,
+    eq_scalar,
     ScalarValue::UInt64
 );
 make_max_selector!(
@@ -758,6 +751,7 @@ make_max_selector!(
     DataType::Utf8,
     StringArray,
     array_max_string,
+    eq_utf8_scalar,
     ScalarValue::Utf8
 );
 make_max_selector!(
@@ -766,5 +760,292 @@ make_max_selector!(
     DataType::Boolean,
     BooleanArray,
     array_max_boolean,
+    eq_bool_scalar,
     ScalarValue::Boolean
 );
+
+// DECIMAL
+//
+// The precision and scale of Decimal128 values are only known at
+// runtime, so rather than using the kernels specialized for each
+// array type, the values are compared row by row using their integer
+// representation (all values of a column have the same scale).
+
+/// Implements the first / last / min / max selectors for Decimal128 values
+#[derive(Debug)]
+pub struct Decimal128Selector {
+    selector_type: SelectorType,
+    tie_break: SelectorTieBreak,
+    precision: u8,
+    scale: u8,
+
+    /// The selected value and its time
+    selected: Option<(i128, i64)>,
+}
+
+impl Decimal128Selector {
+    fn new(
+        selector_type: SelectorType,
+        tie_break: SelectorTieBreak,
+        value_type: &DataType,
+    ) -> Self {
+        let (precision, scale) = match value_type {
+            DataType::Decimal128(precision, scale) => (*precision, *scale),
+            // the value type is ensured by the accumulator factory
+            t => panic!("Decimal128 selector created for {:?}", t),
+        };
+
+        Self {
+            selector_type,
+            tie_break,
+            precision,
+            scale,
+            selected: None,
+        }
+    }
+
+    fn value_data_type(&self) -> DataType {
+        DataType::Decimal128(self.precision, self.scale)
+    }
+
+    fn scalar_values(&self) -> (ScalarValue, ScalarValue) {
+        (
+            ScalarValue::Decimal128(
+                self.selected.map(|(value, _)| value),
+                self.precision,
+                self.scale,
+            ),
+            ScalarValue::TimestampNanosecond(self.selected.map(|(_, time)| time), None),
+        )
+    }
+
+    fn datafusion_state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let (value, time) = self.scalar_values();
+        Ok(vec![
+            AggregateState::Scalar(value),
+            AggregateState::Scalar(time),
+        ])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let (value, time) = self.scalar_values();
+        Ok(make_scalar_struct(vec![value, time]))
+    }
+
+    fn update_batch(&mut self, value_arr: &ArrayRef, time_arr: &ArrayRef) -> DataFusionResult<()> {
+        let time_arr = time_arr
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Second argument was time");
+
+        for idx in 0..value_arr.len() {
+            if value_arr.is_null(idx) || time_arr.is_null(idx) {
+                continue;
+            }
+
+            let value = match ScalarValue::try_from_array(value_arr, idx)? {
+                ScalarValue::Decimal128(Some(value), _, _) => value,
+                v => {
+                    return Err(DataFusionError::Internal(format!(
+                        "Internal error: Expected Decimal128 value, got {:?}",
+                        v
+                    )))
+                }
+            };
+            self.update(value, time_arr.value(idx));
+        }
+        Ok(())
+    }
+
+    /// Update the selected value with a row seen after those already seen
+    fn update(&mut self, value: i128, time: i64) {
+        let (current_value, current_time) = match self.selected {
+            Some(selected) => selected,
+            None => {
+                self.selected = Some((value, time));
+                return;
+            }
+        };
+
+        // Like the other selectors, first / last choose between rows
+        // with the same time using the tie break, and min / max choose
+        // the earliest time for rows with the same value
+        let selected = match (
+            self.selector_type,
+            time.cmp(&current_time),
+            value.cmp(&current_value),
+        ) {
+            (SelectorType::First, Ordering::Less, _)
+            | (SelectorType::Last, Ordering::Greater, _) => (value, time),
+            (SelectorType::First | SelectorType::Last, Ordering::Equal, _) => {
+                (self.tie_break.choose(current_value, value), time)
+            }
+            (SelectorType::Min, _, Ordering::Less) | (SelectorType::Max, _, Ordering::Greater) => {
+                (value, time)
+            }
+            (SelectorType::Min | SelectorType::Max, _, Ordering::Equal) => {
+                (value, time.min(current_time))
+            }
+            _ => (current_value, current_time),
+        };
+        self.selected = Some(selected);
+    }
+}
+
+/// Implements [`Selector`] for `$STRUCTNAME` by delegating to an
+/// `$INNER` for `$SELECTOR_TYPE`
+macro_rules! make_delegating_selector {
+    ($STRUCTNAME:ident, $INNER:ident, $SELECTOR_TYPE:expr) => {
+        #[derive(Debug)]
+        pub struct $STRUCTNAME($INNER);
+
+        impl Selector for $STRUCTNAME {
+            fn new(tie_break: SelectorTieBreak, value_type: &DataType) -> Self {
+                Self($INNER::new($SELECTOR_TYPE, tie_break, value_type))
+            }
+
+            fn value_data_type(&self) -> DataType {
+                self.0.value_data_type()
+            }
+
+            fn datafusion_state(&self) -> DataFusionResult<Vec<AggregateState>> {
+                self.0.datafusion_state()
+            }
+
+            fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+                self.0.evaluate()
+            }
+
+            fn update_batch(
+                &mut self,
+                value_arr: &ArrayRef,
+                time_arr: &ArrayRef,
+            ) -> DataFusionResult<()> {
+                self.0.update_batch(value_arr, time_arr)
+            }
+        }
+    };
+}
+
+make_delegating_selector!(
+    Decimal128FirstSelector,
+    Decimal128Selector,
+    SelectorType::First
+);
+make_delegating_selector!(
+    Decimal128LastSelector,
+    Decimal128Selector,
+    SelectorType::Last
+);
+make_delegating_selector!(Decimal128MinSelector, Decimal128Selector, SelectorType::Min);
+make_delegating_selector!(Decimal128MaxSelector, Decimal128Selector, SelectorType::Max);
+
+// COLLATED STRINGS
+
+/// Implements the min / max selectors for Utf8 values, comparing the
+/// values using the Unicode Collation Algorithm rather than byte-wise
+#[derive(Debug)]
+pub struct CollatedUtf8Selector {
+    selector_type: SelectorType,
+    collator: Collator,
+
+    /// The selected value and its time
+    selected: Option<(String, i64)>,
+}
+
+impl CollatedUtf8Selector {
+    // Rows with the same value are always resolved by choosing the
+    // earliest time
+    fn new(
+        selector_type: SelectorType,
+        _tie_break: SelectorTieBreak,
+        _value_type: &DataType,
+    ) -> Self {
+        Self {
+            selector_type,
+            // the CLDR root collation order, breaking ties between
+            // strings with the same collation key byte-wise so that
+            // only identical strings are equal
+            collator: Collator::new(Tailoring::Cldr(Locale::Root), true, true),
+            selected: None,
+        }
+    }
+
+    fn value_data_type(&self) -> DataType {
+        DataType::Utf8
+    }
+
+    fn scalar_values(&self) -> (ScalarValue, ScalarValue) {
+        (
+            ScalarValue::Utf8(self.selected.as_ref().map(|(value, _)| value.clone())),
+            ScalarValue::TimestampNanosecond(self.selected.as_ref().map(|(_, time)| *time), None),
+        )
+    }
+
+    fn datafusion_state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let (value, time) = self.scalar_values();
+        Ok(vec![
+            AggregateState::Scalar(value),
+            AggregateState::Scalar(time),
+        ])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let (value, time) = self.scalar_values();
+        Ok(make_scalar_struct(vec![value, time]))
+    }
+
+    fn update_batch(&mut self, value_arr: &ArrayRef, time_arr: &ArrayRef) -> DataFusionResult<()> {
+        let value_arr = value_arr
+            .as_any()
+            .downcast_ref::<StringArray>()
+            // the input type arguments should be ensured by datafusion
+            .expect("First argument was value");
+
+        let time_arr = time_arr
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Second argument was time");
+
+        for idx in 0..value_arr.len() {
+            if value_arr.is_null(idx) || time_arr.is_null(idx) {
+                continue;
+            }
+            self.update(value_arr.value(idx), time_arr.value(idx));
+        }
+        Ok(())
+    }
+
+    /// Update the selected value with a row seen after those already seen
+    fn update(&mut self, value: &str, time: i64) {
+        let (current_value, current_time) = match &mut self.selected {
+            Some(selected) => selected,
+            None => {
+                self.selected = Some((value.to_owned(), time));
+                return;
+            }
+        };
+
+        let ordering = self.collator.collate(value, current_value.as_str());
+        match (self.selector_type, ordering) {
+            (SelectorType::Min, Ordering::Less) | (SelectorType::Max, Ordering::Greater) => {
+                self.selected = Some((value.to_owned(), time));
+            }
+            (_, Ordering::Equal) => *current_time = time.min(*current_time),
+            _ => {}
+        }
+    }
+}
+
+make_delegating_selector!(
+    Utf8CollatedMinSelector,
+    CollatedUtf8Selector,
+    SelectorType::Min
+);
+make_delegating_selector!(
+    Utf8CollatedMaxSelector,
+    CollatedUtf8Selector,
+    SelectorType::Max
+);
@@ -0,0 +1,219 @@
+//! Implementation of the `selector_median` function, which returns
+//! the middle value of the sorted input as a float.
+
+use std::{cmp::Ordering, mem::size_of, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, ListArray},
+    compute::cast,
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+
+use super::{
+    memory::{AccumulatorMemory, MemoryReservation},
+    ReturnTypeFunction, StateTypeFactory,
+};
+
+/// Create a User Defined Aggregate Function (UDAF) for median(value),
+/// whose accumulators track their memory with `memory`
+pub(super) fn make_median_uda(name: &str, memory: &Arc<AccumulatorMemory>) -> AggregateUDF {
+    let input_signature = Signature::one_of(
+        vec![TypeSignature::Uniform(
+            1,
+            vec![DataType::Float64, DataType::Int64, DataType::UInt64],
+        )],
+        Volatility::Stable,
+    );
+
+    // The median of an even number of values is the mean of the
+    // middle two, so is always a float
+    let return_type_func: ReturnTypeFunction =
+        Arc::new(move |_arg_types| Ok(Arc::new(DataType::Float64)));
+
+    // The state is all the (non null) values seen
+    let state_type_factory: StateTypeFactory = Arc::new(move |_return_type| {
+        Ok(Arc::new(vec![DataType::List(Box::new(Field::new(
+            "item",
+            DataType::Float64,
+            true,
+        )))]))
+    });
+
+    let memory = Arc::clone(memory);
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |_return_type| {
+        let accumulator: Box<dyn Accumulator> = Box::new(MedianAccumulator::new(&memory));
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// Accumulator that keeps all the values seen
+#[derive(Debug)]
+struct MedianAccumulator {
+    values: Vec<f64>,
+
+    /// The memory reserved for `values`
+    reservation: MemoryReservation,
+}
+
+impl MedianAccumulator {
+    fn new(memory: &Arc<AccumulatorMemory>) -> Self {
+        Self {
+            values: vec![],
+            reservation: MemoryReservation::new(memory),
+        }
+    }
+
+    /// Add the non null values in `value_arr`, converted to floats
+    fn add_values(&mut self, value_arr: &ArrayRef) -> DataFusionResult<()> {
+        let value_arr = cast(value_arr, &DataType::Float64)?;
+        let value_arr = value_arr
+            .as_any()
+            .downcast_ref::<Float64Array>()
+            .expect("cast to f64");
+
+        self.values.extend(value_arr.iter().flatten());
+
+        let size = self.size();
+        self.reservation.try_resize("median", size)
+    }
+
+    /// The memory used by the accumulator, in bytes
+    fn size(&self) -> usize {
+        size_of::<Self>() + self.values.capacity() * size_of::<f64>()
+    }
+}
+
+impl Accumulator for MedianAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let values = self
+            .values
+            .iter()
+            .map(|v| ScalarValue::Float64(Some(*v)))
+            .collect();
+
+        Ok(vec![AggregateState::Scalar(ScalarValue::new_list(
+            Some(values),
+            DataType::Float64,
+        ))])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let mut values = self.values.clone();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let len = values.len();
+        let median = match len {
+            0 => None,
+            _ if len % 2 == 0 => Some((values[len / 2 - 1] + values[len / 2]) / 2.0),
+            _ => Some(values[len / 2]),
+        };
+
+        Ok(ScalarValue::Float64(median))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if values.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 1 argument passed to median function but got {}",
+                values.len()
+            )));
+        }
+
+        self.add_values(&values[0])
+    }
+
+    // Each row of the state is a list of values previously produced
+    // by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        let value_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("State was a list of values");
+
+        for idx in 0..value_lists.len() {
+            if !value_lists.is_null(idx) {
+                self.add_values(&value_lists.value(idx))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        selectors::selector_median,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_selector_median_f64() {
+        run_case(
+            selector_median(&unbounded_memory()).call(vec![col("f64_value")]),
+            vec![
+                "+------------------------------+",
+                "| selector_median(t.f64_value) |",
+                "+------------------------------+",
+                "| 3                            |",
+                "+------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_median_i64() {
+        run_case(
+            selector_median(&unbounded_memory()).call(vec![col("i64_value")]),
+            vec![
+                "+------------------------------+",
+                "| selector_median(t.i64_value) |",
+                "+------------------------------+",
+                "| 30                           |",
+                "+------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_median_u64() {
+        run_case(
+            selector_median(&unbounded_memory()).call(vec![col("u64_value")]),
+            vec![
+                "+------------------------------+",
+                "| selector_median(t.u64_value) |",
+                "+------------------------------+",
+                "| 30                           |",
+                "+------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
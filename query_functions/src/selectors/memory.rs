@@ -0,0 +1,182 @@
+//! Memory accounting for the state of the selector accumulators.
+//!
+//! Most selectors keep a fixed amount of state, but `top`/`bottom`,
+//! the series functions (such as `derivative`), `distinct`, `median`
+//! and `mode` keep state that grows with their input. DataFusion does
+//! not (yet) ask accumulators for the size of their state, so these
+//! accumulators record the memory they use with the memory manager of
+//! the DataFusion [`RuntimeEnv`] their function was created with after
+//! each batch, failing the query with
+//! [`DataFusionError::ResourcesExhausted`] rather than growing without
+//! bound.
+//!
+//! The memory is tracked alongside the memory used by the DataFusion
+//! operators of the same runtime, so the state of the accumulators and
+//! the sorts that spill to disk share a single memory limit.
+
+use std::{mem::size_of, sync::Arc};
+
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::runtime_env::RuntimeEnv,
+    scalar::ScalarValue,
+};
+
+/// The memory of a DataFusion [`RuntimeEnv`] available to the state
+/// of the selector accumulators of the queries using that runtime
+#[derive(Debug)]
+pub struct AccumulatorMemory {
+    runtime: Arc<RuntimeEnv>,
+    limit: usize,
+}
+
+impl AccumulatorMemory {
+    /// Track the state of the accumulators with the memory manager of
+    /// `runtime`, whose memory limit is `limit` bytes
+    pub fn new(runtime: Arc<RuntimeEnv>, limit: usize) -> Self {
+        Self { runtime, limit }
+    }
+
+    /// The memory limit of the runtime, in bytes
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// The memory tracked by the memory manager of the runtime, in
+    /// bytes
+    pub fn used(&self) -> usize {
+        self.runtime.memory_manager.get_tracker_total()
+    }
+
+    /// Track `additional` bytes if doing so does not exceed the limit
+    fn try_grow(&self, additional: usize) -> bool {
+        self.runtime.grow_tracker_usage(additional);
+        if self.used() > self.limit {
+            self.runtime.shrink_tracker_usage(additional);
+            return false;
+        }
+        true
+    }
+
+    fn shrink(&self, freed: usize) {
+        self.runtime.shrink_tracker_usage(freed);
+    }
+}
+
+/// The memory tracked for one accumulator, which is released when
+/// dropped
+#[derive(Debug)]
+pub(crate) struct MemoryReservation {
+    memory: Arc<AccumulatorMemory>,
+    size: usize,
+}
+
+impl MemoryReservation {
+    pub(crate) fn new(memory: &Arc<AccumulatorMemory>) -> Self {
+        Self {
+            memory: Arc::clone(memory),
+            size: 0,
+        }
+    }
+
+    /// Change the reservation to `size` bytes for the state of the
+    /// function `name`, returning an error if the runtime does not
+    /// have enough memory left
+    pub(crate) fn try_resize(&mut self, name: &str, size: usize) -> DataFusionResult<()> {
+        if size <= self.size {
+            self.memory.shrink(self.size - size);
+        } else if !self.memory.try_grow(size - self.size) {
+            return Err(DataFusionError::ResourcesExhausted(format!(
+                "Failed to allocate {} bytes for the state of {}: {} of {} bytes used",
+                size - self.size,
+                name,
+                self.memory.used(),
+                self.memory.limit(),
+            )));
+        }
+        self.size = size;
+        Ok(())
+    }
+}
+
+impl Drop for MemoryReservation {
+    fn drop(&mut self) {
+        self.memory.shrink(self.size);
+    }
+}
+
+/// The memory allocated by `value` outside of the [`ScalarValue`]
+/// itself, such as the contents of strings and lists
+pub(crate) fn scalar_heap_size(value: &ScalarValue) -> usize {
+    match value {
+        ScalarValue::Utf8(Some(s)) | ScalarValue::LargeUtf8(Some(s)) => s.capacity(),
+        ScalarValue::Binary(Some(b)) | ScalarValue::LargeBinary(Some(b)) => b.capacity(),
+        ScalarValue::List(Some(values), _) | ScalarValue::Struct(Some(values), _) => {
+            values.capacity() * size_of::<ScalarValue>()
+                + values.iter().map(scalar_heap_size).sum::<usize>()
+        }
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::execution::runtime_env::RuntimeConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_reservation() {
+        let runtime = Arc::new(RuntimeEnv::new(RuntimeConfig::new()).unwrap());
+        let memory = Arc::new(AccumulatorMemory::new(Arc::clone(&runtime), 100));
+
+        let mut a = MemoryReservation::new(&memory);
+        let mut b = MemoryReservation::new(&memory);
+
+        a.try_resize("a", 60).unwrap();
+        assert_eq!(memory.used(), 60);
+
+        let err = b.try_resize("b", 50).unwrap_err();
+        assert!(matches!(err, DataFusionError::ResourcesExhausted(_)));
+        assert_eq!(
+            err.to_string(),
+            "Resources exhausted: Failed to allocate 50 bytes for the state of b: 60 of 100 bytes used"
+        );
+        assert_eq!(memory.used(), 60);
+
+        a.try_resize("a", 20).unwrap();
+        b.try_resize("b", 50).unwrap();
+        assert_eq!(memory.used(), 70);
+
+        drop(a);
+        assert_eq!(memory.used(), 50);
+        drop(b);
+        assert_eq!(memory.used(), 0);
+
+        // the limit is shared with the other consumers of the runtime
+        runtime.grow_tracker_usage(90);
+        let mut c = MemoryReservation::new(&memory);
+        let err = c.try_resize("c", 20).unwrap_err();
+        assert!(matches!(err, DataFusionError::ResourcesExhausted(_)));
+        assert_eq!(memory.used(), 90);
+        runtime.shrink_tracker_usage(90);
+        c.try_resize("c", 20).unwrap();
+        drop(c);
+        assert_eq!(memory.used(), 0);
+    }
+
+    #[test]
+    fn test_scalar_heap_size() {
+        assert_eq!(scalar_heap_size(&ScalarValue::Float64(Some(1.0))), 0);
+        assert_eq!(scalar_heap_size(&ScalarValue::Utf8(None)), 0);
+
+        let s = String::with_capacity(10);
+        assert_eq!(scalar_heap_size(&ScalarValue::Utf8(Some(s))), 10);
+
+        let list = ScalarValue::new_list(
+            Some(vec![ScalarValue::Utf8(Some(String::with_capacity(5)))]),
+            arrow::datatypes::DataType::Utf8,
+        );
+        assert_eq!(scalar_heap_size(&list), size_of::<ScalarValue>() + 5);
+    }
+}
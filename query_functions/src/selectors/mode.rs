@@ -0,0 +1,309 @@
+//! Implementation of the `selector_mode` function, which returns the
+//! most frequent value.
+
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::Entry, HashMap},
+    mem::size_of,
+    sync::Arc,
+};
+
+use arrow::{
+    array::{Array, ArrayRef, ListArray, UInt64Array},
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+
+use super::{
+    memory::{scalar_heap_size, AccumulatorMemory, MemoryReservation},
+    ReturnTypeFunction, StateTypeFactory,
+};
+
+/// Create a User Defined Aggregate Function (UDAF) for mode(value),
+/// whose accumulators track their memory with `memory`
+pub(super) fn make_mode_uda(name: &str, memory: &Arc<AccumulatorMemory>) -> AggregateUDF {
+    let input_signature = Signature::one_of(
+        vec![TypeSignature::Uniform(
+            1,
+            vec![
+                DataType::Float64,
+                DataType::Int64,
+                DataType::UInt64,
+                DataType::Utf8,
+                DataType::Boolean,
+            ],
+        )],
+        Volatility::Stable,
+    );
+
+    // The mode is one of the input values, so has the same type
+    let return_type_func: ReturnTypeFunction =
+        Arc::new(move |arg_types| Ok(Arc::new(arg_types[0].clone())));
+
+    // The state is the distinct values seen and how many times each was seen
+    let state_type_factory: StateTypeFactory = Arc::new(move |return_type| {
+        Ok(Arc::new(vec![
+            make_list_type(return_type.clone()),
+            make_list_type(DataType::UInt64),
+        ]))
+    });
+
+    let memory = Arc::clone(memory);
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |return_type| {
+        let accumulator: Box<dyn Accumulator> =
+            Box::new(ModeAccumulator::new(return_type.clone(), &memory));
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// A list of `item_type`, as produced by [`ScalarValue::new_list`]
+fn make_list_type(item_type: DataType) -> DataType {
+    DataType::List(Box::new(Field::new("item", item_type, true)))
+}
+
+/// Accumulator that counts the occurrences of each distinct value
+#[derive(Debug)]
+struct ModeAccumulator {
+    value_type: DataType,
+
+    counts: HashMap<ScalarValue, u64>,
+
+    /// The memory allocated by the values in `counts` outside of
+    /// `counts` itself
+    values_heap_size: usize,
+
+    /// The memory reserved for `counts`
+    reservation: MemoryReservation,
+}
+
+impl ModeAccumulator {
+    fn new(value_type: DataType, memory: &Arc<AccumulatorMemory>) -> Self {
+        Self {
+            value_type,
+            counts: HashMap::new(),
+            values_heap_size: 0,
+            reservation: MemoryReservation::new(memory),
+        }
+    }
+
+    /// The memory used by the accumulator, in bytes
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.counts.capacity() * (size_of::<ScalarValue>() + size_of::<u64>())
+            + self.values_heap_size
+    }
+
+    /// Add `count` occurrences of each non null value in `value_arr`
+    fn add_values(
+        &mut self,
+        value_arr: &ArrayRef,
+        count: impl Fn(usize) -> u64,
+    ) -> DataFusionResult<()> {
+        for idx in 0..value_arr.len() {
+            if value_arr.is_null(idx) {
+                continue;
+            }
+            let value = ScalarValue::try_from_array(value_arr, idx)?;
+            match self.counts.entry(value) {
+                Entry::Occupied(entry) => *entry.into_mut() += count(idx),
+                Entry::Vacant(entry) => {
+                    self.values_heap_size += scalar_heap_size(entry.key());
+                    entry.insert(count(idx));
+                }
+            }
+        }
+
+        let size = self.size();
+        self.reservation.try_resize("mode", size)
+    }
+}
+
+impl Accumulator for ModeAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let (values, counts): (Vec<_>, Vec<_>) = self
+            .counts
+            .iter()
+            .map(|(value, count)| (value.clone(), ScalarValue::UInt64(Some(*count))))
+            .unzip();
+
+        Ok(vec![
+            AggregateState::Scalar(ScalarValue::new_list(Some(values), self.value_type.clone())),
+            AggregateState::Scalar(ScalarValue::new_list(Some(counts), DataType::UInt64)),
+        ])
+    }
+
+    // Returns the most frequent value. If multiple values are equally
+    // frequent, the smallest is chosen so the result does not depend
+    // on the order of the input
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let mode = self
+            .counts
+            .iter()
+            .max_by(|(value_a, count_a), (value_b, count_b)| {
+                count_a
+                    .cmp(count_b)
+                    .then_with(|| value_b.partial_cmp(value_a).unwrap_or(Ordering::Equal))
+            })
+            .map(|(value, _)| value.clone());
+
+        match mode {
+            Some(mode) => Ok(mode),
+            None => ScalarValue::try_from(&self.value_type),
+        }
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if values.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 1 argument passed to mode function but got {}",
+                values.len()
+            )));
+        }
+
+        self.add_values(&values[0], |_| 1)
+    }
+
+    // Each row of the states is a list of distinct values and a list
+    // of how often each was seen, previously produced by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        if states.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 states passed to mode function but got {}",
+                states.len()
+            )));
+        }
+
+        let value_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("First state was a list of values");
+
+        let count_lists = states[1]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("Second state was a list of counts");
+
+        for idx in 0..value_lists.len() {
+            if value_lists.is_null(idx) || count_lists.is_null(idx) {
+                continue;
+            }
+
+            let counts = count_lists.value(idx);
+            let counts = counts
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .expect("list of counts");
+
+            self.add_values(&value_lists.value(idx), |i| counts.value(i))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        selectors::selector_mode,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_selector_mode_f64() {
+        run_case(
+            selector_mode(&unbounded_memory()).call(vec![col("f64_value")]),
+            vec![
+                "+----------------------------+",
+                "| selector_mode(t.f64_value) |",
+                "+----------------------------+",
+                "| 1                          |",
+                "+----------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_mode_i64() {
+        run_case(
+            selector_mode(&unbounded_memory()).call(vec![col("i64_value")]),
+            vec![
+                "+----------------------------+",
+                "| selector_mode(t.i64_value) |",
+                "+----------------------------+",
+                "| 10                         |",
+                "+----------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_mode_u64() {
+        run_case(
+            selector_mode(&unbounded_memory()).call(vec![col("u64_value")]),
+            vec![
+                "+----------------------------+",
+                "| selector_mode(t.u64_value) |",
+                "+----------------------------+",
+                "| 10                         |",
+                "+----------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_mode_string() {
+        run_case(
+            selector_mode(&unbounded_memory()).call(vec![col("string_value")]),
+            vec![
+                "+-------------------------------+",
+                "| selector_mode(t.string_value) |",
+                "+-------------------------------+",
+                "| a_one                         |",
+                "+-------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_mode_bool() {
+        run_case(
+            selector_mode(&unbounded_memory()).call(vec![col("bool_value")]),
+            vec![
+                "+-----------------------------+",
+                "| selector_mode(t.bool_value) |",
+                "+-----------------------------+",
+                "| false                       |",
+                "+-----------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,258 @@
+//! Implementation of the `selector_spread` function, which returns
+//! the difference between the largest and smallest values.
+
+use std::{cmp::Ordering, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Float64Array, Int64Array, UInt64Array},
+    compute::kernels::aggregate::{max, min},
+    datatypes::DataType,
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+
+use super::{ReturnTypeFunction, StateTypeFactory};
+
+/// Create a User Defined Aggregate Function (UDAF) for spread(value)
+pub(super) fn make_spread_uda(name: &str) -> AggregateUDF {
+    let input_signature = Signature::one_of(
+        vec![TypeSignature::Uniform(
+            1,
+            vec![DataType::Float64, DataType::Int64, DataType::UInt64],
+        )],
+        Volatility::Stable,
+    );
+
+    // The spread has the same type as the input values
+    let return_type_func: ReturnTypeFunction =
+        Arc::new(move |arg_types| Ok(Arc::new(arg_types[0].clone())));
+
+    // The state is the smallest and largest values seen
+    let state_type_factory: StateTypeFactory =
+        Arc::new(move |return_type| Ok(Arc::new(vec![return_type.clone(), return_type.clone()])));
+
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |return_type| {
+        let accumulator: Box<dyn Accumulator> =
+            Box::new(SpreadAccumulator::new(return_type.clone()));
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// Compute the smallest and largest values in `arr` with the arrow
+/// aggregate kernels, as `ScalarValue`s of the same type
+macro_rules! array_min_max {
+    ($ARRAY_TYPE:ident, $SCALAR_VARIANT:ident, $ARR:expr) => {{
+        let arr = $ARR
+            .as_any()
+            .downcast_ref::<$ARRAY_TYPE>()
+            // the input type arguments should be ensured by datafusion
+            .expect("value array of the spread type");
+        (
+            ScalarValue::$SCALAR_VARIANT(min(arr)),
+            ScalarValue::$SCALAR_VARIANT(max(arr)),
+        )
+    }};
+}
+
+/// Accumulator that tracks both the smallest and largest values, so
+/// partial results from different partitions can be combined
+#[derive(Debug)]
+struct SpreadAccumulator {
+    value_type: DataType,
+
+    min: Option<ScalarValue>,
+
+    max: Option<ScalarValue>,
+}
+
+impl SpreadAccumulator {
+    fn new(value_type: DataType) -> Self {
+        Self {
+            value_type,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// Update the extremes with the non null values in `value_arr`
+    fn update_min_max(&mut self, value_arr: &ArrayRef) -> DataFusionResult<()> {
+        let (arr_min, arr_max) = match &self.value_type {
+            DataType::Float64 => array_min_max!(Float64Array, Float64, value_arr),
+            DataType::Int64 => array_min_max!(Int64Array, Int64, value_arr),
+            DataType::UInt64 => array_min_max!(UInt64Array, UInt64, value_arr),
+            t => {
+                return Err(DataFusionError::Internal(format!(
+                    "Unexpected value type for spread function: {:?}",
+                    t
+                )))
+            }
+        };
+
+        Self::update_extreme(&mut self.min, arr_min, Ordering::Less);
+        Self::update_extreme(&mut self.max, arr_max, Ordering::Greater);
+        Ok(())
+    }
+
+    /// Replace `current` with `candidate` if `candidate` is not null
+    /// and compares as `wanted` to `current`
+    fn update_extreme(current: &mut Option<ScalarValue>, candidate: ScalarValue, wanted: Ordering) {
+        if candidate.is_null() {
+            return;
+        }
+
+        let replace = match current {
+            Some(current) => candidate.partial_cmp(current) == Some(wanted),
+            None => true,
+        };
+
+        if replace {
+            *current = Some(candidate);
+        }
+    }
+
+    /// Return `self.min` or `self.max` as a value, using a typed null
+    /// if no value was seen
+    fn extreme_or_null(&self, extreme: &Option<ScalarValue>) -> DataFusionResult<ScalarValue> {
+        match extreme {
+            Some(v) => Ok(v.clone()),
+            None => ScalarValue::try_from(&self.value_type),
+        }
+    }
+}
+
+impl Accumulator for SpreadAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        Ok(vec![
+            AggregateState::Scalar(self.extreme_or_null(&self.min)?),
+            AggregateState::Scalar(self.extreme_or_null(&self.max)?),
+        ])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let (min, max) = match (&self.min, &self.max) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return ScalarValue::try_from(&self.value_type),
+        };
+
+        match (min, max) {
+            (ScalarValue::Float64(Some(min)), ScalarValue::Float64(Some(max))) => {
+                Ok(ScalarValue::Float64(Some(max - min)))
+            }
+            (ScalarValue::Int64(Some(min)), ScalarValue::Int64(Some(max))) => max
+                .checked_sub(*min)
+                .map(|v| ScalarValue::Int64(Some(v)))
+                .ok_or_else(|| {
+                    DataFusionError::Execution(format!(
+                        "Overflow computing spread of {} and {}",
+                        max, min
+                    ))
+                }),
+            (ScalarValue::UInt64(Some(min)), ScalarValue::UInt64(Some(max))) => {
+                Ok(ScalarValue::UInt64(Some(max - min)))
+            }
+            (min, max) => Err(DataFusionError::Internal(format!(
+                "Unexpected values for spread function: {:?} and {:?}",
+                min, max
+            ))),
+        }
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if values.len() != 1 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 1 argument passed to spread function but got {}",
+                values.len()
+            )));
+        }
+
+        self.update_min_max(&values[0])
+    }
+
+    // The states are the arrays of smallest and largest values
+    // previously produced by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        if states.len() != 2 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 2 states passed to spread function but got {}",
+                states.len()
+            )));
+        }
+
+        self.update_min_max(&states[0])?;
+        self.update_min_max(&states[1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{selectors::selector_spread, test_util::run_case};
+
+    #[tokio::test]
+    async fn test_selector_spread_f64() {
+        run_case(
+            selector_spread().call(vec![col("f64_value")]),
+            vec![
+                "+------------------------------+",
+                "| selector_spread(t.f64_value) |",
+                "+------------------------------+",
+                "| 4                            |",
+                "+------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_spread_i64() {
+        run_case(
+            selector_spread().call(vec![col("i64_value")]),
+            vec![
+                "+------------------------------+",
+                "| selector_spread(t.i64_value) |",
+                "+------------------------------+",
+                "| 40                           |",
+                "+------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_selector_spread_u64() {
+        run_case(
+            selector_spread().call(vec![col("u64_value")]),
+            vec![
+                "+------------------------------+",
+                "| selector_spread(t.u64_value) |",
+                "+------------------------------+",
+                "| 40                           |",
+                "+------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,546 @@
+//! Implementation of the `selector_top` and `selector_bottom`
+//! functions, which select the N largest (or smallest) values along
+//! with the time each value occurred.
+
+use std::{cmp::Ordering, collections::BinaryHeap, mem::size_of, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, Int64Array, ListArray, TimestampNanosecondArray},
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+use schema::TIME_DATA_TYPE;
+
+use super::{
+    make_struct_fields,
+    memory::{scalar_heap_size, AccumulatorMemory, MemoryReservation},
+    ReturnTypeFunction, StateTypeFactory,
+};
+
+/// Which end of the value range is selected
+#[derive(Debug, Clone, Copy)]
+pub(super) enum TopType {
+    /// Select the largest values
+    Top,
+    /// Select the smallest values
+    Bottom,
+}
+
+/// Create a User Defined Aggregate Function (UDAF) for `top_type`,
+/// whose accumulators track their memory with `memory`
+pub(super) fn make_top_uda(
+    name: &str,
+    top_type: TopType,
+    memory: &Arc<AccumulatorMemory>,
+) -> AggregateUDF {
+    let input_signature = Signature::one_of(
+        vec![
+            TypeSignature::Exact(vec![DataType::Float64, TIME_DATA_TYPE(), DataType::Int64]),
+            TypeSignature::Exact(vec![DataType::Int64, TIME_DATA_TYPE(), DataType::Int64]),
+            TypeSignature::Exact(vec![DataType::UInt64, TIME_DATA_TYPE(), DataType::Int64]),
+        ],
+        Volatility::Stable,
+    );
+
+    // The inputs are (value, time, n) and the output is a list of
+    // structs with a 'value' and 'time' field
+    let return_type_func: ReturnTypeFunction = Arc::new(move |arg_types| {
+        assert_eq!(
+            arg_types.len(),
+            3,
+            "top/bottom selector expected exactly 3 arguments, got {}",
+            arg_types.len()
+        );
+        Ok(Arc::new(make_list_type(make_entry_type(&arg_types[0]))))
+    });
+
+    let state_type_factory: StateTypeFactory = Arc::new(move |return_type| {
+        let value_type = value_data_type_from_return_data_type(return_type)?;
+        Ok(Arc::new(vec![
+            make_list_type(value_type),
+            make_list_type(TIME_DATA_TYPE()),
+            DataType::Int64,
+        ]))
+    });
+
+    let memory = Arc::clone(memory);
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |return_type| {
+        let value_type = value_data_type_from_return_data_type(return_type)?;
+        let accumulator: Box<dyn Accumulator> =
+            Box::new(TopAccumulator::new(top_type, value_type, &memory));
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// The type of each selected entry: struct {value, time}
+fn make_entry_type(value_type: &DataType) -> DataType {
+    DataType::Struct(make_struct_fields(value_type.clone(), &[]))
+}
+
+/// A list of `item_type`, as produced by [`ScalarValue::new_list`]
+fn make_list_type(item_type: DataType) -> DataType {
+    DataType::List(Box::new(Field::new("item", item_type, true)))
+}
+
+/// Return the value type given the List<Struct{value, time}> output
+/// data type
+fn value_data_type_from_return_data_type(return_type: &DataType) -> DataFusionResult<DataType> {
+    match return_type {
+        DataType::List(item) => match item.data_type() {
+            DataType::Struct(fields) => Ok(fields[0].data_type().clone()),
+            t => Err(DataFusionError::Internal(format!(
+                "Unexpected item type for top/bottom selector: {:?}",
+                t
+            ))),
+        },
+        t => Err(DataFusionError::Internal(format!(
+            "Unexpected return type for top/bottom selector: {:?}",
+            t
+        ))),
+    }
+}
+
+/// A value selected by top/bottom and the time it occurred
+#[derive(Debug, Clone)]
+struct HeapEntry {
+    value: ScalarValue,
+    time: i64,
+    top_type: TopType,
+}
+
+/// Entries are ordered so that "better" entries compare as less
+/// than "worse" ones. Thus the root of the (max) [`BinaryHeap`] is
+/// always the entry that is evicted first.
+///
+/// If multiple entries have the same value, the one with the
+/// earliest time is considered better.
+///
+/// Float values are compared with [`f64::total_cmp`], so that NaN has
+/// a consistent place in the order (greater than all other values)
+/// rather than comparing equal to everything, which would corrupt the
+/// heap.
+impl Ord for HeapEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let value_ordering = match (&self.value, &other.value) {
+            (ScalarValue::Float64(Some(a)), ScalarValue::Float64(Some(b))) => a.total_cmp(b),
+            (a, b) => a.partial_cmp(b).unwrap_or(Ordering::Equal),
+        };
+
+        let value_ordering = match self.top_type {
+            TopType::Top => value_ordering.reverse(),
+            TopType::Bottom => value_ordering,
+        };
+
+        value_ordering.then_with(|| self.time.cmp(&other.time))
+    }
+}
+
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for HeapEntry {}
+
+/// Accumulator that keeps the N best (value, time) pairs seen so far
+#[derive(Debug)]
+struct TopAccumulator {
+    top_type: TopType,
+
+    value_type: DataType,
+
+    /// The number of values to keep. Not known until the first
+    /// batch is seen, as it is passed as an argument
+    n: Option<usize>,
+
+    heap: BinaryHeap<HeapEntry>,
+
+    /// The memory reserved for `heap`
+    reservation: MemoryReservation,
+}
+
+impl TopAccumulator {
+    fn new(top_type: TopType, value_type: DataType, memory: &Arc<AccumulatorMemory>) -> Self {
+        Self {
+            top_type,
+            value_type,
+            n: None,
+            heap: BinaryHeap::new(),
+            reservation: MemoryReservation::new(memory),
+        }
+    }
+
+    /// The memory used by the accumulator, in bytes
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.heap.capacity() * size_of::<HeapEntry>()
+            + self
+                .heap
+                .iter()
+                .map(|e| scalar_heap_size(&e.value))
+                .sum::<usize>()
+    }
+
+    /// Reserve the memory currently used by the accumulator
+    fn update_reservation(&mut self) -> DataFusionResult<()> {
+        let size = self.size();
+        self.reservation.try_resize("top/bottom selector", size)
+    }
+
+    /// Record the number of values to keep from the first non null
+    /// value in `n_arr`
+    fn update_n(&mut self, n_arr: &ArrayRef) -> DataFusionResult<()> {
+        let n_arr = n_arr
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Third argument was n");
+
+        if let Some(n) = n_arr.iter().flatten().next() {
+            let n = usize::try_from(n).map_err(|_| {
+                DataFusionError::Execution(format!(
+                    "top/bottom selector expected a non negative number of values, got {}",
+                    n
+                ))
+            })?;
+            self.n = Some(n);
+        }
+        Ok(())
+    }
+
+    /// Add the non null values in `value_arr` (and corresponding
+    /// times in `time_arr`), evicting the worst entries to keep at
+    /// most `n`
+    fn update_values(&mut self, value_arr: &ArrayRef, time_arr: &ArrayRef) -> DataFusionResult<()> {
+        let n = match self.n {
+            Some(n) => n,
+            // no values seen yet
+            None => return Ok(()),
+        };
+
+        let time_arr = time_arr
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Second argument was time");
+
+        for idx in 0..value_arr.len() {
+            if value_arr.is_null(idx) || time_arr.is_null(idx) {
+                continue;
+            }
+
+            self.heap.push(HeapEntry {
+                value: ScalarValue::try_from_array(value_arr, idx)?,
+                time: time_arr.value(idx),
+                top_type: self.top_type,
+            });
+
+            if self.heap.len() > n {
+                self.heap.pop();
+            }
+        }
+        Ok(())
+    }
+
+    /// Return the entries currently held, best first
+    fn sorted_entries(&self) -> Vec<&HeapEntry> {
+        let mut entries: Vec<_> = self.heap.iter().collect();
+        entries.sort();
+        entries
+    }
+}
+
+impl Accumulator for TopAccumulator {
+    // The state is the list of values, the list of their times, and n
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let entries = self.sorted_entries();
+
+        let values = entries.iter().map(|e| e.value.clone()).collect();
+        let times = entries
+            .iter()
+            .map(|e| ScalarValue::TimestampNanosecond(Some(e.time), None))
+            .collect();
+
+        Ok(vec![
+            AggregateState::Scalar(ScalarValue::new_list(Some(values), self.value_type.clone())),
+            AggregateState::Scalar(ScalarValue::new_list(Some(times), TIME_DATA_TYPE())),
+            AggregateState::Scalar(ScalarValue::Int64(self.n.map(|n| n as i64))),
+        ])
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        let entries = self
+            .sorted_entries()
+            .into_iter()
+            .map(|e| {
+                ScalarValue::Struct(
+                    Some(vec![
+                        e.value.clone(),
+                        ScalarValue::TimestampNanosecond(Some(e.time), None),
+                    ]),
+                    Box::new(make_struct_fields(self.value_type.clone(), &[])),
+                )
+            })
+            .collect();
+
+        Ok(ScalarValue::new_list(
+            Some(entries),
+            make_entry_type(&self.value_type),
+        ))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if values.len() != 3 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 3 arguments passed to top/bottom selector function but got {}",
+                values.len()
+            )));
+        }
+
+        self.update_n(&values[2])?;
+        self.update_values(&values[0], &values[1])?;
+        self.update_reservation()
+    }
+
+    // Each row of the states is a list of values and a list of times
+    // previously produced by `state`
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        if states.len() != 3 {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected 3 states passed to top/bottom selector function but got {}",
+                states.len()
+            )));
+        }
+
+        self.update_n(&states[2])?;
+
+        let value_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("First state was a list of values");
+
+        let time_lists = states[1]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("Second state was a list of times");
+
+        for idx in 0..value_lists.len() {
+            if value_lists.is_null(idx) || time_lists.is_null(idx) {
+                continue;
+            }
+            self.update_values(&value_lists.value(idx), &time_lists.value(idx))?;
+        }
+        self.update_reservation()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::Float64Array;
+    use datafusion::prelude::{col, lit};
+
+    use super::*;
+    use crate::{
+        selectors::{struct_selector_bottom, struct_selector_top},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_struct_selector_top_f64() {
+        run_case(
+            struct_selector_top(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(2i64)])
+                .alias("top"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------+",
+                "| top                                                                                                  |",
+                "+------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000002}] |",
+                "+------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_top_i64() {
+        run_case(
+            struct_selector_top(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(2i64)])
+                .alias("top"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| top                                                                                                    |",
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}] |",
+                "+--------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_top_u64() {
+        run_case(
+            struct_selector_top(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time"), lit(2i64)])
+                .alias("top"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| top                                                                                                    |",
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}] |",
+                "+--------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_top_i64_more_than_available() {
+        run_case(
+            struct_selector_top(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(10i64)])
+                .alias("top"),
+            vec![
+                "+-----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| top                                                                                                                                                                                                                                                             |",
+                "+-----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 30, \"time\": 1970-01-01 00:00:00.000006}, {\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}] |",
+                "+-----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_bottom_f64() {
+        run_case(
+            struct_selector_bottom(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(2i64)])
+                .alias("bottom"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------+",
+                "| bottom                                                                                               |",
+                "+------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}] |",
+                "+------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_bottom_i64() {
+        run_case(
+            struct_selector_bottom(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(2i64)])
+                .alias("bottom"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| bottom                                                                                                 |",
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}] |",
+                "+--------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_bottom_u64() {
+        run_case(
+            struct_selector_bottom(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time"), lit(2i64)])
+                .alias("bottom"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| bottom                                                                                                 |",
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}] |",
+                "+--------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_struct_selector_bottom_i64_more_than_available() {
+        run_case(
+            struct_selector_bottom(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(10i64)])
+                .alias("bottom"),
+            vec![
+                "+-----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| bottom                                                                                                                                                                                                                                                          |",
+                "+-----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 30, \"time\": 1970-01-01 00:00:00.000006}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}] |",
+                "+-----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[test]
+    fn test_top_bottom_nan() {
+        // The selected entries do not depend on the order in which NaN
+        // is seen
+        let inputs = [
+            (vec![1.0, f64::NAN, 3.0, 2.0], vec![1, 2, 3, 4]),
+            (vec![2.0, 3.0, f64::NAN, 1.0], vec![4, 3, 2, 1]),
+        ];
+
+        for (values, times) in inputs {
+            let args: Vec<ArrayRef> = vec![
+                Arc::new(Float64Array::from(values)),
+                Arc::new(TimestampNanosecondArray::from_vec(times, None)),
+                Arc::new(Int64Array::from(vec![2; 4])),
+            ];
+
+            // NaN is greater than all other values
+            let mut top = TopAccumulator::new(TopType::Top, DataType::Float64, &unbounded_memory());
+            top.update_batch(&args).unwrap();
+            assert_eq!(entry_times(&top), vec![2, 3]);
+
+            let mut bottom =
+                TopAccumulator::new(TopType::Bottom, DataType::Float64, &unbounded_memory());
+            bottom.update_batch(&args).unwrap();
+            assert_eq!(entry_times(&bottom), vec![1, 4]);
+        }
+    }
+
+    /// The times of the entries held by `acc`, best first
+    fn entry_times(acc: &TopAccumulator) -> Vec<i64> {
+        acc.sorted_entries().iter().map(|e| e.time).collect()
+    }
+}
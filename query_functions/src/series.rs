@@ -0,0 +1,848 @@
+//! InfluxQL functions that transform a time ordered series of values,
+//! such as `derivative`, into another series.
+//!
+//! DataFusion does not (yet) support user defined window functions,
+//! so these functions are implemented as user defined aggregates
+//! (UDAFs) that collect the `(time, value)` points of each group and
+//! sort them by time.
+//!
+//! # Output
+//!
+//! Each function returns a single `List<Struct{value, time}>` per
+//! group, with one entry for each point of the output series in time
+//! order. The entries have the same layout as the `{value, time}`
+//! struct of the selectors, with the fields named
+//! [`SELECTOR_VALUE_FIELD`](crate::selectors::SELECTOR_VALUE_FIELD)
+//! and [`SELECTOR_TIME_FIELD`](crate::selectors::SELECTOR_TIME_FIELD), so the list
+//! is also the output type of `selector_top`. An empty output series
+//! is an empty list.
+//!
+//! The exceptions are the functions that compute a single value from
+//! the series, `integral`, `rate` and `irate`, which return just that
+//! value.
+//!
+//! # Planning
+//!
+//! An InfluxQL query such as `SELECT derivative(usage) FROM cpu GROUP
+//! BY host` returns a row for each point of the output series, rather
+//! than a row per group. It is planned as:
+//!
+//! 1. an aggregate grouped by the `GROUP BY` columns that computes the
+//!    function, producing one list per group
+//! 2. an unnest of the list column, producing one row per entry with
+//!    the group columns repeated
+//! 3. a projection that extracts the parts of each entry using
+//!    [`selector_value`](crate::selectors::selector_value) and
+//!    [`selector_time`](crate::selectors::selector_time)
+//!
+//! There is no InfluxQL planner in this crate, so the functions are
+//! registered by [`register_series_functions`], and SQL queries that
+//! invoke them receive the list.
+
+use std::{fmt::Debug, mem::size_of, sync::Arc};
+
+use arrow::{
+    array::{Array, ArrayRef, ListArray, TimestampNanosecondArray},
+    compute::cast,
+    datatypes::{DataType, Field},
+};
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    execution::context::SessionState,
+    logical_expr::{
+        AccumulatorFunctionImplementation, AggregateState, Signature, TypeSignature, Volatility,
+    },
+    physical_plan::{udaf::AggregateUDF, Accumulator},
+    scalar::ScalarValue,
+};
+use schema::TIME_DATA_TYPE;
+
+use crate::{
+    extension::FunctionRegistryExt,
+    selectors::{
+        make_struct_fields,
+        memory::{scalar_heap_size, AccumulatorMemory, MemoryReservation},
+        ReturnTypeFunction, StateTypeFactory,
+    },
+};
+
+mod cumulative_sum;
+mod derivative;
+mod difference;
+mod elapsed;
+mod exponential_moving_average;
+mod gap_fill;
+mod holt_winters;
+mod integral;
+mod interpolate_linear;
+mod locf;
+mod moving_average;
+mod rate;
+use cumulative_sum::CumulativeSum;
+use derivative::Derivative;
+use difference::Difference;
+use elapsed::Elapsed;
+use exponential_moving_average::ExponentialMovingAverage;
+use gap_fill::GapFill;
+use holt_winters::HoltWinters;
+use integral::Integral;
+use interpolate_linear::InterpolateLinear;
+use locf::Locf;
+use moving_average::MovingAverage;
+use rate::Rate;
+
+/// registers the series functions so they can be invoked via SQL.
+///
+/// The accumulators track the memory for the points of each group
+/// with `memory`, failing queries that would exceed its limit
+pub fn register_series_functions(
+    mut state: SessionState,
+    memory: &Arc<AccumulatorMemory>,
+) -> SessionState {
+    let udafs = [
+        derivative(memory),
+        non_negative_derivative(memory),
+        difference(memory),
+        non_negative_difference(memory),
+        moving_average(memory),
+        exponential_moving_average(memory),
+        double_exponential_moving_average(memory),
+        cumulative_sum(memory),
+        integral(memory),
+        elapsed(memory),
+        holt_winters(memory),
+        rate(memory),
+        irate(memory),
+        gap_fill(memory),
+        locf(memory),
+        interpolate_linear(memory),
+    ];
+
+    for udaf in udafs {
+        state.register_udaf(udaf);
+    }
+
+    state
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL derivative(value, time[, unit]) function: the rate of
+/// change between consecutive values, per `unit` nanoseconds
+/// (default one second).
+///
+/// derivative(value, time[, unit]) -> list of {value: f64, time}
+///
+/// The values are ordered by time and, if there are multiple values
+/// with the same time, only the first is used
+pub fn derivative(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "derivative",
+        Arc::new(Derivative::new(false)),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL non_negative_derivative(value, time[, unit]) function:
+/// the same as [`derivative`] but negative rates of change (such as
+/// when a counter is reset) are dropped
+///
+/// non_negative_derivative(value, time[, unit]) -> list of {value: f64, time}
+pub fn non_negative_derivative(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "non_negative_derivative",
+        Arc::new(Derivative::new(true)),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL difference(value, time) function: the change between
+/// consecutive values.
+///
+/// difference(value, time) -> list of {value, time}
+///
+/// The values are ordered by time and, if there are multiple values
+/// with the same time, only the first is used
+pub fn difference(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "difference",
+        Arc::new(Difference::new(false)),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL non_negative_difference(value, time) function: the
+/// same as [`difference`] but negative differences are dropped
+///
+/// non_negative_difference(value, time) -> list of {value, time}
+pub fn non_negative_difference(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "non_negative_difference",
+        Arc::new(Difference::new(true)),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL moving_average(value, time, n) function: the mean of
+/// each window of `n` consecutive values.
+///
+/// moving_average(value, time, n) -> list of {value: f64, time}
+///
+/// The values are ordered by time. The first output is the mean of
+/// the first `n` values, so there is no output for series with fewer
+/// than `n` values
+pub fn moving_average(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "moving_average",
+        Arc::new(MovingAverage),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL exponential_moving_average(value, time, n) function:
+/// the average of the values, weighted by a factor of `2 / (n + 1)`
+/// that decays exponentially with age.
+///
+/// exponential_moving_average(value, time, n) -> list of {value: f64, time}
+///
+/// The values are ordered by time. The average starts at the first
+/// value, and nothing is output for the first `n - 1` values
+pub fn exponential_moving_average(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "exponential_moving_average",
+        Arc::new(ExponentialMovingAverage::new(false)),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL double_exponential_moving_average(value, time, n)
+/// function: `2 * EMA - EMA(EMA)`, where `EMA` is the
+/// [`exponential_moving_average`], which reduces its lag.
+///
+/// double_exponential_moving_average(value, time, n) -> list of {value: f64, time}
+///
+/// The values are ordered by time, and nothing is output for the
+/// first `n - 1` values
+pub fn double_exponential_moving_average(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "double_exponential_moving_average",
+        Arc::new(ExponentialMovingAverage::new(true)),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL cumulative_sum(value, time) function: the running
+/// total of the values.
+///
+/// cumulative_sum(value, time) -> list of {value, time}
+///
+/// The values are ordered by time
+pub fn cumulative_sum(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "cumulative_sum",
+        Arc::new(CumulativeSum),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL integral(value, time[, unit]) function: the area under
+/// the curve of the values, using the trapezoidal rule, per `unit`
+/// nanoseconds (default one second).
+///
+/// integral(value, time[, unit]) -> f64
+///
+/// The values are ordered by time and, if there are multiple values
+/// with the same time, only the first is used
+pub fn integral(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda("integral", Arc::new(Integral), memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL elapsed(time[, unit]) function: the time between
+/// consecutive points, as a whole number of `unit` nanoseconds
+/// (default one nanosecond).
+///
+/// elapsed(time[, unit]) -> list of {value: i64, time}
+pub fn elapsed(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda("elapsed", Arc::new(Elapsed), memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the InfluxQL holt_winters(value, time, n[, seasonality]) function:
+/// a forecast of the next `n` values of the series, using an additive
+/// Holt-Winters model with a seasonal pattern that repeats every
+/// `seasonality` values.
+///
+/// holt_winters(value, time, n[, seasonality]) -> list of {value: f64, time}
+///
+/// The series is expected to have values at a regular interval, such
+/// as the output of a GROUP BY time query. The forecast values are at
+/// the same interval after the last value
+pub fn holt_winters(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "holt_winters",
+        Arc::new(HoltWinters),
+        memory,
+    ))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the rate(value, time[, unit]) function: the rate of increase of a
+/// counter between the first and last values, per `unit` nanoseconds
+/// (default one second).
+///
+/// rate(value, time[, unit]) -> f64
+///
+/// The values are ordered by time. A value lower than the previous
+/// one is treated as a counter reset, after which the counter started
+/// again from zero
+pub fn rate(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda("rate", Arc::new(Rate::new(false)), memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the irate(value, time[, unit]) function: the same as [`rate`] but
+/// using only the last two values
+///
+/// irate(value, time[, unit]) -> f64
+pub fn irate(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda("irate", Arc::new(Rate::new(true)), memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the gap_fill(value, time, interval[, fill[, constant]]) function: a
+/// value for each `interval` nanosecond time bucket between the first
+/// and last values, with missing buckets filled as for InfluxQL
+/// `fill()`.
+///
+/// gap_fill(value, time, interval[, fill[, constant]]) -> list of {value, time}
+///
+/// `fill` is one of:
+/// * `'null'` (the default): fill with null
+/// * `'previous'`: fill with the value of the previous bucket
+/// * `'linear'`: fill with the value linearly interpolated between the
+///   surrounding buckets
+/// * `'value'`: fill with `constant`
+///
+/// Queries fail if a series spans more than 1,000,000 buckets.
+pub fn gap_fill(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda("gap_fill", Arc::new(GapFill), memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the locf(value, time) function: the values with each null value
+/// replaced by the last non null value before it (last observation
+/// carried forward).
+///
+/// locf(value, time) -> list of {value, time}
+///
+/// The values are ordered by time. Null values before the first non
+/// null value stay null
+pub fn locf(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda("locf", Arc::new(Locf), memory))
+}
+
+/// Returns a DataFusion user defined aggregate function for computing
+/// the interpolate_linear(value, time) function: the values with each
+/// null value replaced by the value linearly interpolated by time
+/// between the surrounding non null values.
+///
+/// interpolate_linear(value, time) -> list of {value: f64, time}
+///
+/// The values are ordered by time. Null values before the first or
+/// after the last non null value stay null
+pub fn interpolate_linear(memory: &Arc<AccumulatorMemory>) -> Arc<AggregateUDF> {
+    Arc::new(make_series_uda(
+        "interpolate_linear",
+        Arc::new(InterpolateLinear),
+        memory,
+    ))
+}
+
+/// A point in a series: its time and value, which is not null unless
+/// [`SeriesFunction::keeps_nulls`]
+type Point = (i64, ScalarValue);
+
+/// A function that computes an output series from an input series
+trait SeriesFunction: Debug + Send + Sync {
+    /// The name of the function, used in error messages
+    fn name(&self) -> &'static str;
+
+    /// The types of the optional constant arguments that follow the
+    /// `(value, time)` arguments, such as the unit of `derivative`
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![]
+    }
+
+    /// If false, the function is only interested in the times of the
+    /// series, and takes `(time)` rather than `(value, time)`
+    /// arguments. The time, cast to the output value type, is used as
+    /// the value
+    fn takes_value(&self) -> bool {
+        true
+    }
+
+    /// If true, the function computes a single value from the series,
+    /// which is the output of the aggregate, rather than a series
+    fn is_aggregate(&self) -> bool {
+        false
+    }
+
+    /// If true, points with null values are kept in the series, such
+    /// as for functions that fill them in. Otherwise they are ignored
+    fn keeps_nulls(&self) -> bool {
+        false
+    }
+
+    /// The type of the output values, given the type of the input
+    /// values. Input values are cast to this type as they are
+    /// collected
+    fn return_value_type(&self, value_type: &DataType) -> DataType;
+
+    /// Compute the output series from the input `points`, sorted by
+    /// time. `args` has an entry for each of [`Self::arg_types`],
+    /// which is `None` if the argument was not specified.
+    ///
+    /// Functions for which [`Self::is_aggregate`] is true return at
+    /// most one point
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>>;
+}
+
+/// Create a User Defined Aggregate Function (UDAF) that computes
+/// `func` over the series of each group, whose accumulators track
+/// their memory with `memory`
+fn make_series_uda(
+    name: &str,
+    func: Arc<dyn SeriesFunction>,
+    memory: &Arc<AccumulatorMemory>,
+) -> AggregateUDF {
+    let arg_types = func.arg_types();
+
+    // (value, time), or just (time), followed by any prefix of the
+    // optional arguments
+    let series_types = if func.takes_value() {
+        vec![
+            vec![DataType::Float64, TIME_DATA_TYPE()],
+            vec![DataType::Int64, TIME_DATA_TYPE()],
+            vec![DataType::UInt64, TIME_DATA_TYPE()],
+        ]
+    } else {
+        vec![vec![TIME_DATA_TYPE()]]
+    };
+    let signatures = series_types
+        .into_iter()
+        .flat_map(|series_types| {
+            let arg_types = arg_types.clone();
+            (0..=arg_types.len()).map(move |num_args| {
+                let mut types = series_types.clone();
+                types.extend(arg_types[..num_args].iter().cloned());
+                TypeSignature::Exact(types)
+            })
+        })
+        .collect();
+    let input_signature = Signature::one_of(signatures, Volatility::Stable);
+
+    let captured_func = Arc::clone(&func);
+    let return_type_func: ReturnTypeFunction = Arc::new(move |arg_types| {
+        assert!(
+            !arg_types.is_empty(),
+            "{} expected at least 1 argument, got 0",
+            captured_func.name(),
+        );
+        let value_type = captured_func.return_value_type(&arg_types[0]);
+        if captured_func.is_aggregate() {
+            Ok(Arc::new(value_type))
+        } else {
+            Ok(Arc::new(make_list_type(make_entry_type(&value_type))))
+        }
+    });
+
+    // The state is the list of values, the list of their times, and
+    // the constant arguments
+    let captured_func = Arc::clone(&func);
+    let state_type_factory: StateTypeFactory = Arc::new(move |return_type| {
+        let value_type = value_data_type_from_return_data_type(&*captured_func, return_type)?;
+        let state_types = [make_list_type(value_type), make_list_type(TIME_DATA_TYPE())]
+            .into_iter()
+            .chain(captured_func.arg_types())
+            .collect();
+        Ok(Arc::new(state_types))
+    });
+
+    let memory = Arc::clone(memory);
+    let accumulator_factory: AccumulatorFunctionImplementation = Arc::new(move |return_type| {
+        let value_type = value_data_type_from_return_data_type(&*func, return_type)?;
+        let accumulator: Box<dyn Accumulator> = Box::new(SeriesAccumulator::new(
+            Arc::clone(&func),
+            value_type,
+            &memory,
+        ));
+        Ok(accumulator)
+    });
+
+    AggregateUDF::new(
+        name,
+        &input_signature,
+        &return_type_func,
+        &accumulator_factory,
+        &state_type_factory,
+    )
+}
+
+/// The type of each output entry: struct {value, time}
+fn make_entry_type(value_type: &DataType) -> DataType {
+    DataType::Struct(make_struct_fields(value_type.clone(), &[]))
+}
+
+/// A list of `item_type`, as produced by [`ScalarValue::new_list`]
+fn make_list_type(item_type: DataType) -> DataType {
+    DataType::List(Box::new(Field::new("item", item_type, true)))
+}
+
+/// Return the value type given the List<Struct{value, time}> output
+/// data type, or the output data type itself for aggregates
+fn value_data_type_from_return_data_type(
+    func: &dyn SeriesFunction,
+    return_type: &DataType,
+) -> DataFusionResult<DataType> {
+    if func.is_aggregate() {
+        return Ok(return_type.clone());
+    }
+
+    match return_type {
+        DataType::List(item) => match item.data_type() {
+            DataType::Struct(fields) => Ok(fields[0].data_type().clone()),
+            t => Err(DataFusionError::Internal(format!(
+                "Unexpected item type for series function: {:?}",
+                t
+            ))),
+        },
+        t => Err(DataFusionError::Internal(format!(
+            "Unexpected return type for series function: {:?}",
+            t
+        ))),
+    }
+}
+
+/// Accumulator that collects all the points of the series
+#[derive(Debug)]
+struct SeriesAccumulator {
+    func: Arc<dyn SeriesFunction>,
+
+    /// The type the input values are cast to
+    value_type: DataType,
+
+    points: Vec<Point>,
+
+    /// The memory allocated by the values of `points` outside of
+    /// `points` itself
+    points_heap_size: usize,
+
+    /// The constant arguments. Not known until the first batch is
+    /// seen, as they are passed as arguments
+    args: Vec<Option<ScalarValue>>,
+
+    /// The memory reserved for `points`
+    reservation: MemoryReservation,
+}
+
+impl SeriesAccumulator {
+    fn new(
+        func: Arc<dyn SeriesFunction>,
+        value_type: DataType,
+        memory: &Arc<AccumulatorMemory>,
+    ) -> Self {
+        let args = vec![None; func.arg_types().len()];
+        Self {
+            func,
+            value_type,
+            points: vec![],
+            points_heap_size: 0,
+            args,
+            reservation: MemoryReservation::new(memory),
+        }
+    }
+
+    /// The memory used by the accumulator, in bytes
+    fn size(&self) -> usize {
+        size_of::<Self>()
+            + self.points.capacity() * size_of::<Point>()
+            + self.points_heap_size
+            + self
+                .args
+                .iter()
+                .flatten()
+                .map(scalar_heap_size)
+                .sum::<usize>()
+    }
+
+    /// Reserve the memory currently used by the accumulator
+    fn update_reservation(&mut self) -> DataFusionResult<()> {
+        let size = self.size();
+        self.reservation.try_resize(self.func.name(), size)
+    }
+
+    /// Record the constant arguments from the first non null value
+    /// of each array in `arg_arrs`
+    fn update_args(&mut self, arg_arrs: &[ArrayRef]) -> DataFusionResult<()> {
+        for (arg, arg_arr) in self.args.iter_mut().zip(arg_arrs) {
+            if arg.is_some() {
+                continue;
+            }
+            if let Some(idx) = (0..arg_arr.len()).find(|&idx| !arg_arr.is_null(idx)) {
+                *arg = Some(ScalarValue::try_from_array(arg_arr, idx)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the points with non null times in `time_arr` (and
+    /// corresponding values in `value_arr`), skipping null values
+    /// unless the function keeps them
+    fn update_points(&mut self, value_arr: &ArrayRef, time_arr: &ArrayRef) -> DataFusionResult<()> {
+        let value_arr = cast(value_arr, &self.value_type)?;
+
+        let time_arr = time_arr
+            .as_any()
+            .downcast_ref::<TimestampNanosecondArray>()
+            // the input type arguments should be ensured by datafusion
+            .expect("Second argument was time");
+
+        for idx in 0..value_arr.len() {
+            if time_arr.is_null(idx) || (value_arr.is_null(idx) && !self.func.keeps_nulls()) {
+                continue;
+            }
+            let value = ScalarValue::try_from_array(&value_arr, idx)?;
+            self.points_heap_size += scalar_heap_size(&value);
+            self.points.push((time_arr.value(idx), value));
+        }
+        Ok(())
+    }
+
+    /// Check `num_args` is `min_args` followed by at most all the
+    /// constant arguments
+    fn check_num_args(&self, num_args: usize, min_args: usize, what: &str) -> DataFusionResult<()> {
+        let max_args = min_args + self.args.len();
+        if num_args < min_args || num_args > max_args {
+            return Err(DataFusionError::Internal(format!(
+                "Internal error: Expected between {} and {} {} passed to {} but got {}",
+                min_args,
+                max_args,
+                what,
+                self.func.name(),
+                num_args
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Accumulator for SeriesAccumulator {
+    fn state(&self) -> DataFusionResult<Vec<AggregateState>> {
+        let values = self.points.iter().map(|(_, v)| v.clone()).collect();
+        let times = self
+            .points
+            .iter()
+            .map(|(t, _)| ScalarValue::TimestampNanosecond(Some(*t), None))
+            .collect();
+
+        let mut state = vec![
+            AggregateState::Scalar(ScalarValue::new_list(Some(values), self.value_type.clone())),
+            AggregateState::Scalar(ScalarValue::new_list(Some(times), TIME_DATA_TYPE())),
+        ];
+
+        for (arg, arg_type) in self.args.iter().zip(self.func.arg_types()) {
+            let arg = match arg {
+                Some(arg) => arg.clone(),
+                None => ScalarValue::try_from(&arg_type)?,
+            };
+            state.push(AggregateState::Scalar(arg));
+        }
+
+        Ok(state)
+    }
+
+    fn evaluate(&self) -> DataFusionResult<ScalarValue> {
+        // stable sort so points with the same time keep their input order
+        let mut points = self.points.clone();
+        points.sort_by_key(|(t, _)| *t);
+
+        let output = self.func.evaluate(&points, &self.args)?;
+
+        if self.func.is_aggregate() {
+            return match output.into_iter().next() {
+                Some((_, value)) => Ok(value),
+                None => ScalarValue::try_from(&self.value_type),
+            };
+        }
+
+        let entries = output
+            .into_iter()
+            .map(|(time, value)| {
+                ScalarValue::Struct(
+                    Some(vec![
+                        value,
+                        ScalarValue::TimestampNanosecond(Some(time), None),
+                    ]),
+                    Box::new(make_struct_fields(self.value_type.clone(), &[])),
+                )
+            })
+            .collect();
+
+        Ok(ScalarValue::new_list(
+            Some(entries),
+            make_entry_type(&self.value_type),
+        ))
+    }
+
+    fn update_batch(&mut self, values: &[ArrayRef]) -> DataFusionResult<()> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        if self.func.takes_value() {
+            self.check_num_args(values.len(), 2, "arguments")?;
+            self.update_args(&values[2..])?;
+            self.update_points(&values[0], &values[1])?;
+        } else {
+            self.check_num_args(values.len(), 1, "arguments")?;
+            self.update_args(&values[1..])?;
+            self.update_points(&values[0], &values[0])?;
+        }
+        self.update_reservation()
+    }
+
+    // Each row of the states is a list of values and a list of times
+    // previously produced by `state`, followed by the arguments
+    fn merge_batch(&mut self, states: &[ArrayRef]) -> DataFusionResult<()> {
+        if states.is_empty() {
+            return Ok(());
+        }
+
+        self.check_num_args(states.len(), 2, "states")?;
+        self.update_args(&states[2..])?;
+
+        let value_lists = states[0]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("First state was a list of values");
+
+        let time_lists = states[1]
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .expect("Second state was a list of times");
+
+        for idx in 0..value_lists.len() {
+            if value_lists.is_null(idx) || time_lists.is_null(idx) {
+                continue;
+            }
+            self.update_points(&value_lists.value(idx), &time_lists.value(idx))?;
+        }
+        self.update_reservation()
+    }
+}
+
+/// Return the value of a point as a float, for functions that
+/// compute with floats whatever the input type
+fn value_as_f64(value: &ScalarValue) -> DataFusionResult<f64> {
+    match value {
+        ScalarValue::Float64(Some(v)) => Ok(*v),
+        ScalarValue::Int64(Some(v)) => Ok(*v as f64),
+        ScalarValue::UInt64(Some(v)) => Ok(*v as f64),
+        v => Err(DataFusionError::Internal(format!(
+            "Unexpected value in series: {:?}",
+            v
+        ))),
+    }
+}
+
+/// Return the points with duplicate times removed, keeping the first
+/// point at each time, as InfluxQL does
+fn dedup_times(points: &[Point]) -> Vec<&Point> {
+    let mut deduped: Vec<&Point> = Vec::with_capacity(points.len());
+    for point in points {
+        match deduped.last() {
+            Some((prev_time, _)) if *prev_time == point.0 => {}
+            _ => deduped.push(point),
+        }
+    }
+    deduped
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::{
+        array::{Float64Array, TimestampNanosecondArray},
+        datatypes::Schema,
+        record_batch::RecordBatch,
+    };
+    use datafusion::{
+        datasource::MemTable,
+        prelude::{col, SessionContext},
+    };
+    use schema::TIME_DATA_TIMEZONE;
+
+    use super::*;
+    use crate::test_util::{memory_with_limit, unbounded_memory};
+
+    #[tokio::test]
+    async fn test_accumulator_memory_limit() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("f64_value", DataType::Float64, true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Float64Array::from(vec![Some(2.0), Some(4.0), Some(1.0)])),
+                Arc::new(TimestampNanosecondArray::from_vec(
+                    vec![1000, 2000, 3000],
+                    TIME_DATA_TIMEZONE(),
+                )),
+            ],
+        )
+        .unwrap();
+        let provider = MemTable::try_new(Arc::clone(&schema), vec![vec![batch]]).unwrap();
+        let ctx = SessionContext::new();
+        ctx.register_table("t", Arc::new(provider)).unwrap();
+
+        let run = |memory: Arc<AccumulatorMemory>| {
+            let df = ctx.table("t").unwrap();
+            async move {
+                df.aggregate(
+                    vec![],
+                    vec![derivative(&memory).call(vec![col("f64_value"), col("time")])],
+                )
+                .unwrap()
+                .collect()
+                .await
+            }
+        };
+
+        // The state of the accumulator does not fit in a small memory limit
+        let memory = memory_with_limit(16);
+        let err = run(Arc::clone(&memory)).await.unwrap_err();
+        assert!(
+            err.to_string().contains("for the state of derivative"),
+            "{}",
+            err
+        );
+        assert_eq!(memory.used(), 0);
+
+        // The memory is released once the query completes
+        let memory = unbounded_memory();
+        run(Arc::clone(&memory)).await.unwrap();
+        assert_eq!(memory.used(), 0);
+    }
+}
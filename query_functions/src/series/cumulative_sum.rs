@@ -0,0 +1,118 @@
+//! Implementation of the InfluxQL `cumulative_sum` function, which
+//! computes the running total of the values.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{Point, SeriesFunction};
+
+/// Computes `cumulative_sum(value, time)`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct CumulativeSum;
+
+impl SeriesFunction for CumulativeSum {
+    fn name(&self) -> &'static str {
+        "cumulative_sum"
+    }
+
+    fn return_value_type(&self, value_type: &DataType) -> DataType {
+        value_type.clone()
+    }
+
+    fn evaluate(
+        &self,
+        points: &[Point],
+        _args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let mut output: Vec<Point> = Vec::with_capacity(points.len());
+        for (time, value) in points {
+            // integer sums wrap, the same as InfluxQL
+            let sum = match (output.last().map(|(_, sum)| sum), value) {
+                (None, v) => v.clone(),
+                (Some(ScalarValue::Float64(Some(sum))), ScalarValue::Float64(Some(v))) => {
+                    ScalarValue::Float64(Some(sum + v))
+                }
+                (Some(ScalarValue::Int64(Some(sum))), ScalarValue::Int64(Some(v))) => {
+                    ScalarValue::Int64(Some(sum.wrapping_add(*v)))
+                }
+                (Some(ScalarValue::UInt64(Some(sum))), ScalarValue::UInt64(Some(v))) => {
+                    ScalarValue::UInt64(Some(sum.wrapping_add(*v)))
+                }
+                (Some(sum), v) => {
+                    return Err(DataFusionError::Internal(format!(
+                        "Unexpected values for {}: {:?} and {:?}",
+                        self.name(),
+                        sum,
+                        v
+                    )))
+                }
+            };
+            output.push((*time, sum));
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        series::cumulative_sum,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_cumulative_sum_f64() {
+        run_case(
+            cumulative_sum(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time")])
+                .alias("cumulative_sum"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| cumulative_sum                                                                                                                                                                                                                                               |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 6, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 7, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 12, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 15, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_cumulative_sum_i64() {
+        run_case(
+            cumulative_sum(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time")])
+                .alias("cumulative_sum"),
+            vec![
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| cumulative_sum                                                                                                                                                                                                                                                    |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 60, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 70, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 120, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 150, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_cumulative_sum_u64() {
+        run_case(
+            cumulative_sum(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time")])
+                .alias("cumulative_sum"),
+            vec![
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| cumulative_sum                                                                                                                                                                                                                                                    |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 60, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 70, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 120, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 150, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,157 @@
+//! Implementation of the InfluxQL `derivative` and
+//! `non_negative_derivative` functions, which compute the rate of
+//! change between consecutive values per unit of time.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{dedup_times, value_as_f64, Point, SeriesFunction};
+
+/// The default unit of time for the rate of change: one second
+const DEFAULT_UNIT_NANOS: i64 = 1_000_000_000;
+
+/// Computes `derivative(value, time[, unit])`, where `unit` is the
+/// duration in nanoseconds the rate is computed over
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Derivative {
+    /// If true, negative rates of change are dropped
+    non_negative: bool,
+}
+
+impl Derivative {
+    pub(super) fn new(non_negative: bool) -> Self {
+        Self { non_negative }
+    }
+}
+
+impl SeriesFunction for Derivative {
+    fn name(&self) -> &'static str {
+        if self.non_negative {
+            "non_negative_derivative"
+        } else {
+            "derivative"
+        }
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64]
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let unit = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(unit)))) => *unit,
+            _ => DEFAULT_UNIT_NANOS,
+        };
+        if unit <= 0 {
+            return Err(DataFusionError::Execution(format!(
+                "{} expected a positive unit, got {}",
+                self.name(),
+                unit
+            )));
+        }
+
+        let mut output = vec![];
+        for pair in dedup_times(points).windows(2) {
+            let (prev_time, prev_value) = pair[0];
+            let (time, value) = pair[1];
+
+            let diff = value_as_f64(value)? - value_as_f64(prev_value)?;
+            if self.non_negative && diff < 0.0 {
+                continue;
+            }
+
+            let elapsed = (time - prev_time) as f64 / unit as f64;
+            output.push((*time, ScalarValue::Float64(Some(diff / elapsed))));
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use crate::{
+        series::{derivative, non_negative_derivative},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_derivative_f64() {
+        run_case(
+            derivative(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64)])
+                .alias("derivative"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| derivative                                                                                                                                                                                                   |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": -1.5, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": -2, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_derivative_i64() {
+        run_case(
+            derivative(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(1000i64)])
+                .alias("derivative"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| derivative                                                                                                                                                                                                     |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": -15, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": -20, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_derivative_default_unit() {
+        run_case(
+            derivative(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time")])
+                .alias("derivative"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| derivative                                                                                                                                                                                                                             |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20000000, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": -15000000, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 40000000, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": -20000000, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_non_negative_derivative() {
+        run_case(
+            non_negative_derivative(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64)])
+                .alias("derivative"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------+",
+                "| derivative                                                                                           |",
+                "+------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000005}] |",
+                "+------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,156 @@
+//! Implementation of the InfluxQL `difference` and
+//! `non_negative_difference` functions, which compute the change
+//! between consecutive values.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{dedup_times, Point, SeriesFunction};
+
+/// Computes `difference(value, time)`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Difference {
+    /// If true, negative differences are dropped
+    non_negative: bool,
+}
+
+impl Difference {
+    pub(super) fn new(non_negative: bool) -> Self {
+        Self { non_negative }
+    }
+}
+
+impl SeriesFunction for Difference {
+    fn name(&self) -> &'static str {
+        if self.non_negative {
+            "non_negative_difference"
+        } else {
+            "difference"
+        }
+    }
+
+    fn return_value_type(&self, value_type: &DataType) -> DataType {
+        value_type.clone()
+    }
+
+    fn evaluate(
+        &self,
+        points: &[Point],
+        _args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let mut output = vec![];
+        for pair in dedup_times(points).windows(2) {
+            let (_, prev_value) = pair[0];
+            let (time, value) = pair[1];
+
+            // integer differences wrap, the same as InfluxQL
+            let (diff, negative) = match (prev_value, value) {
+                (ScalarValue::Float64(Some(prev)), ScalarValue::Float64(Some(v))) => {
+                    (ScalarValue::Float64(Some(v - prev)), v < prev)
+                }
+                (ScalarValue::Int64(Some(prev)), ScalarValue::Int64(Some(v))) => {
+                    (ScalarValue::Int64(Some(v.wrapping_sub(*prev))), v < prev)
+                }
+                (ScalarValue::UInt64(Some(prev)), ScalarValue::UInt64(Some(v))) => {
+                    (ScalarValue::UInt64(Some(v.wrapping_sub(*prev))), v < prev)
+                }
+                (prev, v) => {
+                    return Err(DataFusionError::Internal(format!(
+                        "Unexpected values for {}: {:?} and {:?}",
+                        self.name(),
+                        prev,
+                        v
+                    )))
+                }
+            };
+
+            if self.non_negative && negative {
+                continue;
+            }
+
+            output.push((*time, diff));
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        series::{difference, non_negative_difference},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_difference_f64() {
+        run_case(
+            difference(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time")])
+                .alias("difference"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| difference                                                                                                                                                                                                 |",
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": -3, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": -2, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_difference_i64() {
+        run_case(
+            difference(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time")])
+                .alias("difference"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| difference                                                                                                                                                                                                     |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": -30, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": -20, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_non_negative_difference_f64() {
+        run_case(
+            non_negative_difference(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time")])
+                .alias("difference"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------+",
+                "| difference                                                                                           |",
+                "+------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000005}] |",
+                "+------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_non_negative_difference_u64() {
+        run_case(
+            non_negative_difference(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time")])
+                .alias("difference"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| difference                                                                                             |",
+                "+--------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000005}] |",
+                "+--------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,109 @@
+//! Implementation of the InfluxQL `elapsed` function, which computes
+//! the time between consecutive points.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{Point, SeriesFunction};
+
+/// The default unit of time for the elapsed time: one nanosecond
+const DEFAULT_UNIT_NANOS: i64 = 1;
+
+/// Computes `elapsed(time[, unit])`, where `unit` is the duration in
+/// nanoseconds the elapsed time is reported in
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Elapsed;
+
+impl SeriesFunction for Elapsed {
+    fn name(&self) -> &'static str {
+        "elapsed"
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64]
+    }
+
+    fn takes_value(&self) -> bool {
+        false
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Int64
+    }
+
+    // Like InfluxQL, the elapsed time is truncated to a whole number
+    // of units
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let unit = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(unit)))) => *unit,
+            _ => DEFAULT_UNIT_NANOS,
+        };
+        if unit <= 0 {
+            return Err(DataFusionError::Execution(format!(
+                "{} expected a positive unit, got {}",
+                self.name(),
+                unit
+            )));
+        }
+
+        Ok(points
+            .windows(2)
+            .map(|pair| {
+                let (prev_time, _) = &pair[0];
+                let (time, _) = &pair[1];
+                (*time, ScalarValue::Int64(Some((time - prev_time) / unit)))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use crate::{
+        series::elapsed,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_elapsed() {
+        run_case(
+            elapsed(&unbounded_memory())
+                .call(vec![col("time"), lit(1000i64)])
+                .alias("elapsed"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| elapsed                                                                                                                                                                                                                                                    |",
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 1, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_elapsed_default_unit() {
+        run_case(
+            elapsed(&unbounded_memory())
+                .call(vec![col("time")])
+                .alias("elapsed"),
+            vec![
+                "+---------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| elapsed                                                                                                                                                                                                                                                                   |",
+                "+---------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 1000, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 1000, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1000, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 1000, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 1000, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+---------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
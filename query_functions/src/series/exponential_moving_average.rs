@@ -0,0 +1,182 @@
+//! Implementation of the InfluxQL `exponential_moving_average` and
+//! `double_exponential_moving_average` functions, which smooth a
+//! series giving more weight to recent values.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{value_as_f64, Point, SeriesFunction};
+
+/// Computes `exponential_moving_average(value, time, n)`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct ExponentialMovingAverage {
+    /// If true, computes the double exponential moving average
+    double: bool,
+}
+
+impl ExponentialMovingAverage {
+    pub(super) fn new(double: bool) -> Self {
+        Self { double }
+    }
+}
+
+impl SeriesFunction for ExponentialMovingAverage {
+    fn name(&self) -> &'static str {
+        if self.double {
+            "double_exponential_moving_average"
+        } else {
+            "exponential_moving_average"
+        }
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64]
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    // The smoothing factor is 2 / (n + 1), and the average starts at
+    // the first value. Like `moving_average`, nothing is output for
+    // the first `n - 1` values while the average warms up
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let n = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(n)))) if *n > 0 => *n as usize,
+            n => {
+                return Err(DataFusionError::Execution(format!(
+                    "{} expected a positive number of points, got {:?}",
+                    self.name(),
+                    n.cloned().flatten()
+                )))
+            }
+        };
+        let alpha = 2.0 / (n as f64 + 1.0);
+
+        let values = points
+            .iter()
+            .map(|(_, v)| value_as_f64(v))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let ema = exponential_moving_average(&values, alpha);
+        let output = if self.double {
+            // DEMA = 2 * EMA - EMA(EMA)
+            let ema_of_ema = exponential_moving_average(&ema, alpha);
+            ema.iter()
+                .zip(ema_of_ema)
+                .map(|(ema, ema_of_ema)| 2.0 * ema - ema_of_ema)
+                .collect()
+        } else {
+            ema
+        };
+
+        Ok(points
+            .iter()
+            .zip(output)
+            .skip(n - 1)
+            .map(|((time, _), value)| (*time, ScalarValue::Float64(Some(value))))
+            .collect())
+    }
+}
+
+/// The exponential moving average at each of `values` with smoothing
+/// factor `alpha`
+fn exponential_moving_average(values: &[f64], alpha: f64) -> Vec<f64> {
+    let mut ema = match values.first() {
+        Some(first) => *first,
+        None => return vec![],
+    };
+
+    values
+        .iter()
+        .map(|value| {
+            ema += alpha * (value - ema);
+            ema
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use crate::{
+        series::{double_exponential_moving_average, exponential_moving_average},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_exponential_moving_average_f64() {
+        run_case(
+            exponential_moving_average(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(2i64)])
+                .alias("ema"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| ema                                                                                                                                                                                                                                                                        |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 3.333333333333333, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 1.7777777777777777, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 3.9259259259259256, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3.308641975308642, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_exponential_moving_average_i64() {
+        run_case(
+            exponential_moving_average(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(3i64)])
+                .alias("ema"),
+            vec![
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| ema                                                                                                                                                         |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 35, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 32.5, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_double_exponential_moving_average_f64() {
+        run_case(
+            double_exponential_moving_average(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(2i64)])
+                .alias("dema"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| dema                                                                                                                                                                                                                                                                       |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 3.7777777777777777, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 1.4074074074074074, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 4.518518518518518, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3.300411522633745, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_double_exponential_moving_average_u64() {
+        run_case(
+            double_exponential_moving_average(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time"), lit(3i64)])
+                .alias("dema"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| dema                                                                                                                                                               |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 17.5, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 41.25, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 34.375, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
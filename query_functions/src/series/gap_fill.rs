@@ -0,0 +1,291 @@
+//! Implementation of the `gap_fill` function, which emits a value
+//! for each time bucket of a series, filling in the missing buckets
+//! in the same way as InfluxQL `fill()`.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{value_as_f64, Point, SeriesFunction};
+
+/// The maximum number of buckets emitted for a series, so that a
+/// small interval over a long time range fails the query rather than
+/// exhausting the memory of the querier
+const MAX_BUCKETS: i64 = 1_000_000;
+
+/// How the values of missing buckets are filled
+#[derive(Debug, Clone, Copy)]
+enum FillStrategy {
+    /// Fill with null
+    Null,
+    /// Fill with the value of the previous bucket
+    Previous,
+    /// Fill with the value interpolated between the surrounding
+    /// buckets
+    Linear,
+    /// Fill with a constant value
+    Value(f64),
+}
+
+impl FillStrategy {
+    /// Parse the `fill` and `constant` arguments of `gap_fill`
+    fn try_new(fill: Option<&str>, constant: Option<f64>) -> DataFusionResult<Self> {
+        match (fill, constant) {
+            (None | Some("null"), _) => Ok(Self::Null),
+            (Some("previous"), _) => Ok(Self::Previous),
+            (Some("linear"), _) => Ok(Self::Linear),
+            (Some("value"), Some(constant)) => Ok(Self::Value(constant)),
+            (Some("value"), None) => Err(DataFusionError::Execution(
+                "gap_fill with 'value' fill expected a constant value".to_string(),
+            )),
+            (Some(fill), _) => Err(DataFusionError::Execution(format!(
+                "gap_fill expected fill of 'null', 'previous', 'linear' or 'value', got '{}'",
+                fill
+            ))),
+        }
+    }
+}
+
+/// Computes `gap_fill(value, time, interval[, fill[, constant]])`,
+/// where `interval` is the bucket size in nanoseconds
+#[derive(Debug, Clone, Copy)]
+pub(super) struct GapFill;
+
+impl SeriesFunction for GapFill {
+    fn name(&self) -> &'static str {
+        "gap_fill"
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64, DataType::Utf8, DataType::Float64]
+    }
+
+    fn return_value_type(&self, value_type: &DataType) -> DataType {
+        value_type.clone()
+    }
+
+    // The buckets are aligned to multiples of `interval`, and run
+    // from the bucket of the first point to the bucket of the last
+    // point. Each point is output at the start of its bucket and, if
+    // there are multiple points in a bucket, only the first is used
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let interval = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(interval)))) if *interval > 0 => *interval,
+            interval => {
+                return Err(DataFusionError::Execution(format!(
+                    "{} expected a positive interval, got {:?}",
+                    self.name(),
+                    interval.cloned().flatten()
+                )))
+            }
+        };
+        let fill = match args.get(1) {
+            Some(Some(ScalarValue::Utf8(fill))) => fill.as_deref(),
+            _ => None,
+        };
+        let constant = match args.get(2) {
+            Some(Some(ScalarValue::Float64(constant))) => *constant,
+            _ => None,
+        };
+        let strategy = FillStrategy::try_new(fill, constant)?;
+
+        let bucket = |time: i64| time - time.rem_euclid(interval);
+
+        if let (Some((first, _)), Some((last, _))) = (points.first(), points.last()) {
+            let buckets =
+                (i128::from(bucket(*last)) - i128::from(bucket(*first))) / i128::from(interval) + 1;
+            if buckets > i128::from(MAX_BUCKETS) {
+                return Err(DataFusionError::ResourcesExhausted(format!(
+                    "{} would emit {} buckets for a series, more than the maximum of {}",
+                    self.name(),
+                    buckets,
+                    MAX_BUCKETS
+                )));
+            }
+        }
+
+        let mut output: Vec<Point> = vec![];
+        // the last point seen, for filling the following buckets
+        let mut prev: Option<&Point> = None;
+        for point in points {
+            let time = bucket(point.0);
+            let next_bucket = match prev {
+                Some((prev_time, _)) if bucket(*prev_time) == time => continue,
+                Some((prev_time, _)) => bucket(*prev_time) + interval,
+                None => time,
+            };
+
+            let mut missing = next_bucket;
+            while missing < time {
+                let value = match (strategy, prev) {
+                    (FillStrategy::Null, _) => ScalarValue::try_from(&point.1.get_datatype())?,
+                    (FillStrategy::Previous, Some((_, prev_value))) => prev_value.clone(),
+                    (FillStrategy::Linear, Some((prev_time, prev_value))) => {
+                        let prev_time = bucket(*prev_time);
+                        let prev_value = value_as_f64(prev_value)?;
+                        let fraction = (missing - prev_time) as f64 / (time - prev_time) as f64;
+                        let value = prev_value + (value_as_f64(&point.1)? - prev_value) * fraction;
+                        f64_to_value(value, &point.1.get_datatype())?
+                    }
+                    (FillStrategy::Value(constant), _) => {
+                        f64_to_value(constant, &point.1.get_datatype())?
+                    }
+                    // there is always a previous point after the first bucket
+                    (FillStrategy::Previous | FillStrategy::Linear, None) => {
+                        unreachable!("missing bucket before the first point")
+                    }
+                };
+                output.push((missing, value));
+                missing += interval;
+            }
+
+            output.push((time, point.1.clone()));
+            prev = Some(point);
+        }
+        Ok(output)
+    }
+}
+
+/// Convert `value` to a value of `data_type`, truncating it for
+/// integer types
+fn f64_to_value(value: f64, data_type: &DataType) -> DataFusionResult<ScalarValue> {
+    match data_type {
+        DataType::Float64 => Ok(ScalarValue::Float64(Some(value))),
+        DataType::Int64 => Ok(ScalarValue::Int64(Some(value as i64))),
+        DataType::UInt64 => Ok(ScalarValue::UInt64(Some(value as u64))),
+        t => Err(DataFusionError::Internal(format!(
+            "Unexpected value type for gap_fill: {:?}",
+            t
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::{
+        error::DataFusionError,
+        prelude::{col, lit},
+        scalar::ScalarValue,
+    };
+
+    use super::{GapFill, MAX_BUCKETS};
+    use crate::{
+        series::{gap_fill, SeriesFunction},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[test]
+    fn test_gap_fill_max_buckets() {
+        let interval = [Some(ScalarValue::Int64(Some(1000)))];
+        let point = |time: i64| (time, ScalarValue::Float64(Some(1.0)));
+
+        let output = GapFill
+            .evaluate(&[point(0), point(2000)], &interval)
+            .unwrap();
+        assert_eq!(output.len(), 3);
+
+        let err = GapFill
+            .evaluate(&[point(0), point(MAX_BUCKETS * 1000)], &interval)
+            .unwrap_err();
+        assert!(
+            matches!(err, DataFusionError::ResourcesExhausted(_)),
+            "{}",
+            err
+        );
+        assert_eq!(
+            err.to_string(),
+            "Resources exhausted: gap_fill would emit 1000001 buckets for a series, more than the maximum of 1000000"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_null() {
+        run_case(
+            gap_fill(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64)])
+                .alias("gap_fill"),
+            vec![
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| gap_fill                                                                                                                                                                                                                                                                                                    |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": , \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_previous() {
+        run_case(
+            gap_fill(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64), lit("previous")])
+                .alias("gap_fill"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| gap_fill                                                                                                                                                                                                                                                                                                     |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_linear_f64() {
+        run_case(
+            gap_fill(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64), lit("linear")])
+                .alias("gap_fill"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| gap_fill                                                                                                                                                                                                                                                                                                       |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 2.5, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_linear_i64() {
+        run_case(
+            gap_fill(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(1000i64), lit("linear")])
+                .alias("gap_fill"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| gap_fill                                                                                                                                                                                                                                                                                                           |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 25, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 30, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_gap_fill_value() {
+        run_case(
+            gap_fill(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time"), lit(1000i64), lit("value"), lit(7.0)])
+                .alias("gap_fill"),
+            vec![
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| gap_fill                                                                                                                                                                                                                                                                                                          |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 7, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 30, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+-------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,306 @@
+//! Implementation of the InfluxQL `holt_winters` function, which
+//! forecasts future values of a series using triple exponential
+//! smoothing.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{value_as_f64, Point, SeriesFunction};
+
+/// The step used when searching for the best smoothing parameters
+const PARAMETER_STEP: f64 = 0.1;
+
+/// Computes `holt_winters(value, time, n[, seasonality])`: the next
+/// `n` values of the series, with a seasonal pattern that repeats
+/// every `seasonality` points (if greater than 1)
+#[derive(Debug, Clone, Copy)]
+pub(super) struct HoltWinters;
+
+impl SeriesFunction for HoltWinters {
+    fn name(&self) -> &'static str {
+        "holt_winters"
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64, DataType::Int64]
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    // The series is expected to be at a regular interval, such as the
+    // output of a GROUP BY time query. The interval is the smallest
+    // time between consecutive points, and any missing points are
+    // ignored when fitting the model.
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let n = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(n)))) if *n >= 0 => *n,
+            n => {
+                return Err(DataFusionError::Execution(format!(
+                    "{} expected a non negative number of points to forecast, got {:?}",
+                    self.name(),
+                    n.cloned().flatten()
+                )))
+            }
+        };
+        let seasonality = match args.get(1) {
+            Some(Some(ScalarValue::Int64(Some(m)))) if *m >= 0 => (*m as usize).max(1),
+            Some(Some(ScalarValue::Int64(Some(m)))) => {
+                return Err(DataFusionError::Execution(format!(
+                    "{} expected a non negative seasonality, got {}",
+                    self.name(),
+                    m
+                )))
+            }
+            _ => 1,
+        };
+
+        let interval = match points
+            .windows(2)
+            .map(|pair| pair[1].0 - pair[0].0)
+            .filter(|delta| *delta > 0)
+            .min()
+        {
+            Some(interval) => interval,
+            // need at least two distinct times to forecast
+            None => return Ok(vec![]),
+        };
+
+        let y = regular_values(points, interval)?;
+        if y.len() < 2 || y.len() < seasonality {
+            return Ok(vec![]);
+        }
+
+        let model = Model::initial(&y, seasonality);
+
+        // Search for the smoothing parameters that best fit the series
+        let steps = (1.0 / PARAMETER_STEP).round() as usize;
+        let mut best: Option<(f64, Model)> = None;
+        for alpha in 0..=steps {
+            for beta in 0..=steps {
+                for gamma in 0..=steps {
+                    let params = Parameters {
+                        alpha: alpha as f64 * PARAMETER_STEP,
+                        beta: beta as f64 * PARAMETER_STEP,
+                        gamma: gamma as f64 * PARAMETER_STEP,
+                    };
+                    let mut candidate = model.clone();
+                    let sse = candidate.fit(&y, params);
+                    if best.as_ref().map_or(true, |(best_sse, _)| sse < *best_sse) {
+                        best = Some((sse, candidate));
+                    }
+                }
+            }
+        }
+        let (_, model) = best.expect("at least one set of parameters");
+
+        let last_time = points.last().expect("points not empty").0;
+        Ok((1..=n)
+            .map(|k| {
+                let time = last_time + k * interval;
+                let value = model.forecast(y.len(), k as usize);
+                (time, ScalarValue::Float64(Some(value)))
+            })
+            .collect())
+    }
+}
+
+/// Return the values of the series at each `interval` since the
+/// first point, with `NaN` for any missing values. If there are
+/// multiple values in an interval, only the first is used.
+fn regular_values(points: &[Point], interval: i64) -> DataFusionResult<Vec<f64>> {
+    let first_time = points.first().expect("points not empty").0;
+
+    let mut y: Vec<f64> = vec![];
+    for (time, value) in points {
+        let idx = ((time - first_time) as f64 / interval as f64).round() as usize;
+        if idx < y.len() {
+            continue;
+        }
+        y.resize(idx, f64::NAN);
+        y.push(value_as_f64(value)?);
+    }
+    Ok(y)
+}
+
+/// The smoothing parameters of the model
+#[derive(Debug, Clone, Copy)]
+struct Parameters {
+    /// Smoothing of the level
+    alpha: f64,
+    /// Smoothing of the trend
+    beta: f64,
+    /// Smoothing of the seasonal component
+    gamma: f64,
+}
+
+/// The state of an additive Holt-Winters model
+#[derive(Debug, Clone)]
+struct Model {
+    level: f64,
+    trend: f64,
+    /// The seasonal component of each point in the season. Has one
+    /// (zero) entry for series without seasonality
+    seasonal: Vec<f64>,
+}
+
+impl Model {
+    /// The initial state for the series `y` with the seasonal
+    /// pattern repeating every `seasonality` values
+    fn initial(y: &[f64], seasonality: usize) -> Self {
+        if seasonality <= 1 {
+            let trend = if y[1].is_nan() { 0.0 } else { y[1] - y[0] };
+            return Self {
+                level: y[0],
+                trend,
+                seasonal: vec![0.0],
+            };
+        }
+
+        let m = seasonality as f64;
+        let first_season = &y[..seasonality];
+
+        let level = first_season
+            .iter()
+            .filter(|v| !v.is_nan())
+            .map(|v| v / m)
+            .sum();
+
+        let trend = (0..seasonality)
+            .filter(|i| seasonality + i < y.len())
+            .map(|i| (y[seasonality + i] - y[i]) / (m * m))
+            .filter(|v| !v.is_nan())
+            .sum();
+
+        let seasonal = first_season
+            .iter()
+            .map(|v| if v.is_nan() { 0.0 } else { v - level })
+            .collect();
+
+        Self {
+            level,
+            trend,
+            seasonal,
+        }
+    }
+
+    /// Update the model with each of the values of `y` using
+    /// `params`, returning the sum of squared errors of the one step
+    /// ahead forecasts
+    fn fit(&mut self, y: &[f64], params: Parameters) -> f64 {
+        let Parameters { alpha, beta, gamma } = params;
+        let m = self.seasonal.len();
+
+        let mut sse = 0.0;
+        for (t, value) in y.iter().enumerate().skip(1) {
+            let season = self.seasonal[t % m];
+            let expected = self.level + self.trend;
+
+            if value.is_nan() {
+                // nothing to learn from a missing value
+                self.level = expected;
+                continue;
+            }
+
+            sse += (value - (expected + season)).powi(2);
+
+            let level = alpha * (value - season) + (1.0 - alpha) * expected;
+            self.trend = beta * (level - self.level) + (1.0 - beta) * self.trend;
+            self.level = level;
+            if m > 1 {
+                self.seasonal[t % m] = gamma * (value - level) + (1.0 - gamma) * season;
+            }
+        }
+        sse
+    }
+
+    /// The forecast value `k` steps after the end of a series of
+    /// `len` values
+    fn forecast(&self, len: usize, k: usize) -> f64 {
+        let m = self.seasonal.len();
+        self.level + k as f64 * self.trend + self.seasonal[(len + k - 1) % m]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use arrow::{
+        array::{Float64Array, TimestampNanosecondArray},
+        datatypes::{DataType, Field, Schema},
+        record_batch::RecordBatch,
+    };
+    use datafusion::prelude::{col, lit};
+    use schema::{TIME_DATA_TIMEZONE, TIME_DATA_TYPE};
+
+    use crate::{
+        series::holt_winters,
+        test_util::{run_case, run_with_inputs, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_holt_winters_linear() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("value", DataType::Float64, true),
+            Field::new("time", TIME_DATA_TYPE(), true),
+        ]));
+
+        let batch = RecordBatch::try_new(
+            Arc::clone(&schema),
+            vec![
+                Arc::new(Float64Array::from(vec![1.0, 2.0, 3.0, 4.0])),
+                Arc::new(TimestampNanosecondArray::from_vec(
+                    vec![1000, 2000, 3000, 4000],
+                    TIME_DATA_TIMEZONE(),
+                )),
+            ],
+        )
+        .unwrap();
+
+        let agg = holt_winters(&unbounded_memory())
+            .call(vec![col("value"), col("time"), lit(2i64)])
+            .alias("holt_winters");
+
+        let actual = run_with_inputs(schema, vec![agg], None, vec![batch]).await;
+
+        let expected = vec![
+            "+------------------------------------------------------------------------------------------------------+",
+            "| holt_winters                                                                                         |",
+            "+------------------------------------------------------------------------------------------------------+",
+            "| [{\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 6, \"time\": 1970-01-01 00:00:00.000006}] |",
+            "+------------------------------------------------------------------------------------------------------+",
+        ];
+
+        assert_eq!(
+            expected, actual,
+            "\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+            expected, actual
+        );
+    }
+
+    #[tokio::test]
+    async fn test_holt_winters_not_enough_values() {
+        run_case(
+            holt_winters(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(2i64), lit(10i64)])
+                .alias("holt_winters"),
+            vec![
+                "+--------------+",
+                "| holt_winters |",
+                "+--------------+",
+                "| []           |",
+                "+--------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,132 @@
+//! Implementation of the InfluxQL `integral` function, which computes
+//! the area under the curve of the values using the trapezoidal rule.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{dedup_times, value_as_f64, Point, SeriesFunction};
+
+/// The default unit of time for the area: one second
+const DEFAULT_UNIT_NANOS: i64 = 1_000_000_000;
+
+/// Computes `integral(value, time[, unit])`, where `unit` is the
+/// duration in nanoseconds the area is computed over
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Integral;
+
+impl SeriesFunction for Integral {
+    fn name(&self) -> &'static str {
+        "integral"
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64]
+    }
+
+    fn is_aggregate(&self) -> bool {
+        true
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let unit = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(unit)))) => *unit,
+            _ => DEFAULT_UNIT_NANOS,
+        };
+        if unit <= 0 {
+            return Err(DataFusionError::Execution(format!(
+                "{} expected a positive unit, got {}",
+                self.name(),
+                unit
+            )));
+        }
+
+        let points = dedup_times(points);
+        let first_time = match points.first() {
+            Some((time, _)) => *time,
+            None => return Ok(vec![]),
+        };
+
+        let mut area = 0.0;
+        for pair in points.windows(2) {
+            let (prev_time, prev_value) = pair[0];
+            let (time, value) = pair[1];
+
+            let elapsed = (time - prev_time) as f64 / unit as f64;
+            area += (value_as_f64(prev_value)? + value_as_f64(value)?) / 2.0 * elapsed;
+        }
+
+        Ok(vec![(first_time, ScalarValue::Float64(Some(area)))])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use crate::{
+        series::integral,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_integral_f64() {
+        run_case(
+            integral(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64)])
+                .alias("integral"),
+            vec![
+                "+----------+",
+                "| integral |",
+                "+----------+",
+                "| 15       |",
+                "+----------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_integral_i64() {
+        run_case(
+            integral(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(1000i64)])
+                .alias("integral"),
+            vec![
+                "+----------+",
+                "| integral |",
+                "+----------+",
+                "| 150      |",
+                "+----------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_integral_default_unit() {
+        run_case(
+            integral(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time")])
+                .alias("integral"),
+            vec![
+                "+----------+",
+                "| integral |",
+                "+----------+",
+                "| 0.00015  |",
+                "+----------+",
+            ],
+        )
+        .await;
+    }
+}
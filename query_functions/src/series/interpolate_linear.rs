@@ -0,0 +1,113 @@
+//! Implementation of the `interpolate_linear` function, which fills in
+//! null values by linear interpolation between the surrounding non
+//! null values.
+
+use arrow::datatypes::DataType;
+use datafusion::{error::Result as DataFusionResult, scalar::ScalarValue};
+
+use super::{value_as_f64, Point, SeriesFunction};
+
+/// Computes `interpolate_linear(value, time)`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct InterpolateLinear;
+
+impl SeriesFunction for InterpolateLinear {
+    fn name(&self) -> &'static str {
+        "interpolate_linear"
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    fn keeps_nulls(&self) -> bool {
+        true
+    }
+
+    // Each null value is interpolated by time between the previous and
+    // next non null values. Null values before the first or after the
+    // last non null value stay null, as there is nothing to
+    // interpolate between
+    fn evaluate(
+        &self,
+        points: &[Point],
+        _args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let known = points
+            .iter()
+            .filter(|(_, value)| !value.is_null())
+            .map(|(time, value)| Ok((*time, value_as_f64(value)?)))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        // the index in `known` of the next non null value
+        let mut next = 0;
+        Ok(points
+            .iter()
+            .map(|(time, value)| {
+                let value = if value.is_null() {
+                    match (next.checked_sub(1).map(|i| known[i]), known.get(next)) {
+                        (Some((prev_time, prev_value)), Some((next_time, _)))
+                            if *next_time == prev_time =>
+                        {
+                            Some(prev_value)
+                        }
+                        (Some((prev_time, prev_value)), Some((next_time, next_value))) => {
+                            let fraction =
+                                (time - prev_time) as f64 / (next_time - prev_time) as f64;
+                            Some(prev_value + (next_value - prev_value) * fraction)
+                        }
+                        _ => None,
+                    }
+                } else {
+                    next += 1;
+                    Some(known[next - 1].1)
+                };
+                (*time, ScalarValue::Float64(value))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        series::interpolate_linear,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_interpolate_linear_f64() {
+        run_case(
+            interpolate_linear(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time")])
+                .alias("interpolate_linear"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| interpolate_linear                                                                                                                                                                                                                                                                                             |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 2.5, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_interpolate_linear_i64() {
+        run_case(
+            interpolate_linear(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time")])
+                .alias("interpolate_linear"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| interpolate_linear                                                                                                                                                                                                                                                                                                 |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 25, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 30, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,88 @@
+//! Implementation of the `locf` function, which fills in null values
+//! with the last non null value before them (last observation carried
+//! forward).
+
+use arrow::datatypes::DataType;
+use datafusion::{error::Result as DataFusionResult, scalar::ScalarValue};
+
+use super::{Point, SeriesFunction};
+
+/// Computes `locf(value, time)`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Locf;
+
+impl SeriesFunction for Locf {
+    fn name(&self) -> &'static str {
+        "locf"
+    }
+
+    fn return_value_type(&self, value_type: &DataType) -> DataType {
+        value_type.clone()
+    }
+
+    fn keeps_nulls(&self) -> bool {
+        true
+    }
+
+    // Null values before the first non null value stay null
+    fn evaluate(
+        &self,
+        points: &[Point],
+        _args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let mut last: Option<&ScalarValue> = None;
+        Ok(points
+            .iter()
+            .map(|(time, value)| {
+                if !value.is_null() {
+                    last = Some(value);
+                }
+                (*time, last.unwrap_or(value).clone())
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::col;
+
+    use crate::{
+        series::locf,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_locf_f64() {
+        run_case(
+            locf(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time")])
+                .alias("locf"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| locf                                                                                                                                                                                                                                                                                                         |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 2, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 1, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 5, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 3, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_locf_i64() {
+        run_case(
+            locf(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time")])
+                .alias("locf"),
+            vec![
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| locf                                                                                                                                                                                                                                                                                                               |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 20, \"time\": 1970-01-01 00:00:00.000001}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 40, \"time\": 1970-01-01 00:00:00.000003}, {\"value\": 10, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 50, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 30, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+}
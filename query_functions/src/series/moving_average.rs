@@ -0,0 +1,128 @@
+//! Implementation of the InfluxQL `moving_average` function, which
+//! computes the mean of each window of N consecutive values.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{value_as_f64, Point, SeriesFunction};
+
+/// Computes `moving_average(value, time, n)`
+#[derive(Debug, Clone, Copy)]
+pub(super) struct MovingAverage;
+
+impl SeriesFunction for MovingAverage {
+    fn name(&self) -> &'static str {
+        "moving_average"
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64]
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    // Like InfluxQL, nothing is output for the partial windows at the
+    // start of the series: the first value is the average of the
+    // first `n` values, at the time of the `n`th value
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let n = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(n)))) if *n > 0 => *n as usize,
+            n => {
+                return Err(DataFusionError::Execution(format!(
+                    "{} expected a positive number of points, got {:?}",
+                    self.name(),
+                    n.cloned().flatten()
+                )))
+            }
+        };
+
+        let values = points
+            .iter()
+            .map(|(_, v)| value_as_f64(v))
+            .collect::<DataFusionResult<Vec<_>>>()?;
+
+        let mut output = vec![];
+        if values.len() < n {
+            return Ok(output);
+        }
+
+        // keep a running sum rather than summing each window
+        let mut sum: f64 = values[..n - 1].iter().sum();
+        for (idx, (time, _)) in points.iter().enumerate().skip(n - 1) {
+            sum += values[idx];
+            output.push((*time, ScalarValue::Float64(Some(sum / n as f64))));
+            sum -= values[idx + 1 - n];
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use crate::{
+        series::moving_average,
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_moving_average_f64() {
+        run_case(
+            moving_average(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(2i64)])
+                .alias("moving_average"),
+            vec![
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| moving_average                                                                                                                                                                                             |",
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 3, \"time\": 1970-01-01 00:00:00.000002}, {\"value\": 2.5, \"time\": 1970-01-01 00:00:00.000004}, {\"value\": 3, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 4, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_moving_average_i64() {
+        run_case(
+            moving_average(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(4i64)])
+                .alias("moving_average"),
+            vec![
+                "+----------------------------------------------------------------------------------------------------------+",
+                "| moving_average                                                                                           |",
+                "+----------------------------------------------------------------------------------------------------------+",
+                "| [{\"value\": 30, \"time\": 1970-01-01 00:00:00.000005}, {\"value\": 32.5, \"time\": 1970-01-01 00:00:00.000006}] |",
+                "+----------------------------------------------------------------------------------------------------------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_moving_average_more_than_available() {
+        run_case(
+            moving_average(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time"), lit(6i64)])
+                .alias("moving_average"),
+            vec![
+                "+----------------+",
+                "| moving_average |",
+                "+----------------+",
+                "| []             |",
+                "+----------------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,169 @@
+//! Implementation of the `rate` and `irate` functions, which compute
+//! the per unit of time rate of increase of a counter, allowing for
+//! counter resets.
+
+use arrow::datatypes::DataType;
+use datafusion::{
+    error::{DataFusionError, Result as DataFusionResult},
+    scalar::ScalarValue,
+};
+
+use super::{dedup_times, value_as_f64, Point, SeriesFunction};
+
+/// The default unit of time for the rate: one second
+const DEFAULT_UNIT_NANOS: i64 = 1_000_000_000;
+
+/// Computes `rate(value, time[, unit])` or `irate(value, time[,
+/// unit])`, where `unit` is the duration in nanoseconds the rate is
+/// computed over
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Rate {
+    /// If true, only the last two values are used (`irate`), rather
+    /// than all values (`rate`)
+    instant: bool,
+}
+
+impl Rate {
+    pub(super) fn new(instant: bool) -> Self {
+        Self { instant }
+    }
+}
+
+impl SeriesFunction for Rate {
+    fn name(&self) -> &'static str {
+        if self.instant {
+            "irate"
+        } else {
+            "rate"
+        }
+    }
+
+    fn arg_types(&self) -> Vec<DataType> {
+        vec![DataType::Int64]
+    }
+
+    fn is_aggregate(&self) -> bool {
+        true
+    }
+
+    fn return_value_type(&self, _value_type: &DataType) -> DataType {
+        DataType::Float64
+    }
+
+    // A value lower than the previous one is a counter reset, after
+    // which the counter started again from zero
+    fn evaluate(
+        &self,
+        points: &[Point],
+        args: &[Option<ScalarValue>],
+    ) -> DataFusionResult<Vec<Point>> {
+        let unit = match args.first() {
+            Some(Some(ScalarValue::Int64(Some(unit)))) => *unit,
+            _ => DEFAULT_UNIT_NANOS,
+        };
+        if unit <= 0 {
+            return Err(DataFusionError::Execution(format!(
+                "{} expected a positive unit, got {}",
+                self.name(),
+                unit
+            )));
+        }
+
+        let mut points = dedup_times(points);
+        if self.instant && points.len() > 2 {
+            points.drain(..points.len() - 2);
+        }
+
+        // need at least two values to compute a rate
+        let (first_time, last_time) = match (points.first(), points.last()) {
+            (Some((first_time, _)), Some((last_time, _))) if first_time < last_time => {
+                (*first_time, *last_time)
+            }
+            _ => return Ok(vec![]),
+        };
+
+        let mut increase = 0.0;
+        for pair in points.windows(2) {
+            let prev_value = value_as_f64(&pair[0].1)?;
+            let value = value_as_f64(&pair[1].1)?;
+
+            increase += if value < prev_value {
+                value
+            } else {
+                value - prev_value
+            };
+        }
+
+        let elapsed = (last_time - first_time) as f64 / unit as f64;
+        Ok(vec![(
+            last_time,
+            ScalarValue::Float64(Some(increase / elapsed)),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datafusion::prelude::{col, lit};
+
+    use crate::{
+        series::{irate, rate},
+        test_util::{run_case, unbounded_memory},
+    };
+
+    #[tokio::test]
+    async fn test_rate_f64() {
+        run_case(
+            rate(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64)])
+                .alias("rate"),
+            vec!["+------+", "| rate |", "+------+", "| 2    |", "+------+"],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_rate_i64() {
+        run_case(
+            rate(&unbounded_memory())
+                .call(vec![col("i64_value"), col("time"), lit(1000i64)])
+                .alias("rate"),
+            vec!["+------+", "| rate |", "+------+", "| 20   |", "+------+"],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_irate_f64() {
+        run_case(
+            irate(&unbounded_memory())
+                .call(vec![col("f64_value"), col("time"), lit(1000i64)])
+                .alias("irate"),
+            vec![
+                "+-------+",
+                "| irate |",
+                "+-------+",
+                "| 3     |",
+                "+-------+",
+            ],
+        )
+        .await;
+    }
+
+    #[tokio::test]
+    async fn test_irate_u64() {
+        run_case(
+            irate(&unbounded_memory())
+                .call(vec![col("u64_value"), col("time"), lit(1000i64)])
+                .alias("irate"),
+            vec![
+                "+-------+",
+                "| irate |",
+                "+-------+",
+                "| 30    |",
+                "+-------+",
+            ],
+        )
+        .await;
+    }
+}
@@ -0,0 +1,212 @@
+//! Helpers for testing the aggregate functions in this crate against
+//! a small table of values.
+
+use std::sync::Arc;
+
+use arrow::{
+    array::{
+        BooleanArray, Float64Array, Int64Array, StringArray, TimestampNanosecondArray, UInt64Array,
+    },
+    datatypes::{DataType, Field, Schema, SchemaRef},
+    record_batch::RecordBatch,
+    util::pretty::pretty_format_batches,
+};
+use datafusion::{
+    datasource::MemTable,
+    execution::runtime_env::{RuntimeConfig, RuntimeEnv},
+    prelude::{Expr, SessionContext},
+};
+use schema::{TIME_DATA_TIMEZONE, TIME_DATA_TYPE};
+
+use crate::selectors::AccumulatorMemory;
+
+/// Memory for the functions under test, limited to `limit` bytes
+pub(crate) fn memory_with_limit(limit: usize) -> Arc<AccumulatorMemory> {
+    let runtime = RuntimeEnv::new(RuntimeConfig::new()).expect("creating runtime");
+    Arc::new(AccumulatorMemory::new(Arc::new(runtime), limit))
+}
+
+/// Memory for the functions under test that does not limit their
+/// state
+pub(crate) fn unbounded_memory() -> Arc<AccumulatorMemory> {
+    memory_with_limit(usize::MAX)
+}
+
+/// Runs the expr using `run_plan` and compares the result to `expected`
+pub(crate) async fn run_case(expr: Expr, expected: Vec<&'static str>) {
+    println!("Running case for {}", expr);
+
+    let actual = run_plan(vec![expr.clone()]).await;
+
+    assert_eq!(
+        expected, actual,
+        "\n\nexpr: {}\n\nEXPECTED:\n{:#?}\nACTUAL:\n{:#?}\n",
+        expr, expected, actual
+    );
+}
+
+/// Runs the aggregates `aggs` using `run_projected_plan` with no projection
+pub(crate) async fn run_plan(aggs: Vec<Expr>) -> Vec<String> {
+    run_projected_plan(aggs, None).await
+}
+
+/// Run a plan against the following input table as "t"
+///
+/// ```text
+/// +-----------+-----------+-----------+--------------+------------+----------------------------+,
+/// | f64_value | i64_value | u64_value | string_value | bool_value | time                       |,
+/// +-----------+-----------+--------------+------------+----------------------------+,
+/// | 2         | 20        | 20        | two          | true       | 1970-01-01 00:00:00.000001 |,
+/// | 4         | 40        | 40        | four         | false      | 1970-01-01 00:00:00.000002 |,
+/// |           |           |           |              |            | 1970-01-01 00:00:00.000003 |,
+/// | 1         | 10        | 10        | a_one        | true       | 1970-01-01 00:00:00.000004 |,
+/// | 5         | 50        | 50        | z_five       | false      | 1970-01-01 00:00:00.000005 |,
+/// | 3         | 30        | 30        | three        | false      | 1970-01-01 00:00:00.000006 |,
+/// +-----------+-----------+--------------+------------+----------------------------+,
+/// ```
+///
+/// If `projection` is specified, it is applied to the output of
+/// the aggregate
+pub(crate) async fn run_projected_plan(
+    aggs: Vec<Expr>,
+    projection: Option<Vec<Expr>>,
+) -> Vec<String> {
+    // define a schema for input
+    // (value) and timestamp
+    let schema = Arc::new(Schema::new(vec![
+        Field::new("f64_value", DataType::Float64, true),
+        Field::new("i64_value", DataType::Int64, true),
+        Field::new("u64_value", DataType::UInt64, true),
+        Field::new("string_value", DataType::Utf8, true),
+        Field::new("bool_value", DataType::Boolean, true),
+        Field::new("time", TIME_DATA_TYPE(), true),
+    ]));
+
+    // define data in two partitions
+    let batch1 = RecordBatch::try_new(
+        Arc::clone(&schema),
+        vec![
+            Arc::new(Float64Array::from(vec![Some(2.0), Some(4.0), None])),
+            Arc::new(Int64Array::from(vec![Some(20), Some(40), None])),
+            Arc::new(UInt64Array::from(vec![Some(20), Some(40), None])),
+            Arc::new(StringArray::from(vec![Some("two"), Some("four"), None])),
+            Arc::new(BooleanArray::from(vec![Some(true), Some(false), None])),
+            Arc::new(TimestampNanosecondArray::from_vec(
+                vec![1000, 2000, 3000],
+                TIME_DATA_TIMEZONE(),
+            )),
+        ],
+    )
+    .unwrap();
+
+    // No values in this batch
+    let batch2 = match RecordBatch::try_new(
+        Arc::clone(&schema),
+        vec![
+            Arc::new(Float64Array::from(vec![] as Vec<Option<f64>>)),
+            Arc::new(Int64Array::from(vec![] as Vec<Option<i64>>)),
+            Arc::new(UInt64Array::from(vec![] as Vec<Option<u64>>)),
+            Arc::new(StringArray::from(vec![] as Vec<Option<&str>>)),
+            Arc::new(BooleanArray::from(vec![] as Vec<Option<bool>>)),
+            Arc::new(TimestampNanosecondArray::from_vec(
+                vec![],
+                TIME_DATA_TIMEZONE(),
+            )),
+        ],
+    ) {
+        Ok(a) => a,
+        _ => unreachable!(),
+    };
+
+    let batch3 = RecordBatch::try_new(
+        Arc::clone(&schema),
+        vec![
+            Arc::new(Float64Array::from(vec![Some(1.0), Some(5.0), Some(3.0)])),
+            Arc::new(Int64Array::from(vec![Some(10), Some(50), Some(30)])),
+            Arc::new(UInt64Array::from(vec![Some(10), Some(50), Some(30)])),
+            Arc::new(StringArray::from(vec![
+                Some("a_one"),
+                Some("z_five"),
+                Some("three"),
+            ])),
+            Arc::new(BooleanArray::from(vec![
+                Some(true),
+                Some(false),
+                Some(false),
+            ])),
+            Arc::new(TimestampNanosecondArray::from_vec(
+                vec![4000, 5000, 6000],
+                TIME_DATA_TIMEZONE(),
+            )),
+        ],
+    )
+    .unwrap();
+
+    // Ensure the answer is the same regardless of the order of inputs
+    let input = vec![batch1, batch2, batch3];
+    let input_string = pretty_format_batches(&input).unwrap();
+    let results = run_with_inputs(
+        Arc::clone(&schema),
+        aggs.clone(),
+        projection.clone(),
+        input.clone(),
+    )
+    .await;
+
+    use itertools::Itertools;
+    // Get all permutations of the input
+    for p in input.iter().permutations(3) {
+        let p_batches = p.into_iter().cloned().collect::<Vec<_>>();
+        let p_input_string = pretty_format_batches(&p_batches).unwrap();
+        let p_results = run_with_inputs(
+            Arc::clone(&schema),
+            aggs.clone(),
+            projection.clone(),
+            p_batches,
+        )
+        .await;
+        assert_eq!(
+            results, p_results,
+            "Mismatch with permutation.\n\
+                    Input1 \n\n\
+                    {}\n\n\
+                    produces output:\n\n\
+                    {:#?}\n\n\
+                    Input 2\n\n\
+                    {}\n\n\
+                    produces output:\n\n\
+                    {:#?}\n\n",
+            input_string, results, p_input_string, p_results
+        );
+    }
+
+    results
+}
+
+pub(crate) async fn run_with_inputs(
+    schema: SchemaRef,
+    aggs: Vec<Expr>,
+    projection: Option<Vec<Expr>>,
+    inputs: Vec<RecordBatch>,
+) -> Vec<String> {
+    let provider = MemTable::try_new(Arc::clone(&schema), vec![inputs]).unwrap();
+    let ctx = SessionContext::new();
+    ctx.register_table("t", Arc::new(provider)).unwrap();
+
+    let df = ctx.table("t").unwrap();
+    let df = df.aggregate(vec![], aggs).unwrap();
+    let df = match projection {
+        Some(projection) => df.select(projection).unwrap(),
+        None => df,
+    };
+
+    // execute the query
+    let record_batches = df.collect().await.unwrap();
+
+    pretty_format_batches(&record_batches)
+        .unwrap()
+        .to_string()
+        .split('\n')
+        .map(|s| s.to_owned())
+        .collect()
+}
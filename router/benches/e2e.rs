@@ -65,9 +65,12 @@ fn e2e_benchmarks(c: &mut Criterion) {
         let write_buffer = init_write_buffer(1);
         let schema_validator =
             SchemaValidator::new(Arc::clone(&catalog), Arc::clone(&ns_cache), &*metrics);
-        let partitioner = Partitioner::new(PartitionTemplate {
-            parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
-        });
+        let partitioner = Partitioner::new(
+            PartitionTemplate {
+                parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
+            },
+            Arc::clone(&ns_cache),
+        );
 
         let handler_stack = schema_validator.and_then(
             partitioner.and_then(WriteSummaryAdapter::new(FanOutAdaptor::new(write_buffer))),
@@ -0,0 +1,272 @@
+//! An audit log of the DML operations accepted by the router.
+//!
+//! Each successfully routed write & delete is described by an
+//! [`AuditRecord`], which is passed to an [`AuditLog`]. The [`AuditLog`]
+//! buffers records in memory and asynchronously delivers them in batches to
+//! an [`AuditSink`] - the request path never waits for a sink, and records are
+//! dropped (and counted) rather than applying backpressure to writes if the
+//! sink cannot keep up.
+//!
+//! Records are delivered at most once - a batch that the sink fails to
+//! persist is logged and discarded.
+
+mod file_sink;
+pub use file_sink::*;
+
+mod object_store_sink;
+pub use object_store_sink::*;
+
+use async_trait::async_trait;
+use iox_time::Time;
+use metric::U64Counter;
+use observability_deps::tracing::*;
+use serde_json::json;
+use std::{error::Error, fmt::Debug, sync::Arc};
+use tokio::sync::mpsc;
+
+/// The maximum number of records delivered to an [`AuditSink`] in a single
+/// batch.
+const MAX_BATCH_SIZE: usize = 1_000;
+
+/// The DML operation described by an [`AuditRecord`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOperation {
+    /// A write of data to one or more tables.
+    Write,
+    /// A delete of data matching a predicate.
+    Delete,
+}
+
+impl AuditOperation {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AuditOperation::Write => "write",
+            AuditOperation::Delete => "delete",
+        }
+    }
+}
+
+/// A description of a single DML operation accepted by the router.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    /// The time at which the operation was accepted.
+    pub time: Time,
+    /// The operation performed.
+    pub operation: AuditOperation,
+    /// The namespace operated on.
+    pub namespace: String,
+    /// The (sorted) tables written to, or the table deleted from (if any).
+    pub tables: Vec<String>,
+    /// The number of lines (or equivalent points) written.
+    pub num_lines: usize,
+    /// The delete predicate, for delete operations.
+    pub predicate: Option<String>,
+    /// A (non-secret) identifier of the principal that performed the
+    /// operation, if the request carried credentials.
+    pub principal: Option<String>,
+    /// The trace ID of the request, if traced.
+    pub trace_id: Option<String>,
+    /// The span ID of the request, if traced.
+    pub span_id: Option<String>,
+}
+
+impl AuditRecord {
+    /// Serialise this record as a single line of JSON (without the trailing
+    /// newline).
+    pub fn to_json_line(&self) -> String {
+        json!({
+            "time": self.time.to_rfc3339(),
+            "operation": self.operation.as_str(),
+            "namespace": self.namespace,
+            "tables": self.tables,
+            "num_lines": self.num_lines,
+            "predicate": self.predicate,
+            "principal": self.principal,
+            "trace_id": self.trace_id,
+            "span_id": self.span_id,
+        })
+        .to_string()
+    }
+}
+
+/// An error returned by an [`AuditSink`].
+pub type AuditSinkError = Box<dyn Error + Send + Sync>;
+
+/// A destination of [`AuditRecord`] batches, such as a file or object store.
+#[async_trait]
+pub trait AuditSink: Debug + Send + Sync {
+    /// Persist `records`, in order.
+    async fn write(&self, records: &[AuditRecord]) -> Result<(), AuditSinkError>;
+}
+
+/// An asynchronous pipeline delivering [`AuditRecord`] instances to an
+/// [`AuditSink`].
+///
+/// Records are buffered in a bounded queue and delivered by a background
+/// task, which exits once the [`AuditLog`] is dropped and the queue is
+/// drained.
+#[derive(Debug)]
+pub struct AuditLog {
+    tx: mpsc::Sender<AuditRecord>,
+    dropped: U64Counter,
+}
+
+impl AuditLog {
+    /// Initialise an [`AuditLog`] buffering up to `capacity` records for
+    /// delivery to `sink`.
+    ///
+    /// # Panics
+    ///
+    /// This spawns the delivery task, and so must be called from within a
+    /// tokio runtime. Panics if `capacity` is 0.
+    pub fn new(sink: Arc<dyn AuditSink>, capacity: usize, metrics: &metric::Registry) -> Self {
+        let records = metrics.register_metric::<U64Counter>(
+            "router_audit_records",
+            "number of dml audit records by delivery result",
+        );
+        let dropped = records.recorder(&[("result", "dropped")]);
+        let delivered = records.recorder(&[("result", "delivered")]);
+        let failed = records.recorder(&[("result", "failed")]);
+
+        let (tx, rx) = mpsc::channel(capacity);
+        tokio::spawn(deliver(rx, sink, delivered, failed));
+
+        Self { tx, dropped }
+    }
+
+    /// Enqueue `record` for delivery, dropping it if the queue is full.
+    pub fn record(&self, record: AuditRecord) {
+        if self.tx.try_send(record).is_err() {
+            warn!("audit log queue full - dropping audit record");
+            self.dropped.inc(1);
+        }
+    }
+}
+
+/// Deliver the records received on `rx` to `sink` in batches, until all
+/// senders are dropped.
+async fn deliver(
+    mut rx: mpsc::Receiver<AuditRecord>,
+    sink: Arc<dyn AuditSink>,
+    delivered: U64Counter,
+    failed: U64Counter,
+) {
+    let mut batch = Vec::with_capacity(MAX_BATCH_SIZE);
+    while let Some(record) = rx.recv().await {
+        // Deliver all the records queued while the previous batch was being
+        // written.
+        batch.push(record);
+        while batch.len() < MAX_BATCH_SIZE {
+            match rx.try_recv() {
+                Ok(record) => batch.push(record),
+                Err(_) => break,
+            }
+        }
+
+        match sink.write(&batch).await {
+            Ok(()) => delivered.inc(batch.len() as _),
+            Err(e) => {
+                error!(error=%e, num_records=batch.len(), "failed to write audit records");
+                failed.inc(batch.len() as _);
+            }
+        }
+        batch.clear();
+    }
+
+    debug!("audit log closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metric::{Attributes, Metric};
+    use parking_lot::Mutex;
+    use std::time::Duration;
+    use test_helpers::timeout::FutureTimeout;
+
+    #[derive(Debug, Default)]
+    struct MemorySink {
+        records: Mutex<Vec<AuditRecord>>,
+        fail: bool,
+    }
+
+    #[async_trait]
+    impl AuditSink for MemorySink {
+        async fn write(&self, records: &[AuditRecord]) -> Result<(), AuditSinkError> {
+            if self.fail {
+                return Err("bananas".into());
+            }
+            self.records.lock().extend_from_slice(records);
+            Ok(())
+        }
+    }
+
+    fn record(namespace: &str) -> AuditRecord {
+        AuditRecord {
+            time: Time::from_timestamp_nanos(0),
+            operation: AuditOperation::Write,
+            namespace: namespace.to_string(),
+            tables: vec!["platanos".to_string()],
+            num_lines: 1,
+            predicate: None,
+            principal: Some("bananas".to_string()),
+            trace_id: None,
+            span_id: None,
+        }
+    }
+
+    fn metric_value(metrics: &metric::Registry, result: &'static str) -> u64 {
+        metrics
+            .get_instrument::<Metric<U64Counter>>("router_audit_records")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("result", result)]))
+            .expect("failed to get observer")
+            .fetch()
+    }
+
+    async fn wait_for(f: impl Fn() -> bool) {
+        async {
+            while !f() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await
+    }
+
+    #[test]
+    fn test_to_json_line() {
+        assert_eq!(
+            record("bananas_test").to_json_line(),
+            r#"{"namespace":"bananas_test","num_lines":1,"operation":"write","predicate":null,"principal":"bananas","span_id":null,"tables":["platanos"],"time":"1970-01-01T00:00:00+00:00","trace_id":null}"#
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery() {
+        let metrics = metric::Registry::default();
+        let sink = Arc::new(MemorySink::default());
+        let log = AuditLog::new(Arc::clone(&sink) as _, 10, &metrics);
+
+        log.record(record("a"));
+        log.record(record("b"));
+
+        wait_for(|| sink.records.lock().len() == 2).await;
+        assert_eq!(*sink.records.lock(), [record("a"), record("b")]);
+        assert_eq!(metric_value(&metrics, "delivered"), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sink_failure() {
+        let metrics = metric::Registry::default();
+        let sink = Arc::new(MemorySink {
+            fail: true,
+            ..Default::default()
+        });
+        let log = AuditLog::new(sink, 10, &metrics);
+
+        log.record(record("a"));
+
+        wait_for(|| metric_value(&metrics, "failed") == 1).await;
+    }
+}
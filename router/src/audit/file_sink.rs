@@ -0,0 +1,84 @@
+use super::{AuditRecord, AuditSink, AuditSinkError};
+use async_trait::async_trait;
+use std::path::Path;
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::Mutex,
+};
+
+/// An [`AuditSink`] appending records to a local file, one JSON object per
+/// line.
+#[derive(Debug)]
+pub struct FileAuditSink {
+    file: Mutex<File>,
+}
+
+impl FileAuditSink {
+    /// Open (or create) the file at `path`, appending records to any existing
+    /// content.
+    pub async fn new(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .await?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+}
+
+#[async_trait]
+impl AuditSink for FileAuditSink {
+    async fn write(&self, records: &[AuditRecord]) -> Result<(), AuditSinkError> {
+        let mut buf = String::new();
+        for record in records {
+            buf.push_str(&record.to_json_line());
+            buf.push('\n');
+        }
+
+        let mut file = self.file.lock().await;
+        file.write_all(buf.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditOperation;
+    use iox_time::Time;
+
+    #[tokio::test]
+    async fn test_append() {
+        let dir = test_helpers::tmp_dir().unwrap();
+        let path = dir.path().join("audit.log");
+
+        let record = AuditRecord {
+            time: Time::from_timestamp_nanos(0),
+            operation: AuditOperation::Delete,
+            namespace: "bananas_test".to_string(),
+            tables: vec![],
+            num_lines: 0,
+            predicate: Some("tag1=A".to_string()),
+            principal: None,
+            trace_id: None,
+            span_id: None,
+        };
+
+        let sink = FileAuditSink::new(&path).await.unwrap();
+        sink.write(&[record.clone()]).await.unwrap();
+        drop(sink);
+
+        // Existing records are preserved.
+        let sink = FileAuditSink::new(&path).await.unwrap();
+        sink.write(&[record.clone()]).await.unwrap();
+
+        let got = std::fs::read_to_string(&path).unwrap();
+        let line = record.to_json_line();
+        assert_eq!(got, format!("{}\n{}\n", line, line));
+    }
+}
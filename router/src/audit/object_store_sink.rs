@@ -0,0 +1,116 @@
+use super::{AuditRecord, AuditSink, AuditSinkError};
+use async_trait::async_trait;
+use bytes::Bytes;
+use object_store::{path::Path, DynObjectStore};
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+};
+
+/// An [`AuditSink`] writing each batch of records to a new object in an
+/// object store, one JSON object per line.
+///
+/// Objects are named `<prefix>/<time>-<seq>.jsonl`, where `time` is the time
+/// of the first record in the batch (in nanoseconds since the epoch) and
+/// `seq` is a counter of the batches written by this sink. The `prefix` of
+/// each router instance sharing a store must be unique.
+#[derive(Debug)]
+pub struct ObjectStoreAuditSink {
+    store: Arc<DynObjectStore>,
+    prefix: String,
+    seq: AtomicU64,
+}
+
+impl ObjectStoreAuditSink {
+    /// Write audit records to objects under `prefix` in `store`.
+    pub fn new(store: Arc<DynObjectStore>, prefix: impl Into<String>) -> Self {
+        Self {
+            store,
+            prefix: prefix.into(),
+            seq: AtomicU64::new(0),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for ObjectStoreAuditSink {
+    async fn write(&self, records: &[AuditRecord]) -> Result<(), AuditSinkError> {
+        let first = match records.first() {
+            Some(v) => v,
+            None => return Ok(()),
+        };
+
+        let mut buf = String::new();
+        for record in records {
+            buf.push_str(&record.to_json_line());
+            buf.push('\n');
+        }
+
+        let path = Path::from(format!(
+            "{}/{:020}-{:010}.jsonl",
+            self.prefix,
+            first.time.timestamp_nanos(),
+            self.seq.fetch_add(1, Ordering::Relaxed)
+        ));
+        self.store.put(&path, Bytes::from(buf)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditOperation;
+    use futures::TryStreamExt;
+    use iox_time::Time;
+    use object_store::memory::InMemory;
+
+    #[tokio::test]
+    async fn test_write() {
+        let store: Arc<DynObjectStore> = Arc::new(InMemory::new());
+        let sink = ObjectStoreAuditSink::new(Arc::clone(&store), "audit/router-1");
+
+        let record = AuditRecord {
+            time: Time::from_timestamp_nanos(42),
+            operation: AuditOperation::Write,
+            namespace: "bananas_test".to_string(),
+            tables: vec!["platanos".to_string()],
+            num_lines: 1,
+            predicate: None,
+            principal: None,
+            trace_id: None,
+            span_id: None,
+        };
+
+        sink.write(&[record.clone(), record.clone()]).await.unwrap();
+        sink.write(&[]).await.unwrap();
+        sink.write(&[record.clone()]).await.unwrap();
+
+        let mut paths = store
+            .list(Some(&Path::from("audit/router-1")))
+            .await
+            .unwrap()
+            .map_ok(|meta| meta.location.to_string())
+            .try_collect::<Vec<_>>()
+            .await
+            .unwrap();
+        paths.sort();
+        assert_eq!(
+            paths,
+            [
+                "audit/router-1/00000000000000000042-0000000000.jsonl",
+                "audit/router-1/00000000000000000042-0000000001.jsonl",
+            ]
+        );
+
+        let got = store
+            .get(&Path::from(paths[0].as_str()))
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        let line = record.to_json_line();
+        assert_eq!(got, format!("{}\n{}\n", line, line).as_bytes());
+    }
+}
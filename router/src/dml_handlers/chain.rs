@@ -53,6 +53,7 @@ where
     // in order to present a consistent error type for chained handlers.
     type WriteError = DmlError;
     type DeleteError = DmlError;
+    type DeleteOutput = U::DeleteOutput;
 
     /// Write `batches` to `namespace`.
     async fn write(
@@ -80,7 +81,7 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         self.first
             .delete(namespace, table_name, predicate, span_ctx.clone())
             .await
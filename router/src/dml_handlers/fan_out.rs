@@ -1,8 +1,8 @@
 use super::DmlHandler;
 use async_trait::async_trait;
 use data_types::{DatabaseName, DeletePredicate};
-use futures::{stream::FuturesUnordered, TryStreamExt};
-use std::{fmt::Debug, marker::PhantomData};
+use futures::{stream, StreamExt, TryStreamExt};
+use std::{fmt::Debug, marker::PhantomData, num::NonZeroUsize};
 use trace::ctx::SpanContext;
 
 /// A [`FanOutAdaptor`] takes an iterator of DML write operation inputs and
@@ -12,10 +12,15 @@ use trace::ctx::SpanContext;
 /// If handling an operation produces an error the remaining in-flight writes
 /// are aborted and the error is immediately returned.
 ///
+/// By default all operations are executed concurrently - the number of
+/// operations in flight at any one time for a single write can be bounded with
+/// [`FanOutAdaptor::with_max_parallelism()`].
+///
 /// Deletes are passed through to the inner handler unmodified.
 #[derive(Debug, Default)]
 pub struct FanOutAdaptor<T, I> {
     inner: T,
+    max_parallelism: Option<NonZeroUsize>,
     _iter: PhantomData<I>,
 }
 
@@ -25,9 +30,18 @@ impl<T, I> FanOutAdaptor<T, I> {
     pub fn new(inner: T) -> Self {
         Self {
             inner,
+            max_parallelism: None,
             _iter: Default::default(),
         }
     }
+
+    /// Execute at most `max` operations concurrently for each write.
+    pub fn with_max_parallelism(self, max: NonZeroUsize) -> Self {
+        Self {
+            max_parallelism: Some(max),
+            ..self
+        }
+    }
 }
 
 #[async_trait]
@@ -41,6 +55,7 @@ where
     type WriteOutput = Vec<T::WriteOutput>;
     type WriteError = T::WriteError;
     type DeleteError = T::DeleteError;
+    type DeleteOutput = T::DeleteOutput;
 
     /// Concurrently execute the write inputs in `input` against the inner
     /// handler, returning early and aborting in-flight writes if an error
@@ -51,16 +66,16 @@ where
         input: Self::WriteInput,
         span_ctx: Option<SpanContext>,
     ) -> Result<Self::WriteOutput, Self::WriteError> {
-        let results = input
-            .into_iter()
-            .map(|v| {
-                let namespace = namespace.clone();
-                let span_ctx = span_ctx.clone();
-                async move { self.inner.write(&namespace, v, span_ctx).await }
-            })
-            .collect::<FuturesUnordered<_>>()
-            .try_collect::<Vec<_>>()
-            .await?;
+        let max_parallelism = self.max_parallelism.map_or(usize::MAX, NonZeroUsize::get);
+
+        let results = stream::iter(input.into_iter().map(|v| {
+            let namespace = namespace.clone();
+            let span_ctx = span_ctx.clone();
+            async move { self.inner.write(&namespace, v, span_ctx).await }
+        }))
+        .buffer_unordered(max_parallelism)
+        .try_collect::<Vec<_>>()
+        .await?;
         Ok(results)
     }
 
@@ -71,9 +86,91 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         self.inner
             .delete(namespace, table_name, predicate, span_ctx)
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dml_handlers::DmlError;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    /// A [`DmlHandler`] recording the maximum number of concurrent writes it
+    /// observes.
+    #[derive(Debug, Default)]
+    struct ConcurrencyRecorder {
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl DmlHandler for ConcurrencyRecorder {
+        type WriteInput = usize;
+        type WriteOutput = usize;
+        type WriteError = DmlError;
+        type DeleteError = DmlError;
+        type DeleteOutput = ();
+
+        async fn write(
+            &self,
+            _namespace: &DatabaseName<'static>,
+            input: Self::WriteInput,
+            _span_ctx: Option<SpanContext>,
+        ) -> Result<Self::WriteOutput, Self::WriteError> {
+            let n = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(n, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(input)
+        }
+
+        async fn delete(
+            &self,
+            _namespace: &DatabaseName<'static>,
+            _table_name: &str,
+            _predicate: &DeletePredicate,
+            _span_ctx: Option<SpanContext>,
+        ) -> Result<Self::DeleteOutput, Self::DeleteError> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_write_unbounded() {
+        let ns = "bananas".try_into().unwrap();
+        let handler = FanOutAdaptor::<_, Vec<usize>>::new(ConcurrencyRecorder::default());
+
+        let mut got = handler
+            .write(&ns, (0..10).collect(), None)
+            .await
+            .expect("write should succeed");
+        got.sort_unstable();
+
+        assert_eq!(got, (0..10).collect::<Vec<_>>());
+        assert_eq!(handler.inner.max_in_flight.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test]
+    async fn test_write_max_parallelism() {
+        let ns = "bananas".try_into().unwrap();
+        let handler = FanOutAdaptor::<_, Vec<usize>>::new(ConcurrencyRecorder::default())
+            .with_max_parallelism(NonZeroUsize::new(3).unwrap());
+
+        let mut got = handler
+            .write(&ns, (0..10).collect(), None)
+            .await
+            .expect("write should succeed");
+        got.sort_unstable();
+
+        // All the writes are applied, with at most 3 in flight at any one time.
+        assert_eq!(got, (0..10).collect::<Vec<_>>());
+        assert_eq!(handler.inner.max_in_flight.load(Ordering::SeqCst), 3);
+    }
+}
@@ -1,16 +1,132 @@
-use super::DmlHandler;
+use super::{DmlHandler, Partitioned};
 use async_trait::async_trait;
 use data_types::{DatabaseName, DeletePredicate};
+use hashbrown::HashMap;
 use iox_time::{SystemProvider, TimeProvider};
-use metric::{DurationHistogram, Metric};
+use metric::{DurationHistogram, Metric, U64Counter};
+use mutable_batch::MutableBatch;
+use parking_lot::Mutex;
+use std::{borrow::Cow, time::Duration};
 use trace::{
     ctx::SpanContext,
     span::{SpanExt, SpanRecorder},
 };
 
+/// The `table` label value of the per-table metrics recorded for tables
+/// observed after the configured limit of distinct tables is reached.
+pub const OVERFLOW_TABLE_LABEL: &str = "_overflow";
+
+/// A [`DmlHandler`] input that names the tables it contains, allowing the
+/// [`InstrumentationDecorator`] to label metrics by table.
+pub trait TableNames {
+    /// Return the names of the tables in `self`, in no particular order and
+    /// possibly containing duplicates.
+    fn table_names(&self) -> Vec<&str>;
+}
+
+impl TableNames for () {
+    fn table_names(&self) -> Vec<&str> {
+        vec![]
+    }
+}
+
+impl TableNames for HashMap<String, MutableBatch> {
+    fn table_names(&self) -> Vec<&str> {
+        self.keys().map(String::as_str).collect()
+    }
+}
+
+impl<T> TableNames for Partitioned<T>
+where
+    T: TableNames,
+{
+    fn table_names(&self) -> Vec<&str> {
+        self.payload().table_names()
+    }
+}
+
+impl<T> TableNames for Vec<T>
+where
+    T: TableNames,
+{
+    fn table_names(&self) -> Vec<&str> {
+        self.iter().flat_map(TableNames::table_names).collect()
+    }
+}
+
+/// The latency histograms for a single table.
+#[derive(Debug, Clone)]
+struct TableRecorders {
+    write_success: DurationHistogram,
+    write_error: DurationHistogram,
+    delete_success: DurationHistogram,
+    delete_error: DurationHistogram,
+}
+
+impl TableRecorders {
+    fn new(
+        write: &Metric<DurationHistogram>,
+        delete: &Metric<DurationHistogram>,
+        handler: &'static str,
+        table: &str,
+    ) -> Self {
+        let attrs = |result: &'static str| {
+            [
+                ("handler", Cow::Borrowed(handler)),
+                ("table", Cow::Owned(table.to_string())),
+                ("result", Cow::Borrowed(result)),
+            ]
+        };
+
+        Self {
+            write_success: write.recorder(attrs("success")),
+            write_error: write.recorder(attrs("error")),
+            delete_success: delete.recorder(attrs("success")),
+            delete_error: delete.recorder(attrs("error")),
+        }
+    }
+}
+
+/// Per-table latency histograms, bounded to `max_tables` distinct table
+/// labels.
+#[derive(Debug)]
+struct TableMetrics {
+    write: Metric<DurationHistogram>,
+    delete: Metric<DurationHistogram>,
+    max_tables: usize,
+
+    tables: Mutex<HashMap<String, TableRecorders>>,
+    overflow: TableRecorders,
+}
+
+impl TableMetrics {
+    /// Return the recorders for `table`, or [`None`] if `max_tables` other
+    /// tables have already been observed.
+    fn recorders(&self, handler: &'static str, table: &str) -> Option<TableRecorders> {
+        let mut tables = self.tables.lock();
+        if let Some(r) = tables.get(table) {
+            return Some(r.clone());
+        }
+        if tables.len() >= self.max_tables {
+            return None;
+        }
+
+        let r = TableRecorders::new(&self.write, &self.delete, handler, table);
+        tables.insert(table.to_string(), r.clone());
+        Some(r)
+    }
+}
+
 /// An instrumentation decorator recording call latencies for [`DmlHandler`] implementations.
 ///
-/// Metrics are broken down by operation (write/delete) and result (success/error).
+/// Metrics are broken down by operation (write/delete) and result (success/error), and the
+/// number of errors returned by each operation is counted.
+///
+/// Optionally (see [`InstrumentationDecorator::with_table_metrics()`]) the call latency is
+/// also recorded per table, attributing the latency of a write to every table it contains. As
+/// each table label creates a new metric series, the number of distinct table labels is
+/// bounded - calls for tables observed once this limit is reached are recorded with a table
+/// label of [`OVERFLOW_TABLE_LABEL`].
 #[derive(Debug)]
 pub struct InstrumentationDecorator<T, P = SystemProvider> {
     name: &'static str,
@@ -22,6 +138,11 @@ pub struct InstrumentationDecorator<T, P = SystemProvider> {
 
     delete_success: DurationHistogram,
     delete_error: DurationHistogram,
+
+    write_errors: U64Counter,
+    delete_errors: U64Counter,
+
+    tables: Option<TableMetrics>,
 }
 
 impl<T> InstrumentationDecorator<T> {
@@ -41,6 +162,13 @@ impl<T> InstrumentationDecorator<T> {
         let delete_success = delete.recorder(&[("handler", name), ("result", "success")]);
         let delete_error = delete.recorder(&[("handler", name), ("result", "error")]);
 
+        let errors: Metric<U64Counter> = registry.register_metric(
+            "dml_handler_error_count",
+            "number of errors returned by the handler",
+        );
+        let write_errors = errors.recorder(&[("handler", name), ("op", "write")]);
+        let delete_errors = errors.recorder(&[("handler", name), ("op", "delete")]);
+
         Self {
             name,
             inner,
@@ -49,7 +177,79 @@ impl<T> InstrumentationDecorator<T> {
             write_error,
             delete_success,
             delete_error,
+            write_errors,
+            delete_errors,
+            tables: None,
+        }
+    }
+
+    /// Additionally record the call latency labelled by table, for at most
+    /// `max_tables` distinct tables.
+    pub fn with_table_metrics(self, registry: &metric::Registry, max_tables: usize) -> Self {
+        let write: Metric<DurationHistogram> = registry.register_metric(
+            "dml_handler_table_write_duration",
+            "write handler call duration, per table in the write",
+        );
+        let delete: Metric<DurationHistogram> = registry.register_metric(
+            "dml_handler_table_delete_duration",
+            "delete handler call duration, per table",
+        );
+        let overflow = TableRecorders::new(&write, &delete, self.name, OVERFLOW_TABLE_LABEL);
+
+        Self {
+            tables: Some(TableMetrics {
+                write,
+                delete,
+                max_tables,
+                tables: Default::default(),
+                overflow,
+            }),
+            ..self
+        }
+    }
+
+    /// Resolve the per-table recorders for `tables`, if per-table metrics are
+    /// enabled.
+    fn table_recorders<'a>(
+        &self,
+        tables: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<TableRecorders> {
+        let metrics = match &self.tables {
+            Some(v) => v,
+            None => return vec![],
+        };
+
+        let mut tables = tables.into_iter().collect::<Vec<_>>();
+        tables.sort_unstable();
+        tables.dedup();
+
+        // Tables over the limit share the overflow recorders, recording the
+        // call at most once.
+        let mut overflow = false;
+        let mut recorders = tables
+            .into_iter()
+            .filter_map(|table| {
+                let r = metrics.recorders(self.name, table);
+                overflow |= r.is_none();
+                r
+            })
+            .collect::<Vec<_>>();
+        if overflow {
+            recorders.push(metrics.overflow.clone());
         }
+        recorders
+    }
+}
+
+/// Record `delta` in the success or error histogram selected from each of
+/// `recorders` by `select`.
+fn record_tables(
+    recorders: &[TableRecorders],
+    delta: Duration,
+    select: impl Fn(&TableRecorders) -> &DurationHistogram,
+) {
+    for r in recorders {
+        select(r).record(delta);
     }
 }
 
@@ -57,10 +257,12 @@ impl<T> InstrumentationDecorator<T> {
 impl<T> DmlHandler for InstrumentationDecorator<T>
 where
     T: DmlHandler,
+    T::WriteInput: TableNames,
 {
     type WriteInput = T::WriteInput;
     type WriteError = T::WriteError;
     type DeleteError = T::DeleteError;
+    type DeleteOutput = T::DeleteOutput;
     type WriteOutput = T::WriteOutput;
 
     /// Call the inner `write` method and record the call latency.
@@ -76,19 +278,31 @@ where
         let mut span_recorder =
             SpanRecorder::new(span_ctx.clone().map(|parent| parent.child(self.name)));
 
+        // Resolve the per-table recorders before the input is consumed.
+        let tables = match self.tables {
+            Some(_) => self.table_recorders(input.table_names()),
+            None => vec![],
+        };
+
         let res = self.inner.write(namespace, input, span_ctx).await;
 
+        if res.is_err() {
+            self.write_errors.inc(1);
+        }
+
         // Avoid exploding if time goes backwards - simply drop the measurement
         // if it happens.
         if let Some(delta) = self.time_provider.now().checked_duration_since(t) {
             match &res {
                 Ok(_) => {
                     span_recorder.ok("success");
-                    self.write_success.record(delta)
+                    self.write_success.record(delta);
+                    record_tables(&tables, delta, |r| &r.write_success);
                 }
                 Err(e) => {
                     span_recorder.error(e.to_string());
-                    self.write_error.record(delta)
+                    self.write_error.record(delta);
+                    record_tables(&tables, delta, |r| &r.write_error);
                 }
             };
         }
@@ -103,28 +317,37 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         let t = self.time_provider.now();
 
         // Create a tracing span for this handler.
         let mut span_recorder = SpanRecorder::new(span_ctx.child_span(self.name));
 
+        // Deletes that do not specify a table are not recorded per table.
+        let tables = self.table_recorders(Some(table_name).filter(|t| !t.is_empty()));
+
         let res = self
             .inner
             .delete(namespace, table_name, predicate, span_ctx)
             .await;
 
+        if res.is_err() {
+            self.delete_errors.inc(1);
+        }
+
         // Avoid exploding if time goes backwards - simply drop the measurement
         // if it happens.
         if let Some(delta) = self.time_provider.now().checked_duration_since(t) {
             match &res {
                 Ok(_) => {
                     span_recorder.ok("success");
-                    self.delete_success.record(delta)
+                    self.delete_success.record(delta);
+                    record_tables(&tables, delta, |r| &r.delete_success);
                 }
                 Err(e) => {
                     span_recorder.error(e.to_string());
-                    self.delete_error.record(delta)
+                    self.delete_error.record(delta);
+                    record_tables(&tables, delta, |r| &r.delete_error);
                 }
             };
         }
@@ -235,7 +458,7 @@ mod tests {
     #[tokio::test]
     async fn test_delete_ok() {
         let ns = "platanos".try_into().unwrap();
-        let handler = Arc::new(MockDmlHandler::<()>::default().with_delete_return([Ok(())]));
+        let handler = Arc::new(MockDmlHandler::<()>::default().with_delete_return([Ok(summary())]));
 
         let metrics = Arc::new(metric::Registry::default());
         let traces: Arc<dyn TraceCollector> = Arc::new(RingBufferTraceCollector::new(5));
@@ -284,4 +507,161 @@ mod tests {
         assert_metric_hit(&*metrics, "dml_handler_delete_duration", "error");
         assert_trace(traces, SpanStatus::Err);
     }
+
+    fn error_count(metrics: &metric::Registry, op: &'static str) -> u64 {
+        metrics
+            .get_instrument::<Metric<U64Counter>>("dml_handler_error_count")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("handler", HANDLER_NAME), ("op", op)]))
+            .expect("failed to get observer")
+            .fetch()
+    }
+
+    fn table_samples(
+        metrics: &metric::Registry,
+        metric_name: &'static str,
+        table: &'static str,
+        result: &'static str,
+    ) -> Option<u64> {
+        metrics
+            .get_instrument::<Metric<DurationHistogram>>(metric_name)
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[
+                ("handler", HANDLER_NAME),
+                ("table", table),
+                ("result", result),
+            ]))
+            .map(|v| v.fetch().sample_count())
+    }
+
+    fn batches(tables: &[&str]) -> HashMap<String, MutableBatch> {
+        tables
+            .iter()
+            .map(|t| (t.to_string(), MutableBatch::default()))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_error_count() {
+        let ns = "platanos".try_into().unwrap();
+        let handler = Arc::new(
+            MockDmlHandler::<()>::default()
+                .with_write_return([
+                    Ok(summary()),
+                    Err(DmlError::DatabaseNotFound("nope".to_owned())),
+                ])
+                .with_delete_return([Err(DmlError::DatabaseNotFound("nope".to_owned()))]),
+        );
+
+        let metrics = Arc::new(metric::Registry::default());
+        let decorator = InstrumentationDecorator::new(HANDLER_NAME, &*metrics, handler);
+
+        decorator
+            .write(&ns, (), None)
+            .await
+            .expect("inner handler configured to succeed");
+        assert_eq!(error_count(&metrics, "write"), 0);
+
+        decorator
+            .write(&ns, (), None)
+            .await
+            .expect_err("inner handler configured to fail");
+        assert_eq!(error_count(&metrics, "write"), 1);
+
+        let pred = DeletePredicate {
+            range: TimestampRange::new(1, 2),
+            exprs: vec![],
+        };
+        decorator
+            .delete(&ns, "a table", &pred, None)
+            .await
+            .expect_err("inner handler configured to fail");
+        assert_eq!(error_count(&metrics, "delete"), 1);
+        assert_eq!(error_count(&metrics, "write"), 1);
+    }
+
+    #[tokio::test]
+    async fn test_table_metrics() {
+        let ns = "platanos".try_into().unwrap();
+        let handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([
+                    Ok(summary()),
+                    Err(DmlError::DatabaseNotFound("nope".to_owned())),
+                ])
+                .with_delete_return([Ok(summary()), Ok(summary())]),
+        );
+
+        let metrics = Arc::new(metric::Registry::default());
+        let decorator = InstrumentationDecorator::new(HANDLER_NAME, &*metrics, handler)
+            .with_table_metrics(&*metrics, 10);
+
+        decorator
+            .write(&ns, batches(&["cpu", "mem"]), None)
+            .await
+            .expect("inner handler configured to succeed");
+        decorator
+            .write(&ns, batches(&["cpu"]), None)
+            .await
+            .expect_err("inner handler configured to fail");
+
+        let write = "dml_handler_table_write_duration";
+        assert_eq!(table_samples(&metrics, write, "cpu", "success"), Some(1));
+        assert_eq!(table_samples(&metrics, write, "mem", "success"), Some(1));
+        assert_eq!(table_samples(&metrics, write, "cpu", "error"), Some(1));
+        assert_eq!(table_samples(&metrics, write, "mem", "error"), Some(0));
+
+        // The aggregate metrics are still recorded.
+        assert_metric_hit(&*metrics, "dml_handler_write_duration", "success");
+        assert_metric_hit(&*metrics, "dml_handler_write_duration", "error");
+
+        let pred = DeletePredicate {
+            range: TimestampRange::new(1, 2),
+            exprs: vec![],
+        };
+        decorator
+            .delete(&ns, "cpu", &pred, None)
+            .await
+            .expect("inner handler configured to succeed");
+        // Deletes without a table are not recorded per table.
+        decorator
+            .delete(&ns, "", &pred, None)
+            .await
+            .expect("inner handler configured to succeed");
+
+        let delete = "dml_handler_table_delete_duration";
+        assert_eq!(table_samples(&metrics, delete, "cpu", "success"), Some(1));
+        assert_eq!(table_samples(&metrics, delete, "", "success"), None);
+    }
+
+    #[tokio::test]
+    async fn test_table_metrics_overflow() {
+        let ns = "platanos".try_into().unwrap();
+        let handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+
+        let metrics = Arc::new(metric::Registry::default());
+        let decorator = InstrumentationDecorator::new(HANDLER_NAME, &*metrics, handler)
+            .with_table_metrics(&*metrics, 1);
+
+        decorator
+            .write(&ns, batches(&["cpu"]), None)
+            .await
+            .expect("inner handler configured to succeed");
+        decorator
+            .write(&ns, batches(&["cpu", "mem", "disk"]), None)
+            .await
+            .expect("inner handler configured to succeed");
+
+        // Only the first table observed is given its own label, with the
+        // remaining tables sharing the overflow label, recorded once per call.
+        let write = "dml_handler_table_write_duration";
+        assert_eq!(table_samples(&metrics, write, "cpu", "success"), Some(2));
+        assert_eq!(table_samples(&metrics, write, "mem", "success"), None);
+        assert_eq!(table_samples(&metrics, write, "disk", "success"), None);
+        assert_eq!(
+            table_samples(&metrics, write, OVERFLOW_TABLE_LABEL, "success"),
+            Some(1)
+        );
+    }
 }
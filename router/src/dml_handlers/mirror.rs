@@ -0,0 +1,330 @@
+use super::DmlHandler;
+use async_trait::async_trait;
+use data_types::{DatabaseName, DeletePredicate};
+use iox_time::{SystemProvider, TimeProvider};
+use metric::{DurationHistogram, Metric, U64Counter};
+use observability_deps::tracing::*;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use trace::ctx::SpanContext;
+
+/// A [`Mirror`] duplicates writes successfully applied by the primary
+/// [`DmlHandler`] to a secondary "mirror" handler, such as a second write
+/// buffer or a test cluster, to support migrations and shadow testing.
+///
+/// Mirrored writes are dispatched asynchronously once the primary handler has
+/// accepted the write, and never affect the response returned to the caller -
+/// errors returned by the mirror are logged and otherwise ignored.
+///
+/// At most `max_in_flight` mirrored writes are outstanding at any one time.
+/// Writes accepted while this limit is reached are not mirrored, and are
+/// counted in the `dml_mirror_dropped_writes` metric, bounding the resources
+/// consumed by a slow or unavailable mirror. The time between the primary
+/// accepting a write and the mirror completing it is recorded in the
+/// `dml_mirror_lag` metric.
+///
+/// Deletes are only applied to the primary handler.
+#[derive(Debug)]
+pub struct Mirror<T, M> {
+    primary: T,
+    mirror: Arc<M>,
+    in_flight: Arc<Semaphore>,
+    time_provider: Arc<dyn TimeProvider>,
+
+    lag_success: DurationHistogram,
+    lag_error: DurationHistogram,
+    dropped: U64Counter,
+}
+
+impl<T, M> Mirror<T, M> {
+    /// Wrap `primary`, mirroring the writes it accepts to `mirror` with at
+    /// most `max_in_flight` outstanding mirrored writes.
+    pub fn new(
+        primary: T,
+        mirror: Arc<M>,
+        max_in_flight: usize,
+        metrics: &metric::Registry,
+    ) -> Self {
+        let lag: Metric<DurationHistogram> = metrics.register_metric(
+            "dml_mirror_lag",
+            "duration between a write being accepted and it being applied to the mirror",
+        );
+        let dropped = metrics
+            .register_metric::<U64Counter>(
+                "dml_mirror_dropped_writes",
+                "number of writes not mirrored due to the in-flight limit being reached",
+            )
+            .recorder(&[]);
+
+        Self {
+            primary,
+            mirror,
+            in_flight: Arc::new(Semaphore::new(max_in_flight)),
+            time_provider: Arc::new(SystemProvider::default()),
+            lag_success: lag.recorder(&[("result", "success")]),
+            lag_error: lag.recorder(&[("result", "error")]),
+            dropped,
+        }
+    }
+}
+
+#[async_trait]
+impl<T, M> DmlHandler for Mirror<T, M>
+where
+    T: DmlHandler,
+    T::WriteInput: Clone + 'static,
+    M: DmlHandler<WriteInput = T::WriteInput> + 'static,
+{
+    type WriteInput = T::WriteInput;
+    type WriteOutput = T::WriteOutput;
+    type WriteError = T::WriteError;
+    type DeleteError = T::DeleteError;
+    type DeleteOutput = T::DeleteOutput;
+
+    /// Write `input` to the primary handler, mirroring it to the secondary
+    /// handler if it is accepted.
+    async fn write(
+        &self,
+        namespace: &DatabaseName<'static>,
+        input: Self::WriteInput,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<Self::WriteOutput, Self::WriteError> {
+        let mirror_input = input.clone();
+        let res = self.primary.write(namespace, input, span_ctx).await?;
+
+        let permit = match Arc::clone(&self.in_flight).try_acquire_owned() {
+            Ok(v) => v,
+            Err(_) => {
+                debug!(%namespace, "mirror in-flight limit reached, dropping mirrored write");
+                self.dropped.inc(1);
+                return Ok(res);
+            }
+        };
+
+        let mirror = Arc::clone(&self.mirror);
+        let time_provider = Arc::clone(&self.time_provider);
+        let lag_success = self.lag_success.clone();
+        let lag_error = self.lag_error.clone();
+        let namespace = namespace.clone();
+        let t = time_provider.now();
+
+        tokio::spawn(async move {
+            let res = mirror.write(&namespace, mirror_input, None).await;
+
+            // Avoid exploding if time goes backwards - simply drop the
+            // measurement if it happens.
+            let delta = time_provider.now().checked_duration_since(t);
+            match res {
+                Ok(_) => {
+                    if let Some(delta) = delta {
+                        lag_success.record(delta);
+                    }
+                }
+                Err(e) => {
+                    warn!(error=%e, %namespace, "failed to mirror write");
+                    if let Some(delta) = delta {
+                        lag_error.record(delta);
+                    }
+                }
+            }
+
+            drop(permit);
+        });
+
+        Ok(res)
+    }
+
+    /// Pass the delete through to the primary handler only.
+    async fn delete(
+        &self,
+        namespace: &DatabaseName<'static>,
+        table_name: &str,
+        predicate: &DeletePredicate,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
+        self.primary
+            .delete(namespace, table_name, predicate, span_ctx)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dml_handlers::{
+        mock::{MockDmlHandler, MockDmlHandlerCall},
+        DmlError,
+    };
+    use assert_matches::assert_matches;
+    use metric::Attributes;
+    use once_cell::sync::Lazy;
+    use std::time::Duration;
+    use test_helpers::timeout::FutureTimeout;
+    use write_summary::WriteSummary;
+
+    static NAMESPACE: Lazy<DatabaseName<'static>> = Lazy::new(|| "bananas".try_into().unwrap());
+
+    /// Wait for `mock` to observe `n` calls.
+    async fn wait_for_calls(mock: &MockDmlHandler<()>, n: usize) {
+        async {
+            while mock.calls().len() < n {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await
+    }
+
+    fn lag_samples(metrics: &metric::Registry, result: &'static str) -> u64 {
+        metrics
+            .get_instrument::<Metric<DurationHistogram>>("dml_mirror_lag")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("result", result)]))
+            .expect("failed to get observer")
+            .fetch()
+            .sample_count()
+    }
+
+    fn dropped_writes(metrics: &metric::Registry) -> u64 {
+        metrics
+            .get_instrument::<Metric<U64Counter>>("dml_mirror_dropped_writes")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[]))
+            .expect("failed to get observer")
+            .fetch()
+    }
+
+    #[tokio::test]
+    async fn test_write_mirrored() {
+        let metrics = metric::Registry::default();
+        let primary = Arc::new(
+            MockDmlHandler::<()>::default().with_write_return([Ok(WriteSummary::default())]),
+        );
+        let secondary = Arc::new(
+            MockDmlHandler::<()>::default().with_write_return([Ok(WriteSummary::default())]),
+        );
+        let handler = Mirror::new(Arc::clone(&primary), Arc::clone(&secondary), 10, &metrics);
+
+        handler
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect("write should succeed");
+        assert_matches!(
+            primary.calls().as_slice(),
+            [MockDmlHandlerCall::Write { .. }]
+        );
+
+        wait_for_calls(&secondary, 1).await;
+        assert_matches!(
+            secondary.calls().as_slice(),
+            [MockDmlHandlerCall::Write { namespace, .. }] => {
+                assert_eq!(namespace, "bananas");
+            }
+        );
+
+        // The lag is recorded once the mirrored write completes.
+        async {
+            while lag_samples(&metrics, "success") == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+        assert_eq!(dropped_writes(&metrics), 0);
+    }
+
+    #[tokio::test]
+    async fn test_primary_error_not_mirrored() {
+        let metrics = metric::Registry::default();
+        let primary = Arc::new(
+            MockDmlHandler::<()>::default()
+                .with_write_return([Err(DmlError::DatabaseNotFound("bananas".to_string()))]),
+        );
+        let secondary = Arc::new(MockDmlHandler::<()>::default());
+        let handler = Mirror::new(Arc::clone(&primary), Arc::clone(&secondary), 10, &metrics);
+
+        let err = handler
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, DmlError::DatabaseNotFound(_));
+
+        // Yield to allow any (incorrectly) spawned mirror task to run.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(secondary.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_mirror_error_ignored() {
+        let metrics = metric::Registry::default();
+        let primary = Arc::new(
+            MockDmlHandler::<()>::default().with_write_return([Ok(WriteSummary::default())]),
+        );
+        let secondary = Arc::new(
+            MockDmlHandler::<()>::default()
+                .with_write_return([Err(DmlError::DatabaseNotFound("bananas".to_string()))]),
+        );
+        let handler = Mirror::new(Arc::clone(&primary), Arc::clone(&secondary), 10, &metrics);
+
+        handler
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect("mirror errors should not be returned");
+
+        async {
+            while lag_samples(&metrics, "error") == 0 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+        assert_eq!(lag_samples(&metrics, "success"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_limit_drops_writes() {
+        let metrics = metric::Registry::default();
+        let primary = Arc::new(
+            MockDmlHandler::<()>::default()
+                .with_write_return([Ok(WriteSummary::default()), Ok(WriteSummary::default())]),
+        );
+        let secondary = Arc::new(MockDmlHandler::<()>::default());
+        let handler = Mirror::new(Arc::clone(&primary), Arc::clone(&secondary), 0, &metrics);
+
+        for _ in 0..2 {
+            handler
+                .write(&*NAMESPACE, (), None)
+                .await
+                .expect("write should succeed");
+        }
+
+        assert_eq!(primary.calls().len(), 2);
+        assert!(secondary.calls().is_empty());
+        assert_eq!(dropped_writes(&metrics), 2);
+    }
+
+    #[tokio::test]
+    async fn test_delete_not_mirrored() {
+        let metrics = metric::Registry::default();
+        let primary = Arc::new(
+            MockDmlHandler::<()>::default().with_delete_return([Ok(WriteSummary::default())]),
+        );
+        let secondary = Arc::new(MockDmlHandler::<()>::default());
+        let handler = Mirror::new(Arc::clone(&primary), Arc::clone(&secondary), 10, &metrics);
+
+        let predicate = DeletePredicate {
+            range: data_types::TimestampRange::new(1, 2),
+            exprs: vec![],
+        };
+        handler
+            .delete(&*NAMESPACE, "platanos", &predicate, None)
+            .await
+            .expect("delete should succeed");
+
+        assert_matches!(
+            primary.calls().as_slice(),
+            [MockDmlHandlerCall::Delete { .. }]
+        );
+        assert!(secondary.calls().is_empty());
+    }
+}
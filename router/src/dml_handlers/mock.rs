@@ -25,7 +25,7 @@ pub enum MockDmlHandlerCall<W> {
 struct Inner<W> {
     calls: Vec<MockDmlHandlerCall<W>>,
     write_return: VecDeque<Result<WriteSummary, DmlError>>,
-    delete_return: VecDeque<Result<(), DmlError>>,
+    delete_return: VecDeque<Result<WriteSummary, DmlError>>,
 }
 
 impl<W> Default for Inner<W> {
@@ -65,7 +65,10 @@ where
         self
     }
 
-    pub fn with_delete_return(self, ret: impl Into<VecDeque<Result<(), DmlError>>>) -> Self {
+    pub fn with_delete_return(
+        self,
+        ret: impl Into<VecDeque<Result<WriteSummary, DmlError>>>,
+    ) -> Self {
         self.0.lock().delete_return = ret.into();
         self
     }
@@ -94,6 +97,7 @@ where
 {
     type WriteError = DmlError;
     type DeleteError = DmlError;
+    type DeleteOutput = WriteSummary;
     type WriteInput = W;
     type WriteOutput = WriteSummary;
 
@@ -119,7 +123,7 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         _span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         record_and_return!(
             self,
             MockDmlHandlerCall::Delete {
@@ -60,22 +60,47 @@
 //!
 //! Incoming line-protocol writes then pass through the [`Partitioner`], parsing
 //! the LP and splitting them into batches per partition, before passing each
-//! partitioned batch through the rest of the request pipeline.
+//! partitioned batch through the rest of the request pipeline. Partition keys
+//! are derived from the partition template stored for the namespace in the
+//! catalog, or the router's default template if the namespace has none.
+//!
+//! The [`RetentionValidator`] drops any points in a write older than the
+//! retention period of the namespace, rejecting writes with no points left.
 //!
 //! Writes then pass through the [`SchemaValidator`] applying schema enforcement
 //! (a NOP layer for deletes) which pushes additive schema changes to the
 //! catalog and populates the [`NamespaceCache`], converging it to match the set
-//! of [`NamespaceSchema`] in the global catalog.
+//! of [`NamespaceSchema`] in the global catalog. Namespaces may be configured
+//! with [`SchemaConflictPolicy::Coerce`] to widen integer values written to
+//! float columns instead of rejecting the write.
 //!
 //! The [`ShardedWriteBuffer`] uses a sharder implementation to direct the DML
 //! operations into a fixed set of shards.
 //!
+//! Writes to the [`ShardedWriteBuffer`] are wrapped in a [`Retry`] decorator,
+//! retrying transient write buffer errors with an exponential backoff rather
+//! than immediately failing the client request. Each shard may also be
+//! configured with a circuit breaker that fast-fails writes to a shard after
+//! repeated errors, bounding the latency added by an unavailable shard to the
+//! retry deadline rather than the write buffer timeout of every attempt.
+//!
+//! A [`Mirror`] may be added to the stack to asynchronously duplicate accepted
+//! writes to a secondary handler, such as a second write buffer when migrating
+//! between clusters.
+//!
+//! Each layer is typically wrapped in an [`InstrumentationDecorator`] recording
+//! the call latency and error count of the layer, optionally broken down by the
+//! tables in each request.
+//!
 //! [`NamespaceCache`]: crate::namespace_cache::NamespaceCache
 //! [`NamespaceSchema`]: data_types::NamespaceSchema
 
 mod r#trait;
 pub use r#trait::*;
 
+mod retention_validation;
+pub use retention_validation::*;
+
 mod schema_validation;
 pub use schema_validation::*;
 
@@ -93,6 +118,12 @@ pub use partitioner::*;
 mod instrumentation;
 pub use instrumentation::*;
 
+mod retry;
+pub use retry::*;
+
+mod mirror;
+pub use mirror::*;
+
 mod chain;
 pub use chain::*;
 
@@ -24,6 +24,7 @@ where
 {
     type WriteError = DmlError;
     type DeleteError = DmlError;
+    type DeleteOutput = ();
     type WriteInput = T;
     type WriteOutput = T;
 
@@ -43,7 +44,7 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         _span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         info!(%namespace, %table_name, ?predicate, "dropping delete operation");
         Ok(())
     }
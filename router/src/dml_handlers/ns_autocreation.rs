@@ -67,6 +67,7 @@ where
 {
     type WriteError = NamespaceCreationError;
     type DeleteError = NamespaceCreationError;
+    type DeleteOutput = ();
 
     // This handler accepts any write input type, returning it to the caller
     // unmodified.
@@ -123,7 +124,7 @@ where
         _table_name: &str,
         _predicate: &DeletePredicate,
         _span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         Ok(())
     }
 }
@@ -150,6 +151,8 @@ mod tests {
                 query_pool_id: QueryPoolId::new(3),
                 tables: Default::default(),
                 max_columns_per_table: 4,
+                retention_period_ns: None,
+                partition_template: None,
             },
         );
 
@@ -225,6 +228,9 @@ mod tests {
                 query_pool_id: QueryPoolId::new(42),
                 max_tables: iox_catalog::DEFAULT_MAX_TABLES,
                 max_columns_per_table: iox_catalog::DEFAULT_MAX_COLUMNS_PER_TABLE,
+                retention_period_ns: None,
+                deleted_at: None,
+                partition_template: None,
             }
         );
     }
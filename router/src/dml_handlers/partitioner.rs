@@ -1,9 +1,11 @@
 use super::DmlHandler;
+use crate::namespace_cache::{metrics::InstrumentedCache, MemoryNamespaceCache, NamespaceCache};
 use async_trait::async_trait;
 use data_types::{DatabaseName, DeletePredicate, PartitionKey, PartitionTemplate};
 use hashbrown::HashMap;
 use mutable_batch::{MutableBatch, PartitionWrite, WritePayload};
 use observability_deps::tracing::*;
+use std::sync::Arc;
 use thiserror::Error;
 use trace::ctx::SpanContext;
 
@@ -40,28 +42,43 @@ impl<T> Partitioned<T> {
 }
 
 /// A [`DmlHandler`] implementation that splits per-table [`MutableBatch`] into
-/// partitioned per-table [`MutableBatch`] instances according to a configured
-/// [`PartitionTemplate`]. Deletes pass through unmodified.
+/// partitioned per-table [`MutableBatch`] instances according to the
+/// [`PartitionTemplate`] of the namespace. Deletes pass through unmodified.
+///
+/// The partition template of a namespace is read from its schema in the
+/// [`NamespaceCache`], falling back to the configured default template if the
+/// namespace has no template (or is not cached). Changing the template of a
+/// namespace affects the partitioning of subsequent writes only - existing
+/// partitions are unchanged.
 ///
 /// A vector of partitions are returned to the caller, or the first error that
 /// occurs during partitioning.
 #[derive(Debug)]
-pub struct Partitioner {
+pub struct Partitioner<C = Arc<InstrumentedCache<MemoryNamespaceCache>>> {
     partition_template: PartitionTemplate,
+    cache: C,
 }
 
-impl Partitioner {
+impl<C> Partitioner<C> {
     /// Initialise a new [`Partitioner`], splitting writes according to the
-    /// specified [`PartitionTemplate`].
-    pub fn new(partition_template: PartitionTemplate) -> Self {
-        Self { partition_template }
+    /// [`PartitionTemplate`] of each namespace in `cache`, or the specified
+    /// default [`PartitionTemplate`].
+    pub fn new(partition_template: PartitionTemplate, cache: C) -> Self {
+        Self {
+            partition_template,
+            cache,
+        }
     }
 }
 
 #[async_trait]
-impl DmlHandler for Partitioner {
+impl<C> DmlHandler for Partitioner<C>
+where
+    C: NamespaceCache,
+{
     type WriteError = PartitionError;
     type DeleteError = PartitionError;
+    type DeleteOutput = ();
 
     type WriteInput = HashMap<String, MutableBatch>;
     type WriteOutput = Vec<Partitioned<Self::WriteInput>>;
@@ -69,10 +86,17 @@ impl DmlHandler for Partitioner {
     /// Partition the per-table [`MutableBatch`].
     async fn write(
         &self,
-        _namespace: &DatabaseName<'static>,
+        namespace: &DatabaseName<'static>,
         batch: Self::WriteInput,
         _span_ctx: Option<SpanContext>,
     ) -> Result<Self::WriteOutput, Self::WriteError> {
+        // Use the partition template of the namespace, if any.
+        let schema = self.cache.get_schema(namespace);
+        let partition_template = schema
+            .as_ref()
+            .and_then(|v| v.partition_template.as_ref())
+            .unwrap_or(&self.partition_template);
+
         // A collection of partition-keyed, per-table MutableBatch instances.
         let mut partitions: HashMap<PartitionKey, HashMap<_, MutableBatch>> = HashMap::default();
 
@@ -80,7 +104,7 @@ impl DmlHandler for Partitioner {
             // Partition the table batch according to the configured partition
             // template and write it into the partition-keyed map.
             for (partition_key, partition_payload) in
-                PartitionWrite::partition(&table_name, &batch, &self.partition_template)
+                PartitionWrite::partition(&table_name, &batch, partition_template)
             {
                 let partition = partitions.entry(partition_key).or_default();
                 let table_batch = partition
@@ -105,7 +129,7 @@ impl DmlHandler for Partitioner {
         _table_name: &str,
         _predicate: &DeletePredicate,
         _span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         Ok(())
     }
 }
@@ -114,7 +138,7 @@ impl DmlHandler for Partitioner {
 mod tests {
     use super::*;
     use assert_matches::assert_matches;
-    use data_types::TemplatePart;
+    use data_types::{NamespaceId, NamespaceSchema, QueryPoolId, TemplatePart, TopicId};
 
     /// The default timestamp applied to test LP if the write does not specify
     /// one.
@@ -138,7 +162,10 @@ mod tests {
                         parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
                     };
 
-                    let partitioner = Partitioner::new(partition_template);
+                    let partitioner = Partitioner::new(
+                        partition_template,
+                        Arc::new(MemoryNamespaceCache::default()),
+                    );
                     let ns = DatabaseName::new("bananas").expect("valid db name");
 
                     let (writes, _) = mutable_batch_lp::lines_to_batches_stats($lp, DEFAULT_TIMESTAMP_NANOS).expect("failed to parse test LP");
@@ -284,4 +311,63 @@ mod tests {
         ],
         want_handler_ret = Ok(_)
     );
+
+    #[tokio::test]
+    async fn test_write_namespace_template() {
+        let cache = Arc::new(MemoryNamespaceCache::default());
+        let partitioner = Partitioner::new(
+            PartitionTemplate {
+                parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
+            },
+            Arc::clone(&cache),
+        );
+
+        let mut schema = NamespaceSchema::new(
+            NamespaceId::new(1),
+            TopicId::new(1),
+            QueryPoolId::new(1),
+            iox_catalog::DEFAULT_MAX_COLUMNS_PER_TABLE,
+        );
+        schema.partition_template = Some(PartitionTemplate {
+            parts: vec![
+                TemplatePart::Column("region".to_owned()),
+                TemplatePart::TimeFormat("%Y".to_owned()),
+            ],
+        });
+        let ns = DatabaseName::new("bananas").expect("valid db name");
+        cache.put_schema(ns.clone(), schema);
+
+        let lp = "\
+            bananas,region=west val=42i 1\n\
+            bananas,region=east val=42i 1465839830100400200\n\
+            platanos val=42i 1\n\
+        ";
+        let write = || {
+            mutable_batch_lp::lines_to_batches_stats(lp, DEFAULT_TIMESTAMP_NANOS)
+                .expect("failed to parse test LP")
+                .0
+        };
+
+        let mut got = partitioner
+            .write(&ns, write(), None)
+            .await
+            .expect("partitioning should succeed")
+            .into_iter()
+            .map(|p| p.key.to_string())
+            .collect::<Vec<_>>();
+        got.sort();
+        assert_eq!(got, ["region-1970", "region_east-2016", "region_west-1970"]);
+
+        // Other namespaces use the default template.
+        let other = DatabaseName::new("platanos").expect("valid db name");
+        let mut got = partitioner
+            .write(&other, write(), None)
+            .await
+            .expect("partitioning should succeed")
+            .into_iter()
+            .map(|p| p.key.to_string())
+            .collect::<Vec<_>>();
+        got.sort();
+        assert_eq!(got, ["1970-01-01", "2016-06-13"]);
+    }
 }
@@ -0,0 +1,366 @@
+use super::DmlHandler;
+use crate::namespace_cache::{metrics::InstrumentedCache, MemoryNamespaceCache, NamespaceCache};
+use async_trait::async_trait;
+use data_types::{DatabaseName, DeletePredicate, NamespaceSchema};
+use hashbrown::HashMap;
+use iox_catalog::interface::{get_schema_by_name, Catalog};
+use iox_time::TimeProvider;
+use metric::U64Counter;
+use mutable_batch::{MutableBatch, PartitionWrite, WritePayload};
+use observability_deps::tracing::*;
+use std::{ops::DerefMut, sync::Arc};
+use thiserror::Error;
+use trace::ctx::SpanContext;
+
+/// Errors emitted during retention period validation.
+#[derive(Debug, Error)]
+pub enum RetentionError {
+    /// The requested namespace could not be found in the catalog.
+    #[error("failed to read namespace schema from catalog: {0}")]
+    NamespaceLookup(iox_catalog::interface::Error),
+
+    /// Every point in the write is older than the retention period of the
+    /// namespace.
+    #[error(
+        "all points in the write are older than the namespace retention period \
+         (earliest acceptable timestamp is {min_acceptable_ts})"
+    )]
+    OutsideRetention {
+        /// The earliest timestamp the namespace accepts, in nanoseconds.
+        min_acceptable_ts: i64,
+    },
+
+    /// Failed to copy the points within the retention period into a new
+    /// batch.
+    #[error("error truncating write to the retention period: {0}")]
+    BatchWrite(#[from] mutable_batch::Error),
+}
+
+/// A [`RetentionValidator`] drops points older than the retention period of
+/// the namespace they are written to, enforcing retention at ingest rather
+/// than relying on compaction to eventually remove them.
+///
+/// Writes containing a mix of points inside and outside the retention period
+/// are truncated to the points within it, and the number of dropped points is
+/// recorded in the `retention_validation_dropped_lines` metric. A write
+/// containing no points within the retention period is rejected with
+/// [`RetentionError::OutsideRetention`].
+///
+/// The retention period is read from the [`NamespaceSchema`] in the
+/// [`NamespaceCache`] (populating it from the catalog on a miss), so changes
+/// to the retention period of a namespace made outside of this router are not
+/// observed until it is recached. Deletes pass through unmodified.
+#[derive(Debug)]
+pub struct RetentionValidator<C = Arc<InstrumentedCache<MemoryNamespaceCache>>> {
+    catalog: Arc<dyn Catalog>,
+    cache: C,
+    time_provider: Arc<dyn TimeProvider>,
+
+    dropped_lines: U64Counter,
+    rejected_writes: U64Counter,
+}
+
+impl<C> RetentionValidator<C> {
+    /// Initialise a new [`RetentionValidator`], loading namespace schemas
+    /// from `catalog` and caching them in `ns_cache`.
+    pub fn new(
+        catalog: Arc<dyn Catalog>,
+        ns_cache: C,
+        time_provider: Arc<dyn TimeProvider>,
+        metrics: &metric::Registry,
+    ) -> Self {
+        let dropped_lines = metrics
+            .register_metric::<U64Counter>(
+                "retention_validation_dropped_lines",
+                "number of lines dropped from writes for being outside the namespace retention period",
+            )
+            .recorder(&[]);
+        let rejected_writes = metrics
+            .register_metric::<U64Counter>(
+                "retention_validation_rejected_writes",
+                "number of writes rejected for having no lines within the namespace retention period",
+            )
+            .recorder(&[]);
+
+        Self {
+            catalog,
+            cache: ns_cache,
+            time_provider,
+            dropped_lines,
+            rejected_writes,
+        }
+    }
+}
+
+#[async_trait]
+impl<C> DmlHandler for RetentionValidator<C>
+where
+    C: NamespaceCache,
+{
+    type WriteError = RetentionError;
+    type DeleteError = RetentionError;
+    type DeleteOutput = ();
+
+    type WriteInput = HashMap<String, MutableBatch>;
+    type WriteOutput = Self::WriteInput;
+
+    /// Drop all points in `batches` older than the retention period of
+    /// `namespace`.
+    ///
+    /// # Errors
+    ///
+    /// If `namespace` does not exist, [`RetentionError::NamespaceLookup`] is
+    /// returned.
+    ///
+    /// If no point in `batches` is within the retention period,
+    /// [`RetentionError::OutsideRetention`] is returned.
+    async fn write(
+        &self,
+        namespace: &DatabaseName<'static>,
+        batches: Self::WriteInput,
+        _span_ctx: Option<SpanContext>,
+    ) -> Result<Self::WriteOutput, Self::WriteError> {
+        let schema = match self.cache.get_schema(namespace) {
+            Some(v) => v,
+            None => {
+                let mut repos = self.catalog.repositories().await;
+                let schema = get_schema_by_name(namespace, repos.deref_mut())
+                    .await
+                    .map_err(|e| {
+                        warn!(error=%e, %namespace, "failed to retrieve namespace schema");
+                        RetentionError::NamespaceLookup(e)
+                    })
+                    .map(Arc::new)?;
+
+                self.cache
+                    .put_schema(namespace.clone(), Arc::clone(&schema));
+
+                trace!(%namespace, "schema cache populated");
+                schema
+            }
+        };
+
+        let min_acceptable_ts = match min_acceptable_ts(&schema, self.time_provider.now()) {
+            Some(v) => v,
+            None => return Ok(batches),
+        };
+
+        let (batches, dropped) = truncate_batches(batches, min_acceptable_ts)?;
+        if dropped > 0 {
+            self.dropped_lines.inc(dropped as u64);
+            debug!(
+                %namespace,
+                dropped_lines = dropped,
+                min_acceptable_ts,
+                "dropped lines outside retention period"
+            );
+        }
+
+        if batches.is_empty() {
+            warn!(%namespace, min_acceptable_ts, "write entirely outside retention period");
+            self.rejected_writes.inc(1);
+            return Err(RetentionError::OutsideRetention { min_acceptable_ts });
+        }
+
+        Ok(batches)
+    }
+
+    /// This call is passed through to `D` - no retention validation is
+    /// performed on deletes.
+    async fn delete(
+        &self,
+        _namespace: &DatabaseName<'static>,
+        _table_name: &str,
+        _predicate: &DeletePredicate,
+        _span_ctx: Option<SpanContext>,
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
+        Ok(())
+    }
+}
+
+/// Return the earliest timestamp (in nanoseconds) accepted by the namespace
+/// described by `schema` at `now`, or `None` if all timestamps are accepted.
+fn min_acceptable_ts(schema: &NamespaceSchema, now: iox_time::Time) -> Option<i64> {
+    schema
+        .retention_period_ns
+        .map(|period| now.timestamp_nanos().saturating_sub(period))
+}
+
+/// Remove all rows with a timestamp earlier than `min_acceptable_ts` from
+/// `batches`, returning the remaining (non-empty) batches and the number of
+/// rows dropped.
+fn truncate_batches(
+    batches: HashMap<String, MutableBatch>,
+    min_acceptable_ts: i64,
+) -> Result<(HashMap<String, MutableBatch>, usize), mutable_batch::Error> {
+    let mut dropped = 0;
+    let mut out = HashMap::with_capacity(batches.len());
+
+    for (table, batch) in batches {
+        // Skip the copy entirely for the common case of all rows being
+        // within the retention period.
+        match batch.timestamp_summary().and_then(|v| v.stats.min) {
+            Some(min) if min < min_acceptable_ts => {}
+            _ => {
+                out.insert(table, batch);
+                continue;
+            }
+        }
+
+        let rows = batch.rows();
+        let write = match PartitionWrite::new(&batch).filter(|t| t >= min_acceptable_ts) {
+            Some(v) => v,
+            None => {
+                dropped += rows;
+                continue;
+            }
+        };
+
+        dropped += rows - write.rows().get();
+
+        let mut truncated = MutableBatch::new();
+        write.write_to_batch(&mut truncated)?;
+        out.insert(table, truncated);
+    }
+
+    Ok((out, dropped))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use iox_tests::util::{TestCatalog, TestNamespace};
+    use iox_time::Time;
+    use metric::{Attributes, Metric};
+    use once_cell::sync::Lazy;
+
+    static NAMESPACE: Lazy<DatabaseName<'static>> = Lazy::new(|| "bananas".try_into().unwrap());
+
+    /// The current time of the test catalog's clock.
+    const NOW: i64 = 1_000_000_000_000;
+
+    /// One second, in nanoseconds.
+    const RETENTION: i64 = 1_000_000_000;
+
+    fn lp_to_writes(lp: &str) -> HashMap<String, MutableBatch> {
+        let (writes, _) = mutable_batch_lp::lines_to_batches_stats(lp, 42)
+            .expect("failed to build test writes from LP");
+        writes
+    }
+
+    async fn test_setup() -> (Arc<TestCatalog>, Arc<TestNamespace>) {
+        let catalog = TestCatalog::new();
+        catalog
+            .mock_time_provider()
+            .set(Time::from_timestamp_nanos(NOW));
+        let namespace = catalog.create_namespace(&NAMESPACE).await;
+
+        (catalog, namespace)
+    }
+
+    fn new_handler(catalog: &TestCatalog) -> RetentionValidator<Arc<MemoryNamespaceCache>> {
+        RetentionValidator::new(
+            catalog.catalog(),
+            Arc::new(MemoryNamespaceCache::default()),
+            catalog.time_provider(),
+            &catalog.metric_registry(),
+        )
+    }
+
+    fn assert_counter(catalog: &TestCatalog, name: &'static str, want: u64) {
+        let got = catalog
+            .metric_registry()
+            .get_instrument::<Metric<U64Counter>>(name)
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(got, want, "metric {name}");
+    }
+
+    #[tokio::test]
+    async fn test_infinite_retention() {
+        let (catalog, _namespace) = test_setup().await;
+        let handler = new_handler(&catalog);
+
+        let writes = lp_to_writes("bananas,tag1=A val=42i 1");
+        let got = handler
+            .write(&*NAMESPACE, writes, None)
+            .await
+            .expect("write should succeed");
+        assert_eq!(got.get("bananas").expect("table not found").rows(), 1);
+
+        assert_counter(&catalog, "retention_validation_dropped_lines", 0);
+    }
+
+    #[tokio::test]
+    async fn test_truncate_outside_retention() {
+        let (catalog, namespace) = test_setup().await;
+        namespace.update_retention_period(Some(RETENTION)).await;
+        let handler = new_handler(&catalog);
+
+        let writes = lp_to_writes(&format!(
+            "bananas,tag1=A val=1i {}\n\
+             bananas,tag1=B val=2i {}\n\
+             bananas,tag1=C val=3i {}\n\
+             platanos val=4i {}\n\
+             mangos val=5i {}",
+            NOW - RETENTION - 1,
+            NOW - RETENTION,
+            NOW,
+            NOW - RETENTION - 1,
+            NOW,
+        ));
+        let got = handler
+            .write(&*NAMESPACE, writes, None)
+            .await
+            .expect("write should succeed");
+
+        // The tables with no points within the retention period are removed
+        // entirely.
+        assert_eq!(got.len(), 2);
+        let bananas = got.get("bananas").expect("table not found");
+        assert_eq!(bananas.rows(), 2);
+        assert_eq!(
+            bananas.timestamp_summary().unwrap().stats.min,
+            Some(NOW - RETENTION)
+        );
+        assert_eq!(got.get("mangos").expect("table not found").rows(), 1);
+
+        assert_counter(&catalog, "retention_validation_dropped_lines", 2);
+        assert_counter(&catalog, "retention_validation_rejected_writes", 0);
+    }
+
+    #[tokio::test]
+    async fn test_reject_all_outside_retention() {
+        let (catalog, namespace) = test_setup().await;
+        namespace.update_retention_period(Some(RETENTION)).await;
+        let handler = new_handler(&catalog);
+
+        let writes = lp_to_writes(&format!("bananas,tag1=A val=1i {}", NOW - RETENTION - 1));
+        let err = handler
+            .write(&*NAMESPACE, writes, None)
+            .await
+            .expect_err("write should be rejected");
+        assert_matches!(err, RetentionError::OutsideRetention { min_acceptable_ts } => {
+            assert_eq!(min_acceptable_ts, NOW - RETENTION);
+        });
+
+        assert_counter(&catalog, "retention_validation_dropped_lines", 1);
+        assert_counter(&catalog, "retention_validation_rejected_writes", 1);
+    }
+
+    #[tokio::test]
+    async fn test_namespace_not_found() {
+        let (catalog, _namespace) = test_setup().await;
+        let handler = new_handler(&catalog);
+
+        let ns = DatabaseName::try_from("platanos").unwrap();
+        let err = handler
+            .write(&ns, lp_to_writes("bananas val=1i 1"), None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, RetentionError::NamespaceLookup(_));
+    }
+}
@@ -0,0 +1,409 @@
+use super::{DmlError, DmlHandler, SchemaError};
+use async_trait::async_trait;
+use backoff::{Backoff, BackoffConfig, BackoffError};
+use data_types::{DatabaseName, DeletePredicate};
+use metric::U64Counter;
+use std::{ops::ControlFlow, time::Duration};
+use trace::ctx::SpanContext;
+
+/// A [`Retry`] decorator retries writes and deletes that fail with a
+/// transient error against the inner [`DmlHandler`], backing off
+/// exponentially (with jitter) between attempts until a deadline is reached.
+///
+/// The errors considered transient are write buffer errors (other than writes
+/// rejected by a shard with an open circuit breaker), unexpected catalog
+/// errors during schema validation, and namespace creation errors - all other
+/// errors are returned to the caller immediately. Once the deadline
+/// has elapsed, the last error observed is returned.
+///
+/// Write buffer errors following a partial write are not retried, as
+/// retrying would write the same data again to the shards that accepted it -
+/// use [`Shard::with_retry()`] to retry the failed shards alone.
+///
+/// Each attempt passes a clone of the write input to the inner handler, so
+/// this decorator is best placed where the input is cheap to clone.
+///
+/// [`Shard::with_retry()`]: crate::shard::Shard::with_retry
+/// The number of retries is recorded in the `dml_handler_retries` metric.
+#[derive(Debug)]
+pub struct Retry<T> {
+    inner: T,
+    backoff: BackoffConfig,
+
+    write_retries: U64Counter,
+    delete_retries: U64Counter,
+}
+
+impl<T> Retry<T> {
+    /// Wrap `inner`, retrying transient errors with the backoff described by
+    /// `backoff` until `deadline` has elapsed.
+    pub fn new(
+        inner: T,
+        backoff: BackoffConfig,
+        deadline: Duration,
+        metrics: &metric::Registry,
+    ) -> Self {
+        let retries = metrics.register_metric::<U64Counter>(
+            "dml_handler_retries",
+            "number of dml operations retried after a transient error",
+        );
+
+        Self {
+            inner,
+            backoff: BackoffConfig {
+                deadline: Some(deadline),
+                ..backoff
+            },
+            write_retries: retries.recorder(&[("op", "write")]),
+            delete_retries: retries.recorder(&[("op", "delete")]),
+        }
+    }
+}
+
+#[async_trait]
+impl<T> DmlHandler for Retry<T>
+where
+    T: DmlHandler,
+    T::WriteInput: Clone,
+{
+    type WriteInput = T::WriteInput;
+    type WriteOutput = T::WriteOutput;
+    type WriteError = DmlError;
+    type DeleteError = DmlError;
+    type DeleteOutput = T::DeleteOutput;
+
+    /// Write `input` to the inner handler, retrying transient errors.
+    async fn write(
+        &self,
+        namespace: &DatabaseName<'static>,
+        input: Self::WriteInput,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<Self::WriteOutput, Self::WriteError> {
+        let mut attempts = 0_u64;
+        let res = Backoff::new(&self.backoff)
+            .retry_with_backoff("dml_write", || {
+                if attempts > 0 {
+                    self.write_retries.inc(1);
+                }
+                attempts += 1;
+
+                let input = input.clone();
+                let span_ctx = span_ctx.clone();
+                async move {
+                    classify(
+                        self.inner
+                            .write(namespace, input, span_ctx)
+                            .await
+                            .map_err(Into::into),
+                    )
+                }
+            })
+            .await;
+
+        flatten(res)
+    }
+
+    /// Delete the data specified in `delete` with the inner handler,
+    /// retrying transient errors.
+    async fn delete(
+        &self,
+        namespace: &DatabaseName<'static>,
+        table_name: &str,
+        predicate: &DeletePredicate,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
+        let mut attempts = 0_u64;
+        let res = Backoff::new(&self.backoff)
+            .retry_with_backoff("dml_delete", || {
+                if attempts > 0 {
+                    self.delete_retries.inc(1);
+                }
+                attempts += 1;
+
+                let span_ctx = span_ctx.clone();
+                async move {
+                    classify(
+                        self.inner
+                            .delete(namespace, table_name, predicate, span_ctx)
+                            .await
+                            .map_err(Into::into),
+                    )
+                }
+            })
+            .await;
+
+        flatten(res)
+    }
+}
+
+/// Returns true if `e` may succeed if the operation is retried.
+///
+/// Writes rejected by a shard with an open circuit breaker are not retried, as
+/// the circuit breaker exists to fail them fast, and nor are writes partially
+/// applied to a subset of shards.
+fn is_transient(e: &DmlError) -> bool {
+    match e {
+        DmlError::WriteBuffer(e) => !e.is_circuit_open() && !e.is_partial(),
+        DmlError::Schema(SchemaError::UnexpectedCatalogError(_))
+        | DmlError::NamespaceCreation(_) => true,
+        _ => false,
+    }
+}
+
+/// Continue retrying transient errors, breaking out of the retry loop with
+/// the result of the operation otherwise.
+fn classify<V>(res: Result<V, DmlError>) -> ControlFlow<Result<V, DmlError>, DmlError> {
+    match res {
+        Err(e) if is_transient(&e) => ControlFlow::Continue(e),
+        res => ControlFlow::Break(res),
+    }
+}
+
+/// Unwrap the result of a retried operation, returning the last error
+/// observed if the deadline was exceeded.
+fn flatten<V>(res: Result<Result<V, DmlError>, BackoffError<DmlError>>) -> Result<V, DmlError> {
+    match res {
+        Ok(v) => v,
+        Err(BackoffError::DeadlineExceeded { source, .. }) => Err(source),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dml_handlers::{
+            mock::{MockDmlHandler, MockDmlHandlerCall},
+            Partitioned, ShardError, ShardedWriteBuffer,
+        },
+        shard::{CircuitBreakerConfig, Shard},
+    };
+    use assert_matches::assert_matches;
+    use data_types::ShardIndex;
+    use metric::{Attributes, Metric};
+    use once_cell::sync::Lazy;
+    use sharder::mock::MockSharder;
+    use std::sync::Arc;
+    use write_buffer::mock::MockBufferForWritingThatAlwaysErrors;
+    use write_summary::WriteSummary;
+
+    static NAMESPACE: Lazy<DatabaseName<'static>> = Lazy::new(|| "bananas".try_into().unwrap());
+
+    fn backoff() -> BackoffConfig {
+        BackoffConfig {
+            init_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            base: 1.0,
+            deadline: None,
+        }
+    }
+
+    fn transient_error() -> DmlError {
+        DmlError::WriteBuffer(ShardError::WriteBufferErrors {
+            successes: 0,
+            errs: vec![],
+        })
+    }
+
+    fn assert_retries(metrics: &metric::Registry, op: &'static str, want: u64) {
+        let got = metrics
+            .get_instrument::<Metric<U64Counter>>("dml_handler_retries")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("op", op)]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(got, want);
+    }
+
+    #[tokio::test]
+    async fn test_write_retries_transient_error() {
+        let metrics = metric::Registry::default();
+        let inner = Arc::new(MockDmlHandler::<()>::default().with_write_return([
+            Err(transient_error()),
+            Err(transient_error()),
+            Ok(WriteSummary::default()),
+        ]));
+        let retry = Retry::new(
+            Arc::clone(&inner),
+            backoff(),
+            Duration::from_secs(10),
+            &metrics,
+        );
+
+        retry
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect("write should succeed after retrying");
+
+        assert_eq!(inner.calls().len(), 3);
+        assert_retries(&metrics, "write", 2);
+    }
+
+    #[tokio::test]
+    async fn test_write_does_not_retry_permanent_error() {
+        let metrics = metric::Registry::default();
+        let inner = Arc::new(
+            MockDmlHandler::<()>::default()
+                .with_write_return([Err(DmlError::DatabaseNotFound("bananas".to_string()))]),
+        );
+        let retry = Retry::new(
+            Arc::clone(&inner),
+            backoff(),
+            Duration::from_secs(10),
+            &metrics,
+        );
+
+        let err = retry
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, DmlError::DatabaseNotFound(_));
+
+        assert_eq!(inner.calls().len(), 1);
+        assert_retries(&metrics, "write", 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_does_not_retry_partial_write() {
+        let metrics = metric::Registry::default();
+        let inner = Arc::new(MockDmlHandler::<()>::default().with_write_return([Err(
+            DmlError::WriteBuffer(ShardError::WriteBufferErrors {
+                successes: 1,
+                errs: vec![],
+            }),
+        )]));
+        let retry = Retry::new(
+            Arc::clone(&inner),
+            backoff(),
+            Duration::from_secs(10),
+            &metrics,
+        );
+
+        let err = retry
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, DmlError::WriteBuffer(e) if e.is_partial());
+
+        assert_eq!(inner.calls().len(), 1);
+        assert_retries(&metrics, "write", 0);
+    }
+
+    #[tokio::test]
+    async fn test_write_deadline_exceeded() {
+        let metrics = metric::Registry::default();
+        let inner = Arc::new(
+            MockDmlHandler::<()>::default().with_write_return(
+                std::iter::repeat_with(|| Err(transient_error()))
+                    .take(1000)
+                    .collect::<Vec<_>>(),
+            ),
+        );
+        let retry = Retry::new(
+            Arc::clone(&inner),
+            backoff(),
+            Duration::from_millis(10),
+            &metrics,
+        );
+
+        let err = retry
+            .write(&*NAMESPACE, (), None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, DmlError::WriteBuffer(_));
+
+        // The write was attempted more than once, but gave up long before the
+        // mock ran out of errors to return.
+        let calls = inner.calls().len();
+        assert!(calls > 1 && calls < 1000, "unexpected call count {calls}");
+        assert_retries(&metrics, "write", calls as u64 - 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_retries_transient_error() {
+        let metrics = metric::Registry::default();
+        let inner = Arc::new(
+            MockDmlHandler::<()>::default()
+                .with_delete_return([Err(transient_error()), Ok(WriteSummary::default())]),
+        );
+        let retry = Retry::new(
+            Arc::clone(&inner),
+            backoff(),
+            Duration::from_secs(10),
+            &metrics,
+        );
+
+        let predicate = DeletePredicate {
+            range: data_types::TimestampRange::new(1, 2),
+            exprs: vec![],
+        };
+        retry
+            .delete(&*NAMESPACE, "platanos", &predicate, None)
+            .await
+            .expect("delete should succeed after retrying");
+
+        assert_matches!(
+            inner.calls().as_slice(),
+            [
+                MockDmlHandlerCall::Delete { .. },
+                MockDmlHandlerCall::Delete { .. }
+            ]
+        );
+        assert_retries(&metrics, "delete", 1);
+    }
+
+    #[tokio::test]
+    async fn test_write_does_not_retry_open_circuit() {
+        let metrics = metric::Registry::default();
+
+        // A shard that opens its circuit breaker after a single failed write.
+        let shard = Arc::new(
+            Shard::new(
+                ShardIndex::new(0),
+                Arc::new(MockBufferForWritingThatAlwaysErrors),
+                &metrics,
+            )
+            .with_circuit_breaker(
+                CircuitBreakerConfig {
+                    error_threshold: 1,
+                    cool_down: Duration::from_secs(3600),
+                },
+                &metrics,
+            ),
+        );
+        let sharder = Arc::new(
+            MockSharder::default()
+                .with_return(std::iter::repeat(shard).take(3).collect::<Vec<_>>()),
+        );
+        let retry = Retry::new(
+            ShardedWriteBuffer::new(Arc::clone(&sharder)),
+            backoff(),
+            Duration::from_secs(10),
+            &metrics,
+        );
+
+        let (writes, _) = mutable_batch_lp::lines_to_batches_stats("bananas val=42i 1", 42)
+            .expect("failed to build test writes from LP");
+        let writes = Partitioned::new("key".into(), writes);
+
+        // The first attempt fails with a write buffer error and is retried,
+        // after which the open circuit fails the write without further
+        // retries.
+        let err = retry
+            .write(&*NAMESPACE, writes.clone(), None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, DmlError::WriteBuffer(e) if e.is_circuit_open());
+        assert_eq!(sharder.calls().len(), 2);
+        assert_retries(&metrics, "write", 1);
+
+        // While the circuit is open, writes fail without being retried.
+        let err = retry
+            .write(&*NAMESPACE, writes, None)
+            .await
+            .expect_err("write should fail");
+        assert_matches!(err, DmlError::WriteBuffer(e) if e.is_circuit_open());
+        assert_eq!(sharder.calls().len(), 3);
+        assert_retries(&metrics, "write", 1);
+    }
+}
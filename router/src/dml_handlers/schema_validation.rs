@@ -1,7 +1,7 @@
 use super::DmlHandler;
 use crate::namespace_cache::{metrics::InstrumentedCache, MemoryNamespaceCache, NamespaceCache};
 use async_trait::async_trait;
-use data_types::{DatabaseName, DeletePredicate, NamespaceSchema};
+use data_types::{ColumnType, DatabaseName, DeletePredicate, NamespaceSchema};
 use hashbrown::HashMap;
 use iox_catalog::{
     interface::{get_schema_by_name, Catalog, Error as CatalogError},
@@ -38,6 +38,23 @@ pub enum SchemaError {
     UnexpectedCatalogError(iox_catalog::interface::Error),
 }
 
+/// The action taken by the [`SchemaValidator`] when a write contains a column
+/// with a type that conflicts with the existing column schema.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SchemaConflictPolicy {
+    /// Reject the write with a [`SchemaError::Conflict`].
+    #[default]
+    Reject,
+
+    /// Widen integer and unsigned integer values written to an existing float
+    /// column to floats, accepting the write.
+    ///
+    /// Integer values with a magnitude greater than 2^53 may lose precision
+    /// when widened. All other type conflicts (including float values written
+    /// to an existing integer column) are rejected.
+    Coerce,
+}
+
 /// A [`SchemaValidator`] checks the schema of incoming writes against a
 /// centralised schema store, maintaining an in-memory cache of all observed
 /// schemas.
@@ -72,8 +89,9 @@ pub enum SchemaError {
 ///
 /// 1. If the namespace's column limit is updated in the catalog, the new limit
 ///    will not be enforced until the whole namespace is recached, likely only
-///    on startup. In other words, updating the namespace's column limit requires
-///    both a catalog update and service restart.
+///    on startup. Limits updated through the router's namespace gRPC service
+///    are applied to the cached schema of that router immediately, but other
+///    router instances still require a restart to observe them.
 /// 2. There's a race condition that can result in a table ending up with more
 ///    columns than the namespace limit should allow. When multiple concurrent
 ///    writes come in to different service instances that each have their own
@@ -99,13 +117,26 @@ pub enum SchemaError {
 /// produce incorrect schemas ([#3573]).
 ///
 /// [#3573]: https://github.com/influxdata/influxdb_iox/issues/3573
+///
+/// # Conflict Policy
+///
+/// By default, a write containing a column with a type that conflicts with the
+/// existing schema is rejected. A [`SchemaConflictPolicy`] can be configured
+/// per namespace to instead coerce numeric type conflicts, allowing writes
+/// from agents emitting inconsistent integer & float types for the same field
+/// to be accepted.
 #[derive(Debug)]
 pub struct SchemaValidator<C = Arc<InstrumentedCache<MemoryNamespaceCache>>> {
     catalog: Arc<dyn Catalog>,
     cache: C,
 
+    /// The conflict policy of each namespace, defaulting to
+    /// [`SchemaConflictPolicy::Reject`] for unlisted namespaces.
+    conflict_policies: HashMap<String, SchemaConflictPolicy>,
+
     service_limit_hit: U64Counter,
     schema_conflict: U64Counter,
+    schema_coerced: U64Counter,
 }
 
 impl<C> SchemaValidator<C> {
@@ -126,14 +157,39 @@ impl<C> SchemaValidator<C> {
                 "number of requests that fail due to a schema conflict",
             )
             .recorder(&[]);
+        let schema_coerced = metrics
+            .register_metric::<U64Counter>(
+                "schema_validation_schema_coerced",
+                "number of columns widened to resolve a numeric schema conflict",
+            )
+            .recorder(&[]);
 
         Self {
             catalog,
             cache: ns_cache,
+            conflict_policies: Default::default(),
             service_limit_hit,
             schema_conflict,
+            schema_coerced,
         }
     }
+
+    /// Apply `policy` to schema conflicts in writes to `namespace`.
+    pub fn with_conflict_policy(
+        mut self,
+        namespace: impl Into<String>,
+        policy: SchemaConflictPolicy,
+    ) -> Self {
+        self.conflict_policies.insert(namespace.into(), policy);
+        self
+    }
+
+    fn conflict_policy(&self, namespace: &DatabaseName<'_>) -> SchemaConflictPolicy {
+        self.conflict_policies
+            .get(namespace.as_str())
+            .copied()
+            .unwrap_or_default()
+    }
 }
 
 #[async_trait]
@@ -143,6 +199,7 @@ where
 {
     type WriteError = SchemaError;
     type DeleteError = SchemaError;
+    type DeleteOutput = ();
 
     type WriteInput = HashMap<String, MutableBatch>;
     type WriteOutput = Self::WriteInput;
@@ -154,7 +211,8 @@ where
     /// If `namespace` does not exist, [`SchemaError::NamespaceLookup`] is
     /// returned.
     ///
-    /// If the schema validation fails due to a schema conflict in the request,
+    /// If the schema validation fails due to a schema conflict in the request
+    /// that is not resolved by the [`SchemaConflictPolicy`] of `namespace`,
     /// [`SchemaError::Conflict`] is returned.
     ///
     /// If the schema validation fails due to a service limit being reached,
@@ -165,7 +223,7 @@ where
     async fn write(
         &self,
         namespace: &DatabaseName<'static>,
-        batches: Self::WriteInput,
+        mut batches: Self::WriteInput,
         _span_ctx: Option<SpanContext>,
     ) -> Result<Self::WriteOutput, Self::WriteError> {
         let mut repos = self.catalog.repositories().await;
@@ -200,14 +258,43 @@ where
             SchemaError::ServiceLimit(Box::new(e))
         })?;
 
-        let maybe_new_schema = validate_or_insert_schema(
-            batches.iter().map(|(k, v)| (k.as_str(), v)),
-            &schema,
-            repos.deref_mut(),
-        )
-        .await
-        .map_err(|e| {
+        let policy = self.conflict_policy(namespace);
+        let maybe_new_schema = loop {
+            let e = match validate_or_insert_schema(
+                batches.iter().map(|(k, v)| (k.as_str(), v)),
+                &schema,
+                repos.deref_mut(),
+            )
+            .await
+            {
+                Ok(v) => break v.map(Arc::new),
+                Err(e) => e,
+            };
+
             match e.err() {
+                // Numeric schema conflicts that can be resolved by widening
+                // the column in the write to match the existing float column.
+                //
+                // Each iteration widens one column in the request, after
+                // which it no longer conflicts, so this loop terminates.
+                CatalogError::ColumnTypeMismatch {
+                    ref name,
+                    existing: ColumnType::F64,
+                    new: ColumnType::I64 | ColumnType::U64,
+                } if policy == SchemaConflictPolicy::Coerce => {
+                    debug!(
+                        %namespace,
+                        column_name=%name,
+                        table_name=%e.table(),
+                        "coercing column to float"
+                    );
+                    batches
+                        .get_mut(e.table())
+                        .expect("conflict in table not in request")
+                        .widen_to_float(name)
+                        .expect("conflicting numeric column must be widenable");
+                    self.schema_coerced.inc(1);
+                }
                 // Schema conflicts
                 CatalogError::ColumnTypeMismatch {
                     ref name,
@@ -223,22 +310,21 @@ where
                         "schema conflict"
                     );
                     self.schema_conflict.inc(1);
-                    SchemaError::Conflict(e)
+                    return Err(SchemaError::Conflict(e));
                 }
                 // Service limits
                 CatalogError::ColumnCreateLimitError { .. }
                 | CatalogError::TableCreateLimitError { .. } => {
                     warn!(%namespace, error=%e, "service protection limit reached");
                     self.service_limit_hit.inc(1);
-                    SchemaError::ServiceLimit(Box::new(e.into_err()))
+                    return Err(SchemaError::ServiceLimit(Box::new(e.into_err())));
                 }
                 _ => {
                     error!(%namespace, error=%e, "schema validation failed");
-                    SchemaError::UnexpectedCatalogError(e.into_err())
+                    return Err(SchemaError::UnexpectedCatalogError(e.into_err()));
                 }
             }
-        })?
-        .map(Arc::new);
+        };
 
         trace!(%namespace, "schema validation complete");
 
@@ -270,7 +356,7 @@ where
         _table_name: &str,
         _predicate: &DeletePredicate,
         _span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         Ok(())
     }
 }
@@ -611,6 +697,77 @@ mod tests {
         assert_eq!(1, handler.schema_conflict.fetch());
     }
 
+    #[tokio::test]
+    async fn test_write_conflict_coerced() {
+        let (catalog, _namespace) = test_setup().await;
+        let metrics = Arc::new(metric::Registry::default());
+        let new_handler = || {
+            SchemaValidator::new(
+                catalog.catalog(),
+                Arc::new(MemoryNamespaceCache::default()),
+                &*metrics,
+            )
+            .with_conflict_policy(NAMESPACE.as_str(), SchemaConflictPolicy::Coerce)
+        };
+        let handler = new_handler();
+
+        // Populate the cache of a second validator before the "bananas" table
+        // is created.
+        let stale = new_handler();
+        stale
+            .write(&*NAMESPACE, lp_to_writes("platanos val=1i 123456"), None)
+            .await
+            .expect("request should succeed");
+
+        // First write sets the schema
+        let writes = lp_to_writes("bananas,tag1=A val=42.0,count=1i 123456"); // val=float
+        handler
+            .write(&*NAMESPACE, writes, None)
+            .await
+            .expect("request should succeed");
+
+        // Integer and unsigned integer values for the cached float column are
+        // widened.
+        for lp in [
+            "bananas,tag1=A val=42i 123456",
+            "bananas,tag1=A val=42u,new=true 123456",
+        ] {
+            let got = handler
+                .write(&*NAMESPACE, lp_to_writes(lp), None)
+                .await
+                .expect("request should succeed");
+            let col = got["bananas"].column("val").unwrap();
+            assert_eq!(
+                col.influx_type(),
+                schema::InfluxColumnType::Field(schema::InfluxFieldType::Float)
+            );
+        }
+        assert_eq!(2, handler.schema_coerced.fetch());
+
+        // A validator with a stale cache discovers the conflict from the
+        // catalog, and also widens the column.
+        let got = stale
+            .write(&*NAMESPACE, lp_to_writes("bananas val=42i 123456"), None)
+            .await
+            .expect("request should succeed");
+        assert_eq!(
+            got["bananas"].column("val").unwrap().influx_type(),
+            schema::InfluxColumnType::Field(schema::InfluxFieldType::Float)
+        );
+
+        // Narrowing an integer column is still rejected.
+        let err = handler
+            .write(&*NAMESPACE, lp_to_writes("bananas count=4.2 123456"), None)
+            .await
+            .expect_err("request should fail");
+        assert_matches!(err, SchemaError::Conflict(_));
+
+        assert_cache(&handler, "bananas", "val", ColumnType::F64);
+        assert_cache(&handler, "bananas", "count", ColumnType::I64);
+        assert_cache(&handler, "bananas", "new", ColumnType::Bool);
+        assert_eq!(1, handler.schema_conflict.fetch());
+    }
+
     #[tokio::test]
     async fn test_write_table_service_limit() {
         let (catalog, _namespace) = test_setup().await;
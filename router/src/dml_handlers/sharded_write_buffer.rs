@@ -1,7 +1,11 @@
 //! Logic to shard writes/deletes and push them into a write buffer shard.
 
 use super::Partitioned;
-use crate::{dml_handlers::DmlHandler, shard::Shard};
+use crate::{
+    dml_handlers::DmlHandler,
+    provenance::Provenance,
+    shard::{EnqueueError, Shard},
+};
 use async_trait::async_trait;
 use data_types::{DatabaseName, DeletePredicate, NonEmptyString};
 use dml::{DmlDelete, DmlMeta, DmlOperation, DmlWrite};
@@ -15,8 +19,8 @@ use std::{
     sync::Arc,
 };
 use thiserror::Error;
+use tokio::task::JoinHandle;
 use trace::ctx::SpanContext;
-use write_buffer::core::WriteBufferError;
 
 /// Errors occurring while writing to one or more write buffer shards.
 #[derive(Debug, Error)]
@@ -30,10 +34,34 @@ pub enum ShardError {
         /// The number of successful shard writes.
         successes: usize,
         /// The errors returned by the failed shard writes.
-        errs: Vec<WriteBufferError>,
+        errs: Vec<EnqueueError>,
     },
 }
 
+impl ShardError {
+    /// Returns true if the write was rejected by at least one shard with an
+    /// open circuit breaker.
+    ///
+    /// Retrying such a write fails fast in the same way until the circuit
+    /// breaker cool down elapses.
+    pub fn is_circuit_open(&self) -> bool {
+        match self {
+            Self::WriteBufferErrors { errs, .. } => errs
+                .iter()
+                .any(|e| matches!(e, EnqueueError::CircuitOpen(_))),
+        }
+    }
+
+    /// Returns true if the write was accepted by at least one shard before
+    /// failing, and retrying it would write the same data to those shards
+    /// again.
+    pub fn is_partial(&self) -> bool {
+        match self {
+            Self::WriteBufferErrors { successes, .. } => *successes > 0,
+        }
+    }
+}
+
 /// Helper function to turn the set of `T` into strings and join them with `;`.
 ///
 /// Useful to join an array of errors for display purposes.
@@ -84,6 +112,7 @@ where
 {
     type WriteError = ShardError;
     type DeleteError = ShardError;
+    type DeleteOutput = Vec<DmlMeta>;
 
     type WriteInput = Partitioned<HashMap<String, MutableBatch>>;
     type WriteOutput = Vec<DmlMeta>;
@@ -106,20 +135,20 @@ where
         for (table, batch) in writes.into_iter() {
             let shard = self.sharder.shard(&table, namespace, &batch);
 
-            let existing = collated
-                .entry(shard)
-                .or_default()
-                .insert(table, batch.clone());
+            let existing = collated.entry(shard).or_default().insert(table, batch);
 
             assert!(existing.is_none());
         }
 
+        // Annotate each op with the provenance of the request (if any).
+        let annotations = Provenance::current().into_annotations();
+
         let iter = collated.into_iter().map(|(shard, batch)| {
             let dml = DmlWrite::new(
                 namespace,
                 batch,
                 Some(partition_key.clone()),
-                DmlMeta::unsequenced(span_ctx.clone()),
+                DmlMeta::unsequenced(span_ctx.clone()).with_annotations(annotations.clone()),
             );
 
             trace!(
@@ -144,7 +173,7 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), ShardError> {
+    ) -> Result<Self::DeleteOutput, ShardError> {
         let predicate = predicate.clone();
         let shards = self.sharder.shard(table_name, namespace, &predicate);
 
@@ -152,7 +181,8 @@ where
             namespace,
             predicate,
             NonEmptyString::new(table_name),
-            DmlMeta::unsequenced(span_ctx),
+            DmlMeta::unsequenced(span_ctx)
+                .with_annotations(Provenance::current().into_annotations()),
         );
 
         let iter = shards.into_iter().map(|s| {
@@ -165,10 +195,7 @@ where
             (s, DmlOperation::from(dml.clone()))
         });
 
-        // TODO: return shard metadata
-        parallel_enqueue(iter).await?;
-
-        Ok(())
+        parallel_enqueue(iter).await
     }
 }
 
@@ -177,6 +204,11 @@ where
 /// in parallel and gathers any errors.
 ///
 /// Returns a list of the sequences that were written.
+///
+/// If the returned future is dropped before completion (such as when the
+/// client that issued the request disconnects) any shard writes that have not
+/// yet completed are aborted, rather than continuing to consume write buffer
+/// capacity.
 async fn parallel_enqueue<T>(v: T) -> Result<Vec<DmlMeta>, ShardError>
 where
     T: Iterator<Item = (Arc<Shard>, DmlOperation)> + Send,
@@ -185,9 +217,8 @@ where
     let mut errs = vec![];
 
     v.map(|(shard, op)| async move {
-        tokio::spawn(async move { shard.enqueue(op).await })
-            .await
-            .expect("shard enqueue panic")
+        let mut handle = AbortOnDrop(tokio::spawn(async move { shard.enqueue(op).await }));
+        (&mut handle.0).await.expect("shard enqueue panic")
     })
     // Use FuturesUnordered so the futures can run in parallel
     .collect::<FuturesUnordered<_>>()
@@ -209,15 +240,30 @@ where
     }
 }
 
+/// A [`JoinHandle`] that aborts its task when dropped.
+///
+/// Aborting a completed task has no effect.
+#[derive(Debug)]
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::dml_handlers::DmlHandler;
     use assert_matches::assert_matches;
+    use backoff::BackoffConfig;
     use data_types::{ShardIndex, TimestampRange};
     use sharder::mock::{MockSharder, MockSharderCall, MockSharderPayload};
-    use std::sync::Arc;
-    use write_buffer::mock::{MockBufferForWriting, MockBufferSharedState};
+    use std::{sync::Arc, time::Duration};
+    use write_buffer::mock::{
+        MockBufferForWriting, MockBufferForWritingThatAlwaysErrors, MockBufferSharedState,
+    };
 
     // Parse `lp` into a table-keyed MutableBatch map.
     fn lp_to_writes(lp: &str) -> Partitioned<HashMap<String, MutableBatch>> {
@@ -293,6 +339,43 @@ mod tests {
         });
     }
 
+    #[tokio::test]
+    async fn test_write_provenance_annotations() {
+        let write_buffer = init_write_buffer(1);
+        let write_buffer_state = write_buffer.state();
+
+        let shard = Arc::new(Shard::new(
+            ShardIndex::new(0),
+            Arc::new(write_buffer),
+            &Default::default(),
+        ));
+        let sharder = Arc::new(MockSharder::default().with_return([Arc::clone(&shard)]));
+        let w = ShardedWriteBuffer::new(Arc::clone(&sharder));
+
+        let mut headers = hyper::HeaderMap::new();
+        headers.insert(
+            hyper::header::USER_AGENT,
+            hyper::header::HeaderValue::from_static("telegraf"),
+        );
+        let provenance = Provenance::from_headers(&headers);
+
+        // Ops enqueued within the scope of a request carry its provenance.
+        let ns = DatabaseName::new("bananas").unwrap();
+        provenance
+            .clone()
+            .scope(w.write(&ns, lp_to_writes("bananas val=42i 123456"), None))
+            .await
+            .expect("write failed");
+
+        let mut got = write_buffer_state.get_messages(shard.shard_index());
+        assert_eq!(got.len(), 1);
+        let got = got
+            .pop()
+            .unwrap()
+            .expect("write should have been successful");
+        assert_eq!(got.meta().annotations(), &provenance.into_annotations());
+    }
+
     #[tokio::test]
     async fn test_multiple_shard_writes() {
         let writes = lp_to_writes(
@@ -427,6 +510,63 @@ mod tests {
         assert_eq!(got.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_write_partial_success_retry() {
+        let writes = lp_to_writes(
+            "\
+                bananas,tag1=A,tag2=B val=42i 123456\n\
+                platanos,tag1=A,tag2=B value=42i 123456\n\
+            ",
+        );
+        let metrics = metric::Registry::default();
+        let backoff = BackoffConfig {
+            init_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            base: 1.0,
+            deadline: None,
+        };
+
+        let write_buffer1 = init_write_buffer(1);
+        let write_buffer1_state = write_buffer1.state();
+        let shard1 = Arc::new(
+            Shard::new(ShardIndex::new(0), Arc::new(write_buffer1), &metrics).with_retry(
+                backoff.clone(),
+                Duration::from_millis(50),
+                &metrics,
+            ),
+        );
+
+        // The second shard fails every attempt.
+        let shard2 = Arc::new(
+            Shard::new(
+                ShardIndex::new(1),
+                Arc::new(MockBufferForWritingThatAlwaysErrors),
+                &metrics,
+            )
+            .with_retry(backoff, Duration::from_millis(50), &metrics),
+        );
+
+        let sharder = Arc::new(
+            MockSharder::default().with_return([Arc::clone(&shard1), Arc::clone(&shard2)]),
+        );
+        let w = ShardedWriteBuffer::new(Arc::clone(&sharder));
+
+        let ns = DatabaseName::new("bananas").unwrap();
+        let err = w
+            .write(&ns, writes, None)
+            .await
+            .expect_err("write should return a failure");
+        assert_matches!(err, ShardError::WriteBufferErrors{successes, errs} => {
+            assert_eq!(errs.len(), 1);
+            assert_eq!(successes, 1);
+        });
+
+        // Only the failed shard was retried - the first shard observed the
+        // write exactly once.
+        let got = write_buffer1_state.get_messages(shard1.shard_index());
+        assert_eq!(got.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_shard_delete() {
         const TABLE: &str = "bananas";
@@ -452,10 +592,19 @@ mod tests {
 
         // Call the ShardedWriteBuffer and drive the test
         let ns = DatabaseName::new("namespace").unwrap();
-        w.delete(&ns, TABLE, &predicate, None)
+        let metas = w
+            .delete(&ns, TABLE, &predicate, None)
             .await
             .expect("delete failed");
 
+        // The sequencing metadata of the delete is returned.
+        assert_matches!(metas.as_slice(), [meta] => {
+            assert_eq!(
+                meta.sequence().map(|s| s.shard_index),
+                Some(shard.shard_index())
+            );
+        });
+
         // Assert the sharder saw all the tables
         let calls = sharder.calls();
         assert_matches!(calls.as_slice(), [MockSharderCall{table_name, ..}] => {
@@ -1,4 +1,6 @@
-use super::{partitioner::PartitionError, NamespaceCreationError, SchemaError, ShardError};
+use super::{
+    partitioner::PartitionError, NamespaceCreationError, RetentionError, SchemaError, ShardError,
+};
 use async_trait::async_trait;
 use data_types::{DatabaseName, DeletePredicate};
 use std::{error::Error, fmt::Debug, sync::Arc};
@@ -21,6 +23,10 @@ pub enum DmlError {
     #[error(transparent)]
     Schema(#[from] SchemaError),
 
+    /// A retention period validation failure.
+    #[error(transparent)]
+    Retention(#[from] RetentionError),
+
     /// Failed to create the request namespace.
     #[error(transparent)]
     NamespaceCreation(#[from] NamespaceCreationError),
@@ -57,6 +63,10 @@ pub trait DmlHandler: Debug + Send + Sync {
     /// The error type of the delete handler.
     type DeleteError: Error + Into<DmlError> + Send;
 
+    /// The (possibly transformed) output type returned by this handler after
+    /// processing a delete.
+    type DeleteOutput: Debug + Send + Sync;
+
     /// Write `batches` to `namespace`.
     async fn write(
         &self,
@@ -72,7 +82,7 @@ pub trait DmlHandler: Debug + Send + Sync {
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError>;
+    ) -> Result<Self::DeleteOutput, Self::DeleteError>;
 }
 
 #[async_trait]
@@ -84,6 +94,7 @@ where
     type WriteOutput = T::WriteOutput;
     type WriteError = T::WriteError;
     type DeleteError = T::DeleteError;
+    type DeleteOutput = T::DeleteOutput;
 
     async fn write(
         &self,
@@ -101,7 +112,7 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
         (**self)
             .delete(namespace, table_name, predicate, span_ctx)
             .await
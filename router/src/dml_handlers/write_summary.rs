@@ -26,12 +26,13 @@ impl<T> WriteSummaryAdapter<T> {
 #[async_trait]
 impl<T> DmlHandler for WriteSummaryAdapter<T>
 where
-    T: DmlHandler<WriteOutput = Vec<Vec<DmlMeta>>>,
+    T: DmlHandler<WriteOutput = Vec<Vec<DmlMeta>>, DeleteOutput = Vec<DmlMeta>>,
 {
     type WriteInput = T::WriteInput;
     type WriteOutput = WriteSummary;
     type WriteError = T::WriteError;
     type DeleteError = T::DeleteError;
+    type DeleteOutput = WriteSummary;
 
     /// Sends `input` to the inner handler, which returns a
     /// `Vec<Vec<DmlMeta>>`, creating a `WriteSummary`
@@ -52,9 +53,11 @@ where
         table_name: &str,
         predicate: &DeletePredicate,
         span_ctx: Option<SpanContext>,
-    ) -> Result<(), Self::DeleteError> {
-        self.inner
+    ) -> Result<Self::DeleteOutput, Self::DeleteError> {
+        let metas = self
+            .inner
             .delete(namespace, table_name, predicate, span_ctx)
-            .await
+            .await?;
+        Ok(WriteSummary::new(vec![metas]))
     }
 }
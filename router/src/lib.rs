@@ -26,7 +26,11 @@
 )]
 #![allow(clippy::missing_docs_in_private_items)]
 
+pub mod audit;
 pub mod dml_handlers;
 pub mod namespace_cache;
+pub mod provenance;
 pub mod server;
 pub mod shard;
+pub mod usage;
+pub mod write_buffer_health;
@@ -6,6 +6,9 @@ pub use memory::*;
 mod sharded_cache;
 pub use sharded_cache::*;
 
+mod refresh;
+pub use refresh::*;
+
 pub mod metrics;
 
 use data_types::{DatabaseName, NamespaceSchema};
@@ -24,4 +27,11 @@ pub trait NamespaceCache: Debug + Send + Sync {
         namespace: DatabaseName<'static>,
         schema: impl Into<Arc<NamespaceSchema>>,
     ) -> Option<Arc<NamespaceSchema>>;
+
+    /// Remove the [`NamespaceSchema`] mapped to `namespace`, returning it if it
+    /// was cached.
+    fn remove_schema(&self, namespace: &DatabaseName<'static>) -> Option<Arc<NamespaceSchema>>;
+
+    /// Return the names of all the cached namespaces, in no particular order.
+    fn namespaces(&self) -> Vec<DatabaseName<'static>>;
 }
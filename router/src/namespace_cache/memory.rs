@@ -23,6 +23,14 @@ impl NamespaceCache for Arc<MemoryNamespaceCache> {
     ) -> Option<Arc<NamespaceSchema>> {
         self.cache.write().insert(namespace, schema.into())
     }
+
+    fn remove_schema(&self, namespace: &DatabaseName<'static>) -> Option<Arc<NamespaceSchema>> {
+        self.cache.write().remove(namespace)
+    }
+
+    fn namespaces(&self) -> Vec<DatabaseName<'static>> {
+        self.cache.read().keys().cloned().collect()
+    }
 }
 
 #[cfg(test)]
@@ -43,6 +51,8 @@ mod tests {
             query_pool_id: QueryPoolId::new(1234),
             tables: Default::default(),
             max_columns_per_table: 50,
+            retention_period_ns: None,
+            partition_template: None,
         };
         assert!(cache.put_schema(ns.clone(), schema1.clone()).is_none());
         assert_eq!(*cache.get_schema(&ns).expect("lookup failure"), schema1);
@@ -53,6 +63,8 @@ mod tests {
             query_pool_id: QueryPoolId::new(2),
             tables: Default::default(),
             max_columns_per_table: 10,
+            retention_period_ns: None,
+            partition_template: None,
         };
 
         assert_eq!(
@@ -62,5 +74,16 @@ mod tests {
             schema1
         );
         assert_eq!(*cache.get_schema(&ns).expect("lookup failure"), schema2);
+        assert_eq!(cache.namespaces(), [ns.clone()]);
+
+        assert_eq!(
+            *cache
+                .remove_schema(&ns)
+                .expect("should have existing schema"),
+            schema2
+        );
+        assert!(cache.get_schema(&ns).is_none());
+        assert!(cache.remove_schema(&ns).is_none());
+        assert!(cache.namespaces().is_empty());
     }
 }
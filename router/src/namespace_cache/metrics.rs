@@ -130,6 +130,21 @@ where
             }
         }
     }
+
+    fn remove_schema(&self, namespace: &DatabaseName<'static>) -> Option<Arc<NamespaceSchema>> {
+        let res = self.inner.remove_schema(namespace)?;
+
+        // Remove the evicted namespace stats from the counts.
+        let stats = NamespaceStats::new(&*res);
+        self.table_count.dec(stats.table_count);
+        self.column_count.dec(stats.column_count);
+
+        Some(res)
+    }
+
+    fn namespaces(&self) -> Vec<DatabaseName<'static>> {
+        self.inner.namespaces()
+    }
 }
 
 #[derive(Debug)]
@@ -195,6 +210,8 @@ mod tests {
             query_pool_id: QueryPoolId::new(1234),
             tables,
             max_columns_per_table: 100,
+            retention_period_ns: None,
+            partition_template: None,
         }
     }
 
@@ -377,5 +394,11 @@ mod tests {
             ("result", "hit"),
             1,
         );
+
+        // Evict the new namespace
+        assert!(cache.remove_schema(&ns).is_some());
+        assert!(cache.remove_schema(&ns).is_none());
+        assert_eq!(cache.table_count.observe(), Observation::U64Gauge(2));
+        assert_eq!(cache.column_count.observe(), Observation::U64Gauge(11));
     }
 }
@@ -0,0 +1,216 @@
+//! Refreshing of cached [`NamespaceSchema`] from the catalog.
+
+use super::NamespaceCache;
+use data_types::{DatabaseName, NamespaceSchema};
+use iox_catalog::interface::{get_schema_by_id, Catalog, Error as CatalogError};
+use metric::U64Counter;
+use observability_deps::tracing::*;
+use std::{
+    ops::DerefMut,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+/// Reload the [`NamespaceSchema`] of `namespace` from `catalog` into `cache`,
+/// returning the refreshed schema.
+///
+/// If `namespace` does not exist in the catalog (or has been soft-deleted) it
+/// is evicted from `cache` and [`None`] is returned.
+pub async fn refresh_namespace<C>(
+    catalog: &dyn Catalog,
+    cache: &C,
+    namespace: &DatabaseName<'static>,
+) -> Result<Option<Arc<NamespaceSchema>>, CatalogError>
+where
+    C: NamespaceCache,
+{
+    let mut repos = catalog.repositories().await;
+
+    let id = match repos
+        .namespaces()
+        .get_by_name(namespace)
+        .await?
+        .filter(|ns| ns.deleted_at.is_none())
+    {
+        Some(v) => v.id,
+        None => {
+            cache.remove_schema(namespace);
+            debug!(%namespace, "evicted deleted namespace from schema cache");
+            return Ok(None);
+        }
+    };
+
+    let schema = Arc::new(get_schema_by_id(id, repos.deref_mut()).await?);
+    cache.put_schema(namespace.clone(), Arc::clone(&schema));
+    trace!(%namespace, "schema cache refreshed");
+
+    Ok(Some(schema))
+}
+
+/// Spawn a background task refreshing every namespace in `cache` from
+/// `catalog` once per `interval`, bounding the time taken for out-of-band
+/// catalog changes (such as limit updates or namespace deletions) to be
+/// observed by this router.
+///
+/// The task exits once `cache` is dropped.
+///
+/// # Panics
+///
+/// This spawns the refresh task, and so must be called from within a tokio
+/// runtime. Panics if `interval` is zero.
+pub fn spawn_refresh_task<T>(
+    catalog: Arc<dyn Catalog>,
+    cache: &Arc<T>,
+    interval: Duration,
+    metrics: &metric::Registry,
+) -> JoinHandle<()>
+where
+    T: Send + Sync + 'static,
+    Arc<T>: NamespaceCache,
+{
+    let refreshes = metrics.register_metric::<U64Counter>(
+        "namespace_cache_refresh",
+        "number of background namespace cache refreshes by result",
+    );
+    let refreshed = refreshes.recorder(&[("result", "refreshed")]);
+    let evicted = refreshes.recorder(&[("result", "evicted")]);
+    let failed = refreshes.recorder(&[("result", "error")]);
+
+    let cache = Arc::downgrade(cache);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        // The first tick completes immediately.
+        ticker.tick().await;
+
+        loop {
+            ticker.tick().await;
+
+            let cache = match Weak::upgrade(&cache) {
+                Some(v) => v,
+                None => return,
+            };
+
+            for namespace in cache.namespaces() {
+                match refresh_namespace(&*catalog, &cache, &namespace).await {
+                    Ok(Some(_)) => refreshed.inc(1),
+                    Ok(None) => evicted.inc(1),
+                    Err(e) => {
+                        warn!(error=%e, %namespace, "failed to refresh cached namespace schema");
+                        failed.inc(1);
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::namespace_cache::MemoryNamespaceCache;
+    use iox_catalog::mem::MemCatalog;
+    use test_helpers::timeout::FutureTimeout;
+
+    async fn new_catalog() -> (Arc<dyn Catalog>, DatabaseName<'static>) {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(metrics));
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("bananas").await.unwrap();
+        let pool = repos.query_pools().create_or_get("platanos").await.unwrap();
+        repos
+            .namespaces()
+            .create("bananas", "inf", topic.id, pool.id)
+            .await
+            .unwrap();
+        drop(repos);
+
+        (catalog, DatabaseName::try_from("bananas").unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_refresh_namespace() {
+        let (catalog, ns) = new_catalog().await;
+        let cache = Arc::new(MemoryNamespaceCache::default());
+
+        // Refreshing an uncached namespace populates the cache.
+        let got = refresh_namespace(&*catalog, &cache, &ns)
+            .await
+            .expect("refresh should succeed")
+            .expect("namespace should exist");
+        assert_eq!(cache.get_schema(&ns), Some(Arc::clone(&got)));
+
+        // Out-of-band changes are observed once refreshed.
+        catalog
+            .repositories()
+            .await
+            .namespaces()
+            .update_column_limit(&ns, 7)
+            .await
+            .unwrap();
+        assert_ne!(cache.get_schema(&ns).unwrap().max_columns_per_table, 7);
+        refresh_namespace(&*catalog, &cache, &ns)
+            .await
+            .expect("refresh should succeed");
+        assert_eq!(cache.get_schema(&ns).unwrap().max_columns_per_table, 7);
+
+        // Deleted namespaces are evicted.
+        catalog
+            .repositories()
+            .await
+            .namespaces()
+            .soft_delete(&ns)
+            .await
+            .unwrap();
+        let got = refresh_namespace(&*catalog, &cache, &ns)
+            .await
+            .expect("refresh should succeed");
+        assert!(got.is_none());
+        assert!(cache.get_schema(&ns).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_task() {
+        let (catalog, ns) = new_catalog().await;
+        let metrics = metric::Registry::default();
+        let cache = Arc::new(MemoryNamespaceCache::default());
+
+        refresh_namespace(&*catalog, &cache, &ns)
+            .await
+            .expect("refresh should succeed");
+
+        let handle = spawn_refresh_task(
+            Arc::clone(&catalog),
+            &cache,
+            Duration::from_millis(10),
+            &metrics,
+        );
+
+        catalog
+            .repositories()
+            .await
+            .namespaces()
+            .update_column_limit(&ns, 7)
+            .await
+            .unwrap();
+
+        // The background task observes the out-of-band change.
+        async {
+            while cache.get_schema(&ns).unwrap().max_columns_per_table != 7 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+
+        // Dropping the cache stops the task.
+        drop(cache);
+        handle
+            .with_timeout_panic(Duration::from_secs(5))
+            .await
+            .expect("refresh task should not panic");
+    }
+}
@@ -34,6 +34,18 @@ where
     ) -> Option<Arc<NamespaceSchema>> {
         self.shards.hash(&namespace).put_schema(namespace, schema)
     }
+
+    fn remove_schema(&self, namespace: &DatabaseName<'static>) -> Option<Arc<NamespaceSchema>> {
+        self.shards.hash(namespace).remove_schema(namespace)
+    }
+
+    fn namespaces(&self) -> Vec<DatabaseName<'static>> {
+        self.shards
+            .shards()
+            .iter()
+            .flat_map(|shard| shard.namespaces())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -61,6 +73,8 @@ mod tests {
             query_pool_id: QueryPoolId::new(1),
             tables: Default::default(),
             max_columns_per_table: 7,
+            retention_period_ns: None,
+            partition_template: None,
         }
     }
 
@@ -96,9 +110,22 @@ mod tests {
         }
 
         // The mapping should be stable
-        for (name, id) in names {
-            let want = schema_with_id(id as _);
-            assert_eq!(cache.get_schema(&name), Some(Arc::new(want)));
+        for (name, id) in &names {
+            let want = schema_with_id(*id as _);
+            assert_eq!(cache.get_schema(name), Some(Arc::new(want)));
+        }
+
+        // All the namespaces are listed across the shards
+        let mut got = cache.namespaces();
+        got.sort_unstable();
+        let mut want = names.keys().cloned().collect::<Vec<_>>();
+        want.sort_unstable();
+        assert_eq!(got, want);
+
+        // And can be removed from the shard they map to
+        for name in names.keys() {
+            assert!(cache.remove_schema(name).is_some());
         }
+        assert!(cache.namespaces().is_empty());
     }
 }
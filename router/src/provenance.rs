@@ -0,0 +1,144 @@
+//! Client-supplied provenance annotations attached to routed DML operations.
+//!
+//! The [`Provenance`] of a write or delete request describes the client that
+//! produced it (such as the name of the client agent and the host it
+//! originated from), derived from the request headers. When enabled, the HTTP
+//! server scopes the [`Provenance`] of each request to the handling of that
+//! request, and the [`ShardedWriteBuffer`] attaches it to the [`DmlMeta`] of
+//! every operation it enqueues, from where it is persisted with the
+//! sequencing metadata of the operation in the write buffer.
+//!
+//! The provenance is held in a task-local rather than passed through each
+//! [`DmlHandler`] in the handler chain - operations enqueued outside the
+//! scope of a request (or by a task spawned from it) carry no annotations.
+//!
+//! [`ShardedWriteBuffer`]: crate::dml_handlers::ShardedWriteBuffer
+//! [`DmlMeta`]: dml::DmlMeta
+//! [`DmlHandler`]: crate::dml_handlers::DmlHandler
+
+use hyper::{header::USER_AGENT, HeaderMap};
+use std::{collections::BTreeMap, future::Future};
+
+/// The annotation key of the client agent name, from the `User-Agent` header.
+pub const ANNOTATION_AGENT: &str = "agent";
+
+/// The annotation key of the originating client host, from the first entry of
+/// the `X-Forwarded-For` header.
+pub const ANNOTATION_SOURCE_HOST: &str = "source-host";
+
+/// The header carrying the chain of client addresses a request was proxied
+/// for.
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// The maximum length of an annotation value, in bytes - longer values are
+/// truncated.
+const MAX_VALUE_LEN: usize = 256;
+
+tokio::task_local! {
+    static CURRENT: Provenance;
+}
+
+/// The key/value annotations describing the client that produced a DML
+/// request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance(BTreeMap<String, String>);
+
+impl Provenance {
+    /// Derive the [`Provenance`] of a request from its `headers`.
+    ///
+    /// Headers that are absent, or that contain non-ASCII values, are
+    /// ignored.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let mut annotations = BTreeMap::new();
+
+        let mut annotate = |key: &str, value: Option<&str>| {
+            if let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) {
+                let value = &value[..value.len().min(MAX_VALUE_LEN)];
+                annotations.insert(key.to_string(), value.to_string());
+            }
+        };
+
+        annotate(
+            ANNOTATION_AGENT,
+            headers.get(USER_AGENT).and_then(|v| v.to_str().ok()),
+        );
+        annotate(
+            ANNOTATION_SOURCE_HOST,
+            headers
+                .get(X_FORWARDED_FOR)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.split(',').next()),
+        );
+
+        Self(annotations)
+    }
+
+    /// Returns the [`Provenance`] of the request being handled by the current
+    /// task, or an empty [`Provenance`] if called outside of
+    /// [`Provenance::scope()`].
+    pub fn current() -> Self {
+        CURRENT.try_with(Clone::clone).unwrap_or_default()
+    }
+
+    /// Drive `fut` to completion with `self` as the [`Provenance::current()`]
+    /// value.
+    pub async fn scope<F>(self, fut: F) -> F::Output
+    where
+        F: Future,
+    {
+        CURRENT.scope(self, fut).await
+    }
+
+    /// Consume `self`, returning the annotations as key/value pairs.
+    pub fn into_annotations(self) -> BTreeMap<String, String> {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Provenance::from_headers(&headers), Provenance::default());
+
+        headers.insert(USER_AGENT, HeaderValue::from_static("telegraf/1.24"));
+        headers.insert(
+            X_FORWARDED_FOR,
+            HeaderValue::from_static("10.0.0.1, 10.0.0.2"),
+        );
+        assert_eq!(
+            Provenance::from_headers(&headers).into_annotations(),
+            BTreeMap::from([
+                (ANNOTATION_AGENT.to_string(), "telegraf/1.24".to_string()),
+                (ANNOTATION_SOURCE_HOST.to_string(), "10.0.0.1".to_string()),
+            ])
+        );
+
+        // Long values are truncated, and non-ASCII values ignored.
+        let long = "a".repeat(MAX_VALUE_LEN + 1);
+        headers.insert(USER_AGENT, HeaderValue::from_str(&long).unwrap());
+        headers.insert(X_FORWARDED_FOR, HeaderValue::from_bytes(b"\xFF").unwrap());
+        let got = Provenance::from_headers(&headers).into_annotations();
+        assert_eq!(got[ANNOTATION_AGENT].len(), MAX_VALUE_LEN);
+        assert!(!got.contains_key(ANNOTATION_SOURCE_HOST));
+    }
+
+    #[tokio::test]
+    async fn test_scope() {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("telegraf"));
+        let provenance = Provenance::from_headers(&headers);
+
+        assert_eq!(Provenance::current(), Provenance::default());
+        let got = provenance
+            .clone()
+            .scope(async { Provenance::current() })
+            .await;
+        assert_eq!(got, provenance);
+        assert_eq!(Provenance::current(), Provenance::default());
+    }
+}
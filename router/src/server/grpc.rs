@@ -1,79 +1,262 @@
 //! gRPC service implementations for `router`.
 
+pub mod auth;
+pub mod delete;
+pub mod flight;
+pub mod health;
+pub mod message_limit;
+pub mod namespace;
 pub mod sharder;
+pub mod write;
 
-use self::sharder::ShardService;
-use crate::shard::Shard;
+use self::{
+    auth::{GrpcAuth, GrpcAuthPolicy},
+    delete::DeleteService,
+    flight::FlightWriteService,
+    health::{probe_catalog, probe_object_store, ServiceHealth},
+    message_limit::MessageLimit,
+    namespace::NamespaceService,
+    sharder::ShardService,
+    write::WriteService,
+};
+use crate::{
+    dml_handlers::DmlHandler, namespace_cache::NamespaceCache, shard::Shard,
+    write_buffer_health::WriteBufferHealth,
+};
 use ::sharder::Sharder;
-use generated_types::influxdata::iox::{
-    catalog::v1::*, object_store::v1::*, schema::v1::*, sharder::v1::*,
+use arrow_flight::flight_service_server::{FlightService, FlightServiceServer};
+use data_types::{DeletePredicate, QueryPoolId, TopicId};
+use generated_types::influxdata::{
+    iox::{
+        catalog::v1::*, delete::v1::delete_service_server, namespace::v1::namespace_service_server,
+        object_store::v1::*, schema::v1::*, sharder::v1::*,
+    },
+    pbdata::v1::write_service_server,
 };
+use hashbrown::HashMap;
 use iox_catalog::interface::Catalog;
+use mutable_batch::MutableBatch;
 use object_store::DynObjectStore;
 use service_grpc_catalog::CatalogService;
 use service_grpc_object_store::ObjectStoreService;
 use service_grpc_schema::SchemaService;
 use std::sync::Arc;
+use tonic::{codec::CompressionEncoding, transport::NamedService};
+use write_summary::WriteSummary;
+
+/// Configure a generated gRPC server type with the compression and message
+/// size limit of the [`GrpcDelegate`].
+macro_rules! configure_server {
+    ($delegate:expr, $server:expr) => {{
+        let mut server = $server;
+        if let Some(encoding) = $delegate.compression {
+            server = server.accept_compressed(encoding).send_compressed(encoding);
+        }
+        MessageLimit::new(server, $delegate.max_message_size)
+    }};
+}
 
 /// This type is responsible for managing all gRPC services exposed by `router`.
 #[derive(Debug)]
-pub struct GrpcDelegate<S> {
+pub struct GrpcDelegate<D, S, C> {
+    dml_handler: Arc<D>,
     catalog: Arc<dyn Catalog>,
+    ns_cache: C,
     object_store: Arc<DynObjectStore>,
     shard_service: ShardService<S>,
+    delete_service: DeleteService<S>,
+    topic_id: TopicId,
+    query_id: QueryPoolId,
+    admin_auth: Option<Arc<GrpcAuthPolicy>>,
+    compression: Option<CompressionEncoding>,
+    max_message_size: Option<usize>,
+    write_buffer_health: Option<Arc<WriteBufferHealth>>,
 }
 
-impl<S> GrpcDelegate<S> {
+impl<D, S, C> GrpcDelegate<D, S, C> {
     /// Initialise a new gRPC handler, dispatching DML operations to `dml_handler`.
+    ///
+    /// Namespaces created through the [`NamespaceService`] are assigned to
+    /// `topic_id` and `query_id`, and namespace updates are applied to the
+    /// schemas cached in `ns_cache`.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        dml_handler: Arc<D>,
         catalog: Arc<dyn Catalog>,
+        ns_cache: C,
         object_store: Arc<DynObjectStore>,
         shard_service: ShardService<S>,
+        delete_service: DeleteService<S>,
+        topic_id: TopicId,
+        query_id: QueryPoolId,
     ) -> Self {
         Self {
+            dml_handler,
             catalog,
+            ns_cache,
             object_store,
             shard_service,
+            delete_service,
+            topic_id,
+            query_id,
+            admin_auth: None,
+            compression: None,
+            max_message_size: None,
+            write_buffer_health: None,
         }
     }
+
+    /// Require calls to the administrative catalog, object store and namespace
+    /// services to be authorized by `policy`.
+    pub fn with_admin_auth(mut self, policy: Arc<GrpcAuthPolicy>) -> Self {
+        self.admin_auth = Some(policy);
+        self
+    }
+
+    /// Accept requests compressed with `encoding`, and compress responses with
+    /// `encoding` when the client indicates it is supported.
+    pub fn with_compression(mut self, encoding: CompressionEncoding) -> Self {
+        self.compression = Some(encoding);
+        self
+    }
+
+    /// Reject request messages larger than `max_message_size` bytes (before
+    /// decompression) with `RESOURCE_EXHAUSTED`.
+    ///
+    /// Each message of a streaming request is limited individually.
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = Some(max_message_size);
+        self
+    }
+
+    /// Report the write, flight and delete services as not serving while any
+    /// write buffer shard in `health` is unreachable.
+    pub fn with_write_buffer_health(mut self, health: Arc<WriteBufferHealth>) -> Self {
+        self.write_buffer_health = Some(health);
+        self
+    }
+
+    /// Wrap an administrative gRPC service with the configured authorization
+    /// policy, if any.
+    fn admin<T>(&self, service: T) -> GrpcAuth<T> {
+        GrpcAuth::new(service, self.admin_auth.clone())
+    }
 }
 
-impl<S> GrpcDelegate<S>
+impl<D, S, C> GrpcDelegate<D, S, C>
 where
-    S: Sharder<(), Item = Arc<Shard>> + Clone + 'static,
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary> + 'static,
+    S: Sharder<(), Item = Arc<Shard>>
+        + Sharder<DeletePredicate, Item = Vec<Arc<Shard>>>
+        + Clone
+        + 'static,
+    C: NamespaceCache + Clone + 'static,
 {
+    /// Acquire a [`WriteService`] gRPC service implementation, writing to the
+    /// same DML handler stack as the HTTP write endpoints.
+    ///
+    /// [`WriteService`]: generated_types::influxdata::pbdata::v1::write_service_server::WriteService
+    pub fn write_service(
+        &self,
+    ) -> MessageLimit<
+        write_service_server::WriteServiceServer<impl write_service_server::WriteService>,
+    > {
+        configure_server!(
+            self,
+            write_service_server::WriteServiceServer::new(WriteService::new(Arc::clone(
+                &self.dml_handler,
+            )))
+        )
+    }
+
+    /// Acquire an Arrow Flight service implementation accepting `DoPut`
+    /// uploads of record batches, writing to the same DML handler stack as
+    /// the HTTP write endpoints.
+    pub fn flight_service(&self) -> MessageLimit<FlightServiceServer<impl FlightService>> {
+        configure_server!(
+            self,
+            FlightServiceServer::new(FlightWriteService::new(Arc::clone(&self.dml_handler)))
+        )
+    }
+
     /// Acquire a [`SchemaService`] gRPC service implementation.
     ///
     /// [`SchemaService`]: generated_types::influxdata::iox::schema::v1::schema_service_server::SchemaService.
-    pub fn schema_service(&self) -> schema_service_server::SchemaServiceServer<SchemaService> {
-        schema_service_server::SchemaServiceServer::new(SchemaService::new(Arc::clone(
-            &self.catalog,
-        )))
+    pub fn schema_service(
+        &self,
+    ) -> MessageLimit<schema_service_server::SchemaServiceServer<SchemaService>> {
+        configure_server!(
+            self,
+            schema_service_server::SchemaServiceServer::new(SchemaService::new(Arc::clone(
+                &self.catalog,
+            )))
+        )
     }
 
-    /// Acquire a [`CatalogService`] gRPC service implementation.
+    /// Acquire a [`CatalogService`] gRPC service implementation, authorized
+    /// by the admin policy (if any).
     ///
     /// [`CatalogService`]: generated_types::influxdata::iox::catalog::v1::catalog_service_server::CatalogService.
     pub fn catalog_service(
         &self,
-    ) -> catalog_service_server::CatalogServiceServer<impl catalog_service_server::CatalogService>
-    {
-        catalog_service_server::CatalogServiceServer::new(CatalogService::new(Arc::clone(
-            &self.catalog,
-        )))
+    ) -> GrpcAuth<
+        MessageLimit<
+            catalog_service_server::CatalogServiceServer<
+                impl catalog_service_server::CatalogService,
+            >,
+        >,
+    > {
+        self.admin(configure_server!(
+            self,
+            catalog_service_server::CatalogServiceServer::new(CatalogService::new(Arc::clone(
+                &self.catalog
+            )))
+        ))
     }
 
-    /// Acquire a [`ObjectStoreService`] gRPC service implementation.
+    /// Acquire a [`ObjectStoreService`] gRPC service implementation,
+    /// authorized by the admin policy (if any).
     ///
     /// [`ObjectStoreService`]: generated_types::influxdata::iox::object_store::v1::object_store_service_server::ObjectStoreService.
     pub fn object_store_service(
         &self,
-    ) -> object_store_service_server::ObjectStoreServiceServer<
-        impl object_store_service_server::ObjectStoreService,
+    ) -> GrpcAuth<
+        MessageLimit<
+            object_store_service_server::ObjectStoreServiceServer<
+                impl object_store_service_server::ObjectStoreService,
+            >,
+        >,
+    > {
+        self.admin(configure_server!(
+            self,
+            object_store_service_server::ObjectStoreServiceServer::new(ObjectStoreService::new(
+                Arc::clone(&self.catalog),
+                Arc::clone(&self.object_store)
+            ))
+        ))
+    }
+
+    /// Acquire a [`NamespaceService`] gRPC service implementation, authorized
+    /// by the admin policy (if any).
+    ///
+    /// [`NamespaceService`]: generated_types::influxdata::iox::namespace::v1::namespace_service_server::NamespaceService
+    pub fn namespace_service(
+        &self,
+    ) -> GrpcAuth<
+        MessageLimit<
+            namespace_service_server::NamespaceServiceServer<
+                impl namespace_service_server::NamespaceService,
+            >,
+        >,
     > {
-        object_store_service_server::ObjectStoreServiceServer::new(ObjectStoreService::new(
-            Arc::clone(&self.catalog),
-            Arc::clone(&self.object_store),
+        self.admin(configure_server!(
+            self,
+            namespace_service_server::NamespaceServiceServer::new(NamespaceService::new(
+                Arc::clone(&self.catalog),
+                self.ns_cache.clone(),
+                self.topic_id,
+                self.query_id,
+            ))
         ))
     }
 
@@ -82,7 +265,80 @@ where
     /// [`ShardService`]: generated_types::influxdata::iox::sharder::v1::shard_service_server::ShardService
     pub fn shard_service(
         &self,
-    ) -> shard_service_server::ShardServiceServer<impl shard_service_server::ShardService> {
-        shard_service_server::ShardServiceServer::new(self.shard_service.clone())
+    ) -> MessageLimit<
+        shard_service_server::ShardServiceServer<impl shard_service_server::ShardService>,
+    > {
+        configure_server!(
+            self,
+            shard_service_server::ShardServiceServer::new(self.shard_service.clone())
+        )
+    }
+
+    /// Return a gRPC [`DeleteService`] handler.
+    ///
+    /// [`DeleteService`]: generated_types::influxdata::iox::delete::v1::delete_service_server::DeleteService
+    pub fn delete_service(
+        &self,
+    ) -> MessageLimit<
+        delete_service_server::DeleteServiceServer<impl delete_service_server::DeleteService>,
+    > {
+        configure_server!(
+            self,
+            delete_service_server::DeleteServiceServer::new(self.delete_service.clone())
+        )
+    }
+
+    /// Probe the catalog and object store, returning the serving status of
+    /// the services that depend on them.
+    ///
+    /// The schema, catalog and namespace services require the catalog, and
+    /// the object store service requires both the catalog and object store.
+    /// The write, flight and delete services require every write buffer shard
+    /// to be reachable, as last probed by the [`WriteBufferHealth`] (if any).
+    /// The shard service maps tables using in-memory state, and is always
+    /// serving.
+    pub async fn service_health(&self) -> Vec<ServiceHealth> {
+        let catalog = probe_catalog(&*self.catalog).await;
+        let object_store = probe_object_store(&*self.object_store).await;
+        let write_buffer = self
+            .write_buffer_health
+            .as_ref()
+            .map_or(true, |h| h.is_healthy());
+
+        let health = |service, serving| ServiceHealth { service, serving };
+        vec![
+            health(
+                <schema_service_server::SchemaServiceServer<SchemaService>>::NAME,
+                catalog,
+            ),
+            health(
+                <catalog_service_server::CatalogServiceServer<CatalogService>>::NAME,
+                catalog,
+            ),
+            health(
+                <namespace_service_server::NamespaceServiceServer<NamespaceService<C>>>::NAME,
+                catalog,
+            ),
+            health(
+                <object_store_service_server::ObjectStoreServiceServer<ObjectStoreService>>::NAME,
+                catalog && object_store,
+            ),
+            health(
+                <shard_service_server::ShardServiceServer<ShardService<S>>>::NAME,
+                true,
+            ),
+            health(
+                <write_service_server::WriteServiceServer<WriteService<D>>>::NAME,
+                write_buffer,
+            ),
+            health(
+                <FlightServiceServer<FlightWriteService<D>>>::NAME,
+                write_buffer,
+            ),
+            health(
+                <delete_service_server::DeleteServiceServer<DeleteService<S>>>::NAME,
+                write_buffer,
+            ),
+        ]
     }
 }
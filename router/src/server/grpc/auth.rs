@@ -0,0 +1,318 @@
+//! Token authentication and per-method authorization of the router
+//! administrative gRPC services.
+
+use crate::server::http::{AuthError, Credentials};
+use futures::future::{self, Either, Ready};
+use hashbrown::HashMap;
+use observability_deps::tracing::*;
+use sha2::{Digest, Sha256};
+use std::{
+    convert::Infallible,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tonic::{
+    body::BoxBody,
+    codegen::{http, Service},
+    transport::NamedService,
+    Status,
+};
+
+/// Errors parsing a [`TokenGrant`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TokenGrantError {
+    /// The grant is not of the form `<token>=<pattern>[;<pattern>...]`.
+    #[error("invalid token grant, expected <token>=<method pattern>[;<method pattern>...]")]
+    InvalidFormat,
+
+    /// A method pattern is not `*`, `<service>/*` or `<service>/<method>`.
+    #[error("invalid method pattern {0:?}")]
+    InvalidPattern(String),
+}
+
+/// A pattern matching the gRPC methods a token is authorized to call.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MethodPattern {
+    /// All methods of all services.
+    Any,
+    /// All methods of the fully-qualified service.
+    Service(String),
+    /// A single method, as `<service>/<method>`.
+    Method(String),
+}
+
+impl MethodPattern {
+    /// Returns true if this pattern matches the request `path` of the form
+    /// `/<service>/<method>`.
+    fn matches(&self, path: &str) -> bool {
+        let path = path.trim_start_matches('/');
+        match self {
+            Self::Any => true,
+            Self::Service(service) => path
+                .split_once('/')
+                .map_or(false, |(s, _method)| s == service),
+            Self::Method(method) => path == method,
+        }
+    }
+}
+
+impl FromStr for MethodPattern {
+    type Err = TokenGrantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "*" {
+            return Ok(Self::Any);
+        }
+
+        match s.split_once('/') {
+            Some((service, "*")) if !service.is_empty() => Ok(Self::Service(service.to_string())),
+            Some((service, method))
+                if !service.is_empty() && !method.is_empty() && !method.contains('/') =>
+            {
+                Ok(Self::Method(s.to_string()))
+            }
+            _ => Err(TokenGrantError::InvalidPattern(s.to_string())),
+        }
+    }
+}
+
+/// An API token and the gRPC methods it is authorized to call, parsed from
+/// the form `<token>=<pattern>[;<pattern>...]`.
+///
+/// Each pattern is one of:
+///
+///   * `*` - all methods of all protected services
+///   * `<service>/*` - all methods of the fully-qualified service, such as
+///     `influxdata.iox.namespace.v1.NamespaceService/*`
+///   * `<service>/<method>` - a single method, such as
+///     `influxdata.iox.namespace.v1.NamespaceService/GetNamespaces`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenGrant {
+    token: String,
+    methods: Vec<MethodPattern>,
+}
+
+impl FromStr for TokenGrant {
+    type Err = TokenGrantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, methods) = s.split_once('=').ok_or(TokenGrantError::InvalidFormat)?;
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(TokenGrantError::InvalidFormat);
+        }
+
+        let methods = methods
+            .split(';')
+            .map(MethodPattern::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            token: token.to_string(),
+            methods,
+        })
+    }
+}
+
+/// The set of API tokens authorized to call the protected gRPC services, and
+/// the methods each token may call.
+///
+/// Only `Authorization: Token <token>` credentials are accepted. Tokens are
+/// held as their SHA-256 digest, and never logged.
+#[derive(Debug, Default)]
+pub struct GrpcAuthPolicy {
+    grants: HashMap<Vec<u8>, Vec<MethodPattern>>,
+}
+
+impl GrpcAuthPolicy {
+    /// Authorize the token in `grant` to call the methods it names, in
+    /// addition to any methods previously granted to the same token.
+    pub fn with_grant(mut self, grant: TokenGrant) -> Self {
+        self.grants
+            .entry(Sha256::digest(grant.token.as_bytes()).to_vec())
+            .or_default()
+            .extend(grant.methods);
+        self
+    }
+
+    /// Return `Ok(())` if the credentials in `headers` authorize a call to the
+    /// method identified by the request `path`.
+    fn check(&self, headers: &http::HeaderMap, path: &str) -> Result<(), Status> {
+        let credentials = Credentials::try_from(headers).map_err(|e| match e {
+            AuthError::NoCredentials | AuthError::InvalidHeader(_) => {
+                Status::unauthenticated(e.to_string())
+            }
+            _ => Status::internal(e.to_string()),
+        })?;
+
+        let token = match &credentials {
+            Credentials::Token(v) => v,
+            Credentials::Basic { .. } => {
+                return Err(Status::unauthenticated(
+                    "only token credentials are supported",
+                ))
+            }
+        };
+
+        let methods = self
+            .grants
+            .get(Sha256::digest(token.as_bytes()).as_slice())
+            .ok_or_else(|| Status::unauthenticated(AuthError::InvalidCredentials.to_string()))?;
+
+        if !methods.iter().any(|m| m.matches(path)) {
+            warn!(principal=%credentials.principal(), %path, "grpc call denied");
+            return Err(Status::permission_denied(format!(
+                "access to {path} denied"
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// A decorator over a gRPC service `S` rejecting calls that are not
+/// authorized by the configured [`GrpcAuthPolicy`], if any.
+///
+/// Calls with missing or unknown credentials are rejected with
+/// `UNAUTHENTICATED`, and calls to methods the token is not granted are
+/// rejected with `PERMISSION_DENIED`, before the request is passed to `S`.
+#[derive(Debug, Clone)]
+pub struct GrpcAuth<S> {
+    inner: S,
+    policy: Option<Arc<GrpcAuthPolicy>>,
+}
+
+impl<S> GrpcAuth<S> {
+    /// Wrap `inner`, authorizing calls against `policy` (or allowing all calls
+    /// if `None`).
+    pub fn new(inner: S, policy: Option<Arc<GrpcAuthPolicy>>) -> Self {
+        Self { inner, policy }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for GrpcAuth<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>, Error = Infallible>,
+{
+    type Response = http::Response<BoxBody>;
+    type Error = Infallible;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        if let Some(policy) = &self.policy {
+            if let Err(status) = policy.check(req.headers(), req.uri().path()) {
+                return Either::Right(future::ready(Ok(status.to_http())));
+            }
+        }
+
+        Either::Left(self.inner.call(req))
+    }
+}
+
+impl<S> NamedService for GrpcAuth<S>
+where
+    S: NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use tonic::Code;
+
+    const NAMESPACE_SERVICE: &str = "influxdata.iox.namespace.v1.NamespaceService";
+
+    fn headers(authorization: &str) -> http::HeaderMap {
+        let mut headers = http::HeaderMap::new();
+        headers.insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_str(authorization).unwrap(),
+        );
+        headers
+    }
+
+    fn policy() -> GrpcAuthPolicy {
+        GrpcAuthPolicy::default()
+            .with_grant("admin=*".parse().unwrap())
+            .with_grant(
+                format!("reader={NAMESPACE_SERVICE}/GetNamespaces;influxdata.iox.catalog.v1.CatalogService/*")
+                    .parse()
+                    .unwrap(),
+            )
+    }
+
+    #[test]
+    fn test_parse_grant() {
+        let got = "s3cret=*;a.B/*;a.B/Method".parse::<TokenGrant>().unwrap();
+        assert_eq!(got.token, "s3cret");
+        assert_eq!(
+            got.methods,
+            [
+                MethodPattern::Any,
+                MethodPattern::Service("a.B".to_string()),
+                MethodPattern::Method("a.B/Method".to_string()),
+            ]
+        );
+
+        assert_matches!(
+            "s3cret".parse::<TokenGrant>(),
+            Err(TokenGrantError::InvalidFormat)
+        );
+        assert_matches!(
+            "=*".parse::<TokenGrant>(),
+            Err(TokenGrantError::InvalidFormat)
+        );
+        for pattern in ["", "a.B", "/*", "a.B/", "a.B/C/D"] {
+            assert_matches!(
+                format!("s3cret={pattern}").parse::<TokenGrant>(),
+                Err(TokenGrantError::InvalidPattern(_)),
+                "pattern {pattern:?} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let policy = policy();
+        let get = format!("/{NAMESPACE_SERVICE}/GetNamespaces");
+        let delete = format!("/{NAMESPACE_SERVICE}/DeleteNamespace");
+        let catalog = "/influxdata.iox.catalog.v1.CatalogService/GetPartitionsByTableId";
+
+        // The admin token may call any method.
+        for path in [get.as_str(), delete.as_str(), catalog] {
+            assert!(policy.check(&headers("Token admin"), path).is_ok());
+        }
+
+        // The reader token may only call the granted methods.
+        assert!(policy.check(&headers("Token reader"), &get).is_ok());
+        assert!(policy.check(&headers("Token reader"), catalog).is_ok());
+        let err = policy
+            .check(&headers("Token reader"), &delete)
+            .expect_err("ungranted method should be denied");
+        assert_eq!(err.code(), Code::PermissionDenied);
+
+        // Missing, unknown and unsupported credentials are rejected.
+        let err = policy
+            .check(&http::HeaderMap::new(), &get)
+            .expect_err("missing credentials should be rejected");
+        assert_eq!(err.code(), Code::Unauthenticated);
+        let err = policy
+            .check(&headers("Token bananas"), &get)
+            .expect_err("unknown token should be rejected");
+        assert_eq!(err.code(), Code::Unauthenticated);
+        let basic = format!("Basic {}", base64::encode("admin:admin"));
+        let err = policy
+            .check(&headers(&basic), &get)
+            .expect_err("basic credentials should be rejected");
+        assert_eq!(err.code(), Code::Unauthenticated);
+    }
+}
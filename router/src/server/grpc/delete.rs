@@ -0,0 +1,375 @@
+//! A gRPC service applying deletes to every shard they map to in the
+//! background, and reporting the progress of each.
+
+use crate::shard::Shard;
+use data_types::{DatabaseName, DeletePredicate, NonEmptyString, SequenceNumber, ShardIndex};
+use dml::{DmlDelete, DmlMeta, DmlOperation};
+use futures::future;
+use generated_types::{
+    google::{FieldViolationExt, FromOptionalField, OptionalField},
+    influxdata::iox::delete::v1::{
+        delete_service_server, DeleteJobStatus, DeletePayload, DeleteRequest, DeleteResponse,
+        GetDeleteStatusRequest, GetDeleteStatusResponse, ShardDeleteState, ShardDeleteStatus,
+    },
+};
+use observability_deps::tracing::*;
+use parking_lot::Mutex;
+use sharder::Sharder;
+use std::{collections::BTreeMap, sync::Arc};
+use tonic::{Request, Response, Status};
+use trace::ctx::SpanContext;
+
+/// The maximum number of delete jobs retained for status queries - once
+/// exceeded, the oldest jobs are discarded.
+const MAX_RETAINED_JOBS: usize = 1_000;
+
+/// The state of a delete job in a single shard.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ShardState {
+    Pending,
+    Enqueued(SequenceNumber),
+    Failed(String),
+}
+
+/// A delete, and its state in each shard it maps to.
+#[derive(Debug)]
+struct DeleteJob {
+    payload: DeletePayload,
+    shards: BTreeMap<ShardIndex, ShardState>,
+}
+
+impl DeleteJob {
+    fn status(&self, job_id: u64) -> DeleteJobStatus {
+        let shards = self
+            .shards
+            .iter()
+            .map(|(shard_index, state)| {
+                let (state, sequence_number, error) = match state {
+                    ShardState::Pending => (ShardDeleteState::Pending, None, String::new()),
+                    ShardState::Enqueued(s) => {
+                        (ShardDeleteState::Enqueued, Some(s.get()), String::new())
+                    }
+                    ShardState::Failed(e) => (ShardDeleteState::Failed, None, e.clone()),
+                };
+                ShardDeleteStatus {
+                    shard_index: shard_index.get(),
+                    state: state.into(),
+                    sequence_number,
+                    error,
+                }
+            })
+            .collect();
+
+        DeleteJobStatus {
+            job_id,
+            payload: Some(self.payload.clone()),
+            complete: self.shards.values().all(|s| *s != ShardState::Pending),
+            shards,
+        }
+    }
+}
+
+/// The delete jobs accepted by a [`DeleteService`], keyed by job ID.
+#[derive(Debug, Default)]
+struct DeleteJobs {
+    next_id: u64,
+    jobs: BTreeMap<u64, DeleteJob>,
+}
+
+impl DeleteJobs {
+    /// Record `job`, returning the ID assigned to it.
+    fn insert(&mut self, job: DeleteJob) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.jobs.insert(id, job);
+
+        while self.jobs.len() > MAX_RETAINED_JOBS {
+            let oldest = *self.jobs.keys().next().expect("jobs cannot be empty");
+            self.jobs.remove(&oldest);
+        }
+
+        id
+    }
+}
+
+/// A [`DeleteService`] accepts deletes for a namespace (or a table within
+/// it), enqueuing them into every shard they map to in the background.
+///
+/// Each accepted delete is assigned a job ID, which can be used to query the
+/// progress of the delete in each shard, including the sequence number
+/// assigned to it by the shard once enqueued. Because the delete is applied
+/// in the background, it continues to completion if the client disconnects.
+///
+/// Jobs are tracked in memory by this router instance, are lost on restart,
+/// and only the most recent jobs are retained.
+///
+/// This service MUST be initialised with the same sharder instance as the
+/// [`ShardedWriteBuffer`] for deletes to be routed to the same shards as
+/// writes.
+///
+/// [`ShardedWriteBuffer`]: crate::dml_handlers::ShardedWriteBuffer
+#[derive(Debug, Clone)]
+pub struct DeleteService<S> {
+    sharder: S,
+    jobs: Arc<Mutex<DeleteJobs>>,
+}
+
+impl<S> DeleteService<S> {
+    /// Initialise a [`DeleteService`] routing deletes with `sharder`.
+    pub fn new(sharder: S) -> Self {
+        Self {
+            sharder,
+            jobs: Default::default(),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<S> delete_service_server::DeleteService for DeleteService<S>
+where
+    S: Sharder<DeletePredicate, Item = Vec<Arc<Shard>>> + 'static,
+{
+    async fn delete(
+        &self,
+        request: Request<DeleteRequest>,
+    ) -> Result<Response<DeleteResponse>, Status> {
+        let span_ctx: Option<SpanContext> = request.extensions().get().cloned();
+
+        let payload = request.into_inner().payload.unwrap_field("payload")?;
+        let namespace = DatabaseName::try_from(payload.db_name.clone()).scope("payload.db_name")?;
+        let predicate: DeletePredicate = payload.predicate.clone().required("payload.predicate")?;
+
+        let shards = self
+            .sharder
+            .shard(&payload.table_name, &namespace, &predicate);
+
+        let dml = DmlDelete::new(
+            namespace.as_ref(),
+            predicate,
+            NonEmptyString::new(&payload.table_name),
+            DmlMeta::unsequenced(span_ctx),
+        );
+
+        let job_id = self.jobs.lock().insert(DeleteJob {
+            shards: shards
+                .iter()
+                .map(|s| (s.shard_index(), ShardState::Pending))
+                .collect(),
+            payload,
+        });
+
+        debug!(
+            job_id,
+            %namespace,
+            table_name=?dml.table_name(),
+            num_shards=shards.len(),
+            "routing grpc delete",
+        );
+
+        // Enqueue the delete into each shard concurrently, recording the
+        // outcome of each as it completes.
+        let jobs = Arc::clone(&self.jobs);
+        tokio::spawn(future::join_all(shards.into_iter().map(move |shard| {
+            let jobs = Arc::clone(&jobs);
+            let op = DmlOperation::from(dml.clone());
+            async move {
+                let shard_index = shard.shard_index();
+                let state = match shard.enqueue(op).await {
+                    Ok(meta) => ShardState::Enqueued(
+                        meta.sequence()
+                            .expect("enqueued delete must be sequenced")
+                            .sequence_number,
+                    ),
+                    Err(e) => {
+                        warn!(job_id, %shard_index, error=%e, "failed to enqueue delete");
+                        ShardState::Failed(e.to_string())
+                    }
+                };
+
+                // The job may have been discarded if many newer deletes were
+                // accepted in the meantime.
+                if let Some(job) = jobs.lock().jobs.get_mut(&job_id) {
+                    job.shards.insert(shard_index, state);
+                }
+            }
+        })));
+
+        Ok(Response::new(DeleteResponse { job_id }))
+    }
+
+    async fn get_delete_status(
+        &self,
+        request: Request<GetDeleteStatusRequest>,
+    ) -> Result<Response<GetDeleteStatusResponse>, Status> {
+        let job_id = request.into_inner().job_id;
+
+        let status = self
+            .jobs
+            .lock()
+            .jobs
+            .get(&job_id)
+            .map(|job| job.status(job_id))
+            .ok_or_else(|| Status::not_found(format!("delete job {} not found", job_id)))?;
+
+        Ok(Response::new(GetDeleteStatusResponse {
+            status: Some(status),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use generated_types::influxdata::iox::{
+        delete::v1::delete_service_server::DeleteService as _,
+        predicate::v1::{Predicate, TimestampRange},
+    };
+    use sharder::JumpHash;
+    use std::{num::NonZeroU32, time::Duration};
+    use test_helpers::timeout::FutureTimeout;
+    use tonic::Code;
+    use write_buffer::{
+        core::WriteBufferWriting,
+        mock::{MockBufferForWriting, MockBufferForWritingThatAlwaysErrors, MockBufferSharedState},
+    };
+
+    fn delete_request(db_name: &str, table_name: &str) -> Request<DeleteRequest> {
+        Request::new(DeleteRequest {
+            payload: Some(DeletePayload {
+                db_name: db_name.to_string(),
+                table_name: table_name.to_string(),
+                predicate: Some(Predicate {
+                    range: Some(TimestampRange { start: 1, end: 2 }),
+                    exprs: vec![],
+                }),
+            }),
+        })
+    }
+
+    /// Poll the status of `job_id` until it is complete.
+    async fn wait_complete<S>(svc: &DeleteService<S>, job_id: u64) -> DeleteJobStatus
+    where
+        S: Sharder<DeletePredicate, Item = Vec<Arc<Shard>>> + 'static,
+    {
+        async {
+            loop {
+                let status = svc
+                    .get_delete_status(Request::new(GetDeleteStatusRequest { job_id }))
+                    .await
+                    .expect("job should exist")
+                    .into_inner()
+                    .status
+                    .expect("response should contain status");
+                if status.complete {
+                    return status;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_delete_fan_out() {
+        let state = MockBufferSharedState::empty_with_n_shards(NonZeroU32::new(2).unwrap());
+        let write_buffer: Arc<dyn WriteBufferWriting> = Arc::new(
+            MockBufferForWriting::new(
+                state.clone(),
+                None,
+                Arc::new(iox_time::SystemProvider::default()),
+            )
+            .unwrap(),
+        );
+        let failing: Arc<dyn WriteBufferWriting> = Arc::new(MockBufferForWritingThatAlwaysErrors);
+
+        let metrics = metric::Registry::default();
+        let shards = [
+            Shard::new(ShardIndex::new(0), Arc::clone(&write_buffer), &metrics),
+            Shard::new(ShardIndex::new(1), write_buffer, &metrics),
+            Shard::new(ShardIndex::new(2), failing, &metrics),
+        ];
+        let svc = DeleteService::new(Arc::new(JumpHash::new(shards.into_iter().map(Arc::new))));
+
+        let job_id = svc
+            .delete(delete_request("bananas", ""))
+            .await
+            .expect("delete should be accepted")
+            .into_inner()
+            .job_id;
+
+        let status = wait_complete(&svc, job_id).await;
+        assert_eq!(status.job_id, job_id);
+        assert_eq!(status.payload.unwrap().db_name, "bananas");
+        assert_eq!(status.shards.len(), 3);
+
+        // The delete was enqueued into the reachable shards.
+        for (shard, status) in status.shards[..2].iter().enumerate() {
+            assert_eq!(status.shard_index, shard as i32);
+            assert_eq!(status.state(), ShardDeleteState::Enqueued);
+            assert!(status.sequence_number.is_some());
+            assert_eq!(state.get_messages(ShardIndex::new(shard as i32)).len(), 1);
+        }
+
+        // And reported failing in the unreachable shard.
+        let failed = &status.shards[2];
+        assert_eq!(failed.state(), ShardDeleteState::Failed);
+        assert_eq!(failed.sequence_number, None);
+        assert!(!failed.error.is_empty());
+
+        // Each delete is assigned a new job ID.
+        let next = svc
+            .delete(delete_request("bananas", "platanos"))
+            .await
+            .expect("delete should be accepted")
+            .into_inner()
+            .job_id;
+        assert_ne!(next, job_id);
+    }
+
+    #[tokio::test]
+    async fn test_delete_invalid() {
+        let shard = Shard::new(
+            ShardIndex::new(0),
+            Arc::new(MockBufferForWritingThatAlwaysErrors),
+            &metric::Registry::default(),
+        );
+        let svc = DeleteService::new(Arc::new(JumpHash::new([Arc::new(shard)])));
+
+        let err = svc
+            .delete(delete_request("bananas!", ""))
+            .await
+            .expect_err("invalid namespace should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let mut req = delete_request("bananas", "");
+        req.get_mut().payload.as_mut().unwrap().predicate = None;
+        let err = svc
+            .delete(req)
+            .await
+            .expect_err("missing predicate should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        assert_matches!(
+            svc.get_delete_status(Request::new(GetDeleteStatusRequest { job_id: 42 }))
+                .await,
+            Err(e) if e.code() == Code::NotFound
+        );
+    }
+
+    #[test]
+    fn test_jobs_retention() {
+        let mut jobs = DeleteJobs::default();
+        for _ in 0..=MAX_RETAINED_JOBS {
+            jobs.insert(DeleteJob {
+                payload: DeletePayload::default(),
+                shards: Default::default(),
+            });
+        }
+
+        assert_eq!(jobs.jobs.len(), MAX_RETAINED_JOBS);
+        assert!(!jobs.jobs.contains_key(&0));
+        assert!(jobs.jobs.contains_key(&(MAX_RETAINED_JOBS as u64)));
+    }
+}
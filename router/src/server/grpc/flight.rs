@@ -0,0 +1,545 @@
+//! An Arrow Flight service accepting `DoPut` uploads of record batches, for
+//! high-throughput bulk loads.
+
+use super::write::dml_error_to_status;
+use crate::dml_handlers::{DmlError, DmlHandler};
+use arrow::{
+    array::{
+        Array, ArrayRef, BooleanArray, DictionaryArray, Float64Array, Int64Array, StringArray,
+        TimestampNanosecondArray, UInt64Array,
+    },
+    buffer::Buffer,
+    datatypes::{DataType, Int32Type, Schema, SchemaRef, TimeUnit},
+    error::ArrowError,
+    ipc::{self, reader},
+    record_batch::RecordBatch,
+};
+use arrow_flight::{
+    flight_service_server::FlightService, utils::flight_data_to_arrow_batch, Action, ActionType,
+    Criteria, Empty, FlightData, FlightDescriptor, FlightInfo, HandshakeRequest, HandshakeResponse,
+    PutResult, SchemaResult, Ticket,
+};
+use data_types::{DatabaseName, DatabaseNameError};
+use futures::{Stream, StreamExt};
+use hashbrown::HashMap;
+use mutable_batch::{writer::Writer, MutableBatch};
+use observability_deps::tracing::*;
+use schema::TIME_COLUMN_NAME;
+use std::{pin::Pin, sync::Arc};
+use thiserror::Error;
+use tonic::{Request, Response, Status, Streaming};
+use trace::ctx::SpanContext;
+use write_summary::WriteSummary;
+
+type TonicStream<T> = Pin<Box<dyn Stream<Item = Result<T, Status>> + Send + Sync + 'static>>;
+
+/// Errors returned when writing a `DoPut` stream of record batches.
+#[derive(Debug, Error)]
+pub enum FlightWriteError {
+    /// The first message of the stream has no flight descriptor.
+    #[error("no flight descriptor provided")]
+    MissingDescriptor,
+
+    /// The flight descriptor does not identify a namespace & table.
+    #[error("flight descriptor path must be [namespace, table], got {0:?}")]
+    InvalidDescriptor(Vec<String>),
+
+    /// The namespace in the flight descriptor is invalid.
+    #[error(transparent)]
+    InvalidNamespace(#[from] DatabaseNameError),
+
+    /// A flight message could not be decoded.
+    #[error("invalid flight message: {0}")]
+    InvalidMessage(String),
+
+    /// A record batch or dictionary was sent before the schema.
+    #[error("record batch received before schema")]
+    NoSchema,
+
+    /// The flight data could not be decoded to a record batch.
+    #[error("invalid flight data: {0}")]
+    Arrow(#[from] ArrowError),
+
+    /// A column is of a type that cannot be written.
+    #[error("column {column} has unsupported type {data_type}")]
+    UnsupportedColumn {
+        /// The name of the column.
+        column: String,
+        /// The arrow type of the column.
+        data_type: DataType,
+    },
+
+    /// The record batch has no time column, or it contains nulls.
+    #[error("record batch must contain a non-null {} column", TIME_COLUMN_NAME)]
+    InvalidTime,
+
+    /// Converting the record batch failed, such as due to a column of
+    /// conflicting types.
+    #[error("failed to convert record batch: {0}")]
+    Convert(#[from] mutable_batch::writer::Error),
+
+    /// An error returned from the [`DmlHandler`].
+    #[error(transparent)]
+    DmlHandler(#[from] DmlError),
+}
+
+impl From<FlightWriteError> for Status {
+    fn from(e: FlightWriteError) -> Self {
+        match e {
+            FlightWriteError::DmlHandler(e) => dml_error_to_status(e),
+            e => Status::invalid_argument(e.to_string()),
+        }
+    }
+}
+
+/// An Arrow Flight service accepting `DoPut` uploads of record batches to a
+/// single table, converting them directly to [`MutableBatch`] writes without
+/// a line protocol round trip.
+///
+/// The `DoPut` stream is described by a [`FlightDescriptor`] path of
+/// `[namespace, table]` in its first message, which carries the schema of the
+/// stream. Tag columns are dictionary-encoded strings (as in IOx query
+/// results), and the stream MUST contain a non-null `time` column.
+///
+/// Each record batch is passed to the [`DmlHandler`] as a separate write, with
+/// a [`PutResult`] carrying its write token returned to the client.
+#[derive(Debug)]
+pub struct FlightWriteService<D> {
+    dml_handler: Arc<D>,
+}
+
+impl<D> FlightWriteService<D> {
+    /// Initialise a new [`FlightWriteService`] passing writes to `dml_handler`.
+    pub fn new(dml_handler: Arc<D>) -> Self {
+        Self { dml_handler }
+    }
+}
+
+impl<D> FlightWriteService<D>
+where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary>,
+{
+    /// Write the record batches of the `DoPut` message `stream`, returning a
+    /// [`PutResult`] for each.
+    async fn write_stream<S>(
+        &self,
+        mut stream: S,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<Vec<PutResult>, FlightWriteError>
+    where
+        S: Stream<Item = Result<FlightData, Status>> + Unpin + Send,
+    {
+        let mut target: Option<(DatabaseName<'static>, String)> = None;
+        let mut schema: Option<SchemaRef> = None;
+        let mut dictionaries_by_field: std::collections::HashMap<i64, ArrayRef> =
+            Default::default();
+        let mut results = vec![];
+
+        while let Some(data) = stream.next().await {
+            let data = data.map_err(|e| FlightWriteError::InvalidMessage(e.to_string()))?;
+
+            // The first message identifies the namespace & table written to.
+            if target.is_none() {
+                target = Some(parse_descriptor(data.flight_descriptor.as_ref())?);
+            }
+            let (namespace, table) = target.as_ref().expect("target is set");
+
+            let message = ipc::root_as_message(&data.data_header[..])
+                .map_err(|e| FlightWriteError::InvalidMessage(e.to_string()))?;
+            match message.header_type() {
+                ipc::MessageHeader::NONE => {}
+                ipc::MessageHeader::Schema => {
+                    schema = Some(Arc::new(Schema::try_from(&data)?));
+                    dictionaries_by_field.clear();
+                }
+                ipc::MessageHeader::DictionaryBatch => {
+                    let schema = schema.as_ref().ok_or(FlightWriteError::NoSchema)?;
+                    let dictionary = message.header_as_dictionary_batch().ok_or_else(|| {
+                        FlightWriteError::InvalidMessage("invalid dictionary batch".to_string())
+                    })?;
+                    let buffer: Buffer = data.data_body.into();
+                    reader::read_dictionary(
+                        &buffer,
+                        dictionary,
+                        schema,
+                        &mut dictionaries_by_field,
+                        &message.version(),
+                    )?;
+                }
+                ipc::MessageHeader::RecordBatch => {
+                    let schema = schema.as_ref().ok_or(FlightWriteError::NoSchema)?;
+                    let batch = flight_data_to_arrow_batch(
+                        &data,
+                        Arc::clone(schema),
+                        &dictionaries_by_field,
+                    )?;
+                    if batch.num_rows() == 0 {
+                        continue;
+                    }
+
+                    let mut mutable_batch = MutableBatch::new();
+                    write_record_batch(&mut mutable_batch, &batch)?;
+
+                    debug!(
+                        num_rows = batch.num_rows(),
+                        %table,
+                        %namespace,
+                        "routing flight write",
+                    );
+
+                    let summary = self
+                        .dml_handler
+                        .write(
+                            namespace,
+                            std::iter::once((table.clone(), mutable_batch)).collect(),
+                            span_ctx.clone(),
+                        )
+                        .await
+                        .map_err(Into::into)?;
+
+                    results.push(PutResult {
+                        app_metadata: summary.to_token().into_bytes(),
+                    });
+                }
+                other => {
+                    return Err(FlightWriteError::InvalidMessage(format!(
+                        "unsupported message type {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// Parse the `[namespace, table]` path of `descriptor`.
+fn parse_descriptor(
+    descriptor: Option<&FlightDescriptor>,
+) -> Result<(DatabaseName<'static>, String), FlightWriteError> {
+    let descriptor = descriptor.ok_or(FlightWriteError::MissingDescriptor)?;
+    match descriptor.path.as_slice() {
+        [namespace, table] if !table.is_empty() => {
+            Ok((DatabaseName::try_from(namespace.clone())?, table.clone()))
+        }
+        path => Err(FlightWriteError::InvalidDescriptor(path.to_vec())),
+    }
+}
+
+/// Returns the validity bitmask of `array`, or [`None`] if it contains no
+/// nulls.
+fn valid_mask(array: &dyn Array) -> Option<Vec<u8>> {
+    if array.null_count() == 0 {
+        return None;
+    }
+
+    let mut mask = vec![0_u8; (array.len() + 7) / 8];
+    for idx in (0..array.len()).filter(|idx| array.is_valid(*idx)) {
+        mask[idx / 8] |= 1 << (idx % 8);
+    }
+    Some(mask)
+}
+
+/// Downcast `array` to the concrete array type `T`, which the caller has
+/// determined from its data type.
+fn downcast<T: 'static>(array: &ArrayRef) -> &T {
+    array
+        .as_any()
+        .downcast_ref::<T>()
+        .expect("array type matches data type")
+}
+
+/// Append the rows of `record_batch` to `batch`, writing dictionary-encoded
+/// string columns as tags, the `time` column as the timestamp, and all other
+/// columns as fields.
+///
+/// On error, `batch` is left unchanged.
+fn write_record_batch(
+    batch: &mut MutableBatch,
+    record_batch: &RecordBatch,
+) -> Result<(), FlightWriteError> {
+    let schema = record_batch.schema();
+    let mut writer = Writer::new(batch, record_batch.num_rows());
+    let mut has_time = false;
+
+    for (field, array) in schema.fields().iter().zip(record_batch.columns()) {
+        let name = field.name().as_str();
+        let mask = valid_mask(array.as_ref());
+        let mask = mask.as_deref();
+
+        match array.data_type() {
+            DataType::Timestamp(TimeUnit::Nanosecond, _) | DataType::Int64
+                if name == TIME_COLUMN_NAME =>
+            {
+                if mask.is_some() {
+                    return Err(FlightWriteError::InvalidTime);
+                }
+                let values = match array.data_type() {
+                    DataType::Int64 => downcast::<Int64Array>(array).values(),
+                    _ => downcast::<TimestampNanosecondArray>(array).values(),
+                };
+                writer.write_time(name, values.iter().copied())?;
+                has_time = true;
+            }
+            DataType::Dictionary(key, value)
+                if **key == DataType::Int32 && **value == DataType::Utf8 =>
+            {
+                let dict = downcast::<DictionaryArray<Int32Type>>(array);
+                let values = downcast::<StringArray>(dict.values());
+                writer.write_tag_dict(
+                    name,
+                    mask,
+                    dict.keys().iter().flatten().map(|k| k as usize),
+                    (0..values.len()).map(|i| values.value(i)),
+                )?;
+            }
+            DataType::Float64 => {
+                writer.write_f64(name, mask, downcast::<Float64Array>(array).iter().flatten())?
+            }
+            DataType::Int64 => {
+                writer.write_i64(name, mask, downcast::<Int64Array>(array).iter().flatten())?
+            }
+            DataType::UInt64 => {
+                writer.write_u64(name, mask, downcast::<UInt64Array>(array).iter().flatten())?
+            }
+            DataType::Boolean => {
+                writer.write_bool(name, mask, downcast::<BooleanArray>(array).iter().flatten())?
+            }
+            DataType::Utf8 => {
+                writer.write_string(name, mask, downcast::<StringArray>(array).iter().flatten())?
+            }
+            data_type => {
+                return Err(FlightWriteError::UnsupportedColumn {
+                    column: name.to_string(),
+                    data_type: data_type.clone(),
+                })
+            }
+        }
+    }
+
+    if !has_time {
+        return Err(FlightWriteError::InvalidTime);
+    }
+
+    writer.commit();
+    Ok(())
+}
+
+#[tonic::async_trait]
+impl<D> FlightService for FlightWriteService<D>
+where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary> + 'static,
+{
+    type HandshakeStream = TonicStream<HandshakeResponse>;
+    type ListFlightsStream = TonicStream<FlightInfo>;
+    type DoGetStream = TonicStream<FlightData>;
+    type DoPutStream = TonicStream<PutResult>;
+    type DoActionStream = TonicStream<arrow_flight::Result>;
+    type ListActionsStream = TonicStream<ActionType>;
+    type DoExchangeStream = TonicStream<FlightData>;
+
+    async fn do_put(
+        &self,
+        request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoPutStream>, Status> {
+        let span_ctx: Option<SpanContext> = request.extensions().get().cloned();
+
+        let results = self
+            .write_stream(request.into_inner(), span_ctx)
+            .await
+            .map_err(|e| {
+                debug!(error=%e, "flight write failed");
+                Status::from(e)
+            })?;
+
+        let output = futures::stream::iter(results.into_iter().map(Ok));
+        Ok(Response::new(Box::pin(output) as Self::DoPutStream))
+    }
+
+    async fn handshake(
+        &self,
+        _request: Request<Streaming<HandshakeRequest>>,
+    ) -> Result<Response<Self::HandshakeStream>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn list_flights(
+        &self,
+        _request: Request<Criteria>,
+    ) -> Result<Response<Self::ListFlightsStream>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn get_flight_info(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<FlightInfo>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn get_schema(
+        &self,
+        _request: Request<FlightDescriptor>,
+    ) -> Result<Response<SchemaResult>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn do_get(
+        &self,
+        _request: Request<Ticket>,
+    ) -> Result<Response<Self::DoGetStream>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn do_action(
+        &self,
+        _request: Request<Action>,
+    ) -> Result<Response<Self::DoActionStream>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn list_actions(
+        &self,
+        _request: Request<Empty>,
+    ) -> Result<Response<Self::ListActionsStream>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+
+    async fn do_exchange(
+        &self,
+        _request: Request<Streaming<FlightData>>,
+    ) -> Result<Response<Self::DoExchangeStream>, Status> {
+        Err(Status::unimplemented("Not yet implemented"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall};
+    use arrow::ipc::writer::IpcWriteOptions;
+    use arrow_flight::{
+        flight_descriptor::DescriptorType, utils::flight_data_from_arrow_batch, SchemaAsIpc,
+    };
+    use arrow_util::assert_batches_eq;
+    use assert_matches::assert_matches;
+    use mutable_batch_lp::lines_to_batches;
+    use schema::selection::Selection;
+
+    /// Encode `lp` (of a single table) as the messages of a `DoPut` stream to
+    /// `path`.
+    fn put_stream(path: &[&str], lp: &str) -> Vec<FlightData> {
+        let batches = lines_to_batches(lp, 0).expect("invalid line protocol");
+        let (_, batch) = batches.into_iter().next().expect("no table");
+        let batch = batch.to_arrow(Selection::All).unwrap();
+
+        let options = IpcWriteOptions::default();
+        let mut schema: FlightData = SchemaAsIpc::new(&batch.schema(), &options).into();
+        schema.flight_descriptor = Some(FlightDescriptor {
+            r#type: DescriptorType::Path as i32,
+            path: path.iter().map(ToString::to_string).collect(),
+            ..Default::default()
+        });
+
+        let (dictionaries, data) = flight_data_from_arrow_batch(&batch, &options);
+        std::iter::once(schema)
+            .chain(dictionaries)
+            .chain(std::iter::once(data))
+            .collect()
+    }
+
+    async fn write(
+        svc: &FlightWriteService<MockDmlHandler<HashMap<String, MutableBatch>>>,
+        messages: Vec<FlightData>,
+    ) -> Result<Vec<PutResult>, FlightWriteError> {
+        svc.write_stream(futures::stream::iter(messages.into_iter().map(Ok)), None)
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_write() {
+        let dml_handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([Ok(WriteSummary::default()), Ok(WriteSummary::default())]),
+        );
+        let svc = FlightWriteService::new(Arc::clone(&dml_handler));
+
+        // A stream of two record batches, the second preceded by a new
+        // schema (the descriptor of which is ignored).
+        let mut messages = put_stream(
+            &["bananas_test", "platanos"],
+            "platanos,tag1=A val=42i,f=1.5,s=\"x\",b=true,u=1u 1\nplatanos,tag1=B val=24i 2",
+        );
+        messages.extend(put_stream(&["ignored", "ignored"], "platanos v=1 3"));
+
+        let results = write(&svc, messages).await.expect("write should succeed");
+        assert_eq!(results.len(), 2);
+
+        assert_matches!(
+            dml_handler.calls().as_slice(),
+            [
+                MockDmlHandlerCall::Write { namespace, write_input },
+                MockDmlHandlerCall::Write { namespace: namespace2, write_input: write_input2 },
+            ] => {
+                assert_eq!(namespace, "bananas_test");
+                assert_eq!(namespace2, "bananas_test");
+                assert_eq!(write_input2.get("platanos").expect("table not found").rows(), 1);
+
+                let batch = write_input.get("platanos").expect("table not found");
+                assert_batches_eq!(
+                    [
+                        "+------+-----+---+------+--------------------------------+---+-----+",
+                        "| b    | f   | s | tag1 | time                           | u | val |",
+                        "+------+-----+---+------+--------------------------------+---+-----+",
+                        "| true | 1.5 | x | A    | 1970-01-01T00:00:00.000000001Z | 1 | 42  |",
+                        "|      |     |   | B    | 1970-01-01T00:00:00.000000002Z |   | 24  |",
+                        "+------+-----+---+------+--------------------------------+---+-----+",
+                    ],
+                    &[batch.to_arrow(Selection::All).unwrap()]
+                );
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_invalid() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let svc = FlightWriteService::new(Arc::clone(&dml_handler));
+
+        let err = write(&svc, put_stream(&["bananas_test"], "platanos v=1 1"))
+            .await
+            .expect_err("invalid descriptor should be rejected");
+        assert_matches!(err, FlightWriteError::InvalidDescriptor(_));
+
+        let err = write(&svc, put_stream(&["", "platanos"], "platanos v=1 1"))
+            .await
+            .expect_err("invalid namespace should be rejected");
+        assert_matches!(err, FlightWriteError::InvalidNamespace(_));
+
+        // A record batch without the schema message.
+        let mut messages = put_stream(&["bananas_test", "platanos"], "platanos v=1 1");
+        let descriptor = messages.remove(0).flight_descriptor;
+        messages[0].flight_descriptor = descriptor;
+        let err = write(&svc, messages)
+            .await
+            .expect_err("missing schema should be rejected");
+        assert_matches!(err, FlightWriteError::NoSchema);
+
+        assert!(dml_handler.calls().is_empty());
+    }
+
+    #[test]
+    fn test_write_record_batch_no_time() {
+        let batch = RecordBatch::try_from_iter([(
+            "v",
+            Arc::new(Float64Array::from(vec![1.0, 2.0])) as ArrayRef,
+        )])
+        .unwrap();
+
+        let mut mutable_batch = MutableBatch::new();
+        let err = write_record_batch(&mut mutable_batch, &batch).expect_err("should fail");
+        assert_matches!(err, FlightWriteError::InvalidTime);
+        assert_eq!(mutable_batch.rows(), 0);
+    }
+}
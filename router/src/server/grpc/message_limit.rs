@@ -0,0 +1,236 @@
+//! Enforcement of a maximum gRPC request message size.
+
+use hyper::body::{Bytes, HttpBody};
+use std::{
+    error::Error,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use tonic::{
+    codegen::{http, Service},
+    transport::NamedService,
+    Status,
+};
+
+/// The length of the gRPC message frame header: a 1 byte compression flag
+/// followed by a 4 byte big-endian message length.
+const FRAME_HEADER_LEN: usize = 5;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+/// A decorator over a gRPC service `S` rejecting requests containing a message
+/// larger than the configured limit with `RESOURCE_EXHAUSTED`, if a limit is
+/// set.
+///
+/// The limit is enforced as the request body is read, using the length prefix
+/// of each message frame, and so applies to each message of a streaming
+/// request individually. The limit applies to the size of the message as
+/// sent, before decompression.
+#[derive(Debug, Clone)]
+pub struct MessageLimit<S> {
+    inner: S,
+    max_message_size: Option<usize>,
+}
+
+impl<S> MessageLimit<S> {
+    /// Wrap `inner`, rejecting request messages larger than
+    /// `max_message_size` bytes (or accepting messages of any size if
+    /// `None`).
+    pub fn new(inner: S, max_message_size: Option<usize>) -> Self {
+        Self {
+            inner,
+            max_message_size,
+        }
+    }
+}
+
+impl<S, B> Service<http::Request<B>> for MessageLimit<S>
+where
+    S: Service<http::Request<LimitedBody<B>>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let max = self.max_message_size.unwrap_or(usize::MAX);
+        self.inner.call(req.map(|body| LimitedBody::new(body, max)))
+    }
+}
+
+impl<S> NamedService for MessageLimit<S>
+where
+    S: NamedService,
+{
+    const NAME: &'static str = S::NAME;
+}
+
+/// A request body returning an error if a gRPC message frame declares a
+/// length greater than `max` bytes.
+#[derive(Debug)]
+pub struct LimitedBody<B> {
+    inner: B,
+    max: usize,
+
+    /// The partially read header of the next frame.
+    header: [u8; FRAME_HEADER_LEN],
+    header_len: usize,
+    /// The number of message bytes of the current frame yet to be read.
+    remaining: usize,
+    /// Set once the limit has been exceeded, terminating the body.
+    exceeded: bool,
+}
+
+impl<B> LimitedBody<B> {
+    fn new(inner: B, max: usize) -> Self {
+        Self {
+            inner,
+            max,
+            header: [0; FRAME_HEADER_LEN],
+            header_len: 0,
+            remaining: 0,
+            exceeded: false,
+        }
+    }
+
+    /// Advance the frame parser over `buf`, returning an error if a frame
+    /// exceeding the limit is observed.
+    fn observe(&mut self, mut buf: &[u8]) -> Result<(), Status> {
+        while !buf.is_empty() {
+            if self.remaining > 0 {
+                let n = self.remaining.min(buf.len());
+                self.remaining -= n;
+                buf = &buf[n..];
+                continue;
+            }
+
+            let n = (FRAME_HEADER_LEN - self.header_len).min(buf.len());
+            self.header[self.header_len..self.header_len + n].copy_from_slice(&buf[..n]);
+            self.header_len += n;
+            buf = &buf[n..];
+
+            if self.header_len == FRAME_HEADER_LEN {
+                let len = u32::from_be_bytes(self.header[1..].try_into().unwrap()) as usize;
+                if len > self.max {
+                    return Err(Status::resource_exhausted(format!(
+                        "message of {len} bytes exceeds the maximum message size of {} bytes",
+                        self.max
+                    )));
+                }
+                self.remaining = len;
+                self.header_len = 0;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<B> HttpBody for LimitedBody<B>
+where
+    B: HttpBody<Data = Bytes> + Unpin,
+    B::Error: Into<BoxError>,
+{
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_data(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let this = self.get_mut();
+        if this.exceeded {
+            return Poll::Ready(None);
+        }
+
+        match Pin::new(&mut this.inner).poll_data(cx) {
+            Poll::Ready(Some(Ok(data))) => match this.observe(&data) {
+                Ok(()) => Poll::Ready(Some(Ok(data))),
+                Err(status) => {
+                    this.exceeded = true;
+                    Poll::Ready(Some(Err(Box::new(status))))
+                }
+            },
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e.into()))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_trailers(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<http::HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.get_mut().inner)
+            .poll_trailers(cx)
+            .map_err(Into::into)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.exceeded || self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> hyper::body::SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tonic::Code;
+
+    /// Encode `len` bytes of message data as a gRPC frame.
+    fn frame(len: usize) -> Vec<u8> {
+        let mut buf = vec![0];
+        buf.extend_from_slice(&(len as u32).to_be_bytes());
+        buf.extend(std::iter::repeat(42).take(len));
+        buf
+    }
+
+    #[test]
+    fn test_frames_within_limit() {
+        let mut body = LimitedBody::new(hyper::Body::empty(), 10);
+
+        // Frames may be split across chunks at any point, including within
+        // the frame header.
+        let data = [frame(10), frame(0), frame(3)].concat();
+        for chunk in data.chunks(3) {
+            body.observe(chunk).expect("frames within limit");
+        }
+        assert_eq!(body.remaining, 0);
+        assert_eq!(body.header_len, 0);
+    }
+
+    #[test]
+    fn test_frame_exceeds_limit() {
+        let mut body = LimitedBody::new(hyper::Body::empty(), 10);
+
+        let data = [frame(10), frame(11)].concat();
+        let (first, second) = data.split_at(17);
+        body.observe(first).expect("first frame within limit");
+        let err = body.observe(second).expect_err("second frame too large");
+        assert_eq!(err.code(), Code::ResourceExhausted);
+    }
+
+    #[tokio::test]
+    async fn test_body_terminated() {
+        let body = hyper::Body::from([frame(2), frame(11)].concat());
+        let mut body = LimitedBody::new(body, 10);
+
+        let err = body
+            .data()
+            .await
+            .expect("body should yield a result")
+            .expect_err("frame too large");
+        let status = err.downcast::<Status>().expect("error should be a status");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        assert!(body.is_end_stream());
+        assert!(body.data().await.is_none());
+    }
+}
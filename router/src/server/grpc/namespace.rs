@@ -0,0 +1,964 @@
+//! A gRPC service to create, update and delete namespaces in the catalog.
+
+use crate::namespace_cache::{refresh_namespace, NamespaceCache};
+use data_types::{DatabaseName, Namespace, PartitionTemplate, QueryPoolId, TemplatePart, TopicId};
+use generated_types::influxdata::iox::namespace::v1::{
+    namespace_service_server, update_namespace_service_protection_limit_request::LimitUpdate,
+    CreateNamespaceRequest, CreateNamespaceResponse, DeleteNamespaceRequest,
+    DeleteNamespaceResponse, GetNamespaceRequest, GetNamespaceResponse, GetNamespacesRequest,
+    GetNamespacesResponse, InvalidateNamespaceCacheRequest, InvalidateNamespaceCacheResponse,
+    Namespace as ProtoNamespace, PartitionTemplate as ProtoPartitionTemplate,
+    RefreshNamespaceCacheRequest, RefreshNamespaceCacheResponse,
+    UpdateNamespacePartitionTemplateRequest, UpdateNamespacePartitionTemplateResponse,
+    UpdateNamespaceRetentionRequest, UpdateNamespaceRetentionResponse,
+    UpdateNamespaceServiceProtectionLimitRequest, UpdateNamespaceServiceProtectionLimitResponse,
+    ValidatePartitionTemplateRequest, ValidatePartitionTemplateResponse,
+};
+use iox_catalog::interface::{Catalog, Error as CatalogError};
+use mutable_batch::{validate_partition_template, PartitionWrite};
+use mutable_batch_lp::lines_to_batches;
+use observability_deps::tracing::*;
+use std::{collections::BTreeSet, sync::Arc};
+use tonic::{Request, Response, Status};
+
+/// A [`NamespaceService`] manages namespaces in the [`Catalog`], allowing them
+/// to be created with an explicit retention period rather than implicitly by
+/// the first write, and their service protection limits and partition template
+/// to be adjusted.
+///
+/// Updates to a namespace are also applied to its schema in the
+/// [`NamespaceCache`] (if cached), so that the DML handlers of this router
+/// enforce the new retention period, column limit and partition template
+/// without a restart. Other router instances observe the change only once they
+/// recache the namespace - the cache of a router can be explicitly invalidated
+/// or refreshed to apply out-of-band catalog changes immediately.
+///
+/// [`NamespaceService`]: generated_types::influxdata::iox::namespace::v1::namespace_service_server::NamespaceService
+#[derive(Debug)]
+pub struct NamespaceService<C> {
+    catalog: Arc<dyn Catalog>,
+    cache: C,
+    topic_id: TopicId,
+    query_id: QueryPoolId,
+}
+
+impl<C> NamespaceService<C> {
+    /// Initialise a new [`NamespaceService`], creating namespaces in `catalog`
+    /// assigned to `topic_id` and `query_id`, and applying updates to the
+    /// schemas cached in `cache`.
+    pub fn new(
+        catalog: Arc<dyn Catalog>,
+        cache: C,
+        topic_id: TopicId,
+        query_id: QueryPoolId,
+    ) -> Self {
+        Self {
+            catalog,
+            cache,
+            topic_id,
+            query_id,
+        }
+    }
+}
+
+impl<C> NamespaceService<C>
+where
+    C: NamespaceCache,
+{
+    /// Apply the retention period, column limit and partition template of
+    /// `namespace` to its cached schema, if any.
+    ///
+    /// This MAY race with a DML handler overwriting the cached schema with a
+    /// copy read before the update, in which case the update is not observed
+    /// until the namespace is next recached.
+    fn refresh_cache(&self, namespace: &Namespace) {
+        let name = match DatabaseName::try_from(namespace.name.clone()) {
+            Ok(v) => v,
+            Err(_) => return,
+        };
+
+        if let Some(schema) = self.cache.get_schema(&name) {
+            let mut schema = (*schema).clone();
+            schema.retention_period_ns = namespace.retention_period_ns;
+            schema.max_columns_per_table = namespace.max_columns_per_table as usize;
+            schema.partition_template = namespace.partition_template.clone();
+            self.cache.put_schema(name, schema);
+            trace!(namespace=%namespace.name, "schema cache updated");
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<C> namespace_service_server::NamespaceService for NamespaceService<C>
+where
+    C: NamespaceCache + 'static,
+{
+    async fn get_namespaces(
+        &self,
+        _request: Request<GetNamespacesRequest>,
+    ) -> Result<Response<GetNamespacesResponse>, Status> {
+        let namespaces = self
+            .catalog
+            .repositories()
+            .await
+            .namespaces()
+            .list()
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        Ok(Response::new(GetNamespacesResponse {
+            namespaces: namespaces.into_iter().map(namespace_to_proto).collect(),
+        }))
+    }
+
+    async fn create_namespace(
+        &self,
+        request: Request<CreateNamespaceRequest>,
+    ) -> Result<Response<CreateNamespaceResponse>, Status> {
+        let CreateNamespaceRequest {
+            name,
+            retention_period_ns,
+        } = request.into_inner();
+
+        let name =
+            DatabaseName::try_from(name).map_err(|e| Status::invalid_argument(e.to_string()))?;
+        validate_retention_period(retention_period_ns)?;
+
+        let mut txn = self
+            .catalog
+            .start_transaction()
+            .await
+            .map_err(catalog_error_to_status)?;
+        let mut namespace = txn
+            .namespaces()
+            .create(
+                &name,
+                iox_catalog::INFINITE_RETENTION_POLICY,
+                self.topic_id,
+                self.query_id,
+            )
+            .await
+            .map_err(catalog_error_to_status)?;
+        if retention_period_ns.is_some() {
+            namespace = txn
+                .namespaces()
+                .update_retention_period(&name, retention_period_ns)
+                .await
+                .map_err(catalog_error_to_status)?;
+        }
+        txn.commit().await.map_err(catalog_error_to_status)?;
+
+        info!(%name, ?retention_period_ns, "created namespace");
+
+        Ok(Response::new(CreateNamespaceResponse {
+            namespace: Some(namespace_to_proto(namespace)),
+        }))
+    }
+
+    async fn update_namespace_retention(
+        &self,
+        request: Request<UpdateNamespaceRetentionRequest>,
+    ) -> Result<Response<UpdateNamespaceRetentionResponse>, Status> {
+        let UpdateNamespaceRetentionRequest {
+            name,
+            retention_period_ns,
+        } = request.into_inner();
+
+        validate_retention_period(retention_period_ns)?;
+
+        let namespace = self
+            .catalog
+            .repositories()
+            .await
+            .namespaces()
+            .update_retention_period(&name, retention_period_ns)
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        self.refresh_cache(&namespace);
+
+        info!(%name, ?retention_period_ns, "updated namespace retention period");
+
+        Ok(Response::new(UpdateNamespaceRetentionResponse {
+            namespace: Some(namespace_to_proto(namespace)),
+        }))
+    }
+
+    async fn delete_namespace(
+        &self,
+        request: Request<DeleteNamespaceRequest>,
+    ) -> Result<Response<DeleteNamespaceResponse>, Status> {
+        let name = request.into_inner().name;
+
+        self.catalog
+            .repositories()
+            .await
+            .namespaces()
+            .soft_delete(&name)
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        // Stop accepting writes for the namespace on this router.
+        if let Ok(name) = DatabaseName::try_from(name.clone()) {
+            self.cache.remove_schema(&name);
+        }
+
+        info!(%name, "soft-deleted namespace");
+
+        Ok(Response::new(DeleteNamespaceResponse {}))
+    }
+
+    async fn get_namespace(
+        &self,
+        request: Request<GetNamespaceRequest>,
+    ) -> Result<Response<GetNamespaceResponse>, Status> {
+        let name = request.into_inner().name;
+
+        let namespace = self
+            .catalog
+            .repositories()
+            .await
+            .namespaces()
+            .get_by_name(&name)
+            .await
+            .map_err(catalog_error_to_status)?
+            .filter(|ns| ns.deleted_at.is_none())
+            .ok_or_else(|| Status::not_found(format!("namespace {name} not found")))?;
+
+        Ok(Response::new(GetNamespaceResponse {
+            namespace: Some(namespace_to_proto(namespace)),
+        }))
+    }
+
+    async fn update_namespace_service_protection_limit(
+        &self,
+        request: Request<UpdateNamespaceServiceProtectionLimitRequest>,
+    ) -> Result<Response<UpdateNamespaceServiceProtectionLimitResponse>, Status> {
+        let UpdateNamespaceServiceProtectionLimitRequest { name, limit_update } =
+            request.into_inner();
+
+        let mut repos = self.catalog.repositories().await;
+        let namespace = match limit_update {
+            Some(LimitUpdate::MaxTables(n)) => {
+                validate_limit("max_tables", n)?;
+                repos.namespaces().update_table_limit(&name, n).await
+            }
+            Some(LimitUpdate::MaxColumnsPerTable(n)) => {
+                validate_limit("max_columns_per_table", n)?;
+                repos.namespaces().update_column_limit(&name, n).await
+            }
+            None => return Err(Status::invalid_argument("no limit update provided")),
+        }
+        .map_err(catalog_error_to_status)?;
+
+        self.refresh_cache(&namespace);
+
+        info!(
+            %name,
+            max_tables = namespace.max_tables,
+            max_columns_per_table = namespace.max_columns_per_table,
+            "updated namespace service protection limits"
+        );
+
+        Ok(Response::new(
+            UpdateNamespaceServiceProtectionLimitResponse {
+                namespace: Some(namespace_to_proto(namespace)),
+            },
+        ))
+    }
+
+    async fn update_namespace_partition_template(
+        &self,
+        request: Request<UpdateNamespacePartitionTemplateRequest>,
+    ) -> Result<Response<UpdateNamespacePartitionTemplateResponse>, Status> {
+        let UpdateNamespacePartitionTemplateRequest {
+            name,
+            partition_template,
+        } = request.into_inner();
+
+        let partition_template = partition_template
+            .map(partition_template_from_proto)
+            .transpose()?;
+
+        let namespace = self
+            .catalog
+            .repositories()
+            .await
+            .namespaces()
+            .update_partition_template(&name, partition_template)
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        self.refresh_cache(&namespace);
+
+        info!(
+            %name,
+            partition_template=?namespace.partition_template,
+            "updated namespace partition template"
+        );
+
+        Ok(Response::new(UpdateNamespacePartitionTemplateResponse {
+            namespace: Some(namespace_to_proto(namespace)),
+        }))
+    }
+
+    async fn validate_partition_template(
+        &self,
+        request: Request<ValidatePartitionTemplateRequest>,
+    ) -> Result<Response<ValidatePartitionTemplateResponse>, Status> {
+        let ValidatePartitionTemplateRequest {
+            partition_template,
+            line_protocol,
+        } = request.into_inner();
+
+        let partition_template = partition_template
+            .map(partition_template_from_proto)
+            .transpose()?
+            .ok_or_else(|| Status::invalid_argument("no partition template provided"))?;
+
+        // The sample data is optional - an empty payload derives no keys.
+        let batches = match line_protocol.trim().is_empty() {
+            true => Default::default(),
+            false => lines_to_batches(&line_protocol, 0)
+                .map_err(|e| Status::invalid_argument(format!("invalid line protocol: {e}")))?,
+        };
+
+        let partition_keys = batches
+            .iter()
+            .flat_map(|(table, batch)| {
+                PartitionWrite::partition(table, batch, &partition_template)
+                    .into_iter()
+                    .map(|(key, _)| key)
+            })
+            .map(|key| key.to_string())
+            .collect::<BTreeSet<_>>();
+
+        Ok(Response::new(ValidatePartitionTemplateResponse {
+            partition_keys: partition_keys.into_iter().collect(),
+        }))
+    }
+
+    async fn invalidate_namespace_cache(
+        &self,
+        request: Request<InvalidateNamespaceCacheRequest>,
+    ) -> Result<Response<InvalidateNamespaceCacheResponse>, Status> {
+        let name = request.into_inner().name;
+
+        let namespaces = match name.is_empty() {
+            true => self.cache.namespaces(),
+            false => vec![DatabaseName::try_from(name)
+                .map_err(|e| Status::invalid_argument(e.to_string()))?],
+        };
+
+        let evicted = namespaces
+            .iter()
+            .filter(|ns| self.cache.remove_schema(ns).is_some())
+            .count();
+
+        info!(evicted, "invalidated namespace schema cache");
+
+        Ok(Response::new(InvalidateNamespaceCacheResponse {
+            evicted: evicted as u64,
+        }))
+    }
+
+    async fn refresh_namespace_cache(
+        &self,
+        request: Request<RefreshNamespaceCacheRequest>,
+    ) -> Result<Response<RefreshNamespaceCacheResponse>, Status> {
+        let name = DatabaseName::try_from(request.into_inner().name)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        refresh_namespace(&*self.catalog, &self.cache, &name)
+            .await
+            .map_err(catalog_error_to_status)?
+            .ok_or_else(|| Status::not_found(format!("namespace {name} not found")))?;
+
+        info!(%name, "refreshed namespace schema cache");
+
+        Ok(Response::new(RefreshNamespaceCacheResponse {}))
+    }
+}
+
+/// Parse and validate the parts of a [`ProtoPartitionTemplate`], rejecting
+/// templates the partitioner cannot apply.
+fn partition_template_from_proto(
+    template: ProtoPartitionTemplate,
+) -> Result<PartitionTemplate, Status> {
+    let template = PartitionTemplate {
+        parts: template
+            .parts
+            .iter()
+            .map(|part| {
+                part.parse::<TemplatePart>()
+                    .map_err(|e| Status::invalid_argument(e.to_string()))
+            })
+            .collect::<Result<_, _>>()?,
+    };
+
+    validate_partition_template(&template).map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+    Ok(template)
+}
+
+/// Reject retention periods that are not a positive number of nanoseconds.
+fn validate_retention_period(retention_period_ns: Option<i64>) -> Result<(), Status> {
+    match retention_period_ns {
+        Some(v) if v <= 0 => Err(Status::invalid_argument(format!(
+            "retention period must be positive, got {v}ns"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Reject service protection limits that are not positive.
+fn validate_limit(limit: &str, value: i32) -> Result<(), Status> {
+    if value <= 0 {
+        return Err(Status::invalid_argument(format!(
+            "{limit} must be positive, got {value}"
+        )));
+    }
+    Ok(())
+}
+
+/// Translate a catalog [`Namespace`] to its protobuf form.
+fn namespace_to_proto(namespace: Namespace) -> ProtoNamespace {
+    ProtoNamespace {
+        id: namespace.id.get(),
+        name: namespace.name,
+        retention_period_ns: namespace.retention_period_ns,
+        max_tables: namespace.max_tables,
+        max_columns_per_table: namespace.max_columns_per_table,
+        partition_template: namespace
+            .partition_template
+            .map(|template| ProtoPartitionTemplate {
+                parts: template.parts.iter().map(ToString::to_string).collect(),
+            }),
+    }
+}
+
+/// Map a catalog [`CatalogError`] to the appropriate gRPC [`Status`].
+fn catalog_error_to_status(e: CatalogError) -> Status {
+    match e {
+        CatalogError::NameExists { .. } => Status::already_exists(e.to_string()),
+        CatalogError::NamespaceNotFoundByName { .. } => Status::not_found(e.to_string()),
+        _ => {
+            error!(error=%e, "failed to update namespace in catalog");
+            Status::internal(e.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::namespace_cache::MemoryNamespaceCache;
+    use data_types::{NamespaceId, NamespaceSchema};
+    use generated_types::influxdata::iox::namespace::v1::namespace_service_server::NamespaceService as _;
+    use iox_catalog::mem::MemCatalog;
+    use tonic::Code;
+
+    const RETENTION: i64 = 3_600_000_000_000;
+
+    type TestService = NamespaceService<Arc<MemoryNamespaceCache>>;
+
+    async fn new_service() -> (Arc<dyn Catalog>, Arc<MemoryNamespaceCache>, TestService) {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(metrics));
+
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("bananas").await.unwrap();
+        let pool = repos.query_pools().create_or_get("platanos").await.unwrap();
+        drop(repos);
+
+        let cache = Arc::new(MemoryNamespaceCache::default());
+        let svc =
+            NamespaceService::new(Arc::clone(&catalog), Arc::clone(&cache), topic.id, pool.id);
+        (catalog, cache, svc)
+    }
+
+    async fn create(
+        svc: &TestService,
+        name: &str,
+        retention_period_ns: Option<i64>,
+    ) -> Result<Response<CreateNamespaceResponse>, Status> {
+        svc.create_namespace(Request::new(CreateNamespaceRequest {
+            name: name.to_string(),
+            retention_period_ns,
+        }))
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_create_namespace() {
+        let (catalog, _cache, svc) = new_service().await;
+
+        let got = create(&svc, "bananas_test", Some(RETENTION))
+            .await
+            .expect("create should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(got.name, "bananas_test");
+        assert_eq!(got.retention_period_ns, Some(RETENTION));
+
+        let ns = catalog
+            .repositories()
+            .await
+            .namespaces()
+            .get_by_name("bananas_test")
+            .await
+            .unwrap()
+            .expect("namespace should be in the catalog");
+        assert_eq!(ns.id.get(), got.id);
+        assert_eq!(ns.retention_period_ns, Some(RETENTION));
+
+        let err = create(&svc, "bananas_test", None)
+            .await
+            .expect_err("duplicate namespace should be rejected");
+        assert_eq!(err.code(), Code::AlreadyExists);
+    }
+
+    #[tokio::test]
+    async fn test_create_namespace_invalid() {
+        let (catalog, _cache, svc) = new_service().await;
+
+        let err = create(&svc, "", None)
+            .await
+            .expect_err("invalid name should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = create(&svc, "bananas_test", Some(0))
+            .await
+            .expect_err("zero retention period should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = create(&svc, "bananas_test", Some(-1))
+            .await
+            .expect_err("negative retention period should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        assert!(catalog
+            .repositories()
+            .await
+            .namespaces()
+            .list()
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_update_namespace_retention() {
+        let (_catalog, _cache, svc) = new_service().await;
+        create(&svc, "bananas_test", None)
+            .await
+            .expect("create should succeed");
+
+        let got = svc
+            .update_namespace_retention(Request::new(UpdateNamespaceRetentionRequest {
+                name: "bananas_test".to_string(),
+                retention_period_ns: Some(RETENTION),
+            }))
+            .await
+            .expect("update should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(got.retention_period_ns, Some(RETENTION));
+
+        let err = svc
+            .update_namespace_retention(Request::new(UpdateNamespaceRetentionRequest {
+                name: "platanos".to_string(),
+                retention_period_ns: None,
+            }))
+            .await
+            .expect_err("unknown namespace should not be updated");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_delete_namespace() {
+        let (_catalog, cache, svc) = new_service().await;
+        create(&svc, "bananas_test", None)
+            .await
+            .expect("create should succeed");
+        create(&svc, "platanos_test", None)
+            .await
+            .expect("create should succeed");
+        let ns = cache_schema(&cache, "bananas_test");
+
+        svc.delete_namespace(Request::new(DeleteNamespaceRequest {
+            name: "bananas_test".to_string(),
+        }))
+        .await
+        .expect("delete should succeed");
+
+        let namespaces = svc
+            .get_namespaces(Request::new(GetNamespacesRequest {}))
+            .await
+            .expect("list should succeed")
+            .into_inner()
+            .namespaces;
+        assert_eq!(namespaces.len(), 1);
+        assert_eq!(namespaces[0].name, "platanos_test");
+
+        // The deleted namespace is evicted from the cache.
+        assert!(cache.get_schema(&ns).is_none());
+
+        let err = svc
+            .delete_namespace(Request::new(DeleteNamespaceRequest {
+                name: "mangos".to_string(),
+            }))
+            .await
+            .expect_err("unknown namespace should not be deleted");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    /// Place a schema for `name` in `cache`, returning the cache key.
+    fn cache_schema(cache: &Arc<MemoryNamespaceCache>, name: &str) -> DatabaseName<'static> {
+        let name = DatabaseName::try_from(name.to_string()).unwrap();
+        cache.put_schema(
+            name.clone(),
+            NamespaceSchema::new(
+                NamespaceId::new(1),
+                TopicId::new(1),
+                QueryPoolId::new(1),
+                iox_catalog::DEFAULT_MAX_COLUMNS_PER_TABLE,
+            ),
+        );
+        name
+    }
+
+    #[tokio::test]
+    async fn test_get_namespace() {
+        let (_catalog, _cache, svc) = new_service().await;
+        create(&svc, "bananas_test", Some(RETENTION))
+            .await
+            .expect("create should succeed");
+
+        let got = svc
+            .get_namespace(Request::new(GetNamespaceRequest {
+                name: "bananas_test".to_string(),
+            }))
+            .await
+            .expect("get should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(got.retention_period_ns, Some(RETENTION));
+        assert_eq!(got.max_tables, iox_catalog::DEFAULT_MAX_TABLES);
+        assert_eq!(
+            got.max_columns_per_table,
+            iox_catalog::DEFAULT_MAX_COLUMNS_PER_TABLE
+        );
+
+        svc.delete_namespace(Request::new(DeleteNamespaceRequest {
+            name: "bananas_test".to_string(),
+        }))
+        .await
+        .expect("delete should succeed");
+
+        let err = svc
+            .get_namespace(Request::new(GetNamespaceRequest {
+                name: "bananas_test".to_string(),
+            }))
+            .await
+            .expect_err("deleted namespace should not be found");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_update_service_protection_limit() {
+        let (_catalog, cache, svc) = new_service().await;
+        create(&svc, "bananas_test", None)
+            .await
+            .expect("create should succeed");
+        let ns = cache_schema(&cache, "bananas_test");
+
+        let update = |limit_update| {
+            svc.update_namespace_service_protection_limit(Request::new(
+                UpdateNamespaceServiceProtectionLimitRequest {
+                    name: "bananas_test".to_string(),
+                    limit_update,
+                },
+            ))
+        };
+
+        let got = update(Some(LimitUpdate::MaxTables(42)))
+            .await
+            .expect("update should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(got.max_tables, 42);
+
+        let got = update(Some(LimitUpdate::MaxColumnsPerTable(24)))
+            .await
+            .expect("update should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(got.max_tables, 42);
+        assert_eq!(got.max_columns_per_table, 24);
+
+        // The cached schema observes the new column limit.
+        let schema = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(schema.max_columns_per_table, 24);
+
+        for limit_update in [
+            None,
+            Some(LimitUpdate::MaxTables(0)),
+            Some(LimitUpdate::MaxColumnsPerTable(-1)),
+        ] {
+            let err = update(limit_update)
+                .await
+                .expect_err("invalid update should be rejected");
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+
+        let err = svc
+            .update_namespace_service_protection_limit(Request::new(
+                UpdateNamespaceServiceProtectionLimitRequest {
+                    name: "platanos".to_string(),
+                    limit_update: Some(LimitUpdate::MaxTables(1)),
+                },
+            ))
+            .await
+            .expect_err("unknown namespace should not be updated");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_update_retention_refreshes_cache() {
+        let (_catalog, cache, svc) = new_service().await;
+        create(&svc, "bananas_test", None)
+            .await
+            .expect("create should succeed");
+        let ns = cache_schema(&cache, "bananas_test");
+
+        svc.update_namespace_retention(Request::new(UpdateNamespaceRetentionRequest {
+            name: "bananas_test".to_string(),
+            retention_period_ns: Some(RETENTION),
+        }))
+        .await
+        .expect("update should succeed");
+
+        let schema = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(schema.retention_period_ns, Some(RETENTION));
+    }
+
+    #[tokio::test]
+    async fn test_invalidate_namespace_cache() {
+        let (_catalog, cache, svc) = new_service().await;
+        let bananas = cache_schema(&cache, "bananas_test");
+        let platanos = cache_schema(&cache, "platanos_test");
+
+        let invalidate = |name: &str| {
+            svc.invalidate_namespace_cache(Request::new(InvalidateNamespaceCacheRequest {
+                name: name.to_string(),
+            }))
+        };
+
+        let evicted = invalidate("bananas_test")
+            .await
+            .expect("invalidate should succeed")
+            .into_inner()
+            .evicted;
+        assert_eq!(evicted, 1);
+        assert!(cache.get_schema(&bananas).is_none());
+        assert!(cache.get_schema(&platanos).is_some());
+
+        // An empty name evicts all the cached namespaces.
+        cache_schema(&cache, "bananas_test");
+        let evicted = invalidate("")
+            .await
+            .expect("invalidate should succeed")
+            .into_inner()
+            .evicted;
+        assert_eq!(evicted, 2);
+        assert!(cache.namespaces().is_empty());
+
+        let err = invalidate("bananas!")
+            .await
+            .expect_err("invalid namespace name should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_namespace_cache() {
+        let (catalog, cache, svc) = new_service().await;
+        create(&svc, "bananas_test", None)
+            .await
+            .expect("create should succeed");
+
+        // Out-of-band changes to the catalog are applied on refresh.
+        let ns = cache_schema(&cache, "bananas_test");
+        catalog
+            .repositories()
+            .await
+            .namespaces()
+            .update_column_limit("bananas_test", 7)
+            .await
+            .unwrap();
+
+        let refresh = |name: &str| {
+            svc.refresh_namespace_cache(Request::new(RefreshNamespaceCacheRequest {
+                name: name.to_string(),
+            }))
+        };
+
+        refresh("bananas_test")
+            .await
+            .expect("refresh should succeed");
+        let schema = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(schema.max_columns_per_table, 7);
+
+        // A namespace that does not exist in the catalog is evicted.
+        let ns = cache_schema(&cache, "platanos_test");
+        let err = refresh("platanos_test")
+            .await
+            .expect_err("unknown namespace should not be refreshed");
+        assert_eq!(err.code(), Code::NotFound);
+        assert!(cache.get_schema(&ns).is_none());
+    }
+
+    fn proto_template(parts: &[&str]) -> ProtoPartitionTemplate {
+        ProtoPartitionTemplate {
+            parts: parts.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_update_partition_template() {
+        let (catalog, cache, svc) = new_service().await;
+        create(&svc, "bananas_test", None)
+            .await
+            .expect("create should succeed");
+        let ns = cache_schema(&cache, "bananas_test");
+
+        let update = |partition_template| {
+            svc.update_namespace_partition_template(Request::new(
+                UpdateNamespacePartitionTemplateRequest {
+                    name: "bananas_test".to_string(),
+                    partition_template,
+                },
+            ))
+        };
+
+        let got = update(Some(proto_template(&["tag:region", "time:%Y-%m-%d"])))
+            .await
+            .expect("update should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(
+            got.partition_template,
+            Some(proto_template(&["tag:region", "time:%Y-%m-%d"]))
+        );
+
+        let want = PartitionTemplate {
+            parts: vec![
+                TemplatePart::Column("region".to_string()),
+                TemplatePart::TimeFormat("%Y-%m-%d".to_string()),
+            ],
+        };
+        let stored = catalog
+            .repositories()
+            .await
+            .namespaces()
+            .get_by_name("bananas_test")
+            .await
+            .unwrap()
+            .expect("namespace should be in the catalog");
+        assert_eq!(stored.partition_template, Some(want.clone()));
+
+        // The cached schema observes the new template.
+        let schema = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(schema.partition_template, Some(want));
+
+        for partition_template in [
+            Some(proto_template(&[])),
+            Some(proto_template(&["bananas"])),
+            Some(proto_template(&["tag:"])),
+            Some(proto_template(&["regex:region:.*"])),
+            Some(proto_template(&["time:%Q"])),
+        ] {
+            let err = update(partition_template)
+                .await
+                .expect_err("invalid template should be rejected");
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+
+        // Resetting the template restores the default.
+        let got = update(None)
+            .await
+            .expect("reset should succeed")
+            .into_inner()
+            .namespace
+            .expect("no namespace in response");
+        assert_eq!(got.partition_template, None);
+        let schema = cache.get_schema(&ns).expect("schema should be cached");
+        assert_eq!(schema.partition_template, None);
+
+        let err = svc
+            .update_namespace_partition_template(Request::new(
+                UpdateNamespacePartitionTemplateRequest {
+                    name: "platanos".to_string(),
+                    partition_template: None,
+                },
+            ))
+            .await
+            .expect_err("unknown namespace should not be updated");
+        assert_eq!(err.code(), Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_validate_partition_template() {
+        let (_catalog, _cache, svc) = new_service().await;
+
+        let validate = |partition_template, line_protocol: &str| {
+            svc.validate_partition_template(Request::new(ValidatePartitionTemplateRequest {
+                partition_template,
+                line_protocol: line_protocol.to_string(),
+            }))
+        };
+
+        let got = validate(
+            Some(proto_template(&["table", "tag:region", "time:%Y"])),
+            "cpu,region=east v=1 1465839830100400200\n\
+             cpu,region=west v=1 1465839830100400200\n\
+             cpu,region=east v=1 1465839830100400201\n\
+             mem v=1 0",
+        )
+        .await
+        .expect("valid template should be accepted")
+        .into_inner();
+        assert_eq!(
+            got.partition_keys,
+            [
+                "cpu-region_east-2016",
+                "cpu-region_west-2016",
+                "mem-region-1970"
+            ]
+        );
+
+        // A template may be validated without any sample data.
+        let got = validate(Some(proto_template(&["time:%Y-%m"])), "")
+            .await
+            .expect("valid template should be accepted")
+            .into_inner();
+        assert!(got.partition_keys.is_empty());
+
+        for (partition_template, line_protocol) in [
+            (None, ""),
+            (Some(proto_template(&["strftime:time:%Y"])), ""),
+            (Some(proto_template(&["time:%Y"])), "bananas"),
+        ] {
+            let err = validate(partition_template, line_protocol)
+                .await
+                .expect_err("invalid request should be rejected");
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+    }
+}
@@ -1,23 +1,46 @@
-//! A gRPC service to provide shard mappings to external clients.
+//! A gRPC service to provide shard mappings to external clients, and manage
+//! the table shard overrides applied by the router.
 
 use crate::shard::Shard;
-use data_types::{DatabaseName, ShardId, ShardIndex, TopicMetadata};
+use data_types::{DatabaseName, NamespaceId, ShardId, ShardIndex, TopicId, TopicMetadata};
 use generated_types::influxdata::iox::sharder::v1::{
-    shard_service_server, MapToShardRequest, MapToShardResponse,
+    shard_service_server, DeleteTableShardOverrideRequest, DeleteTableShardOverrideResponse,
+    GetShardRingRequest, GetShardRingResponse, ListTableShardOverridesRequest,
+    ListTableShardOverridesResponse, MapToShardRequest, MapToShardResponse, MapToShardsRequest,
+    MapToShardsResponse, ReshardRequest, ReshardResponse, SetTableShardOverrideRequest,
+    SetTableShardOverrideResponse, ShardMapping, ShardRingMember,
+    TableShardOverride as ProtoTableShardOverride,
 };
 use hashbrown::HashMap;
-use iox_catalog::interface::Catalog;
-use sharder::Sharder;
-use std::sync::Arc;
-use tonic::{Request, Response};
+use iox_catalog::interface::{Catalog, Error as CatalogError};
+use observability_deps::tracing::*;
+use sharder::{HashRing, Sharder, TableOverrides};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use tonic::{Request, Response, Status};
 
 /// A [`ShardService`] exposes a [gRPC endpoint] for external systems to discover the shard mapping
-/// for specific tables.
+/// for specific tables (individually, or in bulk), and to pin specific tables to a shard.
 ///
 /// The [`ShardService`] builds a cached mapping of Kafka partition index numbers ([`ShardIndex`])
 /// to [`Catalog`] row IDs ([`ShardId`]) in order to handle requests without generating Catalog
 /// queries. This mapping is expected to be unchanged over the lifetime of a router instance.
 ///
+/// Table shard overrides are persisted to the [`Catalog`], and applied to the [`TableOverrides`]
+/// of the router's sharder. The overrides in the catalog are loaded when the service is
+/// initialised - changes made through other router instances are not observed until restart.
+///
+/// If the router shards writes with a [`HashRing`], the set of shards in the ring can be changed
+/// at runtime. Ring membership is persisted to the [`Catalog`] for the topic, loaded when the ring
+/// is attached to the service, and periodically reloaded by the task started with
+/// [`ShardService::spawn_hash_ring_refresh`], so a reshard through one router is applied by every
+/// router writing to the topic. Until a router reloads the membership, it continues to route
+/// writes using the previous ring.
+///
 /// This service MUST be initialised with the same sharder instance as the
 /// [`ShardedWriteBuffer`] for the outputs to be correct.
 ///
@@ -26,6 +49,18 @@ use tonic::{Request, Response};
 #[derive(Debug, Clone)]
 pub struct ShardService<S> {
     sharder: S,
+    catalog: Arc<dyn Catalog>,
+
+    // The write buffer topic the shards belong to.
+    topic_id: TopicId,
+    topic_name: String,
+
+    // The overrides applied by `sharder`, and the shards they may refer to.
+    overrides: Arc<TableOverrides<Arc<Shard>>>,
+    shards: HashMap<ShardIndex, Arc<Shard>>,
+
+    // The consistent hash ring used by `sharder`, if any.
+    ring: Option<Arc<HashRing<ShardIndex, Arc<Shard>>>>,
 
     // A pre-loaded mapping of all Kafka partition (shard) indexes for the in-use Kafka
     // topic, to their respective catalog row shard ID.
@@ -37,18 +72,29 @@ where
     S: Send + Sync,
 {
     /// Initialise a gRPC [`ShardService`] handler, building a cached mapping
-    /// from the catalog.
+    /// from the catalog and loading the persisted table shard overrides for
+    /// `topic` into `overrides`.
+    ///
+    /// `overrides` must be the set of overrides applied by `sharder`, and
+    /// `shards` the set of shards `sharder` maps to.
     ///
     /// [`ShardService`]: generated_types::influxdata::iox::sharder::v1::shard_service_server::ShardService
     pub async fn new(
         sharder: S,
+        overrides: Arc<TableOverrides<Arc<Shard>>>,
+        shards: impl IntoIterator<Item = Arc<Shard>> + Send,
         topic: TopicMetadata,
         catalog: Arc<dyn Catalog>,
     ) -> Result<Self, iox_catalog::interface::Error> {
+        let shards = shards
+            .into_iter()
+            .map(|s| (s.shard_index(), s))
+            .collect::<HashMap<_, _>>();
+
+        let mut repos = catalog.repositories().await;
+
         // Build the mapping of Kafka partition (shard) index -> Catalog shard ID
-        let mapping = catalog
-            .repositories()
-            .await
+        let mapping = repos
             .shards()
             .list_by_topic(&topic)
             .await?
@@ -56,27 +102,206 @@ where
             .map(|s| (s.shard_index, s.id))
             .collect();
 
-        Ok(Self { sharder, mapping })
+        // Load the overrides for the namespaces using this topic.
+        let namespaces = repos
+            .namespaces()
+            .list()
+            .await?
+            .into_iter()
+            .filter(|ns| ns.topic_id == topic.id)
+            .map(|ns| (ns.id, ns.name))
+            .collect::<HashMap<NamespaceId, String>>();
+
+        for o in repos.shards().list_table_overrides().await? {
+            let namespace = match namespaces.get(&o.namespace_id) {
+                Some(v) => v,
+                None => continue,
+            };
+            match shards.get(&o.shard_index) {
+                Some(shard) => {
+                    overrides.set(namespace.clone(), o.table_name, Arc::clone(shard));
+                }
+                None => warn!(
+                    %namespace,
+                    table_name=%o.table_name,
+                    shard_index=%o.shard_index,
+                    "ignoring table shard override for unknown shard index"
+                ),
+            }
+        }
+
+        Ok(Self {
+            sharder,
+            catalog,
+            topic_id: topic.id,
+            topic_name: topic.name,
+            overrides,
+            shards,
+            ring: None,
+            mapping,
+        })
+    }
+
+    /// Allow the shards in `ring`, the consistent hash ring used by the
+    /// sharder, to be changed through this service, applying the ring
+    /// membership persisted in the catalog (if any).
+    pub async fn with_hash_ring(
+        self,
+        ring: Arc<HashRing<ShardIndex, Arc<Shard>>>,
+    ) -> Result<Self, iox_catalog::interface::Error> {
+        refresh_ring(&*self.catalog, self.topic_id, &self.shards, &ring).await?;
+
+        Ok(Self {
+            ring: Some(ring),
+            ..self
+        })
+    }
+
+    /// Reload the hash ring membership from the catalog every `interval`,
+    /// applying reshards made through other router instances.
+    ///
+    /// Returns [`None`] if this service has no hash ring. The task stops
+    /// once the ring is dropped.
+    pub fn spawn_hash_ring_refresh(&self, interval: Duration) -> Option<JoinHandle<()>> {
+        let ring = Arc::downgrade(self.ring.as_ref()?);
+        let catalog = Arc::clone(&self.catalog);
+        let topic_id = self.topic_id;
+        let shards = self.shards.clone();
+
+        Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+            // The first tick completes immediately.
+            ticker.tick().await;
+
+            loop {
+                ticker.tick().await;
+
+                let ring = match Weak::upgrade(&ring) {
+                    Some(v) => v,
+                    None => return,
+                };
+
+                if let Err(e) = refresh_ring(&*catalog, topic_id, &shards, &ring).await {
+                    warn!(error=%e, "failed to refresh hash ring membership");
+                }
+            }
+        }))
     }
 }
 
-#[tonic::async_trait]
-impl<S> shard_service_server::ShardService for ShardService<S>
+/// Apply the ring membership persisted in `catalog` for `topic_id` to `ring`,
+/// if it has been set and differs from the current membership.
+async fn refresh_ring(
+    catalog: &dyn Catalog,
+    topic_id: TopicId,
+    shards: &HashMap<ShardIndex, Arc<Shard>>,
+    ring: &HashRing<ShardIndex, Arc<Shard>>,
+) -> Result<(), iox_catalog::interface::Error> {
+    let members = match catalog
+        .repositories()
+        .await
+        .shards()
+        .get_ring_members(topic_id)
+        .await?
+    {
+        Some(v) => v,
+        // The ring has never been resharded, and contains all shards.
+        None => return Ok(()),
+    };
+
+    let members = members
+        .into_iter()
+        .filter_map(|shard_index| match shards.get(&shard_index) {
+            Some(shard) => Some((shard_index, Arc::clone(shard))),
+            None => {
+                warn!(%shard_index, "ignoring hash ring member with unknown shard index");
+                None
+            }
+        })
+        .collect::<HashMap<_, _>>();
+    if members.is_empty() {
+        warn!("ignoring hash ring membership with no known shards");
+        return Ok(());
+    }
+
+    let current = ring
+        .shards()
+        .into_iter()
+        .map(|(shard_index, _, _)| shard_index)
+        .collect::<BTreeSet<_>>();
+    if members.keys().copied().collect::<BTreeSet<_>>() == current {
+        return Ok(());
+    }
+
+    let n_shards = members.len();
+    let moved_fraction = ring.set_shards(members);
+
+    info!(
+        n_shards,
+        moved_fraction, "applied hash ring membership from catalog"
+    );
+
+    Ok(())
+}
+
+impl<S> ShardService<S> {
+    /// Resolve the catalog ID of the namespace named `name`.
+    async fn namespace_id(&self, name: &str) -> Result<NamespaceId, Status> {
+        let name = DatabaseName::try_from(name.to_string())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        self.catalog
+            .repositories()
+            .await
+            .namespaces()
+            .get_by_name(&name)
+            .await
+            .map_err(catalog_error_to_status)?
+            .map(|ns| ns.id)
+            .ok_or_else(|| Status::not_found(format!("namespace {name} not found")))
+    }
+
+    fn ring(&self) -> Result<&HashRing<ShardIndex, Arc<Shard>>, Status> {
+        self.ring.as_deref().ok_or_else(|| {
+            Status::failed_precondition("router is not configured with a hash ring sharder")
+        })
+    }
+
+    /// Return the shards in `ring`, ordered by shard index.
+    fn ring_members(&self, ring: &HashRing<ShardIndex, Arc<Shard>>) -> Vec<ShardRingMember> {
+        let mut members = ring
+            .shards()
+            .into_iter()
+            .map(|(shard_index, _, ownership)| ShardRingMember {
+                shard_index: shard_index.get(),
+                shard_id: self
+                    .mapping
+                    .get(&shard_index)
+                    .expect("in-use shard maps to non-existant catalog entry")
+                    .get(),
+                ownership,
+            })
+            .collect::<Vec<_>>();
+        members.sort_unstable_by_key(|m| m.shard_index);
+        members
+    }
+}
+
+impl<S> ShardService<S>
 where
-    S: Sharder<(), Item = Arc<Shard>> + 'static,
+    S: Sharder<(), Item = Arc<Shard>>,
 {
-    async fn map_to_shard(
-        &self,
-        request: Request<MapToShardRequest>,
-    ) -> Result<Response<MapToShardResponse>, tonic::Status> {
-        let req = request.into_inner();
-
+    /// Map the `table_name` in `namespace_name` to the [`ShardIndex`] and
+    /// catalog [`ShardId`] of the shard its writes are routed to.
+    fn map(&self, namespace_name: &str, table_name: &str) -> Result<(ShardIndex, ShardId), Status> {
         // Validate the namespace.
-        let ns = DatabaseName::try_from(req.namespace_name)
+        let ns = DatabaseName::try_from(namespace_name)
             .map_err(|e| tonic::Status::invalid_argument(e.to_string()))?;
 
         // Map the (table, namespace) tuple to the Shard for it.
-        let shard = self.sharder.shard(&req.table_name, &ns, &());
+        let shard = self.sharder.shard(table_name, &ns, &());
 
         // Look up the shard index in the cached mapping, to extract the catalog ID associated with
         // the Shard.
@@ -85,21 +310,252 @@ where
             .get(&shard.shard_index())
             .expect("in-use shard maps to non-existant catalog entry");
 
+        Ok((shard.shard_index(), *shard_id))
+    }
+}
+
+#[tonic::async_trait]
+impl<S> shard_service_server::ShardService for ShardService<S>
+where
+    S: Sharder<(), Item = Arc<Shard>> + 'static,
+{
+    async fn map_to_shard(
+        &self,
+        request: Request<MapToShardRequest>,
+    ) -> Result<Response<MapToShardResponse>, tonic::Status> {
+        let req = request.into_inner();
+
+        let (shard_index, shard_id) = self.map(&req.namespace_name, &req.table_name)?;
+
         Ok(Response::new(MapToShardResponse {
             shard_id: shard_id.get(),
-            shard_index: shard.shard_index().get(),
+            shard_index: shard_index.get(),
+        }))
+    }
+
+    async fn map_to_shards(
+        &self,
+        request: Request<MapToShardsRequest>,
+    ) -> Result<Response<MapToShardsResponse>, tonic::Status> {
+        let mappings = request
+            .into_inner()
+            .tables
+            .into_iter()
+            .map(|req| {
+                let (shard_index, shard_id) = self.map(&req.namespace_name, &req.table_name)?;
+                Ok(ShardMapping {
+                    table_name: req.table_name,
+                    namespace_name: req.namespace_name,
+                    shard_id: shard_id.get(),
+                    shard_index: shard_index.get(),
+                    topic_name: self.topic_name.clone(),
+                })
+            })
+            .collect::<Result<_, Status>>()?;
+
+        Ok(Response::new(MapToShardsResponse { mappings }))
+    }
+
+    async fn set_table_shard_override(
+        &self,
+        request: Request<SetTableShardOverrideRequest>,
+    ) -> Result<Response<SetTableShardOverrideResponse>, Status> {
+        let ProtoTableShardOverride {
+            namespace_name,
+            table_name,
+            shard_index,
+        } = request
+            .into_inner()
+            .shard_override
+            .ok_or_else(|| Status::invalid_argument("missing shard_override"))?;
+
+        if table_name.is_empty() {
+            return Err(Status::invalid_argument("table name must not be empty"));
+        }
+        let shard_index = ShardIndex::new(shard_index);
+        let shard = self.shards.get(&shard_index).ok_or_else(|| {
+            Status::invalid_argument(format!("unknown shard index {shard_index}"))
+        })?;
+
+        let namespace_id = self.namespace_id(&namespace_name).await?;
+        let o = self
+            .catalog
+            .repositories()
+            .await
+            .shards()
+            .set_table_override(namespace_id, &table_name, shard_index)
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        self.overrides.set(
+            namespace_name.clone(),
+            table_name.clone(),
+            Arc::clone(shard),
+        );
+
+        info!(
+            namespace=%namespace_name,
+            %table_name,
+            %shard_index,
+            "set table shard override"
+        );
+
+        Ok(Response::new(SetTableShardOverrideResponse {
+            shard_override: Some(ProtoTableShardOverride {
+                namespace_name,
+                table_name: o.table_name,
+                shard_index: o.shard_index.get(),
+            }),
+        }))
+    }
+
+    async fn delete_table_shard_override(
+        &self,
+        request: Request<DeleteTableShardOverrideRequest>,
+    ) -> Result<Response<DeleteTableShardOverrideResponse>, Status> {
+        let DeleteTableShardOverrideRequest {
+            namespace_name,
+            table_name,
+        } = request.into_inner();
+
+        let namespace_id = self.namespace_id(&namespace_name).await?;
+        let deleted = self
+            .catalog
+            .repositories()
+            .await
+            .shards()
+            .delete_table_override(namespace_id, &table_name)
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        self.overrides.remove(&namespace_name, &table_name);
+
+        if deleted.is_none() {
+            return Err(Status::not_found(format!(
+                "no shard override for table {table_name} in namespace {namespace_name}"
+            )));
+        }
+
+        info!(
+            namespace=%namespace_name,
+            %table_name,
+            "deleted table shard override"
+        );
+
+        Ok(Response::new(DeleteTableShardOverrideResponse {}))
+    }
+
+    async fn list_table_shard_overrides(
+        &self,
+        _request: Request<ListTableShardOverridesRequest>,
+    ) -> Result<Response<ListTableShardOverridesResponse>, Status> {
+        let mut repos = self.catalog.repositories().await;
+
+        let namespaces = repos
+            .namespaces()
+            .list()
+            .await
+            .map_err(catalog_error_to_status)?
+            .into_iter()
+            .map(|ns| (ns.id, ns.name))
+            .collect::<HashMap<_, _>>();
+
+        let overrides = repos
+            .shards()
+            .list_table_overrides()
+            .await
+            .map_err(catalog_error_to_status)?
+            .into_iter()
+            .filter_map(|o| {
+                // Skip overrides for (soft) deleted namespaces.
+                let namespace_name = namespaces.get(&o.namespace_id)?.clone();
+                Some(ProtoTableShardOverride {
+                    namespace_name,
+                    table_name: o.table_name,
+                    shard_index: o.shard_index.get(),
+                })
+            })
+            .collect();
+
+        Ok(Response::new(ListTableShardOverridesResponse { overrides }))
+    }
+
+    async fn reshard(
+        &self,
+        request: Request<ReshardRequest>,
+    ) -> Result<Response<ReshardResponse>, Status> {
+        let ring = self.ring()?;
+
+        let shards = request
+            .into_inner()
+            .shard_indexes
+            .into_iter()
+            .map(|idx| {
+                let idx = ShardIndex::new(idx);
+                self.shards
+                    .get(&idx)
+                    .map(|shard| (idx, Arc::clone(shard)))
+                    .ok_or_else(|| Status::invalid_argument(format!("unknown shard index {idx}")))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        if shards.is_empty() {
+            return Err(Status::invalid_argument(
+                "the ring must contain at least one shard",
+            ));
+        }
+
+        // Persist the membership before applying it, so that it is applied
+        // by all routers (including this one, after a restart).
+        let mut shard_indexes = shards.keys().copied().collect::<Vec<_>>();
+        shard_indexes.sort_unstable();
+        self.catalog
+            .repositories()
+            .await
+            .shards()
+            .set_ring_members(self.topic_id, &shard_indexes)
+            .await
+            .map_err(catalog_error_to_status)?;
+
+        let n_shards = shards.len();
+        let moved_fraction = ring.set_shards(shards);
+
+        info!(n_shards, moved_fraction, "resharded hash ring");
+
+        Ok(Response::new(ReshardResponse {
+            moved_fraction,
+            members: self.ring_members(ring),
+        }))
+    }
+
+    async fn get_shard_ring(
+        &self,
+        _request: Request<GetShardRingRequest>,
+    ) -> Result<Response<GetShardRingResponse>, Status> {
+        let ring = self.ring()?;
+
+        Ok(Response::new(GetShardRingResponse {
+            members: self.ring_members(ring),
         }))
     }
 }
 
+fn catalog_error_to_status(e: CatalogError) -> Status {
+    error!(error=%e, "failed to access shard configuration in catalog");
+    Status::internal(e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{num::NonZeroU32, sync::Arc};
+    use std::{
+        num::{NonZeroU32, NonZeroUsize},
+        sync::Arc,
+    };
 
     use futures::stream::{FuturesUnordered, StreamExt};
     use generated_types::influxdata::iox::sharder::v1::shard_service_server::ShardService as _;
     use iox_catalog::mem::MemCatalog;
-    use sharder::JumpHash;
+    use sharder::{JumpHash, OverrideSharder};
+    use tonic::Code;
     use write_buffer::{
         core::WriteBufferWriting,
         mock::{MockBufferForWriting, MockBufferSharedState},
@@ -109,10 +565,16 @@ mod tests {
 
     const N_SHARDS: i32 = 10;
 
-    #[tokio::test]
-    async fn test_mapping() {
+    /// Initialise a catalog containing `N_SHARDS` shards for the "test" topic,
+    /// returning the shards and their mapping to catalog IDs.
+    async fn init_shards() -> (
+        Arc<dyn Catalog>,
+        TopicMetadata,
+        Vec<Arc<Shard>>,
+        HashMap<ShardIndex, ShardId>,
+    ) {
         let metrics = Arc::new(metric::Registry::default());
-        let catalog = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
         let write_buffer: Arc<dyn WriteBufferWriting> = Arc::new(init_write_buffer());
 
         let topic = catalog
@@ -143,17 +605,33 @@ mod tests {
             .collect::<HashMap<ShardIndex, ShardId>>()
             .await;
 
-        let sharder = JumpHash::new(
-            actual_mapping
-                .clone()
-                .into_iter()
-                .map(|(idx, _id)| Shard::new(idx, Arc::clone(&write_buffer), &*metrics))
-                .map(Arc::new),
-        );
+        // Shards are ordered by index so all sharders built from them are
+        // identical.
+        let mut indexes = actual_mapping.keys().copied().collect::<Vec<_>>();
+        indexes.sort();
+        let shards = indexes
+            .into_iter()
+            .map(|idx| Arc::new(Shard::new(idx, Arc::clone(&write_buffer), &*metrics)))
+            .collect();
 
-        let svc = ShardService::new(sharder, topic, catalog)
-            .await
-            .expect("failed to init service");
+        (catalog, topic, shards, actual_mapping)
+    }
+
+    #[tokio::test]
+    async fn test_mapping() {
+        let (catalog, topic, shards, actual_mapping) = init_shards().await;
+
+        let sharder = JumpHash::new(shards.clone());
+
+        let svc = ShardService::new(
+            sharder,
+            Arc::new(TableOverrides::default()),
+            shards,
+            topic,
+            catalog,
+        )
+        .await
+        .expect("failed to init service");
 
         // Validate the correct mapping was constructed.
         assert_eq!(svc.mapping, actual_mapping);
@@ -174,6 +652,282 @@ mod tests {
                 .expect("returned shard index must exist in mapping");
             assert_eq!(actual.get(), resp.shard_id);
         }
+
+        // The bulk RPC returns the same mapping for each input pair, in
+        // request order.
+        let tables = (0..100)
+            .map(|i| MapToShardRequest {
+                table_name: format!("{}", i),
+                namespace_name: "bananas".to_string(),
+            })
+            .collect::<Vec<_>>();
+        let mappings = svc
+            .map_to_shards(Request::new(MapToShardsRequest {
+                tables: tables.clone(),
+            }))
+            .await
+            .expect("rpc call should succeed")
+            .into_inner()
+            .mappings;
+        assert_eq!(mappings.len(), tables.len());
+        for (req, mapping) in tables.into_iter().zip(mappings) {
+            assert_eq!(mapping.table_name, req.table_name);
+            assert_eq!(mapping.namespace_name, req.namespace_name);
+            assert_eq!(mapping.topic_name, "test");
+
+            let want = svc
+                .map_to_shard(Request::new(req))
+                .await
+                .expect("rpc call should succeed")
+                .into_inner();
+            assert_eq!(mapping.shard_index, want.shard_index);
+            assert_eq!(mapping.shard_id, want.shard_id);
+        }
+
+        // An invalid namespace name fails the whole request.
+        let err = svc
+            .map_to_shards(Request::new(MapToShardsRequest {
+                tables: vec![
+                    MapToShardRequest {
+                        table_name: "platanos".to_string(),
+                        namespace_name: "bananas".to_string(),
+                    },
+                    MapToShardRequest {
+                        table_name: "platanos".to_string(),
+                        namespace_name: "".to_string(),
+                    },
+                ],
+            }))
+            .await
+            .expect_err("invalid namespace");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        // The jump hash sharder cannot be resharded.
+        let err = svc
+            .get_shard_ring(Request::new(GetShardRingRequest {}))
+            .await
+            .expect_err("jump hash has no ring");
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_reshard() {
+        let (catalog, topic, shards, actual_mapping) = init_shards().await;
+
+        let ring = Arc::new(HashRing::new(
+            NonZeroUsize::new(100).unwrap(),
+            shards.iter().map(|s| (s.shard_index(), Arc::clone(s))),
+        ));
+        let svc = ShardService::new(
+            Arc::clone(&ring),
+            Arc::new(TableOverrides::default()),
+            shards.clone(),
+            topic.clone(),
+            Arc::clone(&catalog),
+        )
+        .await
+        .expect("failed to init service")
+        .with_hash_ring(Arc::clone(&ring))
+        .await
+        .expect("failed to load ring");
+
+        let members = svc
+            .get_shard_ring(Request::new(GetShardRingRequest {}))
+            .await
+            .expect("failed to get ring")
+            .into_inner()
+            .members;
+        assert_eq!(members.len(), N_SHARDS as usize);
+        for m in &members {
+            let id = actual_mapping.get(&ShardIndex::new(m.shard_index)).unwrap();
+            assert_eq!(m.shard_id, id.get());
+        }
+        let total = members.iter().map(|m| m.ownership).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+        let removed_ownership = members.last().unwrap().ownership;
+
+        // Remove the last shard from the ring.
+        let resp = svc
+            .reshard(Request::new(ReshardRequest {
+                shard_indexes: (0..N_SHARDS - 1).collect(),
+            }))
+            .await
+            .expect("failed to reshard")
+            .into_inner();
+        assert_eq!(resp.members.len(), N_SHARDS as usize - 1);
+        assert!((resp.moved_fraction - removed_ownership).abs() < 1e-9);
+
+        for i in 0..100 {
+            let resp = svc
+                .map_to_shard(Request::new(MapToShardRequest {
+                    table_name: format!("{}", i),
+                    namespace_name: "bananas".to_string(),
+                }))
+                .await
+                .expect("rpc call should succeed")
+                .into_inner();
+            assert_ne!(resp.shard_index, N_SHARDS - 1);
+        }
+
+        // The ring must contain at least one known shard.
+        for shard_indexes in [vec![], vec![N_SHARDS]] {
+            let err = svc
+                .reshard(Request::new(ReshardRequest { shard_indexes }))
+                .await
+                .expect_err("invalid reshard");
+            assert_eq!(err.code(), Code::InvalidArgument);
+        }
+
+        // Another router loads the persisted membership when initialised.
+        let other_ring = Arc::new(HashRing::new(
+            NonZeroUsize::new(100).unwrap(),
+            shards.iter().map(|s| (s.shard_index(), Arc::clone(s))),
+        ));
+        let other = ShardService::new(
+            Arc::clone(&other_ring),
+            Arc::new(TableOverrides::default()),
+            shards,
+            topic,
+            Arc::clone(&catalog),
+        )
+        .await
+        .expect("failed to init service")
+        .with_hash_ring(other_ring)
+        .await
+        .expect("failed to load ring");
+        let members = other
+            .get_shard_ring(Request::new(GetShardRingRequest {}))
+            .await
+            .expect("failed to get ring")
+            .into_inner()
+            .members;
+        assert_eq!(members, resp.members);
+
+        // A reshard through the other router is applied by the first router
+        // when it next refreshes the membership.
+        let handle = svc
+            .spawn_hash_ring_refresh(Duration::from_millis(10))
+            .expect("service has a ring");
+        other
+            .reshard(Request::new(ReshardRequest {
+                shard_indexes: vec![0, 1],
+            }))
+            .await
+            .expect("failed to reshard");
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while ring.shards().len() != 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("ring membership was not refreshed");
+        handle.abort();
+    }
+
+    /// Return the shard index the "platanos" table in the "bananas" namespace
+    /// is mapped to by `svc`.
+    async fn map_to_shard<S>(svc: &ShardService<S>) -> i32
+    where
+        S: Sharder<(), Item = Arc<Shard>> + 'static,
+    {
+        svc.map_to_shard(Request::new(MapToShardRequest {
+            table_name: "platanos".to_string(),
+            namespace_name: "bananas".to_string(),
+        }))
+        .await
+        .expect("rpc call should succeed")
+        .into_inner()
+        .shard_index
+    }
+
+    #[tokio::test]
+    async fn test_table_overrides() {
+        let (catalog, topic, shards, _mapping) = init_shards().await;
+
+        {
+            let mut repos = catalog.repositories().await;
+            let pool = repos
+                .query_pools()
+                .create_or_get("pool")
+                .await
+                .expect("pool create");
+            repos
+                .namespaces()
+                .create("bananas", "inf", topic.id, pool.id)
+                .await
+                .expect("namespace create");
+        }
+
+        let new_service = || {
+            let overrides = Arc::new(TableOverrides::default());
+            let sharder =
+                OverrideSharder::new(JumpHash::new(shards.clone()), Arc::clone(&overrides));
+            ShardService::new(
+                sharder,
+                overrides,
+                shards.clone(),
+                topic.clone(),
+                Arc::clone(&catalog),
+            )
+        };
+        let svc = new_service().await.expect("failed to init service");
+        let default_index = map_to_shard(&svc).await;
+        let pinned_index = (default_index + 1) % N_SHARDS;
+
+        let set = |namespace_name: &str, shard_index: i32| {
+            svc.set_table_shard_override(Request::new(SetTableShardOverrideRequest {
+                shard_override: Some(ProtoTableShardOverride {
+                    namespace_name: namespace_name.to_string(),
+                    table_name: "platanos".to_string(),
+                    shard_index,
+                }),
+            }))
+        };
+
+        // Overrides must refer to an existing namespace and shard.
+        let err = set("mangos", pinned_index)
+            .await
+            .expect_err("unknown namespace");
+        assert_eq!(err.code(), Code::NotFound);
+        let err = set("bananas", N_SHARDS).await.expect_err("unknown shard");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        set("bananas", pinned_index)
+            .await
+            .expect("failed to set override");
+        assert_eq!(map_to_shard(&svc).await, pinned_index);
+
+        let list = svc
+            .list_table_shard_overrides(Request::new(ListTableShardOverridesRequest {}))
+            .await
+            .expect("failed to list overrides")
+            .into_inner()
+            .overrides;
+        assert_eq!(
+            list,
+            [ProtoTableShardOverride {
+                namespace_name: "bananas".to_string(),
+                table_name: "platanos".to_string(),
+                shard_index: pinned_index,
+            }]
+        );
+
+        // A new service loads the persisted overrides from the catalog.
+        let svc2 = new_service().await.expect("failed to init service");
+        assert_eq!(map_to_shard(&svc2).await, pinned_index);
+
+        // Deleting the override restores the default mapping.
+        let delete = || {
+            svc.delete_table_shard_override(Request::new(DeleteTableShardOverrideRequest {
+                namespace_name: "bananas".to_string(),
+                table_name: "platanos".to_string(),
+            }))
+        };
+        delete().await.expect("failed to delete override");
+        assert_eq!(map_to_shard(&svc).await, default_index);
+
+        let err = delete().await.expect_err("override already deleted");
+        assert_eq!(err.code(), Code::NotFound);
     }
 
     // Init a mock write buffer with the given number of shards.
@@ -0,0 +1,197 @@
+//! A gRPC service accepting pre-encoded table batches, for low-overhead
+//! intra-cluster ingest.
+
+use crate::dml_handlers::{DmlError, DmlHandler, PartitionError, RetentionError, SchemaError};
+use data_types::DatabaseName;
+use generated_types::influxdata::pbdata::v1::{write_service_server, WriteRequest, WriteResponse};
+use hashbrown::HashMap;
+use mutable_batch::MutableBatch;
+use mutable_batch_pb::decode::decode_database_batch;
+use observability_deps::tracing::*;
+use std::sync::Arc;
+use tonic::{metadata::AsciiMetadataValue, Request, Response, Status};
+use trace::ctx::SpanContext;
+use write_summary::WriteSummary;
+
+/// The gRPC metadata key carrying the write token of a successful write,
+/// equivalent to the `X-IOx-Write-Token` header of HTTP writes.
+pub const WRITE_TOKEN_GRPC_HEADER: &str = "x-iox-write-token";
+
+/// A [`WriteService`] accepts protobuf-encoded [`DatabaseBatch`] writes and
+/// passes them through the same [`DmlHandler`] stack as HTTP writes, without
+/// the cost of encoding and parsing line protocol.
+///
+/// [`WriteService`]: generated_types::influxdata::pbdata::v1::write_service_server::WriteService
+/// [`DatabaseBatch`]: generated_types::influxdata::pbdata::v1::DatabaseBatch
+#[derive(Debug)]
+pub struct WriteService<D> {
+    dml_handler: Arc<D>,
+}
+
+impl<D> WriteService<D> {
+    /// Initialise a new [`WriteService`] passing writes to `dml_handler`.
+    pub fn new(dml_handler: Arc<D>) -> Self {
+        Self { dml_handler }
+    }
+}
+
+#[tonic::async_trait]
+impl<D> write_service_server::WriteService for WriteService<D>
+where
+    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary> + 'static,
+{
+    async fn write(
+        &self,
+        request: Request<WriteRequest>,
+    ) -> Result<Response<WriteResponse>, Status> {
+        let span_ctx: Option<SpanContext> = request.extensions().get().cloned();
+
+        let database_batch = request
+            .into_inner()
+            .database_batch
+            .ok_or_else(|| Status::invalid_argument("no database batch provided"))?;
+
+        let namespace = DatabaseName::try_from(database_batch.database_name.clone())
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        let batches = decode_database_batch(&database_batch)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(Response::new(WriteResponse {}));
+        }
+
+        debug!(
+            num_tables = batches.len(),
+            %namespace,
+            "routing grpc write",
+        );
+
+        let summary = self
+            .dml_handler
+            .write(&namespace, batches, span_ctx)
+            .await
+            .map_err(|e| dml_error_to_status(e.into()))?;
+
+        let mut response = Response::new(WriteResponse {});
+        if let Ok(token) = AsciiMetadataValue::try_from(summary.to_token()) {
+            response
+                .metadata_mut()
+                .insert(WRITE_TOKEN_GRPC_HEADER, token);
+        }
+        Ok(response)
+    }
+}
+
+/// Map a [`DmlError`] to the appropriate gRPC [`Status`].
+pub(super) fn dml_error_to_status(e: DmlError) -> Status {
+    let msg = e.to_string();
+    match e {
+        DmlError::DatabaseNotFound(_) => Status::not_found(msg),
+        DmlError::Schema(SchemaError::ServiceLimit(_)) => Status::resource_exhausted(msg),
+        DmlError::Schema(SchemaError::Conflict(_)) => Status::invalid_argument(msg),
+        DmlError::Retention(RetentionError::OutsideRetention { .. }) => {
+            Status::invalid_argument(msg)
+        }
+        DmlError::Schema(SchemaError::NamespaceLookup(_))
+        | DmlError::Schema(SchemaError::UnexpectedCatalogError(_))
+        | DmlError::Retention(RetentionError::NamespaceLookup(_))
+        | DmlError::Retention(RetentionError::BatchWrite(_))
+        | DmlError::Internal(_)
+        | DmlError::WriteBuffer(_)
+        | DmlError::NamespaceCreation(_)
+        | DmlError::Partition(PartitionError::BatchWrite(_)) => Status::internal(msg),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall};
+    use assert_matches::assert_matches;
+    use generated_types::influxdata::pbdata::v1::{
+        write_service_server::WriteService as _, DatabaseBatch,
+    };
+    use mutable_batch_lp::lines_to_batches;
+    use mutable_batch_pb::encode::encode_batch;
+    use tonic::Code;
+
+    fn write_request(namespace: &str, lp: &str) -> Request<WriteRequest> {
+        let table_batches = lines_to_batches(lp, 0)
+            .expect("invalid line protocol")
+            .iter()
+            .map(|(table, batch)| encode_batch(table, batch))
+            .collect();
+
+        Request::new(WriteRequest {
+            database_batch: Some(DatabaseBatch {
+                database_name: namespace.to_string(),
+                partition_key: Default::default(),
+                table_batches,
+            }),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_write() {
+        let dml_handler = Arc::new(
+            MockDmlHandler::<HashMap<String, MutableBatch>>::default()
+                .with_write_return([Ok(WriteSummary::default())]),
+        );
+        let svc = WriteService::new(Arc::clone(&dml_handler));
+
+        let resp = svc
+            .write(write_request(
+                "bananas_test",
+                "platanos,tag1=A val=42i 123456\nplatanos,tag1=B val=24i 123457\nmangos v=1 1",
+            ))
+            .await
+            .expect("write should succeed");
+        assert!(resp.metadata().get(WRITE_TOKEN_GRPC_HEADER).is_some());
+
+        assert_matches!(dml_handler.calls().as_slice(), [MockDmlHandlerCall::Write { namespace, write_input }] => {
+            assert_eq!(namespace, "bananas_test");
+            assert_eq!(write_input.len(), 2);
+            assert_eq!(write_input.get("platanos").expect("table not found").rows(), 2);
+            assert_eq!(write_input.get("mangos").expect("table not found").rows(), 1);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_write_invalid() {
+        let dml_handler = Arc::new(MockDmlHandler::<HashMap<String, MutableBatch>>::default());
+        let svc = WriteService::new(Arc::clone(&dml_handler));
+
+        let err = svc
+            .write(Request::new(WriteRequest {
+                database_batch: None,
+            }))
+            .await
+            .expect_err("missing batch should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = svc
+            .write(write_request("", "platanos v=1 1"))
+            .await
+            .expect_err("invalid namespace should be rejected");
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        assert!(dml_handler.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_dml_handler_error() {
+        let dml_handler = Arc::new(
+            MockDmlHandler::<HashMap<String, MutableBatch>>::default()
+                .with_write_return([Err(DmlError::DatabaseNotFound("bananas_test".into()))]),
+        );
+        let svc = WriteService::new(Arc::clone(&dml_handler));
+
+        let err = svc
+            .write(write_request("bananas_test", "platanos v=1 1"))
+            .await
+            .expect_err("write should fail");
+        assert_eq!(err.code(), Code::NotFound);
+        assert_eq!(dml_handler.calls().len(), 1);
+    }
+}
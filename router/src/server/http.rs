@@ -1,30 +1,109 @@
 //! HTTP service implementations for `router`.
 
+mod admission;
+mod auth;
+mod body;
+mod body_metrics;
+mod cors;
 mod delete_predicate;
-
-use self::delete_predicate::parse_http_delete_request;
-use crate::dml_handlers::{DmlError, DmlHandler, PartitionError, SchemaError};
+mod digest;
+mod graphite;
+mod idempotency;
+mod json;
+mod latency;
+mod limits;
+mod line_limits;
+mod org_concurrency;
+mod otlp;
+mod prometheus;
+mod rate_limit;
+mod time_window;
+mod token_namespace;
+mod v1_compat;
+mod write_metrics;
+
+pub use self::auth::{
+    AuthError, Authorizer, Credentials, NamespaceGrant, NamespaceGrantError, Permission,
+    StaticTokenAuthorizer,
+};
+pub use self::cors::CorsPolicy;
+pub use self::digest::DigestError;
+pub use self::graphite::{GraphiteError, GraphiteTemplateError, GraphiteTemplates};
+pub use self::idempotency::{IdempotencyCache, IdempotencyKeyError};
+pub use self::json::JsonWriteError;
+pub use self::limits::RequestLimits;
+pub use self::otlp::OtlpError;
+pub use self::prometheus::PromWriteError;
+pub use self::rate_limit::OrgRateLimiter;
+pub use self::time_window::{TimeWindow, TimeWindowError, TimeWindows};
+pub use self::token_namespace::{StaticTokenNamespaces, TokenNamespace, TokenNamespaceLookup};
+
+use self::{
+    admission::{estimate_body_size, ByteBudget},
+    body::{BodyError, ContentEncoding, DecodedBody, LineBuffer},
+    body_metrics::BodyMetrics,
+    delete_predicate::parse_http_delete_requests,
+    digest::BodyDigest,
+    graphite::graphite_to_batches,
+    idempotency::IdempotencyKey,
+    json::{json_to_batches, JSON_CONTENT_TYPE},
+    latency::LatencyAverage,
+    limits::RequestLimiter,
+    line_limits::LineLimits,
+    org_concurrency::{OrgConcurrencyLimiter, OrgPermit},
+    otlp::export_request_to_batches,
+    prometheus::write_request_to_batches,
+    v1_compat::{health_response, probe_response},
+    write_metrics::WriteMetrics,
+};
+use crate::{
+    audit::{AuditLog, AuditOperation, AuditRecord},
+    dml_handlers::{DmlError, DmlHandler, PartitionError, RetentionError, SchemaError},
+    provenance::Provenance,
+    usage::UsageAccountant,
+    write_buffer_health::WriteBufferHealth,
+};
 use bytes::{Bytes, BytesMut};
-use data_types::{org_and_bucket_to_database, OrgBucketMappingError};
+use data_types::{
+    org_and_bucket_to_database, DatabaseName, DatabaseNameError, OrgBucketMappingError, ShardIndex,
+};
 use futures::StreamExt;
+use generated_types::{
+    opentelemetry::proto::collector::metrics::v1::{
+        ExportMetricsServiceRequest, ExportMetricsServiceResponse,
+    },
+    prometheus::WriteRequest,
+};
 use hashbrown::HashMap;
-use hyper::{header::CONTENT_ENCODING, Body, Method, Request, Response, StatusCode};
+use hyper::{
+    header::{CONTENT_ENCODING, CONTENT_TYPE},
+    Body, HeaderMap, Method, Request, Response, StatusCode,
+};
 use iox_time::{SystemProvider, TimeProvider};
 use metric::{DurationHistogram, U64Counter};
 use mutable_batch::MutableBatch;
 use mutable_batch_lp::LinesConverter;
 use observability_deps::tracing::*;
+use parking_lot::Mutex;
 use predicate::delete_predicate::parse_delete_predicate;
-use serde::Deserialize;
-use std::time::Instant;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
 use std::{str::Utf8Error, sync::Arc};
 use thiserror::Error;
-use tokio::sync::{Semaphore, TryAcquireError};
+use tokio::sync::watch;
 use trace::ctx::SpanContext;
 use write_summary::WriteSummary;
 
 const WRITE_TOKEN_HTTP_HEADER: &str = "X-IOx-Write-Token";
 
+/// The `Retry-After` advised to clients rejected due to overload before any
+/// request latency has been observed.
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// The content type of protobuf-encoded OTLP/HTTP requests & responses.
+const OTLP_PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+
 /// Errors returned by the `router` HTTP request handler.
 #[derive(Debug, Error)]
 pub enum Error {
@@ -36,6 +115,10 @@ pub enum Error {
     #[error(transparent)]
     InvalidOrgBucket(#[from] OrgBucketError),
 
+    /// An error with the db/rp in a v1 request.
+    #[error(transparent)]
+    InvalidDbRp(#[from] DbRpError),
+
     /// The request body content is not valid utf8.
     #[error("body content is not valid utf8: {0}")]
     NonUtf8Body(Utf8Error),
@@ -60,10 +143,54 @@ pub enum Error {
     #[error("error decoding gzip stream: {0}")]
     InvalidGzip(std::io::Error),
 
+    /// Decoding a zstd-compressed stream of data failed.
+    #[error("error decoding zstd stream: {0}")]
+    InvalidZstd(std::io::Error),
+
+    /// Decoding a snappy-compressed block of data failed.
+    #[error("error decoding snappy block: {0}")]
+    InvalidSnappy(snap::Error),
+
     /// Failure to decode the provided line protocol.
     #[error("failed to parse line protocol: {0}")]
     ParseLineProtocol(mutable_batch_lp::Error),
 
+    /// The write exceeds a configured limit on the number of lines, fields
+    /// per line or tags per line, where each point of a write in a format
+    /// other than line protocol counts as a line.
+    #[error("write limit exceeded: {0}")]
+    WriteLimitExceeded(mutable_batch_lp::Error),
+
+    /// Failure to decode the provided Prometheus remote write request.
+    #[error("failed to parse prometheus remote write request: {0}")]
+    ParsePromWrite(PromWriteError),
+
+    /// Failure to decode the provided OTLP metrics export request.
+    #[error("failed to parse otlp metrics export request: {0}")]
+    ParseOtlp(OtlpError),
+
+    /// Failure to decode the provided Graphite plaintext protocol write.
+    #[error("failed to parse graphite write: {0}")]
+    ParseGraphite(GraphiteError),
+
+    /// Failure to decode the provided JSON write request.
+    #[error("failed to parse json write request: {0}")]
+    ParseJson(JsonWriteError),
+
+    /// The write contains a point outside of the permitted time window of
+    /// the namespace.
+    #[error("timestamp out of bounds: {0}")]
+    TimestampOutOfBounds(#[from] TimeWindowError),
+
+    /// The request body does not match the digest supplied by the client, or
+    /// the digest header is invalid.
+    #[error(transparent)]
+    InvalidDigest(#[from] DigestError),
+
+    /// The idempotency key of the request is invalid.
+    #[error(transparent)]
+    InvalidIdempotencyKey(#[from] IdempotencyKeyError),
+
     /// Failure to parse the request delete predicate.
     #[error("failed to parse delete predicate: {0}")]
     ParseDelete(#[from] predicate::delete_predicate::Error),
@@ -76,10 +203,72 @@ pub enum Error {
     #[error("dml handler error: {0}")]
     DmlHandler(#[from] DmlError),
 
+    /// A delete request containing multiple deletes failed after some of
+    /// them were applied.
+    ///
+    /// The deletes of a request are not applied atomically: the `applied`
+    /// deletes before the failed delete remain applied, and those after it
+    /// are not applied.
+    #[error(
+        "delete {} of {total} failed, the {applied} deletes before it were applied: {source}",
+        .applied + 1
+    )]
+    PartialDelete {
+        /// The number of deletes applied before the failure.
+        applied: usize,
+        /// The number of deletes in the request.
+        total: usize,
+        /// The error returned by the [`DmlHandler`] for the failed delete.
+        source: DmlError,
+    },
+
     /// The router is currently servicing the maximum permitted number of
     /// simultaneous requests.
-    #[error("this service is overloaded, please try again later")]
-    RequestLimit,
+    #[error(
+        "this service is overloaded ({in_flight} requests in flight), please try again after {}s",
+        retry_after_secs(.retry_after).max(1)
+    )]
+    RequestLimit {
+        /// The number of requests being serviced when this request was
+        /// rejected.
+        in_flight: usize,
+        /// The recent average request latency, after which capacity is
+        /// likely to be available again.
+        retry_after: Duration,
+    },
+
+    /// The org has exceeded its write rate limit.
+    #[error("rate limit exceeded for org {org}, retry after {}s", retry_after_secs(.retry_after))]
+    RateLimited {
+        /// The org that exceeded its limit.
+        org: String,
+        /// The duration after which the request may be retried.
+        retry_after: Duration,
+    },
+
+    /// The org is currently making the maximum permitted number of
+    /// simultaneous requests.
+    #[error(
+        "org {org} has too many requests in flight (limit {limit}), please try again after {}s",
+        retry_after_secs(.retry_after).max(1)
+    )]
+    OrgRequestLimit {
+        /// The org that exceeded its limit.
+        org: String,
+        /// The maximum number of simultaneous requests of an org.
+        limit: usize,
+        /// The recent average request latency, after which the org is likely
+        /// to have capacity available again.
+        retry_after: Duration,
+    },
+
+    /// The request was not completed within the configured request timeout.
+    #[error("request timed out after {0:?}")]
+    Timeout(Duration),
+
+    /// The request failed authorization.
+    #[error(transparent)]
+    Auth(#[from] AuthError),
 }
 
 impl Error {
@@ -89,11 +278,27 @@ impl Error {
         match self {
             Error::NoHandler => StatusCode::NOT_FOUND,
             Error::InvalidOrgBucket(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidDbRp(_) => StatusCode::BAD_REQUEST,
             Error::ClientHangup(_) => StatusCode::BAD_REQUEST,
             Error::InvalidGzip(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidZstd(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidSnappy(_) => StatusCode::BAD_REQUEST,
             Error::NonUtf8ContentHeader(_) => StatusCode::BAD_REQUEST,
             Error::NonUtf8Body(_) => StatusCode::BAD_REQUEST,
             Error::ParseLineProtocol(_) => StatusCode::BAD_REQUEST,
+            Error::WriteLimitExceeded(_) => StatusCode::BAD_REQUEST,
+            Error::ParsePromWrite(_) => StatusCode::BAD_REQUEST,
+            Error::ParseOtlp(OtlpError::UnsupportedContentType(_)) => {
+                StatusCode::UNSUPPORTED_MEDIA_TYPE
+            }
+            Error::ParseOtlp(_) => StatusCode::BAD_REQUEST,
+            Error::ParseGraphite(_) => StatusCode::BAD_REQUEST,
+            Error::ParseJson(_) => StatusCode::BAD_REQUEST,
+            // Matches the response of InfluxDB to writes beyond the retention
+            // period.
+            Error::TimestampOutOfBounds(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            Error::InvalidIdempotencyKey(_) => StatusCode::BAD_REQUEST,
+            Error::InvalidDigest(_) => StatusCode::BAD_REQUEST,
             Error::ParseDelete(_) => StatusCode::BAD_REQUEST,
             Error::ParseHttpDelete(_) => StatusCode::BAD_REQUEST,
             Error::RequestSizeExceeded(_) => StatusCode::PAYLOAD_TOO_LARGE,
@@ -102,7 +307,114 @@ impl Error {
                 StatusCode::UNSUPPORTED_MEDIA_TYPE
             }
             Error::DmlHandler(err) => StatusCode::from(err),
-            Error::RequestLimit => StatusCode::SERVICE_UNAVAILABLE,
+            Error::PartialDelete { source, .. } => StatusCode::from(source),
+            Error::RequestLimit { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Error::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::OrgRequestLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Error::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            Error::Auth(AuthError::Forbidden { .. }) => StatusCode::FORBIDDEN,
+            Error::Auth(AuthError::Internal(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Auth(_) => StatusCode::UNAUTHORIZED,
+        }
+    }
+
+    /// A stable, machine-readable identifier of the cause of this error,
+    /// returned to the client alongside the (human-readable) error message.
+    ///
+    /// Unlike the status code, this identifies the specific failure, allowing
+    /// clients to branch on it. These values MUST NOT change once released.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Error::NoHandler => "not_found",
+            Error::InvalidOrgBucket(_) => "invalid_org_bucket",
+            Error::InvalidDbRp(_) => "invalid_db_rp",
+            Error::ClientHangup(_) => "client_hangup",
+            Error::InvalidGzip(_) | Error::InvalidZstd(_) | Error::InvalidSnappy(_) => {
+                "invalid_compressed_body"
+            }
+            Error::NonUtf8ContentHeader(_) => "invalid_header",
+            Error::NonUtf8Body(_) => "invalid_utf8_body",
+            Error::ParseLineProtocol(_) => "invalid_line_protocol",
+            Error::WriteLimitExceeded(_) => "write_limit_exceeded",
+            Error::ParsePromWrite(_) => "invalid_prometheus_write",
+            Error::ParseOtlp(OtlpError::UnsupportedContentType(_)) => "unsupported_content_type",
+            Error::ParseOtlp(_) => "invalid_otlp_request",
+            Error::ParseGraphite(_) => "invalid_graphite_write",
+            Error::ParseJson(_) => "invalid_json_write",
+            Error::TimestampOutOfBounds(_) => "timestamp_out_of_bounds",
+            Error::InvalidIdempotencyKey(_) => "invalid_idempotency_key",
+            Error::InvalidDigest(DigestError::Mismatch(_)) => "digest_mismatch",
+            Error::InvalidDigest(_) => "invalid_digest",
+            Error::ParseDelete(_) | Error::ParseHttpDelete(_) => "invalid_delete_predicate",
+            Error::RequestSizeExceeded(_) => "request_too_large",
+            Error::InvalidContentEncoding(_) => "unsupported_content_encoding",
+            Error::DmlHandler(DmlError::DatabaseNotFound(_)) => "namespace_not_found",
+            Error::DmlHandler(DmlError::Schema(SchemaError::ServiceLimit(_))) => {
+                "schema_limit_exceeded"
+            }
+            Error::DmlHandler(DmlError::Schema(SchemaError::Conflict(_))) => "schema_conflict",
+            Error::DmlHandler(DmlError::Retention(RetentionError::OutsideRetention { .. })) => {
+                "outside_retention_period"
+            }
+            Error::DmlHandler(_) => "internal_error",
+            Error::PartialDelete { .. } => "partial_delete",
+            Error::RequestLimit { .. } => "request_limit_exceeded",
+            Error::RateLimited { .. } => "rate_limited",
+            Error::OrgRequestLimit { .. } => "org_request_limit_exceeded",
+            Error::Timeout(_) => "timeout",
+            Error::Auth(AuthError::Forbidden { .. }) => "forbidden",
+            Error::Auth(AuthError::Internal(_)) => "internal_error",
+            Error::Auth(_) => "unauthorized",
+        }
+    }
+
+    /// The number of seconds the client should wait before retrying the
+    /// request, to be returned in a `Retry-After` header, if any.
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            Error::RateLimited { retry_after, .. } => Some(retry_after_secs(retry_after)),
+            Error::OrgRequestLimit { retry_after, .. } => {
+                Some(retry_after_secs(retry_after).max(1))
+            }
+            // Never advise an immediate retry of an overloaded router.
+            Error::RequestLimit { retry_after, .. } => Some(retry_after_secs(retry_after).max(1)),
+            _ => None,
+        }
+    }
+
+    /// The number of requests the router was servicing when this request was
+    /// rejected due to overload, if applicable.
+    pub fn in_flight_requests(&self) -> Option<usize> {
+        match self {
+            Error::RequestLimit { in_flight, .. } => Some(*in_flight),
+            _ => None,
+        }
+    }
+
+    /// The request timeout that was exceeded, if applicable.
+    pub fn timeout(&self) -> Option<Duration> {
+        match self {
+            Error::Timeout(d) => Some(*d),
+            _ => None,
+        }
+    }
+}
+
+/// `Retry-After` accepts only whole seconds - round up so a client never
+/// retries too early.
+fn retry_after_secs(d: &Duration) -> u64 {
+    d.as_secs() + u64::from(d.subsec_nanos() > 0)
+}
+
+impl From<BodyError> for Error {
+    fn from(e: BodyError) -> Self {
+        match e {
+            BodyError::ClientHangup(e) => Error::ClientHangup(e),
+            BodyError::SizeExceeded(n) => Error::RequestSizeExceeded(n),
+            BodyError::Gzip(e) => Error::InvalidGzip(e),
+            BodyError::Zstd(e) => Error::InvalidZstd(e),
+            BodyError::Snappy(e) => Error::InvalidSnappy(e),
+            BodyError::Digest(e) => Error::InvalidDigest(e),
         }
     }
 }
@@ -127,6 +439,13 @@ impl From<&DmlError> for StatusCode {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
 
+            // Retention validation error cases
+            DmlError::Retention(RetentionError::NamespaceLookup(_)) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+            DmlError::Retention(RetentionError::OutsideRetention { .. }) => StatusCode::BAD_REQUEST,
+            DmlError::Retention(RetentionError::BatchWrite(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+
             DmlError::Internal(_) | DmlError::WriteBuffer(_) | DmlError::NamespaceCreation(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
@@ -152,18 +471,76 @@ pub enum OrgBucketError {
     MappingFail(#[from] OrgBucketMappingError),
 }
 
-#[derive(Debug, Deserialize)]
+/// Errors returned when decoding the database / retention policy information
+/// from an InfluxDB 1.x compatible HTTP request and deriving the namespace
+/// from it.
+#[derive(Debug, Error)]
+pub enum DbRpError {
+    /// The request contains no db destination information.
+    #[error("no db destination provided")]
+    NotSpecified,
+
+    /// The request contains invalid parameters.
+    #[error("failed to deserialize db/rp/precision in request: {0}")]
+    DecodeFail(#[from] serde::de::value::Error),
+
+    /// The provided db/rp could not be converted into a namespace name.
+    #[error("invalid db/rp: {0}")]
+    MappingFail(#[from] DatabaseNameError),
+}
+
+/// The precision of the timestamps in a write request.
+///
+/// In addition to the InfluxDB 2.x precisions (`s`, `ms`, `us` & `ns`), the
+/// InfluxDB 1.x precisions (`n`, `u`, `µ`, `m` & `h`) are accepted so that
+/// existing v1 clients continue to work. Precisions are case-sensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
 enum Precision {
-    #[serde(rename = "s")]
+    Hours,
+    Minutes,
     Seconds,
-    #[serde(rename = "ms")]
     Milliseconds,
-    #[serde(rename = "us")]
     Microseconds,
-    #[serde(rename = "ns")]
     Nanoseconds,
 }
 
+impl TryFrom<String> for Precision {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Ok(match s.as_str() {
+            "h" => Self::Hours,
+            "m" => Self::Minutes,
+            "s" => Self::Seconds,
+            "ms" => Self::Milliseconds,
+            "us" | "u" | "µ" => Self::Microseconds,
+            "ns" | "n" => Self::Nanoseconds,
+            // "M" is commonly used for both minutes and months (and never
+            // milliseconds) - rather than guess, reject it.
+            "M" => {
+                return Err(
+                    r#"ambiguous precision "M" (use "m" for minutes, or "ms" for milliseconds)"#
+                        .to_string(),
+                )
+            }
+            _ => {
+                let lower = s.to_lowercase();
+                if lower != s && Self::try_from(lower.clone()).is_ok() {
+                    return Err(format!(
+                        r#"invalid precision "{}" (precision is case-sensitive, did you mean "{}"?)"#,
+                        s, lower
+                    ));
+                }
+                return Err(format!(
+                    r#"invalid precision "{}" (expected one of ns, n, us, u, µ, ms, s, m or h)"#,
+                    s
+                ));
+            }
+        })
+    }
+}
+
 impl Default for Precision {
     fn default() -> Self {
         Self::Nanoseconds
@@ -174,6 +551,8 @@ impl Precision {
     /// Returns the multiplier to convert to nanosecond timestamps
     fn timestamp_base(&self) -> i64 {
         match self {
+            Precision::Hours => 3_600_000_000_000,
+            Precision::Minutes => 60_000_000_000,
             Precision::Seconds => 1_000_000_000,
             Precision::Milliseconds => 1_000_000,
             Precision::Microseconds => 1_000,
@@ -190,6 +569,11 @@ pub struct WriteInfo {
 
     #[serde(default)]
     precision: Precision,
+
+    /// Skip invalid lines rather than rejecting the entire write, overriding
+    /// the router default.
+    #[serde(default)]
+    partial: Option<bool>,
 }
 
 impl<T> TryFrom<&Request<T>> for WriteInfo {
@@ -208,6 +592,78 @@ impl<T> TryFrom<&Request<T>> for WriteInfo {
     }
 }
 
+/// The write options of a request whose namespace is derived from its API
+/// token, in which any org/bucket (or db/rp) parameters are ignored.
+#[derive(Debug, Default, Deserialize)]
+struct WriteOptions {
+    #[serde(default)]
+    precision: Precision,
+
+    #[serde(default)]
+    partial: Option<bool>,
+}
+
+/// The namespace a request operates on, the org against which per-org limits
+/// are applied, and the options of the write (if any).
+#[derive(Debug)]
+struct RequestTarget {
+    namespace: DatabaseName<'static>,
+    org: String,
+    precision: Precision,
+    partial: Option<bool>,
+}
+
+/// Database, retention policy & precision of an InfluxDB 1.x compatible write
+/// request.
+#[derive(Debug, Deserialize)]
+pub struct V1WriteInfo {
+    db: String,
+    rp: Option<String>,
+
+    #[serde(default)]
+    precision: Precision,
+
+    /// Skip invalid lines rather than rejecting the entire write, overriding
+    /// the router default.
+    #[serde(default)]
+    partial: Option<bool>,
+}
+
+impl V1WriteInfo {
+    /// The retention policy that is implied when no `rp` is specified.
+    const DEFAULT_RP: &'static str = "autogen";
+
+    /// Map the db/rp of this request to an IOx namespace.
+    ///
+    /// Writes to the default retention policy are mapped to a namespace named
+    /// after the db alone, and writes to any other retention policy to a
+    /// namespace named `<db>/<rp>`, following the DBRP mapping convention of
+    /// InfluxDB 2.x.
+    fn namespace(&self) -> Result<DatabaseName<'static>, DbRpError> {
+        let name = match self.rp.as_deref() {
+            None | Some("") | Some(Self::DEFAULT_RP) => self.db.clone(),
+            Some(rp) => format!("{}/{}", self.db, rp),
+        };
+        Ok(DatabaseName::new(name)?)
+    }
+}
+
+impl<T> TryFrom<&Request<T>> for V1WriteInfo {
+    type Error = DbRpError;
+
+    fn try_from(req: &Request<T>) -> Result<Self, Self::Error> {
+        let query = req.uri().query().ok_or(DbRpError::NotSpecified)?;
+        let got: V1WriteInfo = serde_urlencoded::from_str(query)?;
+
+        // An empty db is not acceptable.
+        if got.db.is_empty() {
+            return Err(DbRpError::NotSpecified);
+        }
+
+        Ok(got)
+    }
+}
+
 /// This type is responsible for servicing requests to the `router` HTTP
 /// endpoint.
 ///
@@ -216,10 +672,66 @@ impl<T> TryFrom<&Request<T>> for WriteInfo {
 /// metrics, pprof, etc.
 #[derive(Debug)]
 pub struct HttpDelegate<D, T = SystemProvider> {
-    max_request_bytes: usize,
+    // The request size, simultaneous request & rate limits, which may be
+    // changed at runtime, and the limits currently applied to the request
+    // limiter & rate limiter.
+    limits: watch::Receiver<RequestLimits>,
+    applied_limits: Mutex<RequestLimits>,
+
     time_provider: T,
     dml_handler: Arc<D>,
 
+    // An optional authorizer of write & delete requests - when not set, all
+    // requests are permitted.
+    authorizer: Option<Arc<dyn Authorizer>>,
+
+    // An optional per-org limit of the lines and bytes written per second.
+    rate_limiter: Option<OrgRateLimiter>,
+
+    // An optional lookup of the namespace bound to the API token of a
+    // request - when set, the namespace parameters of requests are ignored.
+    token_namespaces: Option<Arc<dyn TokenNamespaceLookup>>,
+
+    // An optional CORS policy permitting requests from browser-based
+    // clients.
+    cors: Option<CorsPolicy>,
+
+    // Limits on the shape of line protocol writes.
+    line_limits: LineLimits,
+
+    // The templates mapping Graphite metric paths to measurements, fields &
+    // tags.
+    graphite: GraphiteTemplates,
+
+    // The range of point timestamps accepted by each namespace.
+    time_windows: TimeWindows,
+
+    // An optional log of the writes & deletes accepted by the router.
+    audit: Option<Arc<AuditLog>>,
+
+    // An optional cache of recently completed writes, answering retried
+    // writes carrying the same idempotency key without writing them again.
+    idempotency: Option<IdempotencyCache>,
+
+    // Whether request bodies are verified against the digest supplied by the
+    // client (if any).
+    verify_digests: bool,
+
+    // Whether invalid lines of a line protocol write are skipped (rather than
+    // failing the entire write) when the request does not specify.
+    partial_writes: bool,
+
+    // Whether the provenance of each write & delete request (derived from the
+    // request headers) is attached to the DML operations it produces.
+    provenance: bool,
+
+    // The optional health of the write buffer shards, failing health checks
+    // while any shard is unreachable.
+    write_buffer_health: Option<Arc<WriteBufferHealth>>,
+
+    // An optional accountant of the lines & bytes written to each namespace.
+    usage: Option<Arc<UsageAccountant>>,
+
     // A request limiter to restrict the number of simultaneous requests this
     // router services.
     //
@@ -227,15 +739,35 @@ pub struct HttpDelegate<D, T = SystemProvider> {
     // unusual flood of requests (i.e. due to peer routers crashing and
     // depleting the available instances in the pool) in order to preserve
     // overall system availability, instead of OOMing or otherwise failing.
-    request_sem: Semaphore,
+    request_sem: RequestLimiter,
+
+    // An optional per-org limit on the number of simultaneous requests,
+    // sub-dividing the global limit above.
+    org_concurrency: Option<OrgConcurrencyLimiter>,
+
+    // An optional budget of request body bytes serviced simultaneously,
+    // protecting against a small number of large requests exhausting memory.
+    byte_budget: Option<ByteBudget>,
 
-    write_metric_lines: U64Counter,
+    // The recent average latency of serviced requests, used to advise
+    // rejected clients when to retry.
+    request_latency: LatencyAverage,
+
+    // An optional limit on the time taken to service a request, after which
+    // the request (including any in-progress DML handler call) is aborted.
+    request_timeout: Option<Duration>,
+
+    write_metrics: WriteMetrics,
+    body_metrics: BodyMetrics,
     http_line_protocol_parse_duration: DurationHistogram,
-    write_metric_fields: U64Counter,
-    write_metric_tables: U64Counter,
-    write_metric_body_size: U64Counter,
     delete_metric_body_size: U64Counter,
     request_limit_rejected: U64Counter,
+    byte_budget_rejected: U64Counter,
+    rate_limit_rejected: U64Counter,
+    org_request_limit_rejected: U64Counter,
+    request_timeouts: U64Counter,
+    client_aborts: U64Counter,
+    duplicate_writes: U64Counter,
 }
 
 impl<D> HttpDelegate<D, SystemProvider> {
@@ -250,40 +782,54 @@ impl<D> HttpDelegate<D, SystemProvider> {
         dml_handler: Arc<D>,
         metrics: &metric::Registry,
     ) -> Self {
-        let write_metric_lines = metrics
+        let write_metrics = WriteMetrics::new(metrics);
+        let body_metrics = BodyMetrics::new(metrics);
+        let delete_metric_body_size = metrics
+            .register_metric::<U64Counter>(
+                "http_delete_body_bytes_total",
+                "cumulative byte size of successfully routed (decompressed) delete requests",
+            )
+            .recorder(&[]);
+        let request_limit_rejected = metrics
             .register_metric::<U64Counter>(
-                "http_write_lines_total",
-                "cumulative number of line protocol lines successfully routed",
+                "http_request_limit_rejected",
+                "number of HTTP requests rejected due to exceeding parallel request limit",
             )
             .recorder(&[]);
-        let write_metric_fields = metrics
+        let byte_budget_rejected = metrics
             .register_metric::<U64Counter>(
-                "http_write_fields_total",
-                "cumulative number of line protocol fields successfully routed",
+                "http_request_byte_budget_rejected",
+                "number of HTTP requests rejected due to exceeding the simultaneous request byte budget",
             )
             .recorder(&[]);
-        let write_metric_tables = metrics
+        let rate_limit_rejected = metrics
             .register_metric::<U64Counter>(
-                "http_write_tables_total",
-                "cumulative number of tables in each write request",
+                "http_rate_limit_rejected",
+                "number of HTTP write requests rejected due to exceeding the per-org rate limit",
             )
             .recorder(&[]);
-        let write_metric_body_size = metrics
+        let org_request_limit_rejected = metrics
             .register_metric::<U64Counter>(
-                "http_write_body_bytes_total",
-                "cumulative byte size of successfully routed (decompressed) line protocol write requests",
+                "http_org_request_limit_rejected",
+                "number of HTTP requests rejected due to exceeding the per-org parallel request limit",
             )
             .recorder(&[]);
-        let delete_metric_body_size = metrics
+        let request_timeouts = metrics
             .register_metric::<U64Counter>(
-                "http_delete_body_bytes_total",
-                "cumulative byte size of successfully routed (decompressed) delete requests",
+                "http_request_timeouts",
+                "number of HTTP requests aborted due to exceeding the request timeout",
             )
             .recorder(&[]);
-        let request_limit_rejected = metrics
+        let client_aborts = metrics
             .register_metric::<U64Counter>(
-                "http_request_limit_rejected",
-                "number of HTTP requests rejected due to exceeding parallel request limit",
+                "http_client_abort_total",
+                "number of HTTP requests abandoned by the client before completion",
+            )
+            .recorder(&[]);
+        let duplicate_writes = metrics
+            .register_metric::<U64Counter>(
+                "http_write_duplicates",
+                "number of HTTP write requests answered with the result of a previous write with the same idempotency key",
             )
             .recorder(&[]);
         let http_line_protocol_parse_duration = metrics
@@ -293,30 +839,278 @@ impl<D> HttpDelegate<D, SystemProvider> {
             )
             .recorder(&[]);
 
-        Self {
+        let limits = RequestLimits {
             max_request_bytes,
+            max_requests,
+            lines_per_second: None,
+            bytes_per_second: None,
+        };
+
+        Self {
+            // The limits are static unless replaced by a receiver of updates.
+            limits: watch::channel(limits).1,
+            applied_limits: Mutex::new(limits),
             time_provider: SystemProvider::default(),
             dml_handler,
-            request_sem: Semaphore::new(max_requests),
-            write_metric_lines,
+            authorizer: None,
+            rate_limiter: None,
+            token_namespaces: None,
+            cors: None,
+            line_limits: LineLimits::default(),
+            graphite: GraphiteTemplates::default(),
+            time_windows: TimeWindows::default(),
+            audit: None,
+            idempotency: None,
+            verify_digests: false,
+            partial_writes: false,
+            provenance: false,
+            write_buffer_health: None,
+            usage: None,
+            request_sem: RequestLimiter::new(max_requests),
+            org_concurrency: None,
+            byte_budget: None,
+            request_latency: LatencyAverage::default(),
+            request_timeout: None,
+            write_metrics,
+            body_metrics,
             http_line_protocol_parse_duration,
-            write_metric_fields,
-            write_metric_tables,
-            write_metric_body_size,
             delete_metric_body_size,
             request_limit_rejected,
+            byte_budget_rejected,
+            rate_limit_rejected,
+            org_request_limit_rejected,
+            request_timeouts,
+            client_aborts,
+            duplicate_writes,
         }
     }
 }
 
+impl<D, T> HttpDelegate<D, T> {
+    /// Require all write & delete requests to be authorized by `authorizer`
+    /// before they are processed.
+    pub fn with_authorizer(mut self, authorizer: Arc<dyn Authorizer>) -> Self {
+        self.authorizer = Some(authorizer);
+        self
+    }
+
+    /// Limit the rate at which each org may write, rejecting requests from
+    /// orgs that exceed their budget.
+    pub fn with_rate_limiter(mut self, rate_limiter: OrgRateLimiter) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Apply the [`RequestLimits`] received from `limits`, replacing the
+    /// request size & simultaneous request limits passed to
+    /// [`HttpDelegate::new()`], and the budgets of any rate limiter.
+    ///
+    /// Limits sent to the channel are applied to subsequent requests without
+    /// restarting the router - in-flight requests are unaffected. Lowering
+    /// the simultaneous request limit takes full effect as in-flight requests
+    /// complete.
+    pub fn with_limits(mut self, limits: watch::Receiver<RequestLimits>) -> Self {
+        let current = *limits.borrow();
+        self.request_sem.resize(current.max_requests);
+        self.rate_limiter
+            .get_or_insert_with(|| OrgRateLimiter::new(None, None))
+            .set_rates(current.lines_per_second, current.bytes_per_second);
+
+        self.limits = limits;
+        *self.applied_limits.get_mut() = current;
+        self
+    }
+
+    /// Derive the namespace of each write & delete request from its
+    /// credentials using `lookup`, ignoring any org & bucket (or db & rp)
+    /// parameters of the request.
+    ///
+    /// Requests without credentials, or with credentials not bound to a
+    /// namespace, are rejected.
+    pub fn with_token_namespaces(mut self, lookup: Arc<dyn TokenNamespaceLookup>) -> Self {
+        self.token_namespaces = Some(lookup);
+        self
+    }
+
+    /// Permit cross-origin requests from browser-based clients according to
+    /// `policy`.
+    pub fn with_cors(mut self, policy: CorsPolicy) -> Self {
+        self.cors = Some(policy);
+        self
+    }
+
+    /// Record each successfully routed write & delete in `log`.
+    pub fn with_audit_log(mut self, log: Arc<AuditLog>) -> Self {
+        self.audit = Some(log);
+        self
+    }
+
+    /// Answer retried writes carrying the idempotency key (the
+    /// `Idempotency-Key` or `X-Request-Id` header) of a previously completed
+    /// write to the same namespace with the write token of the original
+    /// write, rather than writing the data again.
+    pub fn with_idempotency_cache(mut self, cache: IdempotencyCache) -> Self {
+        self.idempotency = Some(cache);
+        self
+    }
+
+    /// Abort requests that are not completed within `timeout`, returning a
+    /// [`Error::Timeout`] to the client.
+    ///
+    /// The timeout covers the entire request, from reading the body to the
+    /// completion of the DML handler - an aborted request releases its share
+    /// of the request limit, so requests stuck on an unresponsive downstream
+    /// cannot exhaust it.
+    pub fn with_request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Limit each org to at most `max_requests` of the simultaneous requests
+    /// serviced by the router, so that a burst of requests from one org
+    /// cannot starve the others.
+    ///
+    /// Requests from an org at its limit are rejected with
+    /// [`Error::OrgRequestLimit`].
+    pub fn with_max_requests_per_org(mut self, max_requests: usize) -> Self {
+        self.org_concurrency = Some(OrgConcurrencyLimiter::new(max_requests));
+        self
+    }
+
+    /// Limit the total (estimated, decoded) size of the request bodies
+    /// serviced simultaneously to `bytes`, in addition to the limit on the
+    /// number of simultaneous requests.
+    ///
+    /// The size of each request is estimated from its `Content-Length` before
+    /// the body is read - requests of unknown size are assumed to be of the
+    /// maximum permitted size.
+    pub fn with_request_byte_budget(mut self, bytes: usize) -> Self {
+        self.byte_budget = Some(ByteBudget::new(bytes));
+        self
+    }
+
+    /// Record the write metrics (such as `http_write_lines_total`) with a
+    /// `namespace` attribute, allowing the write volume of each namespace to
+    /// be observed.
+    ///
+    /// At most `max_namespaces` distinct namespaces are recorded - writes to
+    /// any further namespaces are recorded against the `<other>` attribute
+    /// value, bounding the cardinality of the metrics.
+    pub fn with_namespace_write_metrics(mut self, max_namespaces: usize) -> Self {
+        self.write_metrics.set_max_namespaces(max_namespaces);
+        self
+    }
+
+    /// Reject writes containing more than `max_lines` lines (or, for formats
+    /// other than line protocol, points).
+    pub fn with_max_lines(mut self, max_lines: usize) -> Self {
+        self.line_limits.max_lines = Some(max_lines);
+        self
+    }
+
+    /// Reject writes containing a line (or point) with more than
+    /// `max_fields` fields.
+    pub fn with_max_fields_per_line(mut self, max_fields: usize) -> Self {
+        self.line_limits.max_fields_per_line = Some(max_fields);
+        self
+    }
+
+    /// Reject writes containing a line (or point) with more than `max_tags`
+    /// tags.
+    pub fn with_max_tags_per_line(mut self, max_tags: usize) -> Self {
+        self.line_limits.max_tags_per_line = Some(max_tags);
+        self
+    }
+
+    /// Map the metric paths of Graphite writes to measurements, fields & tags
+    /// using `templates`, rather than writing each path to a measurement of
+    /// the same name.
+    pub fn with_graphite_templates(mut self, templates: GraphiteTemplates) -> Self {
+        self.graphite = templates;
+        self
+    }
+
+    /// Reject writes containing points with timestamps outside the
+    /// [`TimeWindow`] of the namespace written to, as configured in
+    /// `windows`.
+    ///
+    /// Writes are all-or-nothing - a single point outside the window fails
+    /// the entire write with [`Error::TimestampOutOfBounds`].
+    pub fn with_time_windows(mut self, windows: TimeWindows) -> Self {
+        self.time_windows = windows;
+        self
+    }
+
+    /// Configure whether request bodies are verified against the digest in
+    /// the `Content-Digest` (sha-256 or sha-512) or `Content-MD5` header, if
+    /// the client supplies one.
+    ///
+    /// Requests whose body does not match the digest are rejected before any
+    /// of the body is processed.
+    pub fn with_digest_verification(mut self, enabled: bool) -> Self {
+        self.verify_digests = enabled;
+        self
+    }
+
+    /// Configure whether invalid lines of line protocol writes are skipped by
+    /// default, writing the remaining lines and returning the details of the
+    /// skipped lines to the client.
+    ///
+    /// Requests may override this default with the `partial` query parameter.
+    pub fn with_partial_writes(mut self, enabled: bool) -> Self {
+        self.partial_writes = enabled;
+        self
+    }
+
+    /// Configure whether the [`Provenance`] of each write & delete request
+    /// (the client agent and originating host, from the request headers) is
+    /// attached to the DML operations it produces as key/value annotations.
+    pub fn with_provenance_annotations(mut self, enabled: bool) -> Self {
+        self.provenance = enabled;
+        self
+    }
+
+    /// Fail health checks while any write buffer shard in `health` is
+    /// unreachable, so that load balancers stop routing writes to this
+    /// router.
+    pub fn with_write_buffer_health(mut self, health: Arc<WriteBufferHealth>) -> Self {
+        self.write_buffer_health = Some(health);
+        self
+    }
+
+    /// Account the lines & bytes of each successful write to its namespace
+    /// in `accountant`.
+    pub fn with_usage_accounting(mut self, accountant: Arc<UsageAccountant>) -> Self {
+        self.usage = Some(accountant);
+        self
+    }
+}
+
 impl<D, T> HttpDelegate<D, T>
 where
-    D: DmlHandler<WriteInput = HashMap<String, MutableBatch>, WriteOutput = WriteSummary>,
+    D: DmlHandler<
+        WriteInput = HashMap<String, MutableBatch>,
+        WriteOutput = WriteSummary,
+        DeleteOutput = WriteSummary,
+    >,
     T: TimeProvider,
 {
     /// Routes `req` to the appropriate handler, if any, returning the handler
     /// response.
     pub async fn route(&self, req: Request<Body>) -> Result<Response<Body>, Error> {
+        // CORS preflight requests are answered without consuming any request
+        // capacity.
+        if let Some(response) = self.cors.as_ref().and_then(|c| c.preflight(&req)) {
+            return Ok(response);
+        }
+        // As are the probes of InfluxDB clients & health checkers, so that
+        // they succeed while the router is under load.
+        if let Some(response) = probe_response(&req, || self.unhealthy_shards()) {
+            return Ok(response);
+        }
+        let cors_headers = self.cors_headers(req.headers());
+        self.reload_limits();
+
         // Acquire and hold a permit for the duration of this request, or return
         // a 503 if the existing requests have already exhausted the allocation.
         //
@@ -324,825 +1118,2105 @@ where
         // is read/decompressed, this limit can efficiently shed load to avoid
         // unnecessary memory pressure (the resource this request limit usually
         // aims to protect.)
+        //
+        // The byte budget (if any) is weighted by the size of the request, so
+        // that a few large requests count as much as many small requests.
+        let _byte_permit = match &self.byte_budget {
+            Some(budget) => {
+                let bytes = estimate_body_size(req.headers(), self.max_request_bytes());
+                match budget.try_acquire(bytes) {
+                    Some(p) => Some(p),
+                    None => {
+                        error!(
+                            bytes,
+                            in_use = budget.in_use(),
+                            "simultaneous request byte budget exceeded - dropping request"
+                        );
+                        self.byte_budget_rejected.inc(1);
+                        return Err(self.request_limit_error());
+                    }
+                }
+            }
+            None => None,
+        };
         let _permit = match self.request_sem.try_acquire() {
-            Ok(p) => p,
-            Err(TryAcquireError::NoPermits) => {
+            Some(p) => p,
+            None => {
                 error!("simultaneous request limit exceeded - dropping request");
                 self.request_limit_rejected.inc(1);
-                return Err(Error::RequestLimit);
+                return Err(self.request_limit_error());
             }
-            Err(e) => panic!("request limiter error: {}", e),
         };
 
-        // Route the request to a handler.
-        match (req.method(), req.uri().path()) {
-            (&Method::POST, "/api/v2/write") => self.write_handler(req).await,
-            (&Method::POST, "/api/v2/delete") => self.delete_handler(req).await,
-            _ => return Err(Error::NoHandler),
+        // If the client disconnects, hyper drops this future - aborting the
+        // request (including any in-progress DML handler call) and recording
+        // the abort when the guard is dropped.
+        let abort_guard = AbortGuard::new(&self.client_aborts);
+
+        let start_instant = Instant::now();
+        let res = match self.request_timeout {
+            // Dropping the handler future on timeout aborts the request,
+            // including any in-progress DML handler call.
+            Some(timeout) => match tokio::time::timeout(timeout, self.handle(req)).await {
+                Ok(res) => res,
+                Err(_) => {
+                    warn!(?timeout, "request timed out - aborting request");
+                    self.request_timeouts.inc(1);
+                    Err(Error::Timeout(timeout))
+                }
+            },
+            None => self.handle(req).await,
+        };
+        self.request_latency.observe(start_instant.elapsed());
+
+        abort_guard.disarm();
+        if let Err(Error::ClientHangup(_)) = &res {
+            debug!("client disconnected while sending request body");
+            self.client_aborts.inc(1);
         }
-        .map(|summary| {
-            Response::builder()
-                .status(StatusCode::NO_CONTENT)
-                .header(WRITE_TOKEN_HTTP_HEADER, summary.to_token())
-                .body(Body::empty())
-                .unwrap()
+
+        res.map(|mut response| {
+            response.headers_mut().extend(cors_headers);
+            response
         })
     }
 
-    async fn write_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
-        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
+    /// Returns an InfluxDB 2.x compatible health check response, failing if
+    /// any write buffer shard is unreachable.
+    pub fn health(&self) -> Response<Body> {
+        health_response(&self.unhealthy_shards())
+    }
 
-        let write_info = WriteInfo::try_from(&req)?;
-        let namespace = org_and_bucket_to_database(&write_info.org, &write_info.bucket)
-            .map_err(OrgBucketError::MappingFail)?;
+    /// Returns the write buffer shards that were unreachable when last probed,
+    /// if write buffer health is tracked.
+    fn unhealthy_shards(&self) -> Vec<ShardIndex> {
+        self.write_buffer_health
+            .as_ref()
+            .map(|h| h.unhealthy_shards())
+            .unwrap_or_default()
+    }
 
-        trace!(org=%write_info.org, bucket=%write_info.bucket, %namespace, "processing write request");
+    /// Returns the CORS headers to include in the response to a request with
+    /// `headers` (including error responses), if any.
+    pub fn cors_headers(&self, headers: &HeaderMap) -> HeaderMap {
+        self.cors
+            .as_ref()
+            .map(|c| c.response_headers(headers))
+            .unwrap_or_default()
+    }
 
-        // Read the HTTP body and convert it to a str.
-        let body = self.read_body(req).await?;
-        let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
+    /// Returns the current maximum request body size.
+    fn max_request_bytes(&self) -> usize {
+        self.limits.borrow().max_request_bytes
+    }
 
-        // The time, in nanoseconds since the epoch, to assign to any points that don't
-        // contain a timestamp
-        let default_time = self.time_provider.now().timestamp_nanos();
-        let start_instant = Instant::now();
+    /// Apply any change to the simultaneous request & rate limits since they
+    /// were last applied.
+    fn reload_limits(&self) {
+        let limits = *self.limits.borrow();
+        let mut applied = self.applied_limits.lock();
+        if *applied == limits {
+            return;
+        }
 
-        let mut converter = LinesConverter::new(default_time);
-        converter.set_timestamp_base(write_info.precision.timestamp_base());
-        let (batches, stats) = match converter.write_lp(body).and_then(|_| converter.finish()) {
-            Ok(v) => v,
-            Err(mutable_batch_lp::Error::EmptyPayload) => {
-                debug!("nothing to write");
-                return Ok(WriteSummary::default());
+        info!(?limits, "applying updated request limits");
+        if limits.max_requests != applied.max_requests {
+            self.request_sem.resize(limits.max_requests);
+        }
+        if (limits.lines_per_second, limits.bytes_per_second)
+            != (applied.lines_per_second, applied.bytes_per_second)
+        {
+            if let Some(limiter) = &self.rate_limiter {
+                limiter.set_rates(limits.lines_per_second, limits.bytes_per_second);
             }
-            Err(e) => return Err(Error::ParseLineProtocol(e)),
-        };
+        }
+        *applied = limits;
+    }
 
-        let num_tables = batches.len();
-        let duration = start_instant.elapsed();
-        self.http_line_protocol_parse_duration.record(duration);
-        debug!(
-            num_lines=stats.num_lines,
-            num_fields=stats.num_fields,
-            num_tables,
-            precision=?write_info.precision,
-            body_size=body.len(),
-            %namespace,
-            org=%write_info.org,
-            bucket=%write_info.bucket,
-            duration=?duration,
-            "routing write",
-        );
+    /// Build the error returned when a request is rejected due to overload.
+    fn request_limit_error(&self) -> Error {
+        Error::RequestLimit {
+            in_flight: self.request_sem.in_flight(),
+            // Existing requests are expected to complete (releasing their
+            // permits) in roughly the average request latency.
+            retry_after: self.request_latency.get().unwrap_or(DEFAULT_RETRY_AFTER),
+        }
+    }
 
-        let summary = self
-            .dml_handler
-            .write(&namespace, batches, span_ctx)
-            .await
-            .map_err(Into::into)?;
+    /// Route `req` to the appropriate handler, if any, returning the handler
+    /// response.
+    async fn handle(&self, req: Request<Body>) -> Result<Response<Body>, Error> {
+        match (req.method(), req.uri().path()) {
+            (&Method::POST, "/api/v2/write") => {
+                return self.write_handler(req).await.map(lp_write_response)
+            }
+            (&Method::POST, "/api/v2/delete") => self.delete_handler(req).await,
+            (&Method::POST, "/write") => {
+                return self.v1_write_handler(req).await.map(lp_write_response)
+            }
+            (&Method::POST, "/api/v1/prom/write") => self.prom_write_handler(req).await,
+            (&Method::POST, "/api/v2/write/graphite") => self.graphite_write_handler(req).await,
+            (&Method::POST, "/v1/metrics") => {
+                // OTLP/HTTP requires a 200 response containing an (empty)
+                // protobuf-encoded ExportMetricsServiceResponse.
+                return self.otlp_metrics_handler(req).await.map(|summary| {
+                    Response::builder()
+                        .status(StatusCode::OK)
+                        .header(CONTENT_TYPE, OTLP_PROTOBUF_CONTENT_TYPE)
+                        .header(WRITE_TOKEN_HTTP_HEADER, summary.to_token())
+                        .body(Body::from(
+                            ExportMetricsServiceResponse::default().encode_to_vec(),
+                        ))
+                        .unwrap()
+                });
+            }
+            _ => return Err(Error::NoHandler),
+        }
+        .map(write_response)
+    }
 
-        self.write_metric_lines.inc(stats.num_lines as _);
-        self.write_metric_fields.inc(stats.num_fields as _);
-        self.write_metric_tables.inc(num_tables as _);
-        self.write_metric_body_size.inc(body.len() as _);
+    async fn write_handler(&self, req: Request<Body>) -> Result<LpWriteOutcome, Error> {
+        let target = self.v2_target(&req).await?;
 
-        Ok(summary)
+        trace!(org=%target.org, namespace=%target.namespace, "processing write request");
+
+        if is_json(req.headers()) {
+            return self
+                .write_json(req, target.namespace, &target.org, target.precision)
+                .await;
+        }
+
+        let partial = target.partial.unwrap_or(self.partial_writes);
+        self.write_lp(
+            req,
+            target.namespace,
+            &target.org,
+            target.precision,
+            partial,
+        )
+        .await
     }
 
-    async fn delete_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
-        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
+    /// Handle an InfluxDB 1.x compatible write request.
+    async fn v1_write_handler(&self, req: Request<Body>) -> Result<LpWriteOutcome, Error> {
+        let target = self.v1_target(&req).await?;
+
+        trace!(org=%target.org, namespace=%target.namespace, "processing v1 write request");
+
+        let partial = target.partial.unwrap_or(self.partial_writes);
+        self.write_lp(
+            req,
+            target.namespace,
+            &target.org,
+            target.precision,
+            partial,
+        )
+        .await
+    }
 
-        let account = WriteInfo::try_from(&req)?;
-        let namespace = org_and_bucket_to_database(&account.org, &account.bucket)
-            .map_err(OrgBucketError::MappingFail)?;
+    /// Write the JSON-encoded points in the body of `req` to `namespace`,
+    /// charging the write to the rate limit budget of `org`.
+    ///
+    /// Unlike line protocol, JSON writes are all-or-nothing.
+    async fn write_json(
+        &self,
+        req: Request<Body>,
+        namespace: DatabaseName<'static>,
+        org: &str,
+        precision: Precision,
+    ) -> Result<LpWriteOutcome, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
 
-        trace!(org=%account.org, bucket=%account.bucket, %namespace, "processing delete request");
+        let admitted = match self.admit_write(req.headers(), &namespace, org).await? {
+            Admission::Write(admitted) => admitted,
+            Admission::Duplicate(summary) => return Ok(LpWriteOutcome::duplicate(summary)),
+        };
 
-        // Read the HTTP body and convert it to a str.
         let body = self.read_body(req).await?;
-        let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
-
-        // Parse and extract table name (which can be empty), start, stop, and predicate
-        let parsed_delete = parse_http_delete_request(body)?;
-        let predicate = parse_delete_predicate(
-            &parsed_delete.start_time,
-            &parsed_delete.stop_time,
-            &parsed_delete.predicate,
+        let default_time = self.time_provider.now().timestamp_nanos();
+        let (batches, stats) = json_to_batches(
+            &body,
+            default_time,
+            precision.timestamp_base(),
+            &self.line_limits,
         )?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(LpWriteOutcome {
+                summary: WriteSummary::default(),
+                num_lines: 0,
+                rejected: vec![],
+            });
+        }
 
         debug!(
-            table_name=%parsed_delete.table_name,
-            predicate = %parsed_delete.predicate,
-            start=%parsed_delete.start_time,
-            stop=%parsed_delete.stop_time,
+            num_points=stats.num_points,
+            num_fields=stats.num_fields,
+            num_tables=batches.len(),
+            ?precision,
             body_size=body.len(),
             %namespace,
-            org=%account.org,
-            bucket=%account.bucket,
-            "routing delete"
+            "routing json write",
         );
 
-        self.dml_handler
-            .delete(
-                &namespace,
-                parsed_delete.table_name.as_str(),
-                &predicate,
+        // Each point is the equivalent of a line of line protocol.
+        let summary = self
+            .dispatch_write(
+                namespace,
+                org,
+                batches,
+                stats.num_points,
+                stats.num_fields,
+                body.len(),
+                admitted,
                 span_ctx,
             )
-            .await
-            .map_err(Into::into)?;
+            .await?;
 
-        self.delete_metric_body_size.inc(body.len() as _);
-
-        // TODO pass back write summaries for deletes as well
-        // https://github.com/influxdata/influxdb_iox/issues/4209
-        Ok(WriteSummary::default())
+        Ok(LpWriteOutcome {
+            summary,
+            num_lines: stats.num_points,
+            rejected: vec![],
+        })
     }
 
-    /// Parse the request's body into raw bytes, applying the configured size
-    /// limits and decoding any content encoding.
-    async fn read_body(&self, req: hyper::Request<Body>) -> Result<Bytes, Error> {
-        let encoding = req
-            .headers()
-            .get(&CONTENT_ENCODING)
-            .map(|v| v.to_str().map_err(Error::NonUtf8ContentHeader))
-            .transpose()?;
-        let ungzip = match encoding {
-            None => false,
-            Some("gzip") => true,
-            Some(v) => return Err(Error::InvalidContentEncoding(v.to_string())),
+    /// Write the line protocol body of `req` to `namespace`, charging the
+    /// write to the rate limit budget of `org`.
+    ///
+    /// If `partial` is true, invalid lines are skipped and returned in the
+    /// [`LpWriteOutcome`] rather than failing the entire write.
+    async fn write_lp(
+        &self,
+        req: Request<Body>,
+        namespace: DatabaseName<'static>,
+        org: &str,
+        precision: Precision,
+        partial: bool,
+    ) -> Result<LpWriteOutcome, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
+
+        let admitted = match self.admit_write(req.headers(), &namespace, org).await? {
+            Admission::Write(admitted) => admitted,
+            Admission::Duplicate(summary) => return Ok(LpWriteOutcome::duplicate(summary)),
         };
 
-        let mut payload = req.into_body();
+        let encoding = content_encoding(req.headers())?;
+        let digest = self.body_digest(req.headers())?;
+        let mut body = DecodedBody::new(req.into_body(), encoding, self.max_request_bytes())?
+            .with_digest(digest)
+            .with_metrics(self.body_metrics.clone());
 
-        let mut body = BytesMut::new();
-        while let Some(chunk) = payload.next().await {
-            let chunk = chunk.map_err(Error::ClientHangup)?;
-            // limit max size of in-memory payload
-            if (body.len() + chunk.len()) > self.max_request_bytes {
-                return Err(Error::RequestSizeExceeded(self.max_request_bytes));
+        // The time, in nanoseconds since the epoch, to assign to any points that don't
+        // contain a timestamp
+        let default_time = self.time_provider.now().timestamp_nanos();
+
+        let mut converter = LinesConverter::new(default_time);
+        converter.set_timestamp_base(precision.timestamp_base());
+        converter.set_skip_invalid_lines(partial);
+        self.line_limits.apply(&mut converter);
+
+        // Parse the complete lines of the body as it is received, rather than
+        // buffering the entire body before parsing it.
+        let mut lines = LineBuffer::default();
+        let mut body_size = 0;
+        let mut duration = Duration::ZERO;
+        loop {
+            let (lp, done) = match body.next().await? {
+                Some(chunk) => {
+                    body_size += chunk.len();
+                    (lines.push(&chunk).map_err(Error::NonUtf8Body)?, false)
+                }
+                None => (Some(lines.finish().map_err(Error::NonUtf8Body)?), true),
+            };
+
+            if let Some(lp) = lp {
+                let start_instant = Instant::now();
+                converter.write_lp(&lp).map_err(lp_error)?;
+                duration += start_instant.elapsed();
             }
-            body.extend_from_slice(&chunk);
-        }
-        let body = body.freeze();
 
-        // If the body is not compressed, return early.
-        if !ungzip {
-            return Ok(body);
+            if done {
+                break;
+            }
         }
 
-        // Unzip the gzip-encoded content
-        use std::io::Read;
-        let decoder = flate2::read::GzDecoder::new(&body[..]);
+        let rejected = converter
+            .rejected_lines()
+            .iter()
+            .map(RejectedLine::from)
+            .collect::<Vec<_>>();
+        if !rejected.is_empty() {
+            debug!(num_rejected = rejected.len(), %namespace, "skipped invalid lines");
+        }
 
-        // Read at most max_request_bytes bytes to prevent a decompression bomb
-        // based DoS.
-        //
-        // In order to detect if the entire stream ahs been read, or truncated,
-        // read an extra byte beyond the limit and check the resulting data
-        // length - see the max_request_size_truncation test.
-        let mut decoder = decoder.take(self.max_request_bytes as u64 + 1);
-        let mut decoded_data = Vec::new();
-        decoder
-            .read_to_end(&mut decoded_data)
-            .map_err(Error::InvalidGzip)?;
+        let (batches, stats) = match converter.finish() {
+            Ok(v) => v,
+            Err(mutable_batch_lp::Error::EmptyPayload) => {
+                debug!("nothing to write");
+                return Ok(LpWriteOutcome {
+                    summary: WriteSummary::default(),
+                    num_lines: 0,
+                    rejected,
+                });
+            }
+            Err(e) => return Err(lp_error(e)),
+        };
 
-        // If the length is max_size+1, the body is at least max_size+1 bytes in
-        // length, and possibly longer, but truncated.
-        if decoded_data.len() > self.max_request_bytes {
-            return Err(Error::RequestSizeExceeded(self.max_request_bytes));
-        }
+        self.http_line_protocol_parse_duration.record(duration);
+        debug!(
+            num_lines=stats.num_lines,
+            num_fields=stats.num_fields,
+            num_tables=batches.len(),
+            ?precision,
+            body_size,
+            %namespace,
+            duration=?duration,
+            "routing write",
+        );
 
-        Ok(decoded_data.into())
+        self.dispatch_write(
+            namespace,
+            org,
+            batches,
+            stats.num_lines,
+            stats.num_fields,
+            body_size,
+            admitted,
+            span_ctx,
+        )
+        .await
+        .map(|summary| LpWriteOutcome {
+            summary,
+            num_lines: stats.num_lines,
+            rejected,
+        })
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{io::Write, iter, sync::Arc, time::Duration};
+    /// Handle a Prometheus remote write request.
+    ///
+    /// The request body is a snappy-compressed protobuf `WriteRequest`, as
+    /// described in the [remote write specification].
+    ///
+    /// [remote write specification]: https://prometheus.io/docs/concepts/remote_write_spec/
+    async fn prom_write_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
 
-    use assert_matches::assert_matches;
+        let RequestTarget { namespace, org, .. } = self.v1_target(&req).await?;
 
-    use flate2::{write::GzEncoder, Compression};
-    use hyper::header::HeaderValue;
-    use metric::{Attributes, Metric};
-    use mutable_batch::column::ColumnData;
-    use mutable_batch_lp::LineWriteError;
-    use test_helpers::timeout::FutureTimeout;
-    use tokio_stream::wrappers::ReceiverStream;
+        trace!(%org, %namespace, "processing prometheus write request");
 
-    use crate::dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall};
+        let admitted = match self.admit_write(req.headers(), &namespace, &org).await? {
+            Admission::Write(admitted) => admitted,
+            Admission::Duplicate(summary) => return Ok(summary),
+        };
 
-    use super::*;
+        // The body is always snappy-compressed, regardless of any
+        // Content-Encoding header.
+        let body = self.read_raw_body(req).await?;
+        let decoded_len = snap::raw::decompress_len(&body).map_err(PromWriteError::from)?;
+        let max_request_bytes = self.max_request_bytes();
+        if decoded_len > max_request_bytes {
+            self.body_metrics
+                .record_decompression_limit(ContentEncoding::Snappy);
+            return Err(Error::RequestSizeExceeded(max_request_bytes));
+        }
+        let encoded_len = body.len();
+        let body = snap::raw::Decoder::new()
+            .decompress_vec(&body)
+            .map_err(PromWriteError::from)?;
+        self.body_metrics
+            .record(ContentEncoding::Snappy, encoded_len, body.len());
+
+        let write_request = WriteRequest::decode(body.as_slice()).map_err(PromWriteError::from)?;
+        let (batches, num_samples) = write_request_to_batches(write_request, &self.line_limits)?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(WriteSummary::default());
+        }
 
-    const MAX_BYTES: usize = 1024;
+        debug!(
+            num_samples,
+            num_tables=batches.len(),
+            body_size=body.len(),
+            %namespace,
+            "routing prometheus write",
+        );
 
-    fn summary() -> WriteSummary {
-        WriteSummary::default()
+        // Each sample is the equivalent of a line of line protocol with a
+        // single field.
+        self.dispatch_write(
+            namespace,
+            &org,
+            batches,
+            num_samples,
+            num_samples,
+            body.len(),
+            admitted,
+            span_ctx,
+        )
+        .await
     }
 
-    fn assert_metric_hit(metrics: &metric::Registry, name: &'static str, value: Option<u64>) {
-        let counter = metrics
-            .get_instrument::<Metric<U64Counter>>(name)
-            .expect("failed to read metric")
-            .get_observer(&Attributes::from(&[]))
-            .expect("failed to get observer")
-            .fetch();
+    /// Handle a write of Graphite plaintext protocol lines, mapping each metric
+    /// path according to the configured [`GraphiteTemplates`].
+    async fn graphite_write_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
 
-        if let Some(want) = value {
-            assert_eq!(want, counter, "metric does not have expected value");
-        } else {
-            assert!(counter > 0, "metric {} did not record any values", name);
+        let RequestTarget { namespace, org, .. } = self.v2_target(&req).await?;
+
+        trace!(%org, %namespace, "processing graphite write request");
+
+        let admitted = match self.admit_write(req.headers(), &namespace, &org).await? {
+            Admission::Write(admitted) => admitted,
+            Admission::Duplicate(summary) => return Ok(summary),
+        };
+
+        let body = self.read_body(req).await?;
+        let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
+
+        let default_time = self.time_provider.now().timestamp_nanos();
+        let (batches, num_points) =
+            graphite_to_batches(body, &self.graphite, default_time, &self.line_limits)?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(WriteSummary::default());
         }
+
+        debug!(
+            num_points,
+            num_tables=batches.len(),
+            body_size=body.len(),
+            %namespace,
+            %org,
+            "routing graphite write",
+        );
+
+        // Each line is the equivalent of a line of line protocol with a single
+        // field.
+        self.dispatch_write(
+            namespace,
+            &org,
+            batches,
+            num_points,
+            num_points,
+            body.len(),
+            admitted,
+            span_ctx,
+        )
+        .await
     }
 
-    // Generate two HTTP handler tests - one for a plain request and one with a
-    // gzip-encoded body (and appropriate header), asserting the handler return
-    // value & write op.
-    macro_rules! test_http_handler {
-        (
-            $name:ident,
-            uri = $uri:expr,                                // Request URI
-            body = $body:expr,                              // Request body content
-            dml_write_handler = $dml_write_handler:expr,    // DML write handler response (if called)
-            dml_delete_handler = $dml_delete_handler:expr,  // DML delete handler response (if called)
-            want_result = $want_result:pat,                 // Expected handler return value (as pattern)
-            want_dml_calls = $($want_dml_calls:tt )+        // assert_matches slice pattern for expected DML calls
-        ) => {
-            // Generate the two test cases by feed the same inputs, but varying
-            // the encoding.
-            test_http_handler!(
-                $name,
-                encoding=plain,
-                uri = $uri,
-                body = $body,
-                dml_write_handler = $dml_write_handler,
-                dml_delete_handler = $dml_delete_handler,
-                want_result = $want_result,
-                want_dml_calls = $($want_dml_calls)+
-            );
-            test_http_handler!(
-                $name,
-                encoding=gzip,
-                uri = $uri,
-                body = $body,
-                dml_write_handler = $dml_write_handler,
-                dml_delete_handler = $dml_delete_handler,
-                want_result = $want_result,
-                want_dml_calls = $($want_dml_calls)+
-            );
-        };
-        // Actual test body generator.
-        (
-            $name:ident,
-            encoding = $encoding:tt,
-            uri = $uri:expr,
-            body = $body:expr,
-            dml_write_handler = $dml_write_handler:expr,
-            dml_delete_handler = $dml_delete_handler:expr,
-            want_result = $want_result:pat,
-            want_dml_calls = $($want_dml_calls:tt )+
-        ) => {
-            paste::paste! {
-                #[tokio::test]
-                async fn [<test_http_handler_ $name _ $encoding>]() {
-                    let body = $body;
+    /// Handle an OTLP/HTTP metrics export request.
+    ///
+    /// The request body is a protobuf-encoded `ExportMetricsServiceRequest`, as
+    /// described in the [OTLP specification] - the JSON encoding is not
+    /// supported.
+    ///
+    /// [OTLP specification]: https://opentelemetry.io/docs/reference/specification/protocol/otlp/#otlphttp
+    async fn otlp_metrics_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
 
-                    // Optionally generate a fragment of code to encode the body
-                    let body = test_http_handler!(encoding=$encoding, body);
+        let RequestTarget { namespace, org, .. } = self.v2_target(&req).await?;
 
-                    #[allow(unused_mut)]
-                    let mut request = Request::builder()
-                        .uri($uri)
-                        .method("POST")
-                        .body(Body::from(body))
-                        .unwrap();
+        trace!(%org, %namespace, "processing otlp metrics request");
 
-                    // Optionally modify request to account for the desired
-                    // encoding
-                    test_http_handler!(encoding_header=$encoding, request);
+        let content_type = req
+            .headers()
+            .get(&CONTENT_TYPE)
+            .map(|v| v.to_str().unwrap_or_default());
+        match content_type {
+            None | Some(OTLP_PROTOBUF_CONTENT_TYPE) => {}
+            Some(v) => return Err(OtlpError::UnsupportedContentType(v.to_string()).into()),
+        }
 
-                    let dml_handler = Arc::new(MockDmlHandler::default()
-                        .with_write_return($dml_write_handler)
-                        .with_delete_return($dml_delete_handler)
-                    );
-                    let metrics = Arc::new(metric::Registry::default());
-                    let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+        let admitted = match self.admit_write(req.headers(), &namespace, &org).await? {
+            Admission::Write(admitted) => admitted,
+            Admission::Duplicate(summary) => return Ok(summary),
+        };
 
-                    let got = delegate.route(request).await;
-                    assert_matches!(got, $want_result);
+        let body = self.read_body(req).await?;
+        let export_request =
+            ExportMetricsServiceRequest::decode(body.as_ref()).map_err(OtlpError::from)?;
+        let default_time = self.time_provider.now().timestamp_nanos();
+        let (batches, stats) =
+            export_request_to_batches(export_request, default_time, &self.line_limits)?;
+        if batches.is_empty() {
+            debug!("nothing to write");
+            return Ok(WriteSummary::default());
+        }
 
-                    // All successful responses should have a NO_CONTENT code
-                    // and metrics should be recorded.
-                    if let Ok(v) = got {
-                        assert_eq!(v.status(), StatusCode::NO_CONTENT);
-                        if $uri.contains("/api/v2/write") {
-                            assert_metric_hit(&metrics, "http_write_lines_total", None);
-                            assert_metric_hit(&metrics, "http_write_fields_total", None);
-                            assert_metric_hit(&metrics, "http_write_tables_total", None);
-                            assert_metric_hit(&metrics, "http_write_body_bytes_total", Some($body.len() as _));
-                        } else {
-                            assert_metric_hit(&metrics, "http_delete_body_bytes_total", Some($body.len() as _));
-                        }
-                    }
+        debug!(
+            num_points=stats.num_points,
+            num_fields=stats.num_fields,
+            num_tables=batches.len(),
+            body_size=body.len(),
+            %namespace,
+            %org,
+            "routing otlp metrics write",
+        );
 
-                    let calls = dml_handler.calls();
-                    assert_matches!(calls.as_slice(), $($want_dml_calls)+);
+        // Each data point is the equivalent of a line of line protocol.
+        self.dispatch_write(
+            namespace,
+            &org,
+            batches,
+            stats.num_points,
+            stats.num_fields,
+            body.len(),
+            admitted,
+            span_ctx,
+        )
+        .await
+    }
+
+    /// Ensure a write to `namespace` on behalf of `org` is authorized, and that
+    /// `org` has not exhausted its rate limit budget.
+    ///
+    /// If the write duplicates a previously completed write (by idempotency
+    /// key), the summary of the original write is returned in an
+    /// [`Admission::Duplicate`], and the write should not be performed.
+    ///
+    /// This is called before the request body is read, so that rejected
+    /// requests cost as little as possible.
+    async fn admit_write(
+        &self,
+        headers: &HeaderMap,
+        namespace: &DatabaseName<'static>,
+        org: &str,
+    ) -> Result<Admission, Error> {
+        self.authorize(headers, namespace, Permission::Write)
+            .await?;
+
+        // Retries of completed writes are answered before applying the rate
+        // limit, as they write nothing.
+        let idempotency_key = match &self.idempotency {
+            Some(cache) => match IdempotencyKey::from_headers(headers, namespace)? {
+                Some(key) => {
+                    if let Some(summary) = cache.get(&key, self.time_provider.now()) {
+                        debug!(%namespace, "duplicate write - returning previous write summary");
+                        self.duplicate_writes.inc(1);
+                        return Ok(Admission::Duplicate(summary));
+                    }
+                    Some(key)
                 }
-            }
+                None => None,
+            },
+            None => None,
         };
-        (encoding=plain, $body:ident) => {
-            $body
+
+        let org_permit = self.acquire_org_permit(org)?;
+
+        if let Some(limiter) = &self.rate_limiter {
+            if let Some(retry_after) = limiter.check(org, self.time_provider.now()) {
+                debug!(%org, ?retry_after, "org rate limit exceeded - dropping request");
+                self.rate_limit_rejected.inc(1);
+                return Err(Error::RateLimited {
+                    org: org.to_string(),
+                    retry_after,
+                });
+            }
+        }
+
+        Ok(Admission::Write(AdmittedWrite {
+            idempotency_key,
+            principal: self.audit_principal(headers),
+            provenance: self.provenance(headers),
+            _org_permit: org_permit,
+        }))
+    }
+
+    /// Acquire a permit for a request from `org` if a per-org request limit
+    /// is configured, to be held for the remainder of the request.
+    fn acquire_org_permit(&self, org: &str) -> Result<Option<OrgPermit>, Error> {
+        let limiter = match &self.org_concurrency {
+            Some(v) => v,
+            None => return Ok(None),
         };
-        (encoding=gzip, $body:ident) => {{
-            // Apply gzip compression to the body
-            let mut e = GzEncoder::new(Vec::new(), Compression::default());
-            e.write_all(&$body).unwrap();
-            e.finish().expect("failed to compress test body")
-        }};
-        (encoding_header=plain, $request:ident) => {};
-        (encoding_header=gzip, $request:ident) => {{
-            // Set the gzip content encoding
-            $request
-                .headers_mut()
-                .insert(CONTENT_ENCODING, HeaderValue::from_static("gzip"));
-        }};
+
+        match limiter.try_acquire(org) {
+            Some(p) => Ok(Some(p)),
+            None => {
+                debug!(%org, "org simultaneous request limit exceeded - dropping request");
+                self.org_request_limit_rejected.inc(1);
+                Err(Error::OrgRequestLimit {
+                    org: org.to_string(),
+                    limit: limiter.max_per_org(),
+                    retry_after: self.request_latency.get().unwrap_or(DEFAULT_RETRY_AFTER),
+                })
+            }
+        }
     }
 
-    // Wrapper over test_http_handler specifically for write requests.
-    macro_rules! test_write_handler {
-        (
-            $name:ident,
-            query_string = $query_string:expr,   // Request URI query string
-            body = $body:expr,                   // Request body content
-            dml_handler = $dml_handler:expr,     // DML write handler response (if called)
-            want_result = $want_result:pat,
-            want_dml_calls = $($want_dml_calls:tt )+
-        ) => {
-            paste::paste! {
-                test_http_handler!(
-                    [<write_ $name>],
-                    uri = format!("https://bananas.example/api/v2/write{}", $query_string),
-                    body = $body,
-                    dml_write_handler = $dml_handler,
-                    dml_delete_handler = [],
-                    want_result = $want_result,
-                    want_dml_calls = $($want_dml_calls)+
-                );
-            }
-        };
-    }
+    /// Ensure the points in `batches` fall within the time window of
+    /// `namespace`, then charge the write to the rate limit budget of `org`,
+    /// pass them to the DML handler and record the write metrics.
+    ///
+    /// If the write has an idempotency key, the summary of the successful
+    /// write is remembered to answer any retries. Successful writes are
+    /// recorded in the audit log and usage accounting, if configured.
+    #[allow(clippy::too_many_arguments)]
+    async fn dispatch_write(
+        &self,
+        namespace: DatabaseName<'static>,
+        org: &str,
+        batches: HashMap<String, MutableBatch>,
+        num_lines: usize,
+        num_fields: usize,
+        body_size: usize,
+        admitted: AdmittedWrite,
+        span_ctx: Option<SpanContext>,
+    ) -> Result<WriteSummary, Error> {
+        self.time_windows
+            .check(&namespace, &batches, self.time_provider.now())?;
+
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.record(
+                org,
+                num_lines as _,
+                body_size as _,
+                self.time_provider.now(),
+            );
+        }
 
-    // Wrapper over test_http_handler specifically for delete requests.
-    macro_rules! test_delete_handler {
-        (
-            $name:ident,
-            query_string = $query_string:expr,   // Request URI query string
-            body = $body:expr,                   // Request body content
-            dml_handler = $dml_handler:expr,     // DML delete handler response (if called)
-            want_result = $want_result:pat,
-            want_dml_calls = $($want_dml_calls:tt )+
-        ) => {
-            paste::paste! {
-                test_http_handler!(
-                    [<delete_ $name>],
-                    uri = format!("https://bananas.example/api/v2/delete{}", $query_string),
-                    body = $body,
-                    dml_write_handler = [],
-                    dml_delete_handler = $dml_handler,
-                    want_result = $want_result,
-                    want_dml_calls = $($want_dml_calls)+
-                );
-            }
-        };
-    }
+        // Capture the audited details of the write before the batches & span
+        // context are passed to the DML handler.
+        let audit = self.audit.as_ref().map(|log| {
+            let mut tables = batches.keys().cloned().collect::<Vec<_>>();
+            tables.sort_unstable();
+            (log, tables, trace_ids(span_ctx.as_ref()))
+        });
 
-    test_write_handler!(
-        ok,
-        query_string = "?org=bananas&bucket=test",
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
-            assert_eq!(namespace, "bananas_test");
+        let num_tables = batches.len();
+        let summary = admitted
+            .provenance
+            .scope(self.dml_handler.write(&namespace, batches, span_ctx))
+            .await
+            .map_err(Into::into)?;
+
+        self.write_metrics
+            .record(&namespace, num_lines, num_fields, num_tables, body_size);
+        if let Some(usage) = &self.usage {
+            usage.record(&namespace, num_lines, body_size);
         }
-    );
 
-    test_write_handler!(
-        ok_precision_s,
-        query_string = "?org=bananas&bucket=test&precision=s",
-        body = "platanos,tag1=A,tag2=B val=42i 1647622847".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-            assert_eq!(namespace, "bananas_test");
+        if let (Some(cache), Some(key)) = (&self.idempotency, admitted.idempotency_key) {
+            cache.insert(key, summary.clone(), self.time_provider.now());
+        }
 
-            let table = write_input.get("platanos").expect("table not found");
-            let ts = table.timestamp_summary().expect("no timestamp summary");
-            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        if let Some((log, tables, (trace_id, span_id))) = audit {
+            log.record(AuditRecord {
+                time: self.time_provider.now(),
+                operation: AuditOperation::Write,
+                namespace: namespace.to_string(),
+                tables,
+                num_lines,
+                predicate: None,
+                principal: admitted.principal,
+                trace_id,
+                span_id,
+            });
         }
-    );
 
-    test_write_handler!(
-        ok_precision_ms,
-        query_string = "?org=bananas&bucket=test&precision=ms",
-        body = "platanos,tag1=A,tag2=B val=42i 1647622847000".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-            assert_eq!(namespace, "bananas_test");
+        Ok(summary)
+    }
 
-            let table = write_input.get("platanos").expect("table not found");
-            let ts = table.timestamp_summary().expect("no timestamp summary");
-            assert_eq!(Some(1647622847000000000), ts.stats.min);
+    async fn delete_handler(&self, req: Request<Body>) -> Result<WriteSummary, Error> {
+        let span_ctx: Option<SpanContext> = req.extensions().get().cloned();
+
+        let RequestTarget { namespace, org, .. } = self.v2_target(&req).await?;
+
+        trace!(%org, %namespace, "processing delete request");
+
+        self.authorize(req.headers(), &namespace, Permission::Delete)
+            .await?;
+        let principal = self.audit_principal(req.headers());
+        let provenance = self.provenance(req.headers());
+        let _org_permit = self.acquire_org_permit(&org)?;
+
+        // Read the HTTP body and convert it to a str.
+        let body = self.read_body(req).await?;
+        let body = std::str::from_utf8(&body).map_err(Error::NonUtf8Body)?;
+
+        // Parse and extract table name (which can be empty), start, stop, and
+        // predicate of each delete in the request, validating them all before
+        // any are applied.
+        let deletes = parse_http_delete_requests(body)?
+            .into_iter()
+            .map(|parsed_delete| {
+                let predicate = parse_delete_predicate(
+                    &parsed_delete.start_time,
+                    &parsed_delete.stop_time,
+                    &parsed_delete.predicate,
+                )?;
+                Ok((parsed_delete, predicate))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // The deletes are applied in order, each as a separate DML delete - if
+        // one fails, the subsequent deletes are not applied, but those before
+        // it are, and the error reports how many were applied.
+        let (trace_id, span_id) = trace_ids(span_ctx.as_ref());
+        let total = deletes.len();
+        let mut summary = WriteSummary::default();
+        for (applied, (parsed_delete, predicate)) in deletes.into_iter().enumerate() {
+            debug!(
+                table_name=%parsed_delete.table_name,
+                predicate = %parsed_delete.predicate,
+                start=%parsed_delete.start_time,
+                stop=%parsed_delete.stop_time,
+                body_size=body.len(),
+                %namespace,
+                %org,
+                "routing delete"
+            );
+
+            let delete_summary = provenance
+                .clone()
+                .scope(self.dml_handler.delete(
+                    &namespace,
+                    parsed_delete.table_name.as_str(),
+                    &predicate,
+                    span_ctx.clone(),
+                ))
+                .await
+                .map_err(|e| match applied {
+                    0 => Error::DmlHandler(e.into()),
+                    _ => Error::PartialDelete {
+                        applied,
+                        total,
+                        source: e.into(),
+                    },
+                })?;
+            summary.merge(delete_summary);
+
+            if let Some(log) = &self.audit {
+                log.record(AuditRecord {
+                    time: self.time_provider.now(),
+                    operation: AuditOperation::Delete,
+                    namespace: namespace.to_string(),
+                    tables: Some(parsed_delete.table_name)
+                        .filter(|t| !t.is_empty())
+                        .into_iter()
+                        .collect(),
+                    num_lines: 0,
+                    predicate: Some(parsed_delete.predicate),
+                    principal: principal.clone(),
+                    trace_id: trace_id.clone(),
+                    span_id: span_id.clone(),
+                });
+            }
         }
-    );
 
-    test_write_handler!(
-        ok_precision_us,
-        query_string = "?org=bananas&bucket=test&precision=us",
-        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-            assert_eq!(namespace, "bananas_test");
+        self.delete_metric_body_size.inc(body.len() as _);
 
-            let table = write_input.get("platanos").expect("table not found");
-            let ts = table.timestamp_summary().expect("no timestamp summary");
-            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        Ok(summary)
+    }
+
+    /// Resolve the target of a request addressed by InfluxDB 2.x org & bucket
+    /// parameters.
+    async fn v2_target(&self, req: &Request<Body>) -> Result<RequestTarget, Error> {
+        if let Some(target) = self.token_target(req).await? {
+            return Ok(target);
         }
-    );
 
-    test_write_handler!(
-        ok_precision_ns,
-        query_string = "?org=bananas&bucket=test&precision=ns",
-        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000000".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-            assert_eq!(namespace, "bananas_test");
+        let write_info = WriteInfo::try_from(req)?;
+        let namespace = org_and_bucket_to_database(&write_info.org, &write_info.bucket)
+            .map_err(OrgBucketError::MappingFail)?;
+        trace!(org=%write_info.org, bucket=%write_info.bucket, %namespace, "mapped org/bucket to namespace");
 
-            let table = write_input.get("platanos").expect("table not found");
-            let ts = table.timestamp_summary().expect("no timestamp summary");
-            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        Ok(RequestTarget {
+            namespace,
+            org: write_info.org,
+            precision: write_info.precision,
+            partial: write_info.partial,
+        })
+    }
+
+    /// Resolve the target of a request addressed by InfluxDB 1.x db & rp
+    /// parameters.
+    async fn v1_target(&self, req: &Request<Body>) -> Result<RequestTarget, Error> {
+        if let Some(target) = self.token_target(req).await? {
+            return Ok(target);
         }
-    );
 
-    test_write_handler!(
-        precision_overflow,
-        // SECONDS, so multiplies the provided timestamp by 1,000,000,000
-        query_string = "?org=bananas&bucket=test&precision=s",
-        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000000".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::ParseLineProtocol(_)),
-        want_dml_calls = []
-    );
+        let write_info = V1WriteInfo::try_from(req)?;
+        let namespace = write_info.namespace()?;
+        trace!(db=%write_info.db, rp=?write_info.rp, %namespace, "mapped db/rp to namespace");
+
+        // There is no org in a v1 request - per-org limits are applied per db
+        // instead.
+        Ok(RequestTarget {
+            namespace,
+            org: write_info.db,
+            precision: write_info.precision,
+            partial: write_info.partial,
+        })
+    }
 
-    test_write_handler!(
-        no_query_params,
-        query_string = "",
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
-        want_dml_calls = [] // None
-    );
+    /// Resolve the target of a request from its credentials, if a
+    /// [`TokenNamespaceLookup`] is configured.
+    async fn token_target(&self, req: &Request<Body>) -> Result<Option<RequestTarget>, Error> {
+        let lookup = match &self.token_namespaces {
+            Some(v) => v,
+            None => return Ok(None),
+        };
 
-    test_write_handler!(
-        no_org_bucket,
-        query_string = "?",
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::DecodeFail(_))),
-        want_dml_calls = [] // None
-    );
+        let credentials = Credentials::try_from(req.headers())?;
+        let bound = lookup
+            .lookup(&credentials)
+            .await?
+            .ok_or(AuthError::InvalidCredentials)?;
+        trace!(principal=%credentials.principal(), namespace=%bound.namespace, "mapped token to namespace");
 
-    test_write_handler!(
-        empty_org_bucket,
-        query_string = "?org=&bucket=",
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
-        want_dml_calls = [] // None
-    );
+        let options: WriteOptions = match req.uri().query() {
+            Some(query) => serde_urlencoded::from_str(query).map_err(OrgBucketError::from)?,
+            None => WriteOptions::default(),
+        };
 
-    test_write_handler!(
-        invalid_org_bucket,
-        query_string = format!("?org=test&bucket={}", "A".repeat(1000)),
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::MappingFail(_))),
-        want_dml_calls = [] // None
-    );
+        Ok(Some(RequestTarget {
+            namespace: bound.namespace,
+            org: bound.org,
+            precision: options.precision,
+            partial: options.partial,
+        }))
+    }
 
-    test_write_handler!(
-        invalid_line_protocol,
-        query_string = "?org=bananas&bucket=test",
-        body = "not line protocol".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::ParseLineProtocol(_)),
-        want_dml_calls = [] // None
-    );
+    /// Returns the [`Provenance`] to attach to the DML operations of a request
+    /// with `headers`, which is empty unless provenance annotations are
+    /// enabled.
+    fn provenance(&self, headers: &HeaderMap) -> Provenance {
+        if !self.provenance {
+            return Provenance::default();
+        }
+        Provenance::from_headers(headers)
+    }
 
-    test_write_handler!(
-        non_utf8_body,
-        query_string = "?org=bananas&bucket=test",
-        body = vec![0xc3, 0x28],
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::NonUtf8Body(_)),
-        want_dml_calls = [] // None
-    );
+    /// Returns the principal to record in the audit log for a request with
+    /// `headers`, if an audit log is configured and the request carries
+    /// credentials.
+    fn audit_principal(&self, headers: &HeaderMap) -> Option<String> {
+        self.audit.as_ref()?;
+        Credentials::try_from(headers).ok().map(|c| c.principal())
+    }
 
-    test_write_handler!(
-        max_request_size_truncation,
-        query_string = "?org=bananas&bucket=test",
-        body = {
-            // Generate a LP string in the form of:
-            //
-            //  bananas,A=AAAAAAAAAA(repeated)... B=42
-            //                                  ^
-            //                                  |
-            //                         MAX_BYTES boundary
-            //
-            // So that reading MAX_BYTES number of bytes produces the string:
-            //
-            //  bananas,A=AAAAAAAAAA(repeated)...
-            //
-            // Effectively trimming off the " B=42" suffix.
-            let body = "bananas,A=";
-            iter::once(body)
-                .chain(iter::repeat("A").take(MAX_BYTES - body.len()))
-                .chain(iter::once(" B=42\n"))
-                .flat_map(|s| s.bytes())
-                .collect::<Vec<u8>>()
-        },
-        dml_handler = [Ok(summary())],
-        want_result = Err(Error::RequestSizeExceeded(_)),
-        want_dml_calls = [] // None
-    );
+    /// Ensure the credentials in the request `headers` grant `permission` on
+    /// `namespace`, if an [`Authorizer`] is configured.
+    async fn authorize(
+        &self,
+        headers: &HeaderMap,
+        namespace: &DatabaseName<'static>,
+        permission: Permission,
+    ) -> Result<(), Error> {
+        let authorizer = match &self.authorizer {
+            Some(v) => v,
+            None => return Ok(()),
+        };
 
-    test_write_handler!(
-        db_not_found,
-        query_string = "?org=bananas&bucket=test",
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Err(DmlError::DatabaseNotFound("bananas_test".to_string()))],
-        want_result = Err(Error::DmlHandler(DmlError::DatabaseNotFound(_))),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
-            assert_eq!(namespace, "bananas_test");
+        let credentials = Credentials::try_from(headers)?;
+        authorizer
+            .authorize(&credentials, namespace, permission)
+            .await
+            .map_err(|e| {
+                debug!(error=%e, %namespace, %permission, "request authorization failed");
+                Error::Auth(e)
+            })
+    }
+
+    /// Returns the digest the body of a request with `headers` must match, if
+    /// digest verification is enabled and the client supplied one.
+    fn body_digest(&self, headers: &HeaderMap) -> Result<Option<BodyDigest>, Error> {
+        if !self.verify_digests {
+            return Ok(None);
         }
-    );
+        Ok(BodyDigest::from_headers(headers)?)
+    }
 
-    test_write_handler!(
-        dml_handler_error,
-        query_string = "?org=bananas&bucket=test",
-        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
-        dml_handler = [Err(DmlError::Internal("💣".into()))],
-        want_result = Err(Error::DmlHandler(DmlError::Internal(_))),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
-            assert_eq!(namespace, "bananas_test");
+    /// Read the body of `req` into memory without decoding it, applying the
+    /// configured size limit and verifying any digest.
+    async fn read_raw_body(&self, req: Request<Body>) -> Result<Bytes, Error> {
+        let digest = self.body_digest(req.headers())?;
+        let max_request_bytes = self.max_request_bytes();
+        let mut payload = req.into_body();
+        let mut body = BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            let chunk = chunk.map_err(Error::ClientHangup)?;
+            // limit max size of in-memory payload
+            if (body.len() + chunk.len()) > max_request_bytes {
+                return Err(Error::RequestSizeExceeded(max_request_bytes));
+            }
+            body.extend_from_slice(&chunk);
         }
-    );
 
-    test_write_handler!(
-        field_upsert_within_batch,
-        query_string = "?org=bananas&bucket=test",
-        body = "test field=1u 100\ntest field=2u 100".as_bytes(),
-        dml_handler = [Ok(summary())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-            assert_eq!(namespace, "bananas_test");
-            let table = write_input.get("test").expect("table not in write");
-            let col = table.column("field").expect("column missing");
-            assert_matches!(col.data(), ColumnData::U64(data, _) => {
-                // Ensure both values are recorded, in the correct order.
-                assert_eq!(data.as_slice(), [1, 2]);
-            });
+        if let Some(mut digest) = digest {
+            digest.update(&body);
+            digest.verify()?;
         }
-    );
 
-    test_write_handler!(
-        column_named_time,
-        query_string = "?org=bananas&bucket=test",
-        body = "test field=1u,time=42u 100".as_bytes(),
-        dml_handler = [],
-        want_result = Err(_),
-        want_dml_calls = []
-    );
+        Ok(body.freeze())
+    }
 
-    test_delete_handler!(
-        ok,
-        query_string = "?org=bananas&bucket=test",
-        body = r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#.as_bytes(),
-        dml_handler = [Ok(())],
-        want_result = Ok(_),
-        want_dml_calls = [MockDmlHandlerCall::Delete{namespace, table, predicate}] => {
-            assert_eq!(table, "its_a_table");
-            assert_eq!(namespace, "bananas_test");
-            assert!(!predicate.exprs.is_empty());
+    /// Parse the request's body into raw bytes, applying the configured size
+    /// limits and decoding any content encoding.
+    async fn read_body(&self, req: hyper::Request<Body>) -> Result<Bytes, Error> {
+        let encoding = content_encoding(req.headers())?;
+        let digest = self.body_digest(req.headers())?;
+        let mut body = DecodedBody::new(req.into_body(), encoding, self.max_request_bytes())?
+            .with_digest(digest)
+            .with_metrics(self.body_metrics.clone());
+
+        let mut decoded = Vec::new();
+        while let Some(chunk) = body.next().await? {
+            decoded.extend_from_slice(&chunk);
         }
-    );
 
-    test_delete_handler!(
-        invalid_delete_body,
-        query_string = "?org=bananas&bucket=test",
-        body = r#"{wat}"#.as_bytes(),
-        dml_handler = [],
-        want_result = Err(Error::ParseHttpDelete(_)),
-        want_dml_calls = []
-    );
+        Ok(decoded.into())
+    }
+}
 
-    test_delete_handler!(
-        no_query_params,
-        query_string = "",
-        body = "".as_bytes(),
-        dml_handler = [Ok(())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
-        want_dml_calls = [] // None
-    );
+/// The outcome of a (possibly partial) line protocol write.
+#[derive(Debug)]
+struct LpWriteOutcome {
+    summary: WriteSummary,
 
-    test_delete_handler!(
-        no_org_bucket,
-        query_string = "?",
-        body = "".as_bytes(),
-        dml_handler = [Ok(())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::DecodeFail(_))),
-        want_dml_calls = [] // None
-    );
+    /// The number of lines written.
+    num_lines: usize,
 
-    test_delete_handler!(
-        empty_org_bucket,
-        query_string = "?org=&bucket=",
-        body = "".as_bytes(),
-        dml_handler = [Ok(())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
-        want_dml_calls = [] // None
-    );
+    /// The invalid lines skipped in a partial write.
+    rejected: Vec<RejectedLine>,
+}
 
-    test_delete_handler!(
-        invalid_org_bucket,
-        query_string = format!("?org=test&bucket={}", "A".repeat(1000)),
-        body = "".as_bytes(),
-        dml_handler = [Ok(())],
-        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::MappingFail(_))),
-        want_dml_calls = [] // None
-    );
+impl LpWriteOutcome {
+    /// The outcome of a write answered with the `summary` of a previous
+    /// write with the same idempotency key.
+    fn duplicate(summary: WriteSummary) -> Self {
+        Self {
+            summary,
+            num_lines: 0,
+            rejected: vec![],
+        }
+    }
+}
 
-    test_delete_handler!(
-        non_utf8_body,
-        query_string = "?org=bananas&bucket=test",
-        body = vec![0xc3, 0x28],
-        dml_handler = [Ok(())],
-        want_result = Err(Error::NonUtf8Body(_)),
-        want_dml_calls = [] // None
-    );
+/// The result of admitting a write request.
+#[derive(Debug)]
+enum Admission {
+    /// The write should be performed.
+    Write(AdmittedWrite),
 
-    test_delete_handler!(
-        db_not_found,
-        query_string = "?org=bananas&bucket=test",
-        body = r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#.as_bytes(),
-        dml_handler = [Err(DmlError::DatabaseNotFound("bananas_test".to_string()))],
-        want_result = Err(Error::DmlHandler(DmlError::DatabaseNotFound(_))),
-        want_dml_calls = [MockDmlHandlerCall::Delete{namespace, table, predicate}] => {
-            assert_eq!(table, "its_a_table");
-            assert_eq!(namespace, "bananas_test");
-            assert!(!predicate.exprs.is_empty());
-        }
-    );
+    /// The write duplicates a previously completed write, with the returned
+    /// summary.
+    Duplicate(WriteSummary),
+}
 
-    test_delete_handler!(
-        dml_handler_error,
-        query_string = "?org=bananas&bucket=test",
-        body = r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#.as_bytes(),
-        dml_handler = [Err(DmlError::Internal("💣".into()))],
-        want_result = Err(Error::DmlHandler(DmlError::Internal(_))),
-        want_dml_calls = [MockDmlHandlerCall::Delete{namespace, table, predicate}] => {
-            assert_eq!(table, "its_a_table");
-            assert_eq!(namespace, "bananas_test");
-            assert!(!predicate.exprs.is_empty());
+/// The details of an admitted write request, recorded once the write
+/// completes.
+#[derive(Debug)]
+struct AdmittedWrite {
+    /// The idempotency key of the request, against which the write summary
+    /// is remembered (if any).
+    idempotency_key: Option<IdempotencyKey>,
+
+    /// The principal recorded in the audit log (if any).
+    principal: Option<String>,
+
+    /// The provenance attached to the DML operations of the write.
+    provenance: Provenance,
+
+    /// The per-org request permit (if any), held until the write completes.
+    _org_permit: Option<OrgPermit>,
+}
+
+/// A guard recording a client abort in the wrapped metric if dropped before
+/// [`AbortGuard::disarm()`] is called.
+#[derive(Debug)]
+struct AbortGuard<'a> {
+    client_aborts: &'a U64Counter,
+    armed: bool,
+}
+
+impl<'a> AbortGuard<'a> {
+    fn new(client_aborts: &'a U64Counter) -> Self {
+        Self {
+            client_aborts,
+            armed: true,
         }
-    );
+    }
 
-    test_http_handler!(
-        not_found,
-        uri = "https://bananas.example/wat",
-        body = "".as_bytes(),
-        dml_write_handler = [],
-        dml_delete_handler = [],
-        want_result = Err(Error::NoHandler),
-        want_dml_calls = []
-    );
+    /// Mark the request as completed.
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
 
-    // https://github.com/influxdata/influxdb_iox/issues/4326
-    mod issue4326 {
-        use super::*;
+impl<'a> Drop for AbortGuard<'a> {
+    fn drop(&mut self) {
+        if self.armed {
+            debug!("client disconnected - aborting request");
+            self.client_aborts.inc(1);
+        }
+    }
+}
 
-        test_write_handler!(
-            duplicate_fields_same_value,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo InputPower=300i,InputPower=300i".as_bytes(),
-            dml_handler = [Ok(summary())],
-            want_result = Ok(_),
-            want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-                assert_eq!(namespace, "bananas_test");
-                let table = write_input.get("whydo").expect("table not in write");
-                let col = table.column("InputPower").expect("column missing");
-                assert_matches!(col.data(), ColumnData::I64(data, _) => {
-                    // Ensure the duplicate values are coalesced.
-                    assert_eq!(data.as_slice(), [300]);
-                });
-            }
-        );
+/// Returns the (hex-encoded) trace & span IDs of `span_ctx`, if any.
+fn trace_ids(span_ctx: Option<&SpanContext>) -> (Option<String>, Option<String>) {
+    match span_ctx {
+        Some(ctx) => (
+            Some(format!("{:x}", ctx.trace_id.get())),
+            Some(format!("{:x}", ctx.span_id.get())),
+        ),
+        None => (None, None),
+    }
+}
 
-        test_write_handler!(
-            duplicate_fields_different_value,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo InputPower=300i,InputPower=42i".as_bytes(),
-            dml_handler = [Ok(summary())],
-            want_result = Ok(_),
-            want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
-                assert_eq!(namespace, "bananas_test");
-                let table = write_input.get("whydo").expect("table not in write");
-                let col = table.column("InputPower").expect("column missing");
-                assert_matches!(col.data(), ColumnData::I64(data, _) => {
-                    // Last value wins
-                    assert_eq!(data.as_slice(), [42]);
-                });
-            }
-        );
+/// An invalid line skipped in a partial write.
+#[derive(Debug, Serialize)]
+struct RejectedLine {
+    line: usize,
+    message: String,
+}
 
-        test_write_handler!(
-            duplicate_fields_different_type,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo InputPower=300i,InputPower=4.2".as_bytes(),
-            dml_handler = [],
-            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
-                source: LineWriteError::ConflictedFieldTypes { .. },
-                ..
-            })),
-            want_dml_calls = []
-        );
+/// Map a line protocol conversion error to the appropriate [`Error`].
+fn lp_error(e: mutable_batch_lp::Error) -> Error {
+    if e.is_limit_exceeded() {
+        return Error::WriteLimitExceeded(e);
+    }
+    Error::ParseLineProtocol(e)
+}
 
-        test_write_handler!(
-            duplicate_tags_same_value,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo,InputPower=300i,InputPower=300i field=42i".as_bytes(),
-            dml_handler = [],
-            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
-                source: LineWriteError::DuplicateTag { .. },
-                ..
-            })),
-            want_dml_calls = []
-        );
+// Writes in other formats exceeding the [`LineLimits`] are rejected with the
+// same error as line protocol writes.
 
-        test_write_handler!(
-            duplicate_tags_different_value,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo,InputPower=300i,InputPower=42i field=42i".as_bytes(),
-            dml_handler = [],
-            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
-                source: LineWriteError::DuplicateTag { .. },
-                ..
-            })),
-            want_dml_calls = []
-        );
+impl From<PromWriteError> for Error {
+    fn from(e: PromWriteError) -> Self {
+        match e {
+            PromWriteError::LimitExceeded(e) => Self::WriteLimitExceeded(e),
+            e => Self::ParsePromWrite(e),
+        }
+    }
+}
 
-        test_write_handler!(
-            duplicate_tags_different_type,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo,InputPower=300i,InputPower=4.2 field=42i".as_bytes(),
-            dml_handler = [],
-            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
-                source: LineWriteError::DuplicateTag { .. },
-                ..
-            })),
-            want_dml_calls = []
-        );
+impl From<OtlpError> for Error {
+    fn from(e: OtlpError) -> Self {
+        match e {
+            OtlpError::LimitExceeded(e) => Self::WriteLimitExceeded(e),
+            e => Self::ParseOtlp(e),
+        }
+    }
+}
 
-        test_write_handler!(
-            duplicate_is_tag_and_field,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo,InputPower=300i InputPower=300i".as_bytes(),
-            dml_handler = [],
-            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
-                source: LineWriteError::MutableBatch {
-                    source: mutable_batch::writer::Error::TypeMismatch { .. }
-                },
-                ..
-            })),
-            want_dml_calls = []
-        );
+impl From<GraphiteError> for Error {
+    fn from(e: GraphiteError) -> Self {
+        match e {
+            GraphiteError::LimitExceeded(e) => Self::WriteLimitExceeded(e),
+            e => Self::ParseGraphite(e),
+        }
+    }
+}
 
-        test_write_handler!(
-            duplicate_is_tag_and_field_different_types,
-            query_string = "?org=bananas&bucket=test",
-            body = "whydo,InputPower=300i InputPower=30.0".as_bytes(),
-            dml_handler = [],
-            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
-                source: LineWriteError::MutableBatch {
-                    source: mutable_batch::writer::Error::TypeMismatch { .. }
-                },
-                ..
-            })),
-            want_dml_calls = []
-        );
+impl From<JsonWriteError> for Error {
+    fn from(e: JsonWriteError) -> Self {
+        match e {
+            JsonWriteError::LimitExceeded(e) => Self::WriteLimitExceeded(e),
+            e => Self::ParseJson(e),
+        }
+    }
+}
+
+impl From<&mutable_batch_lp::Error> for RejectedLine {
+    fn from(e: &mutable_batch_lp::Error) -> Self {
+        Self {
+            line: e.line().unwrap_or_default(),
+            message: e.to_string(),
+        }
+    }
+}
+
+/// The JSON body returned for a partial write.
+///
+/// This mirrors the `{code, message}` body of HTTP error responses, with the
+/// details of each rejected line.
+#[derive(Debug, Serialize)]
+struct PartialWriteResponse<'a> {
+    code: &'static str,
+    message: String,
+    line_errors: &'a [RejectedLine],
+}
+
+/// Build the response to a successful write.
+fn write_response(summary: WriteSummary) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header(WRITE_TOKEN_HTTP_HEADER, summary.to_token())
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// Build the response to a line protocol write.
+///
+/// If any lines were rejected, a 400 is returned describing them, matching
+/// the partial write semantics of InfluxDB Cloud - the remaining lines have
+/// been written, and the write token is included as usual.
+fn lp_write_response(outcome: LpWriteOutcome) -> Response<Body> {
+    if outcome.rejected.is_empty() {
+        return write_response(outcome.summary);
+    }
+
+    let body = PartialWriteResponse {
+        code: "invalid",
+        message: format!(
+            "partial write error ({} lines written, {} rejected)",
+            outcome.num_lines,
+            outcome.rejected.len()
+        ),
+        line_errors: &outcome.rejected,
+    };
+
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .header(CONTENT_TYPE, "application/json")
+        .header(WRITE_TOKEN_HTTP_HEADER, outcome.summary.to_token())
+        .body(Body::from(
+            serde_json::to_vec(&body).expect("failed to serialise partial write response"),
+        ))
+        .unwrap()
+}
+
+/// Returns true if the `Content-Type` in `headers` is JSON, ignoring any
+/// parameters (such as the charset).
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(&CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|v| v.trim().eq_ignore_ascii_case(JSON_CONTENT_TYPE))
+        .unwrap_or_default()
+}
+
+/// Read the `Content-Encoding` of a request from `headers`.
+fn content_encoding(headers: &HeaderMap) -> Result<ContentEncoding, Error> {
+    let encoding = headers
+        .get(&CONTENT_ENCODING)
+        .map(|v| v.to_str().map_err(Error::NonUtf8ContentHeader))
+        .transpose()?;
+
+    match encoding {
+        None => Ok(ContentEncoding::Identity),
+        Some("gzip") => Ok(ContentEncoding::Gzip),
+        Some("zstd") => Ok(ContentEncoding::Zstd),
+        Some("snappy") => Ok(ContentEncoding::Snappy),
+        Some(v) => Err(Error::InvalidContentEncoding(v.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        iter,
+        num::{NonZeroU64, NonZeroUsize},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use assert_matches::assert_matches;
+
+    use flate2::{write::GzEncoder, Compression};
+    use hyper::header::{HeaderValue, CONTENT_LENGTH};
+    use metric::{Attributes, Metric, U64Histogram};
+    use mutable_batch::column::ColumnData;
+    use mutable_batch_lp::LineWriteError;
+    use test_helpers::timeout::FutureTimeout;
+    use tokio_stream::wrappers::ReceiverStream;
+
+    use crate::dml_handlers::mock::{MockDmlHandler, MockDmlHandlerCall};
+
+    use super::*;
+
+    const MAX_BYTES: usize = 1024;
+
+    fn summary() -> WriteSummary {
+        WriteSummary::default()
+    }
+
+    fn assert_metric_hit(metrics: &metric::Registry, name: &'static str, value: Option<u64>) {
+        let counter = metrics
+            .get_instrument::<Metric<U64Counter>>(name)
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[]))
+            .expect("failed to get observer")
+            .fetch();
+
+        if let Some(want) = value {
+            assert_eq!(want, counter, "metric does not have expected value");
+        } else {
+            assert!(counter > 0, "metric {} did not record any values", name);
+        }
+    }
+
+    // Generate an HTTP handler test for a plain request, and one for each
+    // supported Content-Encoding with an encoded body (and appropriate header),
+    // asserting the handler return value & write op.
+    macro_rules! test_http_handler {
+        (
+            $name:ident,
+            uri = $uri:expr,                                // Request URI
+            body = $body:expr,                              // Request body content
+            dml_write_handler = $dml_write_handler:expr,    // DML write handler response (if called)
+            dml_delete_handler = $dml_delete_handler:expr,  // DML delete handler response (if called)
+            want_result = $want_result:pat,                 // Expected handler return value (as pattern)
+            want_dml_calls = $($want_dml_calls:tt )+        // assert_matches slice pattern for expected DML calls
+        ) => {
+            // Generate the test cases by feed the same inputs, but varying the
+            // encoding.
+            test_http_handler!(
+                $name,
+                encoding=plain,
+                uri = $uri,
+                body = $body,
+                dml_write_handler = $dml_write_handler,
+                dml_delete_handler = $dml_delete_handler,
+                want_result = $want_result,
+                want_dml_calls = $($want_dml_calls)+
+            );
+            test_http_handler!(
+                $name,
+                encoding=gzip,
+                uri = $uri,
+                body = $body,
+                dml_write_handler = $dml_write_handler,
+                dml_delete_handler = $dml_delete_handler,
+                want_result = $want_result,
+                want_dml_calls = $($want_dml_calls)+
+            );
+            test_http_handler!(
+                $name,
+                encoding=zstd,
+                uri = $uri,
+                body = $body,
+                dml_write_handler = $dml_write_handler,
+                dml_delete_handler = $dml_delete_handler,
+                want_result = $want_result,
+                want_dml_calls = $($want_dml_calls)+
+            );
+            test_http_handler!(
+                $name,
+                encoding=snappy,
+                uri = $uri,
+                body = $body,
+                dml_write_handler = $dml_write_handler,
+                dml_delete_handler = $dml_delete_handler,
+                want_result = $want_result,
+                want_dml_calls = $($want_dml_calls)+
+            );
+        };
+        // Actual test body generator.
+        (
+            $name:ident,
+            encoding = $encoding:tt,
+            uri = $uri:expr,
+            body = $body:expr,
+            dml_write_handler = $dml_write_handler:expr,
+            dml_delete_handler = $dml_delete_handler:expr,
+            want_result = $want_result:pat,
+            want_dml_calls = $($want_dml_calls:tt )+
+        ) => {
+            paste::paste! {
+                #[tokio::test]
+                async fn [<test_http_handler_ $name _ $encoding>]() {
+                    let body = $body;
+
+                    // Optionally generate a fragment of code to encode the body
+                    let body = test_http_handler!(encoding=$encoding, body);
+
+                    #[allow(unused_mut)]
+                    let mut request = Request::builder()
+                        .uri($uri)
+                        .method("POST")
+                        .body(Body::from(body))
+                        .unwrap();
+
+                    // Optionally modify request to account for the desired
+                    // encoding
+                    test_http_handler!(encoding_header=$encoding, request);
+
+                    let dml_handler = Arc::new(MockDmlHandler::default()
+                        .with_write_return($dml_write_handler)
+                        .with_delete_return($dml_delete_handler)
+                    );
+                    let metrics = Arc::new(metric::Registry::default());
+                    let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+                    let got = delegate.route(request).await;
+                    assert_matches!(got, $want_result);
+
+                    // All successful responses should have a NO_CONTENT code
+                    // and metrics should be recorded.
+                    if let Ok(v) = got {
+                        assert_eq!(v.status(), StatusCode::NO_CONTENT);
+                        if $uri.contains("/write") {
+                            assert_metric_hit(&metrics, "http_write_lines_total", None);
+                            assert_metric_hit(&metrics, "http_write_fields_total", None);
+                            assert_metric_hit(&metrics, "http_write_tables_total", None);
+                            assert_metric_hit(&metrics, "http_write_body_bytes_total", Some($body.len() as _));
+                        } else {
+                            assert_metric_hit(&metrics, "http_delete_body_bytes_total", Some($body.len() as _));
+                        }
+                    }
+
+                    let calls = dml_handler.calls();
+                    assert_matches!(calls.as_slice(), $($want_dml_calls)+);
+                }
+            }
+        };
+        (encoding=plain, $body:ident) => {
+            $body
+        };
+        (encoding=gzip, $body:ident) => {{
+            // Apply gzip compression to the body
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(&$body).unwrap();
+            e.finish().expect("failed to compress test body")
+        }};
+        (encoding=zstd, $body:ident) => {{
+            // Apply zstd compression to the body
+            zstd::stream::encode_all(&$body[..], 0).expect("failed to compress test body")
+        }};
+        (encoding=snappy, $body:ident) => {{
+            // Apply snappy (raw block) compression to the body
+            snap::raw::Encoder::new()
+                .compress_vec(&$body)
+                .expect("failed to compress test body")
+        }};
+        (encoding_header=plain, $request:ident) => {};
+        (encoding_header=$encoding:tt, $request:ident) => {{
+            // Set the content encoding
+            $request.headers_mut().insert(
+                CONTENT_ENCODING,
+                HeaderValue::from_static(stringify!($encoding)),
+            );
+        }};
+    }
+
+    // Wrapper over test_http_handler specifically for write requests.
+    macro_rules! test_write_handler {
+        (
+            $name:ident,
+            query_string = $query_string:expr,   // Request URI query string
+            body = $body:expr,                   // Request body content
+            dml_handler = $dml_handler:expr,     // DML write handler response (if called)
+            want_result = $want_result:pat,
+            want_dml_calls = $($want_dml_calls:tt )+
+        ) => {
+            paste::paste! {
+                test_http_handler!(
+                    [<write_ $name>],
+                    uri = format!("https://bananas.example/api/v2/write{}", $query_string),
+                    body = $body,
+                    dml_write_handler = $dml_handler,
+                    dml_delete_handler = [],
+                    want_result = $want_result,
+                    want_dml_calls = $($want_dml_calls)+
+                );
+            }
+        };
+    }
+
+    // Wrapper over test_http_handler specifically for v1 write requests.
+    macro_rules! test_v1_write_handler {
+        (
+            $name:ident,
+            query_string = $query_string:expr,   // Request URI query string
+            body = $body:expr,                   // Request body content
+            dml_handler = $dml_handler:expr,     // DML write handler response (if called)
+            want_result = $want_result:pat,
+            want_dml_calls = $($want_dml_calls:tt )+
+        ) => {
+            paste::paste! {
+                test_http_handler!(
+                    [<v1_write_ $name>],
+                    uri = format!("https://bananas.example/write{}", $query_string),
+                    body = $body,
+                    dml_write_handler = $dml_handler,
+                    dml_delete_handler = [],
+                    want_result = $want_result,
+                    want_dml_calls = $($want_dml_calls)+
+                );
+            }
+        };
+    }
+
+    // Wrapper over test_http_handler specifically for delete requests.
+    macro_rules! test_delete_handler {
+        (
+            $name:ident,
+            query_string = $query_string:expr,   // Request URI query string
+            body = $body:expr,                   // Request body content
+            dml_handler = $dml_handler:expr,     // DML delete handler response (if called)
+            want_result = $want_result:pat,
+            want_dml_calls = $($want_dml_calls:tt )+
+        ) => {
+            paste::paste! {
+                test_http_handler!(
+                    [<delete_ $name>],
+                    uri = format!("https://bananas.example/api/v2/delete{}", $query_string),
+                    body = $body,
+                    dml_write_handler = [],
+                    dml_delete_handler = $dml_handler,
+                    want_result = $want_result,
+                    want_dml_calls = $($want_dml_calls)+
+                );
+            }
+        };
+    }
+
+    test_write_handler!(
+        ok,
+        query_string = "?org=bananas&bucket=test",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
+            assert_eq!(namespace, "bananas_test");
+        }
+    );
+
+    test_write_handler!(
+        ok_precision_s,
+        query_string = "?org=bananas&bucket=test&precision=s",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+            assert_eq!(namespace, "bananas_test");
+
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_write_handler!(
+        ok_precision_ms,
+        query_string = "?org=bananas&bucket=test&precision=ms",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+            assert_eq!(namespace, "bananas_test");
+
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_write_handler!(
+        ok_precision_us,
+        query_string = "?org=bananas&bucket=test&precision=us",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+            assert_eq!(namespace, "bananas_test");
+
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_write_handler!(
+        ok_precision_ns,
+        query_string = "?org=bananas&bucket=test&precision=ns",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+            assert_eq!(namespace, "bananas_test");
+
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_write_handler!(
+        precision_overflow,
+        // SECONDS, so multiplies the provided timestamp by 1,000,000,000
+        query_string = "?org=bananas&bucket=test&precision=s",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::ParseLineProtocol(_)),
+        want_dml_calls = []
+    );
+
+    test_write_handler!(
+        invalid_precision_case,
+        query_string = "?org=bananas&bucket=test&precision=MS",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::DecodeFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        no_query_params,
+        query_string = "",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        no_org_bucket,
+        query_string = "?",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::DecodeFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        empty_org_bucket,
+        query_string = "?org=&bucket=",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        invalid_org_bucket,
+        query_string = format!("?org=test&bucket={}", "A".repeat(1000)),
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::MappingFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        invalid_line_protocol,
+        query_string = "?org=bananas&bucket=test",
+        body = "not line protocol".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::ParseLineProtocol(_)),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        non_utf8_body,
+        query_string = "?org=bananas&bucket=test",
+        body = vec![0xc3, 0x28],
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::NonUtf8Body(_)),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        max_request_size_truncation,
+        query_string = "?org=bananas&bucket=test",
+        body = {
+            // Generate a LP string in the form of:
+            //
+            //  bananas,A=AAAAAAAAAA(repeated)... B=42
+            //                                  ^
+            //                                  |
+            //                         MAX_BYTES boundary
+            //
+            // So that reading MAX_BYTES number of bytes produces the string:
+            //
+            //  bananas,A=AAAAAAAAAA(repeated)...
+            //
+            // Effectively trimming off the " B=42" suffix.
+            let body = "bananas,A=";
+            iter::once(body)
+                .chain(iter::repeat("A").take(MAX_BYTES - body.len()))
+                .chain(iter::once(" B=42\n"))
+                .flat_map(|s| s.bytes())
+                .collect::<Vec<u8>>()
+        },
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::RequestSizeExceeded(_)),
+        want_dml_calls = [] // None
+    );
+
+    test_write_handler!(
+        db_not_found,
+        query_string = "?org=bananas&bucket=test",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Err(DmlError::DatabaseNotFound("bananas_test".to_string()))],
+        want_result = Err(Error::DmlHandler(DmlError::DatabaseNotFound(_))),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
+            assert_eq!(namespace, "bananas_test");
+        }
+    );
+
+    test_write_handler!(
+        dml_handler_error,
+        query_string = "?org=bananas&bucket=test",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Err(DmlError::Internal("💣".into()))],
+        want_result = Err(Error::DmlHandler(DmlError::Internal(_))),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
+            assert_eq!(namespace, "bananas_test");
+        }
+    );
+
+    test_write_handler!(
+        field_upsert_within_batch,
+        query_string = "?org=bananas&bucket=test",
+        body = "test field=1u 100\ntest field=2u 100".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+            assert_eq!(namespace, "bananas_test");
+            let table = write_input.get("test").expect("table not in write");
+            let col = table.column("field").expect("column missing");
+            assert_matches!(col.data(), ColumnData::U64(data, _) => {
+                // Ensure both values are recorded, in the correct order.
+                assert_eq!(data.as_slice(), [1, 2]);
+            });
+        }
+    );
+
+    test_write_handler!(
+        column_named_time,
+        query_string = "?org=bananas&bucket=test",
+        body = "test field=1u,time=42u 100".as_bytes(),
+        dml_handler = [],
+        want_result = Err(_),
+        want_dml_calls = []
+    );
+
+    test_v1_write_handler!(
+        ok,
+        query_string = "?db=bananas",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
+            assert_eq!(namespace, "bananas");
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_default_rp,
+        query_string = "?db=bananas&rp=autogen",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
+            assert_eq!(namespace, "bananas");
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_rp,
+        query_string = "?db=bananas&rp=short&consistency=any",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, ..}] => {
+            assert_eq!(namespace, "bananas/short");
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_precision_s,
+        query_string = "?db=bananas&precision=s",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+            assert_eq!(namespace, "bananas");
+
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_precision_u,
+        query_string = "?db=bananas&precision=u",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{write_input, ..}] => {
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_precision_n,
+        query_string = "?db=bananas&precision=n",
+        body = "platanos,tag1=A,tag2=B val=42i 1647622847000000000".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{write_input, ..}] => {
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_precision_m,
+        query_string = "?db=bananas&precision=m",
+        body = "platanos,tag1=A,tag2=B val=42i 27460380".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{write_input, ..}] => {
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622800000000000), ts.stats.min);
+        }
+    );
+
+    test_v1_write_handler!(
+        ok_precision_h,
+        query_string = "?db=bananas&precision=h",
+        body = "platanos,tag1=A,tag2=B val=42i 457673".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Write{write_input, ..}] => {
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622800000000000), ts.stats.min);
+        }
+    );
+
+    test_v1_write_handler!(
+        ambiguous_precision,
+        query_string = "?db=bananas&precision=M",
+        body = "platanos,tag1=A,tag2=B val=42i 27460380".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidDbRp(DbRpError::DecodeFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_v1_write_handler!(
+        no_query_params,
+        query_string = "",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidDbRp(DbRpError::NotSpecified)),
+        want_dml_calls = [] // None
+    );
+
+    test_v1_write_handler!(
+        no_db,
+        query_string = "?rp=autogen",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidDbRp(DbRpError::DecodeFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_v1_write_handler!(
+        empty_db,
+        query_string = "?db=",
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidDbRp(DbRpError::NotSpecified)),
+        want_dml_calls = [] // None
+    );
+
+    test_v1_write_handler!(
+        invalid_db,
+        query_string = format!("?db={}", "A".repeat(1000)),
+        body = "platanos,tag1=A,tag2=B val=42i 123456".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidDbRp(DbRpError::MappingFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_v1_write_handler!(
+        invalid_line_protocol,
+        query_string = "?db=bananas",
+        body = "not line protocol".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::ParseLineProtocol(_)),
+        want_dml_calls = [] // None
+    );
+
+    test_delete_handler!(
+        ok,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#.as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [MockDmlHandlerCall::Delete{namespace, table, predicate}] => {
+            assert_eq!(table, "its_a_table");
+            assert_eq!(namespace, "bananas_test");
+            assert!(!predicate.exprs.is_empty());
+        }
+    );
+
+    test_delete_handler!(
+        invalid_delete_body,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"{wat}"#.as_bytes(),
+        dml_handler = [],
+        want_result = Err(Error::ParseHttpDelete(_)),
+        want_dml_calls = []
+    );
+
+    test_delete_handler!(
+        no_query_params,
+        query_string = "",
+        body = "".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
+        want_dml_calls = [] // None
+    );
+
+    test_delete_handler!(
+        no_org_bucket,
+        query_string = "?",
+        body = "".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::DecodeFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_delete_handler!(
+        empty_org_bucket,
+        query_string = "?org=&bucket=",
+        body = "".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::NotSpecified)),
+        want_dml_calls = [] // None
+    );
+
+    test_delete_handler!(
+        invalid_org_bucket,
+        query_string = format!("?org=test&bucket={}", "A".repeat(1000)),
+        body = "".as_bytes(),
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::InvalidOrgBucket(OrgBucketError::MappingFail(_))),
+        want_dml_calls = [] // None
+    );
+
+    test_delete_handler!(
+        non_utf8_body,
+        query_string = "?org=bananas&bucket=test",
+        body = vec![0xc3, 0x28],
+        dml_handler = [Ok(summary())],
+        want_result = Err(Error::NonUtf8Body(_)),
+        want_dml_calls = [] // None
+    );
+
+    test_delete_handler!(
+        db_not_found,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#.as_bytes(),
+        dml_handler = [Err(DmlError::DatabaseNotFound("bananas_test".to_string()))],
+        want_result = Err(Error::DmlHandler(DmlError::DatabaseNotFound(_))),
+        want_dml_calls = [MockDmlHandlerCall::Delete{namespace, table, predicate}] => {
+            assert_eq!(table, "its_a_table");
+            assert_eq!(namespace, "bananas_test");
+            assert!(!predicate.exprs.is_empty());
+        }
+    );
+
+    test_delete_handler!(
+        dml_handler_error,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#.as_bytes(),
+        dml_handler = [Err(DmlError::Internal("💣".into()))],
+        want_result = Err(Error::DmlHandler(DmlError::Internal(_))),
+        want_dml_calls = [MockDmlHandlerCall::Delete{namespace, table, predicate}] => {
+            assert_eq!(table, "its_a_table");
+            assert_eq!(namespace, "bananas_test");
+            assert!(!predicate.exprs.is_empty());
+        }
+    );
+
+    test_delete_handler!(
+        multiple_ok,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"[
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"},
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=another_table"}
+        ]"#.as_bytes(),
+        dml_handler = [Ok(summary()), Ok(summary())],
+        want_result = Ok(_),
+        want_dml_calls = [
+            MockDmlHandlerCall::Delete{namespace, table, predicate},
+            MockDmlHandlerCall::Delete{namespace: namespace2, table: table2, predicate: predicate2},
+        ] => {
+            assert_eq!(namespace, "bananas_test");
+            assert_eq!(table, "its_a_table");
+            assert!(!predicate.exprs.is_empty());
+            assert_eq!(namespace2, "bananas_test");
+            assert_eq!(table2, "another_table");
+            assert!(predicate2.exprs.is_empty());
+        }
+    );
+
+    test_delete_handler!(
+        multiple_invalid_predicate,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"[
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table"},
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"location=Boston or"}
+        ]"#.as_bytes(),
+        dml_handler = [],
+        want_result = Err(Error::ParseDelete(_)),
+        want_dml_calls = [] // None of the deletes are applied
+    );
+
+    test_delete_handler!(
+        multiple_dml_handler_error,
+        query_string = "?org=bananas&bucket=test",
+        body = r#"[
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table"},
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=another_table"},
+            {"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=third_table"}
+        ]"#.as_bytes(),
+        dml_handler = [Ok(summary()), Err(DmlError::Internal("💣".into()))],
+        want_result = Err(Error::PartialDelete {
+            applied: 1,
+            total: 3,
+            source: DmlError::Internal(_),
+        }),
+        want_dml_calls = [
+            MockDmlHandlerCall::Delete{table, ..},
+            MockDmlHandlerCall::Delete{table: table2, ..},
+        ] => {
+            // Deletes after the failure are not applied.
+            assert_eq!(table, "its_a_table");
+            assert_eq!(table2, "another_table");
+        }
+    );
+
+    test_http_handler!(
+        not_found,
+        uri = "https://bananas.example/wat",
+        body = "".as_bytes(),
+        dml_write_handler = [],
+        dml_delete_handler = [],
+        want_result = Err(Error::NoHandler),
+        want_dml_calls = []
+    );
+
+    // https://github.com/influxdata/influxdb_iox/issues/4326
+    mod issue4326 {
+        use super::*;
+
+        test_write_handler!(
+            duplicate_fields_same_value,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo InputPower=300i,InputPower=300i".as_bytes(),
+            dml_handler = [Ok(summary())],
+            want_result = Ok(_),
+            want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+                assert_eq!(namespace, "bananas_test");
+                let table = write_input.get("whydo").expect("table not in write");
+                let col = table.column("InputPower").expect("column missing");
+                assert_matches!(col.data(), ColumnData::I64(data, _) => {
+                    // Ensure the duplicate values are coalesced.
+                    assert_eq!(data.as_slice(), [300]);
+                });
+            }
+        );
+
+        test_write_handler!(
+            duplicate_fields_different_value,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo InputPower=300i,InputPower=42i".as_bytes(),
+            dml_handler = [Ok(summary())],
+            want_result = Ok(_),
+            want_dml_calls = [MockDmlHandlerCall::Write{namespace, write_input}] => {
+                assert_eq!(namespace, "bananas_test");
+                let table = write_input.get("whydo").expect("table not in write");
+                let col = table.column("InputPower").expect("column missing");
+                assert_matches!(col.data(), ColumnData::I64(data, _) => {
+                    // Last value wins
+                    assert_eq!(data.as_slice(), [42]);
+                });
+            }
+        );
+
+        test_write_handler!(
+            duplicate_fields_different_type,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo InputPower=300i,InputPower=4.2".as_bytes(),
+            dml_handler = [],
+            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
+                source: LineWriteError::ConflictedFieldTypes { .. },
+                ..
+            })),
+            want_dml_calls = []
+        );
+
+        test_write_handler!(
+            duplicate_tags_same_value,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo,InputPower=300i,InputPower=300i field=42i".as_bytes(),
+            dml_handler = [],
+            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
+                source: LineWriteError::DuplicateTag { .. },
+                ..
+            })),
+            want_dml_calls = []
+        );
+
+        test_write_handler!(
+            duplicate_tags_different_value,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo,InputPower=300i,InputPower=42i field=42i".as_bytes(),
+            dml_handler = [],
+            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
+                source: LineWriteError::DuplicateTag { .. },
+                ..
+            })),
+            want_dml_calls = []
+        );
+
+        test_write_handler!(
+            duplicate_tags_different_type,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo,InputPower=300i,InputPower=4.2 field=42i".as_bytes(),
+            dml_handler = [],
+            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
+                source: LineWriteError::DuplicateTag { .. },
+                ..
+            })),
+            want_dml_calls = []
+        );
+
+        test_write_handler!(
+            duplicate_is_tag_and_field,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo,InputPower=300i InputPower=300i".as_bytes(),
+            dml_handler = [],
+            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
+                source: LineWriteError::MutableBatch {
+                    source: mutable_batch::writer::Error::TypeMismatch { .. }
+                },
+                ..
+            })),
+            want_dml_calls = []
+        );
+
+        test_write_handler!(
+            duplicate_is_tag_and_field_different_types,
+            query_string = "?org=bananas&bucket=test",
+            body = "whydo,InputPower=300i InputPower=30.0".as_bytes(),
+            dml_handler = [],
+            want_result = Err(Error::ParseLineProtocol(mutable_batch_lp::Error::Write {
+                source: LineWriteError::MutableBatch {
+                    source: mutable_batch::writer::Error::TypeMismatch { .. }
+                },
+                ..
+            })),
+            want_dml_calls = []
+        );
     }
 
     #[derive(Debug, Error)]
@@ -1151,6 +3225,1634 @@ mod tests {
         Terrible,
     }
 
+    /// An [`Authorizer`] that grants write access to the "bananas_test"
+    /// namespace to the token "writer" (or the user "writer" with password
+    /// "s3cret"), and rejects all other credentials.
+    #[derive(Debug)]
+    struct MockAuthorizer;
+
+    #[async_trait::async_trait]
+    impl Authorizer for MockAuthorizer {
+        async fn authorize(
+            &self,
+            credentials: &Credentials,
+            namespace: &DatabaseName<'static>,
+            permission: Permission,
+        ) -> Result<(), AuthError> {
+            match credentials {
+                Credentials::Token(t) if t == "writer" => {}
+                Credentials::Basic { username, password }
+                    if username == "writer" && password == "s3cret" => {}
+                _ => return Err(AuthError::InvalidCredentials),
+            }
+            if namespace.as_str() != "bananas_test" || permission != Permission::Write {
+                return Err(AuthError::Forbidden {
+                    namespace: namespace.to_string(),
+                    permission,
+                });
+            }
+            Ok(())
+        }
+    }
+
+    async fn route_authorized(
+        path_and_query: &str,
+        authorization: Option<&str>,
+    ) -> (
+        Result<Response<Body>, Error>,
+        Vec<MockDmlHandlerCall<HashMap<String, MutableBatch>>>,
+    ) {
+        let dml_handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([Ok(summary())])
+                .with_delete_return([Ok(summary())]),
+        );
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_authorizer(Arc::new(MockAuthorizer));
+
+        let mut request = Request::builder()
+            .uri(format!("https://bananas.example{}", path_and_query))
+            .method("POST");
+        if let Some(v) = authorization {
+            request = request.header(hyper::header::AUTHORIZATION, v);
+        }
+        let body = if path_and_query.starts_with("/api/v2/write") {
+            "platanos,tag1=A,tag2=B val=42i 123456"
+        } else {
+            r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#
+        };
+        let request = request.body(Body::from(body)).unwrap();
+
+        let got = delegate.route(request).await;
+        (got, dml_handler.calls())
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_ok() {
+        let (got, calls) = route_authorized(
+            "/api/v2/write?org=bananas&bucket=test",
+            Some("Token writer"),
+        )
+        .await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::NO_CONTENT);
+        });
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { namespace, .. }] => {
+            assert_eq!(namespace, "bananas_test");
+        });
+    }
+
+    #[tokio::test]
+    async fn test_auth_basic_ok() {
+        let header = format!("Basic {}", base64::encode("writer:s3cret"));
+        let (got, calls) =
+            route_authorized("/api/v2/write?org=bananas&bucket=test", Some(&header)).await;
+        assert_matches!(got, Ok(_));
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { .. }]);
+    }
+
+    #[tokio::test]
+    async fn test_auth_no_credentials() {
+        let (got, calls) = route_authorized("/api/v2/write?org=bananas&bucket=test", None).await;
+        let err = got.expect_err("request should be rejected");
+        assert_matches!(err, Error::Auth(AuthError::NoCredentials));
+        assert_eq!(err.as_status_code(), StatusCode::UNAUTHORIZED);
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_auth_invalid_credentials() {
+        let (got, calls) =
+            route_authorized("/api/v2/write?org=bananas&bucket=test", Some("Token wat")).await;
+        let err = got.expect_err("request should be rejected");
+        assert_matches!(err, Error::Auth(AuthError::InvalidCredentials));
+        assert_eq!(err.as_status_code(), StatusCode::UNAUTHORIZED);
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_auth_forbidden_namespace() {
+        let (got, calls) = route_authorized(
+            "/api/v2/write?org=bananas&bucket=other",
+            Some("Token writer"),
+        )
+        .await;
+        let err = got.expect_err("request should be rejected");
+        assert_matches!(err, Error::Auth(AuthError::Forbidden { ref namespace, permission: Permission::Write }) => {
+            assert_eq!(namespace, "bananas_other");
+        });
+        assert_eq!(err.as_status_code(), StatusCode::FORBIDDEN);
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_auth_forbidden_delete() {
+        let (got, calls) = route_authorized(
+            "/api/v2/delete?org=bananas&bucket=test",
+            Some("Token writer"),
+        )
+        .await;
+        let err = got.expect_err("request should be rejected");
+        assert_matches!(
+            err,
+            Error::Auth(AuthError::Forbidden {
+                permission: Permission::Delete,
+                ..
+            })
+        );
+        assert_eq!(err.as_status_code(), StatusCode::FORBIDDEN);
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_token_namespaces() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let lookup = StaticTokenNamespaces::default().with_token(
+            "s3cret",
+            "platanos",
+            DatabaseName::new("platanos_ns").unwrap(),
+        );
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_token_namespaces(Arc::new(lookup));
+
+        let request = |path_and_query: &str, authorization: Option<&str>| {
+            let mut builder = Request::builder()
+                .uri(format!("https://bananas.example{}", path_and_query))
+                .method("POST");
+            if let Some(v) = authorization {
+                builder = builder.header(hyper::header::AUTHORIZATION, v);
+            }
+            builder.body(Body::from("platanos val=42i 1")).unwrap()
+        };
+
+        // The org & bucket are ignored, and may be omitted entirely.
+        delegate
+            .route(request(
+                "/api/v2/write?org=bananas&bucket=test&precision=s",
+                Some("Token s3cret"),
+            ))
+            .await
+            .expect("write should succeed");
+        let basic = format!("Basic {}", base64::encode("anyone:s3cret"));
+        delegate
+            .route(request("/write", Some(&basic)))
+            .await
+            .expect("v1 write should succeed");
+
+        assert_matches!(dml_handler.calls().as_slice(), [
+            MockDmlHandlerCall::Write { namespace: ns1, write_input },
+            MockDmlHandlerCall::Write { namespace: ns2, .. },
+        ] => {
+            assert_eq!(ns1, "platanos_ns");
+            assert_eq!(ns2, "platanos_ns");
+
+            // Write options are still honoured.
+            let table = write_input.get("platanos").expect("table not found");
+            assert_matches!(table.column("time").unwrap().data(), ColumnData::I64(data, _) => {
+                assert_eq!(data.as_slice(), [1_000_000_000]);
+            });
+        });
+
+        // Requests without credentials, or with unbound credentials, are
+        // rejected.
+        let err = delegate
+            .route(request("/api/v2/write?org=bananas&bucket=test", None))
+            .await
+            .expect_err("request should be rejected");
+        assert_matches!(err, Error::Auth(AuthError::NoCredentials));
+
+        let err = delegate
+            .route(request(
+                "/api/v2/write?org=bananas&bucket=test",
+                Some("Token bananas"),
+            ))
+            .await
+            .expect_err("request should be rejected");
+        assert_matches!(err, Error::Auth(AuthError::InvalidCredentials));
+        assert_eq!(err.as_status_code(), StatusCode::UNAUTHORIZED);
+
+        assert_eq!(dml_handler.calls().len(), 2);
+    }
+
+    fn prom_write_request() -> Vec<u8> {
+        use generated_types::prometheus::{Label, Sample, TimeSeries};
+
+        let req = WriteRequest {
+            timeseries: vec![TimeSeries {
+                labels: vec![
+                    Label {
+                        name: "__name__".to_string(),
+                        value: "http_requests_total".to_string(),
+                    },
+                    Label {
+                        name: "code".to_string(),
+                        value: "200".to_string(),
+                    },
+                ],
+                samples: vec![
+                    Sample {
+                        value: 42.0,
+                        timestamp: 1647622847000,
+                    },
+                    Sample {
+                        value: 43.0,
+                        timestamp: 1647622848000,
+                    },
+                ],
+            }],
+        };
+
+        snap::raw::Encoder::new()
+            .compress_vec(&req.encode_to_vec())
+            .expect("failed to compress write request")
+    }
+
+    async fn route_prom_write(
+        query_string: &str,
+        body: Vec<u8>,
+    ) -> (
+        Result<Response<Body>, Error>,
+        Vec<MockDmlHandlerCall<HashMap<String, MutableBatch>>>,
+    ) {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        let request = Request::builder()
+            .uri(format!(
+                "https://bananas.example/api/v1/prom/write{}",
+                query_string
+            ))
+            .method("POST")
+            .header(CONTENT_ENCODING, "snappy")
+            .body(Body::from(body))
+            .unwrap();
+
+        let got = delegate.route(request).await;
+        (got, dml_handler.calls())
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_ok() {
+        let (got, calls) = route_prom_write("?db=bananas", prom_write_request()).await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::NO_CONTENT);
+        });
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { namespace, write_input }] => {
+            assert_eq!(namespace, "bananas");
+
+            let table = write_input.get("http_requests_total").expect("table not found");
+            assert_eq!(table.rows(), 2);
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+            assert_matches!(table.column("value").unwrap().data(), ColumnData::F64(data, _) => {
+                assert_eq!(data.as_slice(), [42.0, 43.0]);
+            });
+        });
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_no_db() {
+        let (got, calls) = route_prom_write("", prom_write_request()).await;
+        assert_matches!(got, Err(Error::InvalidDbRp(DbRpError::NotSpecified)));
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_not_snappy() {
+        let (got, calls) = route_prom_write("?db=bananas", b"not snappy".to_vec()).await;
+        assert_matches!(
+            got,
+            Err(Error::ParsePromWrite(PromWriteError::InvalidSnappy(_)))
+        );
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_not_protobuf() {
+        let body = snap::raw::Encoder::new()
+            .compress_vec(b"not protobuf")
+            .unwrap();
+        let (got, calls) = route_prom_write("?db=bananas", body).await;
+        assert_matches!(got, Err(Error::ParsePromWrite(PromWriteError::Decode(_))));
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_prom_write_decompression_bomb() {
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&[0; MAX_BYTES + 1])
+            .unwrap();
+        assert!(body.len() < MAX_BYTES);
+        let (got, calls) = route_prom_write("?db=bananas", body).await;
+        assert_matches!(got, Err(Error::RequestSizeExceeded(_)));
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_graphite_write() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let templates = GraphiteTemplates::new(["servers.* .host.measurement.field"]).unwrap();
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_graphite_templates(templates);
+
+        let request = |body: &'static str| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write/graphite?org=bananas&bucket=test")
+                .method("POST")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let got = delegate
+            .route(request(
+                "servers.a.cpu.idle 42 1647622847\nservers.b.cpu.idle 43 1647622847\n",
+            ))
+            .await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::NO_CONTENT);
+        });
+        assert_matches!(dml_handler.calls().as_slice(), [MockDmlHandlerCall::Write { namespace, write_input }] => {
+            assert_eq!(namespace, "bananas_test");
+
+            let table = write_input.get("cpu").expect("table not found");
+            assert_eq!(table.rows(), 2);
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1647622847000000000), ts.stats.min);
+            assert_matches!(table.column("idle").unwrap().data(), ColumnData::F64(data, _) => {
+                assert_eq!(data.as_slice(), [42.0, 43.0]);
+            });
+        });
+
+        let err = delegate
+            .route(request("servers.a.cpu.idle bananas"))
+            .await
+            .expect_err("invalid line should be rejected");
+        assert_matches!(
+            err,
+            Error::ParseGraphite(GraphiteError::InvalidLine { line: 1, .. })
+        );
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+        assert_eq!(dml_handler.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_time_windows() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let windows = TimeWindows::new(TimeWindow {
+            max_age: Some(Duration::from_secs(60 * 60)),
+            max_future: Some(Duration::from_secs(15 * 60)),
+        })
+        .with_namespace("bananas_unbounded", TimeWindow::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_time_windows(windows);
+
+        let request = |bucket: &str, body: &'static str| {
+            Request::builder()
+                .uri(format!(
+                    "https://bananas.example/api/v2/write?org=bananas&bucket={}",
+                    bucket
+                ))
+                .method("POST")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        // Points without a timestamp are assigned the current time.
+        let got = delegate.route(request("test", "platanos v=1")).await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::NO_CONTENT);
+        });
+
+        // A single point outside the window fails the entire write.
+        let err = delegate
+            .route(request("test", "platanos v=1\nplatanos v=2 1647622847"))
+            .await
+            .expect_err("old point should be rejected");
+        assert_matches!(
+            err,
+            Error::TimestampOutOfBounds(TimeWindowError::TooOld { ref table, .. }) => {
+                assert_eq!(table, "platanos");
+            }
+        );
+        assert_eq!(err.as_status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let err = delegate
+            .route(request("test", "platanos v=1 9223372036854775806"))
+            .await
+            .expect_err("future point should be rejected");
+        assert_matches!(
+            err,
+            Error::TimestampOutOfBounds(TimeWindowError::TooNew { .. })
+        );
+        assert_eq!(dml_handler.calls().len(), 1);
+
+        // The namespace override accepts points of any timestamp.
+        let got = delegate
+            .route(request("unbounded", "platanos v=2 1647622847"))
+            .await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::NO_CONTENT);
+        });
+        assert_matches!(dml_handler.calls().as_slice(), [_, MockDmlHandlerCall::Write { namespace, .. }] => {
+            assert_eq!(namespace, "bananas_unbounded");
+        });
+    }
+
+    fn otlp_metrics_request() -> Vec<u8> {
+        use generated_types::opentelemetry::proto::{
+            collector::metrics::v1::ExportMetricsServiceRequest,
+            common::v1::{any_value, AnyValue, KeyValue},
+            metrics::v1::{
+                metric, number_data_point, Gauge, Metric, NumberDataPoint, ResourceMetrics,
+                ScopeMetrics,
+            },
+        };
+
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: None,
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics: vec![Metric {
+                        name: "cpu_usage".to_string(),
+                        data: Some(metric::Data::Gauge(Gauge {
+                            data_points: vec![NumberDataPoint {
+                                attributes: vec![KeyValue {
+                                    key: "host".to_string(),
+                                    value: Some(AnyValue {
+                                        value: Some(any_value::Value::StringValue(
+                                            "bananas".to_string(),
+                                        )),
+                                    }),
+                                }],
+                                time_unix_nano: 1647622847000000000,
+                                value: Some(number_data_point::Value::AsDouble(42.0)),
+                                ..Default::default()
+                            }],
+                        })),
+                        ..Default::default()
+                    }],
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    async fn route_otlp_metrics(
+        content_type: &str,
+        body: Vec<u8>,
+    ) -> (
+        Result<Response<Body>, Error>,
+        Vec<MockDmlHandlerCall<HashMap<String, MutableBatch>>>,
+    ) {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        let request = Request::builder()
+            .uri("https://bananas.example/v1/metrics?org=bananas&bucket=test")
+            .method("POST")
+            .header(CONTENT_TYPE, content_type)
+            .body(Body::from(body))
+            .unwrap();
+
+        let got = delegate.route(request).await;
+        (got, dml_handler.calls())
+    }
+
+    #[tokio::test]
+    async fn test_otlp_metrics_ok() {
+        let (got, calls) =
+            route_otlp_metrics("application/x-protobuf", otlp_metrics_request()).await;
+        assert_matches!(got, Ok(r) => {
+            assert_eq!(r.status(), StatusCode::OK);
+            assert_eq!(r.headers().get(CONTENT_TYPE).unwrap(), "application/x-protobuf");
+            assert!(r.headers().contains_key(WRITE_TOKEN_HTTP_HEADER));
+        });
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { namespace, write_input }] => {
+            assert_eq!(namespace, "bananas_test");
+
+            let table = write_input.get("cpu_usage").expect("table not found");
+            assert_eq!(table.rows(), 1);
+            assert_matches!(table.column("value").unwrap().data(), ColumnData::F64(data, _) => {
+                assert_eq!(data.as_slice(), [42.0]);
+            });
+        });
+    }
+
+    #[tokio::test]
+    async fn test_otlp_metrics_json_unsupported() {
+        let (got, calls) = route_otlp_metrics("application/json", b"{}".to_vec()).await;
+        let err = got.expect_err("json request should be rejected");
+        assert_matches!(err, Error::ParseOtlp(OtlpError::UnsupportedContentType(_)));
+        assert_eq!(err.as_status_code(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_otlp_metrics_not_protobuf() {
+        let (got, calls) =
+            route_otlp_metrics("application/x-protobuf", b"not protobuf".to_vec()).await;
+        let err = got.expect_err("invalid request should be rejected");
+        assert_matches!(err, Error::ParseOtlp(OtlpError::Decode(_)));
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_encoded_body() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        let request = |encoding: &'static str| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .header(CONTENT_ENCODING, encoding)
+                .body(Body::from("platanos val=42i 123456"))
+                .unwrap()
+        };
+
+        let got = delegate.route(request("gzip")).await;
+        assert_matches!(got, Err(Error::InvalidGzip(_)));
+        let got = delegate.route(request("zstd")).await;
+        assert_matches!(got, Err(Error::InvalidZstd(_)));
+        let got = delegate.route(request("snappy")).await;
+        assert_matches!(got, Err(Error::InvalidSnappy(_)));
+        let got = delegate.route(request("br")).await;
+        assert_matches!(got, Err(Error::InvalidContentEncoding(_)));
+
+        assert!(dml_handler.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_body_size_metrics() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        let gzip = |data: &[u8]| {
+            let mut e = GzEncoder::new(Vec::new(), Compression::default());
+            e.write_all(data).unwrap();
+            e.finish().unwrap()
+        };
+        let request = |body: Vec<u8>| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .header(CONTENT_ENCODING, "gzip")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let histogram = |name: &'static str| {
+            metrics
+                .get_instrument::<Metric<U64Histogram>>(name)
+                .expect("failed to read metric")
+                .get_observer(&Attributes::from(&[("encoding", "gzip")]))
+                .expect("failed to get observer")
+                .fetch()
+        };
+
+        let lp = "platanos,tag1=A,tag2=B val=42i 123456\n".repeat(10);
+        let body = gzip(lp.as_bytes());
+        let encoded_len = body.len() as u64;
+
+        let got = delegate.route(request(body)).await;
+        assert_matches!(got, Ok(_));
+
+        let encoded = histogram("http_request_body_encoded_bytes");
+        assert_eq!(encoded.sample_count(), 1);
+        assert_eq!(encoded.total, encoded_len);
+        let decoded = histogram("http_request_body_decoded_bytes");
+        assert_eq!(decoded.sample_count(), 1);
+        assert_eq!(decoded.total, lp.len() as u64);
+        let ratio = histogram("http_request_body_decompression_ratio");
+        assert_eq!(ratio.sample_count(), 1);
+
+        // A small compressed body that decodes to more than the maximum
+        // request size is rejected and counted.
+        let body = gzip(&[b'A'; MAX_BYTES * 10]);
+        assert!(body.len() < MAX_BYTES);
+
+        let got = delegate.route(request(body)).await;
+        assert_matches!(got, Err(Error::RequestSizeExceeded(_)));
+
+        let rejected = metrics
+            .get_instrument::<Metric<U64Counter>>("http_request_decompression_limit_rejected")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("encoding", "gzip")]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(rejected, 1);
+
+        // Rejected bodies are not recorded in the size histograms.
+        assert_eq!(
+            histogram("http_request_body_decoded_bytes").sample_count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_org_request_limit() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_max_requests_per_org(1);
+
+        let request = |org: &str, body: Body| {
+            Request::builder()
+                .uri(format!(
+                    "https://bananas.example/api/v2/write?org={}&bucket=test",
+                    org
+                ))
+                .method("POST")
+                .body(body)
+                .unwrap()
+        };
+
+        // Start a request whose body never completes, occupying the only
+        // permit of the org.
+        let (_tx, rx) = tokio::sync::mpsc::channel::<Result<&'static str, MockError>>(1);
+        let mut in_flight = Box::pin(delegate.route(request(
+            "bananas",
+            Body::wrap_stream(ReceiverStream::new(rx)),
+        )));
+        assert!(futures::poll!(&mut in_flight).is_pending());
+
+        let err = delegate
+            .route(request("bananas", Body::from("platanos val=42i 1")))
+            .await
+            .expect_err("request should exceed the org limit");
+        assert_matches!(err, Error::OrgRequestLimit { ref org, limit: 1, .. } => {
+            assert_eq!(org, "bananas");
+        });
+        assert_eq!(err.as_status_code(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.retry_after(), Some(1));
+        assert_metric_hit(&metrics, "http_org_request_limit_rejected", Some(1));
+
+        // Other orgs are unaffected.
+        delegate
+            .route(request("platanos", Body::from("platanos val=42i 1")))
+            .await
+            .expect("request from another org should succeed");
+
+        // Completing (or abandoning) the request releases the permit.
+        drop(in_flight);
+        delegate
+            .route(request("bananas", Body::from("platanos val=42i 1")))
+            .await
+            .expect("permit should be released");
+    }
+
+    #[tokio::test]
+    async fn test_reload_limits() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+
+        let limits = RequestLimits {
+            max_request_bytes: MAX_BYTES,
+            max_requests: 1,
+            lines_per_second: None,
+            bytes_per_second: None,
+        };
+        let (tx, rx) = watch::channel(limits);
+        let delegate =
+            HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics).with_limits(rx);
+
+        let request = |body: Body| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .body(body)
+                .unwrap()
+        };
+
+        // The limits of the channel replace those passed at construction.
+        let (_body_tx, body_rx) = tokio::sync::mpsc::channel::<Result<&'static str, MockError>>(1);
+        let mut in_flight =
+            Box::pin(delegate.route(request(Body::wrap_stream(ReceiverStream::new(body_rx)))));
+        assert!(futures::poll!(&mut in_flight).is_pending());
+
+        let err = delegate
+            .route(request(Body::from("platanos val=42i 1")))
+            .await
+            .expect_err("request should exceed the request limit");
+        assert_matches!(err, Error::RequestLimit { in_flight: 1, .. });
+
+        // Raising the limit admits more requests, without affecting the
+        // in-flight request.
+        tx.send(RequestLimits {
+            max_requests: 2,
+            ..limits
+        })
+        .unwrap();
+        delegate
+            .route(request(Body::from("platanos val=42i 1")))
+            .await
+            .expect("request should be admitted by the raised limit");
+        drop(in_flight);
+
+        // Changes to the maximum request size apply to the next request.
+        tx.send(RequestLimits {
+            max_request_bytes: 10,
+            ..limits
+        })
+        .unwrap();
+        let err = delegate
+            .route(request(Body::from("platanos val=42i 1")))
+            .await
+            .expect_err("request should exceed the reduced size limit");
+        assert_matches!(err, Error::RequestSizeExceeded(10));
+
+        // As do rate limits, even though no rate limiter was configured.
+        tx.send(RequestLimits {
+            lines_per_second: NonZeroU64::new(1),
+            ..limits
+        })
+        .unwrap();
+        delegate
+            .route(request(Body::from(
+                "platanos val=42i 1\nplatanos val=42i 2",
+            )))
+            .await
+            .expect("request should be admitted into debt");
+        let err = delegate
+            .route(request(Body::from("platanos val=42i 1")))
+            .await
+            .expect_err("request should exceed the rate limit");
+        assert_matches!(err, Error::RateLimited { .. });
+    }
+
+    #[tokio::test]
+    async fn test_org_rate_limit() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_rate_limiter(OrgRateLimiter::new(NonZeroU64::new(1), None));
+
+        let request = |org: &str| {
+            Request::builder()
+                .uri(format!(
+                    "https://bananas.example/api/v2/write?org={}&bucket=test",
+                    org
+                ))
+                .method("POST")
+                .body(Body::from(
+                    "platanos val=42i 123456\nplatanos val=24i 123457",
+                ))
+                .unwrap()
+        };
+
+        // The first request is admitted, and exhausts the budget of the org.
+        delegate
+            .route(request("bananas"))
+            .await
+            .expect("first write should succeed");
+
+        // Subsequent requests from the same org are rejected.
+        let err = delegate
+            .route(request("bananas"))
+            .await
+            .expect_err("second write should be rate limited");
+        assert_matches!(err, Error::RateLimited { ref org, .. } => {
+            assert_eq!(org, "bananas");
+        });
+        assert_eq!(err.as_status_code(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(err.retry_after(), Some(2));
+        assert_metric_hit(&metrics, "http_rate_limit_rejected", Some(1));
+
+        // But other orgs are unaffected.
+        delegate
+            .route(request("platanos"))
+            .await
+            .expect("write from another org should succeed");
+
+        assert_matches!(
+            dml_handler.calls().as_slice(),
+            [
+                MockDmlHandlerCall::Write { .. },
+                MockDmlHandlerCall::Write { .. }
+            ]
+        );
+    }
+
+    async fn route_partial_write(
+        uri: &str,
+        body: &'static str,
+        partial_writes: bool,
+    ) -> (
+        Result<Response<Body>, Error>,
+        Vec<MockDmlHandlerCall<HashMap<String, MutableBatch>>>,
+    ) {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_partial_writes(partial_writes);
+
+        let request = Request::builder()
+            .uri(uri)
+            .method("POST")
+            .body(Body::from(body))
+            .unwrap();
+
+        let got = delegate.route(request).await;
+        (got, dml_handler.calls())
+    }
+
+    const PARTIAL_WRITE_BODY: &str =
+        "platanos val=42i 1\nnot line protocol\nplatanos val=4.2 2\nplatanos val=43i 3";
+
+    #[tokio::test]
+    async fn test_partial_write() {
+        let (got, calls) = route_partial_write(
+            "https://bananas.example/api/v2/write?org=bananas&bucket=test&partial=true",
+            PARTIAL_WRITE_BODY,
+            false,
+        )
+        .await;
+
+        let response = got.expect("partial write should succeed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert!(response.headers().contains_key(WRITE_TOKEN_HTTP_HEADER));
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "invalid");
+        assert_eq!(
+            body["message"],
+            "partial write error (2 lines written, 2 rejected)"
+        );
+        let lines = body["line_errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| v["line"].as_u64().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [2, 3]);
+
+        // The valid lines are written.
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { write_input, .. }] => {
+            assert_eq!(write_input["platanos"].rows(), 2);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_partial_write_v1_default_enabled() {
+        let (got, calls) = route_partial_write(
+            "https://bananas.example/write?db=bananas",
+            PARTIAL_WRITE_BODY,
+            true,
+        )
+        .await;
+        let response = got.expect("partial write should succeed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_matches!(calls.as_slice(), [MockDmlHandlerCall::Write { .. }]);
+
+        // The request may opt-out of the default.
+        let (got, calls) = route_partial_write(
+            "https://bananas.example/write?db=bananas&partial=false",
+            PARTIAL_WRITE_BODY,
+            true,
+        )
+        .await;
+        assert_matches!(
+            got,
+            Err(Error::ParseLineProtocol(
+                mutable_batch_lp::Error::LineProtocol { line: 2, .. }
+            ))
+        );
+        assert!(calls.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_partial_write_all_rejected() {
+        let (got, calls) = route_partial_write(
+            "https://bananas.example/api/v2/write?org=bananas&bucket=test&partial=true",
+            "not line protocol\nnor this",
+            false,
+        )
+        .await;
+
+        let response = got.expect("partial write should succeed");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["message"],
+            "partial write error (0 lines written, 2 rejected)"
+        );
+
+        // Nothing is written.
+        assert!(calls.is_empty());
+    }
+
+    #[test]
+    fn test_precision() {
+        let parse = |s: &str| Precision::try_from(s.to_string());
+
+        assert_eq!(parse("µ"), Ok(Precision::Microseconds));
+        assert_eq!(parse("n"), Ok(Precision::Nanoseconds));
+        assert_eq!(parse("m"), Ok(Precision::Minutes));
+
+        assert_matches!(parse("M"), Err(e) => {
+            assert!(e.contains("ambiguous"), "{}", e);
+        });
+        assert_matches!(parse("Ns"), Err(e) => {
+            assert!(e.contains(r#"did you mean "ns""#), "{}", e);
+        });
+        assert_matches!(parse("d"), Err(e) => {
+            assert!(e.contains("expected one of"), "{}", e);
+        });
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(Error::NoHandler.error_code(), "not_found");
+        assert_eq!(
+            Error::InvalidOrgBucket(OrgBucketError::NotSpecified).error_code(),
+            "invalid_org_bucket"
+        );
+        assert_eq!(
+            Error::DmlHandler(DmlError::DatabaseNotFound("bananas".to_string())).error_code(),
+            "namespace_not_found"
+        );
+        assert_eq!(
+            Error::DmlHandler(DmlError::Internal("💣".into())).error_code(),
+            "internal_error"
+        );
+        assert_eq!(
+            Error::Timeout(Duration::from_secs(1)).error_code(),
+            "timeout"
+        );
+
+        let err = Error::PartialDelete {
+            applied: 1,
+            total: 3,
+            source: DmlError::Internal("💣".into()),
+        };
+        assert_eq!(err.error_code(), "partial_delete");
+        assert_eq!(err.as_status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            err.to_string(),
+            "delete 2 of 3 failed, the 1 deletes before it were applied: internal dml handler error: 💣"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_json() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        let request = |body: &'static str| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test&precision=s")
+                .method("POST")
+                .header(CONTENT_TYPE, "application/json; charset=utf-8")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = delegate
+            .route(request(
+                r#"[{"measurement": "platanos", "tags": {"tag1": "A"}, "fields": {"val": 42}, "time": 1}]"#,
+            ))
+            .await
+            .expect("json write should succeed");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response.headers().contains_key(WRITE_TOKEN_HTTP_HEADER));
+
+        assert_matches!(dml_handler.calls().as_slice(), [MockDmlHandlerCall::Write { namespace, write_input }] => {
+            assert_eq!(namespace, "bananas_test");
+            let table = write_input.get("platanos").expect("table not found");
+            let ts = table.timestamp_summary().expect("no timestamp summary");
+            assert_eq!(Some(1_000_000_000), ts.stats.min);
+        });
+        assert_metric_hit(&metrics, "http_write_lines_total", Some(1));
+
+        let err = delegate
+            .route(request(r#"platanos val=42i 1"#))
+            .await
+            .expect_err("line protocol is not valid json");
+        assert_matches!(err, Error::ParseJson(JsonWriteError::Decode(_)));
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_idempotent_writes() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_idempotency_cache(IdempotencyCache::new(
+                NonZeroUsize::new(10).unwrap(),
+                Duration::from_secs(60),
+            ));
+
+        let request = |bucket: &str, key: &str| {
+            Request::builder()
+                .uri(format!(
+                    "https://bananas.example/api/v2/write?org=bananas&bucket={}",
+                    bucket
+                ))
+                .method("POST")
+                .header("X-Request-Id", key)
+                .body(Body::from("platanos val=42i 1"))
+                .unwrap()
+        };
+
+        let first = delegate
+            .route(request("test", "retry-me"))
+            .await
+            .expect("write should succeed");
+        let retry = delegate
+            .route(request("test", "retry-me"))
+            .await
+            .expect("retry should succeed");
+        assert_eq!(retry.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            first.headers()[WRITE_TOKEN_HTTP_HEADER],
+            retry.headers()[WRITE_TOKEN_HTTP_HEADER]
+        );
+
+        // The retry is not written again.
+        assert_eq!(dml_handler.calls().len(), 1);
+        assert_metric_hit(&metrics, "http_write_duplicates", Some(1));
+
+        // Keys are scoped to the namespace written to.
+        delegate
+            .route(request("other", "retry-me"))
+            .await
+            .expect("write should succeed");
+        assert_eq!(dml_handler.calls().len(), 2);
+
+        let err = delegate
+            .route(request("test", &"a".repeat(1024)))
+            .await
+            .expect_err("oversized key should be rejected");
+        assert_matches!(err, Error::InvalidIdempotencyKey(_));
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_client_abort() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 1, Arc::clone(&dml_handler), &metrics);
+
+        // Dropping the request future (as hyper does when the client
+        // disconnects) records an abort, and releases the request permit.
+        let (_tx, rx) = tokio::sync::mpsc::channel::<Result<&'static str, MockError>>(1);
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::wrap_stream(ReceiverStream::new(rx)))
+            .unwrap();
+        tokio::time::timeout(Duration::from_millis(10), delegate.route(request))
+            .await
+            .expect_err("request should not complete");
+        assert_metric_hit(&metrics, "http_client_abort_total", Some(1));
+        assert_eq!(delegate.request_sem.available_permits(), 1);
+
+        // As does a client disconnecting while sending the body.
+        let chunks: Vec<Result<&'static str, MockError>> = vec![Err(MockError::Terrible)];
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::wrap_stream(futures::stream::iter(chunks)))
+            .unwrap();
+        let err = delegate
+            .route(request)
+            .await
+            .expect_err("request should fail");
+        assert_matches!(err, Error::ClientHangup(_));
+        assert_metric_hit(&metrics, "http_client_abort_total", Some(2));
+
+        assert!(dml_handler.calls().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() {
+        #[derive(Debug, Default)]
+        struct MemorySink(parking_lot::Mutex<Vec<AuditRecord>>);
+
+        #[async_trait::async_trait]
+        impl crate::audit::AuditSink for MemorySink {
+            async fn write(
+                &self,
+                records: &[AuditRecord],
+            ) -> Result<(), crate::audit::AuditSinkError> {
+                self.0.lock().extend_from_slice(records);
+                Ok(())
+            }
+        }
+
+        let dml_handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([Ok(summary()), Err(DmlError::Internal("💣".into()))])
+                .with_delete_return([Ok(summary())]),
+        );
+        let metrics = Arc::new(metric::Registry::default());
+        let sink = Arc::new(MemorySink::default());
+        let delegate =
+            HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics).with_audit_log(
+                Arc::new(AuditLog::new(Arc::clone(&sink) as _, 10, &metrics)),
+            );
+
+        let write = || {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .header(hyper::header::AUTHORIZATION, "Token s3cret")
+                .body(Body::from("platanos val=42i 1\nbananas val=1i 2"))
+                .unwrap()
+        };
+        delegate.route(write()).await.expect("write should succeed");
+
+        // Failed writes are not audited.
+        delegate
+            .route(write())
+            .await
+            .expect_err("write should fail");
+
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/delete?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::from(
+                r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=its_a_table and location=Boston"}"#,
+            ))
+            .unwrap();
+        delegate
+            .route(request)
+            .await
+            .expect("delete should succeed");
+
+        async {
+            while sink.0.lock().len() < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+
+        let records = sink.0.lock().clone();
+        assert_matches!(records.as_slice(), [write, delete] => {
+            assert_eq!(write.operation, AuditOperation::Write);
+            assert_eq!(write.namespace, "bananas_test");
+            assert_eq!(write.tables, ["bananas", "platanos"]);
+            assert_eq!(write.num_lines, 2);
+            assert_eq!(
+                write.principal,
+                Some(Credentials::Token("s3cret".to_string()).principal())
+            );
+
+            assert_eq!(delete.operation, AuditOperation::Delete);
+            assert_eq!(delete.tables, ["its_a_table"]);
+            assert!(delete.predicate.is_some());
+            assert_eq!(delete.principal, None);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_usage_accounting() {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn iox_catalog::interface::Catalog> =
+            Arc::new(iox_catalog::mem::MemCatalog::new(Arc::clone(&metrics)));
+        let namespace_id = {
+            let mut repos = catalog.repositories().await;
+            let topic = repos.topics().create_or_get("bananas").await.unwrap();
+            let pool = repos.query_pools().create_or_get("platanos").await.unwrap();
+            repos
+                .namespaces()
+                .create("bananas_test", "inf", topic.id, pool.id)
+                .await
+                .unwrap()
+                .id
+        };
+
+        let time = Arc::new(iox_time::MockProvider::new(iox_time::Time::from_timestamp(
+            0, 0,
+        )));
+        let accountant = Arc::new(UsageAccountant::new(
+            Arc::clone(&catalog),
+            Arc::clone(&time) as _,
+            Duration::from_secs(60),
+            &metrics,
+        ));
+
+        let dml_handler = Arc::new(
+            MockDmlHandler::default()
+                .with_write_return([Ok(summary()), Err(DmlError::Internal("💣".into()))]),
+        );
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_usage_accounting(Arc::clone(&accountant));
+
+        let body = "platanos val=42i 1\nbananas val=1i 2";
+        let write = || {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        delegate.route(write()).await.expect("write should succeed");
+
+        // Failed writes are not accounted.
+        delegate
+            .route(write())
+            .await
+            .expect_err("write should fail");
+
+        time.set(iox_time::Time::from_timestamp(60, 0));
+        accountant.flush().await;
+
+        let usage = catalog
+            .repositories()
+            .await
+            .namespaces()
+            .list_usage(namespace_id)
+            .await
+            .unwrap();
+        assert_matches!(usage.as_slice(), [u] => {
+            assert_eq!(u.lines, 2);
+            assert_eq!(u.bytes, body.len() as i64);
+        });
+    }
+
+    #[tokio::test]
+    async fn test_digest_verification() {
+        use md5::{Digest, Md5};
+
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_digest_verification(true);
+
+        let request = |body: &'static str, digest: &str| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .header("Content-MD5", base64::encode(Md5::digest(digest)))
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        // A body corrupted in transit is rejected before it is parsed.
+        let err = delegate
+            .route(request("platanos val=42i 1", "platanos val=43i 1"))
+            .await
+            .expect_err("mismatched digest should be rejected");
+        assert_matches!(err, Error::InvalidDigest(DigestError::Mismatch(_)));
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+        assert!(dml_handler.calls().is_empty());
+
+        delegate
+            .route(request("platanos val=42i 1", "platanos val=42i 1"))
+            .await
+            .expect("matching digest should be accepted");
+        assert_matches!(
+            dml_handler.calls().as_slice(),
+            [MockDmlHandlerCall::Write { .. }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_limits() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+            .with_max_lines(2)
+            .with_max_fields_per_line(2)
+            .with_max_tags_per_line(1);
+
+        let request = |body: &'static str| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let err = delegate
+            .route(request(
+                "platanos val=1i 1\nplatanos val=2i 2\nplatanos val=3i 3",
+            ))
+            .await
+            .expect_err("line limit should be enforced");
+        assert_matches!(
+            err,
+            Error::WriteLimitExceeded(mutable_batch_lp::Error::TooManyLines { max: 2 })
+        );
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+
+        let err = delegate
+            .route(request("platanos a=1i,b=2i,c=3i 1"))
+            .await
+            .expect_err("field limit should be enforced");
+        assert_matches!(
+            err,
+            Error::WriteLimitExceeded(mutable_batch_lp::Error::TooManyFields { line: 1, .. })
+        );
+
+        let err = delegate
+            .route(request("platanos,t1=A,t2=B val=1i 1"))
+            .await
+            .expect_err("tag limit should be enforced");
+        assert_matches!(
+            err,
+            Error::WriteLimitExceeded(mutable_batch_lp::Error::TooManyTags { line: 1, .. })
+        );
+
+        // Writes in other formats are rejected with the same error.
+        let err = delegate
+            .route(
+                Request::builder()
+                    .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                    .method("POST")
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"[{"measurement": "platanos", "fields": {"a": 1, "b": 2, "c": 3}}]"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .expect_err("field limit should be enforced for json writes");
+        assert_matches!(
+            err,
+            Error::WriteLimitExceeded(mutable_batch_lp::Error::TooManyFields { line: 1, .. })
+        );
+        assert_eq!(err.as_status_code(), StatusCode::BAD_REQUEST);
+
+        assert!(dml_handler.calls().is_empty());
+
+        // Writes within the limits are accepted.
+        delegate
+            .route(request("platanos,t1=A a=1i,b=2i 1\nplatanos val=2i 2"))
+            .await
+            .expect("write within limits should succeed");
+        assert_matches!(
+            dml_handler.calls().as_slice(),
+            [MockDmlHandlerCall::Write { .. }]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_write_streaming() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        // Lines (and quoted strings) split across body chunks are parsed as
+        // if the body was received in one chunk.
+        let chunks: Vec<Result<&'static str, MockError>> = vec![
+            Ok("platanos,tag1=A val=42i 1\nplat"),
+            Ok("anos,tag1=B val=\"ban"),
+            Ok("a\nnas\" 2\n"),
+            Ok("platanos,tag1=C val=44i 3"),
+        ];
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::wrap_stream(futures::stream::iter(chunks)))
+            .unwrap();
+
+        let err = delegate
+            .route(request)
+            .await
+            .expect_err("conflicting field types should be rejected");
+
+        // The error refers to the line number in the entire body.
+        assert_matches!(
+            err,
+            Error::ParseLineProtocol(mutable_batch_lp::Error::Write { line: 2, .. })
+        );
+        assert!(dml_handler.calls().is_empty());
+    }
+
+    // This test ensures the body is parsed as it is received, and an invalid
+    // line is rejected without waiting for the rest of the body.
+    #[tokio::test]
+    async fn test_write_streaming_early_error() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics);
+
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<&'static str, MockError>>(1);
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::wrap_stream(ReceiverStream::new(rx)))
+            .unwrap();
+
+        tx.send(Ok("not line protocol\n"))
+            .await
+            .expect("request closed channel");
+
+        // The request fails without the body stream being closed.
+        let err = delegate
+            .route(request)
+            .with_timeout_panic(Duration::from_secs(1))
+            .await
+            .expect_err("invalid line protocol should be rejected");
+        assert_matches!(
+            err,
+            Error::ParseLineProtocol(mutable_batch_lp::Error::LineProtocol { line: 1, .. })
+        );
+        assert!(dml_handler.calls().is_empty());
+
+        drop(tx);
+    }
+
+    #[tokio::test]
+    async fn test_request_byte_budget_enforced() {
+        let dml_handler =
+            Arc::new(MockDmlHandler::default().with_write_return([Ok(summary()), Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = Arc::new(
+            HttpDelegate::new(MAX_BYTES, 100, Arc::clone(&dml_handler), &metrics)
+                .with_request_byte_budget(MAX_BYTES),
+        );
+
+        let request = |content_length: usize, body: Body| {
+            Request::builder()
+                .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+                .method("POST")
+                .header(CONTENT_LENGTH, content_length)
+                .body(body)
+                .unwrap()
+        };
+
+        // Hold open a request that consumes most of the budget.
+        let (tx, rx) = tokio::sync::mpsc::channel::<Result<&'static str, MockError>>(1);
+        let req_1 = tokio::spawn({
+            let delegate = Arc::clone(&delegate);
+            let request = request(MAX_BYTES - 10, Body::wrap_stream(ReceiverStream::new(rx)));
+            async move { delegate.route(request).await }
+        });
+        tx.send(Ok("platanos ")).await.expect("req1 closed channel");
+        tx.send(Ok("val=42i 1"))
+            .with_timeout_panic(Duration::from_secs(1))
+            .await
+            .expect("req1 closed channel");
+
+        // A request that does not fit in the remaining budget is rejected,
+        // despite the request count limit not being reached.
+        let err = delegate
+            .route(request(20, Body::from("platanos val=42i 2\n")))
+            .await
+            .expect_err("request should exceed the byte budget");
+        assert_matches!(err, Error::RequestLimit { .. });
+        assert_metric_hit(&metrics, "http_request_byte_budget_rejected", Some(1));
+        assert_metric_hit(&metrics, "http_request_limit_rejected", Some(0));
+
+        // But a smaller request fits.
+        delegate
+            .route(request(0, Body::empty()))
+            .await
+            .expect("empty request should be admitted");
+
+        // Once the first request completes, its budget is released.
+        drop(tx);
+        req_1
+            .with_timeout_panic(Duration::from_secs(1))
+            .await
+            .expect("request 1 handler should not panic")
+            .expect("request 1 should succeed");
+        delegate
+            .route(request(20, Body::from("platanos val=42i 2\n")))
+            .await
+            .expect("request should be admitted");
+    }
+
+    #[tokio::test]
+    async fn test_probes_bypass_request_limit() {
+        let dml_handler = Arc::new(MockDmlHandler::default());
+        let metrics = Arc::new(metric::Registry::default());
+        // A delegate with no capacity to service requests.
+        let delegate = HttpDelegate::new(MAX_BYTES, 0, Arc::clone(&dml_handler), &metrics);
+
+        for (method, path, want) in [
+            ("GET", "/ping", StatusCode::NO_CONTENT),
+            ("HEAD", "/ping", StatusCode::NO_CONTENT),
+            ("GET", "/health", StatusCode::OK),
+            ("POST", "/query", StatusCode::OK),
+        ] {
+            let request = Request::builder()
+                .uri(format!("https://bananas.example{}", path))
+                .method(method)
+                .body(Body::empty())
+                .unwrap();
+            let response = delegate.route(request).await.expect("probe failed");
+            assert_eq!(response.status(), want);
+        }
+
+        assert_metric_hit(&metrics, "http_request_limit_rejected", Some(0));
+    }
+
+    #[tokio::test]
+    async fn test_cors() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 1, Arc::clone(&dml_handler), &metrics)
+            .with_cors(CorsPolicy::new(["https://platanos.example"]));
+
+        // A preflight request is answered without being routed.
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("OPTIONS")
+            .header("Origin", "https://platanos.example")
+            .header("Access-Control-Request-Method", "POST")
+            .body(Body::empty())
+            .unwrap();
+        let response = delegate.route(request).await.expect("preflight failed");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers()["Access-Control-Allow-Origin"],
+            "https://platanos.example"
+        );
+        assert_eq!(response.headers()["Access-Control-Allow-Methods"], "POST");
+        assert!(dml_handler.calls().is_empty());
+
+        // And the subsequent write response carries the CORS headers.
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .header("Origin", "https://platanos.example")
+            .body(Body::from("platanos val=42i 1"))
+            .unwrap();
+        let response = delegate.route(request).await.expect("write failed");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert_eq!(
+            response.headers()["Access-Control-Allow-Origin"],
+            "https://platanos.example"
+        );
+        assert_matches!(
+            dml_handler.calls().as_slice(),
+            [MockDmlHandlerCall::Write { .. }]
+        );
+
+        // Requests from other origins receive no CORS headers.
+        let mut headers = HeaderMap::new();
+        headers.insert("Origin", "https://evil.example".parse().unwrap());
+        assert!(delegate.cors_headers(&headers).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let dml_handler = Arc::new(MockDmlHandler::default().with_write_return([Ok(summary())]));
+        let metrics = Arc::new(metric::Registry::default());
+        let delegate = HttpDelegate::new(MAX_BYTES, 1, Arc::clone(&dml_handler), &metrics)
+            .with_request_timeout(Duration::from_millis(10));
+
+        // A request whose body never completes is aborted.
+        let (_tx, rx) = tokio::sync::mpsc::channel::<Result<&'static str, MockError>>(1);
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::wrap_stream(ReceiverStream::new(rx)))
+            .unwrap();
+        let err = delegate
+            .route(request)
+            .with_timeout_panic(Duration::from_secs(5))
+            .await
+            .expect_err("request should time out");
+        assert_matches!(err, Error::Timeout(d) if d == Duration::from_millis(10));
+        assert_eq!(err.as_status_code(), StatusCode::GATEWAY_TIMEOUT);
+        assert_metric_hit(&metrics, "http_request_timeouts", Some(1));
+
+        // And releases its request permit.
+        let request = Request::builder()
+            .uri("https://bananas.example/api/v2/write?org=bananas&bucket=test")
+            .method("POST")
+            .body(Body::from("platanos val=42i 1"))
+            .unwrap();
+        delegate
+            .route(request)
+            .await
+            .expect("request should be admitted");
+        assert_matches!(
+            dml_handler.calls().as_slice(),
+            [MockDmlHandlerCall::Write { .. }]
+        );
+    }
+
     // This test ensures the request limiter drops requests once the configured
     // number of simultaneous requests are being serviced.
     #[tokio::test]
@@ -1229,7 +4931,11 @@ mod tests {
             .with_timeout_panic(Duration::from_secs(1))
             .await
             .expect_err("second request should be rejected");
-        assert_matches!(err, Error::RequestLimit);
+        assert_matches!(err, Error::RequestLimit { in_flight: 1, .. });
+        assert_eq!(err.as_status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(err.in_flight_requests(), Some(1));
+        // No requests have completed, so the default is advised.
+        assert_eq!(err.retry_after(), Some(1));
 
         // Ensure the "rejected requests" metric was incremented
         assert_metric_hit(&*metrics, "http_request_limit_rejected", Some(1));
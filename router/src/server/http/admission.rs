@@ -0,0 +1,132 @@
+//! Admission control of requests weighted by their (estimated) body size.
+
+use hyper::{
+    header::{CONTENT_ENCODING, CONTENT_LENGTH},
+    HeaderMap,
+};
+use tokio::sync::{Semaphore, SemaphorePermit, TryAcquireError};
+
+/// The assumed compression ratio of an encoded request body, used to estimate
+/// its decoded size from its `Content-Length`.
+const COMPRESSION_RATIO_ESTIMATE: usize = 10;
+
+/// A budget of request body bytes the router buffers & decodes at any one
+/// time.
+///
+/// Unlike the limit on the number of simultaneous requests, this accounts for
+/// the size of each request - a small number of large writes can exhaust the
+/// budget, while many small writes can be serviced concurrently.
+#[derive(Debug)]
+pub(crate) struct ByteBudget {
+    sem: Semaphore,
+    capacity: usize,
+}
+
+impl ByteBudget {
+    /// Initialise a budget allowing at most `capacity` bytes of requests to be
+    /// serviced simultaneously.
+    pub(crate) fn new(capacity: usize) -> Self {
+        // The number of permits is limited by both the semaphore, and the
+        // number that can be acquired at once.
+        let capacity = capacity.min(Semaphore::MAX_PERMITS).min(u32::MAX as usize);
+        Self {
+            sem: Semaphore::new(capacity),
+            capacity,
+        }
+    }
+
+    /// Attempt to reserve `bytes` of the budget, returning [`None`] if there
+    /// is insufficient budget available.
+    ///
+    /// Requests larger than the entire budget are admitted only when no other
+    /// requests are being serviced.
+    pub(crate) fn try_acquire(&self, bytes: usize) -> Option<SemaphorePermit<'_>> {
+        let bytes = bytes.min(self.capacity) as u32;
+        match self.sem.try_acquire_many(bytes) {
+            Ok(p) => Some(p),
+            Err(TryAcquireError::NoPermits) => None,
+            Err(e) => panic!("byte budget error: {}", e),
+        }
+    }
+
+    /// Returns the number of bytes of the budget in use.
+    pub(crate) fn in_use(&self) -> usize {
+        self.capacity - self.sem.available_permits()
+    }
+}
+
+/// Estimate the decoded size of the body of a request with `headers`, which
+/// is at most `max_request_bytes`.
+///
+/// Bodies of unknown size are assumed to be as large as permitted.
+pub(crate) fn estimate_body_size(headers: &HeaderMap, max_request_bytes: usize) -> usize {
+    let content_length = headers
+        .get(&CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+
+    let estimate = match content_length {
+        // A chunked request body of unknown length.
+        None => max_request_bytes,
+        Some(n) if headers.contains_key(&CONTENT_ENCODING) => {
+            n.saturating_mul(COMPRESSION_RATIO_ESTIMATE)
+        }
+        Some(n) => n,
+    };
+
+    estimate.min(max_request_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper::header::HeaderValue;
+
+    fn headers(content_length: Option<&'static str>, encoding: Option<&'static str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(v) = content_length {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from_static(v));
+        }
+        if let Some(v) = encoding {
+            headers.insert(CONTENT_ENCODING, HeaderValue::from_static(v));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_estimate_body_size() {
+        assert_eq!(estimate_body_size(&headers(Some("42"), None), 1024), 42);
+        assert_eq!(estimate_body_size(&headers(Some("4200"), None), 1024), 1024);
+        assert_eq!(
+            estimate_body_size(&headers(Some("42"), Some("gzip")), 1024),
+            420
+        );
+        assert_eq!(estimate_body_size(&headers(None, None), 1024), 1024);
+        assert_eq!(
+            estimate_body_size(&headers(Some("bananas"), None), 1024),
+            1024
+        );
+    }
+
+    #[test]
+    fn test_byte_budget() {
+        let budget = ByteBudget::new(100);
+
+        let a = budget.try_acquire(60).expect("should admit request");
+        assert_eq!(budget.in_use(), 60);
+        assert!(budget.try_acquire(50).is_none());
+
+        // Many small requests may still be admitted.
+        let b = budget.try_acquire(20).expect("should admit request");
+        let c = budget.try_acquire(20).expect("should admit request");
+        assert!(budget.try_acquire(1).is_none());
+
+        drop((a, b, c));
+        assert_eq!(budget.in_use(), 0);
+
+        // A request larger than the budget is admitted only when idle.
+        let big = budget.try_acquire(1000).expect("should admit request");
+        assert!(budget.try_acquire(1).is_none());
+        drop(big);
+    }
+}
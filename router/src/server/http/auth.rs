@@ -0,0 +1,487 @@
+//! Authorization of requests to the router HTTP API.
+
+use async_trait::async_trait;
+use data_types::DatabaseName;
+use hashbrown::HashMap;
+use hyper::{header::AUTHORIZATION, HeaderMap};
+use sha2::{Digest, Sha256};
+use std::{error::Error, fmt::Debug, str::FromStr};
+use thiserror::Error;
+
+/// Errors returned when authorizing a request.
+#[derive(Debug, Error)]
+pub enum AuthError {
+    /// The request contains no `Authorization` header.
+    #[error("no authorization credentials provided")]
+    NoCredentials,
+
+    /// The `Authorization` header cannot be decoded.
+    #[error("invalid authorization header: {0}")]
+    InvalidHeader(String),
+
+    /// The credentials do not identify a known principal.
+    #[error("invalid credentials")]
+    InvalidCredentials,
+
+    /// The credentials are valid, but do not grant the required permission on
+    /// the namespace.
+    #[error("{permission} access to namespace {namespace} denied")]
+    Forbidden {
+        /// The namespace the request targets.
+        namespace: String,
+        /// The permission the request requires.
+        permission: Permission,
+    },
+
+    /// An unknown error occured while authorizing the request.
+    #[error("internal authorizer error: {0}")]
+    Internal(Box<dyn Error + Send + Sync>),
+}
+
+/// The action a request performs on a namespace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    /// Write data to the namespace.
+    Write,
+    /// Delete data from the namespace.
+    Delete,
+}
+
+impl FromStr for Permission {
+    type Err = NamespaceGrantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "write" => Ok(Self::Write),
+            "delete" => Ok(Self::Delete),
+            v => Err(NamespaceGrantError::InvalidPermission(v.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Permission::Write => write!(f, "write"),
+            Permission::Delete => write!(f, "delete"),
+        }
+    }
+}
+
+/// The credentials presented in the `Authorization` header of a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// An InfluxDB 2.x API token, presented as `Authorization: Token <token>`.
+    Token(String),
+
+    /// An InfluxDB 1.x username & password, presented as
+    /// `Authorization: Basic <base64(username:password)>`.
+    Basic {
+        /// The username.
+        username: String,
+        /// The password.
+        password: String,
+    },
+}
+
+impl Credentials {
+    /// A non-secret identifier of the principal presenting these credentials,
+    /// suitable for recording in logs.
+    ///
+    /// Tokens are identified by a prefix of their SHA-256 digest - never by
+    /// the token itself.
+    pub fn principal(&self) -> String {
+        match self {
+            Self::Token(token) => {
+                let digest = Sha256::digest(token.as_bytes());
+                let hex = digest[..8]
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect::<String>();
+                format!("token:{}", hex)
+            }
+            Self::Basic { username, .. } => format!("user:{}", username),
+        }
+    }
+}
+
+impl TryFrom<&HeaderMap> for Credentials {
+    type Error = AuthError;
+
+    fn try_from(headers: &HeaderMap) -> Result<Self, Self::Error> {
+        let header = headers
+            .get(&AUTHORIZATION)
+            .ok_or(AuthError::NoCredentials)?
+            .to_str()
+            .map_err(|e| AuthError::InvalidHeader(e.to_string()))?;
+
+        let (scheme, value) = header
+            .trim()
+            .split_once(' ')
+            .ok_or_else(|| AuthError::InvalidHeader("missing credentials".to_string()))?;
+        let value = value.trim();
+
+        // The authentication scheme is case-insensitive.
+        //
+        // https://www.rfc-editor.org/rfc/rfc7235#section-2.1
+        if scheme.eq_ignore_ascii_case("token") {
+            if value.is_empty() {
+                return Err(AuthError::InvalidHeader("empty token".to_string()));
+            }
+            return Ok(Self::Token(value.to_string()));
+        }
+
+        if scheme.eq_ignore_ascii_case("basic") {
+            let decoded = base64::decode(value)
+                .map_err(|e| AuthError::InvalidHeader(format!("invalid base64: {}", e)))?;
+            let decoded =
+                String::from_utf8(decoded).map_err(|e| AuthError::InvalidHeader(e.to_string()))?;
+            let (username, password) = decoded.split_once(':').ok_or_else(|| {
+                AuthError::InvalidHeader("basic credentials contain no password".to_string())
+            })?;
+            return Ok(Self::Basic {
+                username: username.to_string(),
+                password: password.to_string(),
+            });
+        }
+
+        Err(AuthError::InvalidHeader(format!(
+            "unsupported authorization scheme {}",
+            scheme
+        )))
+    }
+}
+
+/// An abstract authorizer of requests to the router HTTP API.
+///
+/// An [`Authorizer`] is consulted for each write & delete request after the
+/// target namespace is resolved, but before the request body is read or the
+/// request is passed to the DML handler.
+#[async_trait]
+pub trait Authorizer: Debug + Send + Sync {
+    /// Return `Ok(())` if `credentials` grant `permission` on `namespace`.
+    ///
+    /// Implementations should return [`AuthError::InvalidCredentials`] if the
+    /// credentials are not recognised, and [`AuthError::Forbidden`] if they
+    /// are recognised but lack the required permission.
+    async fn authorize(
+        &self,
+        credentials: &Credentials,
+        namespace: &DatabaseName<'static>,
+        permission: Permission,
+    ) -> Result<(), AuthError>;
+}
+
+/// Errors parsing a [`NamespaceGrant`].
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum NamespaceGrantError {
+    /// The grant is not of the form `<token>=<namespace>:<permission>[;...]`.
+    #[error(
+        "invalid token grant, expected <token>=<namespace>:<permission>[;<namespace>:<permission>...]"
+    )]
+    InvalidFormat,
+
+    /// The permission is not `write`, `delete` or `*`.
+    #[error("invalid permission {0:?}, expected write, delete or *")]
+    InvalidPermission(String),
+}
+
+/// A permission granted to a token on one namespace, or on all namespaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NamespacePermissions {
+    /// The namespace, or [`None`] for all namespaces.
+    namespace: Option<String>,
+    /// The permission granted, or [`None`] for all permissions.
+    permission: Option<Permission>,
+}
+
+impl NamespacePermissions {
+    fn grants(&self, namespace: &DatabaseName<'_>, permission: Permission) -> bool {
+        self.namespace
+            .as_deref()
+            .map_or(true, |v| v == namespace.as_str())
+            && self.permission.map_or(true, |v| v == permission)
+    }
+}
+
+impl FromStr for NamespacePermissions {
+    type Err = NamespaceGrantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (namespace, permission) = s
+            .trim()
+            .rsplit_once(':')
+            .ok_or(NamespaceGrantError::InvalidFormat)?;
+        let namespace = match namespace.trim() {
+            "" => return Err(NamespaceGrantError::InvalidFormat),
+            "*" => None,
+            v => Some(v.to_string()),
+        };
+        let permission = match permission.trim() {
+            "*" => None,
+            v => Some(v.parse()?),
+        };
+        Ok(Self {
+            namespace,
+            permission,
+        })
+    }
+}
+
+/// An API token and the namespaces it may write to and delete from, parsed
+/// from the form `<token>=<namespace>:<permission>[;...]`.
+///
+/// The namespace is either a namespace name, or `*` for all namespaces, and
+/// the permission is one of `write`, `delete` or `*` (both), for example:
+///
+///   `s3cret=bananas_test:write;bananas_dev:*`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespaceGrant {
+    token: String,
+    permissions: Vec<NamespacePermissions>,
+}
+
+impl FromStr for NamespaceGrant {
+    type Err = NamespaceGrantError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (token, permissions) = s
+            .split_once('=')
+            .ok_or(NamespaceGrantError::InvalidFormat)?;
+        let token = token.trim();
+        if token.is_empty() {
+            return Err(NamespaceGrantError::InvalidFormat);
+        }
+
+        let permissions = permissions
+            .split(';')
+            .map(NamespacePermissions::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            token: token.to_string(),
+            permissions,
+        })
+    }
+}
+
+/// An [`Authorizer`] of a fixed set of API tokens, each granted permissions
+/// on a set of namespaces.
+///
+/// Tokens are presented either as an InfluxDB 2.x API token, or as the
+/// password of InfluxDB 1.x basic credentials (with any username). Tokens are
+/// held as their SHA-256 digest.
+#[derive(Debug, Default)]
+pub struct StaticTokenAuthorizer {
+    grants: HashMap<Vec<u8>, Vec<NamespacePermissions>>,
+}
+
+impl StaticTokenAuthorizer {
+    /// Grant the token in `grant` the permissions it names, in addition to
+    /// any permissions previously granted to the same token.
+    pub fn with_grant(mut self, grant: NamespaceGrant) -> Self {
+        self.grants
+            .entry(Sha256::digest(grant.token.as_bytes()).to_vec())
+            .or_default()
+            .extend(grant.permissions);
+        self
+    }
+}
+
+#[async_trait]
+impl Authorizer for StaticTokenAuthorizer {
+    async fn authorize(
+        &self,
+        credentials: &Credentials,
+        namespace: &DatabaseName<'static>,
+        permission: Permission,
+    ) -> Result<(), AuthError> {
+        let token = match credentials {
+            Credentials::Token(token) => token,
+            Credentials::Basic { password, .. } => password,
+        };
+
+        let permissions = self
+            .grants
+            .get(Sha256::digest(token.as_bytes()).as_slice())
+            .ok_or(AuthError::InvalidCredentials)?;
+
+        if !permissions.iter().any(|p| p.grants(namespace, permission)) {
+            return Err(AuthError::Forbidden {
+                namespace: namespace.to_string(),
+                permission,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use hyper::header::HeaderValue;
+
+    fn headers(authorization: Option<&str>) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let Some(v) = authorization {
+            headers.insert(AUTHORIZATION, HeaderValue::from_str(v).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn test_credentials_token() {
+        let got = Credentials::try_from(&headers(Some("Token s3cret")));
+        assert_matches!(got, Ok(Credentials::Token(t)) => {
+            assert_eq!(t, "s3cret");
+        });
+
+        // Scheme is case-insensitive
+        let got = Credentials::try_from(&headers(Some("token s3cret")));
+        assert_matches!(got, Ok(Credentials::Token(t)) => {
+            assert_eq!(t, "s3cret");
+        });
+
+        let got = Credentials::try_from(&headers(Some("Token ")));
+        assert_matches!(got, Err(AuthError::InvalidHeader(_)));
+    }
+
+    #[test]
+    fn test_credentials_basic() {
+        let header = format!("Basic {}", base64::encode("bananas:pass:word"));
+        let got = Credentials::try_from(&headers(Some(&header)));
+        assert_matches!(got, Ok(Credentials::Basic{username, password}) => {
+            assert_eq!(username, "bananas");
+            assert_eq!(password, "pass:word");
+        });
+
+        let header = format!("Basic {}", base64::encode("bananas"));
+        let got = Credentials::try_from(&headers(Some(&header)));
+        assert_matches!(got, Err(AuthError::InvalidHeader(_)));
+
+        let got = Credentials::try_from(&headers(Some("Basic !!!")));
+        assert_matches!(got, Err(AuthError::InvalidHeader(_)));
+    }
+
+    #[test]
+    fn test_principal() {
+        let token = Credentials::Token("s3cret".to_string()).principal();
+        assert!(token.starts_with("token:"));
+        assert_eq!(token.len(), "token:".len() + 16);
+        assert!(!token.contains("s3cret"));
+
+        let basic = Credentials::Basic {
+            username: "bananas".to_string(),
+            password: "s3cret".to_string(),
+        };
+        assert_eq!(basic.principal(), "user:bananas");
+    }
+
+    #[test]
+    fn test_credentials_invalid() {
+        let got = Credentials::try_from(&headers(None));
+        assert_matches!(got, Err(AuthError::NoCredentials));
+
+        let got = Credentials::try_from(&headers(Some("Bearer s3cret")));
+        assert_matches!(got, Err(AuthError::InvalidHeader(_)));
+
+        let got = Credentials::try_from(&headers(Some("s3cret")));
+        assert_matches!(got, Err(AuthError::InvalidHeader(_)));
+    }
+
+    #[test]
+    fn test_parse_namespace_grant() {
+        let got = "s3cret=bananas_test:write;*:delete;bananas_dev:*"
+            .parse::<NamespaceGrant>()
+            .unwrap();
+        assert_eq!(got.token, "s3cret");
+        assert_eq!(
+            got.permissions,
+            [
+                NamespacePermissions {
+                    namespace: Some("bananas_test".to_string()),
+                    permission: Some(Permission::Write),
+                },
+                NamespacePermissions {
+                    namespace: None,
+                    permission: Some(Permission::Delete),
+                },
+                NamespacePermissions {
+                    namespace: Some("bananas_dev".to_string()),
+                    permission: None,
+                },
+            ]
+        );
+
+        for v in [
+            "s3cret",
+            "=bananas_test:write",
+            "s3cret=bananas_test",
+            "s3cret=:write",
+        ] {
+            assert_eq!(
+                v.parse::<NamespaceGrant>(),
+                Err(NamespaceGrantError::InvalidFormat),
+                "{v}"
+            );
+        }
+        assert_eq!(
+            "s3cret=bananas_test:read".parse::<NamespaceGrant>(),
+            Err(NamespaceGrantError::InvalidPermission("read".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_static_token_authorizer() {
+        let authorizer = StaticTokenAuthorizer::default()
+            .with_grant("writer=bananas_test:write".parse().unwrap())
+            .with_grant("admin=*:*".parse().unwrap())
+            .with_grant("writer=bananas_dev:delete".parse().unwrap());
+
+        let token = |v: &str| Credentials::Token(v.to_string());
+        let test = DatabaseName::new("bananas_test").unwrap();
+        let dev = DatabaseName::new("bananas_dev").unwrap();
+
+        let got = authorizer
+            .authorize(&token("writer"), &test, Permission::Write)
+            .await;
+        assert_matches!(got, Ok(()));
+
+        // Grants to the same token are merged.
+        let got = authorizer
+            .authorize(&token("writer"), &dev, Permission::Delete)
+            .await;
+        assert_matches!(got, Ok(()));
+
+        let got = authorizer
+            .authorize(&token("writer"), &test, Permission::Delete)
+            .await;
+        assert_matches!(
+            got,
+            Err(AuthError::Forbidden {
+                permission: Permission::Delete,
+                ..
+            })
+        );
+
+        let got = authorizer
+            .authorize(&token("admin"), &dev, Permission::Write)
+            .await;
+        assert_matches!(got, Ok(()));
+
+        // Basic credentials present the token as the password.
+        let basic = Credentials::Basic {
+            username: "anyone".to_string(),
+            password: "writer".to_string(),
+        };
+        let got = authorizer.authorize(&basic, &test, Permission::Write).await;
+        assert_matches!(got, Ok(()));
+
+        let got = authorizer
+            .authorize(&token("bananas"), &test, Permission::Write)
+            .await;
+        assert_matches!(got, Err(AuthError::InvalidCredentials));
+    }
+}
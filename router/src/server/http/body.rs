@@ -0,0 +1,587 @@
+//! Incremental decoding of (optionally compressed) request bodies.
+
+use super::{
+    body_metrics::BodyMetrics,
+    digest::{BodyDigest, DigestError},
+};
+use bytes::Bytes;
+use futures::StreamExt;
+use hyper::Body;
+use std::{
+    io::{self, Write},
+    str::Utf8Error,
+};
+use thiserror::Error;
+
+/// The size of the scratch buffer zstd output is decompressed into.
+const ZSTD_OUTPUT_BUFFER_SIZE: usize = 32 * 1024;
+
+/// Errors returned when reading & decoding a request body.
+#[derive(Debug, Error)]
+pub(crate) enum BodyError {
+    /// The client disconnected.
+    #[error("client disconnected")]
+    ClientHangup(hyper::Error),
+
+    /// The (encoded or decoded) body exceeds the configured maximum size.
+    #[error("max body size ({0} bytes) exceeded")]
+    SizeExceeded(usize),
+
+    /// The body is not a valid gzip stream.
+    #[error("error decoding gzip stream: {0}")]
+    Gzip(io::Error),
+
+    /// The body is not a valid zstd stream.
+    #[error("error decoding zstd stream: {0}")]
+    Zstd(io::Error),
+
+    /// The body is not a valid snappy block.
+    #[error("error decoding snappy block: {0}")]
+    Snappy(snap::Error),
+
+    /// The body does not match the digest supplied by the client.
+    #[error(transparent)]
+    Digest(#[from] DigestError),
+}
+
+/// The `Content-Encoding` of a request body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ContentEncoding {
+    Identity,
+    Gzip,
+    Zstd,
+    Snappy,
+}
+
+impl ContentEncoding {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ContentEncoding::Identity => "identity",
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Zstd => "zstd",
+            ContentEncoding::Snappy => "snappy",
+        }
+    }
+}
+
+/// A [`Write`] sink accumulating at most `max_bytes` of decoded data over its
+/// lifetime.
+///
+/// Writes that would exceed the limit fail, bounding the memory a
+/// decompression bomb can consume.
+#[derive(Debug)]
+struct LimitedBuf {
+    buf: Vec<u8>,
+    written: usize,
+    max_bytes: usize,
+    exceeded: bool,
+}
+
+impl LimitedBuf {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            buf: Vec::new(),
+            written: 0,
+            max_bytes,
+            exceeded: false,
+        }
+    }
+
+    /// Map a decoder error `e` to a [`BodyError`], differentiating between
+    /// the size limit being hit and invalid input.
+    fn map_err(&self, e: io::Error, f: impl FnOnce(io::Error) -> BodyError) -> BodyError {
+        if self.exceeded {
+            return BodyError::SizeExceeded(self.max_bytes);
+        }
+        f(e)
+    }
+}
+
+impl Write for LimitedBuf {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.written + data.len() > self.max_bytes {
+            self.exceeded = true;
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "max decoded body size exceeded",
+            ));
+        }
+        self.written += data.len();
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+enum Inner {
+    Identity(LimitedBuf),
+    Gzip(flate2::write::GzDecoder<LimitedBuf>),
+    Zstd {
+        decoder: zstd::stream::raw::Decoder<'static>,
+        scratch: Vec<u8>,
+        frame_complete: bool,
+        out: LimitedBuf,
+    },
+    // Snappy blocks are not streamable, and are buffered until the end of the
+    // body.
+    Snappy {
+        compressed: Vec<u8>,
+        max_bytes: usize,
+    },
+}
+
+/// An incremental decoder of request bodies, accepting chunks of the
+/// (possibly compressed) body as they are received and making the decoded
+/// data available as soon as possible.
+///
+/// At most `max_bytes` of decoded data is produced - once exceeded, a
+/// [`BodyError::SizeExceeded`] is returned.
+pub(crate) struct BodyDecoder {
+    encoding: ContentEncoding,
+    inner: Inner,
+}
+
+impl std::fmt::Debug for BodyDecoder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyDecoder")
+            .field("encoding", &self.encoding)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BodyDecoder {
+    /// Initialise a decoder of `encoding` bodies, producing at most
+    /// `max_bytes` of decoded data.
+    pub(crate) fn new(encoding: ContentEncoding, max_bytes: usize) -> Result<Self, BodyError> {
+        let inner = match encoding {
+            ContentEncoding::Identity => Inner::Identity(LimitedBuf::new(max_bytes)),
+            ContentEncoding::Gzip => {
+                Inner::Gzip(flate2::write::GzDecoder::new(LimitedBuf::new(max_bytes)))
+            }
+            ContentEncoding::Zstd => Inner::Zstd {
+                decoder: zstd::stream::raw::Decoder::new().map_err(BodyError::Zstd)?,
+                scratch: vec![0; ZSTD_OUTPUT_BUFFER_SIZE],
+                // An empty body contains no incomplete frames.
+                frame_complete: true,
+                out: LimitedBuf::new(max_bytes),
+            },
+            ContentEncoding::Snappy => Inner::Snappy {
+                compressed: Vec::new(),
+                max_bytes,
+            },
+        };
+
+        Ok(Self { encoding, inner })
+    }
+
+    /// Decode the next `chunk` of the body.
+    pub(crate) fn write(&mut self, chunk: &[u8]) -> Result<(), BodyError> {
+        match &mut self.inner {
+            Inner::Identity(buf) => buf
+                .write_all(chunk)
+                .map_err(|_| BodyError::SizeExceeded(buf.max_bytes)),
+            Inner::Gzip(decoder) => decoder
+                .write_all(chunk)
+                .map_err(|e| decoder.get_ref().map_err(e, BodyError::Gzip)),
+            Inner::Zstd {
+                decoder,
+                scratch,
+                frame_complete,
+                out,
+            } => {
+                let mut input = chunk;
+                loop {
+                    use zstd::stream::raw::Operation;
+
+                    let status = decoder
+                        .run_on_buffers(input, scratch)
+                        .map_err(BodyError::Zstd)?;
+                    out.write_all(&scratch[..status.bytes_written])
+                        .map_err(|e| out.map_err(e, BodyError::Zstd))?;
+                    input = &input[status.bytes_read..];
+
+                    // A hint of 0 indicates a frame was fully decoded and
+                    // flushed.
+                    *frame_complete = status.remaining == 0;
+
+                    // Keep going until all the input is consumed, and the
+                    // decoder has no more buffered output to flush.
+                    if input.is_empty() && status.bytes_written < scratch.len() {
+                        return Ok(());
+                    }
+                }
+            }
+            Inner::Snappy { compressed, .. } => {
+                compressed.extend_from_slice(chunk);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove and return the data decoded so far.
+    pub(crate) fn take_decoded(&mut self) -> Vec<u8> {
+        match &mut self.inner {
+            Inner::Identity(buf) => std::mem::take(&mut buf.buf),
+            Inner::Gzip(decoder) => std::mem::take(&mut decoder.get_mut().buf),
+            Inner::Zstd { out, .. } => std::mem::take(&mut out.buf),
+            Inner::Snappy { .. } => Vec::new(),
+        }
+    }
+
+    /// Signal the end of the body, returning any remaining decoded data not
+    /// yet returned by [`Self::take_decoded()`].
+    ///
+    /// Returns an error if the body is truncated.
+    pub(crate) fn finish(self) -> Result<Vec<u8>, BodyError> {
+        match self.inner {
+            Inner::Identity(buf) => Ok(buf.buf),
+            Inner::Gzip(mut decoder) => {
+                // Ensure the gzip trailer was received.
+                decoder
+                    .try_finish()
+                    .map_err(|e| decoder.get_ref().map_err(e, BodyError::Gzip))?;
+                decoder.finish().map(|b| b.buf).map_err(BodyError::Gzip)
+            }
+            Inner::Zstd {
+                frame_complete,
+                out,
+                ..
+            } => {
+                if !frame_complete {
+                    return Err(BodyError::Zstd(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "incomplete zstd frame",
+                    )));
+                }
+                Ok(out.buf)
+            }
+            Inner::Snappy {
+                compressed,
+                max_bytes,
+            } => {
+                // Snappy blocks are prefixed with their decompressed length,
+                // allowing oversized bodies to be rejected before they are
+                // decompressed.
+                let decoded_len =
+                    snap::raw::decompress_len(&compressed).map_err(BodyError::Snappy)?;
+                if decoded_len > max_bytes {
+                    return Err(BodyError::SizeExceeded(max_bytes));
+                }
+                snap::raw::Decoder::new()
+                    .decompress_vec(&compressed)
+                    .map_err(BodyError::Snappy)
+            }
+        }
+    }
+}
+
+/// A request body, decoded incrementally as it is received.
+#[derive(Debug)]
+pub(crate) struct DecodedBody {
+    payload: Body,
+    encoding: ContentEncoding,
+    decoder: Option<BodyDecoder>,
+    digest: Option<BodyDigest>,
+    metrics: Option<BodyMetrics>,
+    raw_bytes: usize,
+    decoded_bytes: usize,
+    max_bytes: usize,
+}
+
+impl DecodedBody {
+    /// Decode `payload` according to `encoding`, accepting at most
+    /// `max_bytes` of both encoded and decoded data.
+    pub(crate) fn new(
+        payload: Body,
+        encoding: ContentEncoding,
+        max_bytes: usize,
+    ) -> Result<Self, BodyError> {
+        Ok(Self {
+            payload,
+            encoding,
+            decoder: Some(BodyDecoder::new(encoding, max_bytes)?),
+            digest: None,
+            metrics: None,
+            raw_bytes: 0,
+            decoded_bytes: 0,
+            max_bytes,
+        })
+    }
+
+    /// Verify the (encoded) body matches `digest`, if any.
+    ///
+    /// A body with a digest is buffered and verified in its entirety before
+    /// any of it is decoded, so that a corrupt body is reported as such
+    /// (rather than as invalid content), and none of it is processed.
+    pub(crate) fn with_digest(mut self, digest: Option<BodyDigest>) -> Self {
+        self.digest = digest;
+        self
+    }
+
+    /// Record the encoded & decoded size of the body in `metrics` once it has
+    /// been read in its entirety, and any rejection of a compressed body
+    /// that decodes to more than the maximum size.
+    pub(crate) fn with_metrics(mut self, metrics: BodyMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Return the next chunk of decoded data, or [`None`] once the entire
+    /// body has been returned.
+    pub(crate) async fn next(&mut self) -> Result<Option<Vec<u8>>, BodyError> {
+        let ret = self.next_decoded().await;
+
+        if let Some(metrics) = &self.metrics {
+            match &ret {
+                Ok(Some(v)) => {
+                    self.decoded_bytes += v.len();
+                    // The final chunk is returned as the decoder is finished.
+                    if self.decoder.is_none() {
+                        metrics.record(self.encoding, self.raw_bytes, self.decoded_bytes);
+                    }
+                }
+                // The raw body was entirely within the limit, so it was the
+                // decoded output that exceeded it.
+                Err(BodyError::SizeExceeded(_))
+                    if self.raw_bytes <= self.max_bytes
+                        && self.encoding != ContentEncoding::Identity =>
+                {
+                    metrics.record_decompression_limit(self.encoding);
+                }
+                Ok(None) | Err(_) => {}
+            }
+        }
+
+        ret
+    }
+
+    /// Return the next chunk of decoded data, as [`Self::next()`].
+    async fn next_decoded(&mut self) -> Result<Option<Vec<u8>>, BodyError> {
+        if let Some(mut digest) = self.digest.take() {
+            let mut raw = Vec::new();
+            while let Some(chunk) = self.next_raw().await? {
+                digest.update(&chunk);
+                raw.extend_from_slice(&chunk);
+            }
+            digest.verify()?;
+
+            let mut decoder = self.decoder.take().expect("decoder already finished");
+            decoder.write(&raw)?;
+            let mut decoded = decoder.take_decoded();
+            decoded.extend(decoder.finish()?);
+            return Ok(Some(decoded));
+        }
+
+        loop {
+            if self.decoder.is_none() {
+                return Ok(None);
+            }
+
+            let chunk = match self.next_raw().await? {
+                Some(v) => v,
+                None => {
+                    let decoder = self.decoder.take().expect("decoder already finished");
+                    return decoder.finish().map(Some);
+                }
+            };
+
+            let decoder = self.decoder.as_mut().expect("decoder already finished");
+            decoder.write(&chunk)?;
+            let decoded = decoder.take_decoded();
+            if !decoded.is_empty() {
+                return Ok(Some(decoded));
+            }
+        }
+    }
+
+    /// Return the next chunk of the (encoded) body as received, or [`None`]
+    /// at the end of the body.
+    async fn next_raw(&mut self) -> Result<Option<Bytes>, BodyError> {
+        let chunk = match self.payload.next().await {
+            Some(chunk) => chunk.map_err(BodyError::ClientHangup)?,
+            None => return Ok(None),
+        };
+
+        // limit max size of in-memory payload
+        self.raw_bytes += chunk.len();
+        if self.raw_bytes > self.max_bytes {
+            return Err(BodyError::SizeExceeded(self.max_bytes));
+        }
+
+        Ok(Some(chunk))
+    }
+}
+
+/// A buffer of decoded line protocol, yielding the complete lines it contains
+/// as data is appended.
+///
+/// Lines are split according to the line protocol rules (a newline within a
+/// quoted string field value does not end a line), and never within a UTF-8
+/// code point.
+#[derive(Debug, Default)]
+pub(crate) struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    /// Append `data` to the buffer, returning (and removing) any complete
+    /// lines it now contains.
+    pub(crate) fn push(&mut self, data: &[u8]) -> Result<Option<String>, Utf8Error> {
+        self.buf.extend_from_slice(data);
+
+        // A line can only have been completed if the new data contains a
+        // newline.
+        if !data.contains(&b'\n') {
+            return Ok(None);
+        }
+
+        // The buffer may end part way through a multi-byte code point.
+        let s = match std::str::from_utf8(&self.buf) {
+            Ok(v) => v,
+            Err(e) if e.error_len().is_none() => {
+                std::str::from_utf8(&self.buf[..e.valid_up_to()]).expect("valid utf8 prefix")
+            }
+            Err(e) => return Err(e),
+        };
+
+        // The buffer always starts at the beginning of a line, and the last
+        // split is incomplete.
+        let partial = influxdb_line_protocol::split_lines(s)
+            .last()
+            .unwrap_or_default();
+        let complete_len = s.len() - partial.len();
+        if complete_len == 0 {
+            return Ok(None);
+        }
+
+        let partial = self.buf.split_off(complete_len);
+        let complete = std::mem::replace(&mut self.buf, partial);
+        String::from_utf8(complete)
+            .map(Some)
+            .map_err(|e| e.utf8_error())
+    }
+
+    /// Return the remaining (possibly incomplete) line in the buffer.
+    pub(crate) fn finish(self) -> Result<String, Utf8Error> {
+        String::from_utf8(self.buf).map_err(|e| e.utf8_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use flate2::{write::GzEncoder, Compression};
+
+    const MAX_BYTES: usize = 1024;
+
+    fn encode(encoding: ContentEncoding, data: &[u8]) -> Vec<u8> {
+        match encoding {
+            ContentEncoding::Identity => data.to_vec(),
+            ContentEncoding::Gzip => {
+                let mut e = GzEncoder::new(Vec::new(), Compression::default());
+                e.write_all(data).unwrap();
+                e.finish().unwrap()
+            }
+            ContentEncoding::Zstd => zstd::stream::encode_all(data, 0).unwrap(),
+            ContentEncoding::Snappy => snap::raw::Encoder::new().compress_vec(data).unwrap(),
+        }
+    }
+
+    /// Decode `body`, feeding it to the decoder one byte at a time.
+    fn decode(encoding: ContentEncoding, body: &[u8]) -> Result<Vec<u8>, BodyError> {
+        let mut decoder = BodyDecoder::new(encoding, MAX_BYTES)?;
+        let mut got = Vec::new();
+        for b in body {
+            decoder.write(&[*b])?;
+            got.extend(decoder.take_decoded());
+        }
+        got.extend(decoder.finish()?);
+        Ok(got)
+    }
+
+    const ENCODINGS: [ContentEncoding; 4] = [
+        ContentEncoding::Identity,
+        ContentEncoding::Gzip,
+        ContentEncoding::Zstd,
+        ContentEncoding::Snappy,
+    ];
+
+    #[test]
+    fn test_round_trip() {
+        let data = "platanos,tag=A val=42i 123456\n".repeat(20);
+        for encoding in ENCODINGS {
+            let got = decode(encoding, &encode(encoding, data.as_bytes()))
+                .unwrap_or_else(|e| panic!("failed to decode {:?}: {}", encoding, e));
+            assert_eq!(got, data.as_bytes(), "{:?}", encoding);
+        }
+    }
+
+    #[test]
+    fn test_size_exceeded() {
+        let data = vec![42; MAX_BYTES + 1];
+        for encoding in ENCODINGS {
+            assert_matches!(
+                decode(encoding, &encode(encoding, &data)),
+                Err(BodyError::SizeExceeded(MAX_BYTES)),
+                "{:?}",
+                encoding
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_buffer() {
+        let mut buf = LineBuffer::default();
+        assert_eq!(buf.push(b"m f=1i 1").unwrap(), None);
+        assert_eq!(
+            buf.push(b"\nm f=2i 2\nm f=").unwrap().unwrap(),
+            "m f=1i 1\nm f=2i 2\n"
+        );
+
+        // A newline in a quoted string does not complete the line.
+        assert_eq!(buf.push(b"\"bana\nnas\" 3").unwrap(), None);
+
+        // A multi-byte code point split across pushes is not split.
+        let platano = "pl\u{e1}tano".as_bytes();
+        assert_eq!(
+            buf.push(&[b"\nm f=\"".as_slice(), &platano[..3]].concat())
+                .unwrap()
+                .unwrap(),
+            "m f=\"bana\nnas\" 3\n"
+        );
+        assert_eq!(buf.push(&platano[3..]).unwrap(), None);
+        assert_eq!(buf.push(b"\" 4").unwrap(), None);
+        assert_eq!(buf.finish().unwrap(), "m f=\"pl\u{e1}tano\" 4");
+
+        // Invalid UTF-8 is rejected.
+        let mut buf = LineBuffer::default();
+        assert!(buf.push(&[0xc3, 0x28, b'\n']).is_err());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let data = "platanos,tag=A val=42i 123456\n".repeat(20);
+
+        let body = encode(ContentEncoding::Gzip, data.as_bytes());
+        assert_matches!(
+            decode(ContentEncoding::Gzip, &body[..body.len() - 4]),
+            Err(BodyError::Gzip(_))
+        );
+
+        let body = encode(ContentEncoding::Zstd, data.as_bytes());
+        assert_matches!(
+            decode(ContentEncoding::Zstd, &body[..body.len() - 4]),
+            Err(BodyError::Zstd(_))
+        );
+
+        let body = encode(ContentEncoding::Snappy, data.as_bytes());
+        assert_matches!(
+            decode(ContentEncoding::Snappy, &body[..body.len() - 4]),
+            Err(BodyError::Snappy(_))
+        );
+    }
+}
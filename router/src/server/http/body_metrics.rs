@@ -0,0 +1,146 @@
+//! Metrics describing the size of request bodies.
+
+use super::body::ContentEncoding;
+use metric::{Metric, U64Counter, U64Histogram, U64HistogramOptions};
+
+/// The metric attribute recording the `Content-Encoding` of a request body.
+const ENCODING_ATTRIBUTE: &str = "encoding";
+
+/// The size (in bytes) & compression ratio histograms of request bodies,
+/// broken down by content encoding.
+///
+/// These allow operators to choose the maximum request size based on the
+/// distribution of real request sizes.
+#[derive(Debug, Clone)]
+pub(crate) struct BodyMetrics {
+    encoded_bytes: Metric<U64Histogram>,
+    decoded_bytes: Metric<U64Histogram>,
+    decompression_ratio: Metric<U64Histogram>,
+    decompression_limit_rejected: Metric<U64Counter>,
+}
+
+impl BodyMetrics {
+    pub(crate) fn new(metrics: &metric::Registry) -> Self {
+        let size_buckets = || {
+            U64HistogramOptions::new([
+                1024,              // 1 KiB
+                16 * 1024,         // 16 KiB
+                64 * 1024,         // 64 KiB
+                256 * 1024,        // 256 KiB
+                1024 * 1024,       // 1 MiB
+                4 * 1024 * 1024,   // 4 MiB
+                16 * 1024 * 1024,  // 16 MiB
+                64 * 1024 * 1024,  // 64 MiB
+                256 * 1024 * 1024, // 256 MiB
+                u64::MAX,          // Inf
+            ])
+        };
+
+        let encoded_bytes = metrics.register_metric_with_options(
+            "http_request_body_encoded_bytes",
+            "size of request bodies as received, before any content encoding is decoded",
+            size_buckets,
+        );
+        let decoded_bytes = metrics.register_metric_with_options(
+            "http_request_body_decoded_bytes",
+            "size of request bodies after any content encoding is decoded",
+            size_buckets,
+        );
+        let decompression_ratio = metrics.register_metric_with_options(
+            "http_request_body_decompression_ratio",
+            "ratio of the decoded to encoded size of compressed request bodies",
+            || U64HistogramOptions::new([1, 2, 5, 10, 20, 50, 100, 1000, u64::MAX]),
+        );
+        let decompression_limit_rejected = metrics.register_metric(
+            "http_request_decompression_limit_rejected",
+            "number of compressed requests rejected as their decoded body exceeds the maximum request size",
+        );
+
+        Self {
+            encoded_bytes,
+            decoded_bytes,
+            decompression_ratio,
+            decompression_limit_rejected,
+        }
+    }
+
+    /// Record a completely read body, `encoded` bytes in size as received
+    /// and `decoded` bytes in size once decoded.
+    pub(crate) fn record(&self, encoding: ContentEncoding, encoded: usize, decoded: usize) {
+        let attr = [(ENCODING_ATTRIBUTE, encoding.as_str())];
+        self.encoded_bytes.recorder(attr).record(encoded as _);
+        self.decoded_bytes.recorder(attr).record(decoded as _);
+
+        // The ratio is only meaningful for compressed bodies.
+        if encoding != ContentEncoding::Identity && encoded > 0 {
+            self.decompression_ratio
+                .recorder(attr)
+                .record((decoded / encoded) as _);
+        }
+    }
+
+    /// Record the rejection of a compressed body whose decoded size exceeds
+    /// the maximum request size (a potential decompression bomb).
+    pub(crate) fn record_decompression_limit(&self, encoding: ContentEncoding) {
+        self.decompression_limit_rejected
+            .recorder([(ENCODING_ATTRIBUTE, encoding.as_str())])
+            .inc(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metric::{Attributes, Metric};
+
+    fn histogram(
+        metrics: &metric::Registry,
+        name: &'static str,
+        encoding: &'static str,
+    ) -> Option<(u64, u64)> {
+        metrics
+            .get_instrument::<Metric<U64Histogram>>(name)
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[(ENCODING_ATTRIBUTE, encoding)]))
+            .map(|o| {
+                let v = o.fetch();
+                (v.sample_count(), v.total)
+            })
+    }
+
+    #[test]
+    fn test_record() {
+        let metrics = metric::Registry::default();
+        let body_metrics = BodyMetrics::new(&metrics);
+
+        body_metrics.record(ContentEncoding::Gzip, 10, 100);
+        body_metrics.record(ContentEncoding::Identity, 42, 42);
+
+        assert_eq!(
+            histogram(&metrics, "http_request_body_encoded_bytes", "gzip"),
+            Some((1, 10))
+        );
+        assert_eq!(
+            histogram(&metrics, "http_request_body_decoded_bytes", "gzip"),
+            Some((1, 100))
+        );
+        assert_eq!(
+            histogram(&metrics, "http_request_body_decompression_ratio", "gzip"),
+            Some((1, 10))
+        );
+
+        assert_eq!(
+            histogram(&metrics, "http_request_body_decoded_bytes", "identity"),
+            Some((1, 42))
+        );
+        // Uncompressed bodies have no compression ratio.
+        assert_eq!(
+            histogram(
+                &metrics,
+                "http_request_body_decompression_ratio",
+                "identity"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,234 @@
+//! Cross-Origin Resource Sharing (CORS) support, allowing browser-based
+//! clients to write & delete.
+
+use super::WRITE_TOKEN_HTTP_HEADER;
+use hashbrown::HashSet;
+use hyper::{
+    header::{
+        HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+        ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_EXPOSE_HEADERS, ACCESS_CONTROL_MAX_AGE,
+        ACCESS_CONTROL_REQUEST_HEADERS, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, RETRY_AFTER, VARY,
+    },
+    Body, HeaderMap, Method, Request, Response, StatusCode,
+};
+use std::time::Duration;
+
+/// The methods of the requests served by the router.
+const ALLOWED_METHODS: &str = "POST";
+
+/// The request headers permitted when the preflight request does not list
+/// any.
+const ALLOWED_HEADERS: &str = "Authorization, Content-Type, Content-Encoding";
+
+/// The origins permitted to make cross-origin requests.
+#[derive(Debug)]
+enum AllowedOrigins {
+    Any,
+    List(HashSet<String>),
+}
+
+/// The CORS policy of the router, describing which (browser) origins may
+/// make requests to it.
+///
+/// Preflight (`OPTIONS`) requests from a permitted origin are answered
+/// without being routed, and the responses to all other requests from a
+/// permitted origin include the `Access-Control-Allow-*` headers required for
+/// the browser to expose them to the caller. Requests from any other origin
+/// are served without CORS headers, causing the browser to reject them.
+#[derive(Debug)]
+pub struct CorsPolicy {
+    origins: AllowedOrigins,
+    max_age: Option<Duration>,
+}
+
+impl CorsPolicy {
+    /// Permit requests from the specified `origins`, such as
+    /// `https://example.com`.
+    pub fn new(origins: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            origins: AllowedOrigins::List(origins.into_iter().map(Into::into).collect()),
+            max_age: None,
+        }
+    }
+
+    /// Permit requests from any origin.
+    pub fn any_origin() -> Self {
+        Self {
+            origins: AllowedOrigins::Any,
+            max_age: None,
+        }
+    }
+
+    /// Allow browsers to cache the result of a preflight request for
+    /// `max_age`.
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Returns the `Origin` of a request with `headers`, if it is permitted.
+    fn allowed_origin<'a>(&self, headers: &'a HeaderMap) -> Option<&'a HeaderValue> {
+        let origin = headers.get(ORIGIN)?;
+        match &self.origins {
+            AllowedOrigins::Any => Some(origin),
+            AllowedOrigins::List(list) => origin
+                .to_str()
+                .ok()
+                .filter(|v| list.contains(*v))
+                .map(|_| origin),
+        }
+    }
+
+    /// Returns the CORS headers to include in the response to a request with
+    /// `headers`.
+    ///
+    /// The returned map is empty if the request is not a permitted
+    /// cross-origin request.
+    pub(crate) fn response_headers(&self, headers: &HeaderMap) -> HeaderMap {
+        let mut out = HeaderMap::new();
+        if let Some(origin) = self.allowed_origin(headers) {
+            // The origin is echoed back (rather than using a "*" wildcard) to
+            // permit credentialed requests, and so the response varies by
+            // origin.
+            out.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            out.insert(VARY, HeaderValue::from(ORIGIN));
+            out.insert(
+                ACCESS_CONTROL_EXPOSE_HEADERS,
+                HeaderValue::from_str(&format!("{}, {}", WRITE_TOKEN_HTTP_HEADER, RETRY_AFTER))
+                    .expect("valid header value"),
+            );
+        }
+        out
+    }
+
+    /// Returns the response to `req` if it is a CORS preflight request.
+    pub(crate) fn preflight(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        if req.method() != Method::OPTIONS
+            || !req.headers().contains_key(ORIGIN)
+            || !req.headers().contains_key(ACCESS_CONTROL_REQUEST_METHOD)
+        {
+            return None;
+        }
+
+        let mut response = Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .body(Body::empty())
+            .unwrap();
+
+        let origin = match self.allowed_origin(req.headers()) {
+            Some(v) => v.clone(),
+            // Without the CORS headers the browser rejects the request.
+            None => return Some(response),
+        };
+
+        let headers = response.headers_mut();
+        headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        headers.insert(VARY, HeaderValue::from(ORIGIN));
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_METHODS,
+            HeaderValue::from_static(ALLOWED_METHODS),
+        );
+        headers.insert(
+            ACCESS_CONTROL_ALLOW_HEADERS,
+            req.headers()
+                .get(ACCESS_CONTROL_REQUEST_HEADERS)
+                .cloned()
+                .unwrap_or_else(|| HeaderValue::from_static(ALLOWED_HEADERS)),
+        );
+        if let Some(max_age) = self.max_age {
+            headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+        }
+
+        Some(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn preflight_request(origin: &'static str) -> Request<Body> {
+        Request::builder()
+            .method(Method::OPTIONS)
+            .uri("https://bananas.example/api/v2/write")
+            .header(ORIGIN, origin)
+            .header(ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(
+                ACCESS_CONTROL_REQUEST_HEADERS,
+                "authorization, content-type",
+            )
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_preflight() {
+        let policy =
+            CorsPolicy::new(["https://platanos.example"]).with_max_age(Duration::from_secs(600));
+
+        let response = policy
+            .preflight(&preflight_request("https://platanos.example"))
+            .expect("should be a preflight request");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://platanos.example"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_ALLOW_METHODS], "POST");
+        assert_eq!(
+            headers[ACCESS_CONTROL_ALLOW_HEADERS],
+            "authorization, content-type"
+        );
+        assert_eq!(headers[ACCESS_CONTROL_MAX_AGE], "600");
+
+        // A disallowed origin receives no CORS headers.
+        let response = policy
+            .preflight(&preflight_request("https://evil.example"))
+            .expect("should be a preflight request");
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(response.headers().is_empty());
+    }
+
+    #[test]
+    fn test_not_preflight() {
+        let policy = CorsPolicy::any_origin();
+
+        // An OPTIONS request without the preflight headers.
+        let req = Request::builder()
+            .method(Method::OPTIONS)
+            .uri("https://bananas.example/api/v2/write")
+            .body(Body::empty())
+            .unwrap();
+        assert!(policy.preflight(&req).is_none());
+
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri("https://bananas.example/api/v2/write")
+            .header(ORIGIN, "https://platanos.example")
+            .body(Body::empty())
+            .unwrap();
+        assert!(policy.preflight(&req).is_none());
+    }
+
+    #[test]
+    fn test_response_headers() {
+        let mut headers = HeaderMap::new();
+        assert!(CorsPolicy::any_origin()
+            .response_headers(&headers)
+            .is_empty());
+
+        headers.insert(ORIGIN, HeaderValue::from_static("https://platanos.example"));
+
+        let got = CorsPolicy::any_origin().response_headers(&headers);
+        assert_eq!(got[ACCESS_CONTROL_ALLOW_ORIGIN], "https://platanos.example");
+        assert_eq!(got[VARY], "origin");
+        assert_eq!(
+            got[ACCESS_CONTROL_EXPOSE_HEADERS],
+            "X-IOx-Write-Token, retry-after"
+        );
+
+        let got = CorsPolicy::new(["https://bananas.example"]).response_headers(&headers);
+        assert!(got.is_empty());
+    }
+}
@@ -27,6 +27,16 @@ pub enum Error {
 
     #[snafu(display(r#"Delete must include a start time and a stop time'{}'"#, value))]
     StartStopInvalid { value: String },
+
+    #[snafu(display("Delete request contains no delete specifications"))]
+    NoDeletes,
+
+    #[snafu(display(
+        "Delete request contains {} delete specifications, exceeding the maximum of {}",
+        count,
+        max
+    ))]
+    TooManyDeletes { count: usize, max: usize },
 }
 
 /// Result type for Parser Cient
@@ -34,6 +44,9 @@ pub(crate) type Result<T, E = Error> = std::result::Result<T, E>;
 
 const FLUX_TABLE: &str = "_measurement";
 
+/// The maximum number of delete specifications in a single request.
+pub(crate) const MAX_DELETES_PER_REQUEST: usize = 100;
+
 /// Data of a parsed delete
 ///
 /// Note that this struct and its functions are used to parse FLUX DELETE,
@@ -61,6 +74,47 @@ pub(crate) struct HttpDeleteRequest {
 pub(crate) fn parse_http_delete_request(input: &str) -> Result<HttpDeleteRequest> {
     let parsed_obj: serde_json::Value =
         serde_json::from_str(input).context(InvalidSnafu { value: input })?;
+    parse_delete_object(parsed_obj, input)
+}
+
+/// Return the parsed data of an influx delete containing either a single
+/// delete specification (as accepted by [`parse_http_delete_request()`]), or a
+/// JSON array of up to [`MAX_DELETES_PER_REQUEST`] of them:
+///   [{"predicate":"_measurement=mytable AND host=\"a\"","start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"},
+///    {"predicate":"_measurement=mytable AND host=\"b\"","start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"}]
+///
+/// If any specification is invalid, an error is returned for the request as a
+/// whole. Once parsed, the deletes are applied in order but not atomically - a
+/// failed delete is reported with the number of deletes applied before it (see
+/// [`Error::PartialDelete`](super::Error::PartialDelete)).
+pub(crate) fn parse_http_delete_requests(input: &str) -> Result<Vec<HttpDeleteRequest>> {
+    if !input.trim_start().starts_with('[') {
+        return parse_http_delete_request(input).map(|d| vec![d]);
+    }
+
+    let items: Vec<serde_json::Value> =
+        serde_json::from_str(input).context(InvalidSnafu { value: input })?;
+    if items.is_empty() {
+        return Err(Error::NoDeletes);
+    }
+    if items.len() > MAX_DELETES_PER_REQUEST {
+        return Err(Error::TooManyDeletes {
+            count: items.len(),
+            max: MAX_DELETES_PER_REQUEST,
+        });
+    }
+
+    items
+        .into_iter()
+        .map(|item| {
+            let value = item.to_string();
+            parse_delete_object(item, &value)
+        })
+        .collect()
+}
+
+/// Parse the delete specification `parsed_obj`, decoded from `input`.
+fn parse_delete_object(parsed_obj: serde_json::Value, input: &str) -> Result<HttpDeleteRequest> {
     let mut parsed_delete = HttpDeleteRequest::default();
 
     if let serde_json::Value::Object(items) = parsed_obj {
@@ -228,4 +282,63 @@ mod tests {
         let err = result.unwrap_err();
         assert!(err.to_string().contains("Unable to parse delete string"));
     }
+
+    #[test]
+    fn test_parse_http_delete_multiple() {
+        let delete_str = r#"[
+            {"predicate":"_measurement=mytable AND host=\"a\"","start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"},
+            {"predicate":"host=\"b\"","start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"}
+        ]"#;
+
+        let expected = vec![
+            HttpDeleteRequest {
+                table_name: "mytable".to_string(),
+                predicate: "host=\"a\"".to_string(),
+                start_time: "1970-01-01T00:00:00Z".to_string(),
+                stop_time: "2070-01-02T00:00:00Z".to_string(),
+            },
+            HttpDeleteRequest {
+                table_name: "".to_string(),
+                predicate: "host=\"b\"".to_string(),
+                start_time: "1970-01-01T00:00:00Z".to_string(),
+                stop_time: "2070-01-02T00:00:00Z".to_string(),
+            },
+        ];
+        assert_eq!(parse_http_delete_requests(delete_str).unwrap(), expected);
+
+        // A single delete is also accepted.
+        let delete_str = r#"{"predicate":"host=\"b\"","start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"}"#;
+        assert_eq!(
+            parse_http_delete_requests(delete_str).unwrap(),
+            expected[1..]
+        );
+    }
+
+    #[test]
+    fn test_parse_http_delete_multiple_negative() {
+        let err = parse_http_delete_requests("[]").unwrap_err();
+        assert!(matches!(err, Error::NoDeletes));
+
+        // A single invalid delete fails the request.
+        let delete_str = r#"[
+            {"start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"},
+            {"start":"1970-01-01T00:00:00Z"}
+        ]"#;
+        let err = parse_http_delete_requests(delete_str).unwrap_err();
+        assert!(matches!(err, Error::StartStopInvalid { .. }));
+
+        let delete_str = format!(
+            "[{}]",
+            vec![r#"{"start":"1970-01-01T00:00:00Z","stop":"2070-01-02T00:00:00Z"}"#; 101]
+                .join(",")
+        );
+        let err = parse_http_delete_requests(&delete_str).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::TooManyDeletes {
+                count: 101,
+                max: MAX_DELETES_PER_REQUEST
+            }
+        ));
+    }
 }
@@ -0,0 +1,233 @@
+//! Verification of client-supplied request body digests.
+
+use hyper::HeaderMap;
+use md5::Md5;
+use sha2::{Digest, Sha256, Sha512};
+use thiserror::Error;
+
+/// The header carrying the digest of the request body, as described in [RFC
+/// 9530].
+///
+/// [RFC 9530]: https://www.rfc-editor.org/rfc/rfc9530
+const CONTENT_DIGEST: &str = "Content-Digest";
+
+/// The legacy header carrying the base64-encoded MD5 digest of the request
+/// body, as described in [RFC 1864].
+///
+/// [RFC 1864]: https://www.rfc-editor.org/rfc/rfc1864
+const CONTENT_MD5: &str = "Content-MD5";
+
+/// Errors returned when verifying the digest of a request body.
+#[derive(Debug, Error)]
+pub enum DigestError {
+    /// The digest header value cannot be parsed.
+    #[error("invalid {0} header value")]
+    InvalidHeader(&'static str),
+
+    /// The `Content-Digest` header specifies no supported algorithm.
+    #[error("no supported algorithm in content-digest header (supported: sha-256, sha-512)")]
+    UnsupportedAlgorithm,
+
+    /// The digest of the received body does not match the header.
+    #[error("request body does not match the {0} header - the body may be corrupt")]
+    Mismatch(&'static str),
+}
+
+#[derive(Debug, Clone)]
+enum Hasher {
+    Md5(Md5),
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+/// A verifier of the digest of a request body, accepting the (encoded) body
+/// as it is received.
+///
+/// As specified by the respective RFCs, the digest is of the body as sent by
+/// the client, before any `Content-Encoding` is decoded.
+#[derive(Debug, Clone)]
+pub(crate) struct BodyDigest {
+    header: &'static str,
+    hasher: Hasher,
+    expected: Vec<u8>,
+}
+
+impl BodyDigest {
+    /// Read the digest of the request body from the request `headers`, if
+    /// any.
+    ///
+    /// The `Content-Digest` header is preferred over the `Content-MD5`
+    /// header if both are specified.
+    pub(crate) fn from_headers(headers: &HeaderMap) -> Result<Option<Self>, DigestError> {
+        if let Some(v) = headers.get(CONTENT_DIGEST) {
+            let v = v
+                .to_str()
+                .map_err(|_| DigestError::InvalidHeader(CONTENT_DIGEST))?;
+            return parse_content_digest(v).map(Some);
+        }
+
+        if let Some(v) = headers.get(CONTENT_MD5) {
+            let expected = v
+                .to_str()
+                .ok()
+                .and_then(|v| base64::decode(v.trim()).ok())
+                .ok_or(DigestError::InvalidHeader(CONTENT_MD5))?;
+            return Ok(Some(Self {
+                header: CONTENT_MD5,
+                hasher: Hasher::Md5(Md5::new()),
+                expected,
+            }));
+        }
+
+        Ok(None)
+    }
+
+    /// Add the next `chunk` of the body to the digest.
+    pub(crate) fn update(&mut self, chunk: &[u8]) {
+        match &mut self.hasher {
+            Hasher::Md5(h) => h.update(chunk),
+            Hasher::Sha256(h) => h.update(chunk),
+            Hasher::Sha512(h) => h.update(chunk),
+        }
+    }
+
+    /// Verify the digest of the complete body matches the header.
+    pub(crate) fn verify(self) -> Result<(), DigestError> {
+        let got = match self.hasher {
+            Hasher::Md5(h) => h.finalize().to_vec(),
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+        };
+
+        if got != self.expected {
+            return Err(DigestError::Mismatch(self.header));
+        }
+        Ok(())
+    }
+}
+
+/// Parse a `Content-Digest` header value of the form
+/// `sha-256=:<base64>:, sha-512=:<base64>:`, selecting the strongest
+/// supported algorithm.
+fn parse_content_digest(v: &str) -> Result<BodyDigest, DigestError> {
+    let mut best: Option<(Hasher, Vec<u8>)> = None;
+
+    for member in v.split(',') {
+        let (algorithm, value) = member
+            .split_once('=')
+            .ok_or(DigestError::InvalidHeader(CONTENT_DIGEST))?;
+        let value = value
+            .trim()
+            .strip_prefix(':')
+            .and_then(|v| v.strip_suffix(':'))
+            .and_then(|v| base64::decode(v).ok())
+            .ok_or(DigestError::InvalidHeader(CONTENT_DIGEST))?;
+
+        match algorithm.trim().to_ascii_lowercase().as_str() {
+            "sha-512" => best = Some((Hasher::Sha512(Sha512::new()), value)),
+            "sha-256" if !matches!(best, Some((Hasher::Sha512(_), _))) => {
+                best = Some((Hasher::Sha256(Sha256::new()), value))
+            }
+            // Unsupported (or insecure) algorithms are ignored.
+            _ => {}
+        }
+    }
+
+    let (hasher, expected) = best.ok_or(DigestError::UnsupportedAlgorithm)?;
+    Ok(BodyDigest {
+        header: CONTENT_DIGEST,
+        hasher,
+        expected,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use hyper::header::HeaderValue;
+
+    const BODY: &[u8] = b"platanos,tag1=A val=42i 123456";
+
+    fn headers(name: &'static str, value: String) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(&value).unwrap());
+        headers
+    }
+
+    fn verify(headers: &HeaderMap, body: &[u8]) -> Result<(), DigestError> {
+        let mut digest = BodyDigest::from_headers(headers)?.expect("digest header not found");
+        // Feed the body in multiple chunks.
+        for chunk in body.chunks(7) {
+            digest.update(chunk);
+        }
+        digest.verify()
+    }
+
+    #[test]
+    fn test_no_digest() {
+        assert_matches!(BodyDigest::from_headers(&HeaderMap::new()), Ok(None));
+    }
+
+    #[test]
+    fn test_content_md5() {
+        let headers = headers(CONTENT_MD5, base64::encode(Md5::digest(BODY)));
+        assert_matches!(verify(&headers, BODY), Ok(()));
+        assert_matches!(
+            verify(&headers, b"platanos,tag1=A val=43i 123456"),
+            Err(DigestError::Mismatch(CONTENT_MD5))
+        );
+    }
+
+    #[test]
+    fn test_content_digest() {
+        let sha256 = base64::encode(Sha256::digest(BODY));
+        let sha512 = base64::encode(Sha512::digest(BODY));
+
+        for value in [
+            format!("sha-256=:{}:", sha256),
+            format!("sha-512=:{}:", sha512),
+            format!("SHA-256=:{}:, sha-512=:{}:", sha256, sha512),
+            format!("unixsum=:AAAA:, sha-256=:{}:", sha256),
+        ] {
+            let headers = headers(CONTENT_DIGEST, value.clone());
+            assert_matches!(verify(&headers, BODY), Ok(()), "{}", value);
+            assert_matches!(
+                verify(&headers, b"bananas"),
+                Err(DigestError::Mismatch(CONTENT_DIGEST)),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_digest_preferred() {
+        let mut headers = headers(
+            CONTENT_DIGEST,
+            format!("sha-256=:{}:", base64::encode(Sha256::digest(BODY))),
+        );
+        headers.insert(CONTENT_MD5, HeaderValue::from_static("bm90IGFuIG1kNQ=="));
+        assert_matches!(verify(&headers, BODY), Ok(()));
+    }
+
+    #[test]
+    fn test_invalid_headers() {
+        assert_matches!(
+            BodyDigest::from_headers(&headers(CONTENT_DIGEST, "sha-256".to_string())),
+            Err(DigestError::InvalidHeader(CONTENT_DIGEST))
+        );
+        assert_matches!(
+            BodyDigest::from_headers(&headers(CONTENT_DIGEST, "sha-256=:!!!:".to_string())),
+            Err(DigestError::InvalidHeader(CONTENT_DIGEST))
+        );
+        assert_matches!(
+            BodyDigest::from_headers(&headers(CONTENT_DIGEST, "md5=:AAAA:".to_string())),
+            Err(DigestError::UnsupportedAlgorithm)
+        );
+        assert_matches!(
+            BodyDigest::from_headers(&headers(CONTENT_MD5, "!!!".to_string())),
+            Err(DigestError::InvalidHeader(CONTENT_MD5))
+        );
+    }
+}
@@ -0,0 +1,647 @@
+//! Conversion of Graphite plaintext protocol writes into [`MutableBatch`]
+//! instances.
+//!
+//! Each line of a Graphite write is of the form:
+//!
+//! ```text
+//! <metric path> <value> [timestamp]
+//! ```
+//!
+//! The dotted metric path is mapped to a measurement, field & tags by the
+//! first matching [`GraphiteTemplates`] template, following the template
+//! syntax of the InfluxDB 1.x Graphite service. Tags may also be supplied
+//! using the Graphite 1.1 tagged syntax, `<metric path>;tag1=a;tag2=b`.
+
+use hashbrown::HashMap;
+use mutable_batch::{writer::Writer, MutableBatch};
+use schema::TIME_COLUMN_NAME;
+use std::{collections::BTreeMap, iter};
+use thiserror::Error;
+
+use super::line_limits::LineLimits;
+
+/// The field written when a template does not name one.
+const DEFAULT_FIELD_NAME: &str = "value";
+
+/// The string joining the path elements of multi-element measurement, field &
+/// tag values.
+const DEFAULT_SEPARATOR: &str = ".";
+
+/// The timestamp Graphite clients send to request the time of receipt.
+const NOW_TIMESTAMP: &str = "-1";
+
+/// An invalid Graphite template.
+#[derive(Debug, Error)]
+#[error("invalid graphite template {template:?}: {reason}")]
+pub struct GraphiteTemplateError {
+    template: String,
+    reason: &'static str,
+}
+
+/// Errors returned when converting a Graphite plaintext protocol write.
+#[derive(Debug, Error)]
+pub enum GraphiteError {
+    /// A line is not of the form `<metric path> <value> [timestamp]`.
+    #[error("line {line}: {reason}")]
+    InvalidLine {
+        /// The (1-based) line number.
+        line: usize,
+        /// Why the line is invalid.
+        reason: String,
+    },
+
+    /// A line timestamp cannot be represented in nanoseconds.
+    #[error("line {line}: out of range timestamp {timestamp}")]
+    TimestampOverflow {
+        /// The (1-based) line number.
+        line: usize,
+        /// The timestamp, in seconds.
+        timestamp: String,
+    },
+
+    /// The template applied to a line uses the same name for a tag and the
+    /// field, or names a tag after the time column.
+    #[error("line {line}: measurement {measurement} has conflicting column {column}")]
+    ConflictingColumn {
+        /// The (1-based) line number.
+        line: usize,
+        /// The measurement of the line.
+        measurement: String,
+        /// The conflicting column name.
+        column: String,
+    },
+
+    /// Writing a line to the batch of its measurement failed.
+    #[error("line {line}: failed to write measurement {measurement}: {source}")]
+    Write {
+        /// The (1-based) line number.
+        line: usize,
+        /// The measurement of the line.
+        measurement: String,
+        /// The underlying error.
+        source: mutable_batch::writer::Error,
+    },
+
+    /// The write exceeds the configured line limits, counting each non-blank
+    /// line.
+    #[error(transparent)]
+    LimitExceeded(mutable_batch_lp::Error),
+}
+
+/// The role of a single element of a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Element {
+    /// The path element is ignored.
+    Skip,
+    /// The path element is part of the measurement name.
+    Measurement,
+    /// This and all subsequent path elements form the measurement name.
+    MeasurementRest,
+    /// The path element is part of the field name.
+    Field,
+    /// This and all subsequent path elements form the field name.
+    FieldRest,
+    /// The path element is (part of) the value of the named tag.
+    Tag(String),
+}
+
+/// A single template, mapping the elements of the metric paths matching its
+/// filter to a measurement, field & tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Template {
+    filter: Option<Vec<String>>,
+    elements: Vec<Element>,
+    tags: Vec<(String, String)>,
+}
+
+impl Template {
+    /// Parse a template of the form `[filter] <template> [tag1=a,tag2=b]`.
+    fn parse(spec: &str) -> Result<Self, GraphiteTemplateError> {
+        let err = |reason| GraphiteTemplateError {
+            template: spec.to_string(),
+            reason,
+        };
+
+        let tokens = spec.split_whitespace().collect::<Vec<_>>();
+        let (filter, template, tags) = match tokens.as_slice() {
+            [t] => (None, *t, None),
+            [t, tags] if tags.contains('=') => (None, *t, Some(*tags)),
+            [filter, t] => (Some(*filter), *t, None),
+            [filter, t, tags] => (Some(*filter), *t, Some(*tags)),
+            _ => return Err(err("expected [filter] <template> [tags]")),
+        };
+
+        let elements = template
+            .split('.')
+            .map(|e| match e {
+                "" => Ok(Element::Skip),
+                "measurement" => Ok(Element::Measurement),
+                "measurement*" => Ok(Element::MeasurementRest),
+                "field" => Ok(Element::Field),
+                "field*" => Ok(Element::FieldRest),
+                TIME_COLUMN_NAME => Err(err("the time column cannot be a tag")),
+                tag => Ok(Element::Tag(tag.to_string())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let wildcard = elements
+            .iter()
+            .position(|e| matches!(e, Element::MeasurementRest | Element::FieldRest));
+        if wildcard.map_or(false, |i| i != elements.len() - 1) {
+            return Err(err("a wildcard must be the last template element"));
+        }
+
+        let tags = tags
+            .into_iter()
+            .flat_map(|t| t.split(','))
+            .map(|t| match t.split_once('=') {
+                Some((TIME_COLUMN_NAME, _)) => Err(err("the time column cannot be a tag")),
+                Some((k, v)) if !k.is_empty() && !v.is_empty() => {
+                    Ok((k.to_string(), v.to_string()))
+                }
+                _ => Err(err("tags must be of the form tag1=a,tag2=b")),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            filter: filter.map(|f| f.split('.').map(ToString::to_string).collect()),
+            elements,
+            tags,
+        })
+    }
+
+    /// Returns true if this template has a filter matching the leading
+    /// elements of `path`.
+    fn matches(&self, path: &[&str]) -> bool {
+        match &self.filter {
+            Some(filter) => {
+                filter.len() <= path.len()
+                    && filter.iter().zip(path).all(|(f, p)| f == "*" || f == p)
+            }
+            None => false,
+        }
+    }
+}
+
+/// An ordered set of templates mapping Graphite metric paths to a
+/// measurement, field & tags.
+///
+/// Each template is of the form `[filter] <template> [tag1=a,tag2=b]`:
+///
+///   * The optional `filter` is a dotted pattern matching the leading
+///     elements of a metric path, in which `*` matches any single element.
+///     A path is mapped by the first template (in order) whose filter matches
+///     it, or by the (last) template without a filter if none do.
+///
+///   * Each dotted element of the `template` names the role of the
+///     corresponding path element: `measurement` or `field` (joined with the
+///     separator if more than one element has the role), `measurement*` or
+///     `field*` for all the remaining elements, a tag name, or nothing to
+///     ignore the element.
+///
+///   * The optional `tags` are added to every point the template maps.
+///
+/// A path is mapped to a measurement named after the entire path if the
+/// template does not name one, and to a `value` field if the template does
+/// not name one. By default, every path is mapped by the template
+/// `measurement*`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GraphiteTemplates {
+    templates: Vec<Template>,
+    default: Template,
+    separator: String,
+}
+
+impl Default for GraphiteTemplates {
+    fn default() -> Self {
+        Self {
+            templates: vec![],
+            default: Template {
+                filter: None,
+                elements: vec![Element::MeasurementRest],
+                tags: vec![],
+            },
+            separator: DEFAULT_SEPARATOR.to_string(),
+        }
+    }
+}
+
+impl GraphiteTemplates {
+    /// Parse the templates in `specs`.
+    pub fn new<'a>(
+        specs: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, GraphiteTemplateError> {
+        let mut templates = Self::default();
+        for spec in specs {
+            let template = Template::parse(spec)?;
+            match template.filter {
+                Some(_) => templates.templates.push(template),
+                None => templates.default = template,
+            }
+        }
+        Ok(templates)
+    }
+
+    /// Join multi-element measurement, field & tag values with `separator`,
+    /// rather than `.`.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Map the metric `path` to a measurement, field & tags.
+    fn apply(&self, path: &str) -> (String, String, BTreeMap<String, String>) {
+        let elements = path.split('.').collect::<Vec<_>>();
+        let template = self
+            .templates
+            .iter()
+            .find(|t| t.matches(&elements))
+            .unwrap_or(&self.default);
+
+        let mut measurement = vec![];
+        let mut field = vec![];
+        let mut tags: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for (i, (role, value)) in template.elements.iter().zip(&elements).enumerate() {
+            match role {
+                Element::Skip => {}
+                Element::Measurement => measurement.push(*value),
+                Element::MeasurementRest => measurement.extend(&elements[i..]),
+                Element::Field => field.push(*value),
+                Element::FieldRest => field.extend(&elements[i..]),
+                Element::Tag(name) => tags.entry(name).or_default().push(*value),
+            }
+        }
+
+        let measurement = if measurement.is_empty() {
+            path.to_string()
+        } else {
+            measurement.join(&self.separator)
+        };
+        let field = if field.is_empty() {
+            DEFAULT_FIELD_NAME.to_string()
+        } else {
+            field.join(&self.separator)
+        };
+        let mut tags = tags
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.join(&self.separator)))
+            .collect::<BTreeMap<_, _>>();
+        // Tags taken from the path take precedence over the template tags.
+        for (k, v) in &template.tags {
+            tags.entry(k.clone()).or_insert_with(|| v.clone());
+        }
+
+        (measurement, field, tags)
+    }
+}
+
+/// Convert the Graphite plaintext protocol lines in `body` into a
+/// [`MutableBatch`] per measurement, mapping metric paths according to
+/// `templates`.
+///
+/// Lines without a timestamp (or with a timestamp of `-1`) are assigned
+/// `default_time`. Blank lines are ignored, and do not count towards the
+/// `limits` checked for each point.
+///
+/// Returns the batches, and the number of points they contain.
+pub(crate) fn graphite_to_batches(
+    body: &str,
+    templates: &GraphiteTemplates,
+    default_time: i64,
+    limits: &LineLimits,
+) -> Result<(HashMap<String, MutableBatch>, usize), GraphiteError> {
+    let mut batches: HashMap<String, MutableBatch> = HashMap::new();
+    let mut num_points = 0;
+
+    for (i, text) in body.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        num_points += 1;
+        write_line(&mut batches, i + 1, text, templates, default_time, |tags| {
+            limits.check(num_points, 1, tags)
+        })?;
+    }
+
+    Ok((batches, num_points))
+}
+
+/// Write the `line`-th line, `text`, to the batch of its measurement, once
+/// `check_limits` accepts the number of tags it maps to.
+fn write_line(
+    batches: &mut HashMap<String, MutableBatch>,
+    line: usize,
+    text: &str,
+    templates: &GraphiteTemplates,
+    default_time: i64,
+    check_limits: impl FnOnce(usize) -> Result<(), mutable_batch_lp::Error>,
+) -> Result<(), GraphiteError> {
+    let invalid = |reason: String| GraphiteError::InvalidLine { line, reason };
+
+    let mut tokens = text.split_whitespace();
+    let (path, value, timestamp) =
+        match (tokens.next(), tokens.next(), tokens.next(), tokens.next()) {
+            (Some(path), Some(value), timestamp, None) => (path, value, timestamp),
+            _ => {
+                return Err(invalid(
+                    "expected <metric path> <value> [timestamp]".to_string(),
+                ))
+            }
+        };
+
+    // Split off any Graphite 1.1 tags, which take precedence over the tags of
+    // the template.
+    let mut path_tags = path.split(';');
+    let path = path_tags.next().unwrap_or_default();
+    if path.is_empty() {
+        return Err(invalid("empty metric path".to_string()));
+    }
+    let (measurement, field, mut tags) = templates.apply(path);
+    for tag in path_tags {
+        match tag.split_once('=') {
+            Some((k, v)) if !k.is_empty() && !v.is_empty() => {
+                tags.insert(k.to_string(), v.to_string());
+            }
+            _ => return Err(invalid(format!("invalid tag {:?}", tag))),
+        }
+    }
+
+    check_limits(tags.len()).map_err(GraphiteError::LimitExceeded)?;
+
+    let value = value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite())
+        .ok_or_else(|| invalid(format!("invalid value {:?}", value)))?;
+
+    let time = match timestamp {
+        None | Some(NOW_TIMESTAMP) => default_time,
+        Some(t) => {
+            let overflow = || GraphiteError::TimestampOverflow {
+                line,
+                timestamp: t.to_string(),
+            };
+            // Timestamps are usually whole seconds, which are converted
+            // exactly - fractional seconds are converted via a float.
+            match t.parse::<i64>() {
+                Ok(secs) => secs.checked_mul(1_000_000_000).ok_or_else(overflow)?,
+                Err(_) => {
+                    let secs = t
+                        .parse::<f64>()
+                        .map_err(|_| invalid(format!("invalid timestamp {:?}", t)))?;
+                    let nanos = secs * 1e9;
+                    if !nanos.is_finite() || nanos < i64::MIN as f64 || nanos >= i64::MAX as f64 {
+                        return Err(overflow());
+                    }
+                    nanos as i64
+                }
+            }
+        }
+    };
+
+    // Validate the column names before writing, as the writer panics if a
+    // column is written twice.
+    if let Some(column) = [TIME_COLUMN_NAME, field.as_str()]
+        .into_iter()
+        .find(|c| tags.contains_key(*c))
+        .or_else(|| (field == TIME_COLUMN_NAME).then(|| TIME_COLUMN_NAME))
+    {
+        return Err(GraphiteError::ConflictingColumn {
+            line,
+            measurement,
+            column: column.to_string(),
+        });
+    }
+
+    let write_err = |source| GraphiteError::Write {
+        line,
+        measurement: measurement.clone(),
+        source,
+    };
+
+    let batch = batches.entry_ref(measurement.as_str()).or_default();
+    let mut writer = Writer::new(batch, 1);
+    for (name, value) in &tags {
+        writer
+            .write_tag(name, None, iter::once(value.as_str()))
+            .map_err(write_err)?;
+    }
+    writer
+        .write_f64(&field, None, iter::once(value))
+        .map_err(write_err)?;
+    writer
+        .write_time(TIME_COLUMN_NAME, iter::once(time))
+        .map_err(write_err)?;
+    writer.commit();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_util::assert_batches_eq;
+    use assert_matches::assert_matches;
+    use schema::selection::Selection;
+
+    fn tags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_default_template() {
+        let templates = GraphiteTemplates::default();
+        assert_eq!(
+            templates.apply("servers.localhost.cpu"),
+            (
+                "servers.localhost.cpu".to_string(),
+                "value".to_string(),
+                tags(&[])
+            )
+        );
+    }
+
+    #[test]
+    fn test_templates() {
+        let templates = GraphiteTemplates::new([
+            "servers.* .host.measurement.field* region=eu",
+            "stats.*.* ..measurement.region.host",
+            "measurement.measurement.tag",
+        ])
+        .unwrap()
+        .with_separator("_");
+
+        assert_eq!(
+            templates.apply("servers.localhost.cpu.usage.idle"),
+            (
+                "cpu".to_string(),
+                "usage_idle".to_string(),
+                tags(&[("host", "localhost"), ("region", "eu")])
+            )
+        );
+        assert_eq!(
+            templates.apply("stats.counters.requests.us.web1"),
+            (
+                "requests".to_string(),
+                "value".to_string(),
+                tags(&[("host", "web1"), ("region", "us")])
+            )
+        );
+
+        // Paths matching no filter use the template without one, ignoring any
+        // surplus elements.
+        assert_eq!(
+            templates.apply("app.requests.bananas.extra"),
+            (
+                "app_requests".to_string(),
+                "value".to_string(),
+                tags(&[("tag", "bananas")])
+            )
+        );
+
+        // A path shorter than the template leaves the remaining roles empty.
+        assert_eq!(
+            templates.apply("servers.localhost"),
+            (
+                "servers.localhost".to_string(),
+                "value".to_string(),
+                tags(&[("host", "localhost"), ("region", "eu")])
+            )
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for spec in [
+            "",
+            "a b c d",
+            "measurement*.host",
+            "measurement.time",
+            "servers.* measurement tag=",
+        ] {
+            assert_matches!(
+                GraphiteTemplates::new([spec]),
+                Err(GraphiteTemplateError { .. }),
+                "{:?}",
+                spec
+            );
+        }
+    }
+
+    #[test]
+    fn test_graphite_to_batches() {
+        let templates = GraphiteTemplates::new(["servers.* .host.measurement.field*"]).unwrap();
+        let body = "\
+            servers.a.cpu.idle 42.5 1\n\
+            \n\
+            servers.b.cpu.user 1 2\n\
+            servers.c.cpu.idle 10 -1\n\
+            other.path;region=eu 3\n\
+        ";
+
+        let (batches, num_points) =
+            graphite_to_batches(body, &templates, 3_000_000_000, &LineLimits::default()).unwrap();
+        assert_eq!(num_points, 4);
+        assert_eq!(batches.len(), 2);
+
+        assert_batches_eq!(
+            &[
+                "+------+------+----------------------+------+",
+                "| host | idle | time                 | user |",
+                "+------+------+----------------------+------+",
+                "| a    | 42.5 | 1970-01-01T00:00:01Z |      |",
+                "| b    |      | 1970-01-01T00:00:02Z | 1    |",
+                "| c    | 10   | 1970-01-01T00:00:03Z |      |",
+                "+------+------+----------------------+------+",
+            ],
+            &[batches["cpu"].to_arrow(Selection::All).unwrap()]
+        );
+        assert_batches_eq!(
+            &[
+                "+--------+----------------------+-------+",
+                "| region | time                 | value |",
+                "+--------+----------------------+-------+",
+                "| eu     | 1970-01-01T00:00:03Z | 3     |",
+                "+--------+----------------------+-------+",
+            ],
+            &[batches["other.path"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        let templates = GraphiteTemplates::new(["measurement.value"]).unwrap();
+        for body in [
+            "cpu",
+            "cpu 1 2 3",
+            "cpu bananas",
+            "cpu NaN",
+            "cpu 1 bananas",
+            "cpu 1 1.5.5",
+            "cpu;region 1",
+            ";region=eu 1",
+        ] {
+            assert_matches!(
+                graphite_to_batches(body, &templates, 0, &LineLimits::default()),
+                Err(GraphiteError::InvalidLine { line: 1, .. }),
+                "{:?}",
+                body
+            );
+        }
+
+        assert_matches!(
+            graphite_to_batches("cpu 1 1e300", &templates, 0, &LineLimits::default()),
+            Err(GraphiteError::TimestampOverflow { line: 1, .. })
+        );
+
+        // The tag named "value" conflicts with the default field.
+        assert_matches!(
+            graphite_to_batches("cpu 1\ncpu.a 1", &templates, 0, &LineLimits::default()),
+            Err(GraphiteError::ConflictingColumn { line: 2, column, .. }) => {
+                assert_eq!(column, "value");
+            }
+        );
+    }
+
+    #[test]
+    fn test_line_limits() {
+        let templates = GraphiteTemplates::new(["measurement.host.field"]).unwrap();
+        let limits = LineLimits {
+            max_lines: Some(2),
+            max_fields_per_line: None,
+            max_tags_per_line: Some(1),
+        };
+
+        // Blank lines are not counted.
+        graphite_to_batches("cpu.a.idle 1\n\ncpu.b.idle 2", &templates, 0, &limits)
+            .expect("write within limits should succeed");
+
+        assert_matches!(
+            graphite_to_batches(
+                "cpu.a.idle 1\ncpu.b.idle 2\ncpu.c.idle 3",
+                &templates,
+                0,
+                &limits
+            ),
+            Err(GraphiteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyLines { max: 2 }
+            ))
+        );
+
+        // Tags of the template and the tagged syntax are both counted.
+        assert_matches!(
+            graphite_to_batches("cpu.a.idle;region=eu 1", &templates, 0, &limits),
+            Err(GraphiteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyTags {
+                    line: 1,
+                    count: 2,
+                    max: 1
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Deduplication of retried write requests by their idempotency key.
+
+use hashbrown::HashMap;
+use hyper::HeaderMap;
+use iox_time::Time;
+use parking_lot::Mutex;
+use std::{collections::BTreeMap, num::NonZeroUsize, time::Duration};
+use thiserror::Error;
+use write_summary::WriteSummary;
+
+/// The headers carrying the idempotency key of a request, in order of
+/// preference.
+const IDEMPOTENCY_KEY_HEADERS: [&str; 2] = ["Idempotency-Key", "X-Request-Id"];
+
+/// The maximum length of an idempotency key, bounding the memory used by the
+/// cache.
+pub(crate) const MAX_KEY_LEN: usize = 256;
+
+/// Errors returned when reading the idempotency key of a request.
+#[derive(Debug, Error)]
+pub enum IdempotencyKeyError {
+    /// The header value is not a valid (visible ASCII) string.
+    #[error("invalid {0} header value")]
+    InvalidValue(&'static str),
+
+    /// The header value exceeds [`MAX_KEY_LEN`].
+    #[error("{header} header value exceeds the maximum length of {max} bytes")]
+    TooLong {
+        /// The header carrying the key.
+        header: &'static str,
+        /// The maximum permitted key length.
+        max: usize,
+    },
+}
+
+/// The idempotency key of a write, scoped to the namespace written to so that
+/// keys chosen by different tenants never collide.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct IdempotencyKey {
+    namespace: String,
+    key: String,
+}
+
+impl IdempotencyKey {
+    /// Read the idempotency key of a write to `namespace` from the request
+    /// `headers`, if any.
+    pub(crate) fn from_headers(
+        headers: &HeaderMap,
+        namespace: &str,
+    ) -> Result<Option<Self>, IdempotencyKeyError> {
+        let (header, value) = match IDEMPOTENCY_KEY_HEADERS
+            .iter()
+            .find_map(|h| headers.get(*h).map(|v| (*h, v)))
+        {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let key = value
+            .to_str()
+            .map_err(|_| IdempotencyKeyError::InvalidValue(header))?;
+        if key.is_empty() {
+            return Ok(None);
+        }
+        if key.len() > MAX_KEY_LEN {
+            return Err(IdempotencyKeyError::TooLong {
+                header,
+                max: MAX_KEY_LEN,
+            });
+        }
+
+        Ok(Some(Self {
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+        }))
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    summary: WriteSummary,
+    inserted: Time,
+    /// The position of this entry in the LRU order.
+    seq: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    entries: HashMap<IdempotencyKey, Entry>,
+    /// The keys of `entries`, ordered from least to most recently used.
+    lru: BTreeMap<u64, IdempotencyKey>,
+    next_seq: u64,
+}
+
+impl State {
+    fn remove(&mut self, key: &IdempotencyKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.lru.remove(&entry.seq);
+        }
+    }
+
+    fn next_seq(&mut self) -> u64 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        seq
+    }
+}
+
+/// A bounded cache of the [`WriteSummary`] of recently completed writes,
+/// keyed by the idempotency key of the request.
+///
+/// Clients that retry a write after a network timeout (without knowing if the
+/// original write succeeded) can set the same `Idempotency-Key` (or
+/// `X-Request-Id`) header on each attempt - if the original write completed
+/// successfully, the retry is answered with its write token instead of
+/// writing the data again.
+///
+/// Entries expire after the configured TTL, and the least recently used
+/// entries are evicted once the cache holds the maximum number of entries.
+/// Only successful writes are remembered, and concurrent requests with the
+/// same key are not deduplicated.
+#[derive(Debug)]
+pub struct IdempotencyCache {
+    capacity: NonZeroUsize,
+    ttl: Duration,
+    state: Mutex<State>,
+}
+
+impl IdempotencyCache {
+    /// Initialise a cache remembering at most `capacity` writes, each for
+    /// `ttl`.
+    pub fn new(capacity: NonZeroUsize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            state: Default::default(),
+        }
+    }
+
+    /// Returns the summary of the write with `key` that completed within the
+    /// TTL of `now`, if any.
+    pub(crate) fn get(&self, key: &IdempotencyKey, now: Time) -> Option<WriteSummary> {
+        let mut state = self.state.lock();
+
+        let expired = now
+            .checked_duration_since(state.entries.get(key)?.inserted)
+            .map(|age| age >= self.ttl)
+            .unwrap_or_default();
+        if expired {
+            state.remove(key);
+            return None;
+        }
+
+        // Mark the entry as the most recently used.
+        let seq = state.next_seq();
+        let entry = state.entries.get_mut(key).expect("entry exists");
+        let old_seq = std::mem::replace(&mut entry.seq, seq);
+        let summary = entry.summary.clone();
+        state.lru.remove(&old_seq);
+        state.lru.insert(seq, key.clone());
+
+        Some(summary)
+    }
+
+    /// Remember the `summary` of the write with `key`, completed at `now`.
+    pub(crate) fn insert(&self, key: IdempotencyKey, summary: WriteSummary, now: Time) {
+        let mut state = self.state.lock();
+
+        state.remove(&key);
+        while state.entries.len() >= self.capacity.get() {
+            let evicted = state
+                .lru
+                .values()
+                .next()
+                .cloned()
+                .expect("lru tracks all entries");
+            state.remove(&evicted);
+        }
+
+        let seq = state.next_seq();
+        state.lru.insert(seq, key.clone());
+        state.entries.insert(
+            key,
+            Entry {
+                summary,
+                inserted: now,
+                seq,
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use hyper::header::HeaderValue;
+
+    fn key(namespace: &str, key: &str) -> IdempotencyKey {
+        IdempotencyKey {
+            namespace: namespace.to_string(),
+            key: key.to_string(),
+        }
+    }
+
+    fn summary(sequence_number: i64) -> WriteSummary {
+        let meta = dml::DmlMeta::sequenced(
+            data_types::Sequence::new(
+                data_types::ShardIndex::new(1),
+                data_types::SequenceNumber::new(sequence_number),
+            ),
+            Time::from_timestamp_nanos(0),
+            None,
+            0,
+        );
+        WriteSummary::new(vec![vec![meta]])
+    }
+
+    #[test]
+    fn test_from_headers() {
+        let mut headers = HeaderMap::new();
+        assert_matches!(IdempotencyKey::from_headers(&headers, "ns"), Ok(None));
+
+        headers.insert("X-Request-Id", HeaderValue::from_static("request"));
+        assert_eq!(
+            IdempotencyKey::from_headers(&headers, "ns").unwrap(),
+            Some(key("ns", "request"))
+        );
+
+        // The Idempotency-Key header takes precedence.
+        headers.insert("Idempotency-Key", HeaderValue::from_static("idempotency"));
+        assert_eq!(
+            IdempotencyKey::from_headers(&headers, "ns").unwrap(),
+            Some(key("ns", "idempotency"))
+        );
+
+        headers.insert("Idempotency-Key", HeaderValue::from_static(""));
+        assert_matches!(IdempotencyKey::from_headers(&headers, "ns"), Ok(None));
+
+        headers.insert(
+            "Idempotency-Key",
+            HeaderValue::from_str(&"a".repeat(MAX_KEY_LEN + 1)).unwrap(),
+        );
+        assert_matches!(
+            IdempotencyKey::from_headers(&headers, "ns"),
+            Err(IdempotencyKeyError::TooLong { .. })
+        );
+
+        headers.insert("Idempotency-Key", HeaderValue::from_bytes(b"\xFF").unwrap());
+        assert_matches!(
+            IdempotencyKey::from_headers(&headers, "ns"),
+            Err(IdempotencyKeyError::InvalidValue(_))
+        );
+    }
+
+    #[test]
+    fn test_get_insert() {
+        let cache = IdempotencyCache::new(NonZeroUsize::new(10).unwrap(), Duration::from_secs(60));
+        let now = Time::from_timestamp_nanos(0);
+
+        assert!(cache.get(&key("ns", "a"), now).is_none());
+
+        cache.insert(key("ns", "a"), summary(1), now);
+        assert_eq!(cache.get(&key("ns", "a"), now), Some(summary(1)));
+
+        // Keys are scoped to the namespace.
+        assert!(cache.get(&key("other", "a"), now).is_none());
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = IdempotencyCache::new(NonZeroUsize::new(10).unwrap(), Duration::from_secs(60));
+        let now = Time::from_timestamp_nanos(0);
+
+        cache.insert(key("ns", "a"), summary(1), now);
+
+        let later = now + Duration::from_secs(59);
+        assert_eq!(cache.get(&key("ns", "a"), later), Some(summary(1)));
+
+        // Reading the entry does not extend its lifetime.
+        let later = now + Duration::from_secs(60);
+        assert!(cache.get(&key("ns", "a"), later).is_none());
+        assert!(cache.state.lock().entries.is_empty());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let cache = IdempotencyCache::new(NonZeroUsize::new(2).unwrap(), Duration::from_secs(60));
+        let now = Time::from_timestamp_nanos(0);
+
+        cache.insert(key("ns", "a"), summary(1), now);
+        cache.insert(key("ns", "b"), summary(2), now);
+
+        // Using "a" makes "b" the least recently used entry.
+        assert!(cache.get(&key("ns", "a"), now).is_some());
+        cache.insert(key("ns", "c"), summary(3), now);
+
+        assert_eq!(cache.get(&key("ns", "a"), now), Some(summary(1)));
+        assert!(cache.get(&key("ns", "b"), now).is_none());
+        assert_eq!(cache.get(&key("ns", "c"), now), Some(summary(3)));
+
+        let state = cache.state.lock();
+        assert_eq!(state.entries.len(), 2);
+        assert_eq!(state.lru.len(), 2);
+    }
+}
@@ -0,0 +1,386 @@
+//! Conversion of JSON-encoded write requests into [`MutableBatch`] instances.
+
+use hashbrown::HashMap;
+use mutable_batch::{writer::Writer, MutableBatch};
+use schema::TIME_COLUMN_NAME;
+use serde::Deserialize;
+use std::{collections::BTreeMap, iter};
+use thiserror::Error;
+
+use super::line_limits::LineLimits;
+
+/// The content type of JSON-encoded write requests.
+pub(crate) const JSON_CONTENT_TYPE: &str = "application/json";
+
+/// Errors returned when converting a JSON write request.
+#[derive(Debug, Error)]
+pub enum JsonWriteError {
+    /// The request body is not a valid JSON array of points.
+    #[error("failed to decode json points: {0}")]
+    Decode(#[from] serde_json::Error),
+
+    /// A point has no fields.
+    #[error("point {index} of measurement {measurement} has no fields")]
+    NoFields {
+        /// The (0-based) index of the point in the request.
+        index: usize,
+        /// The measurement of the point.
+        measurement: String,
+    },
+
+    /// A point uses the same name for a tag and field, or names a tag or
+    /// field after the time column.
+    #[error("point {index} of measurement {measurement} has conflicting column {column}")]
+    ConflictingColumn {
+        /// The (0-based) index of the point in the request.
+        index: usize,
+        /// The measurement of the point.
+        measurement: String,
+        /// The conflicting column name.
+        column: String,
+    },
+
+    /// A point timestamp cannot be represented in nanoseconds.
+    #[error("point {index} of measurement {measurement} has out of range timestamp {time}")]
+    TimestampOverflow {
+        /// The (0-based) index of the point in the request.
+        index: usize,
+        /// The measurement of the point.
+        measurement: String,
+        /// The timestamp, in the precision of the request.
+        time: i64,
+    },
+
+    /// Writing a point to the batch of its measurement failed.
+    #[error("failed to write point {index} of measurement {measurement}: {source}")]
+    Write {
+        /// The (0-based) index of the point in the request.
+        index: usize,
+        /// The measurement of the point.
+        measurement: String,
+        /// The underlying error.
+        source: mutable_batch::writer::Error,
+    },
+
+    /// The write exceeds the configured line limits, counting each point
+    /// as a line.
+    #[error(transparent)]
+    LimitExceeded(mutable_batch_lp::Error),
+}
+
+/// A single point of a JSON write request.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JsonPoint {
+    measurement: String,
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+    fields: BTreeMap<String, JsonFieldValue>,
+    time: Option<i64>,
+}
+
+/// The value of a field of a [`JsonPoint`].
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonFieldValue {
+    Bool(bool),
+    Number(f64),
+    String(String),
+}
+
+/// Statistics of a converted JSON write request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct JsonStatistics {
+    /// The number of points converted, each of which becomes a row.
+    pub(crate) num_points: usize,
+    /// The number of fields written.
+    pub(crate) num_fields: usize,
+}
+
+/// Convert the JSON array of points in `body` into a [`MutableBatch`] per
+/// measurement.
+///
+/// Each point is an object of the form:
+///
+/// ```json
+/// {"measurement": "cpu", "tags": {"host": "a"}, "fields": {"usage": 42.5}, "time": 1}
+/// ```
+///
+/// The `tags` are optional, and a point without a `time` is assigned
+/// `default_time`. Point timestamps are multiplied by `timestamp_base` to
+/// convert them to nanoseconds.
+///
+/// Field values may be booleans, strings or numbers - as JSON does not
+/// distinguish integers from floats (and many encoders write whole floats as
+/// integers) all numbers are written as floats.
+///
+/// Each point is checked against `limits` as if it were a line of line
+/// protocol.
+pub(crate) fn json_to_batches(
+    body: &[u8],
+    default_time: i64,
+    timestamp_base: i64,
+    limits: &LineLimits,
+) -> Result<(HashMap<String, MutableBatch>, JsonStatistics), JsonWriteError> {
+    let points: Vec<JsonPoint> = serde_json::from_slice(body)?;
+
+    let mut batches: HashMap<String, MutableBatch> = HashMap::new();
+    let mut stats = JsonStatistics::default();
+
+    for (index, point) in points.into_iter().enumerate() {
+        limits
+            .check(index + 1, point.fields.len(), point.tags.len())
+            .map_err(JsonWriteError::LimitExceeded)?;
+        write_point(&mut batches, index, &point, default_time, timestamp_base)?;
+        stats.num_points += 1;
+        stats.num_fields += point.fields.len();
+    }
+
+    Ok((batches, stats))
+}
+
+/// Write `point`, the `index`-th point of the request, to the batch of its
+/// measurement.
+fn write_point(
+    batches: &mut HashMap<String, MutableBatch>,
+    index: usize,
+    point: &JsonPoint,
+    default_time: i64,
+    timestamp_base: i64,
+) -> Result<(), JsonWriteError> {
+    let measurement = point.measurement.as_str();
+
+    if point.fields.is_empty() {
+        return Err(JsonWriteError::NoFields {
+            index,
+            measurement: measurement.to_string(),
+        });
+    }
+
+    // Validate the column names before writing, as the writer panics if a
+    // column is written twice.
+    if let Some(column) = point.tags.keys().chain(point.fields.keys()).find(|k| {
+        *k == TIME_COLUMN_NAME || (point.tags.contains_key(*k) && point.fields.contains_key(*k))
+    }) {
+        return Err(JsonWriteError::ConflictingColumn {
+            index,
+            measurement: measurement.to_string(),
+            column: column.clone(),
+        });
+    }
+
+    let time = match point.time {
+        Some(t) => {
+            t.checked_mul(timestamp_base)
+                .ok_or_else(|| JsonWriteError::TimestampOverflow {
+                    index,
+                    measurement: measurement.to_string(),
+                    time: t,
+                })?
+        }
+        None => default_time,
+    };
+
+    let write_err = |source| JsonWriteError::Write {
+        index,
+        measurement: measurement.to_string(),
+        source,
+    };
+
+    let batch = batches.entry_ref(measurement).or_default();
+    let mut writer = Writer::new(batch, 1);
+    for (name, value) in &point.tags {
+        writer
+            .write_tag(name, None, iter::once(value.as_str()))
+            .map_err(write_err)?;
+    }
+    for (name, value) in &point.fields {
+        match value {
+            JsonFieldValue::Bool(v) => writer.write_bool(name, None, iter::once(*v)),
+            JsonFieldValue::Number(v) => writer.write_f64(name, None, iter::once(*v)),
+            JsonFieldValue::String(v) => writer.write_string(name, None, iter::once(v.as_str())),
+        }
+        .map_err(write_err)?;
+    }
+    writer
+        .write_time(TIME_COLUMN_NAME, iter::once(time))
+        .map_err(write_err)?;
+    writer.commit();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_util::assert_batches_eq;
+    use assert_matches::assert_matches;
+    use schema::selection::Selection;
+
+    #[test]
+    fn test_json_to_batches() {
+        let body = r#"[
+            {"measurement": "cpu", "tags": {"host": "a"}, "fields": {"usage": 42.5, "ok": true}, "time": 1},
+            {"measurement": "cpu", "tags": {"host": "b"}, "fields": {"usage": 42}, "time": 2},
+            {"measurement": "log", "fields": {"msg": "bananas"}}
+        ]"#;
+
+        let (batches, stats) = json_to_batches(
+            body.as_bytes(),
+            3_000_000_000,
+            1_000_000_000,
+            &LineLimits::default(),
+        )
+        .expect("conversion should succeed");
+        assert_eq!(
+            stats,
+            JsonStatistics {
+                num_points: 3,
+                num_fields: 4
+            }
+        );
+        assert_eq!(batches.len(), 2);
+
+        assert_batches_eq!(
+            &[
+                "+------+------+----------------------+-------+",
+                "| host | ok   | time                 | usage |",
+                "+------+------+----------------------+-------+",
+                "| a    | true | 1970-01-01T00:00:01Z | 42.5  |",
+                "| b    |      | 1970-01-01T00:00:02Z | 42    |",
+                "+------+------+----------------------+-------+",
+            ],
+            &[batches["cpu"].to_arrow(Selection::All).unwrap()]
+        );
+
+        assert_batches_eq!(
+            &[
+                "+---------+----------------------+",
+                "| msg     | time                 |",
+                "+---------+----------------------+",
+                "| bananas | 1970-01-01T00:00:03Z |",
+                "+---------+----------------------+",
+            ],
+            &[batches["log"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_empty() {
+        let (batches, stats) = json_to_batches(b"[]", 0, 1, &LineLimits::default()).unwrap();
+        assert!(batches.is_empty());
+        assert_eq!(stats, JsonStatistics::default());
+    }
+
+    #[test]
+    fn test_invalid_points() {
+        assert_matches!(
+            json_to_batches(br#"{"measurement": "cpu"}"#, 0, 1, &LineLimits::default()),
+            Err(JsonWriteError::Decode(_))
+        );
+        assert_matches!(
+            json_to_batches(
+                br#"[{"measurement": "cpu", "fields": {"v": null}}]"#,
+                0,
+                1,
+                &LineLimits::default()
+            ),
+            Err(JsonWriteError::Decode(_))
+        );
+        assert_matches!(
+            json_to_batches(
+                br#"[{"measurement": "cpu", "fields": {}}]"#,
+                0,
+                1,
+                &LineLimits::default()
+            ),
+            Err(JsonWriteError::NoFields { index: 0, .. })
+        );
+        assert_matches!(
+            json_to_batches(
+                br#"[{"measurement": "cpu", "tags": {"v": "a"}, "fields": {"v": 1}}]"#,
+                0,
+                1,
+                &LineLimits::default()
+            ),
+            Err(JsonWriteError::ConflictingColumn { column, .. }) => {
+                assert_eq!(column, "v");
+            }
+        );
+        assert_matches!(
+            json_to_batches(
+                br#"[{"measurement": "cpu", "fields": {"time": 1}}]"#,
+                0,
+                1,
+                &LineLimits::default()
+            ),
+            Err(JsonWriteError::ConflictingColumn { .. })
+        );
+        assert_matches!(
+            json_to_batches(
+                br#"[{"measurement": "cpu", "fields": {"v": 1}, "time": 9223372036854775807}]"#,
+                0,
+                1_000,
+                &LineLimits::default()
+            ),
+            Err(JsonWriteError::TimestampOverflow { .. })
+        );
+    }
+
+    #[test]
+    fn test_conflicting_types() {
+        let body = r#"[
+            {"measurement": "cpu", "fields": {"v": 1}},
+            {"measurement": "cpu", "fields": {"v": "bananas"}}
+        ]"#;
+        assert_matches!(
+            json_to_batches(body.as_bytes(), 0, 1, &LineLimits::default()),
+            Err(JsonWriteError::Write { index: 1, .. })
+        );
+    }
+
+    #[test]
+    fn test_line_limits() {
+        let limits = LineLimits {
+            max_lines: Some(2),
+            max_fields_per_line: Some(1),
+            max_tags_per_line: Some(1),
+        };
+
+        let body = br#"[
+            {"measurement": "cpu", "fields": {"v": 1}},
+            {"measurement": "cpu", "fields": {"v": 2}},
+            {"measurement": "cpu", "fields": {"v": 3}}
+        ]"#;
+        assert_matches!(
+            json_to_batches(body, 0, 1, &limits),
+            Err(JsonWriteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyLines { max: 2 }
+            ))
+        );
+
+        let body = br#"[{"measurement": "cpu", "fields": {"a": 1, "b": 2}}]"#;
+        assert_matches!(
+            json_to_batches(body, 0, 1, &limits),
+            Err(JsonWriteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyFields {
+                    line: 1,
+                    count: 2,
+                    max: 1
+                }
+            ))
+        );
+
+        let body = br#"[{"measurement": "cpu", "tags": {"a": "x", "b": "y"}, "fields": {"v": 1}}]"#;
+        assert_matches!(
+            json_to_batches(body, 0, 1, &limits),
+            Err(JsonWriteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyTags {
+                    line: 1,
+                    count: 2,
+                    max: 1
+                }
+            ))
+        );
+    }
+}
@@ -0,0 +1,64 @@
+//! Tracking of recent request latency, used to advise rejected clients when
+//! to retry.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// The weight given to each new observation.
+const ALPHA: f64 = 0.2;
+
+/// An exponentially weighted moving average of request latency.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyAverage {
+    /// The average latency in nanoseconds, or 0 if no requests have been
+    /// observed.
+    nanos: AtomicU64,
+}
+
+impl LatencyAverage {
+    /// Record a request completing after `latency`.
+    pub(crate) fn observe(&self, latency: Duration) {
+        let observed = latency.as_nanos().min(u64::MAX as u128) as u64;
+        // The closure never returns None, so the update always succeeds.
+        let _ = self
+            .nanos
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |avg| {
+                if avg == 0 {
+                    return Some(observed.max(1));
+                }
+                let avg = avg as f64 * (1.0 - ALPHA) + observed as f64 * ALPHA;
+                Some((avg as u64).max(1))
+            });
+    }
+
+    /// Returns the average request latency, or [`None`] if no requests have
+    /// been observed.
+    pub(crate) fn get(&self) -> Option<Duration> {
+        match self.nanos.load(Ordering::Relaxed) {
+            0 => None,
+            v => Some(Duration::from_nanos(v)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_average() {
+        let avg = LatencyAverage::default();
+        assert_eq!(avg.get(), None);
+
+        avg.observe(Duration::from_secs(10));
+        assert_eq!(avg.get(), Some(Duration::from_secs(10)));
+
+        avg.observe(Duration::from_secs(0));
+        assert_eq!(avg.get(), Some(Duration::from_secs(8)));
+
+        avg.observe(Duration::from_secs(18));
+        assert_eq!(avg.get(), Some(Duration::from_secs(10)));
+    }
+}
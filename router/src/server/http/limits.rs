@@ -0,0 +1,161 @@
+//! Request limits that may be changed while the router is running.
+
+use parking_lot::Mutex;
+use std::num::NonZeroU64;
+use tokio::sync::{Semaphore, SemaphorePermit, TryAcquireError};
+
+/// The limits applied to the requests serviced by an
+/// [`HttpDelegate`](super::HttpDelegate), which may be updated at runtime
+/// through a [`tokio::sync::watch`] channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestLimits {
+    /// The maximum size of a request body, both as received and once
+    /// decoded.
+    pub max_request_bytes: usize,
+    /// The maximum number of requests serviced simultaneously.
+    pub max_requests: usize,
+    /// The maximum number of lines each org may write per second, if
+    /// limited.
+    pub lines_per_second: Option<NonZeroU64>,
+    /// The maximum number of (decoded) bytes each org may write per second,
+    /// if limited.
+    pub bytes_per_second: Option<NonZeroU64>,
+}
+
+#[derive(Debug)]
+struct State {
+    max: usize,
+    // The number of permits in the semaphore in excess of `max`, to be
+    // removed once they are released by the requests holding them.
+    debt: usize,
+}
+
+/// A limit on the number of simultaneous requests, which may be raised or
+/// lowered while requests are in flight.
+///
+/// Lowering the limit never revokes the permits of in-flight requests -
+/// instead, the excess permits are removed as they are released.
+#[derive(Debug)]
+pub(crate) struct RequestLimiter {
+    sem: Semaphore,
+    state: Mutex<State>,
+}
+
+impl RequestLimiter {
+    pub(crate) fn new(max: usize) -> Self {
+        Self {
+            sem: Semaphore::new(max),
+            state: Mutex::new(State { max, debt: 0 }),
+        }
+    }
+
+    /// Acquire a permit for a request, returning [`None`] if the limit has
+    /// been reached.
+    pub(crate) fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.repay(&mut self.state.lock());
+        match self.sem.try_acquire() {
+            Ok(p) => Some(p),
+            Err(TryAcquireError::NoPermits) => None,
+            Err(e) => panic!("request limiter error: {}", e),
+        }
+    }
+
+    /// Change the maximum number of simultaneous requests to `max`.
+    pub(crate) fn resize(&self, max: usize) {
+        let mut state = self.state.lock();
+        if max >= state.max {
+            // Cancel out any outstanding debt before adding new permits.
+            let grow = max - state.max;
+            let repaid = grow.min(state.debt);
+            state.debt -= repaid;
+            self.sem.add_permits(grow - repaid);
+        } else {
+            state.debt += state.max - max;
+        }
+        state.max = max;
+        self.repay(&mut state);
+    }
+
+    /// Returns the number of requests currently holding a permit.
+    pub(crate) fn in_flight(&self) -> usize {
+        let state = self.state.lock();
+        (state.max + state.debt).saturating_sub(self.sem.available_permits())
+    }
+
+    /// Returns the number of requests that may be admitted before the limit
+    /// is reached.
+    pub(crate) fn available_permits(&self) -> usize {
+        let state = self.state.lock();
+        self.sem.available_permits().saturating_sub(state.debt)
+    }
+
+    /// Remove as much of the excess permits as are currently available.
+    fn repay(&self, state: &mut State) {
+        let n = state.debt.min(self.sem.available_permits());
+        if n == 0 {
+            return;
+        }
+        self.sem
+            .try_acquire_many(n as u32)
+            .expect("permits are available")
+            .forget();
+        state.debt -= n;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grow() {
+        let limiter = RequestLimiter::new(1);
+        let _p1 = limiter.try_acquire().expect("should acquire");
+        assert!(limiter.try_acquire().is_none());
+
+        limiter.resize(2);
+        let _p2 = limiter.try_acquire().expect("should acquire");
+        assert!(limiter.try_acquire().is_none());
+        assert_eq!(limiter.in_flight(), 2);
+    }
+
+    #[test]
+    fn test_shrink_with_requests_in_flight() {
+        let limiter = RequestLimiter::new(3);
+        let p1 = limiter.try_acquire().expect("should acquire");
+        let p2 = limiter.try_acquire().expect("should acquire");
+
+        // The spare permit is removed immediately, and in-flight requests are
+        // unaffected.
+        limiter.resize(1);
+        assert_eq!(limiter.in_flight(), 2);
+        assert_eq!(limiter.available_permits(), 0);
+        assert!(limiter.try_acquire().is_none());
+
+        // Releasing a permit repays the debt, rather than admitting another
+        // request.
+        drop(p1);
+        assert_eq!(limiter.in_flight(), 1);
+        assert!(limiter.try_acquire().is_none());
+
+        drop(p2);
+        assert_eq!(limiter.in_flight(), 0);
+        let _p3 = limiter.try_acquire().expect("should acquire");
+        assert!(limiter.try_acquire().is_none());
+    }
+
+    #[test]
+    fn test_shrink_then_grow() {
+        let limiter = RequestLimiter::new(2);
+        let p1 = limiter.try_acquire().expect("should acquire");
+        let _p2 = limiter.try_acquire().expect("should acquire");
+
+        // Growing the limit before the debt is repaid cancels it out.
+        limiter.resize(1);
+        limiter.resize(2);
+        drop(p1);
+        assert_eq!(limiter.available_permits(), 1);
+        let _p3 = limiter.try_acquire().expect("should acquire");
+        assert!(limiter.try_acquire().is_none());
+    }
+}
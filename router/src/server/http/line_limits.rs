@@ -0,0 +1,103 @@
+//! Limits on the shape of writes, applied to every write format.
+
+use mutable_batch_lp::{Error, LinesConverter};
+
+/// Limits on the shape of writes, protecting the schema validation path from
+/// pathological payloads.
+///
+/// Line protocol writes are limited by the [`LinesConverter`] they are parsed
+/// with. Writes in other formats are checked point by point, treating each
+/// point (or sample) as a line of line protocol, so that every format is
+/// rejected with the same [`Error`].
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct LineLimits {
+    pub(crate) max_lines: Option<usize>,
+    pub(crate) max_fields_per_line: Option<usize>,
+    pub(crate) max_tags_per_line: Option<usize>,
+}
+
+impl LineLimits {
+    /// Configure `converter` to enforce these limits.
+    pub(crate) fn apply(&self, converter: &mut LinesConverter) {
+        if let Some(v) = self.max_lines {
+            converter.set_max_lines(v);
+        }
+        if let Some(v) = self.max_fields_per_line {
+            converter.set_max_fields_per_line(v);
+        }
+        if let Some(v) = self.max_tags_per_line {
+            converter.set_max_tags_per_line(v);
+        }
+    }
+
+    /// Check the `line`-th (1-based) point of a write, which has `fields`
+    /// fields and `tags` tags, against these limits.
+    ///
+    /// The checks match those of a [`LinesConverter`] configured by
+    /// [`Self::apply()`].
+    pub(crate) fn check(&self, line: usize, fields: usize, tags: usize) -> Result<(), Error> {
+        if let Some(max) = self.max_lines {
+            if line > max {
+                return Err(Error::TooManyLines { max });
+            }
+        }
+        if let Some(max) = self.max_fields_per_line {
+            if fields > max {
+                return Err(Error::TooManyFields {
+                    line,
+                    count: fields,
+                    max,
+                });
+            }
+        }
+        if let Some(max) = self.max_tags_per_line {
+            if tags > max {
+                return Err(Error::TooManyTags {
+                    line,
+                    count: tags,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        LineLimits::default()
+            .check(usize::MAX, usize::MAX, usize::MAX)
+            .expect("no limits configured");
+
+        let limits = LineLimits {
+            max_lines: Some(2),
+            max_fields_per_line: Some(3),
+            max_tags_per_line: Some(1),
+        };
+
+        limits.check(2, 3, 1).expect("within limits");
+        assert_matches!(limits.check(3, 1, 0), Err(Error::TooManyLines { max: 2 }));
+        assert_matches!(
+            limits.check(1, 4, 0),
+            Err(Error::TooManyFields {
+                line: 1,
+                count: 4,
+                max: 3
+            })
+        );
+        assert_matches!(
+            limits.check(2, 1, 2),
+            Err(Error::TooManyTags {
+                line: 2,
+                count: 2,
+                max: 1
+            })
+        );
+    }
+}
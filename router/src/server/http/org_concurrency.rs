@@ -0,0 +1,97 @@
+//! Per-org limits on the number of simultaneous requests.
+
+use hashbrown::HashMap;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// A limiter of the number of requests each org may have in flight at once.
+///
+/// This sub-divides the global simultaneous request limit, preventing a burst
+/// of requests from a single org from occupying all of the request capacity
+/// of the router and starving other orgs.
+#[derive(Debug)]
+pub(crate) struct OrgConcurrencyLimiter {
+    max_per_org: usize,
+    in_flight: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl OrgConcurrencyLimiter {
+    /// Initialise a limiter allowing each org at most `max_per_org`
+    /// simultaneous requests.
+    pub(crate) fn new(max_per_org: usize) -> Self {
+        Self {
+            max_per_org,
+            in_flight: Default::default(),
+        }
+    }
+
+    /// The maximum number of simultaneous requests of a single org.
+    pub(crate) fn max_per_org(&self) -> usize {
+        self.max_per_org
+    }
+
+    /// Acquire a permit for a request from `org`, returning [`None`] if `org`
+    /// already has the maximum number of requests in flight.
+    ///
+    /// The permit is held until the returned [`OrgPermit`] is dropped.
+    pub(crate) fn try_acquire(&self, org: &str) -> Option<OrgPermit> {
+        let mut in_flight = self.in_flight.lock();
+        if in_flight.get(org).copied().unwrap_or_default() >= self.max_per_org {
+            return None;
+        }
+        *in_flight.entry_ref(org).or_default() += 1;
+
+        Some(OrgPermit {
+            org: org.to_string(),
+            in_flight: Arc::clone(&self.in_flight),
+        })
+    }
+}
+
+/// A permit for a single in-flight request of an org, released on drop.
+#[derive(Debug)]
+pub(crate) struct OrgPermit {
+    org: String,
+    in_flight: Arc<Mutex<HashMap<String, usize>>>,
+}
+
+impl Drop for OrgPermit {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock();
+        let n = in_flight
+            .get_mut(&self.org)
+            .expect("permit held for untracked org");
+        *n -= 1;
+
+        // Remove idle orgs, so the map does not grow without bound.
+        if *n == 0 {
+            in_flight.remove(&self.org);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_org_limit() {
+        let limiter = OrgConcurrencyLimiter::new(2);
+
+        let a1 = limiter.try_acquire("a").expect("should acquire");
+        let _a2 = limiter.try_acquire("a").expect("should acquire");
+        assert!(limiter.try_acquire("a").is_none());
+
+        // Other orgs are unaffected.
+        let b = limiter.try_acquire("b").expect("should acquire");
+
+        // Releasing a permit allows another request.
+        drop(a1);
+        let _a3 = limiter.try_acquire("a").expect("should acquire");
+        assert!(limiter.try_acquire("a").is_none());
+
+        // Idle orgs are not tracked.
+        drop(b);
+        assert!(!limiter.in_flight.lock().contains_key("b"));
+    }
+}
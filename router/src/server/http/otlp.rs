@@ -0,0 +1,548 @@
+//! Conversion of OpenTelemetry (OTLP) metrics export requests into
+//! [`MutableBatch`] instances.
+
+use generated_types::opentelemetry::proto::{
+    collector::metrics::v1::ExportMetricsServiceRequest,
+    common::v1::{any_value::Value as AnyValueKind, AnyValue, KeyValue},
+    metrics::v1::{metric::Data, number_data_point::Value as NumberValue, Metric, NumberDataPoint},
+};
+use hashbrown::HashMap;
+use mutable_batch::{writer::Writer, MutableBatch};
+use schema::TIME_COLUMN_NAME;
+use std::{collections::BTreeMap, iter};
+use thiserror::Error;
+
+use super::line_limits::LineLimits;
+
+/// The field the value of gauge & sum data points are written to.
+const VALUE_FIELD_NAME: &str = "value";
+
+/// Errors returned when converting an OTLP metrics export request.
+#[derive(Debug, Error)]
+pub enum OtlpError {
+    /// The request body is not a valid protobuf `ExportMetricsServiceRequest`.
+    #[error("failed to decode metrics export request: {0}")]
+    Decode(#[from] prost::DecodeError),
+
+    /// The `Content-Type` of the request is not supported.
+    #[error("unsupported content-type {0}, expected application/x-protobuf")]
+    UnsupportedContentType(String),
+
+    /// A histogram data point has inconsistent buckets.
+    #[error(
+        "histogram {metric} has invalid buckets: expected bucket bounds in \
+        ascending order and one more count than bounds"
+    )]
+    InvalidBuckets {
+        /// The name of the metric.
+        metric: String,
+    },
+
+    /// Writing a data point to the batch of its metric failed.
+    #[error("failed to write data point of metric {metric}: {source}")]
+    Write {
+        /// The name of the metric.
+        metric: String,
+        /// The underlying error.
+        source: mutable_batch::writer::Error,
+    },
+
+    /// The write exceeds the configured line limits, counting each data point
+    /// as a line.
+    #[error(transparent)]
+    LimitExceeded(mutable_batch_lp::Error),
+}
+
+/// Statistics of a converted OTLP request.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct OtlpStatistics {
+    /// The number of data points converted, each of which becomes a row.
+    pub(crate) num_points: usize,
+    /// The number of fields written.
+    pub(crate) num_fields: usize,
+}
+
+/// Convert the metrics in `req` into a [`MutableBatch`] per metric name.
+///
+/// Each data point becomes a row, tagged with the attributes of its resource
+/// and its own attributes (which take precedence). Gauges and sums are written
+/// to a `value` field, while histograms follow the Telegraf Prometheus
+/// convention of `count`, `sum`, `min` and `max` fields, and a field per bucket
+/// named after its upper bound (including `+Inf`) holding the cumulative count
+/// of values less than or equal to that bound.
+///
+/// Data points without a timestamp are assigned `default_time`, and metrics
+/// of unsupported types (exponential histograms & summaries) are skipped.
+/// Each data point is checked against `limits` as a line of line protocol.
+pub(crate) fn export_request_to_batches(
+    req: ExportMetricsServiceRequest,
+    default_time: i64,
+    limits: &LineLimits,
+) -> Result<(HashMap<String, MutableBatch>, OtlpStatistics), OtlpError> {
+    let mut batches: HashMap<String, MutableBatch> = HashMap::new();
+    let mut stats = OtlpStatistics::default();
+
+    for resource_metrics in req.resource_metrics {
+        let resource_tags = resource_metrics
+            .resource
+            .map(|r| attributes_to_tags(&r.attributes, BTreeMap::new()))
+            .unwrap_or_default();
+
+        for metric in resource_metrics
+            .scope_metrics
+            .into_iter()
+            .flat_map(|s| s.metrics)
+        {
+            write_metric(
+                &mut batches,
+                &mut stats,
+                metric,
+                &resource_tags,
+                default_time,
+                limits,
+            )?;
+        }
+    }
+
+    Ok((batches, stats))
+}
+
+/// A field value of a data point.
+enum FieldValue {
+    F64(f64),
+    I64(i64),
+    U64(u64),
+}
+
+/// The attributes, timestamp and fields of a data point.
+type DataPoint<'a> = (&'a [KeyValue], u64, Vec<(String, FieldValue)>);
+
+fn write_metric(
+    batches: &mut HashMap<String, MutableBatch>,
+    stats: &mut OtlpStatistics,
+    metric: Metric,
+    resource_tags: &BTreeMap<String, String>,
+    default_time: i64,
+    limits: &LineLimits,
+) -> Result<(), OtlpError> {
+    let name = metric.name;
+    let points: Vec<DataPoint<'_>> = match &metric.data {
+        Some(Data::Gauge(g)) => number_points(&g.data_points),
+        Some(Data::Sum(s)) => number_points(&s.data_points),
+        Some(Data::Histogram(h)) => h
+            .data_points
+            .iter()
+            .map(|p| {
+                let ascending = p
+                    .explicit_bounds
+                    .windows(2)
+                    .all(|w| w[0].partial_cmp(&w[1]) == Some(std::cmp::Ordering::Less));
+                if !ascending || p.bucket_counts.len() != p.explicit_bounds.len() + 1 {
+                    return Err(OtlpError::InvalidBuckets {
+                        metric: name.clone(),
+                    });
+                }
+
+                let mut fields = vec![("count".to_string(), FieldValue::U64(p.count))];
+                let optional = [("sum", p.sum), ("min", p.min), ("max", p.max)];
+                fields.extend(
+                    optional
+                        .into_iter()
+                        .filter_map(|(k, v)| Some((k.to_string(), FieldValue::F64(v?)))),
+                );
+
+                let upper_bounds = p
+                    .explicit_bounds
+                    .iter()
+                    .map(|b| b.to_string())
+                    .chain(iter::once("+Inf".to_string()));
+                let cumulative_counts = p.bucket_counts.iter().scan(0_u64, |acc, c| {
+                    *acc += c;
+                    Some(FieldValue::U64(*acc))
+                });
+                fields.extend(upper_bounds.zip(cumulative_counts));
+
+                Ok((p.attributes.as_slice(), p.time_unix_nano, fields))
+            })
+            .collect::<Result<_, _>>()?,
+        None => return Ok(()),
+    };
+
+    let write_err = |source| OtlpError::Write {
+        metric: name.clone(),
+        source,
+    };
+
+    for (attributes, time, fields) in points {
+        let tags = attributes_to_tags(attributes, resource_tags.clone());
+        limits
+            .check(stats.num_points + 1, fields.len(), tags.len())
+            .map_err(OtlpError::LimitExceeded)?;
+        let time = match time {
+            0 => default_time,
+            v => i64::try_from(v).unwrap_or(i64::MAX),
+        };
+
+        let batch = batches.entry_ref(name.as_str()).or_default();
+        let mut writer = Writer::new(batch, 1);
+        for (k, v) in &tags {
+            writer
+                .write_tag(k, None, iter::once(v.as_str()))
+                .map_err(write_err)?;
+        }
+        for (k, v) in &fields {
+            match v {
+                FieldValue::F64(v) => writer.write_f64(k, None, iter::once(*v)),
+                FieldValue::I64(v) => writer.write_i64(k, None, iter::once(*v)),
+                FieldValue::U64(v) => writer.write_u64(k, None, iter::once(*v)),
+            }
+            .map_err(write_err)?;
+        }
+        writer
+            .write_time(TIME_COLUMN_NAME, iter::once(time))
+            .map_err(write_err)?;
+        writer.commit();
+
+        stats.num_points += 1;
+        stats.num_fields += fields.len();
+    }
+
+    Ok(())
+}
+
+/// Return the attributes, timestamp & value field of each gauge or sum data
+/// point, skipping those without a value.
+fn number_points(points: &[NumberDataPoint]) -> Vec<DataPoint<'_>> {
+    points
+        .iter()
+        .filter_map(|p| {
+            let value = match p.value.as_ref()? {
+                NumberValue::AsDouble(v) => FieldValue::F64(*v),
+                NumberValue::AsInt(v) => FieldValue::I64(*v),
+            };
+            Some((
+                p.attributes.as_slice(),
+                p.time_unix_nano,
+                vec![(VALUE_FIELD_NAME.to_string(), value)],
+            ))
+        })
+        .collect()
+}
+
+/// Add `attributes` to `tags`, replacing any existing tags with the same keys.
+///
+/// Attributes with empty values are skipped.
+fn attributes_to_tags(
+    attributes: &[KeyValue],
+    mut tags: BTreeMap<String, String>,
+) -> BTreeMap<String, String> {
+    for kv in attributes {
+        if let Some(v) = kv.value.as_ref().and_then(any_value_to_string) {
+            tags.insert(kv.key.clone(), v);
+        }
+    }
+    tags
+}
+
+/// Render an attribute value as a tag value - scalars are rendered as-is, and
+/// arrays & key/value lists as JSON.
+fn any_value_to_string(v: &AnyValue) -> Option<String> {
+    match v.value.as_ref()? {
+        AnyValueKind::StringValue(v) => Some(v.clone()),
+        AnyValueKind::BoolValue(v) => Some(v.to_string()),
+        AnyValueKind::IntValue(v) => Some(v.to_string()),
+        AnyValueKind::DoubleValue(v) => Some(v.to_string()),
+        AnyValueKind::BytesValue(v) => Some(base64::encode(v)),
+        AnyValueKind::ArrayValue(_) | AnyValueKind::KvlistValue(_) => {
+            Some(any_value_to_json(v).to_string())
+        }
+    }
+}
+
+fn any_value_to_json(v: &AnyValue) -> serde_json::Value {
+    use serde_json::Value;
+
+    match &v.value {
+        None => Value::Null,
+        Some(AnyValueKind::StringValue(v)) => Value::from(v.as_str()),
+        Some(AnyValueKind::BoolValue(v)) => Value::from(*v),
+        Some(AnyValueKind::IntValue(v)) => Value::from(*v),
+        Some(AnyValueKind::DoubleValue(v)) => Value::from(*v),
+        Some(AnyValueKind::BytesValue(v)) => Value::from(base64::encode(v)),
+        Some(AnyValueKind::ArrayValue(a)) => {
+            Value::Array(a.values.iter().map(any_value_to_json).collect())
+        }
+        Some(AnyValueKind::KvlistValue(l)) => Value::Object(
+            l.values
+                .iter()
+                .map(|kv| {
+                    let v = kv
+                        .value
+                        .as_ref()
+                        .map(any_value_to_json)
+                        .unwrap_or(Value::Null);
+                    (kv.key.clone(), v)
+                })
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_util::assert_batches_eq;
+    use assert_matches::assert_matches;
+    use generated_types::opentelemetry::proto::{
+        common::v1::ArrayValue,
+        metrics::v1::{Gauge, Histogram, HistogramDataPoint, ResourceMetrics, ScopeMetrics, Sum},
+        resource::v1::Resource,
+    };
+    use schema::selection::Selection;
+
+    fn kv(key: &str, value: AnyValueKind) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(AnyValue { value: Some(value) }),
+        }
+    }
+
+    fn string_kv(key: &str, value: &str) -> KeyValue {
+        kv(key, AnyValueKind::StringValue(value.to_string()))
+    }
+
+    fn number_point(attributes: Vec<KeyValue>, time: u64, value: NumberValue) -> NumberDataPoint {
+        NumberDataPoint {
+            attributes,
+            time_unix_nano: time,
+            value: Some(value),
+            ..Default::default()
+        }
+    }
+
+    fn request(metrics: Vec<Metric>) -> ExportMetricsServiceRequest {
+        ExportMetricsServiceRequest {
+            resource_metrics: vec![ResourceMetrics {
+                resource: Some(Resource {
+                    attributes: vec![
+                        string_kv("service.name", "bananas"),
+                        string_kv("host", "resource"),
+                    ],
+                    dropped_attributes_count: 0,
+                }),
+                scope_metrics: vec![ScopeMetrics {
+                    scope: None,
+                    metrics,
+                    schema_url: String::new(),
+                }],
+                schema_url: String::new(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_gauge_and_sum() {
+        let req = request(vec![
+            Metric {
+                name: "temperature".to_string(),
+                data: Some(Data::Gauge(Gauge {
+                    data_points: vec![
+                        number_point(
+                            vec![string_kv("host", "a")],
+                            1_000_000_000,
+                            NumberValue::AsDouble(21.5),
+                        ),
+                        // Points without a timestamp are assigned the default.
+                        number_point(
+                            vec![kv("shard", AnyValueKind::IntValue(2))],
+                            0,
+                            NumberValue::AsDouble(22.0),
+                        ),
+                    ],
+                })),
+                ..Default::default()
+            },
+            Metric {
+                name: "requests".to_string(),
+                data: Some(Data::Sum(Sum {
+                    data_points: vec![number_point(vec![], 1_000_000_000, NumberValue::AsInt(42))],
+                    aggregation_temporality: 2,
+                    is_monotonic: true,
+                })),
+                ..Default::default()
+            },
+            // Metrics without data are skipped.
+            Metric {
+                name: "empty".to_string(),
+                ..Default::default()
+            },
+        ]);
+
+        let (batches, stats) =
+            export_request_to_batches(req, 2_000_000_000, &LineLimits::default()).unwrap();
+        assert_eq!(
+            stats,
+            OtlpStatistics {
+                num_points: 3,
+                num_fields: 3
+            }
+        );
+        assert_eq!(batches.len(), 2);
+
+        assert_batches_eq!(
+            &[
+                "+----------+--------------+-------+----------------------+-------+",
+                "| host     | service.name | shard | time                 | value |",
+                "+----------+--------------+-------+----------------------+-------+",
+                "| a        | bananas      |       | 1970-01-01T00:00:01Z | 21.5  |",
+                "| resource | bananas      | 2     | 1970-01-01T00:00:02Z | 22    |",
+                "+----------+--------------+-------+----------------------+-------+",
+            ],
+            &[batches["temperature"].to_arrow(Selection::All).unwrap()]
+        );
+
+        assert_batches_eq!(
+            &[
+                "+----------+--------------+----------------------+-------+",
+                "| host     | service.name | time                 | value |",
+                "+----------+--------------+----------------------+-------+",
+                "| resource | bananas      | 1970-01-01T00:00:01Z | 42    |",
+                "+----------+--------------+----------------------+-------+",
+            ],
+            &[batches["requests"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_histogram() {
+        let req = request(vec![Metric {
+            name: "latency".to_string(),
+            data: Some(Data::Histogram(Histogram {
+                data_points: vec![HistogramDataPoint {
+                    attributes: vec![kv(
+                        "path",
+                        AnyValueKind::ArrayValue(ArrayValue {
+                            values: vec![AnyValue {
+                                value: Some(AnyValueKind::StringValue("/write".to_string())),
+                            }],
+                        }),
+                    )],
+                    time_unix_nano: 1_000_000_000,
+                    count: 6,
+                    sum: Some(4.5),
+                    bucket_counts: vec![1, 2, 3],
+                    explicit_bounds: vec![0.5, 1.0],
+                    ..Default::default()
+                }],
+                aggregation_temporality: 1,
+            })),
+            ..Default::default()
+        }]);
+
+        let (batches, stats) = export_request_to_batches(req, 0, &LineLimits::default()).unwrap();
+        assert_eq!(
+            stats,
+            OtlpStatistics {
+                num_points: 1,
+                num_fields: 5
+            }
+        );
+
+        assert_batches_eq!(
+            &[
+                "+------+-----+---+-------+----------+------------+--------------+-----+----------------------+",
+                "| +Inf | 0.5 | 1 | count | host     | path       | service.name | sum | time                 |",
+                "+------+-----+---+-------+----------+------------+--------------+-----+----------------------+",
+                "| 6    | 1   | 3 | 6     | resource | [\"/write\"] | bananas      | 4.5 | 1970-01-01T00:00:01Z |",
+                "+------+-----+---+-------+----------+------------+--------------+-----+----------------------+",
+            ],
+            &[batches["latency"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_histogram_invalid_buckets() {
+        let req = request(vec![Metric {
+            name: "latency".to_string(),
+            data: Some(Data::Histogram(Histogram {
+                data_points: vec![HistogramDataPoint {
+                    count: 6,
+                    bucket_counts: vec![1, 2, 3],
+                    explicit_bounds: vec![1.0, 1.0],
+                    ..Default::default()
+                }],
+                aggregation_temporality: 1,
+            })),
+            ..Default::default()
+        }]);
+
+        assert_matches!(
+            export_request_to_batches(req, 0, &LineLimits::default()),
+            Err(OtlpError::InvalidBuckets { metric }) => {
+                assert_eq!(metric, "latency");
+            }
+        );
+    }
+
+    #[test]
+    fn test_attribute_conflicts_with_field() {
+        let req = request(vec![Metric {
+            name: "temperature".to_string(),
+            data: Some(Data::Gauge(Gauge {
+                data_points: vec![number_point(
+                    vec![string_kv("value", "wat")],
+                    1,
+                    NumberValue::AsDouble(21.5),
+                )],
+            })),
+            ..Default::default()
+        }]);
+
+        assert_matches!(
+            export_request_to_batches(req, 0, &LineLimits::default()),
+            Err(OtlpError::Write { .. })
+        );
+    }
+
+    #[test]
+    fn test_line_limits() {
+        let gauge = |points: Vec<NumberDataPoint>| {
+            request(vec![Metric {
+                name: "temperature".to_string(),
+                data: Some(Data::Gauge(Gauge {
+                    data_points: points,
+                })),
+                ..Default::default()
+            }])
+        };
+        let point = |attributes| number_point(attributes, 1, NumberValue::AsDouble(21.5));
+
+        let limits = LineLimits {
+            max_lines: Some(1),
+            max_fields_per_line: None,
+            max_tags_per_line: Some(2),
+        };
+
+        assert_matches!(
+            export_request_to_batches(gauge(vec![point(vec![]), point(vec![])]), 0, &limits),
+            Err(OtlpError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyLines { max: 1 }
+            ))
+        );
+
+        // The resource attributes are counted as tags of each data point.
+        assert_matches!(
+            export_request_to_batches(gauge(vec![point(vec![string_kv("a", "b")])]), 0, &limits),
+            Err(OtlpError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyTags {
+                    line: 1,
+                    count: 3,
+                    max: 2
+                }
+            ))
+        );
+
+        export_request_to_batches(gauge(vec![point(vec![])]), 0, &limits)
+            .expect("write within limits should succeed");
+    }
+}
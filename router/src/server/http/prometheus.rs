@@ -0,0 +1,383 @@
+//! Conversion of Prometheus remote write requests into [`MutableBatch`]
+//! instances.
+
+use generated_types::prometheus::{TimeSeries, WriteRequest};
+use hashbrown::{HashMap, HashSet};
+use mutable_batch::{writer::Writer, MutableBatch};
+use schema::TIME_COLUMN_NAME;
+use std::iter;
+use thiserror::Error;
+
+use super::line_limits::LineLimits;
+
+/// The label containing the metric name of a series, used as the measurement
+/// name.
+const METRIC_NAME_LABEL: &str = "__name__";
+
+/// The field the sample values of a series are written to.
+const VALUE_FIELD_NAME: &str = "value";
+
+/// The NaN bit pattern Prometheus writes to mark a series as stale.
+///
+/// These markers do not carry a sample value and are dropped.
+const STALE_NAN_BITS: u64 = 0x7ff0000000000002;
+
+/// Errors returned when converting a Prometheus remote write request.
+#[derive(Debug, Error)]
+pub enum PromWriteError {
+    /// The request body is not valid snappy-compressed data.
+    #[error("failed to decode snappy-compressed body: {0}")]
+    InvalidSnappy(#[from] snap::Error),
+
+    /// The request body is not a valid protobuf `WriteRequest`.
+    #[error("failed to decode remote write request: {0}")]
+    Decode(#[from] prost::DecodeError),
+
+    /// A series has no metric name label.
+    #[error("series has no {} label", METRIC_NAME_LABEL)]
+    NoMetricName,
+
+    /// A series contains the same label more than once.
+    #[error("series {metric} contains duplicate label {label}")]
+    DuplicateLabel {
+        /// The metric name of the series.
+        metric: String,
+        /// The duplicated label name.
+        label: String,
+    },
+
+    /// A series contains a label with the same name as the value or time
+    /// column.
+    #[error("series {metric} contains reserved label {label}")]
+    ReservedLabel {
+        /// The metric name of the series.
+        metric: String,
+        /// The reserved label name.
+        label: String,
+    },
+
+    /// A sample timestamp cannot be represented in nanoseconds.
+    #[error("series {metric} contains out of range timestamp {timestamp_ms}ms")]
+    TimestampOverflow {
+        /// The metric name of the series.
+        metric: String,
+        /// The timestamp, in milliseconds.
+        timestamp_ms: i64,
+    },
+
+    /// Writing a series to the batch of its metric failed.
+    #[error("failed to write series {metric}: {source}")]
+    Write {
+        /// The metric name of the series.
+        metric: String,
+        /// The underlying error.
+        source: mutable_batch::writer::Error,
+    },
+
+    /// The write exceeds the configured line limits, counting each sample as
+    /// a line.
+    #[error(transparent)]
+    LimitExceeded(mutable_batch_lp::Error),
+}
+
+/// Convert the series in `req` into a [`MutableBatch`] per metric name, with
+/// the remaining labels of each series as tags and its samples in a `value`
+/// field.
+///
+/// Each sample is checked against `limits` as a line of line protocol with a
+/// single field.
+///
+/// Returns the batches, and the number of samples they contain.
+pub(crate) fn write_request_to_batches(
+    req: WriteRequest,
+    limits: &LineLimits,
+) -> Result<(HashMap<String, MutableBatch>, usize), PromWriteError> {
+    let mut batches: HashMap<String, MutableBatch> = HashMap::new();
+    let mut num_samples = 0;
+
+    for series in req.timeseries {
+        num_samples += write_series(&mut batches, series, num_samples, limits)?;
+    }
+
+    Ok((batches, num_samples))
+}
+
+/// Write the samples of `series` to the batch of its metric name, returning
+/// the number of samples written.
+///
+/// `num_samples` is the number of samples of the request written before this
+/// series.
+fn write_series(
+    batches: &mut HashMap<String, MutableBatch>,
+    series: TimeSeries,
+    num_samples: usize,
+    limits: &LineLimits,
+) -> Result<usize, PromWriteError> {
+    let metric = series
+        .labels
+        .iter()
+        .find(|l| l.name == METRIC_NAME_LABEL)
+        .map(|l| l.value.as_str())
+        .ok_or(PromWriteError::NoMetricName)?;
+
+    // Validate the tags before writing, as the writer panics if a column is
+    // written twice.
+    let mut seen = HashSet::with_capacity(series.labels.len());
+    let tags = series
+        .labels
+        .iter()
+        .filter(|l| l.name != METRIC_NAME_LABEL)
+        .map(|l| {
+            if l.name == TIME_COLUMN_NAME || l.name == VALUE_FIELD_NAME {
+                return Err(PromWriteError::ReservedLabel {
+                    metric: metric.to_string(),
+                    label: l.name.clone(),
+                });
+            }
+            if !seen.insert(l.name.as_str()) {
+                return Err(PromWriteError::DuplicateLabel {
+                    metric: metric.to_string(),
+                    label: l.name.clone(),
+                });
+            }
+            Ok(l)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let samples = series
+        .samples
+        .iter()
+        .filter(|s| s.value.to_bits() != STALE_NAN_BITS)
+        .map(|s| {
+            let time = s.timestamp.checked_mul(1_000_000).ok_or_else(|| {
+                PromWriteError::TimestampOverflow {
+                    metric: metric.to_string(),
+                    timestamp_ms: s.timestamp,
+                }
+            })?;
+            Ok((time, s.value))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if samples.is_empty() {
+        return Ok(0);
+    }
+
+    // Every sample of the series has the same single field and tags, so
+    // checking the last is sufficient.
+    limits
+        .check(num_samples + samples.len(), 1, tags.len())
+        .map_err(PromWriteError::LimitExceeded)?;
+
+    let write_err = |source| PromWriteError::Write {
+        metric: metric.to_string(),
+        source,
+    };
+
+    let batch = batches.entry_ref(metric).or_default();
+    let mut writer = Writer::new(batch, samples.len());
+    for tag in tags {
+        writer
+            .write_tag(
+                &tag.name,
+                None,
+                iter::repeat(tag.value.as_str()).take(samples.len()),
+            )
+            .map_err(write_err)?;
+    }
+    writer
+        .write_f64(VALUE_FIELD_NAME, None, samples.iter().map(|(_, v)| *v))
+        .map_err(write_err)?;
+    writer
+        .write_time(TIME_COLUMN_NAME, samples.iter().map(|(t, _)| *t))
+        .map_err(write_err)?;
+    writer.commit();
+
+    Ok(samples.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_util::assert_batches_eq;
+    use assert_matches::assert_matches;
+    use generated_types::prometheus::{Label, Sample};
+    use schema::selection::Selection;
+
+    fn series(labels: &[(&str, &str)], samples: &[(i64, f64)]) -> TimeSeries {
+        TimeSeries {
+            labels: labels
+                .iter()
+                .map(|(name, value)| Label {
+                    name: name.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            samples: samples
+                .iter()
+                .map(|(timestamp, value)| Sample {
+                    value: *value,
+                    timestamp: *timestamp,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_write_request_to_batches() {
+        let req = WriteRequest {
+            timeseries: vec![
+                series(
+                    &[("__name__", "http_requests_total"), ("code", "200")],
+                    &[(1000, 1.0), (2000, 2.0)],
+                ),
+                series(
+                    &[
+                        ("code", "500"),
+                        ("__name__", "http_requests_total"),
+                        ("path", "/write"),
+                    ],
+                    &[(1000, 3.0)],
+                ),
+                series(&[("__name__", "up")], &[(1000, 1.0)]),
+                // Series with only stale markers are skipped.
+                series(
+                    &[("__name__", "stale")],
+                    &[(1000, f64::from_bits(STALE_NAN_BITS))],
+                ),
+            ],
+        };
+
+        let (batches, num_samples) = write_request_to_batches(req, &LineLimits::default()).unwrap();
+        assert_eq!(num_samples, 4);
+        assert_eq!(batches.len(), 2);
+
+        assert_batches_eq!(
+            &[
+                "+------+--------+----------------------+-------+",
+                "| code | path   | time                 | value |",
+                "+------+--------+----------------------+-------+",
+                "| 200  |        | 1970-01-01T00:00:01Z | 1     |",
+                "| 200  |        | 1970-01-01T00:00:02Z | 2     |",
+                "| 500  | /write | 1970-01-01T00:00:01Z | 3     |",
+                "+------+--------+----------------------+-------+",
+            ],
+            &[batches["http_requests_total"]
+                .to_arrow(Selection::All)
+                .unwrap()]
+        );
+
+        assert_batches_eq!(
+            &[
+                "+----------------------+-------+",
+                "| time                 | value |",
+                "+----------------------+-------+",
+                "| 1970-01-01T00:00:01Z | 1     |",
+                "+----------------------+-------+",
+            ],
+            &[batches["up"].to_arrow(Selection::All).unwrap()]
+        );
+    }
+
+    #[test]
+    fn test_no_metric_name() {
+        let req = WriteRequest {
+            timeseries: vec![series(&[("code", "200")], &[(1000, 1.0)])],
+        };
+        assert_matches!(
+            write_request_to_batches(req, &LineLimits::default()),
+            Err(PromWriteError::NoMetricName)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_label() {
+        let req = WriteRequest {
+            timeseries: vec![series(
+                &[("__name__", "up"), ("code", "200"), ("code", "500")],
+                &[(1000, 1.0)],
+            )],
+        };
+        assert_matches!(
+            write_request_to_batches(req, &LineLimits::default()),
+            Err(PromWriteError::DuplicateLabel { label, .. }) => {
+                assert_eq!(label, "code");
+            }
+        );
+    }
+
+    #[test]
+    fn test_reserved_label() {
+        let req = WriteRequest {
+            timeseries: vec![series(
+                &[("__name__", "up"), ("time", "now")],
+                &[(1000, 1.0)],
+            )],
+        };
+        assert_matches!(
+            write_request_to_batches(req, &LineLimits::default()),
+            Err(PromWriteError::ReservedLabel { label, .. }) => {
+                assert_eq!(label, "time");
+            }
+        );
+    }
+
+    #[test]
+    fn test_timestamp_overflow() {
+        let req = WriteRequest {
+            timeseries: vec![series(&[("__name__", "up")], &[(i64::MAX, 1.0)])],
+        };
+        assert_matches!(
+            write_request_to_batches(req, &LineLimits::default()),
+            Err(PromWriteError::TimestampOverflow { .. })
+        );
+    }
+
+    #[test]
+    fn test_line_limits() {
+        let limits = LineLimits {
+            max_lines: Some(2),
+            max_fields_per_line: Some(1),
+            max_tags_per_line: Some(1),
+        };
+
+        let req = WriteRequest {
+            timeseries: vec![
+                series(&[("__name__", "up")], &[(1000, 1.0)]),
+                series(&[("__name__", "up")], &[(2000, 1.0), (3000, 1.0)]),
+            ],
+        };
+        assert_matches!(
+            write_request_to_batches(req, &limits),
+            Err(PromWriteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyLines { max: 2 }
+            ))
+        );
+
+        let req = WriteRequest {
+            timeseries: vec![series(
+                &[("__name__", "up"), ("code", "200"), ("path", "/write")],
+                &[(1000, 1.0)],
+            )],
+        };
+        assert_matches!(
+            write_request_to_batches(req, &limits),
+            Err(PromWriteError::LimitExceeded(
+                mutable_batch_lp::Error::TooManyTags {
+                    line: 1,
+                    count: 2,
+                    max: 1
+                }
+            ))
+        );
+
+        // The metric name label is not a tag.
+        let req = WriteRequest {
+            timeseries: vec![series(
+                &[("__name__", "up"), ("code", "200")],
+                &[(1000, 1.0)],
+            )],
+        };
+        write_request_to_batches(req, &limits).expect("write within limits should succeed");
+    }
+}
@@ -0,0 +1,334 @@
+//! Per-org rate limiting of write requests.
+
+use hashbrown::HashMap;
+use iox_time::Time;
+use parking_lot::Mutex;
+use std::{num::NonZeroU64, time::Duration};
+
+/// The minimum interval between sweeps evicting the buckets of idle orgs.
+const EVICTION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A token bucket holding up to one second's worth of budget, refilled
+/// continuously at `rate` tokens per second.
+///
+/// Requests are admitted while the bucket holds any tokens, and the actual
+/// cost of a request is taken once known, which may leave the bucket in debt.
+/// Subsequent requests are rejected until the debt is repaid.
+#[derive(Debug)]
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    last_refill: Time,
+}
+
+impl TokenBucket {
+    fn new(rate: NonZeroU64, now: Time) -> Self {
+        let rate = rate.get() as f64;
+        Self {
+            rate,
+            tokens: rate,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Time) {
+        // Time may go backwards - never refill (or drain) in that case.
+        if let Some(elapsed) = now.checked_duration_since(self.last_refill) {
+            self.tokens = (self.tokens + self.rate * elapsed.as_secs_f64()).min(self.rate);
+            self.last_refill = now;
+        }
+    }
+
+    /// Returns the duration until this bucket admits requests again, or
+    /// [`None`] if it admits requests now.
+    fn retry_after(&self) -> Option<Duration> {
+        if self.tokens > 0.0 {
+            return None;
+        }
+        // Wait until the debt is repaid, and one more token is available,
+        // rounded up to the next millisecond.
+        let millis = ((1.0 - self.tokens) * 1000.0 / self.rate).ceil();
+        Some(Duration::from_millis(millis as u64))
+    }
+
+    fn take(&mut self, n: u64) {
+        self.tokens -= n as f64;
+    }
+
+    /// Returns true if the bucket holds its full budget, and is therefore
+    /// indistinguishable from a newly created bucket.
+    fn is_full(&self) -> bool {
+        self.tokens >= self.rate
+    }
+}
+
+/// The token buckets of a single org.
+#[derive(Debug)]
+struct OrgBuckets {
+    lines: Option<TokenBucket>,
+    bytes: Option<TokenBucket>,
+}
+
+impl OrgBuckets {
+    fn buckets(&mut self) -> impl Iterator<Item = &mut TokenBucket> {
+        self.lines.iter_mut().chain(self.bytes.iter_mut())
+    }
+
+    /// Returns true if all the buckets are full, in which case the org is
+    /// treated exactly as an org that has never written.
+    fn is_full(&self) -> bool {
+        self.lines
+            .iter()
+            .chain(self.bytes.iter())
+            .all(TokenBucket::is_full)
+    }
+}
+
+/// A rate limiter of write requests, applying a separate token-bucket budget
+/// of lines per second and bytes per second to each org.
+///
+/// This prevents a single noisy org from monopolising the shared request
+/// capacity of the router - once an org exhausts its budget, subsequent
+/// requests are rejected before their bodies are read.
+///
+/// Only the orgs that have written recently are tracked - the buckets of an
+/// org are dropped once they refill completely, as a full bucket admits
+/// requests exactly as a new one does.
+#[derive(Debug)]
+pub struct OrgRateLimiter {
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    lines_per_second: Option<NonZeroU64>,
+    bytes_per_second: Option<NonZeroU64>,
+
+    orgs: HashMap<String, OrgBuckets>,
+
+    /// The time of the last sweep evicting the buckets of idle orgs.
+    last_eviction: Option<Time>,
+}
+
+impl State {
+    /// Evict the buckets of all orgs that have refilled completely by `now`,
+    /// if at least [`EVICTION_INTERVAL`] has passed since the last sweep.
+    fn evict_idle(&mut self, now: Time) {
+        if let Some(last) = self.last_eviction {
+            match now.checked_duration_since(last) {
+                Some(elapsed) if elapsed >= EVICTION_INTERVAL => {}
+                _ => return,
+            }
+        }
+        self.last_eviction = Some(now);
+
+        self.orgs.retain(|_, buckets| {
+            buckets.buckets().for_each(|b| b.refill(now));
+            !buckets.is_full()
+        });
+    }
+}
+
+impl OrgRateLimiter {
+    /// Initialise a rate limiter allowing each org to write at most
+    /// `lines_per_second` lines and `bytes_per_second` (decompressed) bytes
+    /// each second, averaged over time.
+    ///
+    /// A [`None`] budget is not enforced.
+    pub fn new(lines_per_second: Option<NonZeroU64>, bytes_per_second: Option<NonZeroU64>) -> Self {
+        Self {
+            state: Mutex::new(State {
+                lines_per_second,
+                bytes_per_second,
+                orgs: Default::default(),
+                last_eviction: None,
+            }),
+        }
+    }
+
+    /// Change the per-org budgets to `lines_per_second` and
+    /// `bytes_per_second`.
+    ///
+    /// The budget of every org is reset, forgiving any outstanding debt.
+    pub(crate) fn set_rates(
+        &self,
+        lines_per_second: Option<NonZeroU64>,
+        bytes_per_second: Option<NonZeroU64>,
+    ) {
+        let mut state = self.state.lock();
+        state.lines_per_second = lines_per_second;
+        state.bytes_per_second = bytes_per_second;
+        state.orgs.clear();
+    }
+
+    /// Returns the duration after which `org` should retry if it has exhausted
+    /// its budget at time `now`, or [`None`] if the request is admitted.
+    pub(crate) fn check(&self, org: &str, now: Time) -> Option<Duration> {
+        let mut state = self.state.lock();
+        let buckets = state.orgs.get_mut(org)?;
+
+        let retry_after = buckets
+            .buckets()
+            .filter_map(|b| {
+                b.refill(now);
+                b.retry_after()
+            })
+            .max();
+
+        if buckets.is_full() {
+            state.orgs.remove(org);
+        }
+
+        retry_after
+    }
+
+    /// Take `lines` and `bytes` from the budget of `org` at time `now`.
+    pub(crate) fn record(&self, org: &str, lines: u64, bytes: u64, now: Time) {
+        let mut state = self.state.lock();
+        state.evict_idle(now);
+
+        let State {
+            lines_per_second,
+            bytes_per_second,
+            orgs,
+            ..
+        } = &mut *state;
+        let buckets = orgs.entry_ref(org).or_insert_with(|| OrgBuckets {
+            lines: lines_per_second.map(|r| TokenBucket::new(r, now)),
+            bytes: bytes_per_second.map(|r| TokenBucket::new(r, now)),
+        });
+
+        if let Some(b) = buckets.lines.as_mut() {
+            b.refill(now);
+            b.take(lines);
+        }
+        if let Some(b) = buckets.bytes.as_mut() {
+            b.refill(now);
+            b.take(bytes);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(v: u64) -> Option<NonZeroU64> {
+        Some(NonZeroU64::new(v).unwrap())
+    }
+
+    #[test]
+    fn test_lines_limit() {
+        let limiter = OrgRateLimiter::new(limit(10), None);
+        let t0 = Time::from_timestamp_nanos(0);
+
+        // An unknown org is always admitted.
+        assert_eq!(limiter.check("bananas", t0), None);
+
+        // Using some of the budget continues to admit requests.
+        limiter.record("bananas", 5, 1_000_000, t0);
+        assert_eq!(limiter.check("bananas", t0), None);
+
+        // Exhausting the budget (and going into debt) rejects requests until
+        // the debt is repaid.
+        limiter.record("bananas", 15, 1_000_000, t0);
+        assert_eq!(
+            limiter.check("bananas", t0),
+            Some(Duration::from_millis(1100))
+        );
+
+        // Other orgs are unaffected.
+        assert_eq!(limiter.check("platanos", t0), None);
+
+        // After some time, the debt is partially repaid.
+        let t1 = t0 + Duration::from_millis(500);
+        assert_eq!(
+            limiter.check("bananas", t1),
+            Some(Duration::from_millis(600))
+        );
+
+        // And eventually the budget is available again.
+        let t2 = t1 + Duration::from_millis(600);
+        assert_eq!(limiter.check("bananas", t2), None);
+
+        // The budget never exceeds one second's worth of lines.
+        let t3 = t2 + Duration::from_secs(60);
+        limiter.record("bananas", 10, 0, t3);
+        assert!(limiter.check("bananas", t3).is_some());
+    }
+
+    #[test]
+    fn test_bytes_limit() {
+        let limiter = OrgRateLimiter::new(limit(1_000_000), limit(100));
+        let t0 = Time::from_timestamp_nanos(0);
+
+        limiter.record("bananas", 1, 299, t0);
+        assert_eq!(limiter.check("bananas", t0), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_set_rates() {
+        let limiter = OrgRateLimiter::new(limit(10), None);
+        let t0 = Time::from_timestamp_nanos(0);
+
+        limiter.record("bananas", 20, 0, t0);
+        assert!(limiter.check("bananas", t0).is_some());
+
+        // Changing the rates forgives the debt, and applies the new budget.
+        limiter.set_rates(None, limit(100));
+        assert_eq!(limiter.check("bananas", t0), None);
+        limiter.record("bananas", 1_000, 150, t0);
+        assert_eq!(
+            limiter.check("bananas", t0),
+            Some(Duration::from_millis(510))
+        );
+
+        // Removing all the budgets admits every request.
+        limiter.set_rates(None, None);
+        limiter.record("bananas", 1_000, 1_000, t0);
+        assert_eq!(limiter.check("bananas", t0), None);
+    }
+
+    #[test]
+    fn test_time_goes_backwards() {
+        let limiter = OrgRateLimiter::new(limit(10), None);
+        let t0 = Time::from_timestamp_nanos(1_000_000_000);
+
+        limiter.record("bananas", 10, 0, t0);
+        let t1 = Time::from_timestamp_nanos(0);
+        assert_eq!(
+            limiter.check("bananas", t1),
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn test_evict_idle() {
+        let limiter = OrgRateLimiter::new(limit(10), None);
+        let t0 = Time::from_timestamp_nanos(0);
+        let orgs = |limiter: &OrgRateLimiter| limiter.state.lock().orgs.len();
+
+        limiter.record("bananas", 5, 0, t0);
+        limiter.record("platanos", 20, 0, t0);
+        assert_eq!(orgs(&limiter), 2);
+
+        // An org is evicted once its bucket is observed to be full again.
+        let t1 = t0 + Duration::from_secs(1);
+        assert_eq!(limiter.check("bananas", t1), None);
+        assert_eq!(orgs(&limiter), 1);
+
+        limiter.record("platanos", 200, 0, t1);
+
+        // Writes periodically evict all the orgs whose buckets have refilled,
+        // but not those still in debt.
+        let t2 = t0 + EVICTION_INTERVAL;
+        limiter.record("apples", 1, 0, t2);
+        assert_eq!(orgs(&limiter), 2);
+        assert!(limiter.check("platanos", t2).is_some());
+
+        let t3 = t2 + Duration::from_secs(60);
+        limiter.record("apples", 1, 0, t3);
+        assert_eq!(orgs(&limiter), 1);
+        assert_eq!(limiter.check("platanos", t3), None);
+    }
+}
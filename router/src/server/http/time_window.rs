@@ -0,0 +1,190 @@
+//! Bounds on the timestamps of written points, relative to the current time.
+
+use hashbrown::HashMap;
+use iox_time::Time;
+use mutable_batch::MutableBatch;
+use std::time::Duration;
+use thiserror::Error;
+
+/// Errors returned when a write contains a point outside of the permitted
+/// time window of its namespace.
+#[derive(Debug, Error)]
+pub enum TimeWindowError {
+    /// The write contains a point older than the permitted maximum age.
+    #[error(
+        "table {table} contains a point at {timestamp}, older than the maximum age of {max_age:?}"
+    )]
+    TooOld {
+        /// The table containing the point.
+        table: String,
+        /// The timestamp of the oldest point in the table.
+        timestamp: Time,
+        /// The maximum age of a point.
+        max_age: Duration,
+    },
+
+    /// The write contains a point further in the future than permitted.
+    #[error(
+        "table {table} contains a point at {timestamp}, more than {max_future:?} in the future"
+    )]
+    TooNew {
+        /// The table containing the point.
+        table: String,
+        /// The timestamp of the newest point in the table.
+        timestamp: Time,
+        /// The maximum distance of a point into the future.
+        max_future: Duration,
+    },
+}
+
+/// The range of timestamps, relative to the time of the write, that may be
+/// written to a namespace.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// Reject points older than this, if set (typically the retention period
+    /// of the namespace).
+    pub max_age: Option<Duration>,
+    /// Reject points further than this into the future, if set.
+    pub max_future: Option<Duration>,
+}
+
+/// The [`TimeWindow`] applied to writes, with optional per-namespace
+/// overrides.
+///
+/// By default, points of any timestamp are accepted.
+#[derive(Debug, Default, Clone)]
+pub struct TimeWindows {
+    default: TimeWindow,
+    namespaces: HashMap<String, TimeWindow>,
+}
+
+impl TimeWindows {
+    /// Apply `window` to writes to namespaces without an override.
+    pub fn new(default: TimeWindow) -> Self {
+        Self {
+            default,
+            namespaces: Default::default(),
+        }
+    }
+
+    /// Apply `window` to writes to `namespace`, instead of the default.
+    pub fn with_namespace(mut self, namespace: impl Into<String>, window: TimeWindow) -> Self {
+        self.namespaces.insert(namespace.into(), window);
+        self
+    }
+
+    /// Ensure all points in `batches`, to be written to `namespace` at `now`,
+    /// fall within the time window of the namespace.
+    pub(crate) fn check(
+        &self,
+        namespace: &str,
+        batches: &HashMap<String, MutableBatch>,
+        now: Time,
+    ) -> Result<(), TimeWindowError> {
+        let window = self.namespaces.get(namespace).unwrap_or(&self.default);
+        if *window == TimeWindow::default() {
+            return Ok(());
+        }
+
+        // Bounds beyond the representable range of timestamps are no bound
+        // at all.
+        let min = window
+            .max_age
+            .and_then(|d| now.checked_sub(d).map(|t| (d, t.timestamp_nanos())));
+        let max = window
+            .max_future
+            .and_then(|d| now.checked_add(d).map(|t| (d, t.timestamp_nanos())));
+
+        for (table, batch) in batches {
+            let stats = match batch.timestamp_summary() {
+                Some(v) => v.stats,
+                None => continue,
+            };
+
+            if let (Some((max_age, min)), Some(ts)) = (min, stats.min) {
+                if ts < min {
+                    return Err(TimeWindowError::TooOld {
+                        table: table.clone(),
+                        timestamp: Time::from_timestamp_nanos(ts),
+                        max_age,
+                    });
+                }
+            }
+            if let (Some((max_future, max)), Some(ts)) = (max, stats.max) {
+                if ts > max {
+                    return Err(TimeWindowError::TooNew {
+                        table: table.clone(),
+                        timestamp: Time::from_timestamp_nanos(ts),
+                        max_future,
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use mutable_batch_lp::lines_to_batches;
+
+    const NOW: i64 = 1_000_000_000_000;
+
+    fn batches(lp: &str) -> HashMap<String, MutableBatch> {
+        lines_to_batches(lp, 0).expect("invalid line protocol")
+    }
+
+    #[test]
+    fn test_check() {
+        let windows = TimeWindows::new(TimeWindow {
+            max_age: Some(Duration::from_secs(60)),
+            max_future: Some(Duration::from_secs(10)),
+        })
+        .with_namespace(
+            "bananas_test",
+            TimeWindow {
+                max_age: None,
+                max_future: Some(Duration::from_secs(1)),
+            },
+        );
+        let now = Time::from_timestamp_nanos(NOW);
+
+        // Points at the bounds are accepted.
+        let ok = batches(&format!(
+            "platanos v=1 {}\nplatanos v=2 {}",
+            NOW - 60_000_000_000,
+            NOW + 10_000_000_000
+        ));
+        windows.check("other", &ok, now).expect("should accept");
+
+        let old = batches(&format!("platanos v=1 {}", NOW - 60_000_000_001));
+        assert_matches!(
+            windows.check("other", &old, now),
+            Err(TimeWindowError::TooOld { table, max_age, .. }) => {
+                assert_eq!(table, "platanos");
+                assert_eq!(max_age, Duration::from_secs(60));
+            }
+        );
+
+        let new = batches(&format!("platanos v=1 {}", NOW + 10_000_000_001));
+        assert_matches!(
+            windows.check("other", &new, now),
+            Err(TimeWindowError::TooNew { .. })
+        );
+
+        // The namespace override has no maximum age, but a tighter future
+        // bound.
+        windows
+            .check("bananas_test", &old, now)
+            .expect("should accept");
+        assert_matches!(
+            windows.check("bananas_test", &ok, now),
+            Err(TimeWindowError::TooNew { max_future, .. }) => {
+                assert_eq!(max_future, Duration::from_secs(1));
+            }
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Derivation of the target namespace of a request from its API token.
+
+use super::auth::{AuthError, Credentials};
+use async_trait::async_trait;
+use data_types::DatabaseName;
+use hashbrown::HashMap;
+use std::fmt::Debug;
+
+/// The namespace an API token is bound to, and the org it is accounted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenNamespace {
+    /// The org against which per-org limits are applied.
+    pub org: String,
+    /// The namespace all requests presenting the token operate on.
+    pub namespace: DatabaseName<'static>,
+}
+
+/// An abstract lookup of the namespace bound to the credentials presented by
+/// a request, such as a catalog table or an external token service.
+///
+/// When configured, the org & bucket (or db & rp) parameters of a request are
+/// ignored and the request operates on the namespace bound to its
+/// credentials, so that each token addresses exactly one namespace.
+#[async_trait]
+pub trait TokenNamespaceLookup: Debug + Send + Sync {
+    /// Return the namespace bound to `credentials`, or [`None`] if the
+    /// credentials are not bound to any namespace.
+    async fn lookup(&self, credentials: &Credentials) -> Result<Option<TokenNamespace>, AuthError>;
+}
+
+/// A [`TokenNamespaceLookup`] of a fixed set of tokens.
+///
+/// Tokens are presented either as an InfluxDB 2.x API token, or as the
+/// password of InfluxDB 1.x basic credentials (with any username), following
+/// the InfluxDB 1.x compatibility API convention.
+#[derive(Debug, Default)]
+pub struct StaticTokenNamespaces {
+    tokens: HashMap<String, TokenNamespace>,
+}
+
+impl StaticTokenNamespaces {
+    /// Bind `token` to `namespace`, accounted to `org`.
+    pub fn with_token(
+        mut self,
+        token: impl Into<String>,
+        org: impl Into<String>,
+        namespace: DatabaseName<'static>,
+    ) -> Self {
+        self.tokens.insert(
+            token.into(),
+            TokenNamespace {
+                org: org.into(),
+                namespace,
+            },
+        );
+        self
+    }
+}
+
+#[async_trait]
+impl TokenNamespaceLookup for StaticTokenNamespaces {
+    async fn lookup(&self, credentials: &Credentials) -> Result<Option<TokenNamespace>, AuthError> {
+        let token = match credentials {
+            Credentials::Token(token) => token,
+            Credentials::Basic { password, .. } => password,
+        };
+        Ok(self.tokens.get(token).cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_static_lookup() {
+        let lookup = StaticTokenNamespaces::default().with_token(
+            "s3cret",
+            "bananas",
+            DatabaseName::new("bananas_test").unwrap(),
+        );
+        let want = TokenNamespace {
+            org: "bananas".to_string(),
+            namespace: DatabaseName::new("bananas_test").unwrap(),
+        };
+
+        let got = lookup
+            .lookup(&Credentials::Token("s3cret".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(got.as_ref(), Some(&want));
+
+        let got = lookup
+            .lookup(&Credentials::Basic {
+                username: "anyone".to_string(),
+                password: "s3cret".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(got.as_ref(), Some(&want));
+
+        let got = lookup
+            .lookup(&Credentials::Token("bananas".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(got, None);
+    }
+}
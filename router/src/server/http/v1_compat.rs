@@ -0,0 +1,172 @@
+//! Responses to the probes of InfluxDB 1.x & 2.x clients and health checkers,
+//! which expect a router to respond to the `/ping`, `/health` and `/query`
+//! endpoints.
+
+use data_types::ShardIndex;
+use hyper::{header::CONTENT_TYPE, Body, Method, Request, Response, StatusCode};
+use serde_json::json;
+
+/// The version advertised to clients.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// The build advertised to clients in the `X-Influxdb-Build` header.
+const BUILD: &str = "IOx";
+
+/// The message returned for (unsupported) queries.
+const QUERY_NOT_SUPPORTED: &str = "the router does not serve queries";
+
+/// Returns the response to `req` if it is a request to one of the
+/// compatibility endpoints.
+///
+/// Health checks fail if `unhealthy_shards` returns any shards.
+pub(crate) fn probe_response<F>(req: &Request<Body>, unhealthy_shards: F) -> Option<Response<Body>>
+where
+    F: FnOnce() -> Vec<ShardIndex>,
+{
+    match (req.method(), req.uri().path()) {
+        (&Method::GET | &Method::HEAD, "/ping") => Some(ping_response()),
+        (&Method::GET, "/health") => Some(health_response(&unhealthy_shards())),
+        (&Method::GET | &Method::POST, "/query") => Some(query_response()),
+        _ => None,
+    }
+}
+
+/// An empty response advertising the build & version of the router.
+fn ping_response() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("X-Influxdb-Build", BUILD)
+        .header("X-Influxdb-Version", VERSION)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// An InfluxDB 2.x compatible health check response, failing with a 503 if
+/// the write buffer of any shard in `unhealthy_shards` is unreachable.
+pub(crate) fn health_response(unhealthy_shards: &[ShardIndex]) -> Response<Body> {
+    if unhealthy_shards.is_empty() {
+        let body = json!({
+            "name": "router",
+            "message": "ready for writes",
+            "status": "pass",
+            "checks": [],
+            "version": VERSION,
+        });
+        return json_response(StatusCode::OK, body);
+    }
+
+    let shards = unhealthy_shards
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let body = json!({
+        "name": "router",
+        "message": "write buffer unavailable",
+        "status": "fail",
+        "checks": [{
+            "name": "write-buffer",
+            "message": format!("unreachable shards: {}", shards),
+            "status": "fail",
+        }],
+        "version": VERSION,
+    });
+
+    json_response(StatusCode::SERVICE_UNAVAILABLE, body)
+}
+
+/// An InfluxDB 1.x compatible query response, containing a statement error.
+///
+/// Clients probing the query endpoint (such as by issuing `SHOW DATABASES`
+/// when connecting) receive a well-formed response rather than a 404.
+fn query_response() -> Response<Body> {
+    let body = json!({
+        "results": [{
+            "statement_id": 0,
+            "error": QUERY_NOT_SUPPORTED,
+        }],
+    });
+
+    json_response(StatusCode::OK, body)
+}
+
+fn json_response(status: StatusCode, body: serde_json::Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Influxdb-Build", BUILD)
+        .header("X-Influxdb-Version", VERSION)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(method: Method, path: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(format!("https://bananas.example{}", path))
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    async fn body_json(response: Response<Body>) -> serde_json::Value {
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[test]
+    fn test_ping() {
+        for method in [Method::GET, Method::HEAD] {
+            let response =
+                probe_response(&request(method, "/ping"), Vec::new).expect("should respond");
+            assert_eq!(response.status(), StatusCode::NO_CONTENT);
+            assert_eq!(response.headers()["X-Influxdb-Build"], BUILD);
+            assert_eq!(response.headers()["X-Influxdb-Version"], VERSION);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let response =
+            probe_response(&request(Method::GET, "/health"), Vec::new).expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[CONTENT_TYPE], "application/json");
+
+        let body = body_json(response).await;
+        assert_eq!(body["status"], "pass");
+        assert_eq!(body["version"], VERSION);
+    }
+
+    #[tokio::test]
+    async fn test_health_unhealthy_shards() {
+        let response = probe_response(&request(Method::GET, "/health"), || {
+            vec![ShardIndex::new(1), ShardIndex::new(3)]
+        })
+        .expect("should respond");
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+        let body = body_json(response).await;
+        assert_eq!(body["status"], "fail");
+        assert_eq!(body["checks"][0]["name"], "write-buffer");
+        assert_eq!(body["checks"][0]["message"], "unreachable shards: 1, 3");
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let response =
+            probe_response(&request(Method::POST, "/query"), Vec::new).expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = body_json(response).await;
+        assert_eq!(body["results"][0]["error"], QUERY_NOT_SUPPORTED);
+    }
+
+    #[test]
+    fn test_other_paths() {
+        assert!(probe_response(&request(Method::POST, "/ping"), Vec::new).is_none());
+        assert!(probe_response(&request(Method::POST, "/api/v2/write"), Vec::new).is_none());
+    }
+}
@@ -0,0 +1,200 @@
+//! Write metrics, optionally broken down by the namespace written to.
+
+use hashbrown::HashMap;
+use metric::{Attributes, Metric, U64Counter};
+use parking_lot::Mutex;
+use std::{borrow::Cow, sync::Arc};
+
+/// The metric attribute recording the namespace written to.
+const NAMESPACE_ATTRIBUTE: &str = "namespace";
+
+/// The value of [`NAMESPACE_ATTRIBUTE`] used for writes to namespaces beyond
+/// the cardinality cap.
+///
+/// This is not a valid namespace name, and so never collides with one.
+const OVERFLOW_NAMESPACE: &str = "<other>";
+
+/// The counters recording writes with a single set of attributes.
+#[derive(Debug)]
+struct Recorders {
+    lines: U64Counter,
+    fields: U64Counter,
+    tables: U64Counter,
+    body_size: U64Counter,
+}
+
+/// Per-namespace recorders, limited to a fixed number of namespaces.
+#[derive(Debug)]
+struct NamespaceRecorders {
+    max_namespaces: usize,
+    namespaces: Mutex<HashMap<String, Arc<Recorders>>>,
+    overflow: Arc<Recorders>,
+}
+
+/// The line protocol write metrics of the router.
+///
+/// By default all writes are recorded without attributes. Once
+/// [`WriteMetrics::set_max_namespaces()`] is called, writes are instead
+/// recorded with a `namespace` attribute for up to a fixed number of distinct
+/// namespaces, with writes to any further namespaces recorded against a
+/// single shared overflow attribute value, capping the cardinality of the
+/// metrics.
+#[derive(Debug)]
+pub(crate) struct WriteMetrics {
+    lines: Metric<U64Counter>,
+    fields: Metric<U64Counter>,
+    tables: Metric<U64Counter>,
+    body_size: Metric<U64Counter>,
+
+    unlabelled: Recorders,
+    per_namespace: Option<NamespaceRecorders>,
+}
+
+impl WriteMetrics {
+    pub(crate) fn new(metrics: &metric::Registry) -> Self {
+        let lines = metrics.register_metric::<U64Counter>(
+            "http_write_lines_total",
+            "cumulative number of line protocol lines successfully routed",
+        );
+        let fields = metrics.register_metric::<U64Counter>(
+            "http_write_fields_total",
+            "cumulative number of line protocol fields successfully routed",
+        );
+        let tables = metrics.register_metric::<U64Counter>(
+            "http_write_tables_total",
+            "cumulative number of tables in each write request",
+        );
+        let body_size = metrics.register_metric::<U64Counter>(
+            "http_write_body_bytes_total",
+            "cumulative byte size of successfully routed (decompressed) line protocol write requests",
+        );
+
+        let unlabelled = Recorders {
+            lines: lines.recorder(&[]),
+            fields: fields.recorder(&[]),
+            tables: tables.recorder(&[]),
+            body_size: body_size.recorder(&[]),
+        };
+
+        Self {
+            lines,
+            fields,
+            tables,
+            body_size,
+            unlabelled,
+            per_namespace: None,
+        }
+    }
+
+    fn namespace_recorders(&self, namespace: &str) -> Recorders {
+        let attributes =
+            Attributes::from([(NAMESPACE_ATTRIBUTE, Cow::Owned(namespace.to_string()))]);
+        Recorders {
+            lines: self.lines.recorder(attributes.clone()),
+            fields: self.fields.recorder(attributes.clone()),
+            tables: self.tables.recorder(attributes.clone()),
+            body_size: self.body_size.recorder(attributes),
+        }
+    }
+
+    /// Record writes with a `namespace` attribute, for at most
+    /// `max_namespaces` distinct namespaces.
+    pub(crate) fn set_max_namespaces(&mut self, max_namespaces: usize) {
+        self.per_namespace = Some(NamespaceRecorders {
+            max_namespaces,
+            namespaces: Default::default(),
+            overflow: Arc::new(self.namespace_recorders(OVERFLOW_NAMESPACE)),
+        });
+    }
+
+    /// Record a successful write to `namespace`.
+    pub(crate) fn record(
+        &self,
+        namespace: &str,
+        num_lines: usize,
+        num_fields: usize,
+        num_tables: usize,
+        body_size: usize,
+    ) {
+        let record = |r: &Recorders| {
+            r.lines.inc(num_lines as _);
+            r.fields.inc(num_fields as _);
+            r.tables.inc(num_tables as _);
+            r.body_size.inc(body_size as _);
+        };
+
+        let per_namespace = match &self.per_namespace {
+            Some(v) => v,
+            None => return record(&self.unlabelled),
+        };
+
+        let recorders = {
+            let mut namespaces = per_namespace.namespaces.lock();
+            match namespaces.get(namespace) {
+                Some(v) => Arc::clone(v),
+                None if namespaces.len() < per_namespace.max_namespaces => {
+                    let v = Arc::new(self.namespace_recorders(namespace));
+                    namespaces.insert(namespace.to_string(), Arc::clone(&v));
+                    v
+                }
+                None => Arc::clone(&per_namespace.overflow),
+            }
+        };
+
+        record(&recorders);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(metrics: &metric::Registry, attributes: impl Into<Attributes>) -> Option<u64> {
+        metrics
+            .get_instrument::<Metric<U64Counter>>("http_write_lines_total")
+            .expect("failed to read metric")
+            .get_observer(&attributes.into())
+            .map(|v| v.fetch())
+    }
+
+    #[test]
+    fn test_unlabelled() {
+        let metrics = metric::Registry::default();
+        let write_metrics = WriteMetrics::new(&metrics);
+
+        write_metrics.record("bananas", 1, 2, 3, 4);
+        write_metrics.record("platanos", 1, 2, 3, 4);
+
+        assert_eq!(lines(&metrics, &[]), Some(2));
+        assert_eq!(lines(&metrics, &[(NAMESPACE_ATTRIBUTE, "bananas")]), None);
+    }
+
+    #[test]
+    fn test_per_namespace_cardinality_cap() {
+        let metrics = metric::Registry::default();
+        let mut write_metrics = WriteMetrics::new(&metrics);
+        write_metrics.set_max_namespaces(2);
+
+        write_metrics.record("bananas", 1, 2, 3, 4);
+        write_metrics.record("platanos", 2, 2, 3, 4);
+        write_metrics.record("bananas", 3, 2, 3, 4);
+        // Beyond the cap, namespaces share a single attribute value.
+        write_metrics.record("apples", 4, 2, 3, 4);
+        write_metrics.record("pears", 5, 2, 3, 4);
+
+        assert_eq!(
+            lines(&metrics, &[(NAMESPACE_ATTRIBUTE, "bananas")]),
+            Some(4)
+        );
+        assert_eq!(
+            lines(&metrics, &[(NAMESPACE_ATTRIBUTE, "platanos")]),
+            Some(2)
+        );
+        assert_eq!(
+            lines(&metrics, &[(NAMESPACE_ATTRIBUTE, OVERFLOW_NAMESPACE)]),
+            Some(9)
+        );
+        assert_eq!(lines(&metrics, &[(NAMESPACE_ATTRIBUTE, "apples")]), None);
+        assert_eq!(lines(&metrics, &[]), Some(0));
+    }
+}
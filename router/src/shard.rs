@@ -1,11 +1,61 @@
 //! A representation of a single operation shard.
 
+mod circuit_breaker;
+pub use circuit_breaker::CircuitBreakerConfig;
+
+use self::circuit_breaker::CircuitBreaker;
+use backoff::{Backoff, BackoffConfig, BackoffError};
 use data_types::ShardIndex;
 use dml::{DmlMeta, DmlOperation};
 use iox_time::{SystemProvider, TimeProvider};
-use metric::{DurationHistogram, Metric};
-use std::{borrow::Cow, hash::Hash, sync::Arc};
-use write_buffer::core::{WriteBufferError, WriteBufferWriting};
+use metric::{DurationHistogram, Metric, U64Counter};
+use std::{borrow::Cow, hash::Hash, ops::ControlFlow, sync::Arc, time::Duration};
+use thiserror::Error;
+use write_buffer::core::{WriteBufferError, WriteBufferErrorKind, WriteBufferWriting};
+
+/// Errors returned by [`Shard::enqueue()`].
+#[derive(Debug, Error)]
+pub enum EnqueueError {
+    /// The circuit breaker of the shard is open, and the operation was not
+    /// passed to the write buffer.
+    ///
+    /// Retrying the operation before the circuit breaker cool down elapses
+    /// fails in the same way.
+    #[error("shard {0} unavailable: circuit breaker open")]
+    CircuitOpen(ShardIndex),
+
+    /// The write buffer failed to enqueue the operation.
+    #[error(transparent)]
+    WriteBuffer(#[from] WriteBufferError),
+}
+
+impl EnqueueError {
+    /// Returns true if the error is caused by the write buffer, rather than
+    /// the operation itself or an open circuit breaker, and retrying the
+    /// operation may succeed.
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::CircuitOpen(_) => false,
+            Self::WriteBuffer(e) => !is_invalid_request(e),
+        }
+    }
+}
+
+/// Returns true if `e` is caused by the operation passed to the write buffer,
+/// rather than the health of the write buffer.
+fn is_invalid_request(e: &WriteBufferError) -> bool {
+    matches!(
+        e.kind(),
+        WriteBufferErrorKind::InvalidInput | WriteBufferErrorKind::InvalidData
+    )
+}
+
+/// The backoff applied when retrying failed enqueue calls to a shard.
+#[derive(Debug)]
+struct RetryPolicy {
+    backoff: BackoffConfig,
+    retries: U64Counter,
+}
 
 /// A shard tags a write buffer with a shard index (Kafka partition).
 #[derive(Debug)]
@@ -19,6 +69,14 @@ pub struct Shard<P = SystemProvider> {
     inner: Arc<dyn WriteBufferWriting>,
     time_provider: P,
 
+    /// An optional circuit breaker fast-failing writes while the write buffer
+    /// for this shard is unhealthy.
+    circuit_breaker: Option<CircuitBreaker>,
+
+    /// An optional policy retrying enqueue calls that fail with a transient
+    /// write buffer error.
+    retry: Option<RetryPolicy>,
+
     enqueue_success: DurationHistogram,
     enqueue_error: DurationHistogram,
 }
@@ -67,9 +125,58 @@ impl Shard {
             enqueue_success,
             enqueue_error,
             time_provider: Default::default(),
+            circuit_breaker: None,
+            retry: None,
         }
     }
 
+    /// Fast-fail writes to this shard for [`CircuitBreakerConfig::cool_down`]
+    /// once [`CircuitBreakerConfig::error_threshold`] consecutive enqueue
+    /// calls have failed, rather than waiting on an unhealthy write buffer.
+    pub fn with_circuit_breaker(
+        mut self,
+        config: CircuitBreakerConfig,
+        metrics: &metric::Registry,
+    ) -> Self {
+        self.circuit_breaker = Some(CircuitBreaker::new(config, self.shard_index, metrics));
+        self
+    }
+
+    /// Retry enqueue calls that fail with a transient write buffer error,
+    /// backing off exponentially (with jitter) between attempts until
+    /// `deadline` has elapsed.
+    ///
+    /// Only the operation destined for this shard is retried, so a retry
+    /// never re-writes data to other shards that already accepted their
+    /// share of a write. Operations rejected by an open circuit breaker, or
+    /// as invalid by the write buffer, are not retried.
+    ///
+    /// The write buffer takes ownership of each operation it is passed, so
+    /// every attempt enqueues a copy of the operation while retries are
+    /// enabled.
+    pub fn with_retry(
+        mut self,
+        backoff: BackoffConfig,
+        deadline: Duration,
+        metrics: &metric::Registry,
+    ) -> Self {
+        let retries = metrics
+            .register_metric::<U64Counter>(
+                "shard_enqueue_retries",
+                "number of shard enqueue calls retried after a transient error",
+            )
+            .recorder([("kafka_partition", Cow::from(self.shard_index.to_string()))]);
+
+        self.retry = Some(RetryPolicy {
+            backoff: BackoffConfig {
+                deadline: Some(deadline),
+                ..backoff
+            },
+            retries,
+        });
+        self
+    }
+
     /// Return the 0..N index / identifier for the shard (Kafka partition).
     ///
     /// NOTE: this is NOT the ID of the Shard row in the catalog this
@@ -83,18 +190,286 @@ impl Shard {
     /// The buffering / async return behaviour of this method is defined by the
     /// behaviour of the [`WriteBufferWriting::store_operation()`]
     /// implementation this [`Shard`] wraps.
-    pub async fn enqueue<'a>(&self, op: DmlOperation) -> Result<DmlMeta, WriteBufferError> {
+    ///
+    /// If a circuit breaker is configured and open, [`EnqueueError::CircuitOpen`]
+    /// is returned without calling the write buffer.
+    pub async fn enqueue<'a>(&self, op: DmlOperation) -> Result<DmlMeta, EnqueueError> {
+        let retry = match &self.retry {
+            Some(v) => v,
+            None => return self.try_enqueue(op).await,
+        };
+
+        let mut attempts = 0_u64;
+        let res = Backoff::new(&retry.backoff)
+            .retry_with_backoff("shard_enqueue", || {
+                if attempts > 0 {
+                    retry.retries.inc(1);
+                }
+                attempts += 1;
+
+                let op = op.clone();
+                async move {
+                    match self.try_enqueue(op).await {
+                        Err(e) if e.is_transient() => ControlFlow::Continue(e),
+                        res => ControlFlow::Break(res),
+                    }
+                }
+            })
+            .await;
+
+        match res {
+            Ok(v) => v,
+            Err(BackoffError::DeadlineExceeded { source, .. }) => Err(source),
+        }
+    }
+
+    /// Make a single attempt to enqueue `op` into this shard.
+    async fn try_enqueue(&self, op: DmlOperation) -> Result<DmlMeta, EnqueueError> {
         let t = self.time_provider.now();
 
+        if let Some(breaker) = &self.circuit_breaker {
+            if !breaker.allow(t) {
+                return Err(EnqueueError::CircuitOpen(self.shard_index));
+            }
+        }
+
         let res = self.inner.store_operation(self.shard_index, op).await;
 
-        if let Some(delta) = self.time_provider.now().checked_duration_since(t) {
+        let now = self.time_provider.now();
+        if let Some(delta) = now.checked_duration_since(t) {
             match &res {
                 Ok(_) => self.enqueue_success.record(delta),
                 Err(_) => self.enqueue_error.record(delta),
             }
         }
 
-        res
+        if let Some(breaker) = &self.circuit_breaker {
+            // Errors caused by the request itself say nothing about the
+            // health of the shard, and do not count towards opening the
+            // circuit.
+            let healthy = match &res {
+                Ok(_) => true,
+                Err(e) => is_invalid_request(e),
+            };
+            breaker.observe(healthy, now);
+        }
+
+        Ok(res?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use data_types::{DeletePredicate, TimestampRange};
+    use dml::DmlDelete;
+    use metric::Attributes;
+    use parking_lot::Mutex;
+    use std::collections::{BTreeSet, VecDeque};
+    use write_buffer::mock::MockBufferForWritingThatAlwaysErrors;
+
+    /// A write buffer returning the queued results from successive enqueue
+    /// calls, and succeeding once they are exhausted.
+    #[derive(Debug, Default)]
+    struct QueuedResultWriteBuffer {
+        results: Mutex<VecDeque<Result<(), WriteBufferError>>>,
+        calls: Mutex<usize>,
+    }
+
+    impl QueuedResultWriteBuffer {
+        fn with_errors(kind: WriteBufferErrorKind, n: usize) -> Self {
+            Self {
+                results: Mutex::new(
+                    std::iter::repeat_with(|| Err(WriteBufferError::new(kind, "bananas")))
+                        .take(n)
+                        .collect(),
+                ),
+                calls: Default::default(),
+            }
+        }
+
+        fn calls(&self) -> usize {
+            *self.calls.lock()
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl WriteBufferWriting for QueuedResultWriteBuffer {
+        fn shard_indexes(&self) -> BTreeSet<ShardIndex> {
+            [ShardIndex::new(0)].into_iter().collect()
+        }
+
+        async fn store_operation(
+            &self,
+            _shard_index: ShardIndex,
+            _operation: DmlOperation,
+        ) -> Result<DmlMeta, WriteBufferError> {
+            *self.calls.lock() += 1;
+            self.results
+                .lock()
+                .pop_front()
+                .unwrap_or(Ok(()))
+                .map(|_| DmlMeta::unsequenced(None))
+        }
+
+        async fn flush(&self) -> Result<(), WriteBufferError> {
+            Ok(())
+        }
+
+        fn type_name(&self) -> &'static str {
+            "queued"
+        }
+    }
+
+    fn backoff() -> BackoffConfig {
+        BackoffConfig {
+            init_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(1),
+            base: 1.0,
+            deadline: None,
+        }
+    }
+
+    fn assert_retries(metrics: &metric::Registry, want: u64) {
+        let got = metrics
+            .get_instrument::<Metric<U64Counter>>("shard_enqueue_retries")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("kafka_partition", "0")]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(got, want);
+    }
+
+    fn op() -> DmlOperation {
+        DmlOperation::Delete(DmlDelete::new(
+            "bananas",
+            DeletePredicate {
+                range: TimestampRange::new(1, 2),
+                exprs: vec![],
+            },
+            None,
+            DmlMeta::unsequenced(None),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_fast_fails() {
+        let metrics = metric::Registry::default();
+        let shard = Shard::new(
+            ShardIndex::new(0),
+            Arc::new(MockBufferForWritingThatAlwaysErrors),
+            &metrics,
+        )
+        .with_circuit_breaker(
+            CircuitBreakerConfig {
+                error_threshold: 2,
+                cool_down: Duration::from_secs(3600),
+            },
+            &metrics,
+        );
+
+        for _ in 0..4 {
+            shard.enqueue(op()).await.expect_err("enqueue should fail");
+        }
+
+        // Only the first two calls reached the write buffer, after which the
+        // circuit was opened and the remaining calls rejected.
+        let errors = metrics
+            .get_instrument::<Metric<DurationHistogram>>("shard_enqueue_duration")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[
+                ("kafka_partition", "0"),
+                ("result", "error"),
+            ]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(errors.sample_count(), 2);
+
+        let rejected = metrics
+            .get_instrument::<Metric<U64Counter>>("shard_circuit_breaker_rejected")
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("kafka_partition", "0")]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(rejected, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_error() {
+        let metrics = metric::Registry::default();
+        let write_buffer = Arc::new(QueuedResultWriteBuffer::with_errors(
+            WriteBufferErrorKind::IO,
+            2,
+        ));
+        let shard = Shard::new(ShardIndex::new(0), Arc::clone(&write_buffer) as _, &metrics)
+            .with_retry(backoff(), Duration::from_secs(10), &metrics);
+
+        shard
+            .enqueue(op())
+            .await
+            .expect("enqueue should succeed after retrying");
+
+        assert_eq!(write_buffer.calls(), 3);
+        assert_retries(&metrics, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_not_invalid_input() {
+        let metrics = metric::Registry::default();
+        let write_buffer = Arc::new(QueuedResultWriteBuffer::with_errors(
+            WriteBufferErrorKind::InvalidInput,
+            1,
+        ));
+        let shard = Shard::new(ShardIndex::new(0), Arc::clone(&write_buffer) as _, &metrics)
+            .with_retry(backoff(), Duration::from_secs(10), &metrics);
+
+        let err = shard.enqueue(op()).await.expect_err("enqueue should fail");
+        assert_matches!(err, EnqueueError::WriteBuffer(e) => {
+            assert_eq!(e.kind(), WriteBufferErrorKind::InvalidInput);
+        });
+
+        assert_eq!(write_buffer.calls(), 1);
+        assert_retries(&metrics, 0);
+    }
+
+    #[tokio::test]
+    async fn test_retry_deadline_exceeded() {
+        let metrics = metric::Registry::default();
+        let shard = Shard::new(
+            ShardIndex::new(0),
+            Arc::new(MockBufferForWritingThatAlwaysErrors),
+            &metrics,
+        )
+        .with_retry(backoff(), Duration::from_millis(10), &metrics);
+
+        let err = shard.enqueue(op()).await.expect_err("enqueue should fail");
+        assert_matches!(err, EnqueueError::WriteBuffer(_));
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_at_open_circuit() {
+        let metrics = metric::Registry::default();
+        let write_buffer = Arc::new(QueuedResultWriteBuffer::with_errors(
+            WriteBufferErrorKind::IO,
+            10,
+        ));
+        let shard = Shard::new(ShardIndex::new(0), Arc::clone(&write_buffer) as _, &metrics)
+            .with_circuit_breaker(
+                CircuitBreakerConfig {
+                    error_threshold: 2,
+                    cool_down: Duration::from_secs(3600),
+                },
+                &metrics,
+            )
+            .with_retry(backoff(), Duration::from_secs(10), &metrics);
+
+        // The second failure opens the circuit, failing the third attempt
+        // without calling the write buffer or retrying further.
+        let err = shard.enqueue(op()).await.expect_err("enqueue should fail");
+        assert_matches!(err, EnqueueError::CircuitOpen(_));
+
+        assert_eq!(write_buffer.calls(), 2);
+        assert_retries(&metrics, 2);
     }
 }
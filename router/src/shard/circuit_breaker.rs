@@ -0,0 +1,224 @@
+//! A per-shard circuit breaker, fast-failing writes to shards that are
+//! persistently erroring.
+
+use data_types::ShardIndex;
+use iox_time::Time;
+use metric::U64Counter;
+use parking_lot::Mutex;
+use std::{borrow::Cow, time::Duration};
+
+/// Configuration of a shard [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CircuitBreakerConfig {
+    /// The number of consecutive failed enqueue calls after which the circuit
+    /// is opened.
+    pub error_threshold: usize,
+
+    /// The duration for which writes are rejected once the circuit has been
+    /// opened, before a single probe request is allowed through.
+    pub cool_down: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            error_threshold: 5,
+            cool_down: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// The number of enqueue calls that have failed since the last success.
+    consecutive_errors: usize,
+
+    /// When set, the circuit is open and requests are rejected until this
+    /// time.
+    open_until: Option<Time>,
+}
+
+/// A [`CircuitBreaker`] tracks the outcome of enqueue calls to a single shard,
+/// and "opens" once [`CircuitBreakerConfig::error_threshold`] consecutive
+/// calls have failed.
+///
+/// While open, [`CircuitBreaker::allow()`] returns false, and writes to the
+/// shard should fail immediately rather than wait on an unhealthy write
+/// buffer. Once [`CircuitBreakerConfig::cool_down`] has elapsed, a single
+/// probe request is allowed through - if it succeeds the circuit is closed,
+/// otherwise it is reopened for another cool-down period.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<State>,
+
+    opened: U64Counter,
+    rejected: U64Counter,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(
+        config: CircuitBreakerConfig,
+        shard_index: ShardIndex,
+        metrics: &metric::Registry,
+    ) -> Self {
+        let attr = [("kafka_partition", Cow::from(shard_index.to_string()))];
+
+        let opened = metrics
+            .register_metric::<U64Counter>(
+                "shard_circuit_breaker_opened",
+                "number of times the circuit breaker of a shard was opened",
+            )
+            .recorder(attr.clone());
+        let rejected = metrics
+            .register_metric::<U64Counter>(
+                "shard_circuit_breaker_rejected",
+                "number of shard enqueue calls rejected by an open circuit breaker",
+            )
+            .recorder(attr);
+
+        Self {
+            config,
+            state: Default::default(),
+            opened,
+            rejected,
+        }
+    }
+
+    /// Returns true if a request may be sent to the shard at `now`.
+    ///
+    /// If this call returns true, the outcome of the request MUST be reported
+    /// with [`CircuitBreaker::observe()`].
+    pub(crate) fn allow(&self, now: Time) -> bool {
+        let mut state = self.state.lock();
+
+        let allowed = match state.open_until {
+            None => true,
+            Some(until) if now >= until => {
+                // Allow this request through as a probe, and continue
+                // rejecting all others for another cool-down period. Should
+                // the probe never complete, another is allowed once this
+                // period has elapsed.
+                state.open_until = Some(now + self.config.cool_down);
+                true
+            }
+            Some(_) => false,
+        };
+
+        if !allowed {
+            self.rejected.inc(1);
+        }
+
+        allowed
+    }
+
+    /// Record the outcome of a request allowed by [`CircuitBreaker::allow()`]
+    /// that completed at `now`.
+    pub(crate) fn observe(&self, success: bool, now: Time) {
+        let mut state = self.state.lock();
+
+        if success {
+            *state = State::default();
+            return;
+        }
+
+        state.consecutive_errors = state.consecutive_errors.saturating_add(1);
+        if state.consecutive_errors < self.config.error_threshold {
+            return;
+        }
+
+        // Reaching the error threshold opens the circuit, and a failed probe
+        // keeps it open for another cool-down period.
+        if state.open_until.is_none() {
+            self.opened.inc(1);
+        }
+        state.open_until = Some(now + self.config.cool_down);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metric::{Attributes, Metric};
+
+    const COOL_DOWN: Duration = Duration::from_secs(10);
+
+    fn new_breaker(metrics: &metric::Registry) -> CircuitBreaker {
+        CircuitBreaker::new(
+            CircuitBreakerConfig {
+                error_threshold: 3,
+                cool_down: COOL_DOWN,
+            },
+            ShardIndex::new(42),
+            metrics,
+        )
+    }
+
+    fn assert_counter(metrics: &metric::Registry, name: &'static str, want: u64) {
+        let got = metrics
+            .get_instrument::<Metric<U64Counter>>(name)
+            .expect("failed to read metric")
+            .get_observer(&Attributes::from(&[("kafka_partition", "42")]))
+            .expect("failed to get observer")
+            .fetch();
+        assert_eq!(got, want, "metric {name}");
+    }
+
+    #[test]
+    fn test_opens_after_consecutive_errors() {
+        let metrics = metric::Registry::default();
+        let breaker = new_breaker(&metrics);
+        let now = Time::from_timestamp_nanos(0);
+
+        // A success resets the error count.
+        for success in [false, false, true, false, false] {
+            assert!(breaker.allow(now));
+            breaker.observe(success, now);
+        }
+        assert!(breaker.allow(now));
+        assert_counter(&metrics, "shard_circuit_breaker_opened", 0);
+
+        // The third consecutive error opens the circuit.
+        breaker.observe(false, now);
+        assert!(!breaker.allow(now));
+        assert!(!breaker.allow(now + COOL_DOWN - Duration::from_nanos(1)));
+
+        assert_counter(&metrics, "shard_circuit_breaker_opened", 1);
+        assert_counter(&metrics, "shard_circuit_breaker_rejected", 2);
+    }
+
+    #[test]
+    fn test_probe_after_cool_down() {
+        let metrics = metric::Registry::default();
+        let breaker = new_breaker(&metrics);
+        let now = Time::from_timestamp_nanos(0);
+
+        for _ in 0..3 {
+            assert!(breaker.allow(now));
+            breaker.observe(false, now);
+        }
+        assert!(!breaker.allow(now));
+
+        // Once the cool-down has elapsed, a single probe is allowed through.
+        let now = now + COOL_DOWN;
+        assert!(breaker.allow(now));
+        assert!(!breaker.allow(now));
+
+        // A failed probe keeps the circuit open for another cool-down period.
+        let now = now + Duration::from_secs(1);
+        breaker.observe(false, now);
+        assert!(!breaker.allow(now + COOL_DOWN - Duration::from_nanos(1)));
+        assert_counter(&metrics, "shard_circuit_breaker_opened", 1);
+
+        // A successful probe closes it.
+        let now = now + COOL_DOWN;
+        assert!(breaker.allow(now));
+        breaker.observe(true, now);
+        assert!(breaker.allow(now));
+        breaker.observe(false, now);
+        assert!(breaker.allow(now));
+
+        assert_counter(&metrics, "shard_circuit_breaker_opened", 1);
+        assert_counter(&metrics, "shard_circuit_breaker_rejected", 3);
+    }
+}
@@ -0,0 +1,310 @@
+//! Per-namespace accounting of the lines and bytes written through the
+//! router, for usage based billing.
+//!
+//! The [`UsageAccountant`] accumulates the usage of each namespace in memory,
+//! bucketed into fixed length periods aligned to the UNIX epoch, so that the
+//! usage accounted by every router for the same period is added to the same
+//! [`NamespaceUsage`] record in the catalog. A background task (see
+//! [`spawn_flush_task()`]) periodically writes the usage of all completed
+//! periods to the catalog.
+//!
+//! Usage that cannot be written to the catalog is retained and retried at the
+//! next flush. Usage that has not been flushed when the router stops is lost.
+//!
+//! [`NamespaceUsage`]: data_types::NamespaceUsage
+
+use data_types::{DatabaseName, Timestamp};
+use iox_catalog::interface::Catalog;
+use iox_time::TimeProvider;
+use metric::U64Counter;
+use observability_deps::tracing::*;
+use parking_lot::Mutex;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+
+/// The lines and bytes accumulated for a namespace within a single period.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Usage {
+    lines: u64,
+    bytes: u64,
+}
+
+/// Accumulates the usage of each namespace, periodically writing it to the
+/// catalog.
+#[derive(Debug)]
+pub struct UsageAccountant {
+    catalog: Arc<dyn Catalog>,
+    time_provider: Arc<dyn TimeProvider>,
+
+    /// The length of each accounting period, in nanoseconds.
+    period_ns: i64,
+
+    /// The usage not yet written to the catalog, keyed by the start of the
+    /// period (in nanoseconds) and the namespace name.
+    usage: Mutex<BTreeMap<(i64, String), Usage>>,
+
+    recorded: U64Counter,
+    failed: U64Counter,
+    dropped: U64Counter,
+}
+
+impl UsageAccountant {
+    /// Accumulate usage into periods of length `period`, to be written to
+    /// `catalog`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `period` is less than one second.
+    pub fn new(
+        catalog: Arc<dyn Catalog>,
+        time_provider: Arc<dyn TimeProvider>,
+        period: Duration,
+        metrics: &metric::Registry,
+    ) -> Self {
+        assert!(
+            period >= Duration::from_secs(1),
+            "usage accounting period must be at least one second"
+        );
+
+        let records = metrics.register_metric::<U64Counter>(
+            "router_usage_records",
+            "number of namespace usage records written to the catalog by result",
+        );
+
+        Self {
+            catalog,
+            time_provider,
+            period_ns: period.as_nanos() as i64,
+            usage: Default::default(),
+            recorded: records.recorder(&[("result", "recorded")]),
+            failed: records.recorder(&[("result", "failed")]),
+            dropped: records.recorder(&[("result", "dropped")]),
+        }
+    }
+
+    /// Account `lines` and `bytes` written to `namespace` in the current
+    /// period.
+    pub fn record(&self, namespace: &DatabaseName<'_>, lines: usize, bytes: usize) {
+        let period_start = self.period_start(self.time_provider.now().timestamp_nanos());
+
+        let mut usage = self.usage.lock();
+        let u = usage
+            .entry((period_start, namespace.to_string()))
+            .or_default();
+        u.lines += lines as u64;
+        u.bytes += bytes as u64;
+    }
+
+    /// Write the usage of all completed periods to the catalog.
+    ///
+    /// Usage of namespaces that no longer exist in the catalog is discarded,
+    /// and usage that fails to be written is retained for the next flush.
+    pub async fn flush(&self) {
+        let current = self.period_start(self.time_provider.now().timestamp_nanos());
+
+        // Take the usage of the completed periods, leaving the current period
+        // to continue accumulating.
+        let completed = {
+            let mut usage = self.usage.lock();
+            let current = usage.split_off(&(current, String::new()));
+            std::mem::replace(&mut *usage, current)
+        };
+        if completed.is_empty() {
+            return;
+        }
+
+        let mut retry = Vec::new();
+        let mut repos = self.catalog.repositories().await;
+        for ((period_start, namespace), usage) in completed {
+            let namespace_id = match repos.namespaces().get_by_name(&namespace).await {
+                Ok(Some(ns)) => ns.id,
+                Ok(None) => {
+                    warn!(%namespace, "discarding usage of unknown namespace");
+                    self.dropped.inc(1);
+                    continue;
+                }
+                Err(e) => {
+                    warn!(error=%e, %namespace, "failed to look up namespace to record usage");
+                    self.failed.inc(1);
+                    retry.push(((period_start, namespace), usage));
+                    continue;
+                }
+            };
+
+            match repos
+                .namespaces()
+                .record_usage(
+                    namespace_id,
+                    Timestamp::new(period_start),
+                    Timestamp::new(period_start + self.period_ns),
+                    usage.lines as i64,
+                    usage.bytes as i64,
+                )
+                .await
+            {
+                Ok(_) => self.recorded.inc(1),
+                Err(e) => {
+                    warn!(error=%e, %namespace, "failed to record namespace usage");
+                    self.failed.inc(1);
+                    retry.push(((period_start, namespace), usage));
+                }
+            }
+        }
+
+        // Merge the usage that could not be written back into the
+        // accumulated usage, to be retried at the next flush.
+        let mut usage = self.usage.lock();
+        for (key, failed) in retry {
+            let u = usage.entry(key).or_default();
+            u.lines += failed.lines;
+            u.bytes += failed.bytes;
+        }
+    }
+
+    /// Returns the start of the period containing `ts` (in nanoseconds).
+    fn period_start(&self, ts: i64) -> i64 {
+        ts - ts.rem_euclid(self.period_ns)
+    }
+}
+
+/// Spawn a background task flushing the usage accumulated by `accountant` to
+/// the catalog once per `interval`.
+///
+/// The task exits once `accountant` is dropped.
+///
+/// # Panics
+///
+/// This spawns the flush task, and so must be called from within a tokio
+/// runtime. Panics if `interval` is zero.
+pub fn spawn_flush_task(accountant: &Arc<UsageAccountant>, interval: Duration) -> JoinHandle<()> {
+    let accountant = Arc::downgrade(accountant);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let accountant = match Weak::upgrade(&accountant) {
+                Some(v) => v,
+                None => return,
+            };
+            accountant.flush().await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use data_types::{NamespaceId, NamespaceUsage};
+    use iox_catalog::mem::MemCatalog;
+    use iox_time::{MockProvider, Time};
+
+    const PERIOD: Duration = Duration::from_secs(60);
+
+    async fn create_namespace(catalog: &dyn Catalog, name: &str) -> NamespaceId {
+        let mut repos = catalog.repositories().await;
+        let topic = repos.topics().create_or_get("bananas").await.unwrap();
+        let pool = repos.query_pools().create_or_get("platanos").await.unwrap();
+        repos
+            .namespaces()
+            .create(name, "inf", topic.id, pool.id)
+            .await
+            .unwrap()
+            .id
+    }
+
+    async fn list_usage(catalog: &dyn Catalog, id: NamespaceId) -> Vec<NamespaceUsage> {
+        catalog
+            .repositories()
+            .await
+            .namespaces()
+            .list_usage(id)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_flush_completed_periods() {
+        let metrics = Arc::new(metric::Registry::default());
+        let catalog: Arc<dyn Catalog> = Arc::new(MemCatalog::new(Arc::clone(&metrics)));
+        let id = create_namespace(&*catalog, "bananas").await;
+
+        let time = Arc::new(MockProvider::new(Time::from_timestamp(120, 0)));
+        let accountant = UsageAccountant::new(
+            Arc::clone(&catalog),
+            Arc::clone(&time) as _,
+            PERIOD,
+            &metrics,
+        );
+
+        let ns = DatabaseName::try_from("bananas").unwrap();
+        accountant.record(&ns, 2, 20);
+        accountant.record(&ns, 3, 30);
+        accountant.record(&DatabaseName::try_from("unknown").unwrap(), 1, 1);
+
+        // The current period is not flushed.
+        accountant.flush().await;
+        assert!(list_usage(&*catalog, id).await.is_empty());
+
+        // Once the period completes, its usage is written to the catalog,
+        // and the usage of the next period continues accumulating.
+        time.set(Time::from_timestamp(185, 0));
+        accountant.record(&ns, 1, 10);
+        accountant.flush().await;
+
+        let start = Time::from_timestamp(120, 0).timestamp_nanos();
+        let want = NamespaceUsage {
+            namespace_id: id,
+            period_start: Timestamp::new(start),
+            period_end: Timestamp::new(start + PERIOD.as_nanos() as i64),
+            lines: 5,
+            bytes: 50,
+        };
+        assert_eq!(list_usage(&*catalog, id).await, [want.clone()]);
+
+        // Usage of the same period flushed by another router is added to the
+        // existing record.
+        let other = UsageAccountant::new(
+            Arc::clone(&catalog),
+            Arc::clone(&time) as _,
+            PERIOD,
+            &metric::Registry::default(),
+        );
+        other.record(&ns, 1, 1);
+        time.set(Time::from_timestamp(240, 0));
+        other.flush().await;
+        accountant.flush().await;
+
+        let usage = list_usage(&*catalog, id).await;
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[0], want);
+        assert_eq!(usage[1].period_start, want.period_end);
+        assert_eq!((usage[1].lines, usage[1].bytes), (2, 11));
+
+        assert!(accountant.usage.lock().is_empty());
+        assert_eq!(accountant.dropped.fetch(), 1);
+        assert_eq!(accountant.recorded.fetch(), 2);
+    }
+
+    #[test]
+    fn test_period_start() {
+        let accountant = UsageAccountant::new(
+            Arc::new(MemCatalog::new(Default::default())),
+            Arc::new(MockProvider::new(Time::from_timestamp(0, 0))),
+            PERIOD,
+            &metric::Registry::default(),
+        );
+
+        let period = PERIOD.as_nanos() as i64;
+        assert_eq!(accountant.period_start(0), 0);
+        assert_eq!(accountant.period_start(period - 1), 0);
+        assert_eq!(accountant.period_start(period), period);
+        assert_eq!(accountant.period_start(-1), -period);
+    }
+}
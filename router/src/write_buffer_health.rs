@@ -0,0 +1,219 @@
+//! Background probing of the connectivity of each write buffer shard.
+
+use data_types::ShardIndex;
+use futures::future;
+use metric::{Metric, U64Gauge};
+use observability_deps::tracing::*;
+use parking_lot::Mutex;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use tokio::{task::JoinHandle, time::MissedTickBehavior};
+use write_buffer::core::WriteBufferWriting;
+
+/// The maximum duration a single shard health check may take before the
+/// shard is considered unhealthy.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The most recently probed health of each shard of a write buffer.
+///
+/// All shards are considered healthy until probed.
+#[derive(Debug)]
+pub struct WriteBufferHealth {
+    write_buffer: Arc<dyn WriteBufferWriting>,
+    shards: Mutex<BTreeMap<ShardIndex, bool>>,
+
+    /// Set to 1 for each healthy shard, and 0 for each unhealthy shard.
+    shard_health: Metric<U64Gauge>,
+}
+
+impl WriteBufferHealth {
+    /// Track the health of the shards of `write_buffer`.
+    pub fn new(write_buffer: Arc<dyn WriteBufferWriting>, metrics: &metric::Registry) -> Self {
+        let shard_health = metrics.register_metric::<U64Gauge>(
+            "write_buffer_shard_health",
+            "health of each write buffer shard as of the last probe (1 healthy, 0 unhealthy)",
+        );
+
+        let shards = write_buffer
+            .shard_indexes()
+            .into_iter()
+            .map(|shard_index| {
+                shard_health
+                    .recorder([("kafka_partition", Cow::from(shard_index.to_string()))])
+                    .set(1);
+                (shard_index, true)
+            })
+            .collect();
+
+        Self {
+            write_buffer,
+            shards: Mutex::new(shards),
+            shard_health,
+        }
+    }
+
+    /// Check the health of every shard concurrently, recording the results.
+    pub async fn probe(&self) {
+        let shard_indexes = self.shards.lock().keys().copied().collect::<Vec<_>>();
+
+        let results = future::join_all(shard_indexes.into_iter().map(|shard_index| async move {
+            let healthy = match tokio::time::timeout(
+                PROBE_TIMEOUT,
+                self.write_buffer.check_health(shard_index),
+            )
+            .await
+            {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) => {
+                    warn!(error=%e, %shard_index, "write buffer shard health check failed");
+                    false
+                }
+                Err(_) => {
+                    warn!(%shard_index, "write buffer shard health check timed out");
+                    false
+                }
+            };
+            (shard_index, healthy)
+        }))
+        .await;
+
+        let mut shards = self.shards.lock();
+        for (shard_index, healthy) in results {
+            let was_healthy = shards.insert(shard_index, healthy);
+            if was_healthy == Some(true) && !healthy {
+                error!(%shard_index, "write buffer shard unhealthy");
+            } else if was_healthy == Some(false) && healthy {
+                info!(%shard_index, "write buffer shard healthy");
+            }
+
+            self.shard_health
+                .recorder([("kafka_partition", Cow::from(shard_index.to_string()))])
+                .set(u64::from(healthy));
+        }
+    }
+
+    /// Returns true if every shard was healthy when last probed.
+    pub fn is_healthy(&self) -> bool {
+        self.shards.lock().values().all(|healthy| *healthy)
+    }
+
+    /// Returns the (ordered) indexes of the shards that were unhealthy when
+    /// last probed.
+    pub fn unhealthy_shards(&self) -> Vec<ShardIndex> {
+        self.shards
+            .lock()
+            .iter()
+            .filter(|(_, healthy)| !**healthy)
+            .map(|(shard_index, _)| *shard_index)
+            .collect()
+    }
+}
+
+/// Spawn a background task probing the shards of `health` once per
+/// `interval`, starting immediately.
+///
+/// The task exits once `health` is dropped.
+///
+/// # Panics
+///
+/// This spawns the probe task, and so must be called from within a tokio
+/// runtime. Panics if `interval` is zero.
+pub fn spawn_probe_task(health: &Arc<WriteBufferHealth>, interval: Duration) -> JoinHandle<()> {
+    let health = Arc::downgrade(health);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+
+            let health = match Weak::upgrade(&health) {
+                Some(v) => v,
+                None => return,
+            };
+            health.probe().await;
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use metric::{Attributes, MetricObserver, Observation};
+    use std::num::NonZeroU32;
+    use test_helpers::timeout::FutureTimeout;
+    use write_buffer::mock::{
+        MockBufferForWriting, MockBufferForWritingThatAlwaysErrors, MockBufferSharedState,
+    };
+
+    fn shard_health(metrics: &metric::Registry, shard_index: i32) -> Observation {
+        metrics
+            .get_instrument::<Metric<U64Gauge>>("write_buffer_shard_health")
+            .expect("metric should be registered")
+            .get_observer(&Attributes::from([(
+                "kafka_partition",
+                Cow::from(shard_index.to_string()),
+            )]))
+            .expect("shard should be recorded")
+            .observe()
+    }
+
+    #[tokio::test]
+    async fn test_probe() {
+        let metrics = metric::Registry::default();
+
+        // A reachable write buffer is healthy.
+        let write_buffer = MockBufferForWriting::new(
+            MockBufferSharedState::empty_with_n_shards(NonZeroU32::new(2).unwrap()),
+            None,
+            Arc::new(iox_time::SystemProvider::default()),
+        )
+        .unwrap();
+        let health = WriteBufferHealth::new(Arc::new(write_buffer), &metrics);
+        health.probe().await;
+        assert!(health.is_healthy());
+        assert!(health.unhealthy_shards().is_empty());
+        assert_eq!(shard_health(&metrics, 1), Observation::U64Gauge(1));
+
+        // An unreachable write buffer is healthy until probed.
+        let metrics = metric::Registry::default();
+        let health =
+            WriteBufferHealth::new(Arc::new(MockBufferForWritingThatAlwaysErrors), &metrics);
+        assert!(health.is_healthy());
+        health.probe().await;
+        assert!(!health.is_healthy());
+        assert_eq!(health.unhealthy_shards(), [ShardIndex::new(0)]);
+        assert_eq!(shard_health(&metrics, 0), Observation::U64Gauge(0));
+    }
+
+    #[tokio::test]
+    async fn test_probe_task() {
+        let metrics = metric::Registry::default();
+        let health = Arc::new(WriteBufferHealth::new(
+            Arc::new(MockBufferForWritingThatAlwaysErrors),
+            &metrics,
+        ));
+
+        let handle = spawn_probe_task(&health, Duration::from_millis(10));
+
+        // The first probe is performed immediately.
+        async {
+            while health.is_healthy() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        }
+        .with_timeout_panic(Duration::from_secs(5))
+        .await;
+
+        // Dropping the health state stops the task.
+        drop(health);
+        handle
+            .with_timeout_panic(Duration::from_secs(5))
+            .await
+            .expect("probe task should not panic");
+    }
+}
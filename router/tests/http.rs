@@ -22,6 +22,7 @@ use write_buffer::{
     core::WriteBufferWriting,
     mock::{MockBufferForWriting, MockBufferSharedState},
 };
+use write_summary::WriteSummary;
 
 /// The topic catalog ID assigned by the namespace auto-creator in the
 /// handler stack for namespaces it has not yet observed.
@@ -53,7 +54,7 @@ type HttpDelegateStack = HttpDelegate<
                     >,
                     SchemaValidator<Arc<ShardedCache<Arc<MemoryNamespaceCache>>>>,
                 >,
-                Partitioner,
+                Partitioner<Arc<ShardedCache<Arc<MemoryNamespaceCache>>>>,
             >,
             WriteSummaryAdapter<
                 FanOutAdaptor<
@@ -102,10 +103,14 @@ impl TestContext {
             iox_catalog::INFINITE_RETENTION_POLICY.to_owned(),
         );
 
-        let schema_validator = SchemaValidator::new(Arc::clone(&catalog), ns_cache, &*metrics);
-        let partitioner = Partitioner::new(PartitionTemplate {
-            parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
-        });
+        let schema_validator =
+            SchemaValidator::new(Arc::clone(&catalog), Arc::clone(&ns_cache), &*metrics);
+        let partitioner = Partitioner::new(
+            PartitionTemplate {
+                parts: vec![TemplatePart::TimeFormat("%Y-%m-%d".to_owned())],
+            },
+            ns_cache,
+        );
 
         let handler_stack = ns_creator
             .and_then(schema_validator)
@@ -235,6 +240,45 @@ async fn test_write_ok() {
     assert_eq!(hit_count, 1);
 }
 
+#[tokio::test]
+async fn test_delete_ok() {
+    let ctx = TestContext::new();
+
+    let request = Request::builder()
+        .uri("https://bananas.example/api/v2/delete?org=bananas&bucket=test")
+        .method("POST")
+        .body(Body::from(
+            r#"{"start":"2021-04-01T14:00:00Z","stop":"2021-04-02T14:00:00Z", "predicate":"_measurement=platanos"}"#,
+        ))
+        .expect("failed to construct HTTP request");
+
+    let response = ctx
+        .delegate()
+        .route(request)
+        .await
+        .expect("delete request failed");
+
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    // Check the write buffer observed the delete.
+    let writes = ctx.write_buffer_state().get_messages(ShardIndex::new(0));
+    assert_matches!(writes.as_slice(), [Ok(DmlOperation::Delete(d))] => {
+        assert_eq!(d.namespace(), "bananas_test");
+        assert_eq!(d.table_name(), Some("platanos"));
+    });
+
+    // And the write token returned to the client refers to the shard the
+    // delete was sequenced to.
+    let token = response
+        .headers()
+        .get("X-IOx-Write-Token")
+        .expect("delete response should contain a write token")
+        .to_str()
+        .unwrap();
+    let summary = WriteSummary::try_from_token(token).expect("invalid write token");
+    assert_eq!(summary.shard_indexes(), [ShardIndex::new(0)]);
+}
+
 #[tokio::test]
 async fn test_schema_conflict() {
     let ctx = TestContext::new();
@@ -13,13 +13,21 @@
     clippy::dbg_macro
 )]
 
-use data_types::{PartitionId, TableId};
+use data_types::{CompactionLevel, ParquetFileId, PartitionId, TableId, TimestampMinMax};
 use generated_types::influxdata::iox::catalog::v1::*;
 use iox_catalog::interface::Catalog;
 use observability_deps::tracing::*;
 use std::sync::Arc;
 use tonic::{Request, Response, Status};
 
+/// The number of parquet files returned in a page by `ListParquetFiles` if the
+/// request does not specify a page size.
+pub const DEFAULT_PARQUET_FILE_PAGE_SIZE: usize = 1_000;
+
+/// The maximum number of parquet files returned in a page by
+/// `ListParquetFiles`, bounding the size of a response message.
+pub const MAX_PARQUET_FILE_PAGE_SIZE: usize = 10_000;
+
 /// Implementation of the Catalog gRPC service
 #[derive(Debug)]
 pub struct CatalogService {
@@ -80,6 +88,82 @@ impl catalog_service_server::CatalogService for CatalogService {
 
         Ok(Response::new(response))
     }
+
+    async fn list_parquet_files(
+        &self,
+        request: Request<ListParquetFilesRequest>,
+    ) -> Result<Response<ListParquetFilesResponse>, Status> {
+        let req = request.into_inner();
+        let partition_id = PartitionId::new(req.partition_id);
+
+        let time_range = match (req.min_time, req.max_time) {
+            (None, None) => None,
+            (min, max) => {
+                let min = min.unwrap_or(i64::MIN);
+                let max = max.unwrap_or(i64::MAX);
+                if min > max {
+                    return Err(Status::invalid_argument(format!(
+                        "min_time {min} is after max_time {max}"
+                    )));
+                }
+                Some(TimestampMinMax::new(min, max))
+            }
+        };
+        let compaction_level = req
+            .compaction_level
+            .map(CompactionLevel::try_from)
+            .transpose()
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let page_size = match req.page_size {
+            0 => DEFAULT_PARQUET_FILE_PAGE_SIZE,
+            n if n < 0 => {
+                return Err(Status::invalid_argument(format!(
+                    "page size must not be negative, got {n}"
+                )))
+            }
+            n => (n as usize).min(MAX_PARQUET_FILE_PAGE_SIZE),
+        };
+        let after = match req.page_token.as_str() {
+            "" => None,
+            token => {
+                Some(ParquetFileId::new(token.parse().map_err(|_| {
+                    Status::invalid_argument("invalid page token")
+                })?))
+            }
+        };
+
+        let mut repos = self.catalog.repositories().await;
+        let parquet_files = repos
+            .parquet_files()
+            .list_by_partition_not_to_delete_paged(
+                partition_id,
+                time_range,
+                compaction_level,
+                after,
+                page_size,
+            )
+            .await
+            .map_err(|e| {
+                warn!(error=%e, %req.partition_id, "failed to list parquet_files for partition");
+                Status::internal(e.to_string())
+            })?;
+
+        // A full page may be followed by more files.
+        let next_page_token = match parquet_files.len() == page_size {
+            true => parquet_files
+                .last()
+                .map(|f| f.id.get().to_string())
+                .unwrap_or_default(),
+            false => String::new(),
+        };
+
+        let parquet_files = parquet_files.into_iter().map(to_parquet_file).collect();
+
+        Ok(Response::new(ListParquetFilesResponse {
+            parquet_files,
+            next_page_token,
+        }))
+    }
 }
 
 // converts the catalog ParquetFile to protobuf
@@ -201,6 +285,137 @@ mod tests {
         assert_eq!(expect, response.parquet_files,);
     }
 
+    #[tokio::test]
+    async fn list_parquet_files() {
+        // create a catalog and populate it with some test data, then drop the write lock
+        let partition_id;
+        let files;
+        let catalog = {
+            let metrics = Arc::new(metric::Registry::default());
+            let catalog = Arc::new(MemCatalog::new(metrics));
+            let mut repos = catalog.repositories().await;
+            let topic = repos.topics().create_or_get("iox-shared").await.unwrap();
+            let pool = repos
+                .query_pools()
+                .create_or_get("iox-shared")
+                .await
+                .unwrap();
+            let shard = repos
+                .shards()
+                .create_or_get(&topic, ShardIndex::new(1))
+                .await
+                .unwrap();
+            let namespace = repos
+                .namespaces()
+                .create("catalog_partition_test", "inf", topic.id, pool.id)
+                .await
+                .unwrap();
+            let table = repos
+                .tables()
+                .create_or_get("schema_test_table", namespace.id)
+                .await
+                .unwrap();
+            let partition = repos
+                .partitions()
+                .create_or_get("foo".into(), shard.id, table.id)
+                .await
+                .unwrap();
+
+            // Five files covering [0, 10), [10, 20), ... with alternating
+            // compaction levels.
+            let mut created = vec![];
+            for i in 0..5 {
+                let params = ParquetFileParams {
+                    shard_id: shard.id,
+                    namespace_id: namespace.id,
+                    table_id: table.id,
+                    partition_id: partition.id,
+                    object_store_id: Uuid::new_v4(),
+                    max_sequence_number: SequenceNumber::new(i),
+                    min_time: Timestamp::new(i * 10),
+                    max_time: Timestamp::new(i * 10 + 9),
+                    file_size_bytes: 2343,
+                    row_count: 29,
+                    compaction_level: match i % 2 {
+                        0 => CompactionLevel::Initial,
+                        _ => CompactionLevel::FileNonOverlapped,
+                    },
+                    created_at: Timestamp::new(2343),
+                    column_set: ColumnSet::new([ColumnId::new(1), ColumnId::new(2)]),
+                };
+                created.push(repos.parquet_files().create(params).await.unwrap());
+            }
+            partition_id = partition.id;
+            files = created.into_iter().map(to_parquet_file).collect::<Vec<_>>();
+            Arc::clone(&catalog)
+        };
+
+        let grpc = super::CatalogService::new(catalog);
+        let grpc = &grpc;
+        let list = |request| async move {
+            grpc.list_parquet_files(Request::new(request))
+                .await
+                .map(|r| r.into_inner())
+        };
+
+        // Page through all the files, two at a time.
+        let mut got = vec![];
+        let mut page_token = String::new();
+        loop {
+            let resp = list(ListParquetFilesRequest {
+                partition_id: partition_id.get(),
+                page_size: 2,
+                page_token,
+                ..Default::default()
+            })
+            .await
+            .expect("rpc request should succeed");
+            assert!(resp.parquet_files.len() <= 2);
+            got.extend(resp.parquet_files);
+            if resp.next_page_token.is_empty() {
+                break;
+            }
+            page_token = resp.next_page_token;
+        }
+        assert_eq!(got, files);
+
+        // Filter by time range and compaction level.
+        let resp = list(ListParquetFilesRequest {
+            partition_id: partition_id.get(),
+            min_time: Some(15),
+            max_time: Some(40),
+            compaction_level: Some(CompactionLevel::Initial as i32),
+            ..Default::default()
+        })
+        .await
+        .expect("rpc request should succeed");
+        assert_eq!(resp.parquet_files, [files[2].clone(), files[4].clone()]);
+        assert!(resp.next_page_token.is_empty());
+
+        for request in [
+            ListParquetFilesRequest {
+                min_time: Some(10),
+                max_time: Some(5),
+                ..Default::default()
+            },
+            ListParquetFilesRequest {
+                compaction_level: Some(42),
+                ..Default::default()
+            },
+            ListParquetFilesRequest {
+                page_size: -1,
+                ..Default::default()
+            },
+            ListParquetFilesRequest {
+                page_token: "bananas".to_string(),
+                ..Default::default()
+            },
+        ] {
+            let err = list(request).await.expect_err("invalid request");
+            assert_eq!(err.code(), tonic::Code::InvalidArgument);
+        }
+    }
+
     #[tokio::test]
     async fn get_partitions_by_table_id() {
         // create a catalog and populate it with some test data, then drop the write lock
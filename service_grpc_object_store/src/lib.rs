@@ -14,7 +14,10 @@
     clippy::dbg_macro
 )]
 
-use futures::{stream::BoxStream, StreamExt};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
 use generated_types::influxdata::iox::object_store::v1::*;
 use iox_catalog::interface::Catalog;
 use object_store::DynObjectStore;
@@ -24,7 +27,24 @@ use std::sync::Arc;
 use tonic::{Request, Response, Status};
 use uuid::Uuid;
 
+/// The size of the chunks a parquet file is streamed in if the request does
+/// not specify one.
+pub const DEFAULT_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The maximum size of the chunks a parquet file is streamed in, keeping each
+/// response message well below the default gRPC message size limit.
+pub const MAX_CHUNK_SIZE: usize = 2 * 1024 * 1024;
+
+/// The maximum number of chunks read from the object store ahead of the client
+/// consuming them.
+pub const MAX_PREFETCH_CHUNKS: usize = 8;
+
 /// Implementation of the ObjectStore gRPC service
+///
+/// Parquet files are streamed in chunks, each read from the object store only
+/// once the client has consumed all but `prefetch_chunks` of the previously
+/// read chunks - a slow client therefore bounds the memory used by the
+/// request, and the rate at which it reads from the object store.
 #[derive(Debug)]
 pub struct ObjectStoreService {
     /// Catalog
@@ -76,23 +96,69 @@ impl object_store_service_server::ObjectStoreService for ObjectStoreService {
         );
         let path = path.object_store_path();
 
-        let res = self
+        let file_size = self
             .object_store
-            .get(&path)
+            .head(&path)
             .await
-            .map_err(|e| Status::unknown(e.to_string()))?;
+            .map_err(|e| Status::unknown(e.to_string()))?
+            .size;
+
+        let offset = non_negative("offset", req.offset)?;
+        if offset > file_size {
+            return Err(Status::out_of_range(format!(
+                "offset {offset} is beyond the end of the {file_size} byte file"
+            )));
+        }
+        let end = match req.length {
+            Some(length) => offset
+                .saturating_add(non_negative("length", length)?)
+                .min(file_size),
+            None => file_size,
+        };
+        let chunk_size = match non_negative("chunk_size", req.chunk_size)? {
+            0 => DEFAULT_CHUNK_SIZE,
+            n => n.min(MAX_CHUNK_SIZE),
+        };
+        let prefetch_chunks =
+            non_negative("prefetch_chunks", req.prefetch_chunks)?.clamp(1, MAX_PREFETCH_CHUNKS);
+
+        let object_store = Arc::clone(&self.object_store);
+        let chunks = (offset..end)
+            .step_by(chunk_size)
+            .map(move |start| start..(start + chunk_size).min(end));
 
-        let rx = Box::pin(res.into_stream().map(|next| match next {
-            Ok(data) => Ok(GetParquetFileByObjectStoreIdResponse {
-                data: data.to_vec(),
-            }),
-            Err(e) => Err(Status::unknown(e.to_string())),
-        }));
+        let rx = stream::iter(chunks)
+            .map(move |range| {
+                let object_store = Arc::clone(&object_store);
+                let path = path.clone();
+                async move {
+                    let data = object_store
+                        .get_range(&path, range.clone())
+                        .await
+                        .map_err(|e| Status::unknown(e.to_string()))?;
+                    Ok(GetParquetFileByObjectStoreIdResponse {
+                        data: data.to_vec(),
+                        offset: range.start as i64,
+                    })
+                }
+            })
+            .buffered(prefetch_chunks)
+            .boxed();
 
         Ok(Response::new(rx))
     }
 }
 
+/// Convert the request field `name` to a `usize`, rejecting negative values.
+fn non_negative<T>(name: &str, value: T) -> Result<usize, Status>
+where
+    T: TryInto<usize> + std::fmt::Display + Copy,
+{
+    value
+        .try_into()
+        .map_err(|_| Status::invalid_argument(format!("{name} must not be negative, got {value}")))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +244,7 @@ mod tests {
         let grpc = super::ObjectStoreService::new(catalog, object_store);
         let request = GetParquetFileByObjectStoreIdRequest {
             uuid: p1.object_store_id.to_string(),
+            ..Default::default()
         };
 
         let tonic_response = grpc
@@ -188,5 +255,78 @@ mod tests {
         let response = response.next().await.unwrap().unwrap();
 
         assert_eq!(response.data, data);
+        assert_eq!(response.offset, 0);
+
+        // Fetch a byte range of the file, in chunks of at most 2 bytes.
+        let grpc = &grpc;
+        let get = |request| async move {
+            grpc.get_parquet_file_by_object_store_id(Request::new(request))
+                .await
+                .map(|r| r.into_inner())
+        };
+        let chunks = get(GetParquetFileByObjectStoreIdRequest {
+            uuid: p1.object_store_id.to_string(),
+            offset: 2,
+            length: Some(5),
+            chunk_size: 2,
+            prefetch_chunks: 2,
+        })
+        .await
+        .expect("rpc request should succeed")
+        .map(|r| {
+            let r = r.expect("chunk should be read");
+            (r.offset, r.data)
+        })
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(
+            chunks,
+            [(2, b"me".to_vec()), (4, b" d".to_vec()), (6, b"a".to_vec())]
+        );
+
+        // A range starting at the end of the file is empty.
+        let chunks = get(GetParquetFileByObjectStoreIdRequest {
+            uuid: p1.object_store_id.to_string(),
+            offset: data.len() as i64,
+            ..Default::default()
+        })
+        .await
+        .expect("rpc request should succeed")
+        .collect::<Vec<_>>()
+        .await;
+        assert!(chunks.is_empty());
+
+        for (request, code) in [
+            (
+                GetParquetFileByObjectStoreIdRequest {
+                    offset: data.len() as i64 + 1,
+                    ..Default::default()
+                },
+                tonic::Code::OutOfRange,
+            ),
+            (
+                GetParquetFileByObjectStoreIdRequest {
+                    length: Some(-1),
+                    ..Default::default()
+                },
+                tonic::Code::InvalidArgument,
+            ),
+            (
+                GetParquetFileByObjectStoreIdRequest {
+                    chunk_size: -1,
+                    ..Default::default()
+                },
+                tonic::Code::InvalidArgument,
+            ),
+        ] {
+            let err = get(GetParquetFileByObjectStoreIdRequest {
+                uuid: p1.object_store_id.to_string(),
+                ..request
+            })
+            .await
+            .err()
+            .expect("invalid request should be rejected");
+            assert_eq!(err.code(), code);
+        }
     }
 }
@@ -3,7 +3,7 @@
 use std::{ops::DerefMut, sync::Arc};
 
 use generated_types::influxdata::iox::schema::v1::*;
-use iox_catalog::interface::{get_schema_by_name, Catalog};
+use iox_catalog::interface::{get_schema_by_name, get_table_schema_by_id, Catalog};
 use observability_deps::tracing::warn;
 use tonic::{Request, Response, Status};
 
@@ -38,6 +38,44 @@ impl schema_service_server::SchemaService for SchemaService {
             .map(Arc::new)?;
         Ok(Response::new(schema_to_proto(schema)))
     }
+
+    async fn get_table_schema(
+        &self,
+        request: Request<GetTableSchemaRequest>,
+    ) -> Result<Response<GetTableSchemaResponse>, Status> {
+        let mut repos = self.catalog.repositories().await;
+
+        let req = request.into_inner();
+        let internal = |e: iox_catalog::interface::Error| {
+            warn!(error=%e, %req.namespace, %req.table, "failed to retrieve table schema");
+            Status::internal(e.to_string())
+        };
+
+        let namespace = repos
+            .namespaces()
+            .get_by_name(&req.namespace)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| Status::not_found(format!("namespace {} not found", req.namespace)))?;
+        let table = repos
+            .tables()
+            .get_by_namespace_and_name(namespace.id, &req.table)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| {
+                Status::not_found(format!(
+                    "table {} not found in namespace {}",
+                    req.table, req.namespace
+                ))
+            })?;
+        let schema = get_table_schema_by_id(table.id, repos.deref_mut())
+            .await
+            .map_err(internal)?;
+
+        Ok(Response::new(GetTableSchemaResponse {
+            schema: Some(table_schema_to_proto(&schema)),
+        }))
+    }
 }
 
 fn schema_to_proto(schema: Arc<data_types::NamespaceSchema>) -> GetSchemaResponse {
@@ -49,33 +87,32 @@ fn schema_to_proto(schema: Arc<data_types::NamespaceSchema>) -> GetSchemaRespons
             tables: schema
                 .tables
                 .iter()
-                .map(|(name, t)| {
-                    (
-                        name.clone(),
-                        TableSchema {
-                            id: t.id.get(),
-                            columns: t
-                                .columns
-                                .iter()
-                                .map(|(name, c)| {
-                                    (
-                                        name.clone(),
-                                        ColumnSchema {
-                                            id: c.id.get(),
-                                            column_type: c.column_type as i32,
-                                        },
-                                    )
-                                })
-                                .collect(),
-                        },
-                    )
-                })
+                .map(|(name, t)| (name.clone(), table_schema_to_proto(t)))
                 .collect(),
         }),
     };
     response
 }
 
+fn table_schema_to_proto(schema: &data_types::TableSchema) -> TableSchema {
+    TableSchema {
+        id: schema.id.get(),
+        columns: schema
+            .columns
+            .iter()
+            .map(|(name, c)| {
+                (
+                    name.clone(),
+                    ColumnSchema {
+                        id: c.id.get(),
+                        column_type: c.column_type as i32,
+                    },
+                )
+            })
+            .collect(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -137,5 +174,38 @@ mod tests {
                 .collect::<Vec<&String>>(),
             vec![&"schema_test_column".to_string()]
         );
+
+        // A single table's schema can be fetched on its own.
+        let table = grpc
+            .get_table_schema(Request::new(GetTableSchemaRequest {
+                namespace: "namespace_schema_test".to_string(),
+                table: "schema_test_table".to_string(),
+            }))
+            .await
+            .expect("rpc request should succeed")
+            .into_inner()
+            .schema
+            .expect("schema should be Some()");
+        assert_eq!(
+            &table,
+            schema
+                .tables
+                .get("schema_test_table")
+                .expect("test table should exist")
+        );
+
+        for (namespace, table) in [
+            ("namespace_schema_test", "bananas"),
+            ("bananas", "schema_test_table"),
+        ] {
+            let err = grpc
+                .get_table_schema(Request::new(GetTableSchemaRequest {
+                    namespace: namespace.to_string(),
+                    table: table.to_string(),
+                }))
+                .await
+                .expect_err("unknown table should not be found");
+            assert_eq!(err.code(), tonic::Code::NotFound);
+        }
     }
 }
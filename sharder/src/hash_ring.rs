@@ -0,0 +1,405 @@
+use super::{jumphash::HashKey, Sharder};
+use data_types::{DatabaseName, DeletePredicate};
+use mutable_batch::MutableBatch;
+use parking_lot::RwLock;
+use siphasher::sip::SipHasher13;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    num::NonZeroUsize,
+    sync::Arc,
+};
+
+/// A [`HashRing`] maps operations for a given table in a given namespace
+/// consistently to the same shard, using a consistent hash ring of virtual
+/// nodes.
+///
+/// Unlike a [`JumpHash`], the set of shards in a [`HashRing`] can be changed at
+/// runtime - adding or removing a shard remaps only the keys owned by that
+/// shard (approximately `1/N` of all keys for `N` shards), leaving the mapping
+/// of all other keys unchanged.
+///
+/// Each shard is placed on the ring at `vnodes` pseudo-random points derived
+/// from its key `K`, so the placement of a shard is independent of the order
+/// in which shards are added. Increasing `vnodes` improves the uniformity of
+/// the distribution at the cost of `O(N * vnodes)` memory, with `O(ln (N *
+/// vnodes))` lookup.
+///
+/// [`JumpHash`]: crate::JumpHash
+#[derive(Debug)]
+pub struct HashRing<K, T> {
+    hasher: SipHasher13,
+    vnodes: NonZeroUsize,
+    ring: RwLock<Ring<K, T>>,
+}
+
+#[derive(Debug)]
+struct Ring<K, T> {
+    /// The hash ring, mapping the position of each virtual node to the key of
+    /// the shard that owns it.
+    points: BTreeMap<u64, K>,
+    members: HashMap<K, T>,
+}
+
+impl<K, T> Ring<K, T>
+where
+    K: Hash + Eq,
+{
+    /// Return the key of the shard owning `hash`.
+    fn owner(&self, hash: u64) -> &K {
+        self.points
+            .range(hash..)
+            .next()
+            .or_else(|| self.points.iter().next())
+            .map(|(_, k)| k)
+            .expect("empty hash ring")
+    }
+}
+
+impl<K, T> HashRing<K, T>
+where
+    K: Hash + Eq + Clone,
+    T: Clone,
+{
+    /// Initialise a [`HashRing`] placing each of `shards` (identified by their
+    /// key `K`) at `vnodes` points on the ring.
+    ///
+    /// # Panics
+    ///
+    /// This constructor panics if the number of elements in `shards` is 0.
+    pub fn new(vnodes: NonZeroUsize, shards: impl IntoIterator<Item = (K, T)>) -> Self {
+        // A randomly generated static siphash key to ensure all router
+        // instances place shards at the same points on the ring, and hash the
+        // same input to the same position.
+        //
+        // Generated with: xxd -i -l 16 /dev/urandom
+        let key = [
+            0x1f, 0xa4, 0x3c, 0x97, 0x5e, 0x0b, 0xd2, 0x68, 0x41, 0xc9, 0x7a, 0x13, 0xe5, 0x8d,
+            0x26, 0xb0,
+        ];
+
+        let this = Self {
+            hasher: SipHasher13::new_with_key(&key),
+            vnodes,
+            ring: RwLock::new(Ring {
+                points: Default::default(),
+                members: Default::default(),
+            }),
+        };
+
+        this.set_shards(shards);
+        this
+    }
+
+    /// Replace the set of shards in the ring with `shards`, returning the
+    /// fraction (`0.0..=1.0`) of the key space that is now mapped to a
+    /// different shard.
+    ///
+    /// Shards that remain in the ring retain their position, so only the keys
+    /// owned by added or removed shards are remapped.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the number of elements in `shards` is 0.
+    pub fn set_shards(&self, shards: impl IntoIterator<Item = (K, T)>) -> f64 {
+        let members = shards.into_iter().collect::<HashMap<_, _>>();
+        assert!(!members.is_empty(), "empty shard set given to sharder");
+
+        let mut points = BTreeMap::new();
+        for key in members.keys() {
+            for i in 0..self.vnodes.get() {
+                let mut state = self.hasher;
+                (key, i).hash(&mut state);
+                // In the (extremely unlikely) event of a collision, the point
+                // is owned by whichever shard is placed first.
+                points.entry(state.finish()).or_insert_with(|| key.clone());
+            }
+        }
+
+        let new = Ring { points, members };
+
+        let mut ring = self.ring.write();
+        let moved = if ring.points.is_empty() {
+            0.0
+        } else {
+            moved_fraction(&ring, &new)
+        };
+        *ring = new;
+
+        moved
+    }
+
+    /// Return the shards in the ring, and the fraction (`0.0..=1.0`) of the key
+    /// space each owns.
+    pub fn shards(&self) -> Vec<(K, T, f64)> {
+        let ring = self.ring.read();
+
+        let mut ownership: HashMap<&K, u128> = HashMap::with_capacity(ring.members.len());
+        for (start, end) in arcs(&ring.points) {
+            *ownership.entry(ring.owner(end)).or_default() += arc_len(start, end);
+        }
+
+        ring.members
+            .iter()
+            .map(|(k, v)| {
+                let owned = ownership.get(k).copied().unwrap_or_default();
+                (k.clone(), v.clone(), fraction(owned))
+            })
+            .collect()
+    }
+
+    /// Consistently hash a table and namespace to a `T`.
+    pub fn shard_for_query(&self, table: &str, namespace: &str) -> T {
+        let mut state = self.hasher;
+        HashKey { table, namespace }.hash(&mut state);
+        let hash = state.finish();
+
+        let ring = self.ring.read();
+        ring.members
+            .get(ring.owner(hash))
+            .expect("ring point refers to unknown shard")
+            .clone()
+    }
+}
+
+/// Iterate over the arcs `(start, end]` between consecutive points in
+/// `points`, including the arc wrapping around from the last point to the
+/// first.
+fn arcs<K>(points: &BTreeMap<u64, K>) -> impl Iterator<Item = (u64, u64)> + '_ {
+    let last = points.keys().next_back().copied();
+    last.into_iter()
+        .chain(points.keys().copied())
+        .zip(points.keys().copied())
+}
+
+/// The number of hashes in the arc `(start, end]`, wrapping around the ring if
+/// `end <= start`.
+fn arc_len(start: u64, end: u64) -> u128 {
+    if end > start {
+        (end - start) as u128
+    } else {
+        // The arc wraps around the end of the ring (or, for a ring with a
+        // single point, covers all of it).
+        (u64::MAX as u128 + 1) - (start - end) as u128
+    }
+}
+
+/// Return `n` as a fraction of all the hashes in the ring.
+fn fraction(n: u128) -> f64 {
+    n as f64 / (u64::MAX as f64 + 1.0)
+}
+
+/// Return the fraction of the key space mapped to a different shard in `new`
+/// compared to `old`.
+fn moved_fraction<K, T>(old: &Ring<K, T>, new: &Ring<K, T>) -> f64
+where
+    K: Hash + Eq,
+{
+    // Between any two consecutive points of the combined rings, all hashes are
+    // owned by the same shard in each ring - the owner of the arc's end.
+    let mut boundaries = BTreeMap::new();
+    boundaries.extend(old.points.keys().map(|&p| (p, ())));
+    boundaries.extend(new.points.keys().map(|&p| (p, ())));
+
+    let moved = arcs(&boundaries)
+        .filter(|&(_, end)| old.owner(end) != new.owner(end))
+        .map(|(start, end)| arc_len(start, end))
+        .sum();
+
+    fraction(moved)
+}
+
+/// A [`HashRing`] sharder mapping a [`MutableBatch`] reference according to
+/// the namespace and table it is destined for.
+impl<K, T> Sharder<MutableBatch> for HashRing<K, Arc<T>>
+where
+    K: Hash + Eq + Clone + Debug + Send + Sync,
+    T: Debug + Send + Sync,
+{
+    type Item = Arc<T>;
+
+    fn shard(
+        &self,
+        table: &str,
+        namespace: &DatabaseName<'_>,
+        _payload: &MutableBatch,
+    ) -> Self::Item {
+        self.shard_for_query(table, namespace.as_ref())
+    }
+}
+
+/// A [`HashRing`] sharder mapping a [`DeletePredicate`] reference to all
+/// shards unless a table is specified, in which case the table & namespace are
+/// used to shard to the same destination as a write with the same table &
+/// namespace would.
+impl<K, T> Sharder<DeletePredicate> for HashRing<K, Arc<T>>
+where
+    K: Hash + Eq + Clone + Debug + Send + Sync,
+    T: Debug + Send + Sync,
+{
+    type Item = Vec<Arc<T>>;
+
+    fn shard(
+        &self,
+        table: &str,
+        namespace: &DatabaseName<'_>,
+        _payload: &DeletePredicate,
+    ) -> Self::Item {
+        // A delete that does not specify a table is mapped to all shards.
+        if table.is_empty() {
+            return self.ring.read().members.values().map(Arc::clone).collect();
+        }
+
+        vec![self.shard_for_query(table, namespace.as_ref())]
+    }
+}
+
+impl<K, T> Sharder<()> for HashRing<K, Arc<T>>
+where
+    K: Hash + Eq + Clone + Debug + Send + Sync,
+    T: Debug + Send + Sync,
+{
+    type Item = Arc<T>;
+
+    fn shard(&self, table: &str, namespace: &DatabaseName<'_>, _payload: &()) -> Self::Item {
+        self.shard_for_query(table, namespace.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NUM_KEYS: usize = 10_000;
+
+    fn vnodes() -> NonZeroUsize {
+        NonZeroUsize::new(100).unwrap()
+    }
+
+    fn new_ring(n: usize) -> HashRing<usize, usize> {
+        HashRing::new(vnodes(), (0..n).map(|v| (v, v)))
+    }
+
+    fn mappings(ring: &HashRing<usize, usize>) -> Vec<usize> {
+        (0..NUM_KEYS)
+            .map(|v| ring.shard_for_query(&v.to_string(), "bananas"))
+            .collect()
+    }
+
+    #[test]
+    fn test_consistent_hashing() {
+        let a = new_ring(10);
+        // Shards placed in a different order produce the same ring.
+        let b = HashRing::new(vnodes(), (0..10).rev().map(|v| (v, v)));
+
+        assert_eq!(mappings(&a), mappings(&b));
+    }
+
+    #[test]
+    fn test_distribution() {
+        let ring = new_ring(10);
+
+        let mut counts = HashMap::<usize, usize>::new();
+        for shard in mappings(&ring) {
+            *counts.entry(shard).or_default() += 1;
+        }
+
+        // All shards receive a reasonable share of the keys.
+        assert_eq!(counts.len(), 10);
+        assert!(
+            counts.values().all(|&n| n > NUM_KEYS / 20),
+            "uneven distribution {counts:?}"
+        );
+
+        // And the reported ownership sums to the whole key space.
+        let total: f64 = ring.shards().iter().map(|(_, _, f)| f).sum();
+        assert!((total - 1.0).abs() < 1e-9, "total ownership {total}");
+    }
+
+    #[test]
+    fn test_add_shard_minimal_movement() {
+        let ring = new_ring(10);
+        let before = mappings(&ring);
+
+        let moved = ring.set_shards((0..11).map(|v| (v, v)));
+        let after = mappings(&ring);
+
+        // Only keys moving to the new shard are remapped.
+        let mut n_moved = 0;
+        for (a, b) in before.iter().zip(&after) {
+            if a != b {
+                assert_eq!(*b, 10);
+                n_moved += 1;
+            }
+        }
+
+        // Approximately 1/11 of the keys (and key space) were moved.
+        let observed = n_moved as f64 / NUM_KEYS as f64;
+        assert!((0.04..0.15).contains(&observed), "moved {observed}");
+        assert!((moved - observed).abs() < 0.03, "{moved} vs {observed}");
+
+        let owned = ring
+            .shards()
+            .into_iter()
+            .find(|(k, _, _)| *k == 10)
+            .map(|(_, _, f)| f)
+            .unwrap();
+        assert!((owned - moved).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_remove_shard_minimal_movement() {
+        let ring = new_ring(10);
+        let before = mappings(&ring);
+
+        ring.set_shards((0..10).filter(|&v| v != 3).map(|v| (v, v)));
+        let after = mappings(&ring);
+
+        // Only keys previously owned by the removed shard are remapped.
+        for (a, b) in before.iter().zip(&after) {
+            if *a == 3 {
+                assert_ne!(*b, 3);
+            } else {
+                assert_eq!(a, b);
+            }
+        }
+
+        // Re-adding the shard restores the original mapping.
+        ring.set_shards((0..10).map(|v| (v, v)));
+        assert_eq!(mappings(&ring), before);
+    }
+
+    #[test]
+    fn test_single_shard() {
+        let ring = new_ring(1);
+        assert!(mappings(&ring).iter().all(|&v| v == 0));
+
+        let shards = ring.shards();
+        assert_eq!(shards.len(), 1);
+        assert!((shards[0].2 - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sharder_impl() {
+        let ring = HashRing::new(vnodes(), (0..10).map(|v| (v, Arc::new(v))));
+        let namespace = DatabaseName::try_from("namespace").unwrap();
+
+        let a = ring.shard("table", &namespace, &MutableBatch::default());
+        let b = ring.shard("table", &namespace, &());
+        assert_eq!(a, b);
+
+        let predicate = DeletePredicate {
+            range: data_types::TimestampRange::new(1, 2),
+            exprs: vec![],
+        };
+        assert_eq!(ring.shard("table", &namespace, &predicate), vec![a]);
+        assert_eq!(ring.shard("", &namespace, &predicate).len(), 10);
+    }
+
+    #[test]
+    #[should_panic(expected = "empty shard set given to sharder")]
+    fn test_empty_shards() {
+        new_ring(0);
+    }
+}
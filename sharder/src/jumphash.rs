@@ -108,9 +108,9 @@ impl<T> JumpHash<T> {
 }
 
 #[derive(Hash)]
-struct HashKey<'a> {
-    table: &'a str,
-    namespace: &'a str,
+pub(crate) struct HashKey<'a> {
+    pub(crate) table: &'a str,
+    pub(crate) namespace: &'a str,
 }
 
 /// A [`JumpHash`] sharder mapping a [`MutableBatch`] reference according to the
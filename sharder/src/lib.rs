@@ -23,5 +23,11 @@ pub use r#trait::*;
 mod jumphash;
 pub use jumphash::*;
 
+mod hash_ring;
+pub use hash_ring::*;
+
+mod overrides;
+pub use overrides::*;
+
 #[allow(missing_docs)]
 pub mod mock;
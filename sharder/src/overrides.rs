@@ -0,0 +1,257 @@
+use super::Sharder;
+use data_types::{DatabaseName, DeletePredicate};
+use mutable_batch::MutableBatch;
+use parking_lot::RwLock;
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+/// A set of (namespace, table) pairs pinned to a specific shard `T`.
+///
+/// A [`TableOverrides`] is shared between an [`OverrideSharder`] and the code
+/// managing the overrides, and may be modified at runtime.
+#[derive(Debug)]
+pub struct TableOverrides<T> {
+    // A map of namespace -> table -> T, allowing a lookup by borrowed
+    // namespace and table names without allocating a compound key.
+    overrides: RwLock<HashMap<String, HashMap<String, T>>>,
+}
+
+impl<T> Default for TableOverrides<T> {
+    fn default() -> Self {
+        Self {
+            overrides: Default::default(),
+        }
+    }
+}
+
+impl<T> TableOverrides<T>
+where
+    T: Clone,
+{
+    /// Return the shard `table` in `namespace` is pinned to, if any.
+    pub fn get(&self, namespace: &str, table: &str) -> Option<T> {
+        self.overrides
+            .read()
+            .get(namespace)
+            .and_then(|tables| tables.get(table))
+            .cloned()
+    }
+
+    /// Pin `table` in `namespace` to `shard`, returning the shard it was
+    /// previously pinned to, if any.
+    pub fn set(
+        &self,
+        namespace: impl Into<String>,
+        table: impl Into<String>,
+        shard: T,
+    ) -> Option<T> {
+        self.overrides
+            .write()
+            .entry(namespace.into())
+            .or_default()
+            .insert(table.into(), shard)
+    }
+
+    /// Remove the override for `table` in `namespace`, returning the shard it
+    /// was pinned to, if any.
+    pub fn remove(&self, namespace: &str, table: &str) -> Option<T> {
+        let mut overrides = self.overrides.write();
+
+        let tables = overrides.get_mut(namespace)?;
+        let shard = tables.remove(table);
+        if tables.is_empty() {
+            overrides.remove(namespace);
+        }
+
+        shard
+    }
+
+    /// Return all the overrides as (namespace, table, shard) tuples, in no
+    /// particular order.
+    pub fn list(&self) -> Vec<(String, String, T)> {
+        self.overrides
+            .read()
+            .iter()
+            .flat_map(|(namespace, tables)| {
+                tables
+                    .iter()
+                    .map(|(table, shard)| (namespace.clone(), table.clone(), shard.clone()))
+            })
+            .collect()
+    }
+}
+
+/// An [`OverrideSharder`] routes operations for the (namespace, table) pairs
+/// in its [`TableOverrides`] to the pinned shard, delegating to the inner
+/// sharder `S` for all other tables.
+///
+/// Table-scoped deletes are routed to the pinned shard in the same way as
+/// writes, maintaining the invariant that deletes are routed to the same shard
+/// as writes for the same table. Deletes that do not specify a table are
+/// routed by the inner sharder.
+///
+/// # Correctness
+///
+/// Changing the override of a table changes the shard its writes are routed
+/// to - all routers MUST be configured with the same set of overrides for a
+/// table to be consistently mapped to one shard.
+#[derive(Debug)]
+pub struct OverrideSharder<S, T> {
+    inner: S,
+    overrides: Arc<TableOverrides<T>>,
+}
+
+impl<S, T> OverrideSharder<S, T> {
+    /// Wrap `inner`, routing the tables in `overrides` to their pinned shard.
+    pub fn new(inner: S, overrides: Arc<TableOverrides<T>>) -> Self {
+        Self { inner, overrides }
+    }
+
+    /// Return the inner sharder.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Return the set of overrides applied by this sharder.
+    pub fn overrides(&self) -> &Arc<TableOverrides<T>> {
+        &self.overrides
+    }
+}
+
+impl<S, T> Sharder<MutableBatch> for OverrideSharder<S, Arc<T>>
+where
+    S: Sharder<MutableBatch, Item = Arc<T>>,
+    T: Debug + Send + Sync,
+{
+    type Item = Arc<T>;
+
+    fn shard(
+        &self,
+        table: &str,
+        namespace: &DatabaseName<'_>,
+        payload: &MutableBatch,
+    ) -> Self::Item {
+        self.overrides
+            .get(namespace.as_ref(), table)
+            .unwrap_or_else(|| self.inner.shard(table, namespace, payload))
+    }
+}
+
+impl<S, T> Sharder<()> for OverrideSharder<S, Arc<T>>
+where
+    S: Sharder<(), Item = Arc<T>>,
+    T: Debug + Send + Sync,
+{
+    type Item = Arc<T>;
+
+    fn shard(&self, table: &str, namespace: &DatabaseName<'_>, payload: &()) -> Self::Item {
+        self.overrides
+            .get(namespace.as_ref(), table)
+            .unwrap_or_else(|| self.inner.shard(table, namespace, payload))
+    }
+}
+
+impl<S, T> Sharder<DeletePredicate> for OverrideSharder<S, Arc<T>>
+where
+    S: Sharder<DeletePredicate, Item = Vec<Arc<T>>>,
+    T: Debug + Send + Sync,
+{
+    type Item = Vec<Arc<T>>;
+
+    fn shard(
+        &self,
+        table: &str,
+        namespace: &DatabaseName<'_>,
+        payload: &DeletePredicate,
+    ) -> Self::Item {
+        // A delete that does not specify a table is never overridden.
+        if !table.is_empty() {
+            if let Some(shard) = self.overrides.get(namespace.as_ref(), table) {
+                return vec![shard];
+            }
+        }
+
+        self.inner.shard(table, namespace, payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::JumpHash;
+    use data_types::TimestampRange;
+
+    fn new_sharder() -> OverrideSharder<JumpHash<Arc<u32>>, Arc<u32>> {
+        OverrideSharder::new(
+            JumpHash::new((0..10_u32).map(Arc::new)),
+            Arc::new(TableOverrides::default()),
+        )
+    }
+
+    #[test]
+    fn test_override_writes_and_deletes() {
+        let sharder = new_sharder();
+        let namespace = DatabaseName::try_from("bananas").unwrap();
+        let predicate = DeletePredicate {
+            range: TimestampRange::new(1, 2),
+            exprs: vec![],
+        };
+
+        // The shard the inner sharder maps the table to.
+        let unpinned = sharder.shard("platanos", &namespace, &());
+
+        let pinned = Arc::new(42);
+        assert!(sharder
+            .overrides()
+            .set("bananas", "platanos", Arc::clone(&pinned))
+            .is_none());
+
+        let got: Arc<u32> = sharder.shard("platanos", &namespace, &MutableBatch::default());
+        assert!(Arc::ptr_eq(&got, &pinned));
+        let got: Arc<u32> = sharder.shard("platanos", &namespace, &());
+        assert!(Arc::ptr_eq(&got, &pinned));
+        let got = sharder.shard("platanos", &namespace, &predicate);
+        assert_eq!(got.len(), 1);
+        assert!(Arc::ptr_eq(&got[0], &pinned));
+
+        // Deletes without a table are still sent to all shards.
+        let got = sharder.shard("", &namespace, &predicate);
+        assert_eq!(got.len(), 10);
+
+        // The same table in another namespace is not affected.
+        let other = DatabaseName::try_from("mangos").unwrap();
+        let got: Arc<u32> = sharder.shard("platanos", &other, &());
+        assert_eq!(got, sharder.inner().shard("platanos", &other, &()));
+
+        // Removing the override restores the original mapping.
+        assert_eq!(
+            sharder.overrides().remove("bananas", "platanos"),
+            Some(pinned)
+        );
+        let got: Arc<u32> = sharder.shard("platanos", &namespace, &());
+        assert_eq!(got, unpinned);
+        assert!(sharder.overrides().list().is_empty());
+    }
+
+    #[test]
+    fn test_overrides_list() {
+        let overrides = TableOverrides::default();
+        overrides.set("bananas", "platanos", 1);
+        overrides.set("bananas", "mangos", 2);
+        overrides.set("apples", "platanos", 3);
+        assert_eq!(overrides.set("apples", "platanos", 4), Some(3));
+
+        let mut got = overrides.list();
+        got.sort();
+        assert_eq!(
+            got,
+            [
+                ("apples".to_string(), "platanos".to_string(), 4),
+                ("bananas".to_string(), "mangos".to_string(), 2),
+                ("bananas".to_string(), "platanos".to_string(), 1),
+            ]
+        );
+
+        assert_eq!(overrides.remove("bananas", "nope"), None);
+        assert_eq!(overrides.get("bananas", "mangos"), Some(2));
+    }
+}
@@ -14,7 +14,7 @@ use http::{HeaderMap, HeaderValue};
 use iox_time::Time;
 use mutable_batch_pb::decode::decode_database_batch;
 use prost::Message;
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, collections::BTreeMap, sync::Arc};
 use trace::{ctx::SpanContext, TraceCollector};
 use trace_http::ctx::{format_jaeger_trace_context, TraceHeaderParser};
 
@@ -31,6 +31,10 @@ pub const HEADER_TRACE_CONTEXT: &str = "uber-trace-id";
 /// Message header for namespace.
 pub const HEADER_NAMESPACE: &str = "iox-namespace";
 
+/// Message header name prefix for the provenance annotations of an operation,
+/// with one header per annotation key.
+pub const HEADER_ANNOTATION_PREFIX: &str = "iox-annotation-";
+
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum ContentType {
     Protobuf,
@@ -42,6 +46,7 @@ pub struct IoxHeaders {
     content_type: ContentType,
     span_context: Option<SpanContext>,
     namespace: String,
+    annotations: BTreeMap<String, String>,
 }
 
 impl IoxHeaders {
@@ -55,9 +60,16 @@ impl IoxHeaders {
             content_type,
             span_context,
             namespace,
+            annotations: Default::default(),
         }
     }
 
+    /// Attach the provenance `annotations` of the operation.
+    pub fn with_annotations(mut self, annotations: BTreeMap<String, String>) -> Self {
+        self.annotations = annotations;
+        self
+    }
+
     /// Creates a new IoxHeaders from an iterator of headers
     pub fn from_headers(
         headers: impl IntoIterator<Item = (impl AsRef<str>, impl AsRef<[u8]>)>,
@@ -66,6 +78,7 @@ impl IoxHeaders {
         let mut span_context = None;
         let mut content_type = None;
         let mut namespace = None;
+        let mut annotations = BTreeMap::new();
 
         for (name, value) in headers {
             let name = name.as_ref();
@@ -119,6 +132,16 @@ impl IoxHeaders {
                     ))
                 })?);
             }
+
+            if let Some(key) = strip_prefix_ignore_ascii_case(name, HEADER_ANNOTATION_PREFIX) {
+                let value = String::from_utf8(value.as_ref().to_vec()).map_err(|e| {
+                    WriteBufferError::invalid_data(format!(
+                        "Error decoding annotation header: {}",
+                        e
+                    ))
+                })?;
+                annotations.insert(key.to_ascii_lowercase(), value);
+            }
         }
 
         let content_type =
@@ -128,6 +151,7 @@ impl IoxHeaders {
             content_type,
             span_context,
             namespace: namespace.unwrap_or_default(),
+            annotations,
         })
     }
 
@@ -144,30 +168,45 @@ impl IoxHeaders {
     }
 
     /// Returns the header map to encode
-    pub fn headers(&self) -> impl Iterator<Item = (&str, Cow<'static, str>)> + '_ {
+    pub fn headers(&self) -> impl Iterator<Item = (Cow<'_, str>, Cow<'static, str>)> + '_ {
         let content_type = match self.content_type {
             ContentType::Protobuf => CONTENT_TYPE_PROTOBUF.into(),
         };
 
-        std::iter::once((HEADER_CONTENT_TYPE, content_type))
+        std::iter::once((HEADER_CONTENT_TYPE.into(), content_type))
             .chain(
                 self.span_context
                     .as_ref()
                     .map(|ctx| {
                         (
-                            HEADER_TRACE_CONTEXT,
+                            HEADER_TRACE_CONTEXT.into(),
                             format_jaeger_trace_context(ctx).into(),
                         )
                     })
                     .into_iter(),
             )
             .chain(std::iter::once((
-                HEADER_NAMESPACE,
+                HEADER_NAMESPACE.into(),
                 self.namespace.clone().into(),
             )))
+            .chain(self.annotations.iter().map(|(k, v)| {
+                (
+                    format!("{}{}", HEADER_ANNOTATION_PREFIX, k).into(),
+                    v.clone().into(),
+                )
+            }))
     }
 }
 
+/// Returns `s` with `prefix` removed if `s` starts with `prefix`, ignoring
+/// ASCII case.
+fn strip_prefix_ignore_ascii_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+        .filter(|key| !key.is_empty())
+}
+
 /// Decode a message payload
 pub fn decode(
     data: &[u8],
@@ -178,7 +217,8 @@ pub fn decode(
 ) -> Result<DmlOperation, WriteBufferError> {
     match headers.content_type {
         ContentType::Protobuf => {
-            let meta = DmlMeta::sequenced(sequence, producer_ts, headers.span_context, bytes_read);
+            let meta = DmlMeta::sequenced(sequence, producer_ts, headers.span_context, bytes_read)
+                .with_annotations(headers.annotations);
 
             let payload: WriteBufferPayload = prost::Message::decode(data)
                 .map_err(|e| format!("failed to decode WriteBufferPayload: {}", e))?;
@@ -271,7 +311,11 @@ mod tests {
             ContentType::Protobuf,
             Some(span_context),
             "namespace".to_owned(),
-        );
+        )
+        .with_annotations(BTreeMap::from([
+            ("agent".to_owned(), "telegraf/1.24".to_owned()),
+            ("source-host".to_owned(), "10.0.0.1".to_owned()),
+        ]));
 
         let encoded: Vec<_> = iox_headers1
             .headers()
@@ -287,6 +331,7 @@ mod tests {
             vec![],
         );
         assert_eq!(iox_headers1.namespace, iox_headers2.namespace);
+        assert_eq!(iox_headers1.annotations, iox_headers2.annotations);
     }
 
     #[test]
@@ -298,6 +343,8 @@ mod tests {
             ("uber-trace-id", "1:2:3:1"),
             ("uber-trace-ID", "5:6:7:1"),
             ("iOx-Namespace", "namespace"),
+            ("IOx-Annotation-Agent", "telegraf"),
+            ("iox-annotation-", "ignored"),
         ];
 
         let actual = IoxHeaders::from_headers(headers.into_iter(), Some(&collector)).unwrap();
@@ -308,6 +355,10 @@ mod tests {
         assert_eq!(span_context.span_id.get(), 6);
 
         assert_eq!(actual.namespace, "namespace");
+        assert_eq!(
+            actual.annotations,
+            BTreeMap::from([("agent".to_owned(), "telegraf".to_owned())])
+        );
     }
 
     #[test]
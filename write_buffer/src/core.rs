@@ -191,6 +191,15 @@ pub trait WriteBufferWriting: Sync + Send + Debug + 'static {
     /// for every write.
     async fn flush(&self) -> Result<(), WriteBufferError>;
 
+    /// Check the write buffer backing the specified shard index is reachable
+    /// and able to accept writes, returning an error if it is not.
+    ///
+    /// The default implementation always succeeds, for write buffers with no
+    /// remote dependencies.
+    async fn check_health(&self, _shard_index: ShardIndex) -> Result<(), WriteBufferError> {
+        Ok(())
+    }
+
     /// Return type (like `"mock"` or `"kafka"`) of this writer.
     fn type_name(&self) -> &'static str;
 }
@@ -381,6 +390,7 @@ pub mod test_utils {
         test_unknown_shard_write(&adapter).await;
         test_multi_namespaces(&adapter).await;
         test_flush(&adapter).await;
+        test_check_health(&adapter).await;
     }
 
     /// Writes line protocol and returns the [`DmlWrite`] that was written
@@ -1156,6 +1166,22 @@ pub mod test_utils {
         assert_eq!(shard_indexes_1, shard_indexes_4);
     }
 
+    /// Test every shard of a reachable write buffer is reported healthy.
+    async fn test_check_health<T>(adapter: &T)
+    where
+        T: TestAdapter,
+    {
+        let context = adapter.new_context(NonZeroU32::try_from(2).unwrap()).await;
+        let writer = context.writing(true).await.unwrap();
+
+        for shard_index in writer.shard_indexes() {
+            writer
+                .check_health(shard_index)
+                .await
+                .expect("shard should be healthy");
+        }
+    }
+
     /// Test that span contexts are propagated through the system.
     async fn test_span_context<T>(adapter: &T)
     where
@@ -208,7 +208,8 @@ impl WriteBufferWriting for FileBufferProducer {
             ContentType::Protobuf,
             operation.meta().span_context().cloned(),
             operation.namespace().to_string(),
-        );
+        )
+        .with_annotations(operation.meta().annotations().clone());
 
         for (name, value) in iox_headers.headers() {
             message.extend(format!("{}: {}\n", name, value).into_bytes())
@@ -269,6 +270,18 @@ impl WriteBufferWriting for FileBufferProducer {
         Ok(())
     }
 
+    async fn check_health(&self, shard_index: ShardIndex) -> Result<(), WriteBufferError> {
+        let shard_path = self
+            .dirs
+            .get(&shard_index)
+            .ok_or_else::<WriteBufferError, _>(|| {
+                format!("Unknown shard index: {}", shard_index).into()
+            })?;
+
+        tokio::fs::metadata(shard_path.join("committed")).await?;
+        Ok(())
+    }
+
     fn type_name(&self) -> &'static str {
         "file"
     }
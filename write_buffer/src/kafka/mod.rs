@@ -15,6 +15,7 @@ use async_trait::async_trait;
 use data_types::{Sequence, SequenceNumber, ShardIndex};
 use dml::{DmlMeta, DmlOperation};
 use futures::{
+    future::BoxFuture,
     stream::{self, BoxStream},
     StreamExt, TryStreamExt,
 };
@@ -25,11 +26,11 @@ use rskafka::{
     client::{
         consumer::{StartOffset, StreamConsumerBuilder},
         error::{Error as RSKafkaError, ProtocolError},
-        partition::{OffsetAt, PartitionClient, UnknownTopicHandling},
-        producer::{BatchProducer, BatchProducerBuilder},
+        partition::{Compression, OffsetAt, PartitionClient, UnknownTopicHandling},
+        producer::{BatchProducer, BatchProducerBuilder, ProducerClient},
         ClientBuilder,
     },
-    record::RecordAndOffset,
+    record::{Record, RecordAndOffset},
 };
 use std::{
     collections::{BTreeMap, BTreeSet},
@@ -53,6 +54,25 @@ type Result<T, E = WriteBufferError> = std::result::Result<T, E>;
 #[derive(Debug)]
 pub struct RSKafkaProducer {
     producers: BTreeMap<ShardIndex, BatchProducer<RecordAggregator>>,
+
+    /// The partition clients used by the producers, queried to check the
+    /// health of each shard.
+    partition_clients: BTreeMap<ShardIndex, Arc<PartitionClient>>,
+}
+
+/// A [`ProducerClient`] producing to a [`PartitionClient`] shared with the
+/// health checks of the [`RSKafkaProducer`].
+#[derive(Debug)]
+struct SharedPartitionClient(Arc<PartitionClient>);
+
+impl ProducerClient for SharedPartitionClient {
+    fn produce(
+        &self,
+        records: Vec<Record>,
+        compression: Compression,
+    ) -> BoxFuture<'_, Result<Vec<i64>, RSKafkaError>> {
+        Box::pin(self.0.produce(records, compression))
+    }
 }
 
 impl RSKafkaProducer {
@@ -78,12 +98,17 @@ impl RSKafkaProducer {
 
         let producer_config = ProducerConfig::try_from(connection_config)?;
 
-        let producers = partition_clients
+        let partition_clients = partition_clients
             .into_iter()
-            .map(|(shard_index, partition_client)| {
+            .map(|(shard_index, partition_client)| (shard_index, Arc::new(partition_client)))
+            .collect::<BTreeMap<_, _>>();
+
+        let producers = partition_clients
+            .iter()
+            .map(|(&shard_index, partition_client)| {
                 // Instrument this kafka partition client.
                 let partition_client = KafkaProducerMetrics::new(
-                    Box::new(partition_client),
+                    Box::new(SharedPartitionClient(Arc::clone(partition_client))),
                     topic_name.clone(),
                     shard_index,
                     metric_registry,
@@ -104,7 +129,10 @@ impl RSKafkaProducer {
             })
             .collect();
 
-        Ok(Self { producers })
+        Ok(Self {
+            producers,
+            partition_clients,
+        })
     }
 }
 
@@ -144,6 +172,20 @@ impl WriteBufferWriting for RSKafkaProducer {
         Ok(())
     }
 
+    /// Query the latest offset of the shard's partition, which requires the
+    /// partition leader to be reachable.
+    async fn check_health(&self, shard_index: ShardIndex) -> Result<(), WriteBufferError> {
+        let partition_client = self
+            .partition_clients
+            .get(&shard_index)
+            .ok_or_else::<WriteBufferError, _>(|| {
+                format!("Unknown shard index: {}", shard_index).into()
+            })?;
+
+        partition_client.get_offset(OffsetAt::Latest).await?;
+        Ok(())
+    }
+
     fn type_name(&self) -> &'static str {
         "kafka"
     }
@@ -84,7 +84,8 @@ impl RecordAggregator {
             ContentType::Protobuf,
             op.meta().span_context().cloned(),
             op.namespace().to_owned(),
-        );
+        )
+        .with_annotations(op.meta().annotations().clone());
 
         let mut buf = Vec::new();
         crate::codec::encode_operation(op.namespace(), op, &mut buf)?;
@@ -95,7 +96,7 @@ impl RecordAggregator {
             value: Some(buf),
             headers: headers
                 .headers()
-                .map(|(k, v)| (k.to_owned(), v.as_bytes().to_vec()))
+                .map(|(k, v)| (k.into_owned(), v.as_bytes().to_vec()))
                 .collect(),
             timestamp: now.date_time(),
         };
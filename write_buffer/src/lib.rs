@@ -10,7 +10,7 @@
     clippy::clone_on_ref_ptr
 )]
 
-pub(crate) mod codec;
+pub mod codec;
 pub mod config;
 pub mod core;
 pub mod file;
@@ -309,7 +309,8 @@ impl WriteBufferWriting for MockBufferForWriting {
             timestamp,
             operation.meta().span_context().cloned(),
             0,
-        );
+        )
+        .with_annotations(operation.meta().annotations().clone());
 
         operation.set_meta(meta.clone());
 
@@ -354,6 +355,10 @@ impl WriteBufferWriting for MockBufferForWritingThatAlwaysErrors {
         Ok(())
     }
 
+    async fn check_health(&self, _shard_index: ShardIndex) -> Result<(), WriteBufferError> {
+        Err(String::from("Something bad happened to the write buffer").into())
+    }
+
     fn type_name(&self) -> &'static str {
         "mock_failing"
     }
@@ -84,6 +84,17 @@ impl WriteSummary {
             .map_err(|e| format!("Invalid write token, invalid content: {}", e))
     }
 
+    /// Merge the sequence numbers of `other` into this summary, so that it
+    /// describes both writes.
+    pub fn merge(&mut self, other: Self) {
+        for (shard_index, sequence_numbers) in other.shards {
+            self.shards
+                .entry(shard_index)
+                .or_default()
+                .extend(sequence_numbers);
+        }
+    }
+
     /// return what shard indexes from the write buffer were present in this write summary
     pub fn shard_indexes(&self) -> Vec<ShardIndex> {
         self.shards.keys().cloned().collect()
@@ -231,6 +242,34 @@ mod tests {
         assert_eq!(summary, expected);
     }
 
+    #[test]
+    fn merge() {
+        let mut summary = WriteSummary::new(vec![vec![
+            make_meta(Sequence::new(ShardIndex::new(1), SequenceNumber::new(2))),
+            make_meta(Sequence::new(ShardIndex::new(10), SequenceNumber::new(20))),
+        ]]);
+        summary.merge(WriteSummary::new(vec![vec![make_meta(Sequence::new(
+            ShardIndex::new(1),
+            SequenceNumber::new(3),
+        ))]]));
+        let summary: proto::WriteSummary = summary.into();
+
+        let expected = proto::WriteSummary {
+            shards: vec![
+                proto::ShardWrite {
+                    shard_index: 1,
+                    sequence_numbers: vec![2, 3],
+                },
+                proto::ShardWrite {
+                    shard_index: 10,
+                    sequence_numbers: vec![20],
+                },
+            ],
+        };
+
+        assert_eq!(summary, expected);
+    }
+
     #[test]
     fn different_order() {
         // order in sequences shouldn't matter